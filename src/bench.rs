@@ -0,0 +1,298 @@
+use crate::status::StatusSnapshot;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// 量測結果（資料目錄下）
+pub const REPORT_FILE: &str = "bench_report.json";
+
+/// 每組設定的爬取暫存目錄（量測完刪除）
+pub const SCRATCH_DIR: &str = "bench";
+
+/// 建議的設定可接受的頁面失敗率上限
+pub const MAX_ERROR_RATE: f64 = 0.05;
+
+/// 一組要量測的設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchSetting {
+    pub concurrency: usize,
+    pub batch_delay_ms: u64,
+}
+
+impl BenchSetting {
+    /// 並發數與批次間隔的所有組合（依並發數、間隔排序）
+    pub fn sweep(concurrency: &[usize], batch_delays_ms: &[u64]) -> Vec<Self> {
+        let mut settings: Vec<Self> = concurrency
+            .iter()
+            .flat_map(|&concurrency| {
+                batch_delays_ms.iter().map(move |&batch_delay_ms| Self { concurrency, batch_delay_ms })
+            })
+            .collect();
+        settings.sort_by_key(|s| (s.concurrency, s.batch_delay_ms));
+        settings.dedup();
+        settings
+    }
+}
+
+/// 解析逗號分隔的數值清單（`2,5,10`），每個值至少為 `min`
+pub fn parse_list<T: FromStr + PartialOrd + Display>(spec: &str, flag: &str, min: T) -> Result<Vec<T>> {
+    let values = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().map_err(|_| anyhow::anyhow!("{} 需要以逗號分隔的數字: {}", flag, spec)))
+        .collect::<Result<Vec<T>>>()?;
+    if values.is_empty() {
+        anyhow::bail!("{} 至少需要一個值", flag);
+    }
+    if values.iter().any(|v| *v < min) {
+        anyhow::bail!("{} 的值至少為 {}: {}", flag, min, spec);
+    }
+    Ok(values)
+}
+
+/// 單組設定的量測結果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub setting: BenchSetting,
+    pub pages: u64,
+    pub failed_pages: u64,
+    pub images: u64,
+    pub elapsed_secs: f64,
+}
+
+impl BenchResult {
+    /// 由爬取結束時的狀態建立
+    pub fn from_status(setting: BenchSetting, status: &StatusSnapshot) -> Self {
+        Self {
+            setting,
+            pages: status.completed,
+            failed_pages: status.failed,
+            images: status.items,
+            elapsed_secs: status.elapsed_secs,
+        }
+    }
+
+    pub fn images_per_sec(&self) -> f64 {
+        if self.elapsed_secs > 0.0 {
+            self.images as f64 / self.elapsed_secs
+        } else {
+            0.0
+        }
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.pages > 0 {
+            self.failed_pages as f64 / self.pages as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// 失敗率不超過 `max_error_rate` 的設定中，每秒圖片數最高的一組
+///
+/// 差距在 5% 以內時選並發數較低、間隔較長的（對網站較友善，量測誤差也在這個範圍）。
+pub fn recommend(results: &[BenchResult], max_error_rate: f64) -> Option<&BenchResult> {
+    let candidates: Vec<&BenchResult> = results
+        .iter()
+        .filter(|r| r.pages > 0 && r.error_rate() <= max_error_rate)
+        .collect();
+    let best = candidates.iter().map(|r| r.images_per_sec()).fold(0.0, f64::max);
+
+    candidates
+        .into_iter()
+        .filter(|r| r.images_per_sec() >= best * 0.95)
+        .min_by_key(|r| (r.setting.concurrency, std::cmp::Reverse(r.setting.batch_delay_ms)))
+}
+
+/// `bench_report.json` 的內容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub created_at: DateTime<Utc>,
+    /// 量測的網址樣板（模擬網站時為本機網址）
+    pub list_url: String,
+    pub pages: u32,
+    pub results: Vec<BenchResult>,
+    pub recommended: Option<BenchSetting>,
+}
+
+impl BenchReport {
+    pub fn save(&self, data_dir: &str) -> Result<String> {
+        let path = Path::new(data_dir).join(REPORT_FILE);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp_path, &path).with_context(|| format!("無法寫入 {}", path.display()))?;
+        Ok(path.display().to_string())
+    }
+}
+
+/// 模擬網站的設定
+#[derive(Debug, Clone, Copy)]
+pub struct MockConfig {
+    pub images_per_page: usize,
+    /// 每個回應的延遲
+    pub latency: Duration,
+    /// 同時處理的請求超過此數時回 429
+    pub max_in_flight: usize,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            images_per_page: 10,
+            latency: Duration::from_millis(50),
+            max_in_flight: 16,
+        }
+    }
+}
+
+/// 本機的模擬網站（memes.tw 的列表格式，可用預設 Parser 爬取）
+///
+/// 不連到真正的網站就能量測爬蟲本身的吞吐量；延遲與並發上限模擬網站的回應速度與限流。
+pub struct MockSite {
+    list_url: String,
+}
+
+impl MockSite {
+    pub async fn start(config: MockConfig) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.context("無法啟動模擬網站")?;
+        let addr = listener.local_addr()?;
+        let origin = format!("http://{}", addr);
+        let in_flight = Arc::new(AtomicUsize::new(0));
+
+        let base = origin.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let in_flight = Arc::clone(&in_flight);
+                let origin = base.clone();
+                tokio::spawn(async move {
+                    let mut request = vec![0u8; 4096];
+                    let n = socket.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..n]).to_string();
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+                    let busy = in_flight.fetch_add(1, Ordering::SeqCst) >= config.max_in_flight;
+                    tokio::time::sleep(config.latency).await;
+                    let response = if busy {
+                        http_response("429 Too Many Requests", "text/plain", b"slow down".to_vec())
+                    } else {
+                        mock_response(&origin, &path, config.images_per_page)
+                    };
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    let _ = socket.write_all(&response).await;
+                });
+            }
+        });
+
+        Ok(Self {
+            list_url: format!("{}/list?page={{page}}", origin),
+        })
+    }
+
+    /// 列表頁網址樣板（`{page}` 代入頁碼）
+    pub fn list_url(&self) -> &str {
+        &self.list_url
+    }
+}
+
+fn mock_response(origin: &str, path: &str, images_per_page: usize) -> Vec<u8> {
+    if let Some(page) = path.split_once("page=").and_then(|(_, page)| page.parse::<u32>().ok()) {
+        let items: String = (0..images_per_page)
+            .map(|i| {
+                format!(
+                    r#"<div class="-shadow mt-3 mx-2 relative"><header><b>梗圖 {0}-{1}</b></header><a href="/p/{0}-{1}"><img src="{2}/img/{0}-{1}.png"></a></div>"#,
+                    page, i, origin
+                )
+            })
+            .collect();
+        return http_response("200 OK", "text/html; charset=utf-8", format!("<html><body>{}</body></html>", items).into_bytes());
+    }
+
+    if let Some(name) = path.strip_prefix("/img/").and_then(|name| name.strip_suffix(".png")) {
+        // 每張圖片的內容不同，不會被去重
+        let seed = name.bytes().fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32));
+        let image = image::RgbImage::from_fn(64, 64, |x, y| {
+            image::Rgb([(seed % 251) as u8, (x * 4) as u8, (y * 4 + seed % 7) as u8])
+        });
+        let mut bytes = Vec::new();
+        if image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png).is_ok() {
+            return http_response("200 OK", "image/png", bytes);
+        }
+    }
+
+    http_response("404 Not Found", "text/plain", Vec::new())
+}
+
+fn http_response(status: &str, content_type: &str, body: Vec<u8>) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )
+    .into_bytes();
+    response.extend(body);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{GenericParser, PageParser};
+
+    fn result(concurrency: usize, batch_delay_ms: u64, images: u64, failed_pages: u64) -> BenchResult {
+        BenchResult {
+            setting: BenchSetting { concurrency, batch_delay_ms },
+            pages: 20,
+            failed_pages,
+            images,
+            elapsed_secs: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_recommend() {
+        let settings = BenchSetting::sweep(&parse_list("10,2", "--concurrency", 1).unwrap(), &[500, 0, 500]);
+        assert_eq!(settings.len(), 4);
+        assert_eq!(settings[0], BenchSetting { concurrency: 2, batch_delay_ms: 0 });
+        assert!(parse_list::<usize>("2,x", "--concurrency", 1).is_err());
+        assert!(parse_list::<usize>("0,2", "--concurrency", 1).is_err());
+        assert_eq!(parse_list::<u64>("0,500", "--batch-delay", 0).unwrap(), vec![0, 500]);
+
+        // 最快的失敗率太高；差不多快時選並發數低的
+        let results = vec![
+            result(2, 500, 80, 0),
+            result(5, 0, 190, 0),
+            result(10, 0, 196, 1),
+            result(20, 0, 400, 5),
+        ];
+        let best = recommend(&results, MAX_ERROR_RATE).unwrap();
+        assert_eq!(best.setting, BenchSetting { concurrency: 5, batch_delay_ms: 0 });
+        assert_eq!(best.images_per_sec(), 19.0);
+        assert!(recommend(&results[3..], MAX_ERROR_RATE).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mock_site() {
+        let site = MockSite::start(MockConfig { images_per_page: 3, ..Default::default() }).await.unwrap();
+        let html = reqwest::get(site.list_url().replace("{page}", "2")).await.unwrap().text().await.unwrap();
+
+        let images = GenericParser::memes_tw().unwrap().parse_page(&html).unwrap();
+        assert_eq!(images.len(), 3);
+        assert_eq!(images[1].1, "梗圖 2-1");
+
+        let image = reqwest::get(&images[0].0).await.unwrap();
+        assert_eq!(image.headers()["content-type"], "image/png");
+        assert!(image::load_from_memory(&image.bytes().await.unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,441 @@
+use crate::context::DataContext;
+use crate::file_manager::FileManager;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use image::GrayImage;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+/// 分類結果寫入 metadata 的 `extra` 欄位
+pub const CLASS_FIELD: &str = "meme_class";
+pub const SCORE_FIELD: &str = "meme_score";
+pub const REASONS_FIELD: &str = "meme_reasons";
+/// `manual` 表示人工標記（重新分類時不覆蓋）
+pub const SOURCE_FIELD: &str = "meme_class_source";
+
+/// 待人工確認的圖片清單
+pub const REVIEW_QUEUE: &str = "classify_review.tsv";
+
+/// 是否為迷因圖
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemeClass {
+    Meme,
+    /// 橫幅、頭像、UI 元件等
+    NotMeme,
+    /// 分數落在兩個門檻之間，放進人工確認佇列
+    Borderline,
+}
+
+impl MemeClass {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "meme" => Ok(Self::Meme),
+            "not_meme" | "not-meme" => Ok(Self::NotMeme),
+            "borderline" => Ok(Self::Borderline),
+            other => anyhow::bail!("未知的分類: {}（可用: meme, not_meme, borderline）", other),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Meme => "meme",
+            Self::NotMeme => "not_meme",
+            Self::Borderline => "borderline",
+        }
+    }
+
+    /// 解析逗號分隔的分類清單（`not_meme,borderline`）
+    pub fn parse_list(spec: &str) -> Result<HashSet<Self>> {
+        spec.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+}
+
+/// 單張圖片的分類
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub class: MemeClass,
+    /// 0~1，越高越像迷因圖
+    pub score: f64,
+    /// 加減分的原因
+    pub reasons: Vec<String>,
+}
+
+/// 規則與門檻
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassifierConfig {
+    /// 短邊小於此值視為頭像/圖示
+    pub min_side: u32,
+    /// 長寬比超過此值視為橫幅
+    pub max_aspect: f64,
+    /// 小於此大小（bytes）的檔案通常是 UI 元件
+    pub min_bytes: u64,
+    /// 分數達到此值為 meme
+    pub meme_threshold: f64,
+    /// 分數低於此值為 not_meme
+    pub not_meme_threshold: f64,
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        Self {
+            min_side: 150,
+            max_aspect: 3.0,
+            min_bytes: 4 * 1024,
+            meme_threshold: 0.6,
+            not_meme_threshold: 0.35,
+        }
+    }
+}
+
+impl ClassifierConfig {
+    fn class_for(&self, score: f64) -> MemeClass {
+        if score >= self.meme_threshold {
+            MemeClass::Meme
+        } else if score < self.not_meme_threshold {
+            MemeClass::NotMeme
+        } else {
+            MemeClass::Borderline
+        }
+    }
+}
+
+/// 分類器（規則式或外部模型）
+pub trait MemeClassifier: Send + Sync {
+    fn classify(&self, path: &Path) -> Result<Classification>;
+}
+
+/// 依尺寸、長寬比、檔案大小、色彩數與文字區塊判斷的規則式分類器
+#[derive(Debug, Clone, Default)]
+pub struct RuleClassifier {
+    config: ClassifierConfig,
+}
+
+impl RuleClassifier {
+    pub fn new(config: ClassifierConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl MemeClassifier for RuleClassifier {
+    fn classify(&self, path: &Path) -> Result<Classification> {
+        let file_size = fs::metadata(path)
+            .with_context(|| format!("無法讀取 {}", path.display()))?
+            .len();
+        let image = image::open(path).with_context(|| format!("無法解碼 {}", path.display()))?;
+        let (width, height) = (image.width(), image.height());
+
+        let mut score: f64 = 0.5;
+        let mut reasons = Vec::new();
+        let mut adjust = |delta: f64, reason: String| {
+            score += delta;
+            reasons.push(format!("{:+.2} {}", delta, reason));
+        };
+
+        let short_side = width.min(height);
+        let aspect = width.max(height) as f64 / short_side.max(1) as f64;
+        if short_side < self.config.min_side {
+            adjust(-0.4, format!("尺寸過小 {}x{}（頭像/圖示）", width, height));
+        } else if short_side >= 300 {
+            adjust(0.1, format!("尺寸 {}x{}", width, height));
+        }
+        if aspect > self.config.max_aspect {
+            adjust(-0.4, format!("長寬比 {:.1}（橫幅）", aspect));
+        }
+        if file_size < self.config.min_bytes {
+            adjust(-0.1, format!("檔案過小 {} bytes", file_size));
+        }
+
+        let thumbnail = image.thumbnail(256, 256);
+        let colors = color_count(&thumbnail.to_rgb8());
+        if colors < 16 {
+            adjust(-0.15, format!("色彩過少 {}（UI 元件）", colors));
+        }
+
+        let text = caption_score(&thumbnail.to_luma8());
+        if text >= 0.15 {
+            adjust(0.3, format!("上下緣有文字 {:.2}", text));
+        } else {
+            adjust(-0.1, "上下緣沒有文字".to_string());
+        }
+
+        let score = score.clamp(0.0, 1.0);
+        Ok(Classification {
+            class: self.config.class_for(score),
+            score,
+            reasons,
+        })
+    }
+}
+
+/// 縮圖中的顏色數（量化到每通道 32 階，最多數到 256）
+fn color_count(image: &image::RgbImage) -> usize {
+    let mut colors = HashSet::new();
+    for pixel in image.pixels() {
+        colors.insert([pixel[0] / 8, pixel[1] / 8, pixel[2] / 8]);
+        if colors.len() >= 256 {
+            break;
+        }
+    }
+    colors.len()
+}
+
+/// 上下各 25% 區塊中「像文字」的列比例
+///
+/// 迷因圖的標題多在上下緣，文字列的明暗變化很密集（每 100px 至少 8 次強烈變化）。
+fn caption_score(gray: &GrayImage) -> f64 {
+    let (width, height) = gray.dimensions();
+    if width < 8 || height < 8 {
+        return 0.0;
+    }
+
+    let band = (height / 4).max(1);
+    let rows = (0..band).chain(height - band..height);
+    let mut texty = 0;
+    let mut total = 0;
+
+    for y in rows {
+        let transitions = (1..width)
+            .filter(|&x| gray.get_pixel(x, y)[0].abs_diff(gray.get_pixel(x - 1, y)[0]) > 60)
+            .count();
+        if transitions as f64 * 100.0 / width as f64 >= 8.0 {
+            texty += 1;
+        }
+        total += 1;
+    }
+
+    texty as f64 / total as f64
+}
+
+/// 以外部程式分類（例如載入模型的腳本）
+///
+/// 執行 `<command> <圖片路徑>`，stdout 第一行是 0~1 的分數，依門檻決定分類。
+#[derive(Debug, Clone)]
+pub struct CommandClassifier {
+    command: String,
+    config: ClassifierConfig,
+}
+
+impl CommandClassifier {
+    pub fn new(command: &str, config: ClassifierConfig) -> Self {
+        Self {
+            command: command.to_string(),
+            config,
+        }
+    }
+}
+
+impl MemeClassifier for CommandClassifier {
+    fn classify(&self, path: &Path) -> Result<Classification> {
+        let mut parts = self.command.split_whitespace();
+        let program = parts.next().context("--model 需要指令")?;
+        let output = Command::new(program)
+            .args(parts)
+            .arg(path)
+            .output()
+            .with_context(|| format!("無法執行分類模型: {}", self.command))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "分類模型失敗（{}）: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let score: f64 = stdout
+            .lines()
+            .next()
+            .and_then(|line| line.trim().parse().ok())
+            .with_context(|| format!("分類模型的輸出不是分數: {}", stdout.trim()))?;
+        let score = score.clamp(0.0, 1.0);
+
+        Ok(Classification {
+            class: self.config.class_for(score),
+            score,
+            reasons: vec![format!("模型分數 {:.2}", score)],
+        })
+    }
+}
+
+/// metadata 目前的分類（尚未分類時為 None）
+pub fn class_of(metadata: &ImageMetadata) -> Option<MemeClass> {
+    metadata
+        .extra
+        .get(CLASS_FIELD)
+        .and_then(|v| v.as_str())
+        .and_then(|name| MemeClass::parse(name).ok())
+}
+
+/// 是否為人工標記的分類
+pub fn is_manual(metadata: &ImageMetadata) -> bool {
+    metadata.extra.get(SOURCE_FIELD).and_then(|v| v.as_str()) == Some("manual")
+}
+
+/// 寫入分類結果（`source` 為 rules、model 或 manual）
+pub fn set_class(metadata: &mut ImageMetadata, classification: &Classification, source: &str) {
+    let extra = &mut metadata.extra;
+    extra.insert(CLASS_FIELD.to_string(), classification.class.label().into());
+    extra.insert(
+        SCORE_FIELD.to_string(),
+        ((classification.score * 100.0).round() / 100.0).into(),
+    );
+    extra.insert(REASONS_FIELD.to_string(), classification.reasons.clone().into());
+    extra.insert(SOURCE_FIELD.to_string(), source.into());
+}
+
+/// 分類全部圖片的結果
+#[derive(Debug, Default)]
+pub struct ClassifyReport {
+    /// (索引, 分類)，索引對應傳入的 metadata
+    pub classified: Vec<(usize, Classification)>,
+    /// 人工標記或已分類而略過的數量
+    pub skipped: usize,
+    /// 找不到或無法解碼的檔案
+    pub failed: Vec<(String, String)>,
+}
+
+impl ClassifyReport {
+    pub fn count(&self, class: MemeClass) -> usize {
+        self.classified.iter().filter(|(_, c)| c.class == class).count()
+    }
+}
+
+/// 分類圖片（人工標記的一律略過；`reclassify` 為 false 時也略過已分類的）
+pub fn classify_all(
+    classifier: &dyn MemeClassifier,
+    context: &DataContext,
+    metadata: &[ImageMetadata],
+    reclassify: bool,
+) -> ClassifyReport {
+    let mut report = ClassifyReport::default();
+
+    for (i, m) in metadata.iter().enumerate() {
+        if is_manual(m) || (!reclassify && class_of(m).is_some()) {
+            report.skipped += 1;
+            continue;
+        }
+
+        match context.original_path(&m.filename).and_then(|path| classifier.classify(Path::new(&path))) {
+            Ok(classification) => report.classified.push((i, classification)),
+            Err(e) => report.failed.push((m.filename.clone(), e.to_string())),
+        }
+    }
+
+    report
+}
+
+/// 把 borderline 的圖片寫入人工確認佇列（`classify_review.tsv`），回傳路徑與筆數
+pub fn write_review_queue(file_manager: &FileManager, metadata: &[ImageMetadata]) -> Result<(String, usize)> {
+    let path = format!("{}/{}", file_manager.root_dir(), REVIEW_QUEUE);
+    let temp_path = format!("{}.tmp", path);
+    let mut file = fs::File::create(&temp_path).context("無法建立確認佇列")?;
+    writeln!(file, "filename\tscore\treasons\tpath")?;
+
+    let mut count = 0;
+    for m in metadata {
+        if class_of(m) != Some(MemeClass::Borderline) || is_manual(m) {
+            continue;
+        }
+        let score = m.extra.get(SCORE_FIELD).and_then(|v| v.as_f64()).unwrap_or_default();
+        let reasons = m
+            .extra
+            .get(REASONS_FIELD)
+            .and_then(|v| v.as_array())
+            .map(|reasons| reasons.iter().filter_map(|r| r.as_str()).collect::<Vec<_>>().join("; "))
+            .unwrap_or_default();
+        writeln!(file, "{}\t{:.2}\t{}\t{}", m.filename, score, reasons, file_manager.get_image_path(&m.filename))?;
+        count += 1;
+    }
+
+    file.flush()?;
+    fs::rename(&temp_path, &path).context("無法寫入確認佇列")?;
+    Ok((path, count))
+}
+
+/// 排除指定分類的圖片（尚未分類的保留）
+pub fn exclude_classes(metadata: Vec<ImageMetadata>, excluded: &HashSet<MemeClass>) -> Vec<ImageMetadata> {
+    metadata
+        .into_iter()
+        .filter(|m| class_of(m).is_none_or(|class| !excluded.contains(&class)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MetadataBackend;
+    use image::{Rgb, RgbImage};
+
+    fn save(file_manager: &FileManager, name: &str, image: &RgbImage) {
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        file_manager.save_image(name, &bytes).unwrap();
+    }
+
+    #[test]
+    fn test_rule_classifier() {
+        let dir = std::env::temp_dir().join(format!("meme-classify-{}", std::process::id()));
+        let context = DataContext::open(dir.to_str().unwrap(), MetadataBackend::Jsonl).unwrap();
+        let file_manager = context.file_manager();
+
+        // 上下緣有密集黑白條紋（像標題文字），中間是漸層照片
+        let meme = RgbImage::from_fn(500, 500, |x, y| {
+            if !(100..400).contains(&y) && (y / 4) % 2 == 0 {
+                if (x / 3) % 2 == 0 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) }
+            } else {
+                Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+            }
+        });
+        save(file_manager, "meme.png", &meme);
+        save(file_manager, "avatar.png", &RgbImage::from_pixel(64, 64, Rgb([200, 30, 30])));
+        save(file_manager, "banner.png", &RgbImage::from_fn(1200, 150, |x, _| Rgb([(x % 256) as u8, 0, 0])));
+
+        let classifier = RuleClassifier::default();
+        let path = |name: &str| std::path::PathBuf::from(file_manager.get_image_path(name));
+
+        let result = classifier.classify(&path("meme.png")).unwrap();
+        assert_eq!(result.class, MemeClass::Meme, "{:?}", result);
+        assert_eq!(classifier.classify(&path("avatar.png")).unwrap().class, MemeClass::NotMeme);
+        assert_eq!(classifier.classify(&path("banner.png")).unwrap().class, MemeClass::NotMeme);
+
+        let mut metadata: Vec<ImageMetadata> = ["meme.png", "avatar.png", "gone.png"]
+            .iter()
+            .map(|filename| serde_json::from_value(serde_json::json!({
+                "filename": filename, "description": "", "url": "", "content_hash": "h",
+                "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z",
+            })).unwrap())
+            .collect();
+
+        let report = classify_all(&classifier, &context, &metadata, false);
+        assert_eq!((report.classified.len(), report.failed.len()), (2, 1));
+        for (i, classification) in &report.classified {
+            set_class(&mut metadata[*i], classification, "rules");
+        }
+
+        // 人工標記不會被重新分類覆蓋；排除 not_meme 時保留尚未分類的
+        let manual = Classification { class: MemeClass::Borderline, score: 0.5, reasons: vec![] };
+        set_class(&mut metadata[1], &manual, "manual");
+        let report = classify_all(&classifier, &context, &metadata, true);
+        assert_eq!(report.skipped, 1);
+
+        metadata[0].extra.insert(SOURCE_FIELD.to_string(), "rules".into());
+        metadata[0].extra.insert(CLASS_FIELD.to_string(), "borderline".into());
+        let (_, queued) = write_review_queue(file_manager, &metadata).unwrap();
+        assert_eq!(queued, 1);
+
+        let excluded = MemeClass::parse_list("borderline").unwrap();
+        let kept = exclude_classes(metadata, &excluded);
+        assert_eq!(kept.iter().map(|m| m.filename.as_str()).collect::<Vec<_>>(), ["gone.png"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
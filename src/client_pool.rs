@@ -0,0 +1,250 @@
+use crate::types::ConnectionReport;
+use anyhow::{Context, Result};
+use hyper_util::client::legacy::connect::Connection;
+use reqwest::{Client, ClientBuilder};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::task::{Context as TaskContext, Poll};
+use std::time::Instant;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// 共用 client 的 key：client 設定（timeout、預設 headers 等）的名稱與代理
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientKey {
+    /// 設定名稱（例如 `page:30s`、`download:60s`、`bing`），同名稱的 builder 設定必須相同
+    pub profile: String,
+    pub proxy: Option<String>,
+}
+
+/// 依 (設定, 代理) 共用 `reqwest::Client`
+///
+/// 每個 `Client` 有自己的連線池與 TLS session，爬蟲、下載與各搜尋服務各建一個時，
+/// pipeline 同時執行會開出好幾份；相同設定的元件改從這裡取得，clone 出來的 client 共用同一個連線池。
+#[derive(Default)]
+pub struct ClientFactory {
+    clients: Mutex<HashMap<ClientKey, Client>>,
+    reused: AtomicUsize,
+}
+
+impl ClientFactory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 整個程式共用的 factory
+    pub fn global() -> &'static ClientFactory {
+        static GLOBAL: OnceLock<ClientFactory> = OnceLock::new();
+        GLOBAL.get_or_init(ClientFactory::new)
+    }
+
+    /// 取得 (profile, proxy) 的 client，第一次時以 `builder` 建立（代理由這裡加上）
+    pub fn client(&self, profile: &str, proxy: Option<&str>, builder: impl FnOnce() -> ClientBuilder) -> Result<Client> {
+        let key = ClientKey {
+            profile: profile.to_string(),
+            proxy: proxy.map(str::to_string),
+        };
+
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(&key) {
+            self.reused.fetch_add(1, Ordering::Relaxed);
+            return Ok(client.clone());
+        }
+
+        let mut builder = instrument(builder());
+        if let Some(url) = proxy {
+            builder = builder.proxy(reqwest::Proxy::all(url).with_context(|| format!("無效的代理 URL: {}", url))?);
+        }
+        let client = builder.build().context("無法建立 HTTP 客戶端")?;
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    /// 建立過的 client 數（每個都有自己的連線池）
+    pub fn pooled(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// 重複使用既有 client 的次數
+    pub fn reused(&self) -> usize {
+        self.reused.load(Ordering::Relaxed)
+    }
+}
+
+/// 加上連線統計（所有 client 都應經過這裡，`ClientFactory` 會自動套用）
+pub fn instrument(builder: ClientBuilder) -> ClientBuilder {
+    // TLS 資訊放進連線的 extras，用來區分 TLS 交握與明文連線
+    builder.tls_info(true).connector_layer(CountConnectionsLayer)
+}
+
+/// HTTP 層的連線統計（整個程式共用）
+///
+/// 新連線在 reqwest 的 connector 計算（每建立一條 TCP 連線呼叫一次），請求數由送出請求的地方記錄，
+/// 兩者相減就是重複使用連線池的請求數。
+#[derive(Debug, Default)]
+pub struct ConnectionStats {
+    requests: AtomicUsize,
+    connections: AtomicUsize,
+    tls_handshakes: AtomicUsize,
+    failures: AtomicUsize,
+    /// 建立連線（含 DNS、TCP 與 TLS 交握）的總時間
+    connect_micros: AtomicU64,
+}
+
+impl ConnectionStats {
+    pub fn global() -> &'static ConnectionStats {
+        static GLOBAL: OnceLock<ConnectionStats> = OnceLock::new();
+        GLOBAL.get_or_init(ConnectionStats::default)
+    }
+
+    /// 送出一個請求（含重試）
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_connect(&self, elapsed: std::time::Duration, tls: Option<bool>) {
+        self.connect_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        match tls {
+            Some(tls) => {
+                self.connections.fetch_add(1, Ordering::Relaxed);
+                if tls {
+                    self.tls_handshakes.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            None => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> ConnectionReport {
+        let connections = self.connections.load(Ordering::Relaxed);
+        let failures = self.failures.load(Ordering::Relaxed);
+        ConnectionReport {
+            requests: self.requests.load(Ordering::Relaxed),
+            new_connections: connections,
+            tls_handshakes: self.tls_handshakes.load(Ordering::Relaxed),
+            connect_failures: failures,
+            connect_ms: self.connect_micros.load(Ordering::Relaxed) / 1000,
+        }
+    }
+}
+
+/// 計算新連線的 connector layer
+#[derive(Debug, Clone, Copy)]
+pub struct CountConnectionsLayer;
+
+impl<S> Layer<S> for CountConnectionsLayer {
+    type Service = CountConnections<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountConnections { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CountConnections<S> {
+    inner: S,
+}
+
+impl<S, Req> Service<Req> for CountConnections<S>
+where
+    S: Service<Req>,
+    S::Response: Connection + Send + 'static,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = std::result::Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Req) -> Self::Future {
+        let started = Instant::now();
+        let connect = self.inner.call(request);
+        Box::pin(async move {
+            let result = connect.await;
+            let tls = result.as_ref().ok().map(|conn| {
+                let mut extensions = http::Extensions::new();
+                conn.connected().get_extras(&mut extensions);
+                extensions.get::<reqwest::tls::TlsInfo>().is_some()
+            });
+            ConnectionStats::global().record_connect(started.elapsed(), tls);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_by_profile_and_proxy() {
+        let factory = ClientFactory::new();
+        let builder = || Client::builder().timeout(std::time::Duration::from_secs(5));
+
+        factory.client("page:5s", None, builder).unwrap();
+        factory.client("page:5s", None, builder).unwrap();
+        factory.client("page:5s", Some("http://127.0.0.1:8080"), builder).unwrap();
+        factory.client("bing", None, builder).unwrap();
+        assert_eq!(factory.pooled(), 3);
+        assert_eq!(factory.reused(), 1);
+
+        assert!(factory.client("page:5s", Some("not a url"), builder).is_err());
+        assert_eq!(factory.pooled(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_connection_stats() {
+        use std::io::{BufRead, BufReader, Write};
+
+        // keep-alive 的本地伺服器，記錄接受的連線數
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = std::sync::Arc::clone(&accepted);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                counter.fetch_add(1, Ordering::Relaxed);
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                        if line == "\r\n" {
+                            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+                        }
+                        line.clear();
+                    }
+                });
+            }
+        });
+
+        let factory = ClientFactory::new();
+        let client = factory.client("stats-test", None, Client::builder).unwrap();
+        let before = ConnectionStats::global().snapshot();
+        for _ in 0..3 {
+            ConnectionStats::global().record_request();
+            let body = client.get(format!("http://{}/", addr)).send().await.unwrap().text().await.unwrap();
+            assert_eq!(body, "ok");
+        }
+        let stats = ConnectionStats::global().snapshot().since(&before);
+
+        // 其他測試可能同時建立連線，只檢查下限
+        assert_eq!(accepted.load(Ordering::Relaxed), 1);
+        assert!(stats.new_connections >= 1);
+        assert!(stats.requests >= 3);
+
+        let report = ConnectionReport { requests: 40, new_connections: 30, ..Default::default() };
+        assert_eq!(report.reused(), 10);
+        assert!(report.reconnects_excessive());
+        assert!(!ConnectionReport { requests: 40, new_connections: 2, ..Default::default() }.reconnects_excessive());
+    }
+}
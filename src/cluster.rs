@@ -0,0 +1,343 @@
+//! 以 embedding 把同一個模板（不同文字）的迷因圖分群
+//!
+//! 內容雜湊只能找出完全相同的檔案；同一個模板配上不同標題時雜湊完全不同。
+//! 這裡把每張圖轉成向量，cosine 相似度超過門檻的歸為同一群，群組編號寫進 metadata 的
+//! `extra.template_cluster`。
+//!
+//! 內建的 `LayoutEmbedder` 不需要模型：去掉上下緣（迷因標題通常在那裡）後縮成小圖當向量，
+//! 適合找「同一張底圖」。要分出構圖不同但同模板的圖，用 `--model` 指定外部 embedding 程式
+//! （例如以 onnxruntime 執行 CLIP 或 MobileNet 的腳本），以外掛協定（見 `plugins`）回應
+//! `embed` 請求：`params.path` 是圖片路徑，`result` 是浮點數陣列。
+
+use crate::context::DataContext;
+use crate::file_manager;
+use crate::plugins::{self, PluginProcess};
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// embedding 快取（依模型與內容雜湊，只會追加）
+pub const EMBEDDINGS_FILE: &str = "embeddings.jsonl";
+
+/// 群組編號寫入 metadata 的 `extra` 欄位
+pub const CLUSTER_FIELD: &str = "template_cluster";
+
+/// 預設的 cosine 相似度門檻
+pub const DEFAULT_THRESHOLD: f32 = 0.9;
+
+/// 內建 embedding 的縮圖邊長
+const LAYOUT_SIZE: u32 = 12;
+
+/// 把圖片轉成向量
+pub trait Embedder: Send + Sync {
+    /// 寫入快取的模型名稱（換模型時不會用到舊的向量）
+    fn name(&self) -> &str;
+    fn embed(&self, path: &Path) -> Result<Vec<f32>>;
+}
+
+/// 去掉上下各 20%（標題文字）後縮成 12x12 的彩色縮圖，減去平均值後正規化
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LayoutEmbedder;
+
+impl Embedder for LayoutEmbedder {
+    fn name(&self) -> &str {
+        "layout-v1"
+    }
+
+    fn embed(&self, path: &Path) -> Result<Vec<f32>> {
+        let image = image::open(path).with_context(|| format!("無法解碼 {}", path.display()))?;
+        let (width, height) = (image.width(), image.height());
+        let band = height / 5;
+        let middle = image.crop_imm(0, band, width, height - band * 2);
+        let small = middle
+            .resize_exact(LAYOUT_SIZE, LAYOUT_SIZE, image::imageops::FilterType::Triangle)
+            .to_rgb8();
+
+        let mut vector: Vec<f32> = small.into_raw().into_iter().map(|v| v as f32 / 255.0).collect();
+        let mean = vector.iter().sum::<f32>() / vector.len() as f32;
+        vector.iter_mut().for_each(|v| *v -= mean);
+        Ok(normalize(vector))
+    }
+}
+
+/// 以外部程式計算 embedding（常駐子程式，模型只載入一次）
+pub struct ModelEmbedder {
+    name: String,
+    process: PluginProcess,
+}
+
+impl ModelEmbedder {
+    pub fn new(command: &str) -> Self {
+        Self {
+            name: format!("model:{}", command),
+            process: PluginProcess::new("embedding", command, plugins::DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+impl Embedder for ModelEmbedder {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn embed(&self, path: &Path) -> Result<Vec<f32>> {
+        let result = self.process.call("embed", json!({ "path": path }))?;
+        let vector: Vec<f32> = serde_json::from_value(result).context("embedding 程式的回應不是數字陣列")?;
+        if vector.is_empty() {
+            anyhow::bail!("embedding 程式回傳空向量");
+        }
+        Ok(normalize(vector))
+    }
+}
+
+/// 正規化成單位向量（全為 0 時不變，與任何向量的相似度都是 0）
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > f32::EPSILON {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// 兩個單位向量的 cosine 相似度（維度不同時為 0）
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// `embeddings.jsonl` 的一行
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEmbedding {
+    model: String,
+    content_hash: String,
+    /// little-endian f32 的 base64
+    vector: String,
+}
+
+/// embedding 快取
+pub struct EmbeddingCache {
+    path: String,
+    entries: HashMap<(String, String), Vec<f32>>,
+}
+
+impl EmbeddingCache {
+    /// 讀取資料目錄的 embeddings.jsonl（壞掉的行略過）
+    pub fn load(data_dir: &str) -> Result<Self> {
+        let path = file_manager::join_path(data_dir, EMBEDDINGS_FILE);
+        let mut entries = HashMap::new();
+
+        if Path::new(&path).exists() {
+            let reader = BufReader::new(fs::File::open(&path).context("無法開啟 embeddings.jsonl")?);
+            for line in reader.lines() {
+                let line = line?;
+                let Ok(cached) = serde_json::from_str::<CachedEmbedding>(&line) else {
+                    continue;
+                };
+                let Ok(bytes) = STANDARD.decode(&cached.vector) else {
+                    continue;
+                };
+                let vector = bytes
+                    .chunks_exact(4)
+                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect();
+                entries.insert((cached.model, cached.content_hash), vector);
+            }
+        }
+
+        Ok(Self { path, entries })
+    }
+
+    pub fn get(&self, model: &str, content_hash: &str) -> Option<&Vec<f32>> {
+        self.entries.get(&(model.to_string(), content_hash.to_string()))
+    }
+
+    /// 追加一筆（同時寫入檔案）
+    pub fn insert(&mut self, model: &str, content_hash: &str, vector: Vec<f32>) -> Result<()> {
+        let bytes: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let line = serde_json::to_string(&CachedEmbedding {
+            model: model.to_string(),
+            content_hash: content_hash.to_string(),
+            vector: STANDARD.encode(bytes),
+        })?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("無法寫入 embeddings.jsonl")?;
+        writeln!(file, "{}", line)?;
+
+        self.entries.insert((model.to_string(), content_hash.to_string()), vector);
+        Ok(())
+    }
+}
+
+/// `embed_all` 的結果
+#[derive(Debug, Default)]
+pub struct EmbedReport {
+    /// (內容雜湊, 向量)，依 metadata 順序，相同內容只算一次
+    pub embeddings: Vec<(String, Vec<f32>)>,
+    /// 這次新計算的數量（其餘來自快取）
+    pub computed: usize,
+    /// 找不到或無法計算的檔案
+    pub failed: Vec<(String, String)>,
+}
+
+/// 取得每個不同內容的 embedding（快取沒有的才計算）
+pub fn embed_all(
+    embedder: &dyn Embedder,
+    context: &DataContext,
+    metadata: &[ImageMetadata],
+    cache: &mut EmbeddingCache,
+) -> Result<EmbedReport> {
+    let mut report = EmbedReport::default();
+    let mut seen = std::collections::HashSet::new();
+
+    for m in metadata {
+        if !seen.insert(m.content_hash.as_str()) {
+            continue;
+        }
+        if let Some(vector) = cache.get(embedder.name(), &m.content_hash) {
+            report.embeddings.push((m.content_hash.clone(), vector.clone()));
+            continue;
+        }
+
+        match context.original_path(&m.filename).and_then(|path| embedder.embed(Path::new(&path))) {
+            Ok(vector) => {
+                cache.insert(embedder.name(), &m.content_hash, vector.clone())?;
+                report.embeddings.push((m.content_hash.clone(), vector));
+                report.computed += 1;
+            }
+            Err(e) => report.failed.push((m.filename.clone(), e.to_string())),
+        }
+    }
+
+    Ok(report)
+}
+
+/// 依相似度分群，只回傳兩個以上不同內容的群（每群是內容雜湊，依出現順序）
+///
+/// 每張圖與各群的第一張（代表）比較，加入相似度最高且超過門檻的群，否則自成一群；
+/// 比較次數是 圖片數 × 群數，數萬張也只需幾秒。
+pub fn cluster_hashes(embeddings: &[(String, Vec<f32>)], threshold: f32) -> Vec<Vec<String>> {
+    let mut clusters: Vec<(usize, Vec<String>)> = Vec::new();
+
+    for (i, (hash, vector)) in embeddings.iter().enumerate() {
+        let best = clusters
+            .iter()
+            .enumerate()
+            .map(|(c, (leader, _))| (c, similarity(&embeddings[*leader].1, vector)))
+            .filter(|(_, score)| *score >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match best {
+            Some((c, _)) => clusters[c].1.push(hash.clone()),
+            None => clusters.push((i, vec![hash.clone()])),
+        }
+    }
+
+    clusters
+        .into_iter()
+        .map(|(_, members)| members)
+        .filter(|members| members.len() > 1)
+        .collect()
+}
+
+/// 群組編號（`t00001`，依群組順序）
+pub fn cluster_id(index: usize) -> String {
+    format!("t{:05}", index + 1)
+}
+
+/// metadata 目前的群組（沒有時為 None）
+pub fn cluster_of(metadata: &ImageMetadata) -> Option<&str> {
+    metadata.extra.get(CLUSTER_FIELD).and_then(|v| v.as_str())
+}
+
+/// 把群組編號寫入 metadata（不屬於任何群的移除舊編號），回傳有群組的圖片數
+pub fn apply_clusters(metadata: &mut [ImageMetadata], clusters: &[Vec<String>]) -> usize {
+    let by_hash: HashMap<&str, String> = clusters
+        .iter()
+        .enumerate()
+        .flat_map(|(i, members)| members.iter().map(move |hash| (hash.as_str(), cluster_id(i))))
+        .collect();
+
+    let mut clustered = 0;
+    for m in metadata.iter_mut() {
+        match by_hash.get(m.content_hash.as_str()) {
+            Some(id) => {
+                m.extra.insert(CLUSTER_FIELD.to_string(), id.clone().into());
+                clustered += 1;
+            }
+            None => {
+                m.extra.remove(CLUSTER_FIELD);
+            }
+        }
+    }
+    clustered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MetadataBackend;
+    use image::{Rgb, RgbImage};
+
+    /// 同一張底圖，上下緣配上不同的「文字」條紋
+    fn meme(seed: u32, caption: u32) -> RgbImage {
+        RgbImage::from_fn(300, 300, |x, y| {
+            if !(60..240).contains(&y) {
+                if (x / (2 + caption)).is_multiple_of(2) { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) }
+            } else {
+                Rgb([((x * seed) % 256) as u8, ((y * 2) % 256) as u8, ((x + y * seed) % 256) as u8])
+            }
+        })
+    }
+
+    #[test]
+    fn test_cluster_templates() {
+        let dir = std::env::temp_dir().join(format!("meme-cluster-{}", std::process::id()));
+        let context = DataContext::open(dir.to_str().unwrap(), MetadataBackend::Jsonl).unwrap();
+        let file_manager = context.file_manager();
+
+        let images = [("a.png", meme(1, 0)), ("b.png", meme(1, 3)), ("c.png", meme(7, 0)), ("d.png", meme(1, 5))];
+        let mut metadata: Vec<ImageMetadata> = Vec::new();
+        for (filename, image) in &images {
+            let mut bytes = Vec::new();
+            image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png).unwrap();
+            file_manager.save_image(filename, &bytes).unwrap();
+            metadata.push(serde_json::from_value(json!({
+                "filename": filename, "description": "", "url": "", "content_hash": format!("h-{}", filename),
+                "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z",
+            })).unwrap());
+        }
+        metadata[2].extra.insert(CLUSTER_FIELD.to_string(), "t00009".into());
+
+        let mut cache = EmbeddingCache::load(dir.to_str().unwrap()).unwrap();
+        let report = embed_all(&LayoutEmbedder, &context, &metadata, &mut cache).unwrap();
+        assert_eq!((report.embeddings.len(), report.computed), (4, 4));
+
+        // 第二次全部來自快取
+        let mut cache = EmbeddingCache::load(dir.to_str().unwrap()).unwrap();
+        let cached = embed_all(&LayoutEmbedder, &context, &metadata, &mut cache).unwrap();
+        assert_eq!(cached.computed, 0);
+        assert_eq!(cached.embeddings, report.embeddings);
+
+        let clusters = cluster_hashes(&report.embeddings, DEFAULT_THRESHOLD);
+        assert_eq!(clusters, vec![vec!["h-a.png", "h-b.png", "h-d.png"]]);
+
+        assert_eq!(apply_clusters(&mut metadata, &clusters), 3);
+        assert_eq!(cluster_of(&metadata[1]), Some("t00001"));
+        assert_eq!(cluster_of(&metadata[2]), None);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
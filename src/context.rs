@@ -0,0 +1,178 @@
+use crate::file_manager::{self, FileManager};
+use crate::reverse_search::{self, ReverseSearchResult};
+use crate::store::{MetadataBackend, MetadataStore, SqliteStore};
+use crate::tier::ColdStorage;
+use crate::types::ImageMetadata;
+use anyhow::Result;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// 資料目錄中的檔案名稱
+pub mod files {
+    pub const SEARCH_RESULTS: &str = "reverse_search_results.jsonl";
+    pub const SEARCH_MERGED: &str = "reverse_search_merged.jsonl";
+    pub const SEARCH_PROGRESS: &str = "search_progress.json";
+    pub const SERVICE_LATENCY: &str = "service_latency.jsonl";
+    pub const DUPLICATES: &str = "duplicates.json";
+    pub const PIPELINE_STATE: &str = "pipeline_state.json";
+}
+
+/// 一個資料目錄的共用環境：路徑、metadata 後端與讀取過的資料
+///
+/// 命令開始時建立一次，傳給去重、反向搜尋與 pipeline，
+/// 同一次執行中 metadata 與搜尋結果只讀取一次。
+/// 透過 `rewrite_metadata` 寫入時快取會一併更新；其他元件直接寫入後端時（例如爬蟲）要呼叫 `invalidate`。
+pub struct DataContext {
+    root: String,
+    backend: MetadataBackend,
+    file_manager: Arc<FileManager>,
+    store: Arc<dyn MetadataStore>,
+    /// SQLite 後端時的搜尋結果來源
+    sqlite: Option<Arc<SqliteStore>>,
+    metadata: Mutex<Option<Arc<Vec<ImageMetadata>>>>,
+    results: Mutex<Option<Arc<Vec<ReverseSearchResult>>>>,
+    /// 冷儲存（第一次需要時開啟）
+    cold: Mutex<Option<Arc<ColdStorage>>>,
+}
+
+impl DataContext {
+    pub fn open(data_dir: &str, backend: MetadataBackend) -> Result<Arc<Self>> {
+        let file_manager = Arc::new(FileManager::new(data_dir)?);
+        let (store, sqlite): (Arc<dyn MetadataStore>, _) = match backend {
+            MetadataBackend::Jsonl => (Arc::clone(&file_manager) as _, None),
+            MetadataBackend::Sqlite => {
+                let sqlite = Arc::new(SqliteStore::open(&format!("{}/metadata.db", data_dir))?);
+                (Arc::clone(&sqlite) as _, Some(sqlite))
+            }
+        };
+
+        Ok(Arc::new(Self {
+            root: data_dir.to_string(),
+            backend,
+            file_manager,
+            store,
+            sqlite,
+            metadata: Mutex::new(None),
+            results: Mutex::new(None),
+            cold: Mutex::new(None),
+        }))
+    }
+
+    /// 資料根目錄
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    pub fn backend(&self) -> MetadataBackend {
+        self.backend
+    }
+
+    /// 資料目錄中的檔案路徑（檔名見 `files`）
+    pub fn path(&self, file: &str) -> String {
+        file_manager::join_path(&self.root, file)
+    }
+
+    pub fn file_manager(&self) -> &Arc<FileManager> {
+        &self.file_manager
+    }
+
+    pub fn store(&self) -> &Arc<dyn MetadataStore> {
+        &self.store
+    }
+
+    /// 冷儲存（依 tier.json，第一次呼叫時開啟）
+    pub fn cold_storage(&self) -> Result<Arc<ColdStorage>> {
+        let mut cold = self.cold.lock().unwrap();
+        if let Some(storage) = cold.as_ref() {
+            return Ok(Arc::clone(storage));
+        }
+
+        let storage = Arc::new(ColdStorage::open(&self.root)?);
+        *cold = Some(Arc::clone(&storage));
+        Ok(storage)
+    }
+
+    /// 原圖路徑：不在本地但已移到冷儲存時先取回
+    ///
+    /// 本地與冷儲存都沒有時照樣回傳路徑，由呼叫端處理檔案不存在。
+    pub fn original_path(&self, filename: &str) -> Result<String> {
+        let path = self.file_manager.get_image_path(filename);
+        if !Path::new(&path).exists() {
+            self.cold_storage()?.ensure_local(&self.file_manager, filename)?;
+        }
+        Ok(path)
+    }
+
+    /// 所有圖片 metadata（第一次呼叫時讀取）
+    pub fn metadata(&self) -> Result<Arc<Vec<ImageMetadata>>> {
+        let mut cached = self.metadata.lock().unwrap();
+        if let Some(metadata) = cached.as_ref() {
+            return Ok(Arc::clone(metadata));
+        }
+
+        let metadata = Arc::new(self.store.load_all_metadata()?);
+        *cached = Some(Arc::clone(&metadata));
+        Ok(metadata)
+    }
+
+    /// 以新的列表取代所有 metadata，並更新快取
+    pub fn rewrite_metadata(&self, metadata_list: Vec<ImageMetadata>) -> Result<()> {
+        self.store.rewrite_metadata(&metadata_list)?;
+        *self.metadata.lock().unwrap() = Some(Arc::new(metadata_list));
+        Ok(())
+    }
+
+    /// SQLite 後端的資料庫（JSONL 後端時為 None）
+    pub fn sqlite(&self) -> Option<&Arc<SqliteStore>> {
+        self.sqlite.as_ref()
+    }
+
+    /// 所有反向搜尋結果（依後端讀取，第一次呼叫時讀取）
+    pub fn search_results(&self) -> Result<Arc<Vec<ReverseSearchResult>>> {
+        let mut cached = self.results.lock().unwrap();
+        if let Some(results) = cached.as_ref() {
+            return Ok(Arc::clone(results));
+        }
+
+        let results = Arc::new(match &self.sqlite {
+            Some(sqlite) => sqlite.load_search_results()?,
+            None => reverse_search::load_all_results(&self.path(files::SEARCH_RESULTS))?,
+        });
+        *cached = Some(Arc::clone(&results));
+        Ok(results)
+    }
+
+    /// 丟棄快取，下次重新讀取（後端被其他元件寫入後呼叫）
+    pub fn invalidate(&self) {
+        *self.metadata.lock().unwrap() = None;
+        *self.results.lock().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_cache() {
+        let dir = std::env::temp_dir().join(format!("meme-context-{}", std::process::id()));
+        let context = DataContext::open(dir.to_str().unwrap(), MetadataBackend::Jsonl).unwrap();
+        assert_eq!(context.path(files::DUPLICATES), format!("{}/duplicates.json", dir.display()));
+
+        let metadata = ImageMetadata::for_test("a.jpg", "h1");
+
+        assert!(context.metadata().unwrap().is_empty());
+
+        // 直接寫入後端時快取不變，invalidate 後才重新讀取
+        context.store().append_metadata(&metadata).unwrap();
+        assert!(context.metadata().unwrap().is_empty());
+        context.invalidate();
+        assert_eq!(context.metadata().unwrap().len(), 1);
+
+        context.rewrite_metadata(vec![metadata.clone(), metadata]).unwrap();
+        assert_eq!(context.metadata().unwrap().len(), 2);
+        assert_eq!(context.store().load_all_metadata().unwrap().len(), 2);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
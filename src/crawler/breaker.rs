@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+/// 斷路器設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakerConfig {
+    /// 計算失敗率的最近頁數
+    pub window: usize,
+    /// 失敗率超過這個比例（0.0–1.0）就暫停爬取
+    pub failure_rate: f64,
+    /// 暫停期間每隔幾秒試抓一次頁面
+    pub probe_interval_secs: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            failure_rate: 0.5,
+            probe_interval_secs: 60,
+        }
+    }
+}
+
+/// 整個爬取的失敗率斷路器
+///
+/// 網站掛掉或 IP 被封鎖時每一頁都會失敗，繼續派發只會把剩下的頁碼全部標成失敗；
+/// 最近 `window` 頁的失敗率超過門檻時斷路，引擎暫停並定期試抓，成功後才繼續。
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    /// 最近幾頁是否成功（最舊的在前）
+    recent: VecDeque<bool>,
+    trips: u32,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            recent: VecDeque::with_capacity(config.window),
+            trips: 0,
+        }
+    }
+
+    pub fn config(&self) -> &BreakerConfig {
+        &self.config
+    }
+
+    /// 記錄一頁的結果，這頁讓斷路器跳脫時回傳 true
+    pub fn record(&mut self, success: bool) -> bool {
+        let was_open = self.is_open();
+
+        self.recent.push_back(success);
+        while self.recent.len() > self.config.window.max(1) {
+            self.recent.pop_front();
+        }
+
+        let tripped = !was_open && self.is_open();
+        if tripped {
+            self.trips += 1;
+        }
+        tripped
+    }
+
+    /// 最近頁數已滿且失敗率超過門檻
+    pub fn is_open(&self) -> bool {
+        self.recent.len() >= self.config.window.max(1) && self.failure_rate() > self.config.failure_rate
+    }
+
+    /// 最近頁數的失敗率
+    pub fn failure_rate(&self) -> f64 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        self.recent.iter().filter(|ok| !**ok).count() as f64 / self.recent.len() as f64
+    }
+
+    /// 試抓成功：清空紀錄，重新累積
+    pub fn reset(&mut self) {
+        self.recent.clear();
+    }
+
+    /// 本次執行斷路的次數
+    pub fn trips(&self) -> u32 {
+        self.trips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trip_and_reset() {
+        let mut breaker = CircuitBreaker::new(BreakerConfig {
+            window: 4,
+            failure_rate: 0.5,
+            probe_interval_secs: 1,
+        });
+
+        // 頁數未滿前不斷路
+        assert!(!breaker.record(false));
+        assert!(!breaker.record(false));
+        assert!(!breaker.is_open());
+
+        // 2/4 失敗未超過門檻
+        assert!(!breaker.record(true));
+        assert!(!breaker.record(true));
+        assert!(!breaker.is_open());
+
+        assert!(!breaker.record(false));
+        assert!(!breaker.record(false));
+        assert!(breaker.record(false));
+        assert!(breaker.is_open());
+        assert!(!breaker.record(false));
+        assert_eq!(breaker.trips(), 1);
+
+        breaker.reset();
+        assert!(!breaker.is_open());
+        for _ in 0..3 {
+            breaker.record(false);
+        }
+        assert!(breaker.record(true));
+        assert_eq!(breaker.trips(), 2);
+    }
+}
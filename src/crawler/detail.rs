@@ -0,0 +1,238 @@
+use super::parse_pool::ParsePool;
+use crate::fetcher::{Fetcher, HttpFetcher};
+use crate::parser::DetailPage;
+use crate::terminal;
+use anyhow::{Context, Result};
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// 已造訪的詳細頁（詳細頁網址 -> 解析結果）
+pub const DETAIL_CACHE_FILE: &str = "detail_pages.jsonl";
+
+/// 預設同時抓取的詳細頁數
+pub const DEFAULT_DETAIL_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedDetail {
+    url: String,
+    detail: DetailPage,
+}
+
+/// 列表頁回傳詳細頁網址時，抓取詳細頁換成原圖網址
+///
+/// 詳細頁有自己的並發數（與列表頁的 concurrency 無關）；解析過的詳細頁記在
+/// detail_pages.jsonl，重爬、diff-crawl 或中斷後繼續時不再重新抓取。
+#[derive(Clone)]
+pub struct DetailResolver {
+    cache: Arc<Mutex<HashMap<String, DetailPage>>>,
+    path: PathBuf,
+    concurrency: usize,
+}
+
+impl DetailResolver {
+    /// 讀取資料目錄的詳細頁快取
+    pub fn load(data_dir: &str, concurrency: usize) -> Result<Self> {
+        let path = Path::new(data_dir).join(DETAIL_CACHE_FILE);
+        let mut cache = HashMap::new();
+
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path).context("無法開啟 detail_pages.jsonl")?);
+            for line in reader.lines() {
+                let line = line.context("讀取行失敗")?;
+                // 寫到一半中斷的最後一行略過
+                if let Ok(cached) = serde_json::from_str::<CachedDetail>(&line) {
+                    cache.insert(cached.url, cached.detail);
+                }
+            }
+        }
+
+        Ok(Self {
+            cache: Arc::new(Mutex::new(cache)),
+            path,
+            concurrency: concurrency.max(1),
+        })
+    }
+
+    /// 已快取的詳細頁數
+    pub fn cached_count(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    fn get(&self, url: &str) -> Option<DetailPage> {
+        self.cache.lock().unwrap().get(url).cloned()
+    }
+
+    fn insert(&self, url: &str, detail: &DetailPage) -> Result<()> {
+        let mut cache = self.cache.lock().unwrap();
+        cache.insert(url.to_string(), detail.clone());
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("無法開啟 detail_pages.jsonl")?;
+        let cached = CachedDetail { url: url.to_string(), detail: detail.clone() };
+        writeln!(file, "{}", serde_json::to_string(&cached)?)
+            .context("無法寫入 detail_pages.jsonl")?;
+        Ok(())
+    }
+
+    /// 以詳細頁的原圖網址取代詳細頁網址（失敗的項目略過，順序不變）
+    ///
+    /// 詳細頁的標籤與欄位記在 `parser`，下載時寫入 metadata。
+    pub async fn resolve(
+        &self,
+        page: u32,
+        items: Vec<(String, String)>,
+        fetcher: &Arc<HttpFetcher>,
+        parser: &ParsePool,
+        status_pb: &ProgressBar,
+    ) -> Vec<(String, String)> {
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let total = items.len();
+        let mut tasks = JoinSet::new();
+
+        for (index, (detail_url, name)) in items.into_iter().enumerate() {
+            let resolver = self.clone();
+            let semaphore = Arc::clone(&semaphore);
+            let fetcher = Arc::clone(fetcher);
+            let parser = parser.clone();
+            let status_pb = status_pb.clone();
+
+            tasks.spawn(async move {
+                if let Some(detail) = resolver.get(&detail_url) {
+                    return (index, name, Some(detail));
+                }
+
+                let _permit = semaphore.acquire().await.unwrap();
+                status_pb.set_message(terminal::message(format!("🔎 第 {} 頁: 詳細頁 {}/{} ({})", page, index + 1, total, name)));
+
+                let detail = match fetcher.fetch_page(&detail_url).await {
+                    Ok(html) => parser.parse_detail_page(html).await,
+                    Err(e) => {
+                        eout!("詳細頁失敗 ({}): {}", detail_url, e);
+                        return (index, name, None);
+                    }
+                };
+                match detail {
+                    Ok(Some(detail)) => {
+                        if let Err(e) = resolver.insert(&detail_url, &detail) {
+                            eout!("⚠️  無法快取詳細頁 ({}): {}", detail_url, e);
+                        }
+                        (index, name, Some(detail))
+                    }
+                    Ok(None) => {
+                        eout!("詳細頁找不到圖片: {}", detail_url);
+                        (index, name, None)
+                    }
+                    Err(e) => {
+                        eout!("詳細頁解析失敗 ({}): {}", detail_url, e);
+                        (index, name, None)
+                    }
+                }
+            });
+        }
+
+        let mut resolved = Vec::with_capacity(total);
+        while let Some(result) = tasks.join_next().await {
+            if let Ok((index, name, Some(detail))) = result {
+                resolved.push((index, name, detail));
+            }
+        }
+        resolved.sort_by_key(|(index, _, _)| *index);
+
+        resolved
+            .into_iter()
+            .map(|(_, name, detail)| {
+                let url = detail.image_url.clone();
+                parser.remember_details(detail);
+                (url, name)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{DetailConfig, DetailParser, GenericParser};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// `/item/N` 是第 N 個項目的詳細頁，回傳伺服器位址與請求計數
+    async fn serve_details() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = [0u8; 1024];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let item = path.trim_start_matches("/item/");
+                let body = format!(
+                    r#"<img class="thumb" src="/t/{0}.jpg"><img class="full" src="data:," data-src="/full/{0}.jpg">
+                    <ul class="tags"><li>reaction</li><li>item{0}</li></ul><span class="year">2012</span>"#,
+                    item,
+                );
+                let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (format!("http://{}", addr), requests)
+    }
+
+    #[tokio::test]
+    async fn test_resolve_and_cache() {
+        let (base_url, requests) = serve_details().await;
+        let dir = std::env::temp_dir().join(format!("detail_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let data_dir = dir.to_str().unwrap();
+
+        let config: DetailConfig = serde_json::from_value(serde_json::json!({
+            "image_selector": "img.full",
+            "tag_selector": "ul.tags li",
+            "fields": { "origin_year": "span.year" },
+        }))
+        .unwrap();
+        let list = Arc::new(GenericParser::memes_tw().unwrap());
+        let parser = ParsePool::new(Arc::new(DetailParser::new(list, config).unwrap()), 2);
+        let fetcher = Arc::new(HttpFetcher::new(5, 0).unwrap().with_robots(false));
+        let items: Vec<_> = (1..=3)
+            .map(|i| (format!("{}/item/{}", base_url, i), format!("meme {}", i)))
+            .collect();
+
+        let resolver = DetailResolver::load(data_dir, 2).unwrap();
+        let images = resolver.resolve(1, items.clone(), &fetcher, &parser, &ProgressBar::hidden()).await;
+        assert_eq!(images, vec![
+            ("https://memes.tw/full/1.jpg".to_string(), "meme 1".to_string()),
+            ("https://memes.tw/full/2.jpg".to_string(), "meme 2".to_string()),
+            ("https://memes.tw/full/3.jpg".to_string(), "meme 3".to_string()),
+        ]);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        let detail = parser.take_details("https://memes.tw/full/2.jpg").unwrap();
+        assert_eq!(detail.tags, vec!["reaction", "item2"]);
+        assert_eq!(detail.extra["origin_year"], "2012");
+
+        // 重新載入快取後不再抓取詳細頁
+        let reloaded = DetailResolver::load(data_dir, 2).unwrap();
+        assert_eq!(reloaded.cached_count(), 3);
+        let again = reloaded.resolve(1, items, &fetcher, &parser, &ProgressBar::hidden()).await;
+        assert_eq!(again, images);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,87 @@
+use crate::integrity::sha256_hex;
+use crate::types::ImageMetadata;
+use std::collections::HashSet;
+
+/// 可重現的爬取設定
+///
+/// 啟用後：頁面依序提交、頁內項目依 URL 排序並去除重複、圖片逐張依序下載
+/// （metadata 寫入順序固定），檔名樣板不能包含 `{date}`。
+/// 抽樣只依 seed 與 URL 決定，與爬取順序無關，未啟用時也可以使用。
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Determinism {
+    pub enabled: bool,
+    /// 抽樣用的種子
+    pub seed: u64,
+    /// 保留的比例（0.0–1.0，None 表示全部保留）
+    pub sample_rate: Option<f64>,
+}
+
+impl Determinism {
+    /// 整理單頁的圖片列表：抽樣；啟用時再依 URL 排序並去除重複
+    pub fn arrange(&self, mut images: Vec<(String, String)>) -> Vec<(String, String)> {
+        images.retain(|(url, _)| self.keep(url));
+
+        if self.enabled {
+            let mut seen = HashSet::new();
+            images.retain(|(url, _)| seen.insert(url.clone()));
+            images.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        }
+
+        images
+    }
+
+    /// 抽樣：同樣的 seed 與 URL 永遠得到同樣的結果
+    pub fn keep(&self, url: &str) -> bool {
+        let Some(rate) = self.sample_rate else {
+            return true;
+        };
+
+        sample_score(self.seed, url) < rate
+    }
+}
+
+/// 依 seed 與 key 得到 [0, 1) 之間的固定分數
+pub fn sample_score(seed: u64, key: &str) -> f64 {
+    let digest = sha256_hex(format!("{}:{}", seed, key).as_bytes());
+    let prefix = u64::from_str_radix(&digest[..16], 16).unwrap_or(0);
+    prefix as f64 / u64::MAX as f64
+}
+
+/// 資料集內容摘要：依 metadata 順序計算檔名、URL 與內容雜湊（不含下載時間）
+pub fn content_digest(metadata: &[ImageMetadata]) -> String {
+    let mut lines = String::new();
+    for m in metadata {
+        lines.push_str(&format!("{}\t{}\t{}\n", m.filename, m.url, m.content_hash));
+    }
+    sha256_hex(lines.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrange_and_sample() {
+        let images = vec![
+            ("https://a.com/2.png".to_string(), "b".to_string()),
+            ("https://a.com/1.png".to_string(), "a".to_string()),
+            ("https://a.com/2.png".to_string(), "b".to_string()),
+        ];
+
+        let deterministic = Determinism { enabled: true, ..Default::default() };
+        let arranged = deterministic.arrange(images.clone());
+        assert_eq!(arranged.iter().map(|(u, _)| u.as_str()).collect::<Vec<_>>(), ["https://a.com/1.png", "https://a.com/2.png"]);
+
+        // 未啟用時保留原本的順序
+        assert_eq!(Determinism::default().arrange(images.clone()), images);
+
+        let sampled = Determinism { enabled: true, seed: 42, sample_rate: Some(0.5) };
+        let urls: Vec<String> = (0..1000).map(|i| format!("https://a.com/{}.png", i)).collect();
+        let kept: Vec<&String> = urls.iter().filter(|u| sampled.keep(u)).collect();
+        assert!(kept.len() > 400 && kept.len() < 600);
+        assert_eq!(kept, urls.iter().filter(|u| sampled.keep(u)).collect::<Vec<_>>());
+
+        let other_seed = Determinism { seed: 7, ..sampled };
+        assert_ne!(kept, urls.iter().filter(|u| other_seed.keep(u)).collect::<Vec<_>>());
+    }
+}
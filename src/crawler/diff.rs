@@ -0,0 +1,144 @@
+use crate::types::{ChangeKind, ImageMetadata, SiteChange};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashSet};
+
+/// 圖片數變少的頁面
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageDrop {
+    pub page: u32,
+    /// 本地 metadata 記錄的圖片數
+    pub stored: usize,
+    /// 重爬找到的圖片數
+    pub current: usize,
+}
+
+/// 重爬結果與本地 metadata 的比對
+#[derive(Debug, Default)]
+pub struct DiffReport {
+    /// 消失或換了網址的項目
+    pub changes: Vec<SiteChange>,
+    /// 圖片數變少的頁面
+    pub dropped_pages: Vec<PageDrop>,
+    /// 仍然存在的項目數
+    pub unchanged: usize,
+    /// 本地沒有的新項目數
+    pub new_items: usize,
+}
+
+/// 比對重爬的頁面（頁碼 → (圖片網址, 名稱)）與本地 metadata
+///
+/// 網站刪圖後後面的項目會往前移，所以只要在任何一頁重新找到同一個網址就算仍存在；
+/// 只比對原本所在頁面有重爬到的項目。找不到時，若原頁面有同名的新網址記為 changed，否則為 removed。
+pub fn compare(
+    stored: &[ImageMetadata],
+    current: &BTreeMap<u32, Vec<(String, String)>>,
+    detected_at: DateTime<Utc>,
+) -> DiffReport {
+    let current_urls: HashSet<&str> = current
+        .values()
+        .flatten()
+        .map(|(url, _)| url.as_str())
+        .collect();
+    let stored_urls: HashSet<&str> = stored.iter().map(|m| m.url.as_str()).collect();
+
+    let mut report = DiffReport::default();
+    let mut replacements = HashSet::new();
+
+    for metadata in stored.iter().filter(|m| current.contains_key(&m.page_number)) {
+        if current_urls.contains(metadata.url.as_str()) {
+            report.unchanged += 1;
+            continue;
+        }
+
+        // 同頁同名、而且本地沒有的網址視為換圖（每個新網址只配對一次）
+        let replacement = current[&metadata.page_number]
+            .iter()
+            .find(|(url, name)| {
+                *name == metadata.description
+                    && !stored_urls.contains(url.as_str())
+                    && !replacements.contains(url.as_str())
+            })
+            .map(|(url, _)| url.clone());
+
+        if let Some(url) = &replacement {
+            replacements.insert(url.clone());
+        }
+
+        report.changes.push(SiteChange {
+            detected_at,
+            kind: if replacement.is_some() { ChangeKind::Changed } else { ChangeKind::Removed },
+            page_number: metadata.page_number,
+            filename: metadata.filename.clone(),
+            description: metadata.description.clone(),
+            url: metadata.url.clone(),
+            current_url: replacement,
+        });
+    }
+
+    report.new_items = current_urls
+        .iter()
+        .filter(|url| !stored_urls.contains(*url) && !replacements.contains(**url))
+        .count();
+
+    for (page, images) in current {
+        let count = stored.iter().filter(|m| m.page_number == *page).count();
+        if images.len() < count {
+            report.dropped_pages.push(PageDrop {
+                page: *page,
+                stored: count,
+                current: images.len(),
+            });
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(page: u32, name: &str, url: &str) -> ImageMetadata {
+        ImageMetadata {
+            description: name.to_string(),
+            url: url.to_string(),
+            page_number: page,
+            ..ImageMetadata::for_test(&format!("{}.jpg", name), "")
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let stored = vec![
+            metadata(1, "a", "https://x.com/a.jpg"),
+            metadata(1, "b", "https://x.com/b.jpg"),
+            metadata(1, "c", "https://x.com/c.jpg"),
+            metadata(2, "d", "https://x.com/d.jpg"),
+            metadata(3, "e", "https://x.com/e.jpg"),
+        ];
+
+        // a 被刪、c 換圖、d 因為刪圖移到第 1 頁、第 3 頁沒有重爬
+        let mut current = BTreeMap::new();
+        current.insert(1, vec![
+            ("https://x.com/b.jpg".to_string(), "b".to_string()),
+            ("https://x.com/c2.jpg".to_string(), "c".to_string()),
+            ("https://x.com/d.jpg".to_string(), "d".to_string()),
+        ]);
+        current.insert(2, vec![("https://x.com/f.jpg".to_string(), "f".to_string())]);
+
+        let report = compare(&stored, &current, Utc::now());
+
+        assert_eq!(report.unchanged, 2);
+        assert_eq!(report.new_items, 1);
+        assert_eq!(report.changes.len(), 2);
+        assert_eq!(report.changes[0].kind, ChangeKind::Removed);
+        assert_eq!(report.changes[0].description, "a");
+        assert_eq!(report.changes[1].kind, ChangeKind::Changed);
+        assert_eq!(report.changes[1].current_url.as_deref(), Some("https://x.com/c2.jpg"));
+        assert!(report.dropped_pages.is_empty());
+
+        current.get_mut(&1).unwrap().pop();
+        let report = compare(&stored, &current, Utc::now());
+        assert_eq!(report.dropped_pages, [PageDrop { page: 1, stored: 3, current: 2 }]);
+    }
+}
@@ -0,0 +1,129 @@
+use super::downloader::{DownloadOutcome, ImageDownloader, ItemDetails};
+use anyhow::Result;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, Semaphore};
+
+/// 一張待下載的圖片
+#[derive(Debug, Clone)]
+pub struct ImageJob {
+    pub url: String,
+    pub name: String,
+    pub page: u32,
+    pub details: ItemDetails,
+}
+
+impl ImageJob {
+    pub fn new(url: String, name: String, page: u32) -> Self {
+        Self {
+            url,
+            name,
+            page,
+            details: ItemDetails::default(),
+        }
+    }
+
+    /// 附帶標籤與網站特有欄位
+    pub fn with_details(mut self, details: ItemDetails) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+type Pending = (ImageJob, oneshot::Sender<Result<DownloadOutcome>>);
+
+/// 解析與下載之間的有界佇列
+///
+/// 頁面解析出的圖片先排入佇列，由背景分派器依 `max_in_flight` 限制同時下載的數量；
+/// 佇列滿時 `submit` 會等待，單頁圖片很多時不會一次全部送出，記憶體與頻寬也比較平穩。
+#[derive(Clone)]
+pub struct DownloadQueue {
+    sender: mpsc::Sender<Pending>,
+}
+
+impl DownloadQueue {
+    /// 以圖片下載器處理佇列
+    pub fn new(downloader: ImageDownloader, max_in_flight: usize) -> Self {
+        Self::spawn_with(max_in_flight, move |job: ImageJob| {
+            let downloader = downloader.clone();
+            async move { downloader.download_and_save_with(&job.url, &job.name, job.page, job.details).await }
+        })
+    }
+
+    /// 以自訂的處理函式建立佇列（佇列容量與同時下載數相同）
+    pub fn spawn_with<F, Fut>(max_in_flight: usize, handler: F) -> Self
+    where
+        F: Fn(ImageJob) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<DownloadOutcome>> + Send + 'static,
+    {
+        let max_in_flight = max_in_flight.max(1);
+        let (sender, mut receiver) = mpsc::channel::<Pending>(max_in_flight);
+
+        tokio::spawn(async move {
+            let semaphore = Arc::new(Semaphore::new(max_in_flight));
+
+            // 所有 sender 釋放後，處理完剩下的項目就結束
+            while let Some((job, done)) = receiver.recv().await {
+                let permit = Arc::clone(&semaphore).acquire_owned().await.unwrap();
+                let download = handler(job);
+
+                tokio::spawn(async move {
+                    let _ = done.send(download.await);
+                    drop(permit);
+                });
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// 排入一張圖片（佇列滿時等待），回傳可等待下載結果的 receiver
+    pub async fn submit(&self, job: ImageJob) -> Result<oneshot::Receiver<Result<DownloadOutcome>>> {
+        let (done, result) = oneshot::channel();
+        self.sender
+            .send((job, done))
+            .await
+            .map_err(|_| anyhow::anyhow!("下載佇列已關閉"))?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_in_flight_limit() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let queue = {
+            let in_flight = Arc::clone(&in_flight);
+            let peak = Arc::clone(&peak);
+            DownloadQueue::spawn_with(3, move |_job| {
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak);
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(DownloadOutcome::Saved)
+                }
+            })
+        };
+
+        let mut results = vec![];
+        for i in 0..20 {
+            let job = ImageJob::new(format!("https://example.com/{}.png", i), i.to_string(), 1);
+            results.push(queue.submit(job).await.unwrap());
+        }
+
+        for result in results {
+            assert_eq!(result.await.unwrap().unwrap(), DownloadOutcome::Saved);
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+}
@@ -1,20 +1,420 @@
-use crate::types::ImageMetadata;
+use crate::types::{FailedDownload, ImageMetadata};
 use crate::file_manager::FileManager;
-use anyhow::Result;
-use sha2::{Sha256, Digest};
+use crate::store::MetadataStore;
+use super::naming::{FilenameFields, FilenameTemplate, TitleNumbers};
+use super::types::{CrawlerConfig, DownloadedImage, MediaFilter, SizeFilter};
+use anyhow::{Context, Result};
+use crate::client_pool::ConnectionStats;
+use crate::fetcher;
+use crate::headers::HeaderRotator;
+use crate::integrity;
+use crate::media;
+use crate::normalize::{self, NormalizeOptions};
+use crate::nsfw::{self, NsfwAction, NsfwFilter, Quarantine};
+use crate::rate_limit::{self, HostTokenBucket};
 use chrono::Utc;
-use tokio::sync::Mutex;
-use std::sync::Arc;
+use reqwest::{Client, StatusCode};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, Mutex};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// 預設的下載逾時（連線與等待下一個 chunk 的上限）
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 預設的下載重試次數
+const DEFAULT_RETRIES: u32 = 2;
+
+/// 預設的第一次重試等待時間（之後每次加倍）
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// 保留開頭的 bytes 判斷格式（magic bytes）
+const HEAD_LEN: usize = 64;
+
+/// 暫存檔名的流水號（同一個程序內不重複）
+static PART_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 使用中的續傳暫存檔（同一網址同時只有一個下載寫入）
+static ACTIVE_PARTS: LazyLock<std::sync::Mutex<HashSet<String>>> = LazyLock::new(Default::default);
+
+/// 下載中的暫存檔，沒有改名為正式檔案就在離開時刪除
+///
+/// 伺服器支援續傳（Accept-Ranges: bytes）時，中斷的暫存檔與續傳資訊（`<暫存檔>.json`）
+/// 保留給下次以 Range 請求接續。
+struct PartFile {
+    path: String,
+    persisted: bool,
+    /// 中斷時保留給下次接續
+    keep: bool,
+    /// 依網址命名的暫存檔（可續傳）
+    resumable: bool,
+}
+
+/// 續傳資訊：暫存檔屬於哪個網址，以及接續時確認內容沒變的驗證值
+#[derive(Debug, Serialize, Deserialize)]
+struct ResumeState {
+    url: String,
+    /// ETag 或 Last-Modified（以 If-Range 送出，內容改變時伺服器回傳完整檔案）
+    validator: Option<String>,
+}
+
+impl PartFile {
+    fn resume_path(&self) -> String {
+        format!("{}.json", self.path)
+    }
+    
+    /// 上次中斷留下的同一網址暫存檔（沒有或不屬於這個網址時為 None）
+    fn load_resume(&self, url: &str) -> Option<ResumeState> {
+        if !self.resumable || std::fs::metadata(&self.path).map_or(true, |m| m.len() == 0) {
+            return None;
+        }
+        let state: ResumeState = serde_json::from_str(&std::fs::read_to_string(self.resume_path()).ok()?).ok()?;
+        (state.url == url).then_some(state)
+    }
+    
+    fn save_resume(&self, state: Option<&ResumeState>) -> Result<()> {
+        match state {
+            Some(state) if self.resumable => {
+                std::fs::write(self.resume_path(), serde_json::to_string(state)?).context("無法寫入續傳資訊")
+            }
+            _ => {
+                let _ = std::fs::remove_file(self.resume_path());
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Drop for PartFile {
+    fn drop(&mut self) {
+        if !self.persisted && !self.keep {
+            let _ = std::fs::remove_file(&self.path);
+        }
+        if !self.keep {
+            let _ = std::fs::remove_file(self.resume_path());
+        }
+        if self.resumable {
+            ACTIVE_PARTS.lock().unwrap().remove(&self.path);
+        }
+    }
+}
+
+/// 下載到暫存檔的結果
+enum Transfer {
+    Complete {
+        content_type: Option<String>,
+        hash: String,
+        /// 開頭的 bytes（判斷格式）
+        head: Vec<u8>,
+        file_size: u64,
+    },
+    /// 未通過大小過濾
+    Skipped(String),
+}
+
+/// 這次執行下載過的內容 hash（hash -> 儲存的檔名），超過容量時忘記最早的
+#[derive(Debug)]
+pub struct SeenHashes {
+    capacity: usize,
+    inner: std::sync::Mutex<(HashMap<String, String>, VecDeque<String>)>,
+}
+
+impl SeenHashes {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: std::sync::Mutex::new((HashMap::new(), VecDeque::new())),
+        }
+    }
+    
+    /// 已儲存過相同內容時回傳儲存的檔名
+    pub fn get(&self, hash: &str) -> Option<String> {
+        self.inner.lock().unwrap().0.get(hash).cloned()
+    }
+    
+    pub fn insert(&self, hash: &str, filename: &str) {
+        let (files, order) = &mut *self.inner.lock().unwrap();
+        if files.insert(hash.to_string(), filename.to_string()).is_some() {
+            return;
+        }
+        order.push_back(hash.to_string());
+        while order.len() > self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                files.remove(&oldest);
+            }
+        }
+    }
+}
 
 /// 圖片下載器
 #[derive(Clone)]  // 直接 derive Clone
 pub struct ImageDownloader {
     file_manager: Arc<Mutex<FileManager>>,
+    store: Arc<dyn MetadataStore>,
+    /// 下載完成通知的訂閱者
+    subscribers: Arc<std::sync::Mutex<Vec<mpsc::Sender<DownloadedImage>>>>,
+    /// 尺寸/大小過濾
+    size_filter: SizeFilter,
+    media_filter: MediaFilter,
+    /// 檔名樣板
+    filename_template: FilenameTemplate,
+    /// 寫入 metadata 的來源網站
+    source_site: String,
+    /// 所有下載共用的 client（連線重複使用）
+    client: Client,
+    /// 等待連線或下一個 chunk 的上限（伺服器停止傳送時放棄，不佔住 worker）
+    timeout: Duration,
+    max_retries: u32,
+    /// 第一次重試前的等待（之後每次加倍）
+    backoff: Duration,
+    /// 重試後仍失敗的圖片寫入 failed_downloads.jsonl
+    record_failures: bool,
+    /// 每個 host 的請求速率上限（與頁面請求共用）
+    token_bucket: Option<Arc<HostTokenBucket>>,
+    /// User-Agent 輪替與各網站的 headers（Referer 等）
+    headers: Option<Arc<HeaderRotator>>,
+    /// 同一次執行中內容相同的圖片只存一份（None 表示不檢查）
+    seen_hashes: Option<Arc<SeenHashes>>,
+    /// 檔名樣板有 `{title_n}` 時的同名編號（第一次用到時從 metadata 載入）
+    title_numbers: Arc<OnceLock<std::sync::Mutex<TitleNumbers>>>,
+    /// 下載後的 NSFW 偵測（None 表示不偵測）
+    nsfw: Option<NsfwFilter>,
+    /// 下載後的 EXIF 移除與格式轉換（None 表示保留原檔）
+    normalize: Option<NormalizeOptions>,
+}
+
+/// 來源網站對單一項目提供的額外資訊（寫入 metadata 的 tags/extra/license）
+#[derive(Debug, Clone, Default)]
+pub struct ItemDetails {
+    pub tags: Vec<String>,
+    pub extra: serde_json::Map<String, serde_json::Value>,
+    pub license: Option<String>,
+}
+
+/// `retry_failed` 的結果
+#[derive(Debug, Default)]
+pub struct RetryReport {
+    pub saved: usize,
+    /// 未通過過濾條件
+    pub skipped: usize,
+    /// 仍然失敗（留在佇列）
+    pub failed: usize,
+}
+
+/// 單張圖片的下載結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadOutcome {
+    /// 已儲存
+    Saved,
+    /// 未通過過濾條件而略過（附原因）
+    Skipped(String),
+    /// 這次執行已下載過相同內容，只記錄參照（附已儲存的檔名）
+    Duplicate(String),
 }
 
 impl ImageDownloader {
-    pub fn new(file_manager: Arc<Mutex<FileManager>>) -> Self {
-        Self { file_manager }
+    pub fn new(file_manager: Arc<Mutex<FileManager>>, store: Arc<dyn MetadataStore>) -> Self {
+        Self {
+            file_manager,
+            store,
+            subscribers: Arc::new(std::sync::Mutex::new(Vec::new())),
+            size_filter: SizeFilter::default(),
+            media_filter: MediaFilter::default(),
+            filename_template: FilenameTemplate::default(),
+            source_site: String::new(),
+            client: fetcher::download_client(DEFAULT_TIMEOUT).unwrap_or_default(),
+            timeout: DEFAULT_TIMEOUT,
+            max_retries: DEFAULT_RETRIES,
+            backoff: DEFAULT_BACKOFF,
+            record_failures: true,
+            token_bucket: None,
+            headers: None,
+            seen_hashes: None,
+            title_numbers: Arc::new(OnceLock::new()),
+            nsfw: None,
+            normalize: None,
+        }
+    }
+    
+    /// 依爬蟲設定建立（過濾條件、檔名樣板、下載逾時與重試）
+    pub fn from_config(
+        file_manager: Arc<Mutex<FileManager>>,
+        store: Arc<dyn MetadataStore>,
+        config: &CrawlerConfig,
+    ) -> Result<Self> {
+        Self::new(file_manager, store)
+            .with_size_filter(config.size_filter)
+            .with_media_filter(config.media_filter)
+            .with_filename_template(config.filename_template.clone())
+            .with_download(Duration::from_secs(config.download_timeout_secs), config.download_retries)
+            .map(|downloader| {
+                downloader
+                    .with_backoff(Duration::from_millis(config.download_backoff_ms))
+                    .with_token_bucket(config.token_bucket.clone())
+                    .with_headers(config.headers.clone())
+                    .with_dedup_window(config.dedup_window)
+                    .with_nsfw(config.nsfw.clone())
+                    .with_normalize(config.normalize)
+            })
+    }
+    
+    /// 設定下載逾時與重試次數（建立專用的 client）
+    pub fn with_download(mut self, timeout: Duration, max_retries: u32) -> Result<Self> {
+        self.client = fetcher::download_client(timeout)?;
+        self.timeout = timeout;
+        self.max_retries = max_retries;
+        Ok(self)
+    }
+    
+    /// 第一次重試前的等待時間（之後每次加倍）
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+    
+    /// 每個 host 的請求速率上限（None 表示不限制）
+    pub fn with_token_bucket(mut self, bucket: Option<Arc<HostTokenBucket>>) -> Self {
+        self.token_bucket = bucket;
+        self
+    }
+    
+    /// 輪替 User-Agent 並加上各網站的 headers
+    pub fn with_headers(mut self, headers: Option<Arc<HeaderRotator>>) -> Self {
+        self.headers = headers;
+        self
+    }
+    
+    /// 記住最近 `window` 個下載的內容 hash，重複的圖片不再儲存（0 表示不檢查）
+    pub fn with_dedup_window(mut self, window: usize) -> Self {
+        self.seen_hashes = (window > 0).then(|| Arc::new(SeenHashes::new(window)));
+        self
+    }
+    
+    /// 下載後偵測 NSFW，依設定標記 metadata 或移到隔離目錄
+    pub fn with_nsfw(mut self, nsfw: Option<NsfwFilter>) -> Self {
+        self.nsfw = nsfw;
+        self
+    }
+    
+    /// 下載後移除 EXIF 或統一格式（在去重與命名之前，hash 依轉換後的內容計算）
+    pub fn with_normalize(mut self, normalize: Option<NormalizeOptions>) -> Self {
+        self.normalize = normalize;
+        self
+    }
+    
+    /// 設定尺寸/大小過濾
+    pub fn with_size_filter(mut self, size_filter: SizeFilter) -> Self {
+        self.size_filter = size_filter;
+        self
+    }
+    
+    /// 設定是否下載動圖與影片
+    pub fn with_media_filter(mut self, media_filter: MediaFilter) -> Self {
+        self.media_filter = media_filter;
+        self
+    }
+    
+    /// 設定檔名樣板
+    pub fn with_filename_template(mut self, filename_template: FilenameTemplate) -> Self {
+        self.filename_template = filename_template;
+        self
+    }
+    
+    /// 設定來源網站（網域）
+    pub fn with_source_site(mut self, source_site: impl Into<String>) -> Self {
+        self.source_site = source_site.into();
+        self
+    }
+    
+    /// 訂閱下載完成的圖片（通道滿時下載會等待消費者，形成背壓）
+    pub fn subscribe(&self, buffer: usize) -> mpsc::Receiver<DownloadedImage> {
+        let (tx, rx) = mpsc::channel(buffer);
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+    
+    /// 移除所有訂閱者（訂閱端的串流會因此結束）
+    pub fn close_subscribers(&self) {
+        self.subscribers.lock().unwrap().clear();
+    }
+    
+    /// 通知訂閱者（已關閉的訂閱端自動移除）
+    async fn notify(&self, image: DownloadedImage) {
+        let senders = self.subscribers.lock().unwrap().clone();
+        if senders.is_empty() {
+            return;
+        }
+        
+        for sender in &senders {
+            let _ = sender.send(image.clone()).await;
+        }
+        
+        self.subscribers.lock().unwrap().retain(|s| !s.is_closed());
+    }
+    
+    /// 送出下載請求，失敗時以指數退避重試；`range` 為 (起始位置, If-Range 驗證值)，從中間接續下載
+    ///
+    /// 4xx（429 除外）不重試；接續時伺服器回 416 也會回傳回應，由呼叫端改為從頭下載。
+    async fn request(&self, url: &str, range: Option<(u64, Option<&str>)>) -> Result<reqwest::Response> {
+        let mut last_error = None;
+        
+        for attempt in 0..=self.max_retries {
+            if attempt > 0 {
+                tokio::time::sleep(self.backoff * 2u32.pow(attempt - 1)).await;
+            }
+            
+            if let Some(bucket) = &self.token_bucket {
+                bucket.acquire(&rate_limit::host_of(url)).await;
+            }
+            
+            let mut builder = self.client.get(url);
+            if let Some((offset, validator)) = range {
+                builder = builder.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+                if let Some(validator) = validator {
+                    builder = builder.header(reqwest::header::IF_RANGE, validator);
+                }
+            }
+            let mut request = builder.build().context("無法建立請求")?;
+            if let Some(headers) = &self.headers {
+                headers.apply(&mut request);
+            }
+            
+            // 只限制等待回應標頭的時間，內容在串流時逐 chunk 限制
+            ConnectionStats::global().record_request();
+            let Ok(sent) = tokio::time::timeout(self.timeout, self.client.execute(request)).await else {
+                last_error = Some(anyhow::anyhow!("等待回應逾時（{} 秒）", self.timeout.as_secs()));
+                continue;
+            };
+            match sent {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) if range.is_some() && response.status() == StatusCode::RANGE_NOT_SATISFIABLE => {
+                    return Ok(response);
+                }
+                Ok(response) if response.status().is_client_error()
+                    && response.status() != StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    anyhow::bail!("HTTP 錯誤: {}", response.status());
+                }
+                Ok(response) => last_error = Some(anyhow::anyhow!("HTTP 錯誤: {}", response.status())),
+                Err(e) => last_error = Some(anyhow::anyhow!("請求失敗: {}", e)),
+            }
+        }
+        
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("未知錯誤")))
+    }
+    
+    /// `{title_n}` 的編號（第一次用到時從 metadata 載入已使用的編號）
+    fn title_number(&self, title: &str, hash: &str) -> Result<u32> {
+        if self.title_numbers.get().is_none() {
+            let numbers = TitleNumbers::from_metadata(&self.store.load_all_metadata()?);
+            let _ = self.title_numbers.set(std::sync::Mutex::new(numbers));
+        }
+        Ok(self.title_numbers.get().unwrap().lock().unwrap().assign(title, hash))
     }
     
     /// 下載並儲存單張圖片
@@ -23,52 +423,821 @@ impl ImageDownloader {
         url: &str,
         name: &str,
         page: u32,
-    ) -> Result<()> {
-        // 下載圖片
-        let response = reqwest::get(url).await?;
-        let bytes = response.bytes().await?;
-        
-        // 計算 hash
-        let mut hasher = Sha256::new();
-        hasher.update(&bytes);
-        let hash = format!("{:x}", hasher.finalize());
-        
-        // 生成檔名
-        let ext = url.rsplit('.').next().unwrap_or("jpg");
-        let filename = format!("{}_{}.{}", 
-            &hash[..8], 
-            sanitize_filename(name), 
-            ext
-        );
+    ) -> Result<DownloadOutcome> {
+        self.download_and_save_with(url, name, page, ItemDetails::default()).await
+    }
+    
+    /// 下載並儲存單張圖片，附帶來源網站提供的標籤與欄位
+    ///
+    /// 重試後仍失敗時記錄到 failed_downloads.jsonl，之後以 `retry_failed` 再試。
+    pub async fn download_and_save_with(
+        &self,
+        url: &str,
+        name: &str,
+        page: u32,
+        details: ItemDetails,
+    ) -> Result<DownloadOutcome> {
+        let failure_details = self.record_failures.then(|| details.clone());
+        let result = self.download(url, name, page, details).await;
+        
+        if let (Err(e), Some(details)) = (&result, failure_details) {
+            let failed = FailedDownload {
+                url: url.to_string(),
+                name: name.to_string(),
+                page,
+                source_site: self.source_site.clone(),
+                tags: details.tags,
+                extra: details.extra,
+                license: details.license,
+                error: e.to_string(),
+                failed_at: Utc::now(),
+            };
+            if let Err(log_error) = self.file_manager.lock().await.append_failed_download(&failed) {
+                eout!("⚠️  無法記錄下載失敗: {}", log_error);
+            }
+        }
+        
+        result
+    }
+    
+    /// 重新下載 failed_downloads.jsonl 中的圖片
+    ///
+    /// 成功或被過濾的項目從佇列移除（每完成一張就更新佇列，中斷也不會重複下載），
+    /// 失敗的留在佇列並更新錯誤訊息。
+    pub async fn retry_failed(&self) -> Result<RetryReport> {
+        let mut queue = self.file_manager.lock().await.load_failed_downloads()?;
+        let mut report = RetryReport::default();
+        let mut i = 0;
+        
+        while i < queue.len() {
+            let item = queue[i].clone();
+            // 佇列由這裡維護，失敗時不再另外記錄
+            let downloader = Self {
+                source_site: item.source_site.clone(),
+                record_failures: false,
+                ..self.clone()
+            };
+            let details = ItemDetails {
+                tags: item.tags.clone(),
+                extra: item.extra.clone(),
+                license: item.license.clone(),
+            };
+            
+            match downloader.download(&item.url, &item.name, item.page, details).await {
+                Ok(outcome) => {
+                    match outcome {
+                        DownloadOutcome::Saved => report.saved += 1,
+                        DownloadOutcome::Skipped(_) | DownloadOutcome::Duplicate(_) => report.skipped += 1,
+                    }
+                    queue.remove(i);
+                    self.file_manager.lock().await.rewrite_failed_downloads(&queue)?;
+                }
+                Err(e) => {
+                    eout!("  ❌ {}: {}", item.url, e);
+                    queue[i].error = e.to_string();
+                    queue[i].failed_at = Utc::now();
+                    report.failed += 1;
+                    i += 1;
+                }
+            }
+        }
+        
+        if report.failed > 0 {
+            self.file_manager.lock().await.rewrite_failed_downloads(&queue)?;
+        }
+        Ok(report)
+    }
+    
+    /// 建立暫存檔：依網址命名以便中斷後接續；同一網址正在下載時改用不續傳的暫存檔
+    async fn open_part(&self, url: &str) -> PartFile {
+        let fm = self.file_manager.lock().await;
+        let path = fm.part_path(&format!("download-{}", &integrity::sha256_hex(url.as_bytes())[..16]));
+        if ACTIVE_PARTS.lock().unwrap().insert(path.clone()) {
+            return PartFile { path, persisted: false, keep: false, resumable: true };
+        }
+        
+        PartFile {
+            path: fm.part_path(&format!(
+                "download-{}-{}",
+                std::process::id(),
+                PART_COUNTER.fetch_add(1, Ordering::Relaxed)
+            )),
+            persisted: false,
+            keep: false,
+            resumable: false,
+        }
+    }
+    
+    /// 下載到暫存檔，邊寫入邊計算 hash（記憶體只保留目前的 chunk）
+    ///
+    /// 有上次中斷的暫存檔時以 Range 請求接續；傳輸中斷時也在這次執行中重試（最多 `max_retries` 次），
+    /// 伺服器不支援 Range 時從頭重新下載。伺服器有提供大小（Content-Length 或 Content-Range 的總長）時，
+    /// 收到的大小不符視為中斷。
+    async fn transfer(&self, url: &str, part: &mut PartFile) -> Result<Transfer> {
+        let mut resume = part.load_resume(url);
+        let mut interruptions = 0;
+        
+        loop {
+            let offset = match &resume {
+                Some(_) => tokio::fs::metadata(&part.path).await.map(|m| m.len()).unwrap_or(0),
+                None => 0,
+            };
+            let range = resume
+                .as_ref()
+                .filter(|_| offset > 0)
+                .map(|state| (offset, state.validator.as_deref()));
+            let mut response = match self.request(url, range).await {
+                Ok(response) => response,
+                Err(e) => {
+                    part.keep = resume.is_some() && offset > 0;
+                    return Err(e);
+                }
+            };
+            
+            // 暫存檔比伺服器上的檔案還大（檔案已改變），從頭下載
+            if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+                resume = None;
+                continue;
+            }
+            
+            // 206 才是接續；200 表示伺服器忽略 Range 或內容已改變（If-Range），從頭下載
+            let resumed = range.is_some() && response.status() == StatusCode::PARTIAL_CONTENT;
+            let expected_len = if resumed {
+                content_range_total(&response)
+            } else {
+                response.content_length()
+            };
+            
+            // 伺服器有提供大小時，過大的檔案不必下載
+            if let Some(reason) = expected_len.and_then(|len| self.size_filter.check_bytes(len)) {
+                return Ok(Transfer::Skipped(reason));
+            }
+            
+            let header = |name: reqwest::header::HeaderName| {
+                response.headers().get(name).and_then(|v| v.to_str().ok()).map(|s| s.to_string())
+            };
+            let content_type = header(reqwest::header::CONTENT_TYPE);
+            let accepts_ranges = resumed || header(reqwest::header::ACCEPT_RANGES).is_some_and(|v| v.eq_ignore_ascii_case("bytes"));
+            resume = accepts_ranges.then(|| ResumeState {
+                url: url.to_string(),
+                validator: header(reqwest::header::ETAG).or_else(|| header(reqwest::header::LAST_MODIFIED)),
+            });
+            part.save_resume(resume.as_ref())?;
+            
+            let mut hasher = Sha256::new();
+            let mut head = Vec::with_capacity(HEAD_LEN);
+            let mut file_size: u64 = 0;
+            let file = if resumed {
+                // 已下載的部分先讀回來計算 hash 與檔頭
+                file_size = hash_file(&part.path, &mut hasher, &mut head).await?;
+                tokio::fs::OpenOptions::new().append(true).open(&part.path).await
+            } else {
+                tokio::fs::File::create(&part.path).await
+            }
+            .context("無法建立下載暫存檔")?;
+            let mut writer = tokio::io::BufWriter::new(file);
+            
+            let mut interrupted = None;
+            loop {
+                let chunk = match tokio::time::timeout(self.timeout, response.chunk()).await {
+                    Ok(Ok(Some(chunk))) => chunk,
+                    Ok(Ok(None)) => break,
+                    Ok(Err(e)) => {
+                        interrupted = Some(anyhow::anyhow!("下載中斷: {}", e));
+                        break;
+                    }
+                    Err(_) => {
+                        interrupted = Some(anyhow::anyhow!("下載停滯（{} 秒沒有收到資料）", self.timeout.as_secs()));
+                        break;
+                    }
+                };
+                
+                // 沒有 Content-Length 或不實時，超過上限立即中止
+                file_size += chunk.len() as u64;
+                if let Some(reason) = self.size_filter.check_bytes(file_size) {
+                    return Ok(Transfer::Skipped(reason));
+                }
+                
+                if head.len() < HEAD_LEN {
+                    let take = (HEAD_LEN - head.len()).min(chunk.len());
+                    head.extend_from_slice(&chunk[..take]);
+                }
+                hasher.update(&chunk);
+                writer.write_all(&chunk).await.context("無法寫入下載暫存檔")?;
+            }
+            writer.flush().await.context("無法寫入下載暫存檔")?;
+            drop(writer);
+            
+            if interrupted.is_none() && expected_len.is_some_and(|len| len != file_size) {
+                interrupted = Some(anyhow::anyhow!("下載不完整（{} / {} bytes）", file_size, expected_len.unwrap_or_default()));
+            }
+            if let Some(e) = interrupted {
+                if interruptions >= self.max_retries {
+                    part.keep = resume.is_some() && file_size > 0;
+                    return Err(e);
+                }
+                interruptions += 1;
+                // 不支援 Range 時下一輪 offset 為 0，暫存檔重新建立、hash 重新計算
+                match resume {
+                    Some(_) => eout!("  ⏯️  {}（{}），從 {} bytes 接續", url, e, file_size),
+                    None => eout!("  🔁 {}（{}），伺服器不支援續傳，從頭重新下載", url, e),
+                }
+                tokio::time::sleep(self.backoff * 2u32.pow(interruptions - 1)).await;
+                continue;
+            }
+            
+            let hash = format!("{:x}", hasher.finalize());
+            return Ok(Transfer::Complete { content_type, hash, head, file_size });
+        }
+    }
+    
+    async fn download(
+        &self,
+        url: &str,
+        name: &str,
+        page: u32,
+        details: ItemDetails,
+    ) -> Result<DownloadOutcome> {
+        // 下載到暫存檔（伺服器支援時接續上次中斷的部分）
+        let mut part = self.open_part(url).await;
+        let (content_type, mut hash, mut head, mut file_size) = match self.transfer(url, &mut part).await? {
+            Transfer::Complete { content_type, hash, head, file_size } => (content_type, hash, head, file_size),
+            Transfer::Skipped(reason) => return Ok(DownloadOutcome::Skipped(reason)),
+        };
+        
+        // 移除 EXIF / 統一格式（在 blocking 執行緒執行；失敗只警告，保留原檔）
+        if let Some(options) = self.normalize {
+            let path = part.path.clone();
+            let result = tokio::task::spawn_blocking(move || -> Result<Option<Vec<u8>>> {
+                let Some(bytes) = normalize::normalize(&std::fs::read(&path)?, &options)? else {
+                    return Ok(None);
+                };
+                std::fs::write(&path, &bytes)?;
+                Ok(Some(bytes))
+            })
+            .await?;
+            match result {
+                Ok(Some(bytes)) => {
+                    hash = integrity::sha256_hex(&bytes);
+                    head = bytes[..bytes.len().min(HEAD_LEN)].to_vec();
+                    file_size = bytes.len() as u64;
+                }
+                Ok(None) => {}
+                Err(e) => eout!("⚠️  轉換失敗（{}）: {}", url, e),
+            }
+        }
+        
+        // 動圖要數影格，在 blocking 執行緒讀檔
+        let path = part.path.clone();
+        let media_type = tokio::task::spawn_blocking(move || media::probe_file_media_type(&path)).await?;
+        if let Some(reason) = media_type.and_then(|media_type| self.media_filter.check(media_type)) {
+            return Ok(DownloadOutcome::Skipped(reason));
+        }
+        
+        // 尺寸只讀檔頭
+        let dimensions = media::probe_file_dimensions(&part.path);
+        if let Some(reason) = self.size_filter.check_dimensions(dimensions) {
+            return Ok(DownloadOutcome::Skipped(reason));
+        }
+        
+        // 生成檔名（副檔名依實際內容判斷，不信任 URL）
+        let ext = detect_extension(content_type.as_deref(), &head, url);
+        let downloaded_at = Utc::now();
+        let title_number = match self.filename_template.numbers_titles() {
+            true => Some(self.title_number(name, &hash)?),
+            false => None,
+        };
+        let filename = self.filename_template.render(&FilenameFields {
+            hash: &hash,
+            title: name,
+            title_number: title_number.unwrap_or(1),
+            ext,
+            page,
+            downloaded_at,
+            url,
+        });
         
         // 建立 metadata
-        let metadata = ImageMetadata {
+        let mut metadata = ImageMetadata {
             filename: filename.clone(),
             description: name.to_string(),
             url: url.to_string(),
             content_hash: hash,
             page_number: page,
-            downloaded_at: Utc::now(),
+            downloaded_at,
+            tags: details.tags,
+            source_site: self.source_site.clone(),
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+            file_size: Some(file_size),
+            keywords: Vec::new(),
+            suggested_title: None,
+            license: details.license,
+            title_number,
+            ocr_text: None,
+            media_type,
+            extra: details.extra,
         };
         
-        // 儲存
-        let fm = self.file_manager.lock().await;
-        fm.save_image(&filename, &bytes)?;
-        fm.append_metadata(&metadata)?;
+        // NSFW 偵測（外部模型，在 blocking 執行緒執行；偵測失敗只警告，照常儲存）
+        if let Some(filter) = &self.nsfw {
+            let (detector, path) = (filter.clone(), PathBuf::from(&part.path));
+            match tokio::task::spawn_blocking(move || detector.score(&path)).await? {
+                Ok(score) => {
+                    nsfw::tag(&mut metadata, score, filter.threshold);
+                    if filter.is_flagged(score) && filter.action == NsfwAction::Quarantine {
+                        let fm = self.file_manager.lock().await;
+                        Quarantine::new(fm.root_dir()).move_in(Path::new(&part.path), &metadata)?;
+                        part.persisted = true;
+                        return Ok(DownloadOutcome::Skipped(format!("NSFW 分數 {:.2}，已隔離", score)));
+                    }
+                }
+                Err(e) => eout!("⚠️  NSFW 偵測失敗（{}）: {}", filename, e),
+            }
+        }
+        
+        // 儲存（持有 file_manager 鎖，確保圖片與 metadata 依序寫入）
+        let path = {
+            let fm = self.file_manager.lock().await;
+            
+            // 這次執行已存過相同內容：不再存檔，metadata 指向已儲存的檔案
+            if let Some(seen) = &self.seen_hashes {
+                if let Some(existing) = seen.get(&metadata.content_hash) {
+                    let mut reference = metadata;
+                    reference.filename = existing.clone();
+                    reference.extra.insert("duplicate_of".to_string(), serde_json::Value::String(existing.clone()));
+                    self.store.append_metadata(&reference)?;
+                    return Ok(DownloadOutcome::Duplicate(existing));
+                }
+            }
+            
+            fm.persist_image(&part.path, &filename)?;
+            part.persisted = true;
+            if let Some(seen) = &self.seen_hashes {
+                seen.insert(&metadata.content_hash, &filename);
+            }
+            self.store.append_metadata(&metadata)?;
+            PathBuf::from(fm.get_image_path(&filename))
+        };
+        
+        self.notify(DownloadedImage { metadata, path }).await;
         
-        Ok(())
+        Ok(DownloadOutcome::Saved)
+    }
+}
+
+/// 206 回應的完整檔案大小（`Content-Range: bytes 100-199/200`）
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .parse()
+        .ok()
+}
+
+/// 讀取檔案計算 hash，回傳檔案大小（`head` 收集開頭的 bytes）
+async fn hash_file(path: &str, hasher: &mut Sha256, head: &mut Vec<u8>) -> Result<u64> {
+    use tokio::io::AsyncReadExt;
+    
+    let mut file = tokio::fs::File::open(path).await.context("無法讀取下載暫存檔")?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let read = file.read(&mut buffer).await.context("無法讀取下載暫存檔")?;
+        if read == 0 {
+            return Ok(size);
+        }
+        if head.len() < HEAD_LEN {
+            let take = (HEAD_LEN - head.len()).min(read);
+            head.extend_from_slice(&buffer[..take]);
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+}
+
+/// 判斷圖片副檔名：magic bytes > Content-Type > URL 副檔名 > "jpg"
+pub fn detect_extension(content_type: Option<&str>, bytes: &[u8], url: &str) -> &'static str {
+    sniff_extension(bytes)
+        .or_else(|| content_type.and_then(extension_from_content_type))
+        .or_else(|| extension_from_url(url))
+        .unwrap_or("jpg")
+}
+
+/// 從檔案開頭的 magic bytes 判斷副檔名（見 `media::detect_format`）
+pub fn sniff_extension(bytes: &[u8]) -> Option<&'static str> {
+    media::detect_format(bytes).extension()
+}
+
+/// 從 Content-Type 判斷副檔名（忽略 `; charset=...` 等參數）
+fn extension_from_content_type(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_lowercase();
+    
+    match mime.as_str() {
+        "image/jpeg" | "image/jpg" | "image/pjpeg" => Some("jpg"),
+        "image/png" => Some("png"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "image/avif" => Some("avif"),
+        "image/heic" | "image/heif" => Some("heic"),
+        "image/bmp" => Some("bmp"),
+        "image/svg+xml" => Some("svg"),
+        "image/x-icon" | "image/vnd.microsoft.icon" => Some("ico"),
+        "video/mp4" => Some("mp4"),
+        "video/webm" => Some("webm"),
+        _ => None,
     }
 }
 
-/// 清理檔名
-fn sanitize_filename(name: &str) -> String {
-    name.chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
-            c => c,
-        })
-        .collect::<String>()
-        .chars()
-        .take(50)
-        .collect()
-}
\ No newline at end of file
+/// 從 URL 路徑判斷副檔名（去掉 query string 與 fragment，只接受已知格式）
+fn extension_from_url(url: &str) -> Option<&'static str> {
+    let path = url.split(['?', '#']).next()?;
+    let last_segment = path.rsplit('/').next()?;
+    let (_, ext) = last_segment.rsplit_once('.')?;
+    
+    match ext.to_lowercase().as_str() {
+        "jpg" | "jpeg" => Some("jpg"),
+        "png" => Some("png"),
+        "gif" => Some("gif"),
+        "webp" => Some("webp"),
+        "avif" => Some("avif"),
+        "heic" | "heif" => Some("heic"),
+        "bmp" => Some("bmp"),
+        "svg" => Some("svg"),
+        "ico" => Some("ico"),
+        "mp4" => Some("mp4"),
+        "webm" => Some("webm"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::MediaType;
+
+    #[test]
+    fn test_detect_extension() {
+        let png = b"\x89PNG\r\n\x1a\n....";
+        let webp = b"RIFF\x00\x00\x00\x00WEBPVP8 ";
+        
+        // magic bytes 優先於錯誤的 Content-Type 與 URL
+        assert_eq!(detect_extension(Some("image/jpeg"), png, "https://a.com/x.jpg"), "png");
+        assert_eq!(detect_extension(None, webp, "https://a.com/x"), "webp");
+        assert_eq!(detect_extension(Some("image/gif"), b"\x00\x00\x00\x18ftypmp42", "https://a.com/x.gif"), "mp4");
+        assert_eq!(detect_extension(Some("video/webm"), b"", "https://a.com/x"), "webm");
+        
+        // 無法從內容判斷時使用 Content-Type
+        assert_eq!(detect_extension(Some("image/gif; charset=binary"), b"", "https://a.com/x"), "gif");
+        
+        // 再退回 URL（忽略 query string）
+        assert_eq!(detect_extension(None, b"", "https://a.com/x.JPEG?w=600"), "jpg");
+        assert_eq!(detect_extension(None, b"", "https://a.com/img?id=1.5"), "jpg");
+    }
+
+    #[test]
+    fn test_size_filter() {
+        let mut png = Vec::new();
+        image::RgbImage::new(120, 80)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        
+        assert_eq!(SizeFilter::default().check(&png), None);
+        
+        let thumbnails = SizeFilter { min_width: Some(200), ..Default::default() };
+        assert_eq!(thumbnails.check(&png), Some("尺寸過小 (120x80)".to_string()));
+        
+        let wide_enough = SizeFilter { min_width: Some(100), min_height: Some(80), ..Default::default() };
+        assert_eq!(wide_enough.check(&png), None);
+        
+        let tiny_limit = SizeFilter { max_bytes: Some(10), ..Default::default() };
+        assert!(tiny_limit.check(&png).unwrap().starts_with("檔案過大"));
+        
+        // 無法解析尺寸的內容不做尺寸過濾
+        assert_eq!(thumbnails.check(b"<svg></svg>"), None);
+        
+        // 以 metadata 記錄的尺寸與大小過濾，沒有記錄時不過濾
+        let mut metadata: crate::types::ImageMetadata = serde_json::from_value(serde_json::json!({
+            "filename": "a.png", "description": "", "url": "", "content_hash": "h",
+            "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z",
+        })).unwrap();
+        assert_eq!(thumbnails.check_metadata(&metadata), None);
+        metadata.width = Some(120);
+        metadata.height = Some(80);
+        metadata.file_size = Some(png.len() as u64);
+        assert_eq!(thumbnails.check_metadata(&metadata), Some("尺寸過小 (120x80)".to_string()));
+        assert!(tiny_limit.check_metadata(&metadata).unwrap().starts_with("檔案過大"));
+        assert_eq!(wide_enough.check_metadata(&metadata), None);
+        
+        let no_animated = MediaFilter { animated: false, video: true };
+        assert!(no_animated.check(MediaType::Animated).is_some());
+        assert_eq!(no_animated.check(MediaType::Video), None);
+        assert_eq!(MediaFilter::default().check(MediaType::Animated), None);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_download() {
+        use crate::store::MetadataBackend;
+        use tokio::io::AsyncReadExt;
+        use tokio::net::TcpListener;
+
+        let mut png = Vec::new();
+        image::RgbImage::new(120, 80)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        // 不送 Content-Length，只能在串流中途判斷大小
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let body = png.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                if request.starts_with(b"GET /missing") {
+                    let _ = socket.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await;
+                    continue;
+                }
+                let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n").await;
+                for chunk in body.chunks(50) {
+                    let _ = socket.write_all(chunk).await;
+                }
+            }
+        });
+
+        let dir = std::env::temp_dir().join(format!("meme-download-{}", std::process::id()));
+        let data_dir = dir.to_str().unwrap();
+        let file_manager = FileManager::new(data_dir).unwrap();
+        let store = crate::store::open_store(data_dir, MetadataBackend::Jsonl).unwrap();
+        let downloader = ImageDownloader::new(Arc::new(Mutex::new(file_manager)), store.clone());
+        let url = format!("http://{}/a", addr);
+
+        // 404 不重試，也不會把錯誤頁存成圖片
+        let started = std::time::Instant::now();
+        assert!(downloader.download_and_save(&format!("http://{}/missing", addr), "a", 1).await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let limited = downloader.clone().with_size_filter(SizeFilter { max_bytes: Some(100), ..Default::default() });
+        let outcome = limited.download_and_save(&url, "a", 1).await.unwrap();
+        assert!(matches!(outcome, DownloadOutcome::Skipped(reason) if reason.starts_with("檔案過大")));
+
+        assert_eq!(downloader.download_and_save(&url, "a", 1).await.unwrap(), DownloadOutcome::Saved);
+        let metadata = store.load_all_metadata().unwrap();
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].content_hash, crate::integrity::sha256_hex(&png));
+        assert_eq!(metadata[0].file_size, Some(png.len() as u64));
+        assert_eq!((metadata[0].width, metadata[0].height), (Some(120), Some(80)));
+        assert_eq!(metadata[0].media_type, Some(MediaType::Image));
+        assert!(metadata[0].filename.ends_with(".png"));
+
+        // 略過與完成的下載都不留下暫存檔
+        let leftovers: Vec<_> = std::fs::read_dir(dir.join("images"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(leftovers, vec![metadata[0].filename.clone()]);
+
+        // 404 記錄在 failed_downloads.jsonl；重試時仍失敗的留在佇列，成功的移除
+        let fm = FileManager::new(data_dir).unwrap();
+        let mut failed = fm.load_failed_downloads().unwrap();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].error.contains("404"));
+        failed.push(FailedDownload { url: url.clone(), ..failed[0].clone() });
+        fm.rewrite_failed_downloads(&failed).unwrap();
+
+        let report = downloader.retry_failed().await.unwrap();
+        assert_eq!((report.saved, report.skipped, report.failed), (1, 0, 1));
+        let remaining = fm.load_failed_downloads().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining[0].url.ends_with("/missing"));
+
+        // NSFW 隔離：圖片移到 quarantine/，不進 images/ 與 metadata
+        struct Flagged;
+        impl nsfw::NsfwDetector for Flagged {
+            fn score(&self, _path: &Path) -> Result<f64> {
+                Ok(0.9)
+            }
+        }
+        let filter = NsfwFilter::new(Arc::new(Flagged), nsfw::DEFAULT_THRESHOLD, NsfwAction::Quarantine);
+        let outcome = downloader.clone().with_nsfw(Some(filter)).download_and_save(&url, "b", 1).await.unwrap();
+        assert!(matches!(outcome, DownloadOutcome::Skipped(reason) if reason.contains("隔離")));
+        assert_eq!(store.load_all_metadata().unwrap().len(), 2);
+        let quarantined = Quarantine::new(data_dir).load().unwrap();
+        assert!(nsfw::is_flagged(&quarantined[0]));
+        assert!(dir.join(nsfw::QUARANTINE_DIR).join(&quarantined[0].filename).exists());
+
+        // 轉成 JPEG：副檔名、hash 與大小依轉換後的內容
+        let options = NormalizeOptions { format: Some(normalize::OutputFormat::Jpeg), ..Default::default() };
+        let outcome = downloader.clone().with_normalize(Some(options)).download_and_save(&url, "c", 1).await.unwrap();
+        assert_eq!(outcome, DownloadOutcome::Saved);
+        let converted = store.load_all_metadata().unwrap().pop().unwrap();
+        assert!(converted.filename.ends_with(".jpg"));
+        let saved = std::fs::read(dir.join("images").join(&converted.filename)).unwrap();
+        assert_eq!(converted.content_hash, crate::integrity::sha256_hex(&saved));
+        assert_eq!(converted.file_size, Some(saved.len() as u64));
+        assert_eq!((converted.width, converted.height), (Some(120), Some(80)));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_resume_download() {
+        use crate::store::MetadataBackend;
+        use tokio::io::AsyncReadExt;
+        use tokio::net::TcpListener;
+
+        let body: Vec<u8> = (0..4000u32).map(|i| (i % 251) as u8).collect();
+        let len = body.len();
+
+        // 每個路徑第一次只送一半就斷線；Range 請求（If-Range 相符）回 206
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let ranges = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&ranges);
+        let served = body.clone();
+        tokio::spawn(async move {
+            let mut interrupted = HashSet::new();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0u8; 2048];
+                let n = socket.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..n]).to_lowercase();
+                let path = request.split_whitespace().nth(1).unwrap_or_default().to_string();
+                let range = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("range: bytes="))
+                    .and_then(|v| v.trim().trim_end_matches('-').parse::<usize>().ok());
+
+                if let Some(start) = range.filter(|_| request.contains("if-range: \"v1\"")) {
+                    seen.lock().unwrap().push(start);
+                    let header = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nConnection: close\r\n\r\n",
+                        start, len - 1, len, len - start
+                    );
+                    let _ = socket.write_all(header.as_bytes()).await;
+                    let _ = socket.write_all(&served[start..]).await;
+                    continue;
+                }
+                // /plain 不支援 Range
+                let header = if path.starts_with("/plain") {
+                    format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", len)
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n",
+                        len
+                    )
+                };
+                let _ = socket.write_all(header.as_bytes()).await;
+                if interrupted.insert(path) {
+                    let _ = socket.write_all(&served[..len / 2]).await;
+                } else {
+                    let _ = socket.write_all(&served).await;
+                }
+            }
+        });
+
+        let dir = std::env::temp_dir().join(format!("meme-resume-{}", std::process::id()));
+        let data_dir = dir.to_str().unwrap();
+        let store = crate::store::open_store(data_dir, MetadataBackend::Jsonl).unwrap();
+        let downloader = ImageDownloader::new(Arc::new(Mutex::new(FileManager::new(data_dir).unwrap())), store.clone())
+            .with_download(Duration::from_secs(5), 0)
+            .unwrap()
+            .with_backoff(Duration::from_millis(10));
+        let leftovers = || {
+            std::fs::read_dir(dir.join("images"))
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+                .filter(|name| name.starts_with('.'))
+                .count()
+        };
+
+        // 不重試：中斷的暫存檔與續傳資訊留下，下次從中斷處接續
+        let url = format!("http://{}/a", addr);
+        assert!(downloader.download_and_save(&url, "a", 1).await.is_err());
+        assert_eq!(leftovers(), 2);
+        assert_eq!(downloader.download_and_save(&url, "a", 1).await.unwrap(), DownloadOutcome::Saved);
+        assert_eq!(*ranges.lock().unwrap(), vec![len / 2]);
+        let metadata = store.load_all_metadata().unwrap();
+        assert_eq!(metadata[0].content_hash, crate::integrity::sha256_hex(&body));
+        assert_eq!(metadata[0].file_size, Some(len as u64));
+        assert_eq!(leftovers(), 0);
+
+        // 可重試時在同一次下載中接續
+        let retrying = downloader.clone().with_download(Duration::from_secs(5), 1).unwrap();
+        assert_eq!(retrying.download_and_save(&format!("http://{}/b", addr), "b", 1).await.unwrap(), DownloadOutcome::Saved);
+        assert_eq!(ranges.lock().unwrap().len(), 2);
+        assert_eq!(store.load_all_metadata().unwrap()[1].content_hash, crate::integrity::sha256_hex(&body));
+
+        // 不支援 Range：不留續傳資訊，重試時從頭重新下載
+        let url = format!("http://{}/plain", addr);
+        assert!(downloader.download_and_save(&url, "c", 1).await.is_err());
+        assert_eq!(leftovers(), 0);
+        assert_eq!(retrying.download_and_save(&format!("http://{}/plain2", addr), "c", 1).await.unwrap(), DownloadOutcome::Saved);
+        assert_eq!(ranges.lock().unwrap().len(), 2);
+        let metadata = store.load_all_metadata().unwrap();
+        assert_eq!(metadata[2].content_hash, crate::integrity::sha256_hex(&body));
+        assert_eq!(metadata[2].file_size, Some(len as u64));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_in_crawl_dedup() {
+        use crate::store::MetadataBackend;
+        use tokio::io::AsyncReadExt;
+        use tokio::net::TcpListener;
+
+        // 兩個網址回傳相同內容，第三個不同
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let body: &[u8] = if request.starts_with(b"GET /c") { b"GIF89a-other" } else { b"GIF89a-same" };
+                let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+                let _ = socket.write_all(header.as_bytes()).await;
+                let _ = socket.write_all(body).await;
+            }
+        });
+
+        let dir = std::env::temp_dir().join(format!("meme-seen-{}", std::process::id()));
+        let data_dir = dir.to_str().unwrap();
+        let store = crate::store::open_store(data_dir, MetadataBackend::Jsonl).unwrap();
+        let downloader = ImageDownloader::new(Arc::new(Mutex::new(FileManager::new(data_dir).unwrap())), store.clone())
+            .with_filename_template(FilenameTemplate::parse("{title}.{ext}").unwrap())
+            .with_dedup_window(10);
+
+        let get = |path: &str, name: &str| {
+            let downloader = downloader.clone();
+            let url = format!("http://{}/{}", addr, path);
+            let name = name.to_string();
+            async move { downloader.download_and_save(&url, &name, 1).await.unwrap() }
+        };
+        assert_eq!(get("a", "first").await, DownloadOutcome::Saved);
+        assert_eq!(get("b", "second").await, DownloadOutcome::Duplicate("first.gif".to_string()));
+        assert_eq!(get("c", "third").await, DownloadOutcome::Saved);
+
+        // 重複的項目只記錄參照，不另存檔案
+        let metadata = store.load_all_metadata().unwrap();
+        assert_eq!(metadata.len(), 3);
+        assert_eq!(metadata[1].filename, "first.gif");
+        assert_eq!(metadata[1].description, "second");
+        assert_eq!(metadata[1].extra["duplicate_of"], "first.gif");
+        assert!(!dir.join("images/second.gif").exists());
+
+        // 超過容量時忘記最早的 hash
+        let seen = SeenHashes::new(1);
+        seen.insert("h1", "a.jpg");
+        seen.insert("h2", "b.jpg");
+        assert_eq!((seen.get("h1"), seen.get("h2").as_deref()), (None, Some("b.jpg")));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_windows_safe_filenames() {
+        use crate::store::MetadataBackend;
+        use tokio::io::AsyncReadExt;
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut served = 0u8;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                served += 1;
+                let body = format!("GIF89a-{}", served);
+                let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+                let _ = socket.write_all(header.as_bytes()).await;
+                let _ = socket.write_all(body.as_bytes()).await;
+            }
+        });
+
+        // 資料目錄以分隔字元結尾（Windows 的 `C:\data\` 也一樣處理）
+        let dir = std::env::temp_dir().join(format!("meme-winname-{}", std::process::id()));
+        let data_dir = format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR);
+        let store = crate::store::open_store(&data_dir, MetadataBackend::Jsonl).unwrap();
+        let downloader = ImageDownloader::new(Arc::new(Mutex::new(FileManager::new(&data_dir).unwrap())), store.clone())
+            .with_filename_template(FilenameTemplate::parse("{title}.{ext}").unwrap());
+
+        for (path, title) in [("a", "CON"), ("b", "a\\b:c?"), ("c", "nul ")] {
+            let url = format!("http://{}/{}", addr, path);
+            assert_eq!(downloader.download_and_save(&url, title, 1).await.unwrap(), DownloadOutcome::Saved);
+        }
+
+        // 保留的裝置名稱加上前綴，Windows 不允許的字元換成 `_`
+        let filenames: Vec<String> = store.load_all_metadata().unwrap().into_iter().map(|m| m.filename).collect();
+        assert_eq!(filenames, vec!["_CON.gif", "a_b_c_.gif", "_nul .gif"]);
+        for filename in &filenames {
+            assert!(dir.join("images").join(filename).exists());
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,67 +1,244 @@
-use crate::types::Progress;
+use crate::types::{DatasetManifest, Progress, RunReport, WarmupResult};
+use crate::client_pool::ConnectionStats;
 use crate::file_manager::FileManager;
-use crate::fetcher::{Fetcher, HttpFetcher};
-use crate::parser::PageParser;
-use super::{types::CrawlerConfig, downloader::ImageDownloader};
+use crate::fetcher::{self, Fetcher, HttpFetcher};
+use crate::parser::{PageHint, PageParser};
+use crate::shutdown::ShutdownSignal;
+use crate::status::StatusHandle;
+use crate::terminal;
+use crate::store;
+use crate::rate_limit::{self, AdaptiveRateLimiter};
+use super::{types::{CrawlerConfig, DownloadedImage}, breaker::CircuitBreaker, detail::DetailResolver, downloader::{DownloadOutcome, ImageDownloader, ItemDetails, RetryReport}, download_queue::{DownloadQueue, ImageJob}, parse_pool::ParsePool, determinism::{self, Determinism}, diff::{self, DiffReport}, watch::{RefreshReport, WatchState}};
 use anyhow::{Context, Result};
+use chrono::Utc;
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use tokio::sync::{Semaphore, Mutex};
+use tokio::sync::{mpsc, Semaphore, Mutex};
+use tokio_stream::{Stream, StreamExt, wrappers::ReceiverStream};
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
 
+/// 增量爬取時連續失敗幾頁就停止（網路中斷或網站異常）
+const MAX_CONSECUTIVE_PAGE_FAILURES: u32 = 3;
+
 /// 主爬蟲引擎
 pub struct CrawlerEngine {
     file_manager: Arc<Mutex<FileManager>>,
     fetcher: Arc<HttpFetcher>,
-    parser: Arc<dyn PageParser>,
+    parser: ParsePool,
+    /// 詳細頁的抓取與快取（detail_pages.jsonl）
+    details: DetailResolver,
     downloader: ImageDownloader,
+    /// 頁面請求的自適應限流（rate_limits.json）
+    rate_limiter: Arc<AdaptiveRateLimiter>,
     base_url: String,
-    total_pages: u32,
+    /// 總頁數（None 表示每次執行前自動偵測）
+    total_pages: Option<u32>,
     config: CrawlerConfig,
+    /// 執行中的狀態（`status()` 取得的 handle 共用）
+    status: StatusHandle,
 }
 
 impl CrawlerEngine {
     pub fn new(
         data_dir: &str,
         base_url: String,
-        total_pages: u32,
+        total_pages: Option<u32>,
         parser: Arc<dyn PageParser>,
         config: CrawlerConfig,
     ) -> Result<Self> {
+        if config.determinism.enabled && !config.filename_template.is_stable() {
+            anyhow::bail!(
+                "可重現模式不能使用含 {{date}} 的檔名樣板: {}",
+                config.filename_template.pattern()
+            );
+        }
+        
         let file_manager = Arc::new(Mutex::new(FileManager::new(data_dir)?));
-        let fetcher = Arc::new(HttpFetcher::new(config.timeout_secs, config.max_retries)?);
-        let downloader = ImageDownloader::new(Arc::clone(&file_manager));
+        let rate_limiter = Arc::new(AdaptiveRateLimiter::load(data_dir)?);
+        let fetcher = Arc::new(
+            HttpFetcher::new(config.timeout_secs, config.max_retries)?
+                .with_robots(config.respect_robots)
+                .with_token_bucket(config.token_bucket.clone())
+                .with_headers(config.headers.clone())
+                .with_proxies(&config.proxy)?
+                .with_rate_limiter(
+                    Arc::clone(&rate_limiter),
+                    request_delay_ms(config.batch_delay_ms, config.concurrency),
+                )
+        );
+        let metadata_store = store::open_store(data_dir, config.metadata_backend)?;
+        let downloader = ImageDownloader::from_config(Arc::clone(&file_manager), metadata_store, &config)?
+            .with_source_site(rate_limit::host_of(&base_url));
         
         Ok(Self {
             file_manager,
             fetcher,
-            parser,
+            parser: ParsePool::new(parser, config.parse_workers),
+            details: DetailResolver::load(data_dir, config.detail_concurrency)?,
             downloader,
+            rate_limiter,
             base_url,
             total_pages,
             config,
+            status: StatusHandle::new(),
         })
     }
     
-    pub async fn run(&self) -> Result<()> {
-        println!("載入進度...");
+    /// 是否已爬完所有頁面（依 progress.json；未指定總頁數時看下一頁是否還有項目）
+    pub async fn is_complete(&self) -> Result<bool> {
         let progress = self.file_manager.lock().await.load_progress()?;
+        match self.total_pages {
+            Some(total_pages) => Ok(progress.last_completed_page >= total_pages),
+            None => Ok(self.probe_page(progress.last_completed_page + 1).await?.is_none()),
+        }
+    }
+    
+    /// 探測單頁：有項目時回傳分頁資訊，空列表或頁面不存在（404/410）時為 None
+    ///
+    /// 其他錯誤（逾時、伺服器錯誤、解析失敗）在 fetcher 重試後仍失敗就回傳錯誤，
+    /// 不當作最後一頁，以免一次失敗就截斷爬取範圍。
+    async fn probe_page(&self, page: u32) -> Result<Option<PageHint>> {
+        let html = match self.fetcher.fetch_page(&page_url(&self.base_url, page)).await {
+            Ok(html) => html,
+            Err(e) if fetcher::is_not_found(&e) => return Ok(None),
+            Err(e) => return Err(e.context(format!("無法探測第 {} 頁", page))),
+        };
+        let images = self.parser.parse_page(html.clone()).await?;
+        if images.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.parser.page_hint(html).await.unwrap_or_default()))
+    }
+    
+    /// 未指定總頁數時，找出最後一個有項目的頁面
+    ///
+    /// 先依頁面上的分頁資訊（沒有下一頁、最大頁碼）判斷；沒有分頁資訊時以倍增探測到
+    /// 沒有項目的頁面（超過最後一頁通常是空列表或 404），再以二分搜尋找出最後一頁。
+    async fn discover_total_pages(&self, start_page: u32) -> Result<u32> {
+        // 已完成的頁面視為存在
+        let mut low = start_page.saturating_sub(1);
+        let mut probe = start_page.max(1);
+        
+        let mut high = loop {
+            let Some(hint) = self.probe_page(probe).await? else {
+                break probe;
+            };
+            low = probe;
+            
+            if hint.has_next == Some(false) || hint.last_page.is_some_and(|last| last <= probe) {
+                return Ok(probe);
+            }
+            let next = match hint.last_page {
+                Some(last) => last,
+                None => probe.saturating_mul(2),
+            };
+            if next == probe {
+                return Ok(probe);
+            }
+            probe = next;
+        };
+        
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            match self.probe_page(mid).await? {
+                Some(_) => low = mid,
+                None => high = mid,
+            }
+        }
+        
+        Ok(low)
+    }
+    
+    /// 重新下載之前失敗的圖片（failed_downloads.jsonl）
+    pub async fn retry_failed_downloads(&self) -> Result<RetryReport> {
+        self.downloader.retry_failed().await
+    }
+    
+    /// 執行狀態的 handle，執行期間可從其他 task 查詢 `snapshot()`
+    pub fn status(&self) -> StatusHandle {
+        self.status.clone()
+    }
+    
+    /// 訂閱下載完成的圖片（引擎釋放後通道關閉）
+    pub fn subscribe(&self, buffer: usize) -> mpsc::Receiver<DownloadedImage> {
+        self.downloader.subscribe(buffer)
+    }
+    
+    /// 在背景執行爬取，並以串流回傳下載完成的圖片
+    ///
+    /// 爬取結束時串流結束；爬取失敗時最後一個項目為 `Err`。
+    /// 消費者處理太慢時下載會等待（最多緩衝 64 張）。
+    pub fn stream(self: Arc<Self>) -> impl Stream<Item = Result<DownloadedImage>> {
+        let images = self.downloader.subscribe(64);
+        let (error_tx, error_rx) = mpsc::channel(1);
+        
+        tokio::spawn(async move {
+            let result = self.run().await;
+            self.downloader.close_subscribers();
+            
+            if let Err(e) = result {
+                let _ = error_tx.send(Err(e)).await;
+            }
+        });
+        
+        ReceiverStream::new(images)
+            .map(Ok)
+            .chain(ReceiverStream::new(error_rx))
+    }
+    
+    pub async fn run(&self) -> Result<()> {
+        let started_at = Utc::now();
+        let connections_before = ConnectionStats::global().snapshot();
+        let shutdown = ShutdownSignal::install();
+        
+        out!("載入進度...");
+        let mut progress = self.file_manager.lock().await.load_progress()?;
+        
+        // 進度檔只記錄頁碼，不同網站共用同一個資料目錄會錯亂
+        let site = rate_limit::host_of(&self.base_url);
+        if let Some(other) = progress.site.as_ref().filter(|other| **other != site) {
+            anyhow::bail!("資料目錄的進度屬於 {}，請用 --profile 分開不同網站", other);
+        }
+        progress.site = Some(site);
         
         let start_page = progress.last_completed_page + 1;
-        println!("從第 {} 頁開始爬取", start_page);
-        println!("並發數: {}", self.config.concurrency);
-        println!("總頁數: {}\n", self.total_pages);
+        let images_before = progress.total_images_downloaded;
+        let total_pages = match self.total_pages {
+            Some(total_pages) => total_pages,
+            None => {
+                out!("偵測總頁數...");
+                self.discover_total_pages(start_page).await?
+            }
+        };
+        out!("從第 {} 頁開始爬取", start_page);
+        out!("並發數: {}", self.config.concurrency);
+        out!("解析執行緒: {}", self.config.parse_workers);
+        if self.config.determinism.enabled {
+            out!("可重現模式: 頁面依序提交、圖片逐張下載 (seed {})", self.config.determinism.seed);
+        } else {
+            out!("同時下載圖片: {}", self.config.max_in_flight_images);
+        }
+        if let Some(rate) = self.config.determinism.sample_rate {
+            out!("抽樣比例: {:.1}% (seed {})", rate * 100.0, self.config.determinism.seed);
+        }
+        if self.total_pages.is_some() {
+            out!("總頁數: {}\n", total_pages);
+        } else {
+            out!("總頁數: {}（自動偵測，--pages 可指定）\n", total_pages);
+        }
+        let _running = self.status.start("crawl", Some(total_pages.saturating_sub(start_page - 1) as u64));
         
         // 建立進度條
         let multi_progress = MultiProgress::new();
         
-        let main_pb = multi_progress.add(ProgressBar::new(self.total_pages as u64));
+        let main_pb = multi_progress.add(ProgressBar::new(total_pages as u64));
         main_pb.set_style(
             ProgressStyle::default_bar()
                 .template("{msg}\n[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} 頁 ({percent}%) {eta}")
                 .unwrap()
                 .progress_chars("=>-")
         );
-        main_pb.set_message("📄 頁面進度");
+        main_pb.set_message(terminal::message("📄 頁面進度"));
         main_pb.set_position(progress.last_completed_page as u64);
         
         let image_pb = multi_progress.add(ProgressBar::new(0));
@@ -70,7 +247,7 @@ impl CrawlerEngine {
                 .template("{msg} {pos} 張")
                 .unwrap()
         );
-        image_pb.set_message("🖼️  已下載圖片:");
+        image_pb.set_message(terminal::message("🖼️  已下載圖片:"));
         image_pb.set_position(progress.total_images_downloaded as u64);
         
         let status_pb = multi_progress.add(ProgressBar::new(0));
@@ -80,24 +257,77 @@ impl CrawlerEngine {
                 .unwrap()
         );
         
-        // 並發控制
-        let semaphore = Arc::new(Semaphore::new(self.config.concurrency));
         let progress_mutex = Arc::new(Mutex::new(progress));
+        let mut counts = PageCounts::default();
+        
+        // 所有頁面共用的下載佇列，限制同時下載的圖片數（可重現模式逐張下載，metadata 順序固定）
+        let determinism = self.config.determinism;
+        let max_in_flight = if determinism.enabled { 1 } else { self.config.max_in_flight_images };
+        let queue = DownloadQueue::new(self.downloader.clone(), max_in_flight);
+        
+        // 暖身階段：慢速爬取前幾頁，依錯誤率決定之後的並發數與延遲
+        let mut first_batch_page = start_page;
+        let mut concurrency = self.config.concurrency;
+        let mut batch_delay_ms = self.config.batch_delay_ms;
+        let mut warmup = None;
+        
+        if self.config.warmup_pages > 0 && start_page <= total_pages {
+            let result = self.warm_up(
+                start_page,
+                total_pages,
+                &queue,
+                &shutdown,
+                &progress_mutex,
+                &mut counts,
+                &main_pb,
+                &image_pb,
+                &status_pb,
+            ).await?;
+            
+            first_batch_page = start_page + result.pages;
+            concurrency = result.chosen_concurrency;
+            batch_delay_ms = result.chosen_batch_delay_ms;
+            warmup = Some(result);
+            
+            // 暖身量測的結果取代先前學到的間隔
+            self.rate_limiter.set_delay(
+                &rate_limit::host_of(&self.base_url),
+                request_delay_ms(batch_delay_ms, concurrency),
+            );
+        }
+        
+        // 並發控制
+        let semaphore = Arc::new(Semaphore::new(concurrency));
+        let mut breaker = self.config.circuit_breaker.map(CircuitBreaker::new);
         
         // 分批處理
-        for batch_start in (start_page..=self.total_pages).step_by(self.config.concurrency) {
-            let batch_end = (batch_start + self.config.concurrency as u32 - 1)
-                .min(self.total_pages);
+        for batch_start in (first_batch_page..=total_pages).step_by(concurrency) {
+            // 收到中斷訊號就不再派發新批次（進度在每批結束時已儲存）
+            if shutdown.is_triggered() || !self.wait_for_window(&shutdown, &status_pb).await {
+                break;
+            }
             
-            status_pb.set_message(format!("⚡ 正在處理: 第 {} - {} 頁", batch_start, batch_end));
+            // 失敗率過高時先暫停，試抓成功才派發這一批
+            if let Some(breaker) = breaker.as_mut().filter(|b| b.is_open())
+                && !self.wait_for_recovery(breaker, batch_start, &shutdown, &status_pb).await
+            {
+                break;
+            }
+            
+            let batch_end = (batch_start + concurrency as u32 - 1)
+                .min(total_pages);
+            
+            status_pb.set_message(terminal::message(format!("⚡ 正在處理: 第 {} - {} 頁", batch_start, batch_end)));
+            self.status.set_page_range(batch_start, batch_end);
             
             let mut tasks = vec![];
             
             for page in batch_start..=batch_end {
                 let semaphore = Arc::clone(&semaphore);
                 let fetcher = Arc::clone(&self.fetcher);
-                let parser = Arc::clone(&self.parser);
-                let downloader = self.downloader.clone();
+                let parser = self.parser.clone();
+                let details = self.details.clone();
+                let queue = queue.clone();
                 let base_url = self.base_url.clone();
                 let main_pb = main_pb.clone();
                 let image_pb = image_pb.clone();
@@ -106,19 +336,27 @@ impl CrawlerEngine {
                 let task = tokio::spawn(async move {
                     let _permit = semaphore.acquire().await.unwrap();
                     
-                    status_pb.set_message(format!("🔄 爬取第 {} 頁...", page));
+                    status_pb.set_message(terminal::message(format!("🔄 爬取第 {} 頁...", page)));
                     
-                    let url = format!("{}?page={}", base_url, page);
-                    let result = Self::process_page_static(
+                    let url = page_url(&base_url, page);
+                    let images = Self::fetch_page_images(
                         page,
                         &url,
                         &fetcher,
                         &parser,
-                        &downloader,
+                        &details,
+                        &determinism,
                         &status_pb,
-                        &image_pb,
                     ).await;
                     
+                    // 可重現模式：下載交給批次迴圈依頁序進行
+                    let result = match images {
+                        Ok(images) if determinism.enabled => Ok(PageResult::Pending(images)),
+                        Ok(images) => Self::download_page_images(page, images, &queue, &parser, &image_pb).await
+                            .map(PageResult::Downloaded),
+                        Err(e) => Err(e),
+                    };
+                    
                     main_pb.inc(1);
                     (page, result)
                 });
@@ -126,78 +364,529 @@ impl CrawlerEngine {
                 tasks.push(task);
             }
             
-            // 等待批次完成
+            // 等待批次完成（依頁序）
             for task in tasks {
                 let (page, result) = task.await.unwrap();
-                
-                let mut progress = progress_mutex.lock().await;
-                
-                match result {
-                    Ok(count) => {
-                        progress.update(page, count);
-                        status_pb.set_message(format!("✅ 第 {} 頁完成 ({} 張圖片)", page, count));
+                let result = match result {
+                    Ok(PageResult::Downloaded(count)) => Ok(count),
+                    Ok(PageResult::Pending(images)) => Self::download_page_images(page, images, &queue, &self.parser, &image_pb).await,
+                    Err(e) => Err(e),
+                };
+                let success = self.record_page(&progress_mutex, &mut counts, page, result, &status_pb).await;
+                if let Some(breaker) = breaker.as_mut()
+                    && breaker.record(success)
+                {
+                    eout!(
+                        "⛔ 最近 {} 頁失敗率 {:.0}%，暫停派發新頁面",
+                        breaker.config().window,
+                        breaker.failure_rate() * 100.0,
+                    );
+                }
+            }
+            
+            // 儲存進度
+            {
+                let progress = progress_mutex.lock().await;
+                self.file_manager.lock().await.save_progress(&progress)?;
+            }
+        }
+        
+        let interrupted = shutdown.is_triggered();
+        if interrupted {
+            main_pb.abandon_with_message(terminal::message("⏸️  已中斷，進度已儲存"));
+        } else {
+            main_pb.finish_with_message(terminal::message("✨ 所有頁面爬取完成！"));
+        }
+        image_pb.finish();
+        status_pb.finish_and_clear();
+        
+        // 保存學到的請求間隔，下次直接沿用
+        self.rate_limiter.save()?;
+        
+        // 寫入執行報告
+        let report = RunReport {
+            started_at,
+            finished_at: Utc::now(),
+            start_page,
+            total_pages,
+            pages_processed: counts.processed,
+            pages_failed: counts.failed,
+            images_downloaded: progress_mutex.lock().await.total_images_downloaded - images_before,
+            concurrency,
+            batch_delay_ms,
+            request_delay_ms: self.rate_limiter.delay_ms(&rate_limit::host_of(&self.base_url)),
+            warmup,
+            interrupted,
+            circuit_breaks: breaker.as_ref().map_or(0, |b| b.trips()),
+            connections: Some(ConnectionStats::global().snapshot().since(&connections_before)),
+        };
+        self.file_manager.lock().await.save_run_report(&report)?;
+        self.save_manifest(total_pages).await?;
+        
+        // 顯示統計
+        self.print_statistics(&progress_mutex, &report).await;
+        
+        Ok(())
+    }
+    
+    /// 重爬已完成的頁面（不下載），與本地 metadata 比對
+    ///
+    /// 消失或換了網址的項目寫入 site_changes.jsonl。`last_page` 為 None 時重爬到
+    /// progress.json 記錄的最後一頁；只重爬部分頁面時，移到範圍外的項目會被當成 removed。
+    pub async fn diff_crawl(&self, first_page: u32, last_page: Option<u32>) -> Result<DiffReport> {
+        let shutdown = ShutdownSignal::install();
+        let progress = self.file_manager.lock().await.load_progress()?;
+        
+        let site = rate_limit::host_of(&self.base_url);
+        if let Some(other) = progress.site.as_ref().filter(|other| **other != site) {
+            anyhow::bail!("資料目錄的進度屬於 {}，請用 --profile 分開不同網站", other);
+        }
+        
+        let last_page = last_page
+            .unwrap_or(progress.last_completed_page)
+            .min(progress.last_completed_page);
+        if first_page > last_page {
+            anyhow::bail!("沒有已爬取的頁面可以比對（已完成到第 {} 頁）", progress.last_completed_page);
+        }
+        
+        out!("重爬第 {} - {} 頁並比對 metadata\n", first_page, last_page);
+        
+        let pb = ProgressBar::new((last_page - first_page + 1) as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} 頁 {msg}")
+                .unwrap()
+                .progress_chars("=>-")
+        );
+        let status_pb = ProgressBar::hidden();
+        
+        // 抓不到的頁面不列入比對，避免整頁被當成 removed
+        let mut current = BTreeMap::new();
+        let mut failed = 0;
+        for page in first_page..=last_page {
+            if shutdown.is_triggered() {
+                break;
+            }
+            
+            let url = page_url(&self.base_url, page);
+            match Self::fetch_page_images(
+                page,
+                &url,
+                &self.fetcher,
+                &self.parser,
+                &self.details,
+                &self.config.determinism,
+                &status_pb,
+            ).await {
+                Ok(images) => {
+                    current.insert(page, images);
+                }
+                Err(e) => {
+                    pb.println(terminal::message(format!("❌ 第 {} 頁失敗: {}", page, e)));
+                    failed += 1;
+                }
+            }
+            pb.inc(1);
+        }
+        
+        if shutdown.is_triggered() {
+            pb.abandon_with_message(terminal::message("⏸️  已中斷，只比對已重爬的頁面"));
+        } else {
+            pb.finish_with_message(terminal::message(format!("完成（{} 頁失敗）", failed)));
+        }
+        self.rate_limiter.save()?;
+        
+        // 其他來源（reddit、kym、網址清單）的項目另有自己的頁碼，不列入比對
+        let fm = self.file_manager.lock().await;
+        let stored: Vec<_> = store::open_store(fm.root_dir(), self.config.metadata_backend)?
+            .load_all_metadata()?
+            .into_iter()
+            .filter(|m| !m.extra.contains_key("source") && (m.source_site.is_empty() || m.source_site == site))
+            .collect();
+        
+        let report = diff::compare(&stored, &current, Utc::now());
+        if !report.changes.is_empty() {
+            fm.append_site_changes(&report.changes)?;
+        }
+        
+        Ok(report)
+    }
+    
+    /// 增量爬取：從第 1 頁往後，只下載沒看過的圖片網址，遇到整頁都看過就停止
+    ///
+    /// 看過的網址是 metadata 中的網址加上 watch_state.json 記錄的網址（含未通過過濾的），
+    /// 不更新 progress.json，所以不影響一般爬取的進度。
+    pub async fn refresh(&self) -> Result<RefreshReport> {
+        let shutdown = ShutdownSignal::install();
+        let data_dir = self.file_manager.lock().await.root_dir().to_string();
+        
+        let mut state = WatchState::load(&data_dir)?;
+        state.seen.extend(
+            store::open_store(&data_dir, self.config.metadata_backend)?
+                .load_all_metadata()?
+                .into_iter()
+                .map(|m| m.url)
+        );
+        
+        let mut report = RefreshReport::default();
+        let queue = DownloadQueue::new(self.downloader.clone(), self.config.max_in_flight_images);
+        let status_pb = ProgressBar::hidden();
+        
+        let mut consecutive_failures = 0;
+        
+        // 未指定總頁數時爬到整頁都看過（或沒有項目）為止
+        for page in 1..=self.total_pages.unwrap_or(u32::MAX) {
+            if shutdown.is_triggered() || !self.wait_for_window(&shutdown, &status_pb).await {
+                report.interrupted = true;
+                break;
+            }
+            
+            let url = page_url(&self.base_url, page);
+            let images = match Self::fetch_page_images(
+                page,
+                &url,
+                &self.fetcher,
+                &self.parser,
+                &self.details,
+                &self.config.determinism,
+                &status_pb,
+            ).await {
+                Ok(images) => images,
+                // 超過最後一頁
+                Err(e) if fetcher::is_not_found(&e) => break,
+                Err(e) => {
+                    eout!("❌ 第 {} 頁失敗: {}", page, e);
+                    report.failed += 1;
+                    consecutive_failures += 1;
+                    // 網路中斷或網站異常時不繼續往後試，下一輪再從第 1 頁開始
+                    if consecutive_failures >= MAX_CONSECUTIVE_PAGE_FAILURES {
+                        eout!("⚠️  連續 {} 頁失敗，停止這次增量爬取", consecutive_failures);
+                        break;
+                    }
+                    continue;
+                }
+            };
+            consecutive_failures = 0;
+            report.pages_checked += 1;
+            
+            let new_images: Vec<_> = images
+                .into_iter()
+                .filter(|(url, _)| !state.seen.contains(url))
+                .collect();
+            if new_images.is_empty() {
+                report.caught_up = true;
+                break;
+            }
+            out!("🆕 第 {} 頁: {} 張新圖片", page, new_images.len());
+            report.new_items += new_images.len();
+            
+            let mut pending = Vec::with_capacity(new_images.len());
+            for (url, name) in new_images {
+                let result = queue.submit(Self::image_job(&self.parser, url.clone(), name.clone(), page)).await?;
+                pending.push((url, name, result));
+            }
+            for (url, name, result) in pending {
+                match result.await.unwrap_or_else(|_| Err(anyhow::anyhow!("下載工作中斷"))) {
+                    Ok(outcome) => {
+                        if outcome == DownloadOutcome::Saved {
+                            report.saved += 1;
+                        } else {
+                            report.filtered += 1;
+                        }
+                        state.seen.insert(url);
                     }
                     Err(e) => {
-                        eprintln!("❌ 第 {} 頁失敗: {}", page, e);
-                        progress.add_failed_page(page);
+                        eout!("下載失敗 ({}): {}", name, e);
+                        report.failed += 1;
                     }
                 }
             }
             
-            // 儲存進度
+            // 每頁存檔，中斷後不重複下載
+            state.save(&data_dir)?;
+        }
+        
+        state.runs += 1;
+        state.last_run = Some(Utc::now());
+        state.save(&data_dir)?;
+        self.rate_limiter.save()?;
+        if report.saved > 0 {
+            let total_pages = match self.total_pages {
+                Some(total_pages) => total_pages,
+                None => self.file_manager.lock().await.load_progress()?.last_completed_page,
+            };
+            self.save_manifest(total_pages).await?;
+        }
+        
+        Ok(report)
+    }
+    
+    /// 暖身：以單一並發逐頁爬取，量測錯誤率與 429 比例
+    #[allow(clippy::too_many_arguments)]
+    async fn warm_up(
+        &self,
+        start_page: u32,
+        total_pages: u32,
+        queue: &DownloadQueue,
+        shutdown: &ShutdownSignal,
+        progress_mutex: &Arc<Mutex<Progress>>,
+        counts: &mut PageCounts,
+        main_pb: &ProgressBar,
+        image_pb: &ProgressBar,
+        status_pb: &ProgressBar,
+    ) -> Result<WarmupResult> {
+        let end_page = (start_page + self.config.warmup_pages - 1).min(total_pages);
+        let before = self.fetcher.stats();
+        let mut pages = 0;
+        
+        for page in start_page..=end_page {
+            if shutdown.is_triggered() || !self.wait_for_window(shutdown, status_pb).await {
+                break;
+            }
+            
+            status_pb.set_message(terminal::message(format!("🐢 暖身中: 第 {} 頁 ({} - {})", page, start_page, end_page)));
+            self.status.set_page_range(page, page);
+            
+            let url = page_url(&self.base_url, page);
+            let result = match Self::fetch_page_images(
+                page,
+                &url,
+                &self.fetcher,
+                &self.parser,
+                &self.details,
+                &self.config.determinism,
+                status_pb,
+            ).await {
+                Ok(images) => Self::download_page_images(page, images, queue, &self.parser, image_pb).await,
+                Err(e) => Err(e),
+            };
+            
+            main_pb.inc(1);
+            self.record_page(progress_mutex, counts, page, result, status_pb).await;
+            pages += 1;
+            
             {
                 let progress = progress_mutex.lock().await;
                 self.file_manager.lock().await.save_progress(&progress)?;
             }
             
-            // 批次間延遲
-            if batch_end < self.total_pages {
+            if page < end_page {
                 tokio::time::sleep(
-                    tokio::time::Duration::from_millis(self.config.batch_delay_ms)
+                    tokio::time::Duration::from_millis(self.config.warmup_delay_ms)
                 ).await;
             }
         }
         
-        main_pb.finish_with_message("✨ 所有頁面爬取完成！");
-        image_pb.finish();
-        status_pb.finish_and_clear();
+        let stats = self.fetcher.stats().since(&before);
+        let result = WarmupResult::recommend(
+            pages,
+            stats.requests,
+            stats.failures,
+            stats.rate_limited,
+            self.config.concurrency,
+            self.config.batch_delay_ms,
+        );
         
-        // 顯示統計
-        self.print_statistics(&progress_mutex).await;
+        status_pb.set_message(terminal::message(format!(
+            "🐢 暖身完成: {} 次請求, {} 次失敗, {} 次 429 → 並發 {}, 間隔 {}ms",
+            result.requests,
+            result.failures,
+            result.rate_limited,
+            result.chosen_concurrency,
+            result.chosen_batch_delay_ms,
+        )));
         
-        Ok(())
+        Ok(result)
     }
     
-    async fn process_page_static(
+    /// 不在允許時段內時暫停，直到時段開放（回傳 false 表示等待中收到中斷訊號）
+    ///
+    /// 進度在每頁/每批結束時已存檔，所以暫停點就是乾淨的 checkpoint。
+    async fn wait_for_window(&self, shutdown: &ShutdownSignal, status_pb: &ProgressBar) -> bool {
+        let Some(window) = self.config.allowed_hours else {
+            return true;
+        };
+        
+        if window.contains(Utc::now()) {
+            return true;
+        }
+        
+        let wait = window.until_open(Utc::now());
+        status_pb.set_message(terminal::message(format!(
+            "🌙 不在允許時段 {} 內，暫停 {} 小時 {} 分後繼續",
+            window,
+            wait.num_hours(),
+            wait.num_minutes() % 60,
+        )));
+        
+        while !window.contains(Utc::now()) {
+            if shutdown.is_triggered() {
+                return false;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        }
+        
+        status_pb.set_message(terminal::message(format!("☀️  進入允許時段 {}，繼續爬取", window)));
+        true
+    }
+    
+    /// 斷路時暫停，每隔一段時間試抓 `page`，成功才恢復（回傳 false 表示等待中收到中斷訊號）
+    ///
+    /// 與時段暫停相同，進度在每批結束時已存檔，中斷後下次從同一頁繼續。
+    async fn wait_for_recovery(
+        &self,
+        breaker: &mut CircuitBreaker,
+        page: u32,
+        shutdown: &ShutdownSignal,
+        status_pb: &ProgressBar,
+    ) -> bool {
+        let interval = breaker.config().probe_interval_secs;
+        let url = page_url(&self.base_url, page);
+        
+        loop {
+            status_pb.set_message(terminal::message(format!(
+                "⛔ 失敗率 {:.0}% 超過門檻，{} 秒後試抓第 {} 頁",
+                breaker.failure_rate() * 100.0,
+                interval,
+                page,
+            )));
+            
+            for _ in 0..interval {
+                if shutdown.is_triggered() {
+                    return false;
+                }
+                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            }
+            if shutdown.is_triggered() {
+                return false;
+            }
+            
+            match self.fetcher.fetch_page(&url).await {
+                Ok(_) => {
+                    breaker.reset();
+                    status_pb.set_message(terminal::message(format!("✅ 試抓第 {} 頁成功，繼續爬取", page)));
+                    return true;
+                }
+                Err(e) => eout!("⛔ 試抓第 {} 頁仍失敗: {}", page, e),
+            }
+        }
+    }
+    
+    /// 記錄單頁結果到進度，回傳這頁是否成功
+    async fn record_page(
+        &self,
+        progress_mutex: &Arc<Mutex<Progress>>,
+        counts: &mut PageCounts,
+        page: u32,
+        result: Result<usize>,
+        status_pb: &ProgressBar,
+    ) -> bool {
+        let mut progress = progress_mutex.lock().await;
+        counts.processed += 1;
+        
+        match result {
+            Ok(count) => {
+                progress.update(page, count);
+                self.status.record(true, count as u64);
+                status_pb.set_message(terminal::message(format!("✅ 第 {} 頁完成 ({} 張圖片)", page, count)));
+                true
+            }
+            Err(e) => {
+                eout!("❌ 第 {} 頁失敗: {}", page, e);
+                self.status.record(false, 0);
+                self.status.record_error(format!("第 {} 頁: {:#}", page, e));
+                progress.add_failed_page(page);
+                counts.failed += 1;
+                false
+            }
+        }
+    }
+    
+    /// 爬取並解析單頁，回傳整理過（抽樣/排序）的圖片列表
+    async fn fetch_page_images(
         page: u32,
         url: &str,
-        fetcher: &HttpFetcher,
-        parser: &Arc<dyn PageParser>,
-        downloader: &ImageDownloader,
+        fetcher: &Arc<HttpFetcher>,
+        parser: &ParsePool,
+        details: &DetailResolver,
+        determinism: &Determinism,
         status_pb: &ProgressBar,
-        image_pb: &ProgressBar,
-    ) -> Result<usize> {
+    ) -> Result<Vec<(String, String)>> {
         // 爬取頁面
         let html = fetcher.fetch_page(url).await
             .context("爬取失敗")?;
         
-        // 解析
-        let images = parser.parse_page(&html)
+        // 解析（在 blocking 執行緒池，不佔用 IO worker）
+        let images = parser.parse_page(html).await
             .context("解析失敗")?;
         
-        let count = images.len();
-        status_pb.set_message(format!("📥 第 {} 頁: 找到 {} 張圖片", page, count));
+        // 列表頁只有詳細頁網址時，逐一進詳細頁取得原圖
+        let images = if parser.follows_detail_pages() {
+            details.resolve(page, images, fetcher, parser, status_pb).await
+        } else {
+            images
+        };
         
-        // 下載圖片
-        let mut success_count = 0;
+        let found = images.len();
+        let images = determinism.arrange(images);
+        if images.len() < found {
+            status_pb.set_message(terminal::message(format!("📥 第 {} 頁: 找到 {} 張圖片，抽樣保留 {} 張", page, found, images.len())));
+        } else {
+            status_pb.set_message(terminal::message(format!("📥 第 {} 頁: 找到 {} 張圖片", page, found)));
+        }
+        
+        Ok(images)
+    }
+    
+    /// 建立下載工作，附上解析時找到的授權（寫入 metadata 的 `license`）與詳細頁的標籤/欄位
+    fn image_job(parser: &ParsePool, url: String, name: String, page: u32) -> ImageJob {
+        let license = parser.take_license(&url);
+        let detail = parser.take_details(&url);
+        let job = ImageJob::new(url, name, page);
+        if license.is_none() && detail.is_none() {
+            return job;
+        }
+        
+        let mut details = ItemDetails::default();
+        if let Some(detail) = detail {
+            details.tags = detail.tags;
+            details.extra = detail.extra;
+        }
+        details.license = license;
+        job.with_details(details)
+    }
+    
+    /// 下載單頁的圖片，回傳成功數
+    async fn download_page_images(
+        page: u32,
+        images: Vec<(String, String)>,
+        queue: &DownloadQueue,
+        parser: &ParsePool,
+        image_pb: &ProgressBar,
+    ) -> Result<usize> {
+        // 排入下載佇列（佇列滿時在這裡等待）
+        let mut pending = Vec::with_capacity(images.len());
         for (url, name) in images {
-            match downloader.download_and_save(&url, &name, page).await {
-                Ok(_) => {
+            let result = queue.submit(Self::image_job(parser, url, name.clone(), page)).await?;
+            pending.push((name, result));
+        }
+        
+        // 等待這一頁的圖片下載完成
+        let mut success_count = 0;
+        for (name, result) in pending {
+            match result.await.unwrap_or_else(|_| Err(anyhow::anyhow!("下載工作中斷"))) {
+                Ok(DownloadOutcome::Saved) => {
                     success_count += 1;
                     image_pb.inc(1);
                 }
+                Ok(DownloadOutcome::Skipped(reason)) => {
+                    eout!("略過 ({}): {}", name, reason);
+                }
+                Ok(DownloadOutcome::Duplicate(existing)) => {
+                    eout!("重複 ({}): 與 {} 內容相同，只記錄參照", name, existing);
+                }
                 Err(e) => {
-                    eprintln!("下載失敗 ({}): {}", name, e);
+                    eout!("下載失敗 ({}): {}", name, e);
                 }
             }
         }
@@ -205,19 +894,189 @@ impl CrawlerEngine {
         Ok(success_count)
     }
     
-    async fn print_statistics(&self, progress_mutex: &Arc<Mutex<Progress>>) {
+    /// 寫入資料集清單（dataset_manifest.json），記錄可重現設定與內容摘要
+    async fn save_manifest(&self, total_pages: u32) -> Result<()> {
+        let fm = self.file_manager.lock().await;
+        let metadata = store::open_store(fm.root_dir(), self.config.metadata_backend)?
+            .load_all_metadata()?;
+        let determinism = self.config.determinism;
+        
+        let manifest = DatasetManifest {
+            generated_at: Utc::now(),
+            source: self.base_url.clone(),
+            total_pages,
+            deterministic: determinism.enabled,
+            seed: (determinism.enabled || determinism.sample_rate.is_some()).then_some(determinism.seed),
+            sample_rate: determinism.sample_rate,
+            filename_pattern: self.config.filename_template.pattern().to_string(),
+            image_count: metadata.len(),
+            content_digest: determinism::content_digest(&metadata),
+        };
+        fm.save_manifest(&manifest)
+    }
+    
+    async fn print_statistics(&self, progress_mutex: &Arc<Mutex<Progress>>, report: &RunReport) {
         let progress = progress_mutex.lock().await;
         
-        println!("\n╔══════════════════════════════════╗");
-        println!("║       📊 爬取統計               ║");
-        println!("╠══════════════════════════════════╣");
-        println!("║ 總頁數:   {:>20} ║", self.total_pages);
-        println!("║ 已完成:   {:>20} ║", progress.last_completed_page);
-        println!("║ 圖片總數: {:>20} ║", progress.total_images_downloaded);
-        println!("║ 失敗頁面: {:>20} ║", progress.failed_pages.len());
+        out!("\n╔══════════════════════════════════╗");
+        out!("║       📊 爬取統計               ║");
+        out!("╠══════════════════════════════════╣");
+        out!("║ 總頁數:   {:>20} ║", report.total_pages);
+        out!("║ 已完成:   {:>20} ║", progress.last_completed_page);
+        out!("║ 圖片總數: {:>20} ║", progress.total_images_downloaded);
+        out!("║ 失敗頁面: {:>20} ║", progress.failed_pages.len());
         if !progress.failed_pages.is_empty() {
-            println!("║ 失敗清單: {:?}", progress.failed_pages);
+            out!("║ 失敗清單: {:?}", progress.failed_pages);
+        }
+        if report.interrupted {
+            out!("║ 狀態:     {:>18} ║", "已中斷");
+        }
+        if report.circuit_breaks > 0 {
+            out!("║ 斷路暫停: {:>18}次 ║", report.circuit_breaks);
+        }
+        if let Some(delay) = report.request_delay_ms {
+            out!("║ 請求間隔: {:>18}ms ║", delay);
+        }
+        if let Some(warmup) = &report.warmup {
+            out!("║ 暖身頁數: {:>20} ║", warmup.pages);
+            out!("║ 選定並發: {:>20} ║", report.concurrency);
+            out!("║ 選定間隔: {:>18}ms ║", report.batch_delay_ms);
+        }
+        if let Some(connections) = report.connections.filter(|c| c.requests > 0) {
+            out!("║ 請求數:   {:>20} ║", connections.requests);
+            out!("║ 新連線:   {:>20} ║", connections.new_connections);
+            out!("║ TLS 交握: {:>20} ║", connections.tls_handshakes);
+            out!("║ 連線重用: {:>19.1}% ║", connections.reuse_rate() * 100.0);
+        }
+        out!("╚══════════════════════════════════╝");
+        
+        if let Some(connections) = report.connections.filter(|c| c.reconnects_excessive()) {
+            out!(
+                "💡 {} 個請求中有 {} 個重新建立連線（共花 {:.1} 秒），伺服器關閉閒置連線的時間可能比請求間隔短；",
+                connections.requests,
+                connections.new_connections,
+                connections.connect_ms as f64 / 1000.0,
+            );
+            out!("   可用 --warmup 重新調整並發數與間隔，讓連線在被關閉前重複使用");
         }
-        println!("╚══════════════════════════════════╝");
     }
-}
\ No newline at end of file
+}
+
+/// 單頁的處理結果（可重現模式下載延後到批次迴圈）
+enum PageResult {
+    Downloaded(usize),
+    Pending(Vec<(String, String)>),
+}
+
+/// 本次執行的頁面計數
+#[derive(Debug, Default)]
+struct PageCounts {
+    processed: u32,
+    failed: u32,
+}
+
+/// 列表頁網址：`base_url` 含 `{page}` 時代入頁碼，否則加上 `?page=N`
+fn page_url(base_url: &str, page: u32) -> String {
+    if base_url.contains("{page}") {
+        base_url.replace("{page}", &page.to_string())
+    } else {
+        format!("{}?page={}", base_url, page)
+    }
+}
+
+/// 批次間隔平均分給批次內的每個請求
+fn request_delay_ms(batch_delay_ms: u64, concurrency: usize) -> u64 {
+    batch_delay_ms / concurrency.max(1) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{GenericParser, NameExtraction, Pagination, ParserConfig, SourceKind};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 第 1 ~ `last` 頁各有一張圖片，之後的頁面是空列表
+    async fn serve_pages(last: u32) -> String {
+        serve_site(last, false, None).await
+    }
+
+    /// `not_found_past_end` 時超過最後一頁回應 404；`failing_page` 一律回應 500
+    async fn serve_site(last: u32, not_found_past_end: bool, failing_page: Option<u32>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let n = socket.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..n]).to_string();
+                let page: u32 = request
+                    .split_once("page=")
+                    .and_then(|(_, rest)| rest.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok())
+                    .unwrap_or(0);
+                let body = if (1..=last).contains(&page) {
+                    let next = if page < last { format!(r#"<a rel="next" href="?page={}">下一頁</a>"#, page + 1) } else { String::new() };
+                    format!(r#"<div class="item"><b>{0}</b><img src="/{0}.jpg"></div>{1}"#, page, next)
+                } else {
+                    "<p>沒有更多了</p>".to_string()
+                };
+                let status = if failing_page == Some(page) {
+                    "500 Internal Server Error"
+                } else if not_found_past_end && page > last {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}/list?page={{page}}", addr)
+    }
+
+    fn engine(dir: &std::path::Path, base_url: String, pagination: Pagination) -> CrawlerEngine {
+        let parser = GenericParser::new(base_url.clone(), ParserConfig {
+            container_selector: "div.item".to_string(),
+            image_selector: "img".to_string(),
+            image_attr: "src".to_string(),
+            name_selector: "b".to_string(),
+            name_extraction: NameExtraction::TextContent,
+            license_selector: None,
+            all_images: false,
+            source_kind: SourceKind::Attribute,
+        })
+        .with_pagination(pagination);
+
+        let mut config = CrawlerConfig::default().with_requests_per_second(0.0, 0);
+        config.batch_delay_ms = 0;
+        config.max_retries = 0;
+        CrawlerEngine::new(dir.to_str().unwrap(), base_url, None, Arc::new(parser), config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_discover_total_pages() {
+        let base_url = serve_pages(37).await;
+        let dir = std::env::temp_dir().join(format!("meme-pages-{}", std::process::id()));
+
+        // 沒有分頁資訊：倍增探測到空頁面，再二分搜尋
+        let probing = engine(&dir, base_url.clone(), Pagination::None);
+        assert_eq!(probing.discover_total_pages(1).await.unwrap(), 37);
+        assert_eq!(probing.discover_total_pages(30).await.unwrap(), 37);
+        assert_eq!(probing.discover_total_pages(38).await.unwrap(), 37);
+        assert!(!probing.is_complete().await.unwrap());
+
+        // 有「下一頁」連結：走到沒有連結的頁面
+        let next_link = engine(&dir, base_url, Pagination::NextLink(r#"a[rel="next"]"#.to_string()));
+        assert_eq!(next_link.discover_total_pages(35).await.unwrap(), 37);
+
+        // 超過最後一頁是 404：視為結束
+        let not_found = engine(&dir, serve_site(37, true, None).await, Pagination::None);
+        assert_eq!(not_found.discover_total_pages(1).await.unwrap(), 37);
+
+        // 其他錯誤不當作最後一頁
+        let failing = engine(&dir, serve_site(37, false, Some(16)).await, Pagination::None);
+        assert!(failing.discover_total_pages(1).await.is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -2,7 +2,22 @@
 pub mod types;
 pub mod engine;
 pub mod downloader;
+pub mod schedule;
+pub mod naming;
+pub mod parse_pool;
+pub mod download_queue;
+pub mod determinism;
+pub mod site;
+pub mod diff;
+pub mod watch;
+pub mod breaker;
+pub mod detail;
 
 // 重新導出
-pub use types::CrawlerConfig;
-pub use engine::CrawlerEngine;
\ No newline at end of file
+pub use types::{CrawlerConfig, DownloadedImage, MediaFilter, SizeFilter};
+pub use engine::CrawlerEngine;
+pub use schedule::TimeWindow;
+pub use naming::FilenameTemplate;
+pub use determinism::Determinism;
+pub use site::Site;
+pub use breaker::BreakerConfig;
\ No newline at end of file
@@ -0,0 +1,309 @@
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// 可用的佔位符
+const PLACEHOLDERS: &[&str] = &["hash", "hash8", "title", "title_n", "ext", "page", "date", "site", "template_id"];
+
+/// 圖片檔名樣板，例如 `{hash8}_{title}.{ext}`（預設）
+///
+/// 佔位符：
+/// - `{hash}` / `{hash8}`：內容 SHA-256（完整 / 前 8 碼）
+/// - `{title}`：圖片名稱
+/// - `{title_n}`：圖片名稱，同名的不同圖片從第 2 張起加上編號（`標題 (2)`、`標題 (3)`）
+/// - `{ext}`：依內容判斷的副檔名
+/// - `{page}`：來源頁碼
+/// - `{date}`：下載日期（YYYYMMDD）
+/// - `{site}`：圖片 URL 的網域
+/// - `{template_id}`：圖片 URL 最後一段（去掉副檔名），通常是網站上的模板編號
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenameTemplate {
+    pattern: String,
+}
+
+/// 產生檔名需要的欄位
+#[derive(Debug, Clone, Copy)]
+pub struct FilenameFields<'a> {
+    pub hash: &'a str,
+    pub title: &'a str,
+    /// 同名不同圖的編號（見 `TitleNumbers`，1 表示不加編號）
+    pub title_number: u32,
+    pub ext: &'a str,
+    pub page: u32,
+    pub downloaded_at: DateTime<Utc>,
+    pub url: &'a str,
+}
+
+impl Default for FilenameTemplate {
+    fn default() -> Self {
+        Self {
+            pattern: Self::DEFAULT.to_string(),
+        }
+    }
+}
+
+impl FilenameTemplate {
+    pub const DEFAULT: &'static str = "{hash8}_{title}.{ext}";
+
+    /// 解析樣板（檢查佔位符，且必須包含 `{ext}`）
+    pub fn parse(pattern: &str) -> Result<Self> {
+        if pattern.contains(['/', '\\']) {
+            anyhow::bail!("檔名樣板不能包含路徑分隔符號: {}", pattern);
+        }
+
+        let mut rest = pattern;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .with_context(|| format!("檔名樣板缺少 '}}': {}", pattern))?;
+            let name = &rest[start + 1..start + end];
+            if !PLACEHOLDERS.contains(&name) {
+                anyhow::bail!("未知的佔位符 {{{}}}（可用: {}）", name, PLACEHOLDERS.join(", "));
+            }
+            rest = &rest[start + end + 1..];
+        }
+
+        if !pattern.contains("{ext}") {
+            anyhow::bail!("檔名樣板必須包含 {{ext}}: {}", pattern);
+        }
+        if !pattern.contains("{hash") && !pattern.contains("{title_n}") {
+            eout!("⚠️  檔名樣板沒有 {{hash}} 或 {{hash8}}，不同圖片可能產生相同檔名");
+        }
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// 是否使用 `{title_n}`（需要替同名圖片編號）
+    pub fn numbers_titles(&self) -> bool {
+        self.pattern.contains("{title_n}")
+    }
+
+    /// 相同內容是否一定產生相同檔名（`{date}` 依下載時間而變）
+    pub fn is_stable(&self) -> bool {
+        !self.pattern.contains("{date}")
+    }
+
+    /// 產生檔名
+    pub fn render(&self, fields: &FilenameFields) -> String {
+        // 單次掃描，避免標題裡的 `{...}` 被當成佔位符
+        let mut output = String::new();
+        let mut rest = self.pattern.as_str();
+
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                rest = &rest[start..];
+                break;
+            };
+
+            let value = match &rest[start + 1..start + len] {
+                "hash" => fields.hash.to_string(),
+                "hash8" => fields.hash[..8.min(fields.hash.len())].to_string(),
+                "title" => sanitize_filename(fields.title),
+                "title_n" => match fields.title_number {
+                    0 | 1 => sanitize_filename(fields.title),
+                    n => format!("{} ({})", sanitize_filename(fields.title), n),
+                },
+                "ext" => fields.ext.to_string(),
+                "page" => fields.page.to_string(),
+                "date" => fields.downloaded_at.format("%Y%m%d").to_string(),
+                "site" => sanitize_filename(&site_of(fields.url)),
+                "template_id" => sanitize_filename(&template_id_of(fields.url)),
+                other => format!("{{{}}}", other),
+            };
+            output.push_str(&value);
+            rest = &rest[start + len + 1..];
+        }
+
+        output.push_str(rest);
+        windows_safe(output)
+    }
+
+    /// 讀取資料目錄保存的樣板（filename_pattern.txt，不存在時使用預設）
+    pub fn load(data_dir: &str) -> Result<Self> {
+        let path = format!("{}/filename_pattern.txt", data_dir);
+        if !Path::new(&path).exists() {
+            return Ok(Self::default());
+        }
+
+        Self::parse(fs::read_to_string(&path)?.trim())
+    }
+
+    /// 保存樣板，之後的爬取與改名都使用同一個樣板
+    pub fn save(&self, data_dir: &str) -> Result<()> {
+        fs::create_dir_all(data_dir)?;
+        fs::write(format!("{}/filename_pattern.txt", data_dir), &self.pattern)
+            .context("無法儲存 filename_pattern.txt")
+    }
+}
+
+/// `{title_n}` 的編號表：標題 -> 內容 hash -> 編號
+///
+/// 標題以檔名中的寫法比較且不分大小寫（Windows 與 macOS 的檔案系統不分大小寫），
+/// 每個不同的內容依出現順序取最小的未使用編號；已記在 metadata 的編號沿用，
+/// 不會與還留著的檔案撞名。
+#[derive(Debug, Default)]
+pub struct TitleNumbers {
+    titles: HashMap<String, TitleEntry>,
+}
+
+#[derive(Debug, Default)]
+struct TitleEntry {
+    by_hash: HashMap<String, u32>,
+    used: BTreeSet<u32>,
+}
+
+impl TitleNumbers {
+    /// 從既有 metadata 建立（先放已記錄的編號，再依順序替舊資料編號）
+    pub fn from_metadata(metadata_list: &[ImageMetadata]) -> Self {
+        let mut numbers = Self::default();
+        for metadata in metadata_list {
+            if let Some(number) = metadata.title_number {
+                let entry = numbers.titles.entry(title_key(&metadata.description)).or_default();
+                entry.by_hash.entry(metadata.content_hash.clone()).or_insert(number);
+                entry.used.insert(number);
+            }
+        }
+        for metadata in metadata_list.iter().filter(|m| m.title_number.is_none()) {
+            numbers.assign(&metadata.description, &metadata.content_hash);
+        }
+        numbers
+    }
+
+    /// 取得內容的編號（相同標題的新內容取最小的未使用編號）
+    pub fn assign(&mut self, title: &str, hash: &str) -> u32 {
+        let entry = self.titles.entry(title_key(title)).or_default();
+        if let Some(number) = entry.by_hash.get(hash) {
+            return *number;
+        }
+        let number = (1..).find(|n| !entry.used.contains(n)).unwrap_or(1);
+        entry.used.insert(number);
+        entry.by_hash.insert(hash.to_string(), number);
+        number
+    }
+}
+
+fn title_key(title: &str) -> String {
+    sanitize_filename(title).to_lowercase()
+}
+
+/// URL 的網域
+fn site_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_start_matches("www.").to_string()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// URL 路徑最後一段（去掉 query string 與副檔名）
+fn template_id_of(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let last = path.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+    let id = last.split('.').next().unwrap_or_default();
+
+    if id.is_empty() { "unknown".to_string() } else { id.to_string() }
+}
+
+/// Windows 保留的裝置名稱（`CON.png`、`nul.jpg` 這類檔名在 Windows 上無法建立）
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 檔名主體是保留名稱時加上 `_` 前綴
+fn windows_safe(filename: String) -> String {
+    let stem = filename.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES.iter().any(|name| name.eq_ignore_ascii_case(stem)) {
+        format!("_{}", filename)
+    } else {
+        filename
+    }
+}
+
+/// 清理檔名
+fn sanitize_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .chars()
+        .take(50)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_filename_template() {
+        let fields = FilenameFields {
+            hash: "0123456789abcdef",
+            title: "好/笑",
+            title_number: 1,
+            ext: "png",
+            page: 42,
+            downloaded_at: Utc.with_ymd_and_hms(2025, 3, 9, 12, 0, 0).unwrap(),
+            url: "https://www.memes.tw/images/12345.jpg?w=600",
+        };
+
+        assert_eq!(FilenameTemplate::default().render(&fields), "01234567_好_笑.png");
+
+        let template = FilenameTemplate::parse("{site}/{page}_{hash8}.{ext}");
+        assert!(template.is_err());
+
+        let template = FilenameTemplate::parse("{site}_{date}_p{page}_{template_id}_{hash8}.{ext}").unwrap();
+        assert_eq!(template.render(&fields), "memes.tw_20250309_p42_12345_01234567.png");
+
+        assert!(FilenameTemplate::parse("{hash8}.{nope}").is_err());
+        assert!(FilenameTemplate::parse("{hash8}_{title}").is_err());
+    }
+
+    #[test]
+    fn test_title_numbers() {
+        let template = FilenameTemplate::parse("{title_n}.{ext}").unwrap();
+        assert!(template.numbers_titles());
+
+        let existing: Vec<ImageMetadata> = serde_json::from_value(serde_json::json!([
+            { "filename": "Doge.jpg", "description": "Doge", "url": "", "content_hash": "h1", "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z" },
+            { "filename": "doge (2).jpg", "description": "doge", "url": "", "content_hash": "h2", "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z", "title_number": 2 },
+            { "filename": "doge (5).jpg", "description": "doge", "url": "", "content_hash": "h5", "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z", "title_number": 5 },
+        ]))
+        .unwrap();
+        let mut numbers = TitleNumbers::from_metadata(&existing);
+
+        // 沒有記錄編號的舊資料取空著的 1，新圖片填入空號
+        assert_eq!(numbers.assign("DOGE", "h2"), 2);
+        assert_eq!(numbers.assign("Doge", "h1"), 1);
+        assert_eq!(numbers.assign("doge", "h7"), 3);
+        assert_eq!(numbers.assign("doge", "h7"), 3);
+        assert_eq!(numbers.assign("doge", "h8"), 4);
+        assert_eq!(numbers.assign("doge", "h9"), 6);
+        assert_eq!(numbers.assign("cat", "h7"), 1);
+
+        let fields = |title_number| FilenameFields {
+            hash: "h7",
+            title: "doge?",
+            title_number,
+            ext: "gif",
+            page: 1,
+            downloaded_at: Utc::now(),
+            url: "",
+        };
+        assert_eq!(template.render(&fields(1)), "doge_.gif");
+        assert_eq!(template.render(&fields(3)), "doge_ (3).gif");
+    }
+}
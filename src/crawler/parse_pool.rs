@@ -0,0 +1,97 @@
+use crate::parser::{DetailPage, PageHint, PageParser};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+
+/// 頁面解析池
+///
+/// HTML 解析是 CPU 密集工作，放到 `spawn_blocking` 執行，避免卡住處理網路 IO 的
+/// async worker；同時解析的數量由獨立的 semaphore 控制（預設為 CPU 核心數），
+/// 與爬取並發數無關。
+#[derive(Clone)]
+pub struct ParsePool {
+    parser: Arc<dyn PageParser>,
+    semaphore: Arc<Semaphore>,
+    /// 解析時找到的授權（圖片 URL -> 授權），下載排入佇列時取出
+    licenses: Arc<Mutex<HashMap<String, String>>>,
+    /// 詳細頁的標籤與欄位（原圖 URL -> 詳細頁），下載排入佇列時取出
+    details: Arc<Mutex<HashMap<String, DetailPage>>>,
+}
+
+impl ParsePool {
+    pub fn new(parser: Arc<dyn PageParser>, workers: usize) -> Self {
+        Self {
+            parser,
+            semaphore: Arc::new(Semaphore::new(workers.max(1))),
+            licenses: Arc::new(Mutex::new(HashMap::new())),
+            details: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 預設的解析執行緒數（CPU 核心數）
+    pub fn default_workers() -> usize {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4)
+    }
+
+    /// 列表頁回傳的是否為詳細頁網址
+    pub fn follows_detail_pages(&self) -> bool {
+        self.parser.follows_detail_pages()
+    }
+
+    /// 解析詳細頁的原圖 URL 與 metadata
+    pub async fn parse_detail_page(&self, html: String) -> Result<Option<DetailPage>> {
+        let _permit = self.semaphore.acquire().await?;
+        let parser = Arc::clone(&self.parser);
+
+        tokio::task::spawn_blocking(move || parser.parse_detail_page(&html))
+            .await
+            .context("解析工作異常結束")?
+    }
+
+    /// 記下詳細頁的 metadata，下載原圖時寫入（見 `take_details`）
+    pub fn remember_details(&self, detail: DetailPage) {
+        if !detail.tags.is_empty() || !detail.extra.is_empty() {
+            self.details.lock().unwrap().insert(detail.image_url.clone(), detail);
+        }
+    }
+
+    /// 取出詳細頁的 metadata
+    pub fn take_details(&self, image_url: &str) -> Option<DetailPage> {
+        self.details.lock().unwrap().remove(image_url)
+    }
+
+    /// 解析單頁的圖片列表（同時記下圖片的授權，見 `take_license`）
+    pub async fn parse_page(&self, html: String) -> Result<Vec<(String, String)>> {
+        let _permit = self.semaphore.acquire().await?;
+        let parser = Arc::clone(&self.parser);
+
+        let (images, licenses) = tokio::task::spawn_blocking(move || {
+            Ok::<_, anyhow::Error>((parser.parse_page(&html)?, parser.parse_licenses(&html)?))
+        })
+        .await
+        .context("解析工作異常結束")??;
+
+        if !licenses.is_empty() {
+            self.licenses.lock().unwrap().extend(licenses);
+        }
+        Ok(images)
+    }
+
+    /// 解析列表頁的分頁資訊
+    pub async fn page_hint(&self, html: String) -> Result<PageHint> {
+        let _permit = self.semaphore.acquire().await?;
+        let parser = Arc::clone(&self.parser);
+
+        tokio::task::spawn_blocking(move || parser.page_hint(&html))
+            .await
+            .context("解析工作異常結束")?
+    }
+
+    /// 取出解析時找到的圖片授權
+    pub fn take_license(&self, image_url: &str) -> Option<String> {
+        self.licenses.lock().unwrap().remove(image_url)
+    }
+}
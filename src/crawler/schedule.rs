@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// 允許爬取的時段（例如 "01:00-07:00"，可跨午夜）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+    zone: Zone,
+}
+
+/// 時段所在的時區：固定 UTC 偏移、IANA 時區（隨夏令時間調整）或本機時區
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Zone {
+    Fixed(FixedOffset),
+    Named(Tz),
+    Local,
+}
+
+impl Zone {
+    fn local_time(&self, now: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Fixed(offset) => now.with_timezone(offset).naive_local(),
+            Zone::Named(tz) => now.with_timezone(tz).naive_local(),
+            Zone::Local => now.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /// 當地時間換成 UTC（夏令時間跳過的時刻不存在時為 None，重複的時刻取較早的一個）
+    fn utc_of(&self, local: &NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::Fixed(offset) => offset.from_local_datetime(local).earliest().map(|t| t.with_timezone(&Utc)),
+            Zone::Named(tz) => tz.from_local_datetime(local).earliest().map(|t| t.with_timezone(&Utc)),
+            Zone::Local => Local.from_local_datetime(local).earliest().map(|t| t.with_timezone(&Utc)),
+        }
+    }
+}
+
+impl std::fmt::Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Zone::Fixed(offset) => write!(f, "UTC{}", offset),
+            Zone::Named(tz) => write!(f, "{}", tz.name()),
+            Zone::Local => write!(f, "本機時區"),
+        }
+    }
+}
+
+impl TimeWindow {
+    /// 解析時段設定
+    ///
+    /// `timezone` 為 IANA 時區名稱（"Asia/Taipei"、"America/New_York"）或固定 UTC 偏移
+    /// （"+08:00"、"-05:00"、"UTC"），`None` 則使用本機時區。時區名稱會隨夏令時間調整，固定偏移不會。
+    pub fn parse(spec: &str, timezone: Option<&str>) -> Result<Self> {
+        let (start, end) = spec
+            .split_once('-')
+            .with_context(|| format!("時段格式錯誤（應為 HH:MM-HH:MM）: {}", spec))?;
+
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M")
+            .with_context(|| format!("無法解析開始時間: {}", start))?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M")
+            .with_context(|| format!("無法解析結束時間: {}", end))?;
+
+        let zone = match timezone {
+            Some(tz) => parse_zone(tz)?,
+            None => Zone::Local,
+        };
+
+        Ok(Self { start, end, zone })
+    }
+
+    /// 指定時間是否在時段內（開始與結束相同視為全天）
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let time = self.zone.local_time(now).time();
+
+        if self.start == self.end {
+            true
+        } else if self.start < self.end {
+            time >= self.start && time < self.end
+        } else {
+            // 跨午夜
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// 距離下次開放還要多久（目前已開放則為 0）
+    pub fn until_open(&self, now: DateTime<Utc>) -> Duration {
+        if self.contains(now) {
+            return Duration::zero();
+        }
+
+        let local = self.zone.local_time(now);
+        let mut next = local.date().and_time(self.start);
+        if local.time() >= self.start {
+            next += Duration::days(1);
+        }
+
+        match self.zone.utc_of(&next) {
+            Some(next) => (next - now).max(Duration::zero()),
+            None => Duration::zero(),
+        }
+    }
+}
+
+impl std::fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{} ({})",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.zone
+        )
+    }
+}
+
+/// 解析時區：UTC、固定偏移（+08:00）或 IANA 時區名稱（Asia/Taipei）
+fn parse_zone(tz: &str) -> Result<Zone> {
+    let tz = tz.trim();
+    if tz.eq_ignore_ascii_case("utc") || tz == "Z" {
+        return Ok(Zone::Fixed(Utc.fix()));
+    }
+
+    let (sign, rest) = match tz.chars().next() {
+        Some('+') => (1, &tz[1..]),
+        Some('-') => (-1, &tz[1..]),
+        _ => {
+            let zone = tz.parse::<Tz>().map_err(|_| {
+                anyhow::anyhow!("未知的時區: {}（應為 IANA 時區名稱如 Asia/Taipei，或 UTC 偏移如 +08:00）", tz)
+            })?;
+            return Ok(Zone::Named(zone));
+        }
+    };
+
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: i32 = hours.parse().with_context(|| format!("時區偏移格式錯誤（應為 +08:00）: {}", tz))?;
+    let minutes: i32 = minutes.parse().with_context(|| format!("時區偏移格式錯誤（應為 +08:00）: {}", tz))?;
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+        .map(Zone::Fixed)
+        .with_context(|| format!("時區超出範圍: {}", tz))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(h: u32, m: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_window_across_midnight() {
+        let window = TimeWindow::parse("23:00-02:00", Some("UTC")).unwrap();
+
+        assert!(window.contains(utc(23, 30)));
+        assert!(window.contains(utc(1, 59)));
+        assert!(!window.contains(utc(2, 0)));
+        assert_eq!(window.until_open(utc(22, 0)), Duration::hours(1));
+    }
+
+    #[test]
+    fn test_window_with_offset() {
+        // 台灣 01:00-07:00 = UTC 17:00-23:00
+        let window = TimeWindow::parse("01:00-07:00", Some("+08:00")).unwrap();
+
+        assert!(window.contains(utc(17, 0)));
+        assert!(!window.contains(utc(23, 0)));
+        assert_eq!(window.until_open(utc(23, 0)), Duration::hours(18));
+    }
+
+    #[test]
+    fn test_window_with_named_zone() {
+        // 紐約 01:00-07:00：冬天 (EST, -05:00) = UTC 06:00-12:00，夏天 (EDT, -04:00) = UTC 05:00-11:00
+        let window = TimeWindow::parse("01:00-07:00", Some("America/New_York")).unwrap();
+        let summer = |h| Utc.with_ymd_and_hms(2024, 7, 1, h, 0, 0).unwrap();
+
+        assert!(window.contains(utc(6, 0)));
+        assert!(!window.contains(utc(5, 0)));
+        assert!(window.contains(summer(5)));
+        assert!(!window.contains(summer(11)));
+        assert_eq!(window.until_open(utc(5, 0)), Duration::hours(1));
+        assert_eq!(window.to_string(), "01:00-07:00 (America/New_York)");
+
+        let error = TimeWindow::parse("01:00-07:00", Some("Asia/Taipeii")).unwrap_err();
+        assert!(error.to_string().contains("未知的時區"));
+    }
+}
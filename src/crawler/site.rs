@@ -0,0 +1,47 @@
+use crate::parser::{GenericParser, ImgflipParser, PageParser};
+use anyhow::Result;
+use std::sync::Arc;
+
+/// 內建的網站設定（`crawl --site <name>`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Site {
+    /// memes.tw 梗圖產生器（預設）
+    #[default]
+    MemesTw,
+    /// imgflip.com 模板列表
+    Imgflip,
+}
+
+impl Site {
+    pub const NAMES: &'static [&'static str] = &["memes_tw", "imgflip"];
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "memes_tw" | "memes-tw" | "memes.tw" => Ok(Self::MemesTw),
+            "imgflip" => Ok(Self::Imgflip),
+            other => anyhow::bail!("未知的網站: {}（可用: {}）", other, Self::NAMES.join(", ")),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MemesTw => "memes_tw",
+            Self::Imgflip => "imgflip",
+        }
+    }
+
+    /// 列表頁網址（`{page}` 代入頁碼）
+    pub fn list_url(&self) -> &'static str {
+        match self {
+            Self::MemesTw => "https://memes.tw/maker?page={page}",
+            Self::Imgflip => "https://imgflip.com/memetemplates?page={page}",
+        }
+    }
+
+    pub fn parser(&self) -> Result<Arc<dyn PageParser>> {
+        Ok(match self {
+            Self::MemesTw => Arc::new(GenericParser::memes_tw()?),
+            Self::Imgflip => Arc::new(ImgflipParser::new()?),
+        })
+    }
+}
@@ -1,3 +1,26 @@
+use super::breaker::BreakerConfig;
+use super::detail::DEFAULT_DETAIL_CONCURRENCY;
+use super::determinism::Determinism;
+use super::naming::FilenameTemplate;
+use super::parse_pool::ParsePool;
+use super::schedule::TimeWindow;
+use crate::headers::HeaderRotator;
+use crate::media::MediaType;
+use crate::normalize::NormalizeOptions;
+use crate::nsfw::NsfwFilter;
+use crate::proxy::ProxyConfig;
+use crate::rate_limit::HostTokenBucket;
+use crate::store::MetadataBackend;
+use crate::types::ImageMetadata;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// 預設每個 host 每秒最多 5 個請求
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 5.0;
+
+/// 預設在一次執行中記住的內容 hash 數（約數 MB 記憶體）
+pub const DEFAULT_DEDUP_WINDOW: usize = 100_000;
+
 /// 爬蟲配置
 #[derive(Debug, Clone)]
 pub struct CrawlerConfig {
@@ -8,7 +31,56 @@ pub struct CrawlerConfig {
     /// 最大重試次數
     pub max_retries: u32,
     /// 每批次間隔（毫秒）
+    ///
+    /// 平均分給批次內的請求，作為自適應限流的起始請求間隔；
+    /// 之後依 429/403 與連續成功自動調整，並存在 rate_limits.json。
     pub batch_delay_ms: u64,
+    /// 暖身頁數（0 表示不暖身）
+    pub warmup_pages: u32,
+    /// 暖身時每頁間隔（毫秒）
+    pub warmup_delay_ms: u64,
+    /// 允許爬取的時段（None 表示不限制）
+    pub allowed_hours: Option<TimeWindow>,
+    /// metadata 儲存後端
+    pub metadata_backend: MetadataBackend,
+    /// 圖片尺寸/大小過濾
+    pub size_filter: SizeFilter,
+    /// 動圖與影片是否下載
+    pub media_filter: MediaFilter,
+    /// 代理設定（爬取頁面用）
+    pub proxy: ProxyConfig,
+    /// 圖片檔名樣板
+    pub filename_template: FilenameTemplate,
+    /// 同時解析頁面的數量（預設為 CPU 核心數，與 concurrency 無關）
+    pub parse_workers: usize,
+    /// 同時下載的圖片數上限（解析結果先排入同樣大小的有界佇列）
+    pub max_in_flight_images: usize,
+    /// 可重現模式與抽樣
+    pub determinism: Determinism,
+    /// 下載圖片的逾時（秒，連線與兩次讀取之間；與頁面請求分開設定）
+    pub download_timeout_secs: u64,
+    /// 下載圖片的最大重試次數
+    pub download_retries: u32,
+    /// 下載圖片第一次重試前的等待（毫秒，之後每次加倍）
+    pub download_backoff_ms: u64,
+    /// 頁面請求遵守 robots.txt 的 Disallow 與 Crawl-delay
+    pub respect_robots: bool,
+    /// 每個 host 的請求速率上限（None 表示不限制）
+    ///
+    /// 以同一份設定建立的 fetcher 與 downloader 共用這個 bucket（clone 設定時也共用）。
+    pub token_bucket: Option<Arc<HostTokenBucket>>,
+    /// 頁面與圖片請求的 User-Agent 輪替與各網站 headers（headers.json）
+    pub headers: Option<Arc<HeaderRotator>>,
+    /// 同一次執行中記住的內容 hash 數（內容相同的圖片只存一份，0 表示不檢查）
+    pub dedup_window: usize,
+    /// 失敗率斷路器（None 表示不論失敗多少頁都繼續）
+    pub circuit_breaker: Option<BreakerConfig>,
+    /// 同時抓取的詳細頁數（列表頁只有詳細頁網址時）
+    pub detail_concurrency: usize,
+    /// 下載時以本機模型偵測 NSFW（None 表示不偵測）
+    pub nsfw: Option<NsfwFilter>,
+    /// 下載後移除 EXIF 或統一格式（None 表示保留原檔）
+    pub normalize: Option<NormalizeOptions>,
 }
 
 impl Default for CrawlerConfig {
@@ -18,17 +90,47 @@ impl Default for CrawlerConfig {
             timeout_secs: 30,
             max_retries: 3,
             batch_delay_ms: 1000,
+            warmup_pages: 0,
+            warmup_delay_ms: 3000,
+            allowed_hours: None,
+            metadata_backend: MetadataBackend::default(),
+            size_filter: SizeFilter::default(),
+            media_filter: MediaFilter::default(),
+            proxy: ProxyConfig::default(),
+            filename_template: FilenameTemplate::default(),
+            parse_workers: ParsePool::default_workers(),
+            max_in_flight_images: 32,
+            determinism: Determinism::default(),
+            download_timeout_secs: 60,
+            download_retries: 2,
+            download_backoff_ms: 1000,
+            respect_robots: true,
+            token_bucket: Some(Arc::new(HostTokenBucket::new(DEFAULT_REQUESTS_PER_SECOND, DEFAULT_REQUESTS_PER_SECOND as u32))),
+            headers: None,
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            circuit_breaker: Some(BreakerConfig::default()),
+            detail_concurrency: DEFAULT_DETAIL_CONCURRENCY,
+            nsfw: None,
+            normalize: None,
         }
     }
 }
 
 impl CrawlerConfig {
+    #[allow(dead_code)]
     pub fn new() -> Self {
         Self::default()
     }
     
+    /// 同時爬取的頁數（至少 1）
     pub fn with_concurrency(mut self, concurrency: usize) -> Self {
-        self.concurrency = concurrency;
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    
+    /// 每批次間隔（毫秒），自適應限流的起始請求間隔由此推算
+    pub fn with_batch_delay(mut self, batch_delay_ms: u64) -> Self {
+        self.batch_delay_ms = batch_delay_ms;
         self
     }
     
@@ -36,4 +138,240 @@ impl CrawlerConfig {
         self.timeout_secs = timeout_secs;
         self
     }
-}
\ No newline at end of file
+    
+    /// 先以單一並發慢速爬取前 N 頁，量測錯誤率後自動調整並發數與延遲
+    pub fn with_warmup(mut self, pages: u32) -> Self {
+        self.warmup_pages = pages;
+        self
+    }
+    
+    /// 只在指定時段內爬取，時段結束時暫停、開放時繼續
+    pub fn with_allowed_hours(mut self, window: Option<TimeWindow>) -> Self {
+        self.allowed_hours = window;
+        self
+    }
+    
+    pub fn with_metadata_backend(mut self, backend: MetadataBackend) -> Self {
+        self.metadata_backend = backend;
+        self
+    }
+    
+    /// 透過代理爬取頁面
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = proxy;
+        self
+    }
+    
+    /// 同時解析頁面的數量
+    pub fn with_parse_workers(mut self, workers: usize) -> Self {
+        self.parse_workers = workers;
+        self
+    }
+    
+    /// 同時下載的圖片數上限，佇列滿時暫停解析下一批圖片
+    pub fn with_max_in_flight_images(mut self, max_in_flight_images: usize) -> Self {
+        self.max_in_flight_images = max_in_flight_images;
+        self
+    }
+    
+    /// 可重現模式：相同網站狀態產生相同的圖片、檔名與 metadata 順序
+    pub fn with_deterministic(mut self, enabled: bool, seed: u64) -> Self {
+        self.determinism.enabled = enabled;
+        self.determinism.seed = seed;
+        self
+    }
+    
+    /// 依 seed 抽樣保留部分圖片（0.0–1.0）
+    pub fn with_sample_rate(mut self, sample_rate: Option<f64>) -> Self {
+        self.determinism.sample_rate = sample_rate;
+        self
+    }
+    
+    /// 下載圖片的檔名樣板
+    pub fn with_filename_template(mut self, filename_template: FilenameTemplate) -> Self {
+        self.filename_template = filename_template;
+        self
+    }
+    
+    /// 略過寬或高小於指定像素的圖片（過濾縮圖）
+    pub fn with_min_size(mut self, min_width: Option<u32>, min_height: Option<u32>) -> Self {
+        self.size_filter.min_width = min_width;
+        self.size_filter.min_height = min_height;
+        self
+    }
+    
+    /// 略過超過指定位元組數的圖片
+    pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.size_filter.max_bytes = max_bytes;
+        self
+    }
+    
+    /// 下載圖片的逾時與重試次數（頁面請求用 `with_timeout`）
+    pub fn with_download(mut self, timeout_secs: u64, retries: u32) -> Self {
+        self.download_timeout_secs = timeout_secs;
+        self.download_retries = retries;
+        self
+    }
+    
+    /// 下載圖片第一次重試前的等待（毫秒，之後每次加倍）
+    pub fn with_download_backoff(mut self, backoff_ms: u64) -> Self {
+        self.download_backoff_ms = backoff_ms;
+        self
+    }
+    
+    /// 是否遵守 robots.txt（預設遵守；`--ignore-robots` 時關閉）
+    pub fn with_robots(mut self, respect: bool) -> Self {
+        self.respect_robots = respect;
+        self
+    }
+    
+    /// 每個 host 每秒的請求數上限與瞬間請求數（`rate` 為 0 時不限制）
+    pub fn with_requests_per_second(mut self, rate: f64, burst: u32) -> Self {
+        self.token_bucket = (rate > 0.0).then(|| Arc::new(HostTokenBucket::new(rate, burst)));
+        self
+    }
+    
+    /// 同一次執行中內容相同的圖片只存一份（記住最近 `window` 個 hash，0 表示不檢查）
+    pub fn with_dedup_window(mut self, window: usize) -> Self {
+        self.dedup_window = window;
+        self
+    }
+    
+    /// 最近頁面的失敗率超過門檻時暫停爬取，定期試抓直到網站恢復（None 表示關閉）
+    pub fn with_circuit_breaker(mut self, breaker: Option<BreakerConfig>) -> Self {
+        self.circuit_breaker = breaker;
+        self
+    }
+    
+    /// 同時抓取的詳細頁數（與列表頁的並發數分開）
+    pub fn with_detail_concurrency(mut self, concurrency: usize) -> Self {
+        self.detail_concurrency = concurrency;
+        self
+    }
+    
+    /// 下載後以本機模型偵測 NSFW，依設定標記或隔離（None 表示不偵測）
+    pub fn with_nsfw(mut self, nsfw: Option<NsfwFilter>) -> Self {
+        self.nsfw = nsfw;
+        self
+    }
+    
+    /// 下載後移除 EXIF 或統一格式，hash 依轉換後的內容計算（None 表示保留原檔）
+    pub fn with_normalize(mut self, normalize: Option<NormalizeOptions>) -> Self {
+        self.normalize = normalize;
+        self
+    }
+    
+    /// 是否下載動圖（多影格的 GIF/WebP/APNG）與影片（mp4、webm）
+    pub fn with_media(mut self, animated: bool, video: bool) -> Self {
+        self.media_filter = MediaFilter { animated, video };
+        self
+    }
+    
+    /// 輪替 User-Agent 並加上各網站的 headers（None 表示只用預設的 User-Agent）
+    pub fn with_headers(mut self, headers: Option<Arc<HeaderRotator>>) -> Self {
+        self.headers = headers;
+        self
+    }
+}
+
+/// 下載圖片的尺寸/大小限制（None 表示不限制）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SizeFilter {
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_bytes: Option<u64>,
+}
+
+impl SizeFilter {
+    pub fn is_empty(&self) -> bool {
+        self.min_width.is_none() && self.min_height.is_none() && self.max_bytes.is_none()
+    }
+    
+    /// 檢查檔案大小，超過時回傳略過原因
+    pub fn check_bytes(&self, len: u64) -> Option<String> {
+        match self.max_bytes {
+            Some(max) if len > max => Some(format!("檔案過大 ({} > {} bytes)", len, max)),
+            _ => None,
+        }
+    }
+    
+    /// 檢查圖片內容，不符合時回傳略過原因
+    ///
+    /// 只讀取檔頭解析尺寸；無法解析的格式（例如 SVG）不做尺寸過濾。
+    pub fn check(&self, bytes: &[u8]) -> Option<String> {
+        if let Some(reason) = self.check_bytes(bytes.len() as u64) {
+            return Some(reason);
+        }
+        
+        if self.min_width.is_none() && self.min_height.is_none() {
+            return None;
+        }
+        
+        self.check_dimensions(crate::media::probe_dimensions(bytes))
+    }
+    
+    /// 以 metadata 記錄的檔案大小與尺寸檢查（下載時已記錄，不必讀檔；沒有記錄的不做過濾）
+    pub fn check_metadata(&self, metadata: &ImageMetadata) -> Option<String> {
+        if let Some(reason) = metadata.file_size.and_then(|len| self.check_bytes(len)) {
+            return Some(reason);
+        }
+        
+        self.check_dimensions(metadata.width.zip(metadata.height))
+    }
+    
+    /// 檢查已解析的尺寸（None 表示無法解析，不做尺寸過濾）
+    pub fn check_dimensions(&self, dimensions: Option<(u32, u32)>) -> Option<String> {
+        let (width, height) = dimensions?;
+        
+        if self.min_width.is_some_and(|min| width < min) || self.min_height.is_some_and(|min| height < min) {
+            return Some(format!("尺寸過小 ({}x{})", width, height));
+        }
+        
+        None
+    }
+}
+
+/// 依媒體類型過濾（預設全部下載）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediaFilter {
+    pub animated: bool,
+    pub video: bool,
+}
+
+impl Default for MediaFilter {
+    fn default() -> Self {
+        Self { animated: true, video: true }
+    }
+}
+
+impl MediaFilter {
+    /// 檢查媒體類型，不下載時回傳略過原因
+    pub fn check(&self, media_type: MediaType) -> Option<String> {
+        match media_type {
+            MediaType::Animated if !self.animated => Some("動圖（--no-animated）".to_string()),
+            MediaType::Video if !self.video => Some("影片（--no-video）".to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// 下載完成的圖片（`CrawlerEngine::stream` 的項目）
+#[derive(Debug, Clone)]
+pub struct DownloadedImage {
+    /// 圖片 metadata
+    pub metadata: ImageMetadata,
+    /// 本地檔案路徑
+    pub path: PathBuf,
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_concurrency() {
+        // 0 會讓分批的 step_by 與 semaphore 無法運作
+        let config = CrawlerConfig::default().with_concurrency(0);
+        assert_eq!(config.concurrency, 1);
+        assert_eq!((1..=5u32).step_by(config.concurrency).count(), 5);
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// 常駐模式的狀態（watch_state.json）
+///
+/// 記錄處理過的圖片網址（下載成功或未通過過濾），增量爬取時只下載沒看過的網址。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchState {
+    pub seen: BTreeSet<String>,
+    /// 已執行的增量爬取次數
+    #[serde(default)]
+    pub runs: u32,
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+impl WatchState {
+    pub fn load(data_dir: &str) -> Result<Self> {
+        let path = state_path(data_dir);
+        if !Path::new(&path).exists() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(&fs::read_to_string(&path)?).context("無法解析 watch_state.json")
+    }
+
+    pub fn save(&self, data_dir: &str) -> Result<()> {
+        let path = state_path(data_dir);
+        let temp_path = format!("{}.tmp", path);
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+}
+
+fn state_path(data_dir: &str) -> String {
+    format!("{}/watch_state.json", data_dir)
+}
+
+/// 一次增量爬取的結果
+#[derive(Debug, Default)]
+pub struct RefreshReport {
+    /// 抓取的頁數
+    pub pages_checked: u32,
+    /// 頁面上沒看過的圖片數
+    pub new_items: usize,
+    pub saved: usize,
+    /// 未通過過濾條件的圖片數
+    pub filtered: usize,
+    pub failed: usize,
+    /// 遇到整頁都看過的頁面而停止（之後的頁面上次已爬過）
+    pub caught_up: bool,
+    pub interrupted: bool,
+}
+
+/// 解析間隔（`90s`、`30m`、`6h`、`1d`；只有數字時為分鐘）
+pub fn parse_interval(spec: &str) -> Result<Duration> {
+    let spec = spec.trim();
+    let (number, unit) = match spec.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => spec.split_at(i),
+        None => (spec, "m"),
+    };
+
+    let value: u64 = number
+        .parse()
+        .with_context(|| format!("間隔格式錯誤（例如 30m、6h）: {}", spec))?;
+    let secs = match unit {
+        "s" => value,
+        "m" => value * 60,
+        "h" => value * 3600,
+        "d" => value * 86400,
+        _ => anyhow::bail!("未知的時間單位: {}（可用: s, m, h, d）", unit),
+    };
+    if secs == 0 {
+        anyhow::bail!("間隔必須大於 0: {}", spec);
+    }
+
+    Ok(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("6h").unwrap(), Duration::from_secs(6 * 3600));
+        assert_eq!(parse_interval("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("15").unwrap(), Duration::from_secs(15 * 60));
+        assert!(parse_interval("0h").is_err());
+        assert!(parse_interval("6w").is_err());
+        assert!(parse_interval("h").is_err());
+    }
+}
@@ -1,167 +1,455 @@
+use crate::context::{files, DataContext};
+use crate::dedup_index::{DedupIndex, HashSummary};
+use crate::dedup_report;
+use crate::file_manager;
+use crate::impact::ImpactSummary;
 use crate::types::{ImageMetadata, DuplicateRecord};
-use crate::file_manager::FileManager;
-use anyhow::Result;
-use std::collections::{HashMap, HashSet};
+use crate::reverse_search::{self, ReverseSearchResult};
+use crate::store::MetadataBackend;
+use crate::trash::{TrashBatch, TrashEntry};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::fs;
+use std::sync::Arc;
 
 /// 去重分析器
 pub struct DedupAnalyzer {
-    file_manager: FileManager,
+    context: Arc<DataContext>,
+    /// 重複圖片移到 `trash/<時間>/`（可用 `dedup restore` 還原），而不是直接刪除
+    trash: bool,
 }
 
 impl DedupAnalyzer {
     pub fn new(data_dir: &str) -> Result<Self> {
-        Ok(Self {
-            file_manager: FileManager::new(data_dir)?,
-        })
+        Ok(Self::from_context(DataContext::open(data_dir, MetadataBackend::Jsonl)?))
+    }
+    
+    /// 使用共用的資料目錄環境（metadata 後端與讀取過的資料）
+    pub fn from_context(context: Arc<DataContext>) -> Self {
+        Self { context, trash: false }
+    }
+    
+    /// 移除時把圖片移到回收桶並記錄 manifest
+    pub fn with_trash(mut self, trash: bool) -> Self {
+        self.trash = trash;
+        self
     }
     
-    /// 分析重複圖片
+    /// 分析重複圖片（以雜湊索引找出重複組，只讀取重複組成員的 metadata）
     pub fn analyze(&self) -> Result<DedupResult> {
-        println!("📖 讀取所有 metadata...");
-        let all_metadata = self.file_manager.load_all_metadata()?;
+        let summary = self.hash_summary()?;
         
-        println!("🔍 分析中... (共 {} 張圖片)", all_metadata.len());
+        out!("🔍 分析中... (共 {} 張圖片)", summary.total);
         
-        // hash -> Vec<ImageMetadata>
-        let mut hash_map: HashMap<String, Vec<ImageMetadata>> = HashMap::new();
-        
-        for metadata in all_metadata {
-            hash_map
-                .entry(metadata.content_hash.clone())
-                .or_insert_with(Vec::new)
-                .push(metadata);
+        // 用來替重複組命名
+        let results = match summary.groups.is_empty() {
+            true => Default::default(),
+            false => self.context.search_results()?,
+        };
+        let mut results_by_file: HashMap<&str, Vec<&ReverseSearchResult>> = HashMap::new();
+        for result in results.iter() {
+            results_by_file.entry(result.filename.as_str()).or_default().push(result);
         }
         
         // 找出重複的
         let mut duplicates = Vec::new();
-        let mut unique_count = 0;
         let mut duplicate_count = 0;
+        let mut members = Vec::new();
         
-        for (hash, items) in hash_map.iter() {
-            if items.len() > 1 {
-                // 有重複
-                duplicate_count += items.len() - 1; // 保留一個，其餘算重複
-                
-                let record = DuplicateRecord {
-                    content_hash: hash.clone(),
-                    files: items.iter().map(|m| m.filename.clone()).collect(),
-                };
-                duplicates.push(record);
-            } else {
-                unique_count += 1;
-            }
+        for (hash, items) in summary.groups {
+            duplicate_count += items.len() - 1; // 保留一個，其餘算重複
+            
+            duplicates.push(DuplicateRecord {
+                content_hash: hash,
+                files: items.iter().map(|m| m.filename.clone()).collect(),
+                name: group_name(&items, &results_by_file),
+            });
+            members.extend(items);
         }
         
         Ok(DedupResult {
-            total_images: hash_map.values().map(|v| v.len()).sum(),
-            unique_images: hash_map.len(),
+            total_images: summary.total,
+            unique_images: summary.unique,
             duplicate_groups: duplicates.len(),
             duplicate_images: duplicate_count,
             duplicates,
+            members,
         })
     }
     
-    /// 標記重複圖片（寫入檔案）
+    /// 總數與重複組：SQLite 後端直接查詢，JSONL 後端先把 metadata.jsonl 新增的行補進 dedup_index.db
+    fn hash_summary(&self) -> Result<HashSummary> {
+        if let Some(sqlite) = self.context.sqlite() {
+            return sqlite.hash_summary();
+        }
+        
+        let mut index = DedupIndex::open(self.context.root())?;
+        let added = index.sync()?;
+        if added > 0 {
+            out!("📇 去重索引新增 {} 筆", added);
+        }
+        match index.summary() {
+            Ok(summary) => Ok(summary),
+            Err(e) => {
+                eout!("⚠️  {}，重建去重索引", e);
+                index.rebuild()?;
+                index.summary()
+            }
+        }
+    }
+    
+    /// 標記重複圖片（寫入 duplicates.json 與 dedup_report.html）
     pub fn mark_duplicates(&self, result: &DedupResult) -> Result<()> {
-        println!("💾 儲存重複圖片報告...");
+        out!("💾 儲存重複圖片報告...");
         
         // 儲存到 duplicates.json
+        let path = self.context.path(files::DUPLICATES);
         let json = serde_json::to_string_pretty(&result.duplicates)?;
-        fs::write("./data/duplicates.json", json)?;
+        fs::write(&path, json)?;
+        self.context.store().save_duplicate_groups(&result.duplicates)?;
+        
+        out!("✅ 報告已儲存到 {}", path);
         
-        println!("✅ 報告已儲存到 ./data/duplicates.json");
+        // 並排顯示每組的縮圖，remove 前用瀏覽器檢查
+        let html = dedup_report::write_report(self.context.file_manager(), &result.duplicates, &result.members)?;
+        out!("🖼️  視覺化報告: {}", html);
         
         Ok(())
     }
     
-    /// 自動刪除重複圖片（保留第一個）+ 更新 metadata
+    /// 計算刪除重複圖片的影響（不刪除）
+    pub fn impact(&self, result: &DedupResult) -> Result<ImpactSummary> {
+        Ok(ImpactSummary::compute(
+            "dedup",
+            self.context.file_manager(),
+            &self.context.metadata()?,
+            &self.context.search_results()?,
+            &result.deletions(),
+        ))
+    }
+    
+    /// 自動刪除重複圖片（保留第一個）+ 更新 metadata，搜尋結果改指向保留的檔案
     pub fn remove_duplicates(&self, result: &DedupResult, dry_run: bool) -> Result<()> {
         if dry_run {
-            println!("🔍 預覽模式：不會實際刪除檔案\n");
+            out!("🔍 預覽模式：不會實際刪除檔案\n");
         } else {
-            println!("⚠️  警告：即將刪除重複圖片並更新 metadata！\n");
-            
-            // 先備份 metadata
-            self.file_manager.backup_metadata()?;
+            out!("⚠️  警告：即將刪除重複圖片並更新 metadata！\n");
         }
         
-        // 收集要刪除的檔名
-        let mut files_to_remove = HashSet::new();
-        let mut removed_count = 0;
-        
         for dup_group in &result.duplicates {
-            println!("📦 重複組 (Hash: {}...):", &dup_group.content_hash[..12]);
+            out!("📦 重複組 (Hash: {}...):", &dup_group.content_hash[..12]);
             
             // 保留第一個，刪除其餘
             for (i, filename) in dup_group.files.iter().enumerate() {
                 if i == 0 {
-                    println!("  ✅ 保留: {}", filename);
-                    continue;
+                    out!("  ✅ 保留: {}", filename);
+                } else if dry_run && *filename != dup_group.files[0] {
+                    out!("  🗑️  [預覽] 將刪除: {}", filename);
                 }
-                
-                files_to_remove.insert(filename.clone());
-                let path = self.file_manager.get_image_path(filename);
-                
-                if dry_run {
-                    println!("  🗑️  [預覽] 將刪除: {}", filename);
-                } else {
-                    match fs::remove_file(&path) {
-                        Ok(_) => {
-                            println!("  ❌ 已刪除圖片: {}", filename);
-                            removed_count += 1;
-                        }
-                        Err(e) => {
-                            eprintln!("  ⚠️  刪除圖片失敗 ({}): {}", filename, e);
-                        }
+            }
+            out!();
+        }
+        
+        if dry_run {
+            out!("💡 預覽完成！執行 'cargo run dedup remove' 來實際刪除");
+            return Ok(());
+        }
+        
+        let removal = self.remove_files(&result.deletions())?;
+        
+        // 總結
+        out!("╔══════════════════════════════════╗");
+        out!("║       ✅ 去重完成               ║");
+        out!("╠══════════════════════════════════╣");
+        out!("║ 刪除圖片:   {:>18} ║", removal.removed);
+        out!("║ 更新 metadata: {:>14} ║", "完成");
+        out!("║ 備份檔案:   {:>18} ║", "metadata.jsonl.backup");
+        if let Some(id) = &removal.trash {
+            out!("║ 回收桶:     {:>18} ║", id);
+        }
+        out!("╚══════════════════════════════════╝");
+        if let Some(id) = &removal.trash {
+            out!("💡 執行 'cargo run dedup restore {}' 可還原", id);
+        }
+        
+        Ok(())
+    }
+    
+    /// 刪除（或移到回收桶）`deletions` 的圖片並更新 metadata
+    ///
+    /// `deletions` 是 刪除的檔名 -> 保留的檔名；搜尋結果改指向保留的檔案，
+    /// 保留的檔案在其他資料目錄（None）時搜尋結果不變。
+    pub fn remove_files(&self, deletions: &HashMap<String, Option<String>>) -> Result<Removal> {
+        let mut removal = Removal::default();
+        if deletions.is_empty() {
+            return Ok(removal);
+        }
+        
+        // 先備份 metadata
+        self.context.file_manager().backup_metadata()?;
+        let mut batch = match self.trash {
+            true => Some(TrashBatch::create(self.context.root(), "dedup")?),
+            false => None,
+        };
+        
+        let mut files_to_remove: Vec<&String> = deletions.keys().collect();
+        files_to_remove.sort();
+        
+        for filename in &files_to_remove {
+            let path = self.context.file_manager().get_image_path(filename);
+            
+            if let Some(batch) = &batch {
+                match batch.move_in(&path, filename) {
+                    Ok(_) => {
+                        out!("  🗑️  已移到回收桶: {}", filename);
+                        removal.removed += 1;
+                    }
+                    Err(e) => {
+                        eout!("  ⚠️  {}: {}", filename, e);
+                    }
+                }
+            } else {
+                match fs::remove_file(&path) {
+                    Ok(_) => {
+                        out!("  ❌ 已刪除圖片: {}", filename);
+                        removal.removed += 1;
+                    }
+                    Err(e) => {
+                        eout!("  ⚠️  刪除圖片失敗 ({}): {}", filename, e);
                     }
                 }
             }
-            println!();
         }
+        out!();
         
         // 更新 metadata.jsonl
-        if !dry_run && !files_to_remove.is_empty() {
-            println!("📝 更新 metadata.jsonl...");
-            
-            // 讀取所有 metadata
-            let all_metadata = self.file_manager.load_all_metadata()?;
-            let original_count = all_metadata.len();
-            
-            // 過濾掉已刪除的檔案
-            let filtered_metadata: Vec<ImageMetadata> = all_metadata
-                .into_iter()
-                .filter(|m| !files_to_remove.contains(&m.filename))
-                .collect();
+        out!("📝 更新 metadata.jsonl...");
+        
+        // 讀取所有 metadata
+        let all_metadata = self.context.store().load_all_metadata()?;
+        let original_count = all_metadata.len();
+        let results_file = self.context.path(files::SEARCH_RESULTS);
+        let mut results = reverse_search::load_all_results(&results_file)?;
+        
+        // 改寫 metadata 前先記下還原所需的資料
+        if let Some(batch) = &mut batch {
+            // 先依檔名建立索引，避免每個檔案都掃描全部 metadata 與搜尋結果
+            let mut metadata_by_file: HashMap<&str, &ImageMetadata> = HashMap::new();
+            for m in all_metadata.iter().filter(|m| deletions.contains_key(&m.filename)) {
+                metadata_by_file.entry(m.filename.as_str()).or_insert(m);
+            }
+            let mut results_by_file: HashMap<&str, Vec<ReverseSearchResult>> = HashMap::new();
+            for r in results.iter().filter(|r| deletions.contains_key(&r.filename)) {
+                results_by_file.entry(r.filename.as_str()).or_default().push(r.clone());
+            }
             
-            let filtered_count = filtered_metadata.len();
-            let removed_metadata_count = original_count - filtered_count;
+            for filename in &files_to_remove {
+                batch.push(TrashEntry {
+                    filename: filename.to_string(),
+                    kept: deletions.get(*filename).cloned().flatten(),
+                    metadata: metadata_by_file.get(filename.as_str()).map(|m| (*m).clone()),
+                    results: results_by_file.remove(filename.as_str()).unwrap_or_default(),
+                });
+            }
+            batch.save()?;
+            removal.trash = Some(batch.id().to_string());
+        }
+        
+        // 過濾掉已刪除的檔案
+        let filtered_metadata: Vec<ImageMetadata> = all_metadata
+            .into_iter()
+            .filter(|m| !deletions.contains_key(&m.filename))
+            .collect();
+        
+        let filtered_count = filtered_metadata.len();
+        let removed_metadata_count = original_count - filtered_count;
+        
+        // 重寫 metadata
+        self.context.rewrite_metadata(filtered_metadata)?;
+        
+        out!("✅ metadata.jsonl 已更新");
+        out!("   原始記錄: {} 筆", original_count);
+        out!("   保留記錄: {} 筆", filtered_count);
+        out!("   移除記錄: {} 筆", removed_metadata_count);
+        out!();
+        
+        // 被刪除的重複檔案的搜尋結果改指向保留的檔案
+        let mut remapped = 0;
+        for search_result in &mut results {
+            if let Some(Some(kept)) = deletions.get(&search_result.filename) {
+                search_result.filename = kept.clone();
+                remapped += 1;
+            }
+        }
+        if remapped > 0 {
+            reverse_search::rewrite_all_results(&results_file, &results)?;
+            out!("🔗 {} 筆搜尋結果改指向保留的檔案\n", remapped);
+        }
+        self.context.invalidate();
+        
+        Ok(removal)
+    }
+    
+    /// 跨資料目錄分析（`dedup --dirs a,b,c`），`analyzers` 的順序就是保留的優先順序
+    ///
+    /// 所有目錄的 metadata 一起比對雜湊，只回報出現在兩個以上目錄的重複組；
+    /// 每組保留優先順序最前面的目錄中的第一個檔案，其餘副本都刪除。
+    pub fn analyze_dirs(analyzers: &[DedupAnalyzer]) -> Result<CrossDedupResult> {
+        let mut metadata = Vec::with_capacity(analyzers.len());
+        let mut results = Vec::with_capacity(analyzers.len());
+        for analyzer in analyzers {
+            metadata.push(analyzer.context.metadata()?);
+            results.push(analyzer.context.search_results()?);
+        }
+        let total_images = metadata.iter().map(|m| m.len()).sum();
+        
+        out!("🔍 分析中... ({} 個資料目錄，共 {} 張圖片)", analyzers.len(), total_images);
+        
+        // hash -> (目錄, metadata)，依目錄順序
+        let mut by_hash: HashMap<&str, Vec<(usize, &ImageMetadata)>> = HashMap::new();
+        let mut order = Vec::new();
+        for (dir, list) in metadata.iter().enumerate() {
+            for m in list.iter() {
+                let members = by_hash.entry(m.content_hash.as_str()).or_default();
+                if members.is_empty() {
+                    order.push(m.content_hash.as_str());
+                }
+                members.push((dir, m));
+            }
+        }
+        
+        let mut groups = Vec::new();
+        for hash in order {
+            let members = &by_hash[hash];
+            if members.iter().all(|(dir, _)| *dir == members[0].0) {
+                continue;
+            }
             
-            // 重寫 metadata.jsonl
-            self.file_manager.rewrite_metadata(&filtered_metadata)?;
+            // 同名檔案可能在各目錄都有，命名只用各成員自己目錄的搜尋結果
+            let mut results_by_file: HashMap<&str, Vec<&ReverseSearchResult>> = HashMap::new();
+            for (dir, m) in members {
+                results_by_file.entry(m.filename.as_str()).or_default().extend(
+                    results[*dir].iter().filter(|r| r.filename == m.filename),
+                );
+            }
+            let items: Vec<ImageMetadata> = members.iter().map(|(_, m)| (*m).clone()).collect();
             
-            println!("✅ metadata.jsonl 已更新");
-            println!("   原始記錄: {} 筆", original_count);
-            println!("   保留記錄: {} 筆", filtered_count);
-            println!("   移除記錄: {} 筆", removed_metadata_count);
-            println!();
+            groups.push(CrossDuplicateRecord {
+                content_hash: hash.to_string(),
+                name: group_name(&items, &results_by_file),
+                files: members
+                    .iter()
+                    .map(|(dir, m)| CrossFile {
+                        dir: analyzers[*dir].context.root().to_string(),
+                        filename: m.filename.clone(),
+                    })
+                    .collect(),
+            });
         }
         
-        // 總結
-        if !dry_run {
-            println!("╔══════════════════════════════════╗");
-            println!("║       ✅ 去重完成               ║");
-            println!("╠══════════════════════════════════╣");
-            println!("║ 刪除圖片:   {:>18} ║", removed_count);
-            println!("║ 更新 metadata: {:>14} ║", "完成");
-            println!("║ 備份檔案:   {:>18} ║", "metadata.jsonl.backup");
-            println!("╚══════════════════════════════════╝");
-        } else {
-            println!("💡 預覽完成！執行 'cargo run dedup remove' 來實際刪除");
+        Ok(CrossDedupResult {
+            dirs: analyzers.iter().map(|a| a.context.root().to_string()).collect(),
+            total_images,
+            groups,
+        })
+    }
+    
+    /// 計算刪除跨目錄重複圖片在這個目錄的影響（不刪除）
+    pub fn cross_impact(&self, result: &CrossDedupResult) -> Result<ImpactSummary> {
+        Ok(ImpactSummary::compute(
+            "dedup_dirs",
+            self.context.file_manager(),
+            &self.context.metadata()?,
+            &self.context.search_results()?,
+            &result.deletions(self.context.root()),
+        ))
+    }
+}
+
+/// `remove_files` 的結果
+#[derive(Debug, Default)]
+pub struct Removal {
+    /// 實際刪除（或移到回收桶）的圖片數
+    pub removed: usize,
+    /// 回收桶批次
+    pub trash: Option<String>,
+}
+
+/// 依成員的搜尋結果替重複組命名
+///
+/// 每個成員各自投票（同一個標籤只算一次，取最高權重）：best guess 3 分、推測標題 2 分、
+/// 關鍵字 1 分，不分大小寫合併，同分時取先出現的。沒有任何標籤時取最常見的來源名稱。
+pub fn group_name(
+    members: &[ImageMetadata],
+    results_by_file: &HashMap<&str, Vec<&ReverseSearchResult>>,
+) -> Option<String> {
+    // 小寫標籤 -> (顯示用的寫法, 總分, 首次出現順序)
+    let mut scores: HashMap<String, (String, u32, usize)> = HashMap::new();
+    
+    for metadata in members {
+        let mut votes: HashMap<String, (String, u32)> = HashMap::new();
+        let mut vote = |label: &str, weight: u32| {
+            let label = label.trim();
+            if label.is_empty() || is_match_count(label) {
+                return;
+            }
+            let entry = votes.entry(label.to_lowercase()).or_insert((label.to_string(), 0));
+            entry.1 = entry.1.max(weight);
+        };
+        
+        for result in results_by_file.get(metadata.filename.as_str()).into_iter().flatten() {
+            if let Some(guess) = &result.best_guess {
+                vote(guess, 3);
+            }
+            if let Some(title) = &result.suggested_title {
+                vote(title, 2);
+            }
+            for keyword in &result.keywords {
+                vote(keyword, 1);
+            }
+        }
+        if let Some(title) = &metadata.suggested_title {
+            vote(title, 2);
+        }
+        for keyword in &metadata.keywords {
+            vote(keyword, 1);
         }
         
-        Ok(())
+        for (key, (label, weight)) in votes {
+            let order = scores.len();
+            let entry = scores.entry(key).or_insert((label, 0, order));
+            entry.1 += weight;
+        }
+    }
+    
+    if let Some((label, _, _)) = scores
+        .into_values()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)))
+    {
+        return Some(label);
     }
+    
+    // 沒有搜尋結果：最常見的來源名稱
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for description in members.iter().map(|m| m.description.trim()).filter(|d| !d.is_empty()) {
+        match counts.iter_mut().find(|(d, _)| *d == description) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((description, 1)),
+        }
+    }
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(description, _)| description.to_string())
+}
+
+/// TinEye 的 "N matches" 不是標籤
+fn is_match_count(label: &str) -> bool {
+    label
+        .strip_suffix(" matches")
+        .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
 }
 
 /// 去重結果
@@ -177,44 +465,255 @@ pub struct DedupResult {
     pub duplicate_images: usize,
     /// 重複記錄
     pub duplicates: Vec<DuplicateRecord>,
+    /// 重複組成員的 metadata（產生報告用）
+    pub members: Vec<ImageMetadata>,
 }
 
 impl DedupResult {
+    /// 要刪除的檔名 -> 保留的檔名（每組保留第一個）
+    pub fn deletions(&self) -> HashMap<String, Option<String>> {
+        let mut deletions = HashMap::new();
+        for group in &self.duplicates {
+            let Some((kept, rest)) = group.files.split_first() else {
+                continue;
+            };
+            for filename in rest {
+                // 同一個檔名在 metadata 出現多次時不刪除保留的那個
+                if filename != kept {
+                    deletions.insert(filename.clone(), Some(kept.clone()));
+                }
+            }
+        }
+        deletions
+    }
+    
     /// 顯示報告
     pub fn print_report(&self) {
-        println!("\n╔══════════════════════════════════╗");
-        println!("║     🔍 重複圖片分析報告         ║");
-        println!("╠══════════════════════════════════╣");
-        println!("║ 總圖片數:   {:>18} ║", self.total_images);
-        println!("║ 唯一圖片:   {:>18} ║", self.unique_images);
-        println!("║ 重複組數:   {:>18} ║", self.duplicate_groups);
-        println!("║ 重複圖片:   {:>18} ║", self.duplicate_images);
+        out!("\n╔══════════════════════════════════╗");
+        out!("║     🔍 重複圖片分析報告         ║");
+        out!("╠══════════════════════════════════╣");
+        out!("║ 總圖片數:   {:>18} ║", self.total_images);
+        out!("║ 唯一圖片:   {:>18} ║", self.unique_images);
+        out!("║ 重複組數:   {:>18} ║", self.duplicate_groups);
+        out!("║ 重複圖片:   {:>18} ║", self.duplicate_images);
         
         if self.total_images > 0 {
-            println!("║ 重複率:     {:>17.1}% ║", 
+            out!("║ 重複率:     {:>17.1}% ║", 
                 (self.duplicate_images as f64 / self.total_images as f64) * 100.0);
         }
         
-        println!("╚══════════════════════════════════╝\n");
+        out!("╚══════════════════════════════════╝\n");
         
         if self.duplicate_groups > 0 {
-            println!("📋 重複組詳情 (前 10 組):\n");
+            out!("📋 重複組詳情 (前 10 組):\n");
             
             for (i, dup) in self.duplicates.iter().take(10).enumerate() {
-                println!("  組 {}: {} 張重複", i + 1, dup.files.len());
-                println!("  Hash: {}...", &dup.content_hash[..16]);
+                match &dup.name {
+                    Some(name) => out!("  組 {}: 「{}」 {} 張重複", i + 1, name, dup.files.len()),
+                    None => out!("  組 {}: {} 張重複", i + 1, dup.files.len()),
+                }
+                out!("  Hash: {}...", &dup.content_hash[..16]);
                 for (j, file) in dup.files.iter().enumerate() {
                     let marker = if j == 0 { "✅ 保留" } else { "❌ 重複" };
-                    println!("    {} {}", marker, file);
+                    out!("    {} {}", marker, file);
                 }
-                println!();
+                out!();
             }
             
             if self.duplicates.len() > 10 {
-                println!("  ... 還有 {} 組重複\n", self.duplicates.len() - 10);
+                out!("  ... 還有 {} 組重複\n", self.duplicates.len() - 10);
             }
         } else {
-            println!("🎉 沒有發現重複圖片！\n");
+            out!("🎉 沒有發現重複圖片！\n");
+        }
+    }
+}
+
+/// 跨目錄重複組中的一個檔案
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrossFile {
+    pub dir: String,
+    pub filename: String,
+}
+
+/// 跨目錄的重複組（第一個檔案保留）
+#[derive(Debug, Clone, Serialize)]
+pub struct CrossDuplicateRecord {
+    pub content_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub files: Vec<CrossFile>,
+}
+
+/// 跨資料目錄去重結果
+#[derive(Debug, Serialize)]
+pub struct CrossDedupResult {
+    /// 依保留優先順序
+    pub dirs: Vec<String>,
+    pub total_images: usize,
+    pub groups: Vec<CrossDuplicateRecord>,
+}
+
+impl CrossDedupResult {
+    /// 重複報告（寫在優先目錄）
+    pub const REPORT_FILE: &str = "duplicates_dirs.json";
+    
+    /// `dir` 中要刪除的檔名 -> 保留的檔名（保留的檔案在其他目錄時為 None）
+    pub fn deletions(&self, dir: &str) -> HashMap<String, Option<String>> {
+        let mut deletions = HashMap::new();
+        for group in &self.groups {
+            let Some((kept, rest)) = group.files.split_first() else {
+                continue;
+            };
+            for file in rest.iter().filter(|f| f.dir == dir && **f != *kept) {
+                let remap = (kept.dir == dir).then(|| kept.filename.clone());
+                deletions.insert(file.filename.clone(), remap);
+            }
         }
+        deletions
     }
-}
\ No newline at end of file
+    
+    /// 要刪除的檔案數
+    pub fn duplicate_images(&self) -> usize {
+        self.groups.iter().map(|g| g.files.len() - 1).sum()
+    }
+    
+    /// 寫入優先目錄的 `duplicates_dirs.json`，回傳路徑
+    pub fn save(&self) -> Result<String> {
+        let path = file_manager::join_path(&self.dirs[0], Self::REPORT_FILE);
+        let temp_path = format!("{}.tmp", path);
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp_path, &path).with_context(|| format!("無法寫入 {}", path))?;
+        Ok(path)
+    }
+    
+    /// 顯示報告
+    pub fn print_report(&self) {
+        out!("\n╔══════════════════════════════════╗");
+        out!("║   🔍 跨目錄重複圖片分析報告     ║");
+        out!("╠══════════════════════════════════╣");
+        out!("║ 資料目錄:   {:>18} ║", self.dirs.len());
+        out!("║ 總圖片數:   {:>18} ║", self.total_images);
+        out!("║ 跨目錄重複組: {:>16} ║", self.groups.len());
+        out!("║ 重複圖片:   {:>18} ║", self.duplicate_images());
+        out!("╚══════════════════════════════════╝\n");
+        
+        out!("📂 保留優先順序:");
+        for (i, dir) in self.dirs.iter().enumerate() {
+            let count: usize = self
+                .groups
+                .iter()
+                .map(|g| g.files.iter().skip(1).filter(|f| f.dir == *dir).count())
+                .sum();
+            out!("  {}. {}（刪除 {} 張）", i + 1, dir, count);
+        }
+        out!();
+        
+        if self.groups.is_empty() {
+            out!("🎉 沒有發現跨目錄的重複圖片！\n");
+            return;
+        }
+        
+        out!("📋 重複組詳情 (前 10 組):\n");
+        for (i, group) in self.groups.iter().take(10).enumerate() {
+            match &group.name {
+                Some(name) => out!("  組 {}: 「{}」 {} 張重複", i + 1, name, group.files.len()),
+                None => out!("  組 {}: {} 張重複", i + 1, group.files.len()),
+            }
+            out!("  Hash: {}...", &group.content_hash[..group.content_hash.len().min(16)]);
+            for (j, file) in group.files.iter().enumerate() {
+                let marker = if j == 0 { "✅ 保留" } else { "❌ 重複" };
+                out!("    {} {}: {}", marker, file.dir, file.filename);
+            }
+            out!();
+        }
+        if self.groups.len() > 10 {
+            out!("  ... 還有 {} 組重複\n", self.groups.len() - 10);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    
+    fn metadata(filename: &str, description: &str) -> ImageMetadata {
+        ImageMetadata {
+            url: String::new(),
+            description: description.to_string(),
+            ..ImageMetadata::for_test(filename, "abc")
+        }
+    }
+    
+    fn result(filename: &str, best_guess: Option<&str>, keywords: &[&str]) -> ReverseSearchResult {
+        ReverseSearchResult {
+            filename: filename.to_string(),
+            service: "bing".to_string(),
+            suggested_title: None,
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            related_sites: vec![],
+            best_guess: best_guess.map(str::to_string),
+            similarity: None,
+            searched_at: Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn test_group_name() {
+        let members = vec![metadata("a.jpg", "迷因"), metadata("b.jpg", "狗"), metadata("c.jpg", "狗")];
+        assert_eq!(group_name(&members, &HashMap::new()).as_deref(), Some("狗"));
+        
+        let results = vec![
+            result("a.jpg", Some("Doge"), &["shiba", "12 matches"]),
+            result("b.jpg", None, &["doge", "Shiba", "shiba"]),
+            result("c.jpg", None, &["shiba", "12 matches"]),
+        ];
+        let mut by_file: HashMap<&str, Vec<&ReverseSearchResult>> = HashMap::new();
+        for r in &results {
+            by_file.entry(r.filename.as_str()).or_default().push(r);
+        }
+        
+        // Doge: 3 + 1，shiba: 1 + 1 + 1
+        assert_eq!(group_name(&members, &by_file).as_deref(), Some("Doge"));
+        assert!(is_match_count("12 matches") && !is_match_count("no matches"));
+    }
+    
+    #[test]
+    fn test_analyze_dirs() {
+        let root = std::env::temp_dir().join(format!("meme-dedup-dirs-{}", std::process::id()));
+        let dirs = [root.join("memes_tw"), root.join("imgflip")];
+        let mut analyzers = Vec::new();
+        for (dir, files) in dirs.iter().zip([vec![("a.jpg", "h1"), ("b.jpg", "h2"), ("c.jpg", "h1")], vec![("x.jpg", "h3"), ("y.jpg", "h1"), ("z.jpg", "h2")]]) {
+            let file_manager = crate::file_manager::FileManager::new(dir.to_str().unwrap()).unwrap();
+            for (filename, hash) in files {
+                let mut m = metadata(filename, "");
+                m.content_hash = hash.to_string();
+                file_manager.append_metadata(&m).unwrap();
+                file_manager.save_image(filename, b"x").unwrap();
+            }
+            analyzers.push(DedupAnalyzer::new(dir.to_str().unwrap()).unwrap());
+        }
+        
+        let result = DedupAnalyzer::analyze_dirs(&analyzers).unwrap();
+        assert_eq!(result.total_images, 6);
+        assert_eq!(result.groups.len(), 2);
+        assert_eq!(result.duplicate_images(), 3);
+        
+        // 保留第一個目錄的檔案；同目錄的副本改指向保留的檔案
+        let first = dirs[0].to_str().unwrap();
+        let second = dirs[1].to_str().unwrap();
+        assert_eq!(result.deletions(first), HashMap::from([("c.jpg".to_string(), Some("a.jpg".to_string()))]));
+        let mut other: Vec<_> = result.deletions(second).into_iter().collect();
+        other.sort();
+        assert_eq!(other, vec![("y.jpg".to_string(), None), ("z.jpg".to_string(), None)]);
+        
+        let removal = analyzers[1].remove_files(&result.deletions(second)).unwrap();
+        assert_eq!(removal.removed, 2);
+        let kept: Vec<String> = analyzers[1].context.store().load_all_metadata().unwrap().into_iter().map(|m| m.filename).collect();
+        assert_eq!(kept, vec!["x.jpg"]);
+        assert!(DedupAnalyzer::analyze_dirs(&analyzers).unwrap().groups.is_empty());
+        
+        fs::remove_dir_all(&root).ok();
+    }
+}
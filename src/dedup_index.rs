@@ -0,0 +1,265 @@
+use crate::integrity;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// JSONL 後端的雜湊索引（metadata.jsonl 每一行的 hash、檔名與位置）
+pub const INDEX_FILE: &str = "dedup_index.db";
+
+/// 指紋涵蓋的位置前位元組數（判斷 metadata.jsonl 是否被改寫）
+const FINGERPRINT_BYTES: u64 = 256;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        offset       INTEGER PRIMARY KEY,
+        content_hash TEXT NOT NULL,
+        filename     TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_entries_hash ON entries(content_hash);
+
+    CREATE TABLE IF NOT EXISTS state (
+        key   TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// 去重需要的數量與重複組成員
+#[derive(Debug, Default)]
+pub struct HashSummary {
+    pub total: usize,
+    pub unique: usize,
+    /// (hash, 成員 metadata)，組內依 metadata 的順序
+    pub groups: Vec<(String, Vec<ImageMetadata>)>,
+}
+
+/// 只解析索引需要的欄位
+#[derive(Deserialize)]
+struct IndexLine {
+    filename: String,
+    content_hash: String,
+}
+
+/// metadata.jsonl 的持久雜湊索引
+///
+/// metadata.jsonl 只會追加，索引記下讀到的位置，每次 `sync` 只讀新增的行；
+/// 位置前的內容與記錄的指紋不同時（檔案被改寫過）整個重建。
+/// 去重時以 SQL 找出重複的雜湊，只從 metadata.jsonl 讀回重複組成員那幾行。
+pub struct DedupIndex {
+    conn: Connection,
+    metadata_path: PathBuf,
+}
+
+impl DedupIndex {
+    pub fn open(data_dir: &str) -> Result<Self> {
+        let path = Path::new(data_dir).join(INDEX_FILE);
+        let conn = Connection::open(&path)
+            .with_context(|| format!("無法開啟去重索引: {}", path.display()))?;
+        conn.execute_batch(SCHEMA).context("無法建立去重索引資料表")?;
+
+        Ok(Self {
+            conn,
+            metadata_path: Path::new(data_dir).join("metadata.jsonl"),
+        })
+    }
+
+    fn state(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row("SELECT value FROM state WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// 已索引的位置（metadata.jsonl 的位元組數）
+    pub fn watermark(&self) -> Result<u64> {
+        Ok(self.state("offset")?.and_then(|v| v.parse().ok()).unwrap_or(0))
+    }
+
+    /// 讀取上次之後新增的行，回傳新增筆數（metadata.jsonl 被改寫時重建全部）
+    pub fn sync(&mut self) -> Result<usize> {
+        let len = fs::metadata(&self.metadata_path).map(|m| m.len()).unwrap_or(0);
+        let mut offset = self.watermark()?;
+        let valid = offset <= len
+            && self.state("fingerprint")?.as_deref() == Some(fingerprint(&self.metadata_path, offset)?.as_str());
+        if !valid {
+            offset = 0;
+        }
+        if valid && offset == len {
+            return Ok(0);
+        }
+
+        let tx = self.conn.transaction()?;
+        if offset == 0 {
+            tx.execute("DELETE FROM entries", [])?;
+        }
+
+        let mut added = 0;
+        if len > 0 {
+            let mut file = File::open(&self.metadata_path).context("無法開啟 metadata.jsonl")?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut reader = BufReader::new(file);
+            let mut line = String::new();
+            loop {
+                line.clear();
+                let read = reader.read_line(&mut line).context("讀取 metadata.jsonl 失敗")?;
+                // 寫到一半的最後一行留到下次
+                if read == 0 || !line.ends_with('\n') {
+                    break;
+                }
+                if let Ok(entry) = serde_json::from_str::<IndexLine>(line.trim()) {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO entries (offset, content_hash, filename) VALUES (?1, ?2, ?3)",
+                        params![offset as i64, entry.content_hash, entry.filename],
+                    )?;
+                    added += 1;
+                }
+                offset += read as u64;
+            }
+        }
+
+        for (key, value) in [("offset", offset.to_string()), ("fingerprint", fingerprint(&self.metadata_path, offset)?)] {
+            tx.execute("INSERT OR REPLACE INTO state (key, value) VALUES (?1, ?2)", params![key, value])?;
+        }
+        tx.commit().context("無法更新去重索引")?;
+        Ok(added)
+    }
+
+    /// 清空後重新索引全部
+    pub fn rebuild(&mut self) -> Result<usize> {
+        self.conn.execute("DELETE FROM state", [])?;
+        self.sync()
+    }
+
+    /// 總數、不重複雜湊數與重複組（成員從 metadata.jsonl 依位置讀回）
+    pub fn summary(&self) -> Result<HashSummary> {
+        let (total, unique) = self.conn.query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT content_hash) FROM entries",
+            [],
+            |row| Ok((row.get::<_, i64>(0)? as usize, row.get::<_, i64>(1)? as usize)),
+        )?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT offset, content_hash, filename FROM entries
+             WHERE content_hash IN (SELECT content_hash FROM entries GROUP BY content_hash HAVING COUNT(*) > 1)
+             ORDER BY content_hash, offset",
+        )?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut groups: Vec<(String, Vec<ImageMetadata>)> = Vec::new();
+        if !rows.is_empty() {
+            let mut reader = BufReader::new(File::open(&self.metadata_path).context("無法開啟 metadata.jsonl")?);
+            let mut line = String::new();
+            for (offset, hash, filename) in rows {
+                reader.seek(SeekFrom::Start(offset))?;
+                line.clear();
+                reader.read_line(&mut line)?;
+                let metadata: ImageMetadata = serde_json::from_str(line.trim())
+                    .with_context(|| format!("去重索引與 metadata.jsonl 不一致（位置 {}）", offset))?;
+                if metadata.filename != filename || metadata.content_hash != hash {
+                    anyhow::bail!("去重索引與 metadata.jsonl 不一致（{}）", filename);
+                }
+
+                match groups.last_mut() {
+                    Some((last, members)) if *last == hash => members.push(metadata),
+                    _ => groups.push((hash, vec![metadata])),
+                }
+            }
+        }
+
+        Ok(HashSummary { total, unique, groups })
+    }
+}
+
+/// `offset` 之前最後一段內容的雜湊
+fn fingerprint(path: &Path, offset: u64) -> Result<String> {
+    if offset == 0 {
+        return Ok(String::new());
+    }
+    let start = offset.saturating_sub(FINGERPRINT_BYTES);
+    let mut file = File::open(path).context("無法開啟 metadata.jsonl")?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut buffer = Vec::with_capacity((offset - start) as usize);
+    file.take(offset - start).read_to_end(&mut buffer)?;
+    Ok(integrity::sha256_hex(&buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::FileManager;
+    use serde_json::json;
+    use std::io::Write;
+
+    fn line(filename: &str, hash: &str) -> String {
+        json!({
+            "filename": filename,
+            "description": "",
+            "url": format!("https://example.com/{}", filename),
+            "content_hash": hash,
+            "page_number": 1,
+            "downloaded_at": "2024-01-01T00:00:00Z",
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_incremental_sync() {
+        let dir = std::env::temp_dir().join(format!("meme-dedup-index-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let data_dir = dir.to_str().unwrap();
+        let metadata_path = dir.join("metadata.jsonl");
+        let append = |text: &str| {
+            let mut file = fs::OpenOptions::new().create(true).append(true).open(&metadata_path).unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+        };
+
+        append(&format!("{}\n{}\n{}\n", line("a.jpg", "h1"), line("b.jpg", "h2"), line("c.jpg", "h1")));
+        let mut index = DedupIndex::open(data_dir).unwrap();
+        assert_eq!(index.sync().unwrap(), 3);
+        assert_eq!(index.sync().unwrap(), 0);
+
+        let summary = index.summary().unwrap();
+        assert_eq!((summary.total, summary.unique), (3, 2));
+        assert_eq!(summary.groups.len(), 1);
+        let files: Vec<&str> = summary.groups[0].1.iter().map(|m| m.filename.as_str()).collect();
+        assert_eq!(files, vec!["a.jpg", "c.jpg"]);
+
+        // 只讀新增的行；還沒寫完的最後一行留到下次
+        let partial = line("e.jpg", "h2");
+        append(&format!("{}\n{}", line("d.jpg", "h3"), &partial[..10]));
+        let mut index = DedupIndex::open(data_dir).unwrap();
+        assert_eq!(index.sync().unwrap(), 1);
+        append(&format!("{}\n", &partial[10..]));
+        assert_eq!(index.sync().unwrap(), 1);
+        let summary = index.summary().unwrap();
+        assert_eq!((summary.total, summary.unique, summary.groups.len()), (5, 3, 2));
+
+        // 改寫 metadata.jsonl 後整個重建
+        drop(index);
+        let file_manager = FileManager::new(data_dir).unwrap();
+        let kept: Vec<ImageMetadata> = file_manager
+            .load_all_metadata()
+            .unwrap()
+            .into_iter()
+            .filter(|m| m.filename != "c.jpg")
+            .collect();
+        file_manager.rewrite_metadata(&kept).unwrap();
+        assert!(!dir.join(INDEX_FILE).exists());
+        let mut index = DedupIndex::open(data_dir).unwrap();
+        assert_eq!(index.sync().unwrap(), 4);
+        assert_eq!(index.summary().unwrap().groups.len(), 1);
+
+        // 其他程式直接改寫時，以位置前的指紋發現
+        fs::write(&metadata_path, format!("{}\n{}\n{}\n{}\n{}\n", line("x.jpg", "h9"), line("y.jpg", "h9"), line("z.jpg", "h8"), line("w.jpg", "h7"), line("v.jpg", "h6"))).unwrap();
+        assert_eq!(index.sync().unwrap(), 5);
+        let summary = index.summary().unwrap();
+        assert_eq!(summary.groups[0].0, "h9");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,208 @@
+use crate::file_manager::FileManager;
+use crate::media;
+use crate::thumbnail;
+use crate::types::{DuplicateRecord, ImageMetadata};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// 重複組的視覺化報告（放在資料目錄，圖片以相對路徑引用）
+pub const REPORT_FILE: &str = "dedup_report.html";
+
+const STYLE: &str = "body{font-family:sans-serif;margin:1.5em;background:#fafafa}\
+h2{font-size:1.05em;margin:0 0 .4em}\
+.group{background:#fff;border:1px solid #ddd;border-radius:6px;padding:1em;margin-bottom:1.2em}\
+.hash{font-family:monospace;font-size:.85em;color:#666;word-break:break-all}\
+.files{display:flex;flex-wrap:wrap;gap:1em;margin-top:.6em}\
+figure{margin:0;width:220px;border:3px solid #e57373;border-radius:4px;padding:4px}\
+figure.keep{border-color:#66bb6a}\
+figure img{max-width:100%;max-height:200px;display:block;margin:auto}\
+figcaption{font-size:.8em;word-break:break-all;margin-top:.3em}\
+.badge{font-weight:bold}.keep .badge{color:#2e7d32}.remove .badge{color:#c62828}\
+.missing{height:120px;display:flex;align-items:center;justify-content:center;color:#999}";
+
+/// 一個檔案的顯示資訊
+struct FileCard<'a> {
+    filename: &'a str,
+    keep: bool,
+    /// 顯示的圖片（相對於資料目錄，優先用縮圖；本地沒有圖片時為 None）
+    src: Option<String>,
+    /// 點選後開啟的圖片（原圖，不在本地時為縮圖）
+    href: Option<String>,
+    dimensions: Option<(u32, u32)>,
+    size: Option<u64>,
+    url: Option<&'a str>,
+}
+
+/// 產生重複組的 HTML：每組並排顯示縮圖、完整雜湊、解析度、大小，並標示保留的檔案（每組第一個）
+pub fn render_html(file_manager: &FileManager, groups: &[DuplicateRecord], metadata: &[ImageMetadata]) -> String {
+    let by_name: HashMap<&str, &ImageMetadata> = metadata.iter().map(|m| (m.filename.as_str(), m)).collect();
+    let removable: usize = groups.iter().map(|g| g.files.len().saturating_sub(1)).sum();
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"zh-Hant\">\n<head>\n<meta charset=\"utf-8\">\n<title>重複圖片報告</title>\n<style>{}</style>\n</head>\n<body>\n\
+         <h1>重複圖片報告</h1>\n<p>{} 組，{} 張會被刪除（綠框保留、紅框刪除）</p>\n",
+        STYLE,
+        groups.len(),
+        removable,
+    );
+
+    for (index, group) in groups.iter().enumerate() {
+        let title = group.name.as_deref().unwrap_or("未命名");
+        let _ = write!(
+            html,
+            "<section class=\"group\">\n<h2>#{} {}（{} 個檔案）</h2>\n<div class=\"hash\">{}</div>\n<div class=\"files\">\n",
+            index + 1,
+            escape_html(title),
+            group.files.len(),
+            escape_html(&group.content_hash),
+        );
+
+        for (i, filename) in group.files.iter().enumerate() {
+            let card = file_card(file_manager, filename, i == 0, by_name.get(filename.as_str()).copied());
+            write_card(&mut html, &card);
+        }
+        html.push_str("</div>\n</section>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// 寫入 `dedup_report.html`，回傳路徑
+pub fn write_report(file_manager: &FileManager, groups: &[DuplicateRecord], metadata: &[ImageMetadata]) -> Result<String> {
+    let path = format!("{}/{}", file_manager.root_dir(), REPORT_FILE);
+    let temp_path = format!("{}.tmp", path);
+    fs::write(&temp_path, render_html(file_manager, groups, metadata))?;
+    fs::rename(&temp_path, &path).context("無法寫入 dedup_report.html")?;
+    Ok(path)
+}
+
+fn file_card<'a>(
+    file_manager: &FileManager,
+    filename: &'a str,
+    keep: bool,
+    meta: Option<&'a ImageMetadata>,
+) -> FileCard<'a> {
+    let image_path = file_manager.get_image_path(filename);
+    let thumbnail = thumbnail::relative_url(file_manager, filename);
+
+    // 有縮圖時顯示縮圖、連到原圖（原圖已移到冷儲存時只有縮圖）
+    let (href, info) = if Path::new(&image_path).exists() {
+        (Some(format!("images/{}", urlencoding::encode(filename))), fs::metadata(&image_path).ok())
+    } else {
+        (thumbnail.clone(), None)
+    };
+    let src = thumbnail.or_else(|| href.clone());
+
+    let dimensions = meta
+        .and_then(|m| m.width.zip(m.height))
+        .or_else(|| info.as_ref().and_then(|_| media::probe_file_dimensions(&image_path)));
+    let size = meta.and_then(|m| m.file_size).or_else(|| info.map(|i| i.len()));
+
+    FileCard {
+        filename,
+        keep,
+        src,
+        href,
+        dimensions,
+        size,
+        url: meta.map(|m| m.url.as_str()).filter(|url| !url.is_empty()),
+    }
+}
+
+fn write_card(html: &mut String, card: &FileCard) {
+    let (class, badge) = if card.keep { ("keep", "保留") } else { ("remove", "刪除") };
+    let _ = writeln!(html, "<figure class=\"{}\">", class);
+    match (&card.src, &card.href) {
+        (Some(src), Some(href)) => {
+            let _ = writeln!(
+                html,
+                "<a href=\"{}\" target=\"_blank\"><img src=\"{}\" loading=\"lazy\" alt=\"{}\"></a>",
+                escape_html(href),
+                escape_html(src),
+                escape_html(card.filename),
+            );
+        }
+        _ => html.push_str("<div class=\"missing\">找不到圖片</div>\n"),
+    }
+
+    let dimensions = card
+        .dimensions
+        .map(|(w, h)| format!("{}×{}", w, h))
+        .unwrap_or_else(|| "?".to_string());
+    let size = card
+        .size
+        .map(|bytes| format!("{:.1} KB", bytes as f64 / 1024.0))
+        .unwrap_or_else(|| "?".to_string());
+    let _ = write!(
+        html,
+        "<figcaption><span class=\"badge\">{}</span> {}<br>{} · {}",
+        badge,
+        escape_html(card.filename),
+        dimensions,
+        size,
+    );
+    if let Some(url) = card.url {
+        let _ = write!(html, "<br><a href=\"{0}\" target=\"_blank\">來源</a>", escape_html(url));
+    }
+    html.push_str("</figcaption>\n</figure>\n");
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_html() {
+        let dir = std::env::temp_dir().join(format!("meme-dedup-report-{}", std::process::id()));
+        fs::create_dir_all(dir.join("images")).unwrap();
+        let file_manager = FileManager::new(dir.to_str().unwrap()).unwrap();
+        fs::write(dir.join("images/a b.jpg"), "x").unwrap();
+
+        let metadata: Vec<ImageMetadata> = serde_json::from_value(json!([{
+            "filename": "a b.jpg",
+            "description": "",
+            "url": "https://example.com/a.jpg",
+            "content_hash": "0123456789abcdef0123",
+            "page_number": 1,
+            "downloaded_at": "2024-01-01T00:00:00Z",
+            "width": 640,
+            "height": 480,
+        }]))
+        .unwrap();
+        let groups = vec![DuplicateRecord {
+            content_hash: "0123456789abcdef0123".to_string(),
+            files: vec!["a b.jpg".to_string(), "<gone>.jpg".to_string()],
+            name: Some("Doge & friends".to_string()),
+        }];
+
+        let html = render_html(&file_manager, &groups, &metadata);
+        assert!(html.contains("1 組，1 張會被刪除"));
+        assert!(html.contains("Doge &amp; friends"));
+        assert!(html.contains("0123456789abcdef0123"));
+        assert!(html.contains("<figure class=\"keep\">\n<a href=\"images/a%20b.jpg\""));
+        assert!(html.contains("640×480 · 0.0 KB"));
+        assert!(html.contains("<figure class=\"remove\">\n<div class=\"missing\">"));
+        assert!(html.contains("&lt;gone&gt;.jpg"));
+
+        // 有縮圖時顯示縮圖，仍連到原圖
+        file_manager.save_thumbnail("a b.jpg", b"x").unwrap();
+        let html = render_html(&file_manager, &groups, &metadata);
+        assert!(html.contains("<a href=\"images/a%20b.jpg\" target=\"_blank\"><img src=\"thumbnails/a%20b.jpg.webp\""));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,274 @@
+use crate::reverse_search::ReverseSearchResult;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+/// 事件發佈介面（NATS subject / Kafka topic）
+#[async_trait::async_trait]
+pub trait EventPublisher: Send + Sync {
+    /// 發佈一則訊息
+    async fn publish(&self, subject: &str, payload: &[u8]) -> Result<()>;
+}
+
+/// 依 URL 建立發佈者
+///
+/// - `nats://host:4222`：NATS（原生文字協定）
+/// - `kafka+http://host:8082`：透過 Kafka REST Proxy 發佈到 Kafka
+pub fn connect(url: &str) -> Result<Arc<dyn EventPublisher>> {
+    if let Some(addr) = url.strip_prefix("nats://") {
+        Ok(Arc::new(NatsPublisher::new(addr)))
+    } else if let Some(rest) = url.strip_prefix("kafka+") {
+        Ok(Arc::new(KafkaRestPublisher::new(rest)?))
+    } else {
+        anyhow::bail!("不支援的事件 URL: {}（可用: nats://host:port, kafka+http://host:port）", url)
+    }
+}
+
+/// 事件內容
+#[derive(Debug, Serialize)]
+struct Event<'a, T: Serialize> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    data: &'a T,
+}
+
+/// 事件出口：把領域事件轉成訊息並發佈
+#[derive(Clone)]
+pub struct EventSink {
+    publisher: Arc<dyn EventPublisher>,
+    prefix: String,
+}
+
+impl EventSink {
+    /// `prefix` 為 subject/topic 前綴，例如 "memes" 會發佈到 `memes.images` 與 `memes.search_results`
+    pub fn new(publisher: Arc<dyn EventPublisher>, prefix: &str) -> Self {
+        Self {
+            publisher,
+            prefix: prefix.to_string(),
+        }
+    }
+
+    /// 新圖片下載完成
+    pub async fn image_downloaded(&self, metadata: &ImageMetadata) -> Result<()> {
+        self.send("images", "image_downloaded", metadata).await
+    }
+
+    /// 反向搜尋完成
+    pub async fn search_completed(&self, result: &ReverseSearchResult) -> Result<()> {
+        self.send("search_results", "search_completed", result).await
+    }
+
+    async fn send<T: Serialize>(&self, suffix: &str, kind: &str, data: &T) -> Result<()> {
+        let subject = format!("{}.{}", self.prefix, suffix);
+        let payload = serde_json::to_vec(&Event { kind, data })?;
+        self.publisher.publish(&subject, &payload).await
+    }
+}
+
+/// NATS 發佈者（連線中斷時下次發佈自動重連）
+///
+/// 每次 PUB 後送 PING 並等到 PONG，確認伺服器收到；等待期間回應伺服器的 PING，
+/// 收到 `-ERR` 時回報錯誤（不會因為沒回 PONG 被伺服器斷線而默默遺失訊息）。
+pub struct NatsPublisher {
+    addr: String,
+    conn: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+/// 等待 PONG 的逾時
+const NATS_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl NatsPublisher {
+    pub fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.to_string(),
+            conn: Mutex::new(None),
+        }
+    }
+
+    async fn open(&self) -> Result<BufReader<TcpStream>> {
+        let stream = tokio::time::timeout(Duration::from_secs(10), TcpStream::connect(&self.addr))
+            .await
+            .context("連線 NATS 逾時")?
+            .with_context(|| format!("無法連線 NATS: {}", self.addr))?;
+
+        // 伺服器先送 INFO，再回 CONNECT
+        let mut reader = BufReader::new(stream);
+        let mut info = String::new();
+        reader.read_line(&mut info).await.context("讀取 NATS INFO 失敗")?;
+        if !info.starts_with("INFO") {
+            anyhow::bail!("非預期的 NATS 回應: {}", info.trim());
+        }
+
+        reader
+            .write_all(b"CONNECT {\"verbose\":false,\"pedantic\":false,\"name\":\"meme-data-crawler\"}\r\n")
+            .await
+            .context("送出 NATS CONNECT 失敗")?;
+
+        Ok(reader)
+    }
+
+    /// 送出訊息並等到 PONG，伺服器回 `-ERR` 時回傳錯誤訊息
+    async fn deliver(stream: &mut BufReader<TcpStream>, frame: &[u8]) -> std::io::Result<Option<String>> {
+        stream.write_all(frame).await?;
+        stream.write_all(b"PING\r\n").await?;
+
+        loop {
+            let mut line = String::new();
+            let read = tokio::time::timeout(NATS_ACK_TIMEOUT, stream.read_line(&mut line))
+                .await
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "等待 NATS PONG 逾時"))??;
+            if read == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+
+            match line.trim_end() {
+                "PONG" => return Ok(None),
+                "PING" => stream.write_all(b"PONG\r\n").await?,
+                reply if reply.starts_with("-ERR") => return Ok(Some(reply.to_string())),
+                // +OK、INFO 等
+                _ => {}
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventPublisher for NatsPublisher {
+    async fn publish(&self, subject: &str, payload: &[u8]) -> Result<()> {
+        let mut frame = format!("PUB {} {}\r\n", subject, payload.len()).into_bytes();
+        frame.extend_from_slice(payload);
+        frame.extend_from_slice(b"\r\n");
+
+        let mut conn = self.conn.lock().await;
+
+        // 最多重連一次
+        for attempt in 0..2 {
+            if conn.is_none() {
+                *conn = Some(self.open().await?);
+            }
+
+            if let Some(stream) = conn.as_mut() {
+                match Self::deliver(stream, &frame).await {
+                    Ok(None) => return Ok(()),
+                    Ok(Some(error)) => {
+                        // 大部分 -ERR 之後伺服器會關閉連線
+                        *conn = None;
+                        anyhow::bail!("NATS 發佈失敗: {}", error);
+                    }
+                    Err(e) if attempt == 0 => {
+                        eout!("⚠️  NATS 連線中斷，重新連線: {}", e);
+                        *conn = None;
+                    }
+                    Err(e) => return Err(e).context("NATS 發佈失敗"),
+                }
+            }
+        }
+
+        anyhow::bail!("NATS 發佈失敗")
+    }
+}
+
+/// Kafka REST Proxy 發佈者（POST /topics/<topic>）
+pub struct KafkaRestPublisher {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl KafkaRestPublisher {
+    pub fn new(base_url: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EventPublisher for KafkaRestPublisher {
+    async fn publish(&self, subject: &str, payload: &[u8]) -> Result<()> {
+        let value: serde_json::Value = serde_json::from_slice(payload)?;
+        let body = serde_json::json!({ "records": [{ "value": value }] });
+
+        let response = self.client
+            .post(format!("{}/topics/{}", self.base_url, subject))
+            .header(reqwest::header::CONTENT_TYPE, "application/vnd.kafka.json.v2+json")
+            .body(serde_json::to_vec(&body)?)
+            .send()
+            .await
+            .context("Kafka REST Proxy 請求失敗")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Kafka REST Proxy 回應錯誤: {}", response.status());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// 模擬 NATS 伺服器：先送 PING 給客戶端，回應 PING，`bad.` 開頭的 subject 回 -ERR
+    async fn serve_nats() -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            socket.write_all(b"INFO {\"server_id\":\"test\"}\r\n").await.unwrap();
+
+            let mut received = String::new();
+            let mut rejected = false;
+            loop {
+                let mut line = String::new();
+                if socket.read_line(&mut line).await.unwrap() == 0 {
+                    return received;
+                }
+                received.push_str(&line);
+                if line.starts_with("PUB ") {
+                    rejected = line.starts_with("PUB bad.");
+                    socket.write_all(b"PING\r\n").await.unwrap();
+                } else if line == "PING\r\n" {
+                    let reply: &[u8] = if rejected { b"-ERR 'Permissions Violation'\r\n" } else { b"PONG\r\n" };
+                    socket.write_all(reply).await.unwrap();
+                }
+            }
+        });
+
+        (addr, server)
+    }
+
+    #[tokio::test]
+    async fn test_nats_publish_frame() {
+        let (addr, server) = serve_nats().await;
+
+        let publisher = NatsPublisher::new(&addr);
+        publisher.publish("memes.images", b"{\"a\":1}").await.unwrap();
+        drop(publisher);
+
+        let received = server.await.unwrap();
+        assert!(received.starts_with("CONNECT {"));
+        assert!(received.ends_with("PUB memes.images 7\r\n{\"a\":1}\r\nPING\r\nPONG\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_nats_publish_error() {
+        let (addr, _server) = serve_nats().await;
+
+        let publisher = NatsPublisher::new(&addr);
+        let error = publisher.publish("bad.subject", b"{}").await.unwrap_err();
+        assert!(error.to_string().contains("Permissions Violation"));
+    }
+}
@@ -0,0 +1,808 @@
+use crate::context::DataContext;
+use crate::crawler::determinism::sample_score;
+use crate::rate_limit::host_of;
+use crate::reverse_search::ReverseSearchResult;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt32Array, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// 匯出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Parquet,
+    /// Hugging Face `imagefolder` 目錄（train/validation/test）
+    Hf,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            "hf" | "imagefolder" => Ok(Self::Hf),
+            other => anyhow::bail!("未知的匯出格式: {}（可用: csv, parquet, hf）", other),
+        }
+    }
+
+    /// 未指定 `--output` 時的輸出位置
+    pub fn default_output(&self, data_dir: &str) -> String {
+        match self {
+            Self::Csv => format!("{}/dataset.csv", data_dir),
+            Self::Parquet => format!("{}/dataset.parquet", data_dir),
+            Self::Hf => format!("{}/hf_dataset", data_dir),
+        }
+    }
+}
+
+/// 一張圖片的匯出資料（metadata 與所有服務的搜尋結果合併）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRow {
+    pub filename: String,
+    pub description: String,
+    pub url: String,
+    pub content_hash: String,
+    pub page_number: u32,
+    /// RFC 3339
+    pub downloaded_at: String,
+    pub source_site: String,
+    /// 授權（沒有時為空）
+    pub license: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub file_size: Option<u64>,
+    /// image、animated 或 video（未記錄時為空）
+    pub media_type: String,
+    /// 來源網站提供的標籤
+    pub tags: Vec<String>,
+    /// 有結果的搜尋服務
+    pub services: Vec<String>,
+    /// 各服務的關鍵字（去除重複，保留先後順序）
+    pub keywords: Vec<String>,
+    pub best_guesses: Vec<String>,
+    pub suggested_titles: Vec<String>,
+    /// 圖片上的文字（`ocr` 擷取，沒有時為空）
+    pub ocr_text: String,
+    /// 網站特有欄位（JSON 字串，沒有時為空）
+    pub extra: String,
+}
+
+/// 單張圖片在各服務的搜尋結果（去除重複，保留先後順序）
+#[derive(Debug, Clone, Default)]
+struct MergedResults {
+    services: Vec<String>,
+    keywords: Vec<String>,
+    best_guesses: Vec<String>,
+    suggested_titles: Vec<String>,
+}
+
+fn merge_results(results: &[ReverseSearchResult]) -> HashMap<&str, MergedResults> {
+    let mut by_file: HashMap<&str, MergedResults> = HashMap::new();
+
+    for result in results {
+        let merged = by_file.entry(result.filename.as_str()).or_default();
+        push_unique(&mut merged.services, &result.service);
+        for keyword in &result.keywords {
+            push_unique(&mut merged.keywords, keyword);
+        }
+        if let Some(guess) = &result.best_guess {
+            push_unique(&mut merged.best_guesses, guess);
+        }
+        if let Some(title) = &result.suggested_title {
+            push_unique(&mut merged.suggested_titles, title);
+        }
+    }
+
+    by_file
+}
+
+/// 依 metadata 順序合併搜尋結果，每張圖片一列
+pub fn build_rows(metadata: &[ImageMetadata], results: &[ReverseSearchResult]) -> Vec<ExportRow> {
+    let merged = merge_results(results);
+
+    metadata
+        .iter()
+        .map(|m| {
+            let results = merged.get(m.filename.as_str()).cloned().unwrap_or_default();
+
+            ExportRow {
+                filename: m.filename.clone(),
+                description: m.description.clone(),
+                url: m.url.clone(),
+                content_hash: m.content_hash.clone(),
+                page_number: m.page_number,
+                downloaded_at: m.downloaded_at.to_rfc3339(),
+                source_site: m.source_site.clone(),
+                license: m.license().unwrap_or_default().to_string(),
+                width: m.width,
+                height: m.height,
+                file_size: m.file_size,
+                media_type: m.media_type.map(|t| t.label().to_string()).unwrap_or_default(),
+                tags: m.tags.clone(),
+                services: results.services,
+                keywords: results.keywords,
+                best_guesses: results.best_guesses,
+                suggested_titles: results.suggested_titles,
+                ocr_text: m.ocr_text.clone().unwrap_or_default(),
+                extra: if m.extra.is_empty() {
+                    String::new()
+                } else {
+                    serde_json::Value::Object(m.extra.clone()).to_string()
+                },
+            }
+        })
+        .collect()
+}
+
+/// 把搜尋結果合併進 metadata 的 `keywords`/`suggested_title`，回傳合併後的列表與有結果的圖片數
+///
+/// 推測標題以 best guess 優先，其次是服務提供的標題；沒有搜尋結果的圖片保留原本的欄位。
+pub fn enrich(metadata: &[ImageMetadata], results: &[ReverseSearchResult]) -> (Vec<ImageMetadata>, usize) {
+    let merged = merge_results(results);
+    let mut enriched_count = 0;
+
+    let enriched = metadata
+        .iter()
+        .map(|m| {
+            let mut m = m.clone();
+            if let Some(results) = merged.get(m.filename.as_str()) {
+                for keyword in &results.keywords {
+                    push_unique(&mut m.keywords, keyword);
+                }
+                if let Some(title) = results.best_guesses.first().or(results.suggested_titles.first()) {
+                    m.suggested_title = Some(title.clone());
+                }
+                enriched_count += 1;
+            }
+            m
+        })
+        .collect();
+
+    (enriched, enriched_count)
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    let value = value.trim();
+    if !value.is_empty() && !list.iter().any(|v| v == value) {
+        list.push(value.to_string());
+    }
+}
+
+/// 寫出資料集（先寫暫存檔再改名）
+pub fn write(path: &str, format: ExportFormat, rows: &[ExportRow]) -> Result<()> {
+    let temp_path = format!("{}.tmp", path);
+
+    match format {
+        ExportFormat::Csv => write_csv(&temp_path, rows)?,
+        ExportFormat::Parquet => write_parquet(&temp_path, rows)?,
+        ExportFormat::Hf => anyhow::bail!("hf 格式輸出的是目錄，請使用 write_imagefolder"),
+    }
+
+    fs::rename(&temp_path, path).with_context(|| format!("無法更新 {}", path))?;
+    Ok(())
+}
+
+/// train/validation/test 的比例與分配用的種子
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitConfig {
+    pub train: f64,
+    pub validation: f64,
+    pub test: f64,
+    pub seed: u64,
+}
+
+impl Default for SplitConfig {
+    fn default() -> Self {
+        Self {
+            train: 0.8,
+            validation: 0.1,
+            test: 0.1,
+            seed: 0,
+        }
+    }
+}
+
+impl SplitConfig {
+    /// 解析 `0.8,0.1,0.1`（或 `8,1,1`，會自動正規化；省略 test 時為 0）
+    pub fn parse(spec: &str, seed: u64) -> Result<Self> {
+        let parts: Vec<f64> = spec
+            .split(',')
+            .map(|part| part.trim().parse::<f64>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| anyhow::anyhow!("比例格式錯誤: {}（例如 0.8,0.1,0.1）", spec))?;
+
+        if parts.is_empty() || parts.len() > 3 || parts.iter().any(|p| *p < 0.0) {
+            anyhow::bail!("比例格式錯誤: {}（例如 0.8,0.1,0.1）", spec);
+        }
+        let total: f64 = parts.iter().sum();
+        if total <= 0.0 {
+            anyhow::bail!("比例總和必須大於 0: {}", spec);
+        }
+
+        let part = |i: usize| parts.get(i).copied().unwrap_or(0.0) / total;
+        Ok(Self {
+            train: part(0),
+            validation: part(1),
+            test: part(2),
+            seed,
+        })
+    }
+
+    /// 依內容雜湊分配：同一張圖的重複檔案一定在同一個 split，資料集增加時既有的分配不變
+    pub fn assign(&self, content_hash: &str) -> &'static str {
+        let score = sample_score(self.seed, content_hash);
+        if score < self.train {
+            "train"
+        } else if score < self.train + self.validation {
+            "validation"
+        } else {
+            "test"
+        }
+    }
+}
+
+/// imagefolder 匯出結果
+#[derive(Debug, Default)]
+pub struct ImagefolderSummary {
+    /// 各 split 的圖片數
+    pub splits: Vec<(&'static str, usize)>,
+    /// 找不到檔案而略過的圖片數
+    pub missing: usize,
+}
+
+/// 匯出成 `datasets.load_dataset("imagefolder", data_dir=...)` 可直接讀取的目錄
+///
+/// 每個 split 一個子目錄，放圖片與 `metadata.jsonl`（`file_name` 欄位對應圖片，其餘欄位成為 features）。
+/// 圖片優先建立 hard link，不同檔案系統時才複製；已移到冷儲存的原圖先取回。
+pub fn write_imagefolder(
+    context: &DataContext,
+    output_dir: &str,
+    rows: &[ExportRow],
+    split: &SplitConfig,
+) -> Result<ImagefolderSummary> {
+    const SPLITS: [&str; 3] = ["train", "validation", "test"];
+
+    // 清掉上次的輸出，避免殘留已刪除的圖片
+    for name in SPLITS {
+        let dir = format!("{}/{}", output_dir, name);
+        if Path::new(&dir).exists() {
+            fs::remove_dir_all(&dir).with_context(|| format!("無法清除 {}", dir))?;
+        }
+    }
+
+    let mut writers: HashMap<&str, (BufWriter<File>, usize)> = HashMap::new();
+    let mut summary = ImagefolderSummary::default();
+
+    for row in rows {
+        let source = context.original_path(&row.filename)?;
+        if !Path::new(&source).exists() {
+            summary.missing += 1;
+            continue;
+        }
+
+        let name = split.assign(&row.content_hash);
+        let dir = format!("{}/{}", output_dir, name);
+
+        if !writers.contains_key(name) {
+            fs::create_dir_all(&dir).with_context(|| format!("無法建立 {}", dir))?;
+            let file = File::create(format!("{}/metadata.jsonl.tmp", dir))
+                .context("無法建立 metadata.jsonl")?;
+            writers.insert(name, (BufWriter::new(file), 0));
+        }
+
+        let target = format!("{}/{}", dir, row.filename);
+        if fs::hard_link(&source, &target).is_err() {
+            fs::copy(&source, &target).with_context(|| format!("無法複製 {}", row.filename))?;
+        }
+
+        let mut record = serde_json::to_value(row)?;
+        record["file_name"] = serde_json::Value::String(row.filename.clone());
+
+        let (writer, count) = writers.get_mut(name).unwrap();
+        serde_json::to_writer(&mut *writer, &record)?;
+        writeln!(writer)?;
+        *count += 1;
+    }
+
+    for name in SPLITS {
+        if let Some((mut writer, count)) = writers.remove(name) {
+            writer.flush().context("無法寫入 metadata.jsonl")?;
+            let dir = format!("{}/{}", output_dir, name);
+            fs::rename(format!("{}/metadata.jsonl.tmp", dir), format!("{}/metadata.jsonl", dir))
+                .context("無法更新 metadata.jsonl")?;
+            summary.splits.push((name, count));
+        }
+    }
+
+    Ok(summary)
+}
+
+/// 單張圖片的來源標示（attribution.jsonl 的一列）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttributionRecord {
+    pub filename: String,
+    /// 來源網站（metadata 沒有記錄時取原始網址的主機名稱）
+    pub source_site: String,
+    /// 原始網址
+    pub url: String,
+    /// 爬取日期（RFC 3339）
+    pub crawled_at: String,
+    /// 解析到的授權（metadata 的 `license`）
+    pub license: Option<String>,
+}
+
+/// 依 metadata 順序建立每張圖片的來源標示
+pub fn build_attribution(metadata: &[ImageMetadata]) -> Vec<AttributionRecord> {
+    metadata
+        .iter()
+        .map(|m| AttributionRecord {
+            filename: m.filename.clone(),
+            source_site: if m.source_site.is_empty() {
+                host_of(&m.url)
+            } else {
+                m.source_site.clone()
+            },
+            url: m.url.clone(),
+            crawled_at: m.downloaded_at.to_rfc3339(),
+            license: m.license().map(str::to_string),
+        })
+        .collect()
+}
+
+/// 依授權篩選匯出的圖片（`export --license cc-by,cc0`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LicenseFilter {
+    /// 正規化後的授權代號（見 `license_id`）
+    allowed: Vec<String>,
+    /// `any`：只要有授權資訊
+    any: bool,
+}
+
+impl LicenseFilter {
+    /// 逗號分隔的授權代號，例如 `cc-by`、`cc-by-sa-4.0`、`cc0`、`public-domain`、`any`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let allowed: Vec<String> = spec.split(',').map(license_id).filter(|id| !id.is_empty()).collect();
+        if allowed.is_empty() {
+            anyhow::bail!("--license 需要授權代號，例如 cc-by,cc0");
+        }
+
+        Ok(Self {
+            any: allowed.iter().any(|id| id == "any"),
+            allowed,
+        })
+    }
+
+    /// 不寫版本時符合任何版本（`cc-by` 符合 `CC BY 4.0`，但不符合 `CC BY-SA 4.0`）
+    pub fn matches(&self, license: Option<&str>) -> bool {
+        let Some(license) = license else {
+            return false;
+        };
+        if self.any {
+            return true;
+        }
+
+        let id = license_id(license);
+        self.allowed.iter().any(|allowed| {
+            id == *allowed
+                || id
+                    .strip_prefix(allowed.as_str())
+                    .and_then(|rest| rest.strip_prefix('-'))
+                    .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+        })
+    }
+}
+
+/// 把授權文字或網址正規化成代號
+///
+/// `CC BY-SA 4.0`、`https://creativecommons.org/licenses/by-sa/4.0/` -> `cc-by-sa-4.0`，
+/// `CC0 1.0`、`.../publicdomain/zero/1.0/` -> `cc0-1.0`，`Public Domain` -> `public-domain`。
+pub fn license_id(text: &str) -> String {
+    let text = text.trim().to_lowercase();
+
+    if let Some((_, path)) = text.split_once("creativecommons.org/") {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        return match segments.as_slice() {
+            ["licenses", kind, version, ..] => format!("cc-{}-{}", kind, version),
+            ["licenses", kind] => format!("cc-{}", kind),
+            ["publicdomain", "zero", version, ..] => format!("cc0-{}", version),
+            ["publicdomain", "zero"] => "cc0".to_string(),
+            ["publicdomain", ..] => "public-domain".to_string(),
+            _ => slug(&text),
+        };
+    }
+    slug(&text)
+}
+
+/// 英數字與 `.` 以外的字元換成 `-`
+fn slug(text: &str) -> String {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '.'))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// 在 `dir` 寫入 `attribution.jsonl`（每張圖片一列）與彙整的 `ATTRIBUTION`，回傳兩個檔案的路徑
+///
+/// `ATTRIBUTION` 依來源網站分組，列出各授權的圖片數與每張圖片的原始網址，
+/// 發布資料集時隨附即可標示出處。
+pub fn write_attribution(dir: &str, records: &[AttributionRecord]) -> Result<(String, String)> {
+    fs::create_dir_all(dir).with_context(|| format!("無法建立 {}", dir))?;
+
+    let jsonl_path = format!("{}/attribution.jsonl", dir);
+    let mut jsonl = String::new();
+    for record in records {
+        jsonl.push_str(&serde_json::to_string(record)?);
+        jsonl.push('\n');
+    }
+    write_atomic(&jsonl_path, &jsonl)?;
+
+    let mut by_site: BTreeMap<&str, Vec<&AttributionRecord>> = BTreeMap::new();
+    for record in records {
+        by_site.entry(record.source_site.as_str()).or_default().push(record);
+    }
+
+    let mut text = String::new();
+    text.push_str("ATTRIBUTION\n===========\n\n");
+    text.push_str(&format!(
+        "本資料集的 {} 張圖片來自 {} 個網站，版權屬於原作者與各網站。\n",
+        records.len(),
+        by_site.len()
+    ));
+    text.push_str("未標示授權的圖片請依來源網站的使用條款使用。\n");
+
+    for (site, items) in &by_site {
+        let mut licenses: BTreeMap<&str, usize> = BTreeMap::new();
+        for item in items {
+            *licenses.entry(item.license.as_deref().unwrap_or("未標示")).or_default() += 1;
+        }
+
+        text.push_str(&format!("\n## {}（{} 張）\n\n", site, items.len()));
+        for (license, count) in &licenses {
+            text.push_str(&format!("授權: {}（{} 張）\n", license, count));
+        }
+        text.push('\n');
+        for item in items {
+            let date = item.crawled_at.get(..10).unwrap_or(&item.crawled_at);
+            match &item.license {
+                Some(license) => text.push_str(&format!("- {} <{}> {} [{}]\n", item.filename, item.url, date, license)),
+                None => text.push_str(&format!("- {} <{}> {}\n", item.filename, item.url, date)),
+            }
+        }
+    }
+
+    text.push_str("\n---\n由 meme-data-crawler 匯出時產生。\n");
+    let text_path = format!("{}/ATTRIBUTION", dir);
+    write_atomic(&text_path, &text)?;
+
+    Ok((jsonl_path, text_path))
+}
+
+fn write_atomic(path: &str, content: &str) -> Result<()> {
+    let temp_path = format!("{}.tmp", path);
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path).with_context(|| format!("無法更新 {}", path))?;
+    Ok(())
+}
+
+const COLUMNS: [&str; 19] = [
+    "filename",
+    "description",
+    "url",
+    "content_hash",
+    "page_number",
+    "downloaded_at",
+    "source_site",
+    "license",
+    "width",
+    "height",
+    "file_size",
+    "media_type",
+    "tags",
+    "services",
+    "keywords",
+    "best_guesses",
+    "suggested_titles",
+    "ocr_text",
+    "extra",
+];
+
+/// CSV：多值欄位以 `|` 分隔（pandas 可用 `.str.split("|")` 展開）
+fn write_csv(path: &str, rows: &[ExportRow]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path).context("無法建立 CSV 檔")?;
+    writer.write_record(COLUMNS)?;
+
+    let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    for row in rows {
+        writer.write_record([
+            row.filename.clone(),
+            row.description.clone(),
+            row.url.clone(),
+            row.content_hash.clone(),
+            row.page_number.to_string(),
+            row.downloaded_at.clone(),
+            row.source_site.clone(),
+            row.license.clone(),
+            optional(row.width.map(u64::from)),
+            optional(row.height.map(u64::from)),
+            optional(row.file_size),
+            row.media_type.clone(),
+            row.tags.join("|"),
+            row.services.join("|"),
+            row.keywords.join("|"),
+            row.best_guesses.join("|"),
+            row.suggested_titles.join("|"),
+            row.ocr_text.clone(),
+            row.extra.clone(),
+        ])?;
+    }
+
+    writer.flush().context("無法寫入 CSV 檔")?;
+    Ok(())
+}
+
+/// Parquet：多值欄位為 `list<string>`
+fn write_parquet(path: &str, rows: &[ExportRow]) -> Result<()> {
+    let list = |name: &str| {
+        Field::new(name, DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))), false)
+    };
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("filename", DataType::Utf8, false),
+        Field::new("description", DataType::Utf8, false),
+        Field::new("url", DataType::Utf8, false),
+        Field::new("content_hash", DataType::Utf8, false),
+        Field::new("page_number", DataType::UInt32, false),
+        Field::new("downloaded_at", DataType::Utf8, false),
+        Field::new("source_site", DataType::Utf8, true),
+        Field::new("license", DataType::Utf8, true),
+        Field::new("width", DataType::UInt32, true),
+        Field::new("height", DataType::UInt32, true),
+        Field::new("file_size", DataType::UInt64, true),
+        Field::new("media_type", DataType::Utf8, true),
+        list("tags"),
+        list("services"),
+        list("keywords"),
+        list("best_guesses"),
+        list("suggested_titles"),
+        Field::new("ocr_text", DataType::Utf8, true),
+        Field::new("extra", DataType::Utf8, true),
+    ]));
+
+    let strings = |get: fn(&ExportRow) -> &str| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(rows.iter().map(get)))
+    };
+    let optional_strings = |get: fn(&ExportRow) -> &str| -> ArrayRef {
+        Arc::new(StringArray::from_iter(rows.iter().map(|r| Some(get(r)).filter(|s| !s.is_empty()))))
+    };
+    let lists = |get: fn(&ExportRow) -> &Vec<String>| -> ArrayRef {
+        let mut builder = ListBuilder::new(StringBuilder::new());
+        for row in rows {
+            for value in get(row) {
+                builder.values().append_value(value);
+            }
+            builder.append(true);
+        }
+        Arc::new(builder.finish())
+    };
+
+    let batch = RecordBatch::try_new(
+        Arc::clone(&schema),
+        vec![
+            strings(|r| &r.filename),
+            strings(|r| &r.description),
+            strings(|r| &r.url),
+            strings(|r| &r.content_hash),
+            Arc::new(UInt32Array::from_iter_values(rows.iter().map(|r| r.page_number))),
+            strings(|r| &r.downloaded_at),
+            optional_strings(|r| &r.source_site),
+            optional_strings(|r| &r.license),
+            Arc::new(UInt32Array::from_iter(rows.iter().map(|r| r.width))),
+            Arc::new(UInt32Array::from_iter(rows.iter().map(|r| r.height))),
+            Arc::new(UInt64Array::from_iter(rows.iter().map(|r| r.file_size))),
+            optional_strings(|r| &r.media_type),
+            lists(|r| &r.tags),
+            lists(|r| &r.services),
+            lists(|r| &r.keywords),
+            lists(|r| &r.best_guesses),
+            lists(|r| &r.suggested_titles),
+            optional_strings(|r| &r.ocr_text),
+            optional_strings(|r| &r.extra),
+        ],
+    )
+    .context("無法建立 Parquet 資料")?;
+
+    let file = File::create(path).context("無法建立 Parquet 檔")?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(file, schema, Some(properties))?;
+    writer.write(&batch)?;
+    writer.close().context("無法寫入 Parquet 檔")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn metadata(filename: &str) -> ImageMetadata {
+        ImageMetadata {
+            description: "desc, with comma".to_string(),
+            url: format!("https://x.com/{}", filename),
+            page_number: 3,
+            tags: vec!["cat".to_string()],
+            source_site: "memes.tw".to_string(),
+            width: Some(640),
+            height: Some(480),
+            ..ImageMetadata::for_test(filename, "abc")
+        }
+    }
+
+    fn result(filename: &str, service: &str, keywords: &[&str], best_guess: Option<&str>) -> ReverseSearchResult {
+        ReverseSearchResult {
+            filename: filename.to_string(),
+            service: service.to_string(),
+            suggested_title: None,
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            related_sites: vec![],
+            best_guess: best_guess.map(str::to_string),
+            similarity: None,
+            searched_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_build_and_write() {
+        let metadata = vec![metadata("a.jpg"), metadata("b.jpg")];
+        let results = vec![
+            result("a.jpg", "google", &["doge", "shiba"], Some("doge meme")),
+            result("a.jpg", "bing", &["shiba", "dog"], None),
+        ];
+
+        let rows = build_rows(&metadata, &results);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].services, ["google", "bing"]);
+        assert_eq!(rows[0].keywords, ["doge", "shiba", "dog"]);
+        assert_eq!(rows[0].best_guesses, ["doge meme"]);
+        assert!(rows[1].keywords.is_empty());
+
+        let dir = std::env::temp_dir().join(format!("meme-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let csv_path = dir.join("dataset.csv");
+        write(csv_path.to_str().unwrap(), ExportFormat::Csv, &rows).unwrap();
+        let mut reader = csv::Reader::from_path(&csv_path).unwrap();
+        let records: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(&records[0][1], "desc, with comma");
+        assert_eq!(&records[0][14], "doge|shiba|dog");
+        assert_eq!(&records[0][10], "");
+
+        let (enriched, count) = enrich(&metadata, &results);
+        assert_eq!(count, 1);
+        assert_eq!(enriched[0].keywords, ["doge", "shiba", "dog"]);
+        assert_eq!(enriched[0].suggested_title.as_deref(), Some("doge meme"));
+        assert_eq!(enriched[1].suggested_title, None);
+
+        let parquet_path = dir.join("dataset.parquet");
+        write(parquet_path.to_str().unwrap(), ExportFormat::Parquet, &rows).unwrap();
+        let reader = SerializedFileReader::new(File::open(&parquet_path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        assert_eq!(reader.metadata().file_metadata().schema_descr().num_columns(), COLUMNS.len());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_imagefolder() {
+        let split = SplitConfig::parse("8,2", 7).unwrap();
+        assert_eq!((split.train, split.validation, split.test), (0.8, 0.2, 0.0));
+        assert!(SplitConfig::parse("0.8,x", 0).is_err());
+
+        let dir = std::env::temp_dir().join(format!("meme-hf-{}", std::process::id()));
+        let context = DataContext::open(dir.to_str().unwrap(), crate::store::MetadataBackend::Jsonl).unwrap();
+        let file_manager = context.file_manager();
+        let mut metadata: Vec<ImageMetadata> = (0..20).map(|i| metadata(&format!("{}.jpg", i))).collect();
+        for (i, m) in metadata.iter_mut().enumerate() {
+            let bytes = format!("jpeg{}", i);
+            m.content_hash = crate::integrity::sha256_hex(bytes.as_bytes());
+            file_manager.save_image(&m.filename, bytes.as_bytes()).unwrap();
+        }
+        metadata.push(self::metadata("gone.jpg"));
+
+        // 已移到冷儲存的原圖照樣匯出
+        context.cold_storage().unwrap().offload(file_manager, &crate::tier::TierItem {
+            filename: metadata[0].filename.clone(),
+            content_hash: metadata[0].content_hash.clone(),
+            size: 5,
+            reason: crate::tier::TierReason::Exported,
+        }).unwrap();
+        assert!(!Path::new(&file_manager.get_image_path(&metadata[0].filename)).exists());
+
+        let rows = build_rows(&metadata, &[]);
+        let output = dir.join("hf");
+        let summary = write_imagefolder(&context, output.to_str().unwrap(), &rows, &split).unwrap();
+
+        assert_eq!(summary.missing, 1);
+        assert_eq!(summary.splits.iter().map(|(_, n)| n).sum::<usize>(), 20);
+        assert!(summary.splits.iter().all(|(name, _)| *name != "test"));
+
+        let train = fs::read_to_string(output.join("train/metadata.jsonl")).unwrap();
+        let first: serde_json::Value = serde_json::from_str(train.lines().next().unwrap()).unwrap();
+        assert!(output.join("train").join(first["file_name"].as_str().unwrap()).exists());
+        assert_eq!(split.assign(first["content_hash"].as_str().unwrap()), "train");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+    #[test]
+    fn test_attribution() {
+        let mut licensed = metadata("a.jpg");
+        licensed.extra.insert("license".to_string(), "CC BY 4.0".into());
+        let mut unknown_site = metadata("b.jpg");
+        unknown_site.source_site = String::new();
+        unknown_site.url = "https://i.imgflip.com/b.jpg".to_string();
+
+        let records = build_attribution(&[licensed, metadata("c.jpg"), unknown_site]);
+        assert_eq!(records[0].license.as_deref(), Some("CC BY 4.0"));
+        assert_eq!(records[1].license, None);
+        assert_eq!(records[2].source_site, "i.imgflip.com");
+
+        let dir = std::env::temp_dir().join(format!("meme-attribution-{}", std::process::id()));
+        let (jsonl_path, text_path) = write_attribution(dir.to_str().unwrap(), &records).unwrap();
+
+        assert_eq!(fs::read_to_string(&jsonl_path).unwrap().lines().count(), 3);
+        let text = fs::read_to_string(&text_path).unwrap();
+        assert!(text.contains("## memes.tw（2 張）"));
+        assert!(text.contains("授權: CC BY 4.0（1 張）"));
+        assert!(text.contains("授權: 未標示（1 張）"));
+        assert!(text.contains("- b.jpg <https://i.imgflip.com/b.jpg>"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_license_filter() {
+        assert_eq!(license_id("CC BY-SA 4.0"), "cc-by-sa-4.0");
+        assert_eq!(license_id("https://creativecommons.org/licenses/by/4.0/deed.zh_TW"), "cc-by-4.0");
+        assert_eq!(license_id("https://creativecommons.org/publicdomain/zero/1.0/"), "cc0-1.0");
+        assert_eq!(license_id("Public Domain"), "public-domain");
+
+        let filter = LicenseFilter::parse("cc-by, CC0").unwrap();
+        assert!(filter.matches(Some("CC BY 4.0")));
+        assert!(filter.matches(Some("https://creativecommons.org/publicdomain/zero/1.0/")));
+        assert!(!filter.matches(Some("CC BY-SA 4.0")));
+        assert!(!filter.matches(None));
+        assert!(LicenseFilter::parse("cc-by-sa-4.0").unwrap().matches(Some("CC BY-SA 4.0")));
+        assert!(!LicenseFilter::parse("cc-by-sa-3.0").unwrap().matches(Some("CC BY-SA 4.0")));
+        assert!(LicenseFilter::parse("any").unwrap().matches(Some("All rights reserved")));
+        assert!(LicenseFilter::parse(" , ").is_err());
+
+        // 舊資料的授權在 extra.license
+        let mut old = metadata("a.jpg");
+        old.extra.insert("license".to_string(), "CC0 1.0".into());
+        let mut current = metadata("b.jpg");
+        current.license = Some("CC BY 4.0".to_string());
+        let all = [old, current, metadata("c.jpg")];
+        let kept: Vec<&str> = all
+            .iter()
+            .filter(|m| filter.matches(m.license()))
+            .map(|m| m.filename.as_str())
+            .collect();
+        assert_eq!(kept, ["a.jpg", "b.jpg"]);
+    }
+}
@@ -1,34 +1,240 @@
-use anyhow::{Context, Result};
-use reqwest::Client;
-use std::time::Duration;
+use crate::headers::HeaderRotator;
+use crate::client_pool::ClientFactory;
+use crate::proxy::{ProxyConfig, ProxyRotator};
+use crate::rate_limit::{AdaptiveRateLimiter, HostTokenBucket};
+use crate::robots::{RobotsDisallowed, RobotsRules};
+use anyhow::Result;
+use reqwest::{Client, ClientBuilder, StatusCode};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 /// HTTP Fetcher trait - 抽象介面（為未來擴充預留）
+#[allow(async_fn_in_trait)]
 pub trait Fetcher {
     async fn fetch_page(&self, url: &str) -> Result<String>;
 }
 
+/// 頁面回應非 2xx（重試後仍失敗）
+#[derive(Debug)]
+pub struct HttpStatusError {
+    pub url: String,
+    pub status: StatusCode,
+}
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP 錯誤: {}", self.status)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// 頁面不存在（404/410），列表頁通常表示超過最後一頁
+pub fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<HttpStatusError>()
+        .is_some_and(|e| matches!(e.status, StatusCode::NOT_FOUND | StatusCode::GONE))
+}
+
+/// 請求統計（每次嘗試都計算，包含重試）
+#[derive(Debug, Default)]
+pub struct FetchStats {
+    requests: AtomicUsize,
+    failures: AtomicUsize,
+    rate_limited: AtomicUsize,
+}
+
+/// 請求統計快照
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FetchStatsSnapshot {
+    /// 請求次數
+    pub requests: usize,
+    /// 失敗次數（含 429）
+    pub failures: usize,
+    /// 被限流次數（HTTP 429）
+    pub rate_limited: usize,
+}
+
+impl FetchStats {
+    fn record(&self, status: Option<StatusCode>) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        match status {
+            Some(status) if status.is_success() => {}
+            Some(StatusCode::TOO_MANY_REQUESTS) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                self.rate_limited.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+    
+    pub fn snapshot(&self) -> FetchStatsSnapshot {
+        FetchStatsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl FetchStatsSnapshot {
+    /// 與較早快照的差值
+    pub fn since(&self, earlier: &FetchStatsSnapshot) -> FetchStatsSnapshot {
+        FetchStatsSnapshot {
+            requests: self.requests - earlier.requests,
+            failures: self.failures - earlier.failures,
+            rate_limited: self.rate_limited - earlier.rate_limited,
+        }
+    }
+}
+
+/// 讀取 robots.txt 失敗（5xx、連不上）後多久再試
+const ROBOTS_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// 快取的 robots.txt 規則（讀取失敗時的「全部禁止」只保留到 `expires_at`）
+struct CachedRobots {
+    rules: Arc<RobotsRules>,
+    expires_at: Option<Instant>,
+}
+
 /// HTTP 實作
 pub struct HttpFetcher {
-    client: Client,
+    clients: ProxyRotator,
     timeout: Duration,
     max_retries: u32,
+    stats: FetchStats,
+    /// 遵守 robots.txt（預設啟用）
+    respect_robots: bool,
+    /// 各 origin 的 robots.txt 規則（每次執行各讀取一次，各 origin 各自一把鎖）
+    robots: std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<Option<CachedRobots>>>>>,
+    robots_retry_after: Duration,
+    /// Crawl-delay 透過限流器套用
+    rate_limiter: Option<Arc<AdaptiveRateLimiter>>,
 }
 
 impl HttpFetcher {
     /// 建立新的 HTTP Fetcher
     pub fn new(timeout_secs: u64, max_retries: u32) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(timeout_secs))
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-            .build()
-            .context("無法建立 HTTP 客戶端")?;
+        let timeout = Duration::from_secs(timeout_secs);
+        let clients = ProxyRotator::shared(&ProxyConfig::default(), &format!("page:{}s", timeout_secs), || client_builder(timeout))?;
 
         Ok(Self {
-            client,
-            timeout: Duration::from_secs(timeout_secs),
+            clients,
+            timeout,
             max_retries,
+            stats: FetchStats::default(),
+            respect_robots: true,
+            robots: std::sync::Mutex::new(HashMap::new()),
+            robots_retry_after: ROBOTS_RETRY_AFTER,
+            rate_limiter: None,
         })
     }
+    
+    /// 透過代理發送請求（依設定的策略輪替）
+    pub fn with_proxies(mut self, config: &ProxyConfig) -> Result<Self> {
+        let timeout = self.timeout;
+        self.clients.set_proxies(config, || client_builder(timeout))?;
+        Ok(self)
+    }
+    
+    /// 依 host 自適應限流，`default_delay_ms` 為尚未學到延遲時的請求間隔
+    pub fn with_rate_limiter(mut self, limiter: Arc<AdaptiveRateLimiter>, default_delay_ms: u64) -> Self {
+        self.clients.set_rate_limiter(Arc::clone(&limiter), default_delay_ms);
+        self.rate_limiter = Some(limiter);
+        self
+    }
+    
+    /// 每個 host 的請求速率上限（傳入與圖片下載共用的 bucket，None 表示不限制）
+    pub fn with_token_bucket(mut self, bucket: Option<Arc<HostTokenBucket>>) -> Self {
+        self.clients.set_token_bucket(bucket);
+        self
+    }
+    
+    /// 輪替 User-Agent 並加上各網站的 headers（None 表示只用預設的 User-Agent）
+    pub fn with_headers(mut self, headers: Option<Arc<HeaderRotator>>) -> Self {
+        self.clients.set_headers(headers);
+        self
+    }
+    
+    /// 是否遵守 robots.txt 的 Disallow 與 Crawl-delay（`--ignore-robots` 時關閉）
+    pub fn with_robots(mut self, respect: bool) -> Self {
+        self.respect_robots = respect;
+        self
+    }
+    
+    /// 確認 robots.txt 允許爬取這個網址（第一次遇到的 origin 先讀取 robots.txt）
+    async fn check_robots(&self, url: &str) -> Result<()> {
+        if !self.respect_robots {
+            return Ok(());
+        }
+        let Ok(parsed) = reqwest::Url::parse(url) else {
+            return Ok(());
+        };
+        let Some(host) = parsed.host_str() else {
+            return Ok(());
+        };
+        
+        let origin = parsed.origin().ascii_serialization();
+        let entry = Arc::clone(self.robots.lock().unwrap().entry(origin.clone()).or_default());
+        let rules = {
+            // 同一 origin 的並發請求等第一個讀完，其他 origin 不受影響
+            let mut cached = entry.lock().await;
+            match cached.as_ref() {
+                Some(c) if c.expires_at.is_none_or(|expires_at| Instant::now() < expires_at) => Arc::clone(&c.rules),
+                _ => {
+                    let (rules, failed) = self.fetch_robots(&origin).await;
+                    let rules = Arc::new(rules);
+                    if let (Some(delay), Some(limiter)) = (rules.crawl_delay(), &self.rate_limiter) {
+                        limiter.set_min_delay(host, delay.as_millis() as u64);
+                    }
+                    *cached = Some(CachedRobots {
+                        rules: Arc::clone(&rules),
+                        expires_at: failed.then(|| Instant::now() + self.robots_retry_after),
+                    });
+                    rules
+                }
+            }
+        };
+        
+        let path = match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        };
+        if !rules.is_allowed(&path) {
+            return Err(RobotsDisallowed { url: url.to_string() }.into());
+        }
+        Ok(())
+    }
+    
+    /// 讀取 robots.txt：不存在（4xx）時全部允許，伺服器錯誤或連不上時暫時全部禁止（回傳 true 表示讀取失敗）
+    async fn fetch_robots(&self, origin: &str) -> (RobotsRules, bool) {
+        let url = format!("{}/robots.txt", origin);
+        
+        match self.clients.send(|client| client.get(&url)).await {
+            Ok(response) if response.status().is_success() => match response.text().await {
+                Ok(text) => (RobotsRules::parse(&text), false),
+                Err(_) => (RobotsRules::disallow_all(), true),
+            },
+            Ok(response) if response.status().is_client_error() => (RobotsRules::allow_all(), false),
+            Ok(response) => {
+                eout!("⚠️  無法讀取 {}（HTTP {}），暫不爬取該網站", url, response.status());
+                (RobotsRules::disallow_all(), true)
+            }
+            Err(e) => {
+                eout!("⚠️  無法讀取 {}: {}，暫不爬取該網站", url, e);
+                (RobotsRules::disallow_all(), true)
+            }
+        }
+    }
+    
+    /// 目前的請求統計
+    pub fn stats(&self) -> FetchStatsSnapshot {
+        self.stats.snapshot()
+    }
 
     /// 帶重試的請求
     async fn fetch_with_retry(&self, url: &str) -> Result<String> {
@@ -39,11 +245,13 @@ impl HttpFetcher {
                 // 重試前等待（指數退避）
                 let wait_time = Duration::from_secs(2u64.pow(attempt - 1));
                 tokio::time::sleep(wait_time).await;
-                println!("重試 {} - {}", attempt, url);
+                out!("重試 {} - {}", attempt, url);
             }
 
-            match self.client.get(url).send().await {
+            match self.clients.send(|client| client.get(url)).await {
                 Ok(response) => {
+                    self.stats.record(Some(response.status()));
+                    
                     if response.status().is_success() {
                         match response.text().await {
                             Ok(body) => return Ok(body),
@@ -53,14 +261,17 @@ impl HttpFetcher {
                             }
                         }
                     } else {
-                        last_error = Some(anyhow::anyhow!(
-                            "HTTP 錯誤: {}",
-                            response.status()
-                        ));
+                        let error = HttpStatusError { url: url.to_string(), status: response.status() };
+                        // 頁面不存在時重試也不會變
+                        if matches!(error.status, StatusCode::NOT_FOUND | StatusCode::GONE) {
+                            return Err(error.into());
+                        }
+                        last_error = Some(error.into());
                         continue;
                     }
                 }
                 Err(e) => {
+                    self.stats.record(None);
                     last_error = Some(anyhow::anyhow!("請求失敗: {}", e));
                     continue;
                 }
@@ -71,8 +282,40 @@ impl HttpFetcher {
     }
 }
 
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36";
+
+/// 閒置連線保留時間：比常見伺服器與 CDN 的 keep-alive 逾時（60～75 秒）短，
+/// 避免拿到對方已關閉的連線而多一次重連
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(50);
+
+/// TCP keep-alive 探測間隔，讓 NAT 與負載平衡器不會在請求間隔中丟掉連線
+const TCP_KEEPALIVE: Duration = Duration::from_secs(30);
+
+fn client_builder(timeout: Duration) -> ClientBuilder {
+    Client::builder()
+        .timeout(timeout)
+        .user_agent(USER_AGENT)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(TCP_KEEPALIVE)
+}
+
+/// 下載圖片用的 client（同一個逾時的下載共用連線池）
+///
+/// 逾時指連線與兩次讀取之間的等待，不限制總時間，大檔案慢慢下載不會被中斷。
+pub fn download_client(timeout: Duration) -> Result<Client> {
+    ClientFactory::global().client(&format!("download:{}s", timeout.as_secs()), None, || {
+        Client::builder()
+            .connect_timeout(timeout)
+            .read_timeout(timeout)
+            .user_agent(USER_AGENT)
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .tcp_keepalive(TCP_KEEPALIVE)
+    })
+}
+
 impl Fetcher for HttpFetcher {
     async fn fetch_page(&self, url: &str) -> Result<String> {
+        self.check_robots(url).await?;
         self.fetch_with_retry(url).await
     }
 }
@@ -87,4 +330,73 @@ mod tests {
         let result = fetcher.fetch_page("https://httpbin.org/html").await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_robots_disallow() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let body = if request.starts_with(b"GET /robots.txt") {
+                    "User-agent: *\nDisallow: /private\nCrawl-delay: 1\n"
+                } else {
+                    "<html></html>"
+                };
+                let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let limiter = Arc::new(AdaptiveRateLimiter::new());
+        let fetcher = HttpFetcher::new(5, 0).unwrap().with_rate_limiter(Arc::clone(&limiter), 100);
+        assert!(fetcher.fetch_page(&format!("http://{}/memes?page=1", addr)).await.is_ok());
+        let error = fetcher.fetch_page(&format!("http://{}/private/a", addr)).await.unwrap_err();
+        assert!(error.downcast_ref::<RobotsDisallowed>().is_some());
+        assert_eq!(limiter.delay_ms("127.0.0.1"), Some(1000));
+
+        let ignoring = HttpFetcher::new(5, 0).unwrap().with_robots(false);
+        assert!(ignoring.fetch_page(&format!("http://{}/private/a", addr)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_robots_error_expires() {
+        use std::sync::atomic::AtomicBool;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        // 第一次讀取 robots.txt 回應 503，之後正常
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let failed_once = Arc::new(AtomicBool::new(false));
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let (status, body) = if !request.starts_with(b"GET /robots.txt") {
+                    ("200 OK", "<html></html>")
+                } else if !failed_once.swap(true, Ordering::Relaxed) {
+                    ("503 Service Unavailable", "")
+                } else {
+                    ("200 OK", "User-agent: *\nDisallow: /private\n")
+                };
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let mut fetcher = HttpFetcher::new(5, 0).unwrap();
+        fetcher.robots_retry_after = Duration::from_millis(50);
+        let page = format!("http://{}/memes", addr);
+        assert!(fetcher.fetch_page(&page).await.unwrap_err().downcast_ref::<RobotsDisallowed>().is_some());
+        assert!(fetcher.fetch_page(&page).await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(fetcher.fetch_page(&page).await.is_ok());
+        assert!(fetcher.fetch_page(&format!("http://{}/private/a", addr)).await.is_err());
+    }
 }
\ No newline at end of file
@@ -1,9 +1,39 @@
-use crate::types::{ImageMetadata, Progress};
+use crate::dedup_index;
+use crate::thumbnail::THUMBNAIL_DIR;
+use crate::types::{DatasetManifest, FailedDownload, ImageMetadata, Progress, RunReport, SiteChange};
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
 
+/// 一個檔案的改名（images/ 下的檔名）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenameEntry {
+    pub from: String,
+    pub to: String,
+}
+
+/// 改名交易的意圖紀錄（rename_journal.json）
+///
+/// 流程：`begin_renames` 寫入紀錄 → `apply_journaled_renames` 改名檔案 →
+/// 呼叫端更新 metadata 等紀錄檔 → `commit_renames` 刪除紀錄。
+/// 中途 crash 時紀錄仍在，下次啟動以 `pending_renames` 取出並從頭重做；
+/// 每個步驟都可以重複執行，所以只會往前完成，不會留下 metadata 指向不存在的檔案。
+///
+/// 改名分兩階段：來源先全部移到暫存名稱，再移到目標，所以 a→b、b→c 這類連鎖
+/// （或互換）不受順序影響。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameJournal {
+    pub started_at: DateTime<Utc>,
+    pub renames: Vec<RenameEntry>,
+    /// 來源都已移到暫存名稱（第二階段）
+    #[serde(default)]
+    pub staged: bool,
+}
+
 /// 檔案操作管理器
 pub struct FileManager {
     /// 專案根目錄
@@ -14,17 +44,27 @@ impl FileManager {
     /// 建立新的檔案管理器
     pub fn new(root_dir: &str) -> Result<Self> {
         // 建立必要的目錄
-        fs::create_dir_all(format!("{}/images", root_dir))
+        fs::create_dir_all(join_path(root_dir, "images"))
             .context("無法建立 images 目錄")?;
         
         Ok(Self {
-            root_dir: root_dir.to_string(),
+            root_dir: trim_separators(root_dir).to_string(),
         })
     }
 
+    /// 資料根目錄
+    pub fn root_dir(&self) -> &str {
+        &self.root_dir
+    }
+
+    /// 資料目錄下的路徑（`relative` 以 `/` 分隔）
+    fn path(&self, relative: &str) -> String {
+        join_path(&self.root_dir, relative)
+    }
+
     /// 讀取進度檔案
     pub fn load_progress(&self) -> Result<Progress> {
-        let path = format!("{}/progress.json", self.root_dir);
+        let path = self.path("progress.json");
         
         // 如果檔案不存在，回傳新的進度
         if !Path::new(&path).exists() {
@@ -43,7 +83,7 @@ impl FileManager {
 
     /// 儲存進度檔案（原子性寫入）
     pub fn save_progress(&self, progress: &Progress) -> Result<()> {
-        let path = format!("{}/progress.json", self.root_dir);
+        let path = self.path("progress.json");
         let temp_path = format!("{}.tmp", path);
 
         // 先寫到暫存檔
@@ -60,9 +100,118 @@ impl FileManager {
         Ok(())
     }
 
+    /// 儲存本次執行報告（覆寫 run_report.json）
+    pub fn save_run_report(&self, report: &RunReport) -> Result<()> {
+        let path = self.path("run_report.json");
+        let temp_path = format!("{}.tmp", path);
+        
+        let file = File::create(&temp_path)
+            .context("無法建立暫存檔")?;
+        
+        serde_json::to_writer_pretty(file, report)
+            .context("無法寫入 run_report.json")?;
+        
+        fs::rename(&temp_path, &path)
+            .context("無法更新 run_report.json")?;
+        
+        Ok(())
+    }
+
+    /// 儲存資料集清單（原子性寫入）
+    pub fn save_manifest(&self, manifest: &DatasetManifest) -> Result<()> {
+        let path = self.path("dataset_manifest.json");
+        let temp_path = format!("{}.tmp", path);
+        
+        let file = File::create(&temp_path)
+            .context("無法建立暫存檔")?;
+        
+        serde_json::to_writer_pretty(file, manifest)
+            .context("無法寫入 dataset_manifest.json")?;
+        
+        fs::rename(&temp_path, &path)
+            .context("無法更新 dataset_manifest.json")?;
+        
+        Ok(())
+    }
+
+    /// Append 網站變動紀錄到 site_changes.jsonl
+    pub fn append_site_changes(&self, changes: &[SiteChange]) -> Result<()> {
+        let path = self.path("site_changes.jsonl");
+        
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context("無法開啟 site_changes.jsonl")?;
+        let mut writer = BufWriter::new(file);
+        
+        for change in changes {
+            serde_json::to_writer(&mut writer, change)
+                .context("無法寫入 site_changes.jsonl")?;
+            writeln!(writer).context("無法寫入換行符號")?;
+        }
+        
+        writer.flush().context("無法 flush buffer")?;
+        Ok(())
+    }
+
+    /// Append 下載失敗的圖片到 failed_downloads.jsonl
+    pub fn append_failed_download(&self, failed: &FailedDownload) -> Result<()> {
+        let path = self.path("failed_downloads.jsonl");
+        
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context("無法開啟 failed_downloads.jsonl")?;
+        writeln!(file, "{}", serde_json::to_string(failed)?)
+            .context("無法寫入 failed_downloads.jsonl")?;
+        
+        Ok(())
+    }
+
+    /// 讀取下載失敗的圖片
+    pub fn load_failed_downloads(&self) -> Result<Vec<FailedDownload>> {
+        let path = self.path("failed_downloads.jsonl");
+        if !Path::new(&path).exists() {
+            return Ok(Vec::new());
+        }
+        
+        let reader = BufReader::new(File::open(&path).context("無法開啟 failed_downloads.jsonl")?);
+        let mut failed = Vec::new();
+        for line in reader.lines() {
+            let line = line.context("讀取行失敗")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            failed.push(serde_json::from_str(&line).context("解析 failed_downloads.jsonl 失敗")?);
+        }
+        
+        Ok(failed)
+    }
+
+    /// 以剩下的項目重寫 failed_downloads.jsonl（原子性寫入）
+    pub fn rewrite_failed_downloads(&self, failed: &[FailedDownload]) -> Result<()> {
+        let path = self.path("failed_downloads.jsonl");
+        let temp_path = format!("{}.tmp", path);
+        
+        let mut writer = BufWriter::new(File::create(&temp_path).context("無法建立暫存檔")?);
+        for item in failed {
+            serde_json::to_writer(&mut writer, item)
+                .context("無法寫入 failed_downloads.jsonl")?;
+            writeln!(writer).context("無法寫入換行符號")?;
+        }
+        writer.flush().context("無法 flush buffer")?;
+        drop(writer);
+        
+        fs::rename(&temp_path, &path)
+            .context("無法更新 failed_downloads.jsonl")?;
+        Ok(())
+    }
+
     /// Append metadata 到 JSONL 檔案
     pub fn append_metadata(&self, metadata: &ImageMetadata) -> Result<()> {
-        let path = format!("{}/metadata.jsonl", self.root_dir);
+        let path = self.path("metadata.jsonl");
         
         // 以 append 模式開啟檔案
         let file = OpenOptions::new()
@@ -85,7 +234,7 @@ impl FileManager {
 
     /// 讀取所有 metadata (從 metadata.jsonl)
     pub fn load_all_metadata(&self) -> Result<Vec<ImageMetadata>> {
-        let path = format!("{}/metadata.jsonl", self.root_dir);
+        let path = self.path("metadata.jsonl");
         
         // 檢查檔案是否存在
         if !Path::new(&path).exists() {
@@ -122,7 +271,17 @@ impl FileManager {
 
     /// 重寫 metadata.jsonl（用於去重後更新）
     pub fn rewrite_metadata(&self, metadata_list: &[ImageMetadata]) -> Result<()> {
-        let path = format!("{}/metadata.jsonl", self.root_dir);
+        self.write_metadata_file("metadata.jsonl", metadata_list)
+    }
+
+    /// 寫出合併搜尋結果後的 metadata_enriched.jsonl
+    pub fn save_enriched_metadata(&self, metadata_list: &[ImageMetadata]) -> Result<()> {
+        self.write_metadata_file("metadata_enriched.jsonl", metadata_list)
+    }
+
+    /// 以 JSONL 寫出 metadata 列表（原子性寫入）
+    fn write_metadata_file(&self, name: &str, metadata_list: &[ImageMetadata]) -> Result<()> {
+        let path = self.path(name);
         let temp_path = format!("{}.tmp", path);
         
         // 先寫到暫存檔
@@ -140,36 +299,239 @@ impl FileManager {
         
         // 原子性地重新命名
         fs::rename(&temp_path, &path)
-            .context("無法更新 metadata.jsonl")?;
+            .with_context(|| format!("無法更新 {}", name))?;
+        
+        // 去重索引記錄的是舊檔案的位置，下次去重時重建
+        if name == "metadata.jsonl" {
+            let index = self.path(dedup_index::INDEX_FILE);
+            if Path::new(&index).exists() {
+                fs::remove_file(&index).context("無法刪除過期的去重索引")?;
+            }
+        }
         
         Ok(())
     }
 
     /// 備份 metadata.jsonl
+    ///
+    /// 上一份備份改名為 `metadata.jsonl.backup.<時間>` 保留（舊備份由 `gc` 清理）。
     pub fn backup_metadata(&self) -> Result<()> {
-        let path = format!("{}/metadata.jsonl", self.root_dir);
-        let backup_path = format!("{}/metadata.jsonl.backup", self.root_dir);
+        let path = self.path("metadata.jsonl");
+        let backup_path = self.path("metadata.jsonl.backup");
         
         if Path::new(&path).exists() {
+            if let Ok(modified) = fs::metadata(&backup_path).and_then(|m| m.modified()) {
+                let stamp = chrono::DateTime::<chrono::Utc>::from(modified).format("%Y%m%d%H%M%S");
+                fs::rename(&backup_path, format!("{}.{}", backup_path, stamp))
+                    .context("無法保留上一份備份")?;
+            }
+            
             fs::copy(&path, &backup_path)
                 .context("無法備份 metadata.jsonl")?;
-            println!("📦 已備份 metadata.jsonl -> metadata.jsonl.backup");
+            out!("📦 已備份 metadata.jsonl -> metadata.jsonl.backup");
         }
         
         Ok(())
     }
 
-    /// 儲存圖片檔案
+    /// 儲存圖片檔案（原子性寫入）
+    ///
+    /// 寫到一半 crash 只會留下 `.tmp` 暫存檔，不會有截斷的圖片通過去重與搜尋。
     pub fn save_image(&self, filename: &str, data: &[u8]) -> Result<()> {
-        let path = format!("{}/images/{}", self.root_dir, filename);
-        fs::write(&path, data)
+        let path = self.path(&format!("images/{}", filename));
+        let temp_path = format!("{}.tmp", path);
+        
+        fs::write(&temp_path, data)
+            .context("無法寫入圖片檔案")?;
+        fs::rename(&temp_path, &path)
+            .context("無法更新圖片檔案")?;
+        Ok(())
+    }
+
+    fn journal_path(&self) -> String {
+        self.path("rename_journal.json")
+    }
+
+    /// 開始改名交易：先寫入意圖紀錄（原子性寫入）
+    ///
+    /// 已有未完成的交易時回傳錯誤，應先完成它。
+    pub fn begin_renames(&self, renames: &[RenameEntry]) -> Result<RenameJournal> {
+        if self.pending_renames()?.is_some() {
+            anyhow::bail!("有未完成的改名交易（rename_journal.json），請先完成");
+        }
+
+        let journal = RenameJournal {
+            started_at: Utc::now(),
+            renames: renames.to_vec(),
+            staged: false,
+        };
+        self.write_journal(&journal)?;
+        Ok(journal)
+    }
+
+    /// 原子性寫入 rename_journal.json
+    fn write_journal(&self, journal: &RenameJournal) -> Result<()> {
+        let path = self.journal_path();
+        let temp_path = format!("{}.tmp", path);
+        
+        let file = File::create(&temp_path)
+            .context("無法建立暫存檔")?;
+        serde_json::to_writer_pretty(&file, journal)
+            .context("無法寫入 rename_journal.json")?;
+        file.sync_all().context("無法同步 rename_journal.json")?;
+        
+        fs::rename(&temp_path, &path)
+            .context("無法更新 rename_journal.json")?;
+        
+        Ok(())
+    }
+
+    /// 讀取未完成的改名交易（沒有時回傳 None）
+    pub fn pending_renames(&self) -> Result<Option<RenameJournal>> {
+        let path = self.journal_path();
+        if !Path::new(&path).exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .context("無法讀取 rename_journal.json")?;
+        let journal = serde_json::from_str(&content)
+            .context("無法解析 rename_journal.json")?;
+        Ok(Some(journal))
+    }
+
+    /// 改名途中的暫存路徑（images/.<目標>.renaming）
+    fn staging_path(&self, to: &str) -> String {
+        self.path(&format!("images/.{}.renaming", to))
+    }
+
+    /// 依紀錄改名 images/ 下的檔案，回傳完成改名的項目
+    ///
+    /// 可重複執行：先把來源移到暫存名稱並記錄在紀錄檔，再移到目標；
+    /// 已改名的（來源與暫存都不存在、目標存在）視為完成，來源不存在的略過。
+    /// 目標已被其他檔案佔用時回傳錯誤（不覆蓋），紀錄保留到問題排除後重做。
+    pub fn apply_journaled_renames(&self, journal: &RenameJournal) -> Result<Vec<RenameEntry>> {
+        let exists = |path: &str| Path::new(path).exists();
+        let mut journal = journal.clone();
+        
+        if !journal.staged {
+            // 目標存在、又不是其他項目的來源（會先移走）時不覆蓋
+            let sources: HashSet<&str> = journal.renames.iter().map(|entry| entry.from.as_str()).collect();
+            for entry in &journal.renames {
+                let pending = exists(&self.get_image_path(&entry.from)) || exists(&self.staging_path(&entry.to));
+                if pending && exists(&self.get_image_path(&entry.to)) && !sources.contains(entry.to.as_str()) {
+                    anyhow::bail!("改名目標已存在: {} -> {}", entry.from, entry.to);
+                }
+            }
+            
+            for entry in &journal.renames {
+                let from = self.get_image_path(&entry.from);
+                let staging = self.staging_path(&entry.to);
+                if !exists(&staging) && exists(&from) {
+                    fs::rename(&from, &staging)
+                        .with_context(|| format!("無法改名 {} -> {}", entry.from, entry.to))?;
+                }
+            }
+            journal.staged = true;
+            self.write_journal(&journal)?;
+        }
+        
+        let mut done = Vec::new();
+        for entry in &journal.renames {
+            let staging = self.staging_path(&entry.to);
+            let to = self.get_image_path(&entry.to);
+            
+            match (exists(&staging), exists(&to)) {
+                (true, false) => {
+                    fs::rename(&staging, &to)
+                        .with_context(|| format!("無法改名 {} -> {}", entry.from, entry.to))?;
+                    done.push(entry.clone());
+                }
+                (false, true) => done.push(entry.clone()),
+                (true, true) => anyhow::bail!("改名目標已存在: {} -> {}", entry.from, entry.to),
+                (false, false) => eout!("  ⚠️  檔案不存在，跳過: {}", entry.from),
+            }
+        }
+        
+        Ok(done)
+    }
+
+    /// 完成改名交易（紀錄檔都更新後呼叫）
+    pub fn commit_renames(&self) -> Result<()> {
+        match fs::remove_file(self.journal_path()) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).context("無法刪除 rename_journal.json"),
+        }
+    }
+
+    /// 下載中圖片的暫存路徑（images/.<name>.part，完成後以 `persist_image` 改名）
+    pub fn part_path(&self, name: &str) -> String {
+        self.path(&format!("images/.{}.part", name))
+    }
+
+    /// 將下載完成的暫存檔改名為正式檔名
+    pub fn persist_image(&self, part_path: &str, filename: &str) -> Result<()> {
+        fs::rename(part_path, self.get_image_path(filename))
             .context("無法寫入圖片檔案")?;
         Ok(())
     }
 
     /// 取得圖片儲存路徑
     pub fn get_image_path(&self, filename: &str) -> String {
-        format!("{}/images/{}", self.root_dir, filename)
+        self.path(&format!("images/{}", filename))
+    }
+
+    /// 取得縮圖路徑（thumbnails/<檔名>.webp）
+    pub fn get_thumbnail_path(&self, filename: &str) -> String {
+        self.path(&format!("{}/{}.webp", THUMBNAIL_DIR, filename))
+    }
+
+    /// 儲存縮圖（原子性寫入，中斷時只留下 `.tmp`，下次重新產生）
+    pub fn save_thumbnail(&self, filename: &str, data: &[u8]) -> Result<()> {
+        fs::create_dir_all(self.path(THUMBNAIL_DIR))
+            .context("無法建立 thumbnails 目錄")?;
+        let path = self.get_thumbnail_path(filename);
+        let temp_path = format!("{}.tmp", path);
+        
+        fs::write(&temp_path, data)
+            .context("無法寫入縮圖")?;
+        fs::rename(&temp_path, &path)
+            .context("無法更新縮圖")?;
+        Ok(())
+    }
+}
+
+/// 接上資料目錄下的相對路徑（`relative` 以 `/` 分隔）
+///
+/// 沿用根目錄本身的分隔字元：`C:\Users\me\data` 這類 Windows 路徑接成
+/// `C:\Users\me\data\images\a.jpg`，不會混用 `/` 與 `\`；根目錄沒有分隔字元時用系統預設。
+pub fn join_path(root: &str, relative: &str) -> String {
+    let separator = if root.contains('\\') && !root.contains('/') {
+        '\\'
+    } else if root.contains('/') {
+        '/'
+    } else {
+        std::path::MAIN_SEPARATOR
+    };
+    let relative = relative.trim_start_matches(['/', '\\']);
+    let relative = if separator == '/' { relative.to_string() } else { relative.replace('/', "\\") };
+
+    let root = trim_separators(root);
+    if root.is_empty() || root.ends_with(['/', '\\']) {
+        format!("{}{}", root, relative)
+    } else {
+        format!("{}{}{}", root, separator, relative)
+    }
+}
+
+/// 去掉結尾的分隔字元（根目錄 `/`、`C:\` 本身保留一個）
+fn trim_separators(root: &str) -> &str {
+    let trimmed = root.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() || trimmed.ends_with(':') {
+        &root[..(trimmed.len() + 1).min(root.len())]
+    } else {
+        trimmed
     }
 }
 
@@ -192,4 +554,149 @@ mod tests {
         // 清理
         std::fs::remove_dir_all("./test_data").ok();
     }
+
+    #[test]
+    fn test_windows_paths() {
+        // Windows 路徑（%APPDATA% 下的 profile、磁碟根目錄）不混用 `/`
+        assert_eq!(join_path(r"C:\Users\me\data", "images/a.jpg"), r"C:\Users\me\data\images\a.jpg");
+        assert_eq!(join_path(r"C:\Users\me\data\", "metadata.jsonl"), r"C:\Users\me\data\metadata.jsonl");
+        assert_eq!(join_path(r"D:\", "images/.x.part"), r"D:\images\.x.part");
+        assert_eq!(join_path(r"\\nas\memes", "progress.json"), r"\\nas\memes\progress.json");
+        
+        // 已用 `/` 的路徑維持原樣（Windows 也接受 `C:/data`）
+        assert_eq!(join_path("./data/", "images/a.jpg"), "./data/images/a.jpg");
+        assert_eq!(join_path("C:/data", "images/a.jpg"), "C:/data/images/a.jpg");
+        assert_eq!(join_path("/", "images"), "/images");
+        
+        // 沒有分隔字元時用系統預設
+        let sep = std::path::MAIN_SEPARATOR;
+        assert_eq!(join_path("data", "images/a.jpg"), format!("data{0}images{0}a.jpg", sep));
+        
+        let dir = std::env::temp_dir().join(format!("meme-fm-win-{}", std::process::id()));
+        let root = format!("{}{}", dir.display(), sep);
+        let manager = FileManager::new(&root).unwrap();
+        assert_eq!(manager.root_dir(), dir.to_str().unwrap());
+        assert_eq!(manager.get_image_path("a.jpg"), dir.join("images").join("a.jpg").to_str().unwrap());
+        assert_eq!(manager.part_path("x"), dir.join("images").join(".x.part").to_str().unwrap());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_load_old_metadata() {
+        let dir = std::env::temp_dir().join(format!("meme-fm-{}", std::process::id()));
+        let manager = FileManager::new(dir.to_str().unwrap()).unwrap();
+        
+        // 舊版 metadata.jsonl 沒有 tags/source_site/尺寸等欄位
+        let old_line = r#"{"filename":"a.jpg","description":"a","url":"https://x.com/a.jpg","content_hash":"abc","page_number":1,"downloaded_at":"2024-01-01T00:00:00Z"}"#;
+        std::fs::write(dir.join("metadata.jsonl"), format!("{}\n", old_line)).unwrap();
+        
+        let mut loaded = manager.load_all_metadata().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded[0].tags.is_empty());
+        assert_eq!(loaded[0].source_site, "");
+        assert_eq!(loaded[0].width, None);
+        
+        // 空的欄位不寫出，舊格式原樣保留
+        manager.rewrite_metadata(&loaded).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("metadata.jsonl")).unwrap().trim(), old_line);
+        
+        loaded[0].width = Some(640);
+        loaded[0].source_site = "memes.tw".to_string();
+        manager.rewrite_metadata(&loaded).unwrap();
+        let reloaded = manager.load_all_metadata().unwrap();
+        assert_eq!(reloaded[0].width, Some(640));
+        assert_eq!(reloaded[0].source_site, "memes.tw");
+        
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rename_journal_replay() {
+        let dir = std::env::temp_dir().join(format!("meme-journal-{}", std::process::id()));
+        let manager = FileManager::new(dir.to_str().unwrap()).unwrap();
+        manager.save_image("a.jpg", b"a").unwrap();
+        manager.save_image("b.jpg", b"b").unwrap();
+        
+        let renames = vec![
+            RenameEntry { from: "a.jpg".to_string(), to: "a.png".to_string() },
+            RenameEntry { from: "b.jpg".to_string(), to: "b.png".to_string() },
+        ];
+        let journal = manager.begin_renames(&renames).unwrap();
+        assert!(manager.begin_renames(&renames).is_err());
+        
+        // 模擬改名到一半 crash：只有第一個檔案改了名
+        std::fs::rename(manager.get_image_path("a.jpg"), manager.get_image_path("a.png")).unwrap();
+        
+        let pending = manager.pending_renames().unwrap().unwrap();
+        assert_eq!(pending.renames, journal.renames);
+        assert_eq!(manager.apply_journaled_renames(&pending).unwrap(), renames);
+        assert!(Path::new(&manager.get_image_path("b.png")).exists());
+        
+        manager.commit_renames().unwrap();
+        assert!(manager.pending_renames().unwrap().is_none());
+        
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rename_chain() {
+        let dir = std::env::temp_dir().join(format!("meme-journal-chain-{}", std::process::id()));
+        let manager = FileManager::new(dir.to_str().unwrap()).unwrap();
+        manager.save_image("a.jpg", b"a").unwrap();
+        manager.save_image("b.jpg", b"b").unwrap();
+        
+        // a -> b、b -> c：b 要先移走
+        let renames = vec![
+            RenameEntry { from: "a.jpg".to_string(), to: "b.jpg".to_string() },
+            RenameEntry { from: "b.jpg".to_string(), to: "c.jpg".to_string() },
+        ];
+        let journal = manager.begin_renames(&renames).unwrap();
+        
+        // 模擬第一階段到一半 crash：只有 b 移到暫存名稱
+        std::fs::rename(manager.get_image_path("b.jpg"), manager.staging_path("c.jpg")).unwrap();
+        assert_eq!(manager.apply_journaled_renames(&journal).unwrap(), renames);
+        assert_eq!(std::fs::read(manager.get_image_path("b.jpg")).unwrap(), b"a");
+        assert_eq!(std::fs::read(manager.get_image_path("c.jpg")).unwrap(), b"b");
+        assert!(!Path::new(&manager.get_image_path("a.jpg")).exists());
+        
+        // 重做已完成的交易不變
+        let pending = manager.pending_renames().unwrap().unwrap();
+        assert!(pending.staged);
+        assert_eq!(manager.apply_journaled_renames(&pending).unwrap(), renames);
+        assert_eq!(std::fs::read(manager.get_image_path("c.jpg")).unwrap(), b"b");
+        manager.commit_renames().unwrap();
+        
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rename_collision() {
+        let dir = std::env::temp_dir().join(format!("meme-journal-collision-{}", std::process::id()));
+        let manager = FileManager::new(dir.to_str().unwrap()).unwrap();
+        manager.save_image("a.jpg", b"a").unwrap();
+        manager.save_image("x.jpg", b"x").unwrap();
+        
+        let renames = vec![
+            RenameEntry { from: "a.jpg".to_string(), to: "a.png".to_string() },
+            RenameEntry { from: "x.jpg".to_string(), to: "x.png".to_string() },
+        ];
+        manager.begin_renames(&renames).unwrap();
+        
+        // 中斷後目標被其他檔案佔用：回報錯誤，不移動任何檔案，紀錄保留
+        manager.save_image("x.png", b"other").unwrap();
+        let pending = manager.pending_renames().unwrap().unwrap();
+        assert!(manager.apply_journaled_renames(&pending).is_err());
+        assert!(Path::new(&manager.get_image_path("a.jpg")).exists());
+        assert_eq!(std::fs::read(manager.get_image_path("x.png")).unwrap(), b"other");
+        assert!(manager.pending_renames().unwrap().is_some());
+        
+        // 第二階段才被佔用時同樣不覆蓋
+        std::fs::remove_file(manager.get_image_path("x.png")).unwrap();
+        std::fs::rename(manager.get_image_path("x.jpg"), manager.staging_path("x.png")).unwrap();
+        manager.save_image("x.png", b"other").unwrap();
+        assert!(manager.apply_journaled_renames(&pending).is_err());
+        assert_eq!(std::fs::read(manager.get_image_path("x.png")).unwrap(), b"other");
+        
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
\ No newline at end of file
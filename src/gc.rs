@@ -0,0 +1,262 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// 清理設定（gc.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GcConfig {
+    /// 每次啟動時自動清理
+    pub auto: bool,
+    /// `.tmp`、`.part` 保留的小時數（寫入中的暫存檔不會超過；中斷的下載在這之前可以接續）
+    pub temp_max_age_hours: u64,
+    /// 每個檔案保留最新的幾份備份
+    pub keep_backups: usize,
+    /// 除錯用 HTML 保留的天數
+    pub debug_max_age_days: u64,
+    /// 輪替後的舊 log 保留的天數
+    pub log_max_age_days: u64,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        Self {
+            auto: true,
+            temp_max_age_hours: 24,
+            keep_backups: 3,
+            debug_max_age_days: 7,
+            log_max_age_days: 30,
+        }
+    }
+}
+
+impl GcConfig {
+    pub fn load(data_dir: &str) -> Result<Self> {
+        let path = config_path(data_dir);
+        if !Path::new(&path).exists() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(&fs::read_to_string(&path)?).context("無法解析 gc.json")
+    }
+
+    pub fn save(&self, data_dir: &str) -> Result<()> {
+        fs::create_dir_all(data_dir)?;
+        let path = config_path(data_dir);
+        let temp_path = format!("{}.tmp", path);
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp_path, &path).context("無法儲存 gc.json")?;
+        Ok(())
+    }
+}
+
+fn config_path(data_dir: &str) -> String {
+    format!("{}/gc.json", data_dir)
+}
+
+/// 會被清理的檔案種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArtifactKind {
+    /// `*.tmp`、`*.part`（與續傳資訊 `*.part.json`）
+    Temp,
+    /// `*.backup`、`*.backup.<時間>`
+    Backup,
+    /// `debug/` 下或 `debug_*.html` 的頁面存檔
+    DebugHtml,
+    /// `*.log.1`、`*.log.gz` 等輪替後的 log
+    RotatedLog,
+}
+
+impl ArtifactKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Temp => "暫存檔",
+            Self::Backup => "備份",
+            Self::DebugHtml => "除錯 HTML",
+            Self::RotatedLog => "舊 log",
+        }
+    }
+
+    /// 依檔名判斷種類（`in_debug_dir` 表示位於 `debug/` 目錄下）
+    fn classify(name: &str, in_debug_dir: bool) -> Option<Self> {
+        if name.ends_with(".tmp") || name.ends_with(".part") || name.ends_with(".part.json") {
+            return Some(Self::Temp);
+        }
+        if name.ends_with(".backup") || name.contains(".backup.") {
+            return Some(Self::Backup);
+        }
+        if name.ends_with(".html") && (in_debug_dir || name.starts_with("debug_")) {
+            return Some(Self::DebugHtml);
+        }
+
+        // app.log.1、app.log.2024-01-01、app.log.gz
+        let (_, suffix) = name.split_once(".log.")?;
+        (!suffix.is_empty()).then_some(Self::RotatedLog)
+    }
+}
+
+/// 要刪除的檔案
+#[derive(Debug, Clone)]
+pub struct GcItem {
+    pub path: PathBuf,
+    pub kind: ArtifactKind,
+    pub size: u64,
+}
+
+/// 依保留規則找出要刪除的檔案（不刪除）
+pub fn plan_gc(data_dir: &str, config: &GcConfig, now: SystemTime) -> Result<Vec<GcItem>> {
+    let mut found = Vec::new();
+    collect(Path::new(data_dir), false, &mut found)?;
+
+    let age = |modified: SystemTime| now.duration_since(modified).unwrap_or(Duration::ZERO);
+    let mut plan = Vec::new();
+    let mut backups: HashMap<String, Vec<(SystemTime, GcItem)>> = HashMap::new();
+
+    for (item, modified) in found {
+        let max_age = match item.kind {
+            ArtifactKind::Temp => Duration::from_secs(config.temp_max_age_hours * 3600),
+            ArtifactKind::DebugHtml => Duration::from_secs(config.debug_max_age_days * 86400),
+            ArtifactKind::RotatedLog => Duration::from_secs(config.log_max_age_days * 86400),
+            ArtifactKind::Backup => {
+                // 同一個檔案的備份一起比較（metadata.jsonl.backup、metadata.jsonl.backup.20240101...）
+                let name = item.path.file_name().unwrap_or_default().to_string_lossy();
+                let base = name.split(".backup").next().unwrap_or_default();
+                let key = item.path.with_file_name(base).to_string_lossy().to_string();
+                backups.entry(key).or_default().push((modified, item));
+                continue;
+            }
+        };
+
+        if age(modified) > max_age {
+            plan.push(item);
+        }
+    }
+
+    for (_, mut group) in backups {
+        group.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        plan.extend(group.into_iter().skip(config.keep_backups).map(|(_, item)| item));
+    }
+
+    plan.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(plan)
+}
+
+fn collect(dir: &Path, in_debug_dir: bool, found: &mut Vec<(GcItem, SystemTime)>) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("無法讀取 {}", dir.display())),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if file_type.is_dir() {
+            collect(&entry.path(), in_debug_dir || name == "debug", found)?;
+            continue;
+        }
+
+        let Some(kind) = ArtifactKind::classify(&name, in_debug_dir) else {
+            continue;
+        };
+        let info = entry.metadata()?;
+        found.push((
+            GcItem {
+                path: entry.path(),
+                kind,
+                size: info.len(),
+            },
+            info.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        ));
+    }
+
+    Ok(())
+}
+
+/// 刪除檔案，回傳 (刪除數, 釋放的 bytes)
+pub fn apply_gc(plan: &[GcItem]) -> (usize, u64) {
+    let mut removed = 0;
+    let mut freed = 0;
+
+    for item in plan {
+        match fs::remove_file(&item.path) {
+            Ok(_) => {
+                removed += 1;
+                freed += item.size;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => eout!("  ⚠️  無法刪除 {}: {}", item.path.display(), e),
+        }
+    }
+
+    (removed, freed)
+}
+
+/// 啟動時的自動清理（gc.json 的 `auto` 關閉時不做事），回傳刪除數
+pub fn run_auto(data_dir: &str) -> Result<usize> {
+    let config = GcConfig::load(data_dir)?;
+    if !config.auto {
+        return Ok(0);
+    }
+
+    let plan = plan_gc(data_dir, &config, SystemTime::now())?;
+    Ok(apply_gc(&plan).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_gc() {
+        let dir = std::env::temp_dir().join(format!("meme-gc-{}", std::process::id()));
+        fs::create_dir_all(dir.join("images")).unwrap();
+        fs::create_dir_all(dir.join("debug")).unwrap();
+
+        for name in [
+            "images/a.jpg.tmp",
+            "images/a.jpg",
+            "progress.json",
+            "debug/page_3.html",
+            "crawl.log.1",
+            "crawl.log",
+            "metadata.jsonl.backup",
+            "metadata.jsonl.backup.20240101000000",
+            "metadata.jsonl.backup.20240102000000",
+        ] {
+            fs::write(dir.join(name), "x").unwrap();
+        }
+
+        let config = GcConfig { keep_backups: 2, ..GcConfig::default() };
+        let names = |plan: &[GcItem]| -> Vec<String> {
+            plan.iter()
+                .map(|item| item.path.strip_prefix(&dir).unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+
+        // 剛寫入的暫存檔與 log 都還在保留期限內，三份備份只保留兩份
+        let plan = plan_gc(dir.to_str().unwrap(), &config, SystemTime::now()).unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].kind, ArtifactKind::Backup);
+
+        let later = SystemTime::now() + Duration::from_secs(40 * 86400);
+        let plan = plan_gc(dir.to_str().unwrap(), &config, later).unwrap();
+        let planned = names(&plan);
+        assert!(planned.contains(&"images/a.jpg.tmp".to_string()));
+        assert!(planned.contains(&"debug/page_3.html".to_string()));
+        assert!(planned.contains(&"crawl.log.1".to_string()));
+        assert_eq!(plan.iter().filter(|item| item.kind == ArtifactKind::Backup).count(), 1);
+        assert!(!planned.iter().any(|name| name == "images/a.jpg" || name == "crawl.log"));
+
+        assert_eq!(apply_gc(&plan).0, 4);
+        assert!(dir.join("images/a.jpg").exists());
+        assert!(!dir.join("images/a.jpg.tmp").exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
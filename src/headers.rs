@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderName, HeaderValue, USER_AGENT};
+use reqwest::Request;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// User-Agent 輪替與各網站 headers 的設定檔
+pub const HEADERS_FILE: &str = "headers.json";
+
+/// 請求 headers 設定（headers.json）
+///
+/// ```json
+/// {
+///   "user_agents": ["Mozilla/5.0 (Windows NT 10.0; ...)", "Mozilla/5.0 (Macintosh; ...)"],
+///   "sites": {
+///     "imgflip.com": { "Referer": "{origin}/", "Accept-Language": "en-US,en;q=0.9", "Cookie": "..." }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeaderConfig {
+    /// 每個請求輪流使用的 User-Agent（空白時用各 client 原本的）
+    pub user_agents: Vec<String>,
+    /// 各網域額外的 headers（子網域也適用），值可用 `{origin}`、`{url}`
+    pub sites: HashMap<String, BTreeMap<String, String>>,
+}
+
+impl HeaderConfig {
+    pub fn is_empty(&self) -> bool {
+        self.user_agents.is_empty() && self.sites.is_empty()
+    }
+}
+
+/// 套用 headers 到每個送出的請求（頁面、圖片下載、搜尋服務共用）
+#[derive(Debug)]
+pub struct HeaderRotator {
+    user_agents: Vec<HeaderValue>,
+    /// (網域, headers)，網域短的在前，較精確的網域後套用而覆蓋
+    sites: Vec<(String, Vec<(HeaderName, String)>)>,
+    next: AtomicUsize,
+}
+
+impl HeaderRotator {
+    pub fn new(config: &HeaderConfig) -> Result<Self> {
+        let user_agents = config
+            .user_agents
+            .iter()
+            .map(|ua| HeaderValue::from_str(ua).with_context(|| format!("無效的 User-Agent: {}", ua)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut sites = Vec::new();
+        for (domain, headers) in &config.sites {
+            let mut parsed = Vec::new();
+            for (name, value) in headers {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("{} 的 header 名稱無效: {}", domain, name))?;
+                HeaderValue::from_str(value)
+                    .with_context(|| format!("{} 的 {} 值無效", domain, name))?;
+                parsed.push((name, value.clone()));
+            }
+            sites.push((domain.trim_start_matches("www.").to_ascii_lowercase(), parsed));
+        }
+        sites.sort_by_key(|(domain, _)| domain.len());
+
+        Ok(Self {
+            user_agents,
+            sites,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// 讀取資料目錄的 headers.json（不存在或沒有設定時為 None）
+    pub fn load(data_dir: &str) -> Result<Option<Arc<Self>>> {
+        let path = format!("{}/{}", data_dir, HEADERS_FILE);
+        if !Path::new(&path).exists() {
+            return Ok(None);
+        }
+
+        let config: HeaderConfig = serde_json::from_str(&fs::read_to_string(&path)?)
+            .with_context(|| format!("無法解析 {}", path))?;
+        if config.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Arc::new(Self::new(&config)?)))
+    }
+
+    pub fn user_agent_count(&self) -> usize {
+        self.user_agents.len()
+    }
+
+    pub fn site_count(&self) -> usize {
+        self.sites.len()
+    }
+
+    /// 換上下一個 User-Agent 並加上符合網域的 headers（網站設定的 User-Agent 優先）
+    pub fn apply(&self, request: &mut Request) {
+        if !self.user_agents.is_empty() {
+            let index = self.next.fetch_add(1, Ordering::Relaxed) % self.user_agents.len();
+            request.headers_mut().insert(USER_AGENT, self.user_agents[index].clone());
+        }
+
+        let host = request.url().host_str().unwrap_or_default().to_ascii_lowercase();
+        let origin = request.url().origin().ascii_serialization();
+        let url = request.url().to_string();
+
+        for (domain, headers) in &self.sites {
+            if host != *domain && !host.ends_with(&format!(".{}", domain)) {
+                continue;
+            }
+            for (name, template) in headers {
+                let value = template.replace("{origin}", &origin).replace("{url}", &url);
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    request.headers_mut().insert(name.clone(), value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_headers() {
+        let config: HeaderConfig = serde_json::from_value(serde_json::json!({
+            "user_agents": ["ua-1", "ua-2"],
+            "sites": {
+                "imgflip.com": { "Referer": "{origin}/", "Accept-Language": "en-US" },
+                "i.imgflip.com": { "Referer": "{url}", "User-Agent": "image-ua" },
+            },
+        }))
+        .unwrap();
+        let rotator = HeaderRotator::new(&config).unwrap();
+        let request = |url: &str| {
+            let mut request = reqwest::Client::new().get(url).build().unwrap();
+            rotator.apply(&mut request);
+            request
+        };
+        let header = |request: &Request, name: &str| {
+            request.headers().get(name).map(|v| v.to_str().unwrap().to_string())
+        };
+
+        let first = request("https://example.com/a");
+        let second = request("https://example.com/b");
+        assert_eq!(header(&first, "user-agent").as_deref(), Some("ua-1"));
+        assert_eq!(header(&second, "user-agent").as_deref(), Some("ua-2"));
+        assert_eq!(header(&first, "referer"), None);
+
+        let page = request("https://imgflip.com/memetemplates?page=2");
+        assert_eq!(header(&page, "referer").as_deref(), Some("https://imgflip.com/"));
+        assert_eq!(header(&page, "accept-language").as_deref(), Some("en-US"));
+
+        // 較精確的網域覆蓋上層網域的設定
+        let image = request("https://i.imgflip.com/1bij.jpg");
+        assert_eq!(header(&image, "referer").as_deref(), Some("https://i.imgflip.com/1bij.jpg"));
+        assert_eq!(header(&image, "user-agent").as_deref(), Some("image-ua"));
+        assert_eq!(header(&image, "accept-language").as_deref(), Some("en-US"));
+
+        let invalid: HeaderConfig = serde_json::from_value(serde_json::json!({
+            "sites": { "a.com": { "bad header": "x" } },
+        }))
+        .unwrap();
+        assert!(HeaderRotator::new(&invalid).is_err());
+    }
+}
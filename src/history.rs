@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// 每個命令完成時追加一筆的操作紀錄
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// 值可能含有帳密或 token 的旗標，記錄時以 `***` 取代
+const SECRET_FLAGS: &[&str] = &["webhook", "token", "password", "secret", "api-key"];
+
+/// 命令結束狀態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    Ok,
+    Error,
+}
+
+/// 一次命令執行（history.jsonl 的一列）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationRecord {
+    pub command: String,
+    /// 子命令的參數（秘密值已遮蔽）
+    pub args: Vec<String>,
+    /// 執行者（`USER`/`USERNAME` 環境變數）
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub host: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub status: ExitStatus,
+    /// 失敗時的錯誤訊息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 改變資料集的命令執行後的數量（images、images_added、unique_hashes 等；取自成長快照）
+    #[serde(default)]
+    pub counts: BTreeMap<String, i64>,
+}
+
+impl OperationRecord {
+    /// 以目前的環境建立紀錄（`args` 是子命令之後的參數）
+    pub fn new(command: &str, args: &[String], started_at: DateTime<Utc>, result: &Result<()>) -> Self {
+        let (status, error) = match result {
+            Ok(()) => (ExitStatus::Ok, None),
+            Err(e) => (ExitStatus::Error, Some(format!("{:#}", e))),
+        };
+        let env = |names: &[&str]| names.iter().find_map(|name| std::env::var(name).ok()).filter(|v| !v.is_empty());
+
+        Self {
+            command: command.to_string(),
+            args: redact_args(args),
+            user: env(&["USER", "USERNAME"]),
+            host: env(&["HOSTNAME", "COMPUTERNAME"]),
+            started_at,
+            duration_ms: (Utc::now() - started_at).num_milliseconds().max(0) as u64,
+            status,
+            error,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_count(mut self, name: &str, value: i64) -> Self {
+        self.counts.insert(name.to_string(), value);
+        self
+    }
+}
+
+/// 遮蔽秘密旗標的值（`--block-webhook https://...` -> `--block-webhook ***`）
+pub fn redact_args(args: &[String]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut hide_next = false;
+    for arg in args {
+        if hide_next {
+            redacted.push("***".to_string());
+            hide_next = false;
+            continue;
+        }
+        hide_next = arg.starts_with("--") && SECRET_FLAGS.iter().any(|flag| arg.contains(flag));
+        redacted.push(arg.clone());
+    }
+    redacted
+}
+
+/// 追加一筆紀錄（多人共用資料目錄時各自追加，不改寫既有內容）
+pub fn append(data_dir: &str, record: &OperationRecord) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(Path::new(data_dir).join(HISTORY_FILE))
+        .context("無法開啟 history.jsonl")?;
+    writeln!(file, "{}", serde_json::to_string(record)?).context("無法寫入 history.jsonl")?;
+    Ok(())
+}
+
+/// 讀取所有紀錄（舊的在前；檔案不存在時為空，無法解析的行略過）
+pub fn load(data_dir: &str) -> Result<Vec<OperationRecord>> {
+    let path = Path::new(data_dir).join(HISTORY_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(fs::read_to_string(&path)
+        .with_context(|| format!("無法讀取 {}", path.display()))?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_load() {
+        let dir = std::env::temp_dir().join(format!("meme-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let data_dir = dir.to_str().unwrap();
+
+        let args: Vec<String> = ["--upload", "--block-webhook", "https://hooks.example/abc", "--concurrency", "2"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let ok = OperationRecord::new("search", &args, Utc::now(), &Ok(()))
+            .with_count("images", 120)
+            .with_count("images_added", 0);
+        let failed = OperationRecord::new("crawl", &[], Utc::now(), &Err(anyhow::anyhow!("連線失敗")));
+        append(data_dir, &ok).unwrap();
+        append(data_dir, &failed).unwrap();
+
+        let history = load(data_dir).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].args, vec!["--upload", "--block-webhook", "***", "--concurrency", "2"]);
+        assert_eq!(history[0].counts["images"], 120);
+        assert_eq!(history[0].status, ExitStatus::Ok);
+        assert_eq!(history[1].status, ExitStatus::Error);
+        assert_eq!(history[1].error.as_deref(), Some("連線失敗"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,161 @@
+use crate::file_manager::FileManager;
+use crate::reverse_search::ReverseSearchResult;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+
+/// 刪除操作的影響摘要（由計畫計算，套用前顯示並寫入 `impact_<operation>.json`）
+#[derive(Debug, Clone, Serialize)]
+pub struct ImpactSummary {
+    /// 操作名稱（dedup、prune...）
+    pub operation: String,
+    pub computed_at: DateTime<Utc>,
+    /// 會刪除的檔案數（實際存在的）
+    pub files_deleted: usize,
+    /// 釋放的空間（依實際檔案大小）
+    pub bytes_reclaimed: u64,
+    /// 計畫中但檔案已不存在的項目
+    pub files_missing: usize,
+    /// 會移除的 metadata 筆數
+    pub metadata_rows_removed: usize,
+    /// 改指向保留檔案的搜尋結果數
+    pub search_results_remapped: usize,
+    /// 對應的圖片被刪除、之後不再使用的搜尋結果數
+    pub search_results_orphaned: usize,
+    /// 會刪除的檔名
+    pub files: Vec<String>,
+}
+
+impl ImpactSummary {
+    /// 依刪除計畫計算影響
+    ///
+    /// `deletions` 是 刪除的檔名 -> 搜尋結果改指向的檔名（None 表示不改指向）。
+    pub fn compute(
+        operation: &str,
+        file_manager: &FileManager,
+        metadata: &[ImageMetadata],
+        results: &[ReverseSearchResult],
+        deletions: &HashMap<String, Option<String>>,
+    ) -> Self {
+        let mut files: Vec<String> = deletions.keys().cloned().collect();
+        files.sort();
+
+        let mut files_deleted = 0;
+        let mut bytes_reclaimed = 0;
+        for filename in &files {
+            if let Ok(info) = fs::metadata(file_manager.get_image_path(filename)) {
+                files_deleted += 1;
+                bytes_reclaimed += info.len();
+            }
+        }
+
+        let mut search_results_remapped = 0;
+        let mut search_results_orphaned = 0;
+        for result in results {
+            match deletions.get(&result.filename) {
+                Some(Some(_)) => search_results_remapped += 1,
+                Some(None) => search_results_orphaned += 1,
+                None => {}
+            }
+        }
+
+        Self {
+            operation: operation.to_string(),
+            computed_at: Utc::now(),
+            files_missing: files.len() - files_deleted,
+            files_deleted,
+            bytes_reclaimed,
+            metadata_rows_removed: metadata.iter().filter(|m| deletions.contains_key(&m.filename)).count(),
+            search_results_remapped,
+            search_results_orphaned,
+            files,
+        }
+    }
+
+    pub fn print(&self) {
+        out!("╔══════════════════════════════════╗");
+        out!("║       📊 刪除影響預估           ║");
+        out!("╠══════════════════════════════════╣");
+        out!("║ 刪除檔案:   {:>18} ║", self.files_deleted);
+        out!("║ 釋放空間:   {:>15.1} MB ║", self.bytes_reclaimed as f64 / 1_048_576.0);
+        out!("║ 已不存在:   {:>18} ║", self.files_missing);
+        out!("║ 移除 metadata: {:>15} ║", self.metadata_rows_removed);
+        out!("║ 改指向的搜尋結果: {:>12} ║", self.search_results_remapped);
+        out!("║ 不再使用的搜尋結果: {:>10} ║", self.search_results_orphaned);
+        out!("╚══════════════════════════════════╝");
+    }
+
+    /// 寫入 `impact_<operation>.json`，回傳路徑
+    pub fn save(&self, data_dir: &str) -> Result<String> {
+        let path = format!("{}/impact_{}.json", data_dir, self.operation);
+        let temp_path = format!("{}.tmp", path);
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp_path, &path).with_context(|| format!("無法寫入 {}", path))?;
+        Ok(path)
+    }
+
+    /// 超過 `--max-delete` 上限時回傳錯誤（不套用）
+    pub fn check_limit(&self, max_delete: Option<usize>) -> Result<()> {
+        match max_delete {
+            Some(max) if self.files_deleted > max => anyhow::bail!(
+                "{} 會刪除 {} 個檔案，超過上限 --max-delete {}，未執行",
+                self.operation,
+                self.files_deleted,
+                max
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_impact() {
+        let dir = std::env::temp_dir().join(format!("meme-impact-{}", std::process::id()));
+        let file_manager = FileManager::new(dir.to_str().unwrap()).unwrap();
+        file_manager.save_image("b.jpg", b"12345").unwrap();
+
+        let metadata: Vec<ImageMetadata> = ["a.jpg", "b.jpg", "c.jpg", "b.jpg"]
+            .iter()
+            .map(|filename| serde_json::from_value(serde_json::json!({
+                "filename": filename, "description": "", "url": "", "content_hash": "h",
+                "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z",
+            })).unwrap())
+            .collect();
+        let result = |filename: &str| ReverseSearchResult {
+            filename: filename.to_string(),
+            service: "bing".to_string(),
+            suggested_title: None,
+            keywords: vec![],
+            related_sites: vec![],
+            best_guess: None,
+            similarity: None,
+            searched_at: Utc::now(),
+        };
+        let results = vec![result("a.jpg"), result("b.jpg"), result("c.jpg")];
+
+        // b 是 a 的重複（搜尋結果改指向 a），c 已不存在
+        let deletions = HashMap::from([
+            ("b.jpg".to_string(), Some("a.jpg".to_string())),
+            ("c.jpg".to_string(), None),
+        ]);
+        let summary = ImpactSummary::compute("dedup", &file_manager, &metadata, &results, &deletions);
+
+        assert_eq!((summary.files_deleted, summary.bytes_reclaimed, summary.files_missing), (1, 5, 1));
+        assert_eq!(summary.metadata_rows_removed, 3);
+        assert_eq!((summary.search_results_remapped, summary.search_results_orphaned), (1, 1));
+        assert!(summary.check_limit(Some(1)).is_ok());
+        assert!(summary.check_limit(Some(0)).is_err());
+
+        let path = summary.save(dir.to_str().unwrap()).unwrap();
+        assert!(path.ends_with("impact_dedup.json"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
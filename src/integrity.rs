@@ -0,0 +1,238 @@
+use crate::file_manager::FileManager;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// 檔案內容與 metadata 的 hash 不符
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashMismatch {
+    pub filename: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for HashMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "檔案內容已損毀: {}（預期 hash {}...，實際 {}...）",
+            self.filename,
+            &self.expected[..12.min(self.expected.len())],
+            &self.actual[..12.min(self.actual.len())],
+        )
+    }
+}
+
+impl std::error::Error for HashMismatch {}
+
+/// 計算 SHA-256（與下載時相同的格式）
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+/// 讀取圖片並確認內容與 metadata 的 hash 一致
+///
+/// 不一致時回傳 `HashMismatch` 錯誤（可用 `downcast_ref` 判斷）。
+pub fn read_verified(file_manager: &FileManager, metadata: &ImageMetadata) -> Result<Vec<u8>> {
+    let path = file_manager.get_image_path(&metadata.filename);
+    let bytes = fs::read(&path)
+        .with_context(|| format!("無法讀取圖片: {}", metadata.filename))?;
+
+    let actual = sha256_hex(&bytes);
+    if actual != metadata.content_hash {
+        return Err(HashMismatch {
+            filename: metadata.filename.clone(),
+            expected: metadata.content_hash.clone(),
+            actual,
+        }
+        .into());
+    }
+
+    Ok(bytes)
+}
+
+/// `verify` 的結果
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// 比對過 hash 的檔案數
+    pub checked: usize,
+    /// 內容與 metadata 的 hash 不符
+    pub corrupted: Vec<HashMismatch>,
+    /// metadata 記錄的檔案不存在
+    pub missing: Vec<String>,
+}
+
+/// 重新計算所有圖片的 hash，與 metadata 的 `content_hash` 比對
+///
+/// 沒有 `content_hash` 的舊紀錄略過。
+pub fn verify_images(file_manager: &FileManager, metadata_list: &[ImageMetadata]) -> Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+
+    for metadata in metadata_list {
+        if metadata.content_hash.is_empty() {
+            continue;
+        }
+
+        let path = file_manager.get_image_path(&metadata.filename);
+        if !Path::new(&path).exists() {
+            report.missing.push(metadata.filename.clone());
+            continue;
+        }
+
+        report.checked += 1;
+        match read_verified(file_manager, metadata) {
+            Ok(_) => {}
+            Err(e) => match e.downcast::<HashMismatch>() {
+                Ok(mismatch) => report.corrupted.push(mismatch),
+                Err(e) => return Err(e),
+            },
+        }
+    }
+
+    Ok(report)
+}
+
+fn queue_path(file_manager: &FileManager) -> String {
+    format!("{}/redownload_queue.jsonl", file_manager.root_dir())
+}
+
+/// 將損毀的圖片加入重新下載佇列（redownload_queue.jsonl，同一檔名只記一次）
+pub fn queue_redownload(file_manager: &FileManager, metadata: &ImageMetadata) -> Result<()> {
+    if load_redownload_queue(file_manager)?
+        .iter()
+        .any(|m| m.filename == metadata.filename)
+    {
+        return Ok(());
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(queue_path(file_manager))
+        .context("無法開啟 redownload_queue.jsonl")?;
+
+    writeln!(file, "{}", serde_json::to_string(metadata)?)?;
+    Ok(())
+}
+
+/// 讀取重新下載佇列
+pub fn load_redownload_queue(file_manager: &FileManager) -> Result<Vec<ImageMetadata>> {
+    let path = queue_path(file_manager);
+    if !Path::new(&path).exists() {
+        return Ok(vec![]);
+    }
+
+    let reader = BufReader::new(fs::File::open(&path)?);
+    let mut queue = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        queue.push(serde_json::from_str(&line).context("解析 redownload_queue.jsonl 失敗")?);
+    }
+
+    Ok(queue)
+}
+
+/// 重新下載佇列中的圖片，內容 hash 與原本一致才覆蓋本地檔案
+///
+/// 成功的項目從佇列移除，回傳 (成功數, 失敗數)。
+pub async fn redownload_queued(file_manager: &FileManager) -> Result<(usize, usize)> {
+    let queue = load_redownload_queue(file_manager)?;
+    let mut remaining = Vec::new();
+    let mut fixed = 0;
+
+    for metadata in queue {
+        match redownload_one(file_manager, &metadata).await {
+            Ok(()) => {
+                out!("  ✅ {}", metadata.filename);
+                fixed += 1;
+            }
+            Err(e) => {
+                eout!("  ❌ {}: {}", metadata.filename, e);
+                remaining.push(metadata);
+            }
+        }
+    }
+
+    let failed = remaining.len();
+    let path = queue_path(file_manager);
+    let temp_path = format!("{}.tmp", path);
+    let mut content = String::new();
+    for metadata in &remaining {
+        content.push_str(&serde_json::to_string(metadata)?);
+        content.push('\n');
+    }
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, &path)?;
+
+    Ok((fixed, failed))
+}
+
+async fn redownload_one(file_manager: &FileManager, metadata: &ImageMetadata) -> Result<()> {
+    let bytes = reqwest::get(&metadata.url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    let actual = sha256_hex(&bytes);
+    if actual != metadata.content_hash {
+        anyhow::bail!("來源內容已變更（hash {}...）", &actual[..12]);
+    }
+
+    let path = file_manager.get_image_path(&metadata.filename);
+    let temp_path = format!("{}.tmp", path);
+    fs::write(&temp_path, &bytes)?;
+    fs::rename(&temp_path, &path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_verified() {
+        let dir = std::env::temp_dir().join(format!("meme-integrity-{}", std::process::id()));
+        let file_manager = FileManager::new(dir.to_str().unwrap()).unwrap();
+
+        let content = b"image bytes";
+        let mut metadata = ImageMetadata {
+            description: "測試".to_string(),
+            ..ImageMetadata::for_test("a.jpg", &sha256_hex(content))
+        };
+        file_manager.save_image("a.jpg", content).unwrap();
+
+        assert_eq!(read_verified(&file_manager, &metadata).unwrap(), content);
+
+        metadata.content_hash = sha256_hex(b"other");
+        let err = read_verified(&file_manager, &metadata).unwrap_err();
+        assert!(err.downcast_ref::<HashMismatch>().is_some());
+
+        queue_redownload(&file_manager, &metadata).unwrap();
+        queue_redownload(&file_manager, &metadata).unwrap();
+        assert_eq!(load_redownload_queue(&file_manager).unwrap().len(), 1);
+
+        // 截斷的檔案與不存在的檔案
+        let mut missing = metadata.clone();
+        missing.filename = "gone.jpg".to_string();
+        metadata.content_hash = sha256_hex(content);
+        fs::write(file_manager.get_image_path("a.jpg"), &content[..5]).unwrap();
+        let report = verify_images(&file_manager, &[metadata, missing]).unwrap();
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.corrupted[0].filename, "a.jpg");
+        assert_eq!(report.missing, vec!["gone.jpg".to_string()]);
+        assert!(!Path::new(&format!("{}.tmp", file_manager.get_image_path("a.jpg"))).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
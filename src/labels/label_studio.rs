@@ -0,0 +1,246 @@
+use super::LabelRecord;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+
+/// 搭配匯出任務使用的標註介面（Label Studio 專案的 Labeling Interface → Code）
+///
+/// 關鍵字與標籤每行一個；`$image` 是任務的 `data.image`。
+pub const LABELING_CONFIG: &str = r#"<View>
+  <Image name="image" value="$image"/>
+  <Header value="$filename"/>
+  <TextArea name="title" toName="image" placeholder="標題" maxSubmissions="1" editable="true"/>
+  <TextArea name="keywords" toName="image" placeholder="關鍵字（每行一個）" editable="true"/>
+  <TextArea name="tags" toName="image" placeholder="標籤（每行一個）" editable="true"/>
+</View>
+"#;
+
+/// 預標註的模型版本（Label Studio 會顯示在 prediction 上）
+const MODEL_VERSION: &str = "reverse-search";
+
+/// Label Studio 任務
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub data: TaskData,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub predictions: Vec<Annotation>,
+    #[serde(default, skip_serializing)]
+    pub annotations: Vec<Annotation>,
+}
+
+/// 任務資料：圖片位置與對應回 metadata 用的欄位
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskData {
+    pub image: String,
+    #[serde(default)]
+    pub filename: String,
+    #[serde(default)]
+    pub content_hash: String,
+}
+
+/// 標註或預標註（兩者格式相同）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Annotation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_version: Option<String>,
+    #[serde(default)]
+    pub result: Vec<Region>,
+    /// 標註者跳過的任務
+    #[serde(default, skip_serializing)]
+    pub was_cancelled: bool,
+}
+
+/// 單一欄位的標註結果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Region {
+    pub from_name: String,
+    #[serde(default)]
+    pub to_name: String,
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    #[serde(default)]
+    pub value: Value,
+}
+
+impl Region {
+    fn textarea(from_name: &str, lines: &[String]) -> Self {
+        Self {
+            from_name: from_name.to_string(),
+            to_name: "image".to_string(),
+            kind: "textarea".to_string(),
+            value: json!({ "text": lines }),
+        }
+    }
+
+    /// 欄位的值（TextArea 的 `text`，也接受 Choices/Taxonomy 改成的介面）
+    fn values(&self) -> Vec<String> {
+        let mut values = Vec::new();
+        for key in ["text", "choices", "taxonomy"] {
+            collect_strings(&self.value[key], &mut values);
+        }
+
+        // TextArea 一格可能貼了多行
+        values
+            .iter()
+            .flat_map(|v| v.lines())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+}
+
+/// 收集字串（Taxonomy 的值是路徑陣列，取最後一層）
+fn collect_strings(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => out.push(s.clone()),
+        Value::Array(items) => {
+            for item in items {
+                match item {
+                    Value::String(s) => out.push(s.clone()),
+                    Value::Array(path) => {
+                        if let Some(Value::String(last)) = path.last() {
+                            out.push(last.clone());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 建立任務：圖片預設用原始網址，指定前綴時改用 `前綴 + 檔名`（例如本地儲存的 `/data/local-files/?d=images/`）
+///
+/// metadata 既有的標題、關鍵字與標籤（含 enrich 的搜尋結果）作為預標註，標註者只需修正。
+pub fn build_tasks(metadata: &[ImageMetadata], image_prefix: Option<&str>) -> Vec<Task> {
+    metadata
+        .iter()
+        .map(|m| {
+            let mut result = Vec::new();
+            if let Some(title) = &m.suggested_title {
+                result.push(Region::textarea("title", std::slice::from_ref(title)));
+            }
+            if !m.keywords.is_empty() {
+                result.push(Region::textarea("keywords", &m.keywords));
+            }
+            if !m.tags.is_empty() {
+                result.push(Region::textarea("tags", &m.tags));
+            }
+
+            Task {
+                data: TaskData {
+                    image: match image_prefix {
+                        Some(prefix) => format!("{}{}", prefix, m.filename),
+                        None => m.url.clone(),
+                    },
+                    filename: m.filename.clone(),
+                    content_hash: m.content_hash.clone(),
+                },
+                predictions: if result.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![Annotation {
+                        model_version: Some(MODEL_VERSION.to_string()),
+                        result,
+                        was_cancelled: false,
+                    }]
+                },
+                annotations: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+/// 寫出任務檔與標註介面設定（`<檔名>.xml`），先寫暫存檔再改名
+pub fn write_tasks(path: &str, tasks: &[Task]) -> Result<String> {
+    let temp_path = format!("{}.tmp", path);
+    fs::write(&temp_path, serde_json::to_string_pretty(tasks)?)?;
+    fs::rename(&temp_path, path).with_context(|| format!("無法寫入 {}", path))?;
+
+    let config_path = format!("{}.xml", path.strip_suffix(".json").unwrap_or(path));
+    fs::write(&config_path, LABELING_CONFIG).with_context(|| format!("無法寫入 {}", config_path))?;
+    Ok(config_path)
+}
+
+/// 解析 Label Studio 的 JSON 匯出，每個已完成的任務取最後一份未跳過的標註
+///
+/// 沒有標註的任務略過（預標註不算）；標註中沒有的欄位視為清空，
+/// 所以匯入時應以取代方式套用。
+pub fn parse_export(content: &str) -> Result<Vec<LabelRecord>> {
+    let tasks: Vec<Task> = serde_json::from_str(content).context("無法解析 Label Studio 匯出檔")?;
+
+    let labels = tasks
+        .into_iter()
+        .filter_map(|task| {
+            let annotation = task.annotations.into_iter().rev().find(|a| !a.was_cancelled)?;
+            let field = |name: &str| -> Vec<String> {
+                annotation
+                    .result
+                    .iter()
+                    .filter(|region| region.from_name == name)
+                    .flat_map(Region::values)
+                    .collect()
+            };
+
+            Some(LabelRecord {
+                filename: task.data.filename,
+                content_hash: task.data.content_hash,
+                url: String::new(),
+                title: field("title").into_iter().next(),
+                keywords: field("keywords"),
+                tags: field("tags"),
+            })
+        })
+        .collect();
+
+    Ok(labels)
+}
+
+/// 內容是否為 Label Studio 的任務陣列（標註檔是物件）
+pub fn is_label_studio(content: &str) -> bool {
+    content.trim_start().starts_with('[')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasks_and_import() {
+        let metadata = ImageMetadata {
+            keywords: vec!["doge".to_string()],
+            suggested_title: Some("Doge".to_string()),
+            ..ImageMetadata::for_test("doge.jpg", "h1")
+        };
+
+        let tasks = build_tasks(std::slice::from_ref(&metadata), Some("/data/local-files/?d=images/"));
+        assert_eq!(tasks[0].data.image, "/data/local-files/?d=images/doge.jpg");
+        let exported = serde_json::to_value(&tasks).unwrap();
+        assert_eq!(exported[0]["predictions"][0]["result"][1]["value"]["text"][0], "doge");
+        assert!(exported[0].get("annotations").is_none());
+        assert!(build_tasks(std::slice::from_ref(&metadata), None)[0].data.image.starts_with("https://"));
+
+        // 標註者修改了標題與關鍵字；第二個任務只有被跳過的標註
+        let content = r#"[
+            {"id": 1, "data": {"image": "x", "filename": "doge.jpg", "content_hash": "h1"},
+             "annotations": [{"id": 7, "result": [
+                {"from_name": "title", "to_name": "image", "type": "textarea", "value": {"text": ["Doge (Shiba Inu)"]}},
+                {"from_name": "keywords", "to_name": "image", "type": "textarea", "value": {"text": ["doge\nshiba inu", " "]}},
+                {"from_name": "tags", "to_name": "image", "type": "taxonomy", "value": {"taxonomy": [["animal", "dog"]]}}
+             ]}]},
+            {"id": 2, "data": {"image": "y", "filename": "cat.jpg"},
+             "annotations": [{"was_cancelled": true, "result": []}]}
+        ]"#;
+        assert!(is_label_studio(content));
+
+        let labels = parse_export(content).unwrap();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].content_hash, "h1");
+        assert_eq!(labels[0].title.as_deref(), Some("Doge (Shiba Inu)"));
+        assert_eq!(labels[0].keywords, vec!["doge".to_string(), "shiba inu".to_string()]);
+        assert_eq!(labels[0].tags, vec!["dog".to_string()]);
+    }
+}
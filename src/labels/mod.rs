@@ -0,0 +1,337 @@
+//! 標註的匯入/匯出格式
+//!
+//! 標註是 metadata 中人工或工具給的欄位：`title`（寫入 `suggested_title`）、`keywords`、`tags`。
+//! 匯出檔是固定格式的 JSON，外部標註工具或試算表處理後可以再匯入：
+//!
+//! ```json
+//! {
+//!   "schema": "meme-data-crawler/labels",
+//!   "version": 1,
+//!   "exported_at": "2024-01-01T00:00:00Z",
+//!   "labels": [
+//!     {
+//!       "filename": "doge_p1_0.jpg",
+//!       "content_hash": "9f86d0...",
+//!       "url": "https://example.com/doge.jpg",
+//!       "title": "Doge",
+//!       "keywords": ["doge", "shiba inu"],
+//!       "tags": ["animal"]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! - 匯入時以 `content_hash` 對應（同內容的重複圖片一起更新），沒有雜湊時用 `filename`
+//! - 除了對應用的欄位，其他欄位都可省略；`url` 只供參考，匯入時忽略
+//! - 試算表可匯入 CSV：欄位 `filename,content_hash,title,keywords,tags`，多值以 `|` 分隔（與 `export` 的 CSV 相同）
+//! - 新增欄位只會是選填的；不相容的變更會提高 `version`，舊版本的檔案匯入時會被拒絕
+//! - Label Studio 的任務與標註匯出見 `label_studio` 模組，匯入時自動辨識
+
+pub mod label_studio;
+
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// 格式名稱（`schema` 欄位）
+pub const SCHEMA: &str = "meme-data-crawler/labels";
+/// 目前的格式版本
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 標註檔
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelFile {
+    pub schema: String,
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported_at: Option<DateTime<Utc>>,
+    pub labels: Vec<LabelRecord>,
+}
+
+/// 一張圖片的標註
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LabelRecord {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub filename: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub content_hash: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// 匯入方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// 關鍵字與標籤加到既有的後面，有標題時覆蓋
+    #[default]
+    Merge,
+    /// 以匯入的標註取代既有的標題、關鍵字與標籤
+    Replace,
+}
+
+/// 標註檔的格式（匯入時依副檔名與內容辨識）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelFormat {
+    Json,
+    Csv,
+    LabelStudio,
+}
+
+impl LabelFormat {
+    /// 預設的匯入方式：Label Studio 的標註是人工確認過的完整結果，取代既有的標註
+    pub fn default_mode(&self) -> ImportMode {
+        match self {
+            Self::LabelStudio => ImportMode::Replace,
+            Self::Json | Self::Csv => ImportMode::Merge,
+        }
+    }
+}
+
+/// 匯入的結果
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// 對應到圖片的標註數
+    pub matched: usize,
+    /// 實際有變更的圖片數
+    pub updated: usize,
+    /// 對應不到任何圖片的標註（檔名或雜湊）
+    pub unmatched: Vec<String>,
+}
+
+/// 從 metadata 建立標註（依 metadata 順序）
+pub fn build_labels(metadata: &[ImageMetadata]) -> Vec<LabelRecord> {
+    metadata
+        .iter()
+        .map(|m| LabelRecord {
+            filename: m.filename.clone(),
+            content_hash: m.content_hash.clone(),
+            url: m.url.clone(),
+            title: m.suggested_title.clone(),
+            keywords: m.keywords.clone(),
+            tags: m.tags.clone(),
+        })
+        .collect()
+}
+
+/// 寫出標註檔（先寫暫存檔再改名）
+pub fn write_labels(path: &str, labels: Vec<LabelRecord>) -> Result<()> {
+    let file = LabelFile {
+        schema: SCHEMA.to_string(),
+        version: SCHEMA_VERSION,
+        exported_at: Some(Utc::now()),
+        labels,
+    };
+
+    let temp_path = format!("{}.tmp", path);
+    fs::write(&temp_path, serde_json::to_string_pretty(&file)?)?;
+    fs::rename(&temp_path, path).with_context(|| format!("無法寫入 {}", path))?;
+    Ok(())
+}
+
+/// 讀取標註檔（副檔名 `.csv` 時以 CSV 解析，JSON 陣列視為 Label Studio 的匯出）
+pub fn read_labels(path: &str) -> Result<(LabelFormat, Vec<LabelRecord>)> {
+    let is_csv = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        return Ok((LabelFormat::Csv, read_csv(path)?));
+    }
+
+    let content = fs::read_to_string(path).with_context(|| format!("無法讀取標註檔: {}", path))?;
+    if label_studio::is_label_studio(&content) {
+        return Ok((LabelFormat::LabelStudio, label_studio::parse_export(&content)?));
+    }
+    Ok((LabelFormat::Json, parse_json(&content)?))
+}
+
+/// 解析 JSON 標註檔並檢查格式名稱與版本
+pub fn parse_json(content: &str) -> Result<Vec<LabelRecord>> {
+    let file: LabelFile = serde_json::from_str(content).context("無法解析標註檔")?;
+    if file.schema != SCHEMA {
+        anyhow::bail!("不是標註檔（schema: {}，應為 {}）", file.schema, SCHEMA);
+    }
+    if file.version != SCHEMA_VERSION {
+        anyhow::bail!("不支援的標註檔版本: {}（目前為 {}）", file.version, SCHEMA_VERSION);
+    }
+    Ok(file.labels)
+}
+
+fn read_csv(path: &str) -> Result<Vec<LabelRecord>> {
+    let mut reader = csv::Reader::from_path(path).with_context(|| format!("無法讀取標註檔: {}", path))?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let (filename, content_hash, title, keywords, tags) =
+        (column("filename"), column("content_hash"), column("title"), column("keywords"), column("tags"));
+    if filename.is_none() && content_hash.is_none() {
+        anyhow::bail!("CSV 需要 filename 或 content_hash 欄位");
+    }
+
+    let mut labels = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or_default().trim().to_string();
+        let list = |i: Option<usize>| split_list(&field(i));
+        let title = field(title);
+
+        labels.push(LabelRecord {
+            filename: field(filename),
+            content_hash: field(content_hash),
+            url: String::new(),
+            title: (!title.is_empty()).then_some(title),
+            keywords: list(keywords),
+            tags: list(tags),
+        });
+    }
+
+    Ok(labels)
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split('|')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// 把標註合併進 metadata，回傳更新後的列表
+pub fn apply(metadata: &[ImageMetadata], labels: &[LabelRecord], mode: ImportMode) -> (Vec<ImageMetadata>, ImportSummary) {
+    let mut updated = metadata.to_vec();
+    let mut summary = ImportSummary::default();
+
+    let mut by_hash: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut by_filename: HashMap<&str, usize> = HashMap::new();
+    for (i, m) in metadata.iter().enumerate() {
+        if !m.content_hash.is_empty() {
+            by_hash.entry(m.content_hash.as_str()).or_default().push(i);
+        }
+        by_filename.insert(m.filename.as_str(), i);
+    }
+
+    let mut changed = vec![false; updated.len()];
+    for label in labels {
+        let targets = match by_hash.get(label.content_hash.as_str()) {
+            Some(indices) if !label.content_hash.is_empty() => indices.clone(),
+            _ => by_filename.get(label.filename.as_str()).map(|&i| vec![i]).unwrap_or_default(),
+        };
+        if targets.is_empty() {
+            let key = if label.filename.is_empty() { &label.content_hash } else { &label.filename };
+            summary.unmatched.push(key.clone());
+            continue;
+        }
+
+        summary.matched += 1;
+        for i in targets {
+            let before = (updated[i].suggested_title.clone(), updated[i].keywords.clone(), updated[i].tags.clone());
+            apply_one(&mut updated[i], label, mode);
+            changed[i] |= before != (updated[i].suggested_title.clone(), updated[i].keywords.clone(), updated[i].tags.clone());
+        }
+    }
+
+    summary.updated = changed.iter().filter(|&&c| c).count();
+    (updated, summary)
+}
+
+fn apply_one(m: &mut ImageMetadata, label: &LabelRecord, mode: ImportMode) {
+    if mode == ImportMode::Replace {
+        m.suggested_title = None;
+        m.keywords.clear();
+        m.tags.clear();
+    }
+
+    if let Some(title) = label.title.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        m.suggested_title = Some(title.to_string());
+    }
+    for keyword in &label.keywords {
+        push_unique(&mut m.keywords, keyword);
+    }
+    for tag in &label.tags {
+        push_unique(&mut m.tags, tag);
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    let value = value.trim();
+    if !value.is_empty() && !list.iter().any(|v| v == value) {
+        list.push(value.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(filename: &str, content_hash: &str) -> ImageMetadata {
+        ImageMetadata {
+            tags: vec!["animal".to_string()],
+            keywords: vec!["doge".to_string()],
+            ..ImageMetadata::for_test(filename, content_hash)
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_and_apply() {
+        let dir = std::env::temp_dir().join(format!("meme-labels-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let json_path = dir.join("labels.json");
+        let json_path = json_path.to_str().unwrap();
+
+        let corpus = vec![metadata("a.jpg", "h1"), metadata("b.jpg", "h1"), metadata("c.jpg", "")];
+        write_labels(json_path, build_labels(&corpus)).unwrap();
+        let (format, labels) = read_labels(json_path).unwrap();
+        assert_eq!(format, LabelFormat::Json);
+        assert_eq!(labels, build_labels(&corpus));
+
+        // 以雜湊對應時重複的圖片一起更新，沒有雜湊時用檔名
+        let imported = vec![
+            LabelRecord {
+                content_hash: "h1".to_string(),
+                title: Some("Doge".to_string()),
+                keywords: vec!["shiba inu".to_string(), "doge".to_string()],
+                ..Default::default()
+            },
+            LabelRecord {
+                filename: "c.jpg".to_string(),
+                tags: vec!["reaction".to_string()],
+                ..Default::default()
+            },
+            LabelRecord {
+                filename: "missing.jpg".to_string(),
+                ..Default::default()
+            },
+        ];
+        let (updated, summary) = apply(&corpus, &imported, ImportMode::Merge);
+        assert_eq!(summary.matched, 2);
+        assert_eq!(summary.updated, 3);
+        assert_eq!(summary.unmatched, vec!["missing.jpg".to_string()]);
+        assert_eq!(updated[1].suggested_title.as_deref(), Some("Doge"));
+        assert_eq!(updated[1].keywords, vec!["doge".to_string(), "shiba inu".to_string()]);
+        assert_eq!(updated[2].tags, vec!["animal".to_string(), "reaction".to_string()]);
+
+        let (replaced, _) = apply(&corpus, &imported[1..2], ImportMode::Replace);
+        assert!(replaced[2].keywords.is_empty());
+        assert_eq!(replaced[2].tags, vec!["reaction".to_string()]);
+
+        // 試算表匯出的 CSV
+        let csv_path = dir.join("labels.csv");
+        fs::write(&csv_path, "filename,title,keywords\nc.jpg,Cat, cat | meme \n").unwrap();
+        let (_, labels) = read_labels(csv_path.to_str().unwrap()).unwrap();
+        assert_eq!(labels[0].title.as_deref(), Some("Cat"));
+        assert_eq!(labels[0].keywords, vec!["cat".to_string(), "meme".to_string()]);
+
+        let error = parse_json(r#"{"schema":"meme-data-crawler/labels","version":2,"labels":[]}"#).unwrap_err();
+        assert!(error.to_string().contains("版本"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,66 @@
+#![allow(clippy::collapsible_if)]
+
+//! Memes 圖片爬蟲：爬取、去重、反向搜尋
+//!
+//! `main.rs` 是命令列介面；其他服務可以直接嵌入 `CrawlerEngine` 等元件。
+
+// 終端機輸出都經過 `terminal::render`（--plain 時只輸出 ASCII 符號）；
+// 定義在模組宣告之前，crate 內的模組都能使用，不會匯出到函式庫的公開 API。
+
+/// `println!` 加上 `terminal::render`
+macro_rules! out {
+    () => { ::std::println!() };
+    ($($arg:tt)*) => { ::std::println!("{}", $crate::terminal::render(&::std::format!($($arg)*))) };
+}
+
+/// `eprintln!` 加上 `terminal::render`
+macro_rules! eout {
+    () => { ::std::eprintln!() };
+    ($($arg:tt)*) => { ::std::eprintln!("{}", $crate::terminal::render(&::std::format!($($arg)*))) };
+}
+
+pub mod types;
+pub mod context;
+pub mod file_manager;
+pub mod fetcher;
+pub mod robots;
+pub mod parser;
+pub mod crawler;
+pub mod dedup;
+pub mod dedup_report;
+pub mod dedup_index;
+pub mod reverse_search;
+pub mod tags;
+pub mod shutdown;
+pub mod status;
+pub mod bench;
+pub mod profile;
+pub mod store;
+pub mod maintenance;
+pub mod events;
+pub mod prune;
+pub mod impact;
+pub mod integrity;
+pub mod gc;
+pub mod proxy;
+pub mod client_pool;
+pub mod headers;
+pub mod rate_limit;
+pub mod pipeline;
+pub mod sources;
+pub mod review;
+pub mod export;
+pub mod labels;
+pub mod media;
+pub mod thumbnail;
+pub mod normalize;
+pub mod classify;
+pub mod cluster;
+pub mod ocr;
+pub mod nsfw;
+pub mod metrics;
+pub mod history;
+pub mod trash;
+pub mod tier;
+pub mod plugins;
+pub mod terminal;
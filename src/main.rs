@@ -1,210 +1,3187 @@
-mod types;
-mod file_manager;
-mod fetcher;
-mod parser;
-mod crawler;
-mod dedup;
-mod reverse_search;
-
-use crawler::{CrawlerEngine, CrawlerConfig};
-use parser::GenericParser;
+#![allow(clippy::collapsible_if)]
+
+// 與函式庫相同：終端機輸出都經過 `terminal::render`（--plain 時只輸出 ASCII 符號）
+macro_rules! out {
+    () => { ::std::println!() };
+    ($($arg:tt)*) => { ::std::println!("{}", terminal::render(&::std::format!($($arg)*))) };
+}
+
+macro_rules! eout {
+    () => { ::std::eprintln!() };
+    ($($arg:tt)*) => { ::std::eprintln!("{}", terminal::render(&::std::format!($($arg)*))) };
+}
+
+use meme_data_crawler::{
+    bench, classify, client_pool, cluster, context, crawler, dedup, events, export, file_manager, gc, headers, history, impact, integrity, labels, maintenance, media, metrics, normalize, nsfw, ocr, parser, pipeline, plugins, profile, proxy, prune, rate_limit, review, reverse_search, shutdown, sources, store, tags, thumbnail, tier, trash, types,
+};
+use crawler::{CrawlerEngine, CrawlerConfig, FilenameTemplate, Site, TimeWindow};
 use dedup::DedupAnalyzer;
+use tags::TagIndex;
 use reverse_search::{ReverseSearchEngine, KeywordFilter};
-use anyhow::Result;
+use meme_data_crawler::fetcher::{Fetcher, HttpFetcher};
+use meme_data_crawler::terminal;
+use store::{MetadataBackend, SqliteStore};
+use context::DataContext;
+use impact::ImpactSummary;
+use anyhow::{Context, Result};
 use std::sync::Arc;
 use std::env;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    
+    // 全域旗標：--plain 只輸出 ASCII 符號（舊版 Windows 主控台會自動啟用）
+    let plain = args.iter().any(|a| a == "--plain");
+    args.retain(|a| a != "--plain");
+    terminal::init(plain);
+    
+    // 全域旗標：--profile <name> 把所有資料放到 ~/.meme-crawler/profiles/<name>/
+    let data_dir = match take_flag_value(&mut args, "--profile") {
+        Some(name) => {
+            let dir = profile::profile_dir(&name)?;
+            out!("📁 Profile: {} ({})\n", name, dir);
+            dir
+        }
+        None => "./data".to_string(),
+    };
+    let data_dir = data_dir.as_str();
+    
+    // 全域旗標：--store jsonl|sqlite 選擇 metadata 後端
+    let backend = match take_flag_value(&mut args, "--store") {
+        Some(name) => MetadataBackend::parse(&name)?,
+        None => MetadataBackend::default(),
+    };
+    
+    // 全域旗標：--events nats://host:4222 | kafka+http://host:8082 發佈新圖片與搜尋結果事件
+    let events_prefix = take_flag_value(&mut args, "--events-prefix")
+        .unwrap_or_else(|| "memes".to_string());
+    let event_sink = match take_flag_value(&mut args, "--events") {
+        Some(url) => {
+            out!("📡 事件發佈: {} ({}.images, {}.search_results)\n", url, events_prefix, events_prefix);
+            Some(events::EventSink::new(events::connect(&url)?, &events_prefix))
+        }
+        None => None,
+    };
+    
+    // 全域旗標：--filename-pattern "{site}_{hash8}.{ext}" 設定檔名樣板（保存在資料目錄，之後沿用）
+    if let Some(pattern) = take_flag_value(&mut args, "--filename-pattern") {
+        let template = FilenameTemplate::parse(&pattern)?;
+        template.save(data_dir)?;
+        out!("📝 檔名樣板: {}\n", template.pattern());
+    }
+    
+    // 啟動時依 gc.json 清理過期的暫存檔、舊備份與 log（gc 命令本身會顯示明細）
+    if args.get(1).map(|s| s.as_str()) != Some("gc") {
+        match gc::run_auto(data_dir) {
+            Ok(0) => {}
+            Ok(removed) => out!("🧹 已清理 {} 個過期的暫存檔/備份（gc --auto off 可關閉）\n", removed),
+            Err(e) => eout!("⚠️  自動清理失敗: {}\n", e),
+        }
+    }
+    
+    // 上次改名到一半中斷時，先完成改名交易，避免 metadata 指向不存在的檔案
+    let file_manager = file_manager::FileManager::new(data_dir)?;
+    if file_manager.pending_renames()?.is_some() {
+        let context = DataContext::open(data_dir, backend)?;
+        let count = maintenance::recover_renames(&context)?;
+        out!("🔁 已完成上次中斷的改名（{} 個檔案）\n", count);
+    }
+    
+    // 全域旗標：--proxy <url> / --proxy-list <file> 透過代理爬取與搜尋
+    let mut proxy_config = proxy::ProxyConfig::default();
+    if let Some(url) = take_flag_value(&mut args, "--proxy") {
+        proxy_config.proxies.push(url);
+    }
+    if let Some(path) = take_flag_value(&mut args, "--proxy-list") {
+        proxy_config.proxies.extend(proxy::ProxyConfig::load_list(&path)?);
+    }
+    if let Some(name) = take_flag_value(&mut args, "--proxy-rotation") {
+        proxy_config.strategy = proxy::RotationStrategy::parse(&name)?;
+    }
+    if let Some(secs) = take_flag_value(&mut args, "--proxy-cooldown") {
+        proxy_config.cooldown_secs = secs.parse()
+            .map_err(|_| anyhow::anyhow!("--proxy-cooldown 需要秒數: {}", secs))?;
+    }
+    if !proxy_config.is_empty() {
+        out!("🌐 代理: {} 個 ({:?}, 封鎖後冷卻 {} 秒)\n",
+            proxy_config.proxies.len(), proxy_config.strategy, proxy_config.cooldown_secs);
+    }
+    
+    // headers.json：頁面、圖片下載與搜尋服務輪替 User-Agent 並加上各網站的 headers
+    if let Some(headers) = headers::HeaderRotator::load(data_dir)? {
+        out!("🎭 Headers: {} 個 User-Agent 輪替，{} 個網站的自訂 headers（{}）\n",
+            headers.user_agent_count(), headers.site_count(), headers::HEADERS_FILE);
+    }
+    
+    // 只有會改變資料集的命令記錄規模
+    let command = args.get(1).map(|s| s.as_str()).unwrap_or("crawl").to_string();
+    let tracks_growth = changes_corpus(&command, args.get(2).map(|s| s.as_str()));
+    let started_at = chrono::Utc::now();
+    
+    let outcome: Result<()> = async {
+        if args.len() > 1 {
+            match args[1].as_str() {
+                "crawl" => run_crawler(data_dir, backend, event_sink, proxy_config, &args[2..]).await?,
+                "parse-test" => run_parse_test(data_dir, proxy_config, &args[2..]).await?,
+                "parse-compare" => run_parse_compare(data_dir, proxy_config, &args[2..]).await?,
+                "bench" => run_bench(data_dir, backend, proxy_config, &args[2..]).await?,
+                "diff-crawl" => run_diff_crawl(data_dir, backend, proxy_config, &args[2..]).await?,
+                "reddit" => run_reddit(data_dir, backend, event_sink, proxy_config, &args[2..]).await?,
+                "knowyourmeme" | "kym" => run_knowyourmeme(data_dir, backend, event_sink, proxy_config, &args[2..]).await?,
+                "feeds" => run_feeds(data_dir, backend, event_sink, proxy_config, &args[2..]).await?,
+                "dedup" => run_dedup(data_dir, backend, &args[2..]).await?,
+                "enrich" => run_enrich(data_dir, backend, args.iter().any(|a| a == "--in-place"))?,
+                "export" => run_export(data_dir, backend, &args[2..])?,
+                "review" => run_review(data_dir, backend, &args[2..])?,
+                "classify" => run_classify(data_dir, backend, &args[2..])?,
+                "cluster" => run_cluster(data_dir, backend, &args[2..])?,
+                "ocr" => run_ocr(data_dir, backend, &args[2..])?,
+                "thumbnails" => run_thumbnails(data_dir, backend, &args[2..])?,
+                "normalize" => run_normalize(data_dir, backend, &args[2..])?,
+                "labels" => run_labels(data_dir, backend, &args[2..])?,
+                "search" => run_reverse_search(data_dir, backend, event_sink, &proxy_config, &args[2..]).await?,
+                "pipeline" => run_pipeline(data_dir, backend, event_sink, proxy_config, &args[2..]).await?,
+                "merge-results" => run_merge_results(data_dir, backend, &args[2..])?,
+                "search-stats" => reverse_search::print_statistics(
+                    &format!("{}/reverse_search_results.jsonl", data_dir),
+                    &format!("{}/service_latency.jsonl", data_dir),
+                )?,
+                "tags" => run_tags(data_dir, backend, &args[2..])?,
+                "profile" => run_profile(&args[2..])?,
+                "store" => run_store(data_dir, &args[2..])?,
+                "redownload" => run_redownload(data_dir).await?,
+                "prune" => run_prune(data_dir, backend, &args[2..])?,
+                "gc" => run_gc(data_dir, &args[2..])?,
+                "tier" => run_tier(data_dir, backend, &args[2..])?,
+                "verify" => run_verify(data_dir, backend, args.get(2).map(|s| s.as_str()))?,
+                "reconcile" => run_reconcile(data_dir, backend, args.get(2).map(|s| s.as_str()))?,
+                "fix-extensions" | "rename" => run_fix_extensions(data_dir, backend, args.get(2).map(|s| s.as_str()))?,
+                "stats" => run_stats(data_dir, backend, &args[2..])?,
+                "history" => run_history(data_dir, &args[2..])?,
+                "--help" | "-h" => print_help(),
+                _ => {
+                    out!("未知命令: {}", args[1]);
+                    print_help();
+                }
+            }
+        } else {
+            run_crawler(data_dir, backend, event_sink, proxy_config, &[]).await?;
+        }
+        
+        Ok(())
+    }
+    .await;
     
-    if args.len() > 1 {
-        match args[1].as_str() {
-            "crawl" => run_crawler().await?,
-            "dedup" => run_dedup(args.get(2).map(|s| s.as_str())).await?,
-            "search" => run_reverse_search(args.get(2).map(|s| s.as_str())).await?,
-            "search-stats" => reverse_search::print_statistics("./data/reverse_search_results.jsonl")?,
-            "--help" | "-h" => print_help(),
-            _ => {
-                println!("未知命令: {}", args[1]);
-                print_help();
+    // 命令完成後記錄當天的資料集規模（stats growth 可查看），操作紀錄的數量沿用同一份快照
+    let mut record = history::OperationRecord::new(&command, args.get(2..).unwrap_or_default(), started_at, &outcome);
+    if tracks_growth {
+        match record_growth(data_dir, backend, &command) {
+            Ok((snapshot, previous)) => {
+                record = record
+                    .with_count("images", snapshot.total_images as i64)
+                    .with_count("unique_hashes", snapshot.unique_hashes as i64)
+                    .with_count("labeled", snapshot.labeled as i64);
+                if let Some(previous) = previous {
+                    record = record.with_count("images_added", snapshot.total_images as i64 - previous.total_images as i64);
+                }
             }
+            Err(e) => eout!("⚠️  無法記錄資料集成長: {}", e),
+        }
+    }
+    
+    // 操作紀錄（history 命令可查看；--help 不記錄）
+    if !matches!(command.as_str(), "history" | "--help" | "-h") {
+        if let Err(e) = history::append(data_dir, &record) {
+            eout!("⚠️  無法寫入操作紀錄: {}", e);
         }
+    }
+    
+    outcome
+}
+
+/// 會改變資料集（圖片或標註）的命令，完成後才記錄規模
+fn changes_corpus(command: &str, subcommand: Option<&str>) -> bool {
+    match command {
+        "crawl" | "reddit" | "knowyourmeme" | "kym" | "feeds" | "pipeline" | "dedup" | "prune" | "tier" => true,
+        "labels" => subcommand == Some("import"),
+        _ => false,
+    }
+}
+
+/// 把目前的資料集規模寫入 metrics_history.jsonl（同一天只保留最後一筆），並回傳上一筆快照
+fn record_growth(data_dir: &str, backend: MetadataBackend, command: &str) -> Result<(metrics::MetricsSnapshot, Option<metrics::MetricsSnapshot>)> {
+    let ctx = DataContext::open(data_dir, backend)?;
+    let snapshot = metrics::MetricsSnapshot::compute(&ctx.metadata()?, command, chrono::Utc::now());
+    let previous = metrics::record(data_dir, &snapshot)?;
+    Ok((snapshot, previous))
+}
+
+/// 從參數中取出旗標與其值（用於全域旗標，取出後不影響子命令的位置參數）
+fn take_flag_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let i = args.iter().position(|a| a == flag)?;
+    if i + 1 >= args.len() {
+        args.remove(i);
+        return None;
+    }
+    
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value)
+}
+
+/// 取得旗標的值（例如 `--warmup 5` 回傳 `Some("5")`）
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+/// 解析數值旗標（未指定時回傳 None）
+fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Result<Option<T>> {
+    match flag_value(args, flag) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("{} 需要數字: {}", flag, value)),
+        None => Ok(None),
+    }
+}
+
+/// 依命令列參數建立爬蟲設定（crawl、pipeline 與 reddit 共用）
+fn build_crawler_config(
+    data_dir: &str,
+    backend: MetadataBackend,
+    proxy_config: proxy::ProxyConfig,
+    args: &[String],
+) -> Result<CrawlerConfig> {
+    let warmup_pages = match flag_value(args, "--warmup") {
+        Some(n) => n.parse().map_err(|_| anyhow::anyhow!("--warmup 需要頁數: {}", n))?,
+        None => 0,
+    };
+    
+    let allowed_hours = match flag_value(args, "--allowed-hours") {
+        Some(spec) => Some(TimeWindow::parse(spec, flag_value(args, "--timezone"))?),
+        None => None,
+    };
+    
+    let concurrency = parse_flag::<usize>(args, "--concurrency")?.unwrap_or(10);
+    if concurrency == 0 {
+        anyhow::bail!("--concurrency 至少為 1");
+    }
+    
+    let mut config = CrawlerConfig::default()
+        .with_concurrency(concurrency)
+        .with_batch_delay(parse_flag(args, "--batch-delay")?.unwrap_or(1000))
+        .with_timeout(30)
+        .with_warmup(warmup_pages)
+        .with_allowed_hours(allowed_hours)
+        .with_min_size(parse_flag(args, "--min-width")?, parse_flag(args, "--min-height")?)
+        .with_max_bytes(parse_flag(args, "--max-bytes")?)
+        .with_media(!args.iter().any(|a| a == "--no-animated"), !args.iter().any(|a| a == "--no-video"))
+        .with_download(
+            parse_flag(args, "--download-timeout")?.unwrap_or(60),
+            parse_flag(args, "--download-retries")?.unwrap_or(2),
+        )
+        .with_download_backoff(parse_flag(args, "--download-backoff")?.unwrap_or(1000))
+        .with_robots(!args.iter().any(|a| a == "--ignore-robots"))
+        .with_proxy(proxy_config)
+        .with_headers(headers::HeaderRotator::load(data_dir)?)
+        .with_filename_template(FilenameTemplate::load(data_dir)?)
+        .with_metadata_backend(backend);
+    if let Some(workers) = parse_flag(args, "--parse-workers")? {
+        config = config.with_parse_workers(workers);
+    }
+    if let Some(limit) = parse_flag(args, "--max-in-flight")? {
+        config = config.with_max_in_flight_images(limit);
+    }
+    if let Some(concurrency) = parse_flag(args, "--detail-concurrency")? {
+        config = config.with_detail_concurrency(concurrency);
+    }
+    if let Some(window) = parse_flag(args, "--dedup-window")? {
+        config = config.with_dedup_window(window);
+    }
+    if let Some(command) = flag_value(args, "--nsfw-model") {
+        let action = nsfw::NsfwAction::parse(flag_value(args, "--nsfw-action").unwrap_or("tag"))?;
+        let threshold = parse_flag(args, "--nsfw-threshold")?.unwrap_or(nsfw::DEFAULT_THRESHOLD);
+        out!("🔞 下載時偵測 NSFW（門檻 {:.2}，{}）\n", threshold, if action == nsfw::NsfwAction::Quarantine { "隔離" } else { "標記" });
+        config = config.with_nsfw(Some(nsfw::NsfwFilter::new(Arc::new(nsfw::CommandDetector::new(command)), threshold, action)));
+    }
+    if args.iter().any(|a| a == "--normalize") || flag_value(args, "--normalize-format").is_some() {
+        let options = normalize::NormalizeOptions {
+            strip_metadata: args.iter().any(|a| a == "--normalize"),
+            format: flag_value(args, "--normalize-format").map(normalize::OutputFormat::parse).transpose()?,
+            quality: parse_flag(args, "--quality")?.unwrap_or(normalize::DEFAULT_QUALITY),
+        };
+        out!("🧽 下載後{}\n", describe_normalize(&options));
+        config = config.with_normalize(Some(options));
+    }
+    if let Some(rate) = parse_flag::<f64>(args, "--rps")? {
+        let burst = parse_flag(args, "--burst")?.unwrap_or(rate.ceil().max(1.0) as u32);
+        config = config.with_requests_per_second(rate, burst);
+    }
+    
+    if args.iter().any(|a| a == "--no-breaker") {
+        config = config.with_circuit_breaker(None);
     } else {
-        run_crawler().await?;
+        let defaults = crawler::BreakerConfig::default();
+        let breaker = crawler::BreakerConfig {
+            window: parse_flag(args, "--breaker-window")?.unwrap_or(defaults.window),
+            failure_rate: parse_flag(args, "--breaker-threshold")?.unwrap_or(defaults.failure_rate),
+            probe_interval_secs: parse_flag(args, "--breaker-probe")?.unwrap_or(defaults.probe_interval_secs),
+        };
+        if !(0.0..=1.0).contains(&breaker.failure_rate) {
+            anyhow::bail!("--breaker-threshold 需要 0 到 1 之間的比例");
+        }
+        config = config.with_circuit_breaker(Some(breaker));
     }
     
-    Ok(())
+    let sample_rate: Option<f64> = parse_flag(args, "--sample")?;
+    if sample_rate.is_some_and(|rate| !(0.0..=1.0).contains(&rate)) {
+        anyhow::bail!("--sample 需要 0 到 1 之間的比例");
+    }
+    config = config
+        .with_deterministic(args.iter().any(|a| a == "--deterministic"), parse_flag(args, "--seed")?.unwrap_or(0))
+        .with_sample_rate(sample_rate);
+    
+    if let Some(window) = &allowed_hours {
+        out!("🌙 只在 {} 爬取\n", window);
+    }
+    
+    let filter = config.size_filter;
+    if !filter.is_empty() {
+        out!("📐 圖片過濾: 最小寬 {} / 最小高 {} / 最大 {} bytes\n",
+            filter.min_width.map_or("-".to_string(), |v| v.to_string()),
+            filter.min_height.map_or("-".to_string(), |v| v.to_string()),
+            filter.max_bytes.map_or("-".to_string(), |v| v.to_string()),
+        );
+    }
+    if config.media_filter != crawler::MediaFilter::default() {
+        out!("🎞️  略過{}\n", match (config.media_filter.animated, config.media_filter.video) {
+            (false, false) => "動圖與影片",
+            (false, true) => "動圖",
+            _ => "影片",
+        });
+    }
+    
+    Ok(config)
+}
+
+/// 依命令列參數建立爬蟲（crawl 與 pipeline 共用）
+fn build_crawler(
+    data_dir: &str,
+    backend: MetadataBackend,
+    proxy_config: proxy::ProxyConfig,
+    args: &[String],
+) -> Result<CrawlerEngine> {
+    let (list_url, parser) = build_parser(data_dir, args)?;
+    // 沒有指定 --pages 時，每次執行前偵測最後一頁
+    let total_pages = parse_flag(args, "--pages")?;
+    let config = build_crawler_config(data_dir, backend, proxy_config, args)?;
+    
+    CrawlerEngine::new(data_dir, list_url, total_pages, parser, config)
 }
 
-async fn run_crawler() -> Result<()> {
-    println!("=== Memes Crawler ===\n");
+/// 依 --api / --regex / --plugin / --site 選擇列表網址樣板與 Parser（crawl 與 parse-test 共用）
+///
+/// 加上 --detail <config.json> 時，列表 Parser 取得的網址視為詳細頁，原圖從詳細頁取得。
+fn build_parser(data_dir: &str, args: &[String]) -> Result<(String, Arc<dyn parser::PageParser>)> {
+    let (list_url, list_parser) = build_list_parser(data_dir, args)?;
+    let Some(path) = flag_value(args, "--detail") else {
+        return Ok((list_url, list_parser));
+    };
     
-    let parser = Arc::new(GenericParser::memes_tw()?);
+    out!("🔗 詳細頁: {}\n", path);
+    let parser = parser::DetailParser::new(list_parser, parser::DetailConfig::load(path)?)?;
+    Ok((list_url, Arc::new(parser)))
+}
+
+/// 列表頁的網址樣板與 Parser
+fn build_list_parser(data_dir: &str, args: &[String]) -> Result<(String, Arc<dyn parser::PageParser>)> {
+    // --api <config.json>：分頁端點回傳 JSON 的網站
+    if let Some(path) = flag_value(args, "--api") {
+        let parser = parser::JsonApiParser::new(parser::JsonApiConfig::load(path)?)?;
+        out!("🌐 JSON API: {}\n", parser.url_template());
+        return Ok((parser.url_template().to_string(), Arc::new(parser)));
+    }
     
-    let config = CrawlerConfig::default()
-        .with_concurrency(10)
-        .with_timeout(30);
+    // --selectors <config.json>：以 CSS 選擇器設定的網站
+    if let Some(path) = flag_value(args, "--selectors") {
+        let config = parser::SelectorSiteConfig::load(path)?;
+        out!("🧩 選擇器: {}\n", config.url_template);
+        return Ok((config.url_template.clone(), Arc::new(config.parser()?)));
+    }
     
-    let crawler = CrawlerEngine::new(
-        "./data",
-        "https://memes.tw/maker".to_string(),
-        1594,
-        parser,
-        config,
-    )?;
+    // --regex <config.json>：圖片資料藏在 inline script 裡的網站
+    if let Some(path) = flag_value(args, "--regex") {
+        let parser = parser::RegexParser::new(parser::RegexParserConfig::load(path)?)?;
+        out!("🔎 Regex: {}\n", parser.url_template());
+        return Ok((parser.url_template().to_string(), Arc::new(parser)));
+    }
     
-    crawler.run().await?;
+    // --plugin <name>：plugins.json 登記的外部 Parser
+    if let Some(name) = flag_value(args, "--plugin") {
+        let plugins = plugins::PluginsConfig::load(data_dir)?;
+        let Some(config) = plugins.parser(name) else {
+            anyhow::bail!("plugins.json 沒有名為 {} 的 Parser 外掛", name);
+        };
+        let parser = plugins::PluginParser::new(config.clone());
+        out!("🔌 外掛: {} ({})\n", name, parser.url_template());
+        return Ok((parser.url_template().to_string(), Arc::new(parser)));
+    }
     
-    println!("\n✨ 爬蟲完成！");
-    println!("\n💡 下一步：");
-    println!("  - cargo run dedup          # 分析重複圖片");
-    println!("  - cargo run search         # 反向搜尋");
+    let site = match flag_value(args, "--site") {
+        Some(name) => Site::parse(name)?,
+        None => Site::default(),
+    };
+    if site != Site::default() {
+        out!("🌐 網站: {}\n", site.name());
+    }
     
-    Ok(())
+    Ok((site.list_url().to_string(), site.parser()?))
 }
 
-async fn run_dedup(mode: Option<&str>) -> Result<()> {
-    println!("=== 重複圖片分析 ===\n");
+/// 以設定的 Parser 解析單一頁面並列出結果，不下載任何圖片（調整選擇器用）
+async fn run_parse_test(data_dir: &str, proxy_config: proxy::ProxyConfig, args: &[String]) -> Result<()> {
+    out!("=== Parser 測試 ===\n");
     
-    let analyzer = DedupAnalyzer::new("./data")?;
-    let result = analyzer.analyze()?;
+    let (list_url, parser) = build_parser(data_dir, args)?;
+    let html = if let Some(path) = flag_value(args, "--file") {
+        out!("📄 檔案: {}\n", path);
+        std::fs::read_to_string(path).with_context(|| format!("無法讀取 {}", path))?
+    } else {
+        let url = match flag_value(args, "--url") {
+            Some(url) => url.to_string(),
+            None => list_url.replace("{page}", &parse_flag::<u32>(args, "--page")?.unwrap_or(1).to_string()),
+        };
+        out!("🌐 網址: {}\n", url);
+        
+        let fetcher = HttpFetcher::new(30, 1)?
+            .with_robots(!args.iter().any(|a| a == "--ignore-robots"))
+            .with_headers(headers::HeaderRotator::load(data_dir)?)
+            .with_proxies(&proxy_config)?;
+        fetcher.fetch_page(&url).await.context("爬取失敗")?
+    };
     
-    result.print_report();
-    analyzer.mark_duplicates(&result)?;
+    // 在 blocking 執行緒解析（外掛 Parser 以同步 IO 溝通）
+    let pool = crawler::parse_pool::ParsePool::new(parser, 1);
+    let images = pool.parse_page(html.clone()).await.context("解析失敗")?;
     
-    match mode {
-        Some("remove") => {
-            println!("⚠️  確定要刪除重複圖片嗎？(y/N)");
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input)?;
-            
-            if input.trim().to_lowercase() == "y" {
-                analyzer.remove_duplicates(&result, false)?;
-            } else {
-                println!("❌ 已取消");
+    if pool.follows_detail_pages() {
+        out!("ℹ️  這個 Parser 在列表頁回傳詳細頁網址，原圖要再進詳細頁取得\n");
+    }
+    for (i, (url, name)) in images.iter().enumerate() {
+        out!("{:>3}. {}", i + 1, name);
+        out!("     {}", url);
+        if let Some(license) = pool.take_license(url) {
+            out!("     授權: {}", license);
+        }
+    }
+    
+    if images.is_empty() {
+        out!("⚠️  沒有找到任何圖片，請檢查選擇器或頁面是否需要 JavaScript 才會產生內容");
+    } else {
+        out!("\n✅ 共 {} 張圖片", images.len());
+    }
+    
+    let hint = pool.page_hint(html).await.unwrap_or_default();
+    if let Some(has_next) = hint.has_next {
+        out!("📑 下一頁: {}", if has_next { "有" } else { "沒有（最後一頁）" });
+    }
+    if let Some(last_page) = hint.last_page {
+        out!("📑 最大頁碼: {}", last_page);
+    }
+    
+    Ok(())
+}
+
+/// 以新舊兩組 Parser 設定解析相同的頁面並列出差異（替換正式設定前驗證選擇器）
+async fn run_parse_compare(data_dir: &str, proxy_config: proxy::ProxyConfig, args: &[String]) -> Result<()> {
+    let (Some(old_spec), Some(new_spec)) = (flag_value(args, "--old"), flag_value(args, "--new")) else {
+        anyhow::bail!("用法: parse-compare --old \"--site memes_tw\" --new \"--selectors new.json\" [--pages 1-3|--url <url>|--file a.html,b.html]");
+    };
+    out!("=== Parser 比對 ===\n");
+    
+    let spec_args = |spec: &str| spec.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+    out!("舊設定: {}", old_spec);
+    let (list_url, old_parser) = build_parser(data_dir, &spec_args(old_spec))?;
+    out!("新設定: {}", new_spec);
+    let (_, new_parser) = build_parser(data_dir, &spec_args(new_spec))?;
+    
+    // 兩邊解析同一份 HTML：本地檔案，或依舊設定的列表網址抓取
+    let mut pages = Vec::new();
+    if let Some(files) = flag_value(args, "--file") {
+        for path in files.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let html = std::fs::read_to_string(path).with_context(|| format!("無法讀取 {}", path))?;
+            pages.push((path.to_string(), html));
+        }
+    } else {
+        let urls = match flag_value(args, "--url") {
+            Some(url) => vec![url.to_string()],
+            None => {
+                let range = flag_value(args, "--pages").unwrap_or("1");
+                let (first, last) = range.split_once('-').unwrap_or((range, range));
+                let parse = |n: &str| n.trim().parse::<u32>().map_err(|_| anyhow::anyhow!("--pages 需要頁碼範圍（例如 1-3）: {}", range));
+                (parse(first)?..=parse(last)?)
+                    .map(|page| list_url.replace("{page}", &page.to_string()))
+                    .collect()
+            }
+        };
+        
+        let fetcher = HttpFetcher::new(30, 1)?
+            .with_robots(!args.iter().any(|a| a == "--ignore-robots"))
+            .with_headers(headers::HeaderRotator::load(data_dir)?)
+            .with_proxies(&proxy_config)?;
+        for url in urls {
+            match fetcher.fetch_page(&url).await {
+                Ok(html) => pages.push((url, html)),
+                Err(e) => eout!("❌ 無法抓取 {}: {}", url, e),
             }
         }
-        Some("preview") | None => {
-            println!("💡 預覽模式：");
-            analyzer.remove_duplicates(&result, true)?;
-            println!("\n💡 執行 'cargo run dedup remove' 來實際刪除");
+    }
+    out!();
+    
+    // 在 blocking 執行緒解析（外掛 Parser 以同步 IO 溝通）
+    let old_pool = crawler::parse_pool::ParsePool::new(old_parser, 1);
+    let new_pool = crawler::parse_pool::ParsePool::new(new_parser, 1);
+    let mut changed_pages = 0;
+    let mut totals = parser::ParseDiff::default();
+    for (label, html) in &pages {
+        let old_items = old_pool.parse_page(html.clone()).await.with_context(|| format!("舊設定解析失敗: {}", label))?;
+        let new_items = new_pool.parse_page(html.clone()).await.with_context(|| format!("新設定解析失敗: {}", label))?;
+        let diff = parser::ParseDiff::compare(&old_items, &new_items);
+        
+        let mark = if diff.is_empty() { "✅" } else { "⚠️ " };
+        out!("{} {}: 舊 {} 張 / 新 {} 張", mark, label, old_items.len(), new_items.len());
+        for (url, name) in diff.removed.iter().take(20) {
+            out!("    - {} ({})", url, name);
         }
-        Some(other) => {
-            println!("未知模式: {}", other);
+        for (url, name) in diff.added.iter().take(20) {
+            out!("    + {} ({})", url, name);
         }
+        for (url, old_name, new_name) in diff.renamed.iter().take(20) {
+            out!("    ~ {}: {} → {}", url, old_name, new_name);
+        }
+        
+        if !diff.is_empty() {
+            changed_pages += 1;
+        }
+        totals.unchanged += diff.unchanged;
+        totals.removed.extend(diff.removed);
+        totals.added.extend(diff.added);
+        totals.renamed.extend(diff.renamed);
+    }
+    
+    out!("\n╔══════════════════════════════════╗");
+    out!("║       🧪 比對結果               ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 頁數:     {:>20} ║", pages.len());
+    out!("║ 有差異:   {:>18}頁 ║", changed_pages);
+    out!("║ 相同:     {:>20} ║", totals.unchanged);
+    out!("║ 只在舊的: {:>20} ║", totals.removed.len());
+    out!("║ 只在新的: {:>20} ║", totals.added.len());
+    out!("║ 名稱不同: {:>20} ║", totals.renamed.len());
+    out!("╚══════════════════════════════════╝");
+    
+    if totals.is_empty() && !pages.is_empty() {
+        out!("\n✅ 新設定的結果與舊設定相同，可以替換");
     }
     
     Ok(())
 }
 
-async fn run_reverse_search(service_name: Option<&str>) -> Result<()> {
-    println!("=== 反向圖片搜尋 ===\n");
+/// 新圖片事件在背景發佈，來源釋放後通道關閉
+fn spawn_image_publisher(
+    mut images: tokio::sync::mpsc::Receiver<crawler::DownloadedImage>,
+    sink: events::EventSink,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(image) = images.recv().await {
+            if let Err(e) = sink.image_downloaded(&image.metadata).await {
+                eout!("⚠️  事件發佈失敗: {}", e);
+            }
+        }
+    })
+}
+
+async fn run_crawler(
+    data_dir: &str,
+    backend: MetadataBackend,
+    event_sink: Option<events::EventSink>,
+    proxy_config: proxy::ProxyConfig,
+    args: &[String],
+) -> Result<()> {
+    if let Some(list_path) = flag_value(args, "--from-urls") {
+        return run_url_list(data_dir, backend, event_sink, proxy_config, list_path, args).await;
+    }
     
-    let filter = KeywordFilter {
-        blocklist: vec![
-            "porn".to_string(),
-            "xxx".to_string(),
-            "adult".to_string(),
-            "sex".to_string(),
-        ],
-        allowlist: vec![],
-        min_length: 3,
+    out!("=== Memes Crawler ===\n");
+    
+    let crawler = build_crawler(data_dir, backend, proxy_config, args)?;
+    let publisher = event_sink.map(|sink| spawn_image_publisher(crawler.subscribe(256), sink));
+    
+    let result = if args.iter().any(|a| a == "--retry-downloads") {
+        retry_failed_downloads(&crawler).await
+    } else if args.iter().any(|a| a == "--watch") {
+        let interval = crawler::watch::parse_interval(flag_value(args, "--interval").unwrap_or("6h"))?;
+        watch_crawl(&crawler, interval).await
+    } else {
+        crawler.run().await
     };
+    drop(crawler);
+    if let Some(publisher) = publisher {
+        publisher.await?;
+    }
+    result?;
+    
+    out!("\n✨ 爬蟲完成！");
+    out!("\n💡 下一步：");
+    out!("  - cargo run dedup          # 分析重複圖片");
+    out!("  - cargo run search         # 反向搜尋");
+    
+    Ok(())
+}
+
+/// 重新下載 failed_downloads.jsonl 中的圖片
+async fn retry_failed_downloads(crawler: &CrawlerEngine) -> Result<()> {
+    out!("🔁 重新下載之前失敗的圖片...\n");
+    let report = crawler.retry_failed_downloads().await?;
+    
+    out!("\n✅ 成功 {} 張，略過 {} 張（未通過過濾條件）", report.saved, report.skipped);
+    if report.failed > 0 {
+        out!("⚠️  {} 張仍然失敗，留在 failed_downloads.jsonl", report.failed);
+    }
+    Ok(())
+}
+
+/// 常駐模式：先把一般爬取跑完，之後每隔一段時間增量爬取新圖片，直到 Ctrl+C
+async fn watch_crawl(crawler: &CrawlerEngine, interval: std::time::Duration) -> Result<()> {
+    let shutdown = shutdown::ShutdownSignal::install();
     
-    let mut services: Vec<Arc<dyn reverse_search::ReverseSearchService>> = vec![];
+    if !crawler.is_complete().await? {
+        crawler.run().await?;
+    }
     
-    match service_name {
-        Some("tineye") => {
-            services.push(Arc::new(
-                reverse_search::services::tineye::TinEyeService::new()?
-            ));
+    loop {
+        let next = chrono::Local::now() + chrono::Duration::from_std(interval)?;
+        out!("\n⏰ 常駐模式: 下次檢查 {}（Ctrl+C 結束）", next.format("%m-%d %H:%M"));
+        
+        let wake_at = std::time::Instant::now() + interval;
+        while std::time::Instant::now() < wake_at {
+            if shutdown.is_triggered() {
+                return Ok(());
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
-        Some("bing") => {
-            services.push(Arc::new(
-                reverse_search::services::bing::BingService::new(filter.clone())?
-            ));
+        
+        out!("\n🔄 增量爬取（{}）", chrono::Local::now().format("%m-%d %H:%M"));
+        let report = crawler.refresh().await?;
+        out!("   檢查 {} 頁，新圖片 {} 張（下載 {}，過濾 {}，失敗 {}）{}",
+            report.pages_checked,
+            report.new_items,
+            report.saved,
+            report.filtered,
+            report.failed,
+            if report.caught_up { "" } else { "，未遇到上次爬過的頁面" },
+        );
+        if report.interrupted {
+            return Ok(());
         }
-        Some("all") => {
-            services.push(Arc::new(
-                reverse_search::services::tineye::TinEyeService::new()?
-            ));
-            services.push(Arc::new(
-                reverse_search::services::bing::BingService::new(filter.clone())?
-            ));
+    }
+}
+
+/// 略過頁面抓取與解析，直接下載網址清單中的圖片（中斷後只下載未完成的網址）
+async fn run_url_list(
+    data_dir: &str,
+    backend: MetadataBackend,
+    event_sink: Option<events::EventSink>,
+    proxy_config: proxy::ProxyConfig,
+    list_path: &str,
+    args: &[String],
+) -> Result<()> {
+    out!("=== 網址清單下載 ===\n");
+    
+    let config = build_crawler_config(data_dir, backend, proxy_config, args)?;
+    let source = sources::UrlListSource::new(data_dir, list_path, config)?;
+    let publisher = event_sink.map(|sink| spawn_image_publisher(source.subscribe(256), sink));
+    
+    let result = source.run().await;
+    drop(source);
+    if let Some(publisher) = publisher {
+        publisher.await?;
+    }
+    let summary = result?;
+    
+    out!("\n╔══════════════════════════════════╗");
+    out!("║       📥 下載統計               ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 清單網址: {:>20} ║", summary.total);
+    out!("║ 先前完成: {:>20} ║", summary.already_done);
+    out!("║ 本次下載: {:>20} ║", summary.saved);
+    out!("║ 未通過過濾: {:>18} ║", summary.filtered);
+    out!("║ 失敗:     {:>20} ║", summary.failed);
+    out!("╚══════════════════════════════════╝");
+    
+    if summary.failed > 0 {
+        out!("\n💡 失敗原因記錄在 {}/url_list_progress.json，重新執行會重試", data_dir);
+    }
+    if !summary.interrupted {
+        out!("\n💡 下一步：");
+        out!("  - cargo run dedup          # 分析重複圖片");
+    }
+    
+    Ok(())
+}
+
+/// 重爬已完成的頁面，記錄消失/換圖的項目到 site_changes.jsonl
+async fn run_diff_crawl(
+    data_dir: &str,
+    backend: MetadataBackend,
+    proxy_config: proxy::ProxyConfig,
+    args: &[String],
+) -> Result<()> {
+    out!("=== 網站變動比對 ===\n");
+    
+    let crawler = build_crawler(data_dir, backend, proxy_config, args)?;
+    let first_page = parse_flag(args, "--from")?.unwrap_or(1);
+    let report = crawler.diff_crawl(first_page, parse_flag(args, "--to")?).await?;
+    
+    let removed = report.changes.iter().filter(|c| c.kind == types::ChangeKind::Removed).count();
+    
+    out!("\n╔══════════════════════════════════╗");
+    out!("║       🔍 變動統計               ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 仍存在:   {:>20} ║", report.unchanged);
+    out!("║ 已消失:   {:>20} ║", removed);
+    out!("║ 換了圖片: {:>20} ║", report.changes.len() - removed);
+    out!("║ 新項目:   {:>20} ║", report.new_items);
+    out!("║ 數量減少: {:>18}頁 ║", report.dropped_pages.len());
+    out!("╚══════════════════════════════════╝");
+    
+    for drop in report.dropped_pages.iter().take(20) {
+        out!("  📉 第 {} 頁: {} → {} 張", drop.page, drop.stored, drop.current);
+    }
+    if report.dropped_pages.len() > 20 {
+        out!("  ... 還有 {} 頁", report.dropped_pages.len() - 20);
+    }
+    
+    if !report.changes.is_empty() {
+        out!("\n📝 已記錄 {} 筆變動到 {}/site_changes.jsonl", report.changes.len(), data_dir);
+    }
+    
+    Ok(())
+}
+
+/// 以不同的並發數與批次間隔各爬 N 頁，比較每秒圖片數與失敗率並建議設定
+///
+/// 每組設定爬到 `<data_dir>/bench/<i>` 這個暫存目錄（從第 1 頁開始、學到的延遲也不沿用），
+/// 量測完刪除，不影響資料集。
+async fn run_bench(
+    data_dir: &str,
+    backend: MetadataBackend,
+    proxy_config: proxy::ProxyConfig,
+    args: &[String],
+) -> Result<()> {
+    out!("=== 吞吐量量測 ===\n");
+    
+    // --concurrency 與 --batch-delay 在這裡是清單，其他參數照 crawl 的方式建立設定
+    let mut crawl_args = args.to_vec();
+    let concurrency = take_flag_value(&mut crawl_args, "--concurrency").unwrap_or_else(|| "2,5,10".to_string());
+    let delays = take_flag_value(&mut crawl_args, "--batch-delay").unwrap_or_else(|| "0,500,1000".to_string());
+    let settings = bench::BenchSetting::sweep(
+        &bench::parse_list(&concurrency, "--concurrency", 1)?,
+        &bench::parse_list(&delays, "--batch-delay", 0)?,
+    );
+    let pages = parse_flag(args, "--pages")?.unwrap_or(20);
+    
+    let mock = args.iter().any(|a| a == "--mock");
+    let (list_url, parser) = if mock {
+        let defaults = bench::MockConfig::default();
+        let site = bench::MockSite::start(bench::MockConfig {
+            latency: std::time::Duration::from_millis(parse_flag(args, "--latency")?.unwrap_or(defaults.latency.as_millis() as u64)),
+            max_in_flight: parse_flag(args, "--mock-limit")?.unwrap_or(defaults.max_in_flight),
+            ..defaults
+        }).await?;
+        out!("🧪 模擬網站: {}\n", site.list_url());
+        let parser: Arc<dyn parser::PageParser> = Arc::new(parser::GenericParser::memes_tw()?);
+        (site.list_url().to_string(), parser)
+    } else {
+        build_parser(data_dir, &crawl_args)?
+    };
+    
+    let mut base_config = build_crawler_config(data_dir, backend, proxy_config, &crawl_args)?
+        .with_warmup(0)
+        .with_allowed_hours(None);
+    if mock {
+        // 模擬網站只量測爬蟲本身，不限速
+        base_config = base_config.with_requests_per_second(0.0, 0).with_robots(false);
+    }
+    
+    out!("⚙️  {} 組設定，每組 {} 頁\n", settings.len(), pages);
+    
+    let shutdown = shutdown::ShutdownSignal::install();
+    let scratch_root = std::path::Path::new(data_dir).join(bench::SCRATCH_DIR);
+    let mut results = Vec::new();
+    for (i, setting) in settings.iter().enumerate() {
+        if shutdown.is_triggered() {
+            break;
         }
-        None => {
-            // 預設使用 TinEye
-            services.push(Arc::new(
-                reverse_search::services::tineye::TinEyeService::new()?
-            ));
+        out!("\n▶️  [{}/{}] 並發 {}，批次間隔 {}ms", i + 1, settings.len(), setting.concurrency, setting.batch_delay_ms);
+        
+        let scratch = scratch_root.join(i.to_string());
+        std::fs::remove_dir_all(&scratch).ok();
+        let scratch_dir = scratch.to_str().context("暫存目錄路徑不是 UTF-8")?;
+        let config = base_config.clone()
+            .with_concurrency(setting.concurrency)
+            .with_batch_delay(setting.batch_delay_ms);
+        let crawler = CrawlerEngine::new(scratch_dir, list_url.clone(), Some(pages), Arc::clone(&parser), config)?;
+        let status = crawler.status();
+        if let Err(e) = crawler.run().await {
+            out!("⚠️  爬取中止: {}", e);
         }
-        Some(other) => {
-            println!("❌ 未知服務: {}", other);
-            println!("可用服務: tineye, bing, all");
-            return Ok(());
+        drop(crawler);
+        results.push(bench::BenchResult::from_status(*setting, &status.snapshot()));
+    }
+    std::fs::remove_dir_all(&scratch_root).ok();
+    
+    if results.is_empty() {
+        return Ok(());
+    }
+    
+    out!("\n╔═══════════════════════════════════════════════════╗");
+    out!("║                 ⏱️  量測結果                      ║");
+    out!("╠══════╤═════════╤════════╤════════╤═══════╤════════╣");
+    out!("║ 並發 │ 間隔 ms │ 頁數   │ 圖片   │ 張/秒 │ 失敗率 ║");
+    out!("╟──────┼─────────┼────────┼────────┼───────┼────────╢");
+    for r in &results {
+        out!("║ {:>4} │ {:>7} │ {:>6} │ {:>6} │ {:>5.1} │ {:>5.1}% ║",
+            r.setting.concurrency,
+            r.setting.batch_delay_ms,
+            r.pages,
+            r.images,
+            r.images_per_sec(),
+            r.error_rate() * 100.0,
+        );
+    }
+    out!("╚══════╧═════════╧════════╧════════╧═══════╧════════╝");
+    
+    let recommended = bench::recommend(&results, bench::MAX_ERROR_RATE);
+    match recommended {
+        Some(best) => {
+            out!("\n💡 建議設定（失敗率 ≤ {:.0}% 中最快的）：", bench::MAX_ERROR_RATE * 100.0);
+            out!("  並發 {}，批次間隔 {}ms，約 {:.1} 張/秒", best.setting.concurrency, best.setting.batch_delay_ms, best.images_per_sec());
+            out!("  cargo run crawl --concurrency {} --batch-delay {}", best.setting.concurrency, best.setting.batch_delay_ms);
         }
+        None => out!("\n⚠️  每組設定的失敗率都超過 {:.0}%，請降低並發數或加大間隔再試", bench::MAX_ERROR_RATE * 100.0),
     }
     
-    println!("⚙️  設定：");
-    println!("  - 服務: {}", 
-        services.iter().map(|s| s.name()).collect::<Vec<_>>().join(", ")
-    );
-    println!("  - 並發數: 1");
-    println!("  - 關鍵字最小長度: {}", filter.min_length);
-    println!("  - 黑名單: {:?}\n", filter.blocklist);
+    let report = bench::BenchReport {
+        created_at: chrono::Utc::now(),
+        list_url,
+        pages,
+        recommended: recommended.map(|best| best.setting),
+        results,
+    };
+    out!("\n📝 結果已存到 {}", report.save(data_dir)?);
     
-    let engine = ReverseSearchEngine::new("./data", services, 1)?;
+    Ok(())
+}
+
+/// 從 Reddit 版面下載圖片（與爬蟲共用下載設定）
+async fn run_reddit(
+    data_dir: &str,
+    backend: MetadataBackend,
+    event_sink: Option<events::EventSink>,
+    proxy_config: proxy::ProxyConfig,
+    args: &[String],
+) -> Result<()> {
+    out!("=== Reddit ===\n");
     
-    let progress = engine.load_progress()?;
-    if !progress.completed_files.is_empty() {
-        println!("📋 已完成 {} 張圖片", progress.completed_files.len());
-        println!("⏭️  將從上次中斷處繼續\n");
+    let mut reddit = sources::RedditConfig::default();
+    if let Some(list) = args.first().filter(|s| !s.starts_with("--")) {
+        reddit.subreddits = sources::RedditConfig::parse_subreddits(list);
     }
+    if let Some(sort) = flag_value(args, "--sort") {
+        reddit.sort = sources::RedditSort::parse(sort)?;
+    }
+    if let Some(pages) = parse_flag(args, "--pages")? {
+        reddit.max_pages = pages;
+    }
+    reddit.include_nsfw = args.iter().any(|a| a == "--nsfw");
     
-    println!("⚠️  注意：");
-    println!("  - 可以隨時 Ctrl+C 中斷，下次會自動繼續");
-    println!("  - 進度會自動儲存\n");
+    out!("⚙️  設定：");
+    out!("  - 版面: {}", reddit.subreddits.iter().map(|s| format!("r/{}", s)).collect::<Vec<_>>().join(", "));
+    out!("  - 排序: {:?}", reddit.sort);
+    out!("  - 每版最多: {} 頁\n", reddit.max_pages);
     
-    println!("確定要開始嗎？(y/N)");
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input)?;
+    let config = build_crawler_config(data_dir, backend, proxy_config, args)?;
+    let source = sources::RedditSource::new(data_dir, reddit, config)?;
+    let publisher = event_sink.map(|sink| spawn_image_publisher(source.subscribe(256), sink));
     
-    if input.trim().to_lowercase() != "y" {
-        println!("❌ 已取消");
+    let result = source.run().await;
+    drop(source);
+    if let Some(publisher) = publisher {
+        publisher.await?;
+    }
+    result?;
+    
+    out!("\n💡 下一步：");
+    out!("  - cargo run dedup          # 分析重複圖片");
+    
+    Ok(())
+}
+
+/// 從 RSS/Atom feed 下載新文章的圖片（--watch 時常駐輪詢）
+async fn run_feeds(
+    data_dir: &str,
+    backend: MetadataBackend,
+    event_sink: Option<events::EventSink>,
+    proxy_config: proxy::ProxyConfig,
+    args: &[String],
+) -> Result<()> {
+    out!("=== RSS/Atom Feeds ===\n");
+    
+    let mut feeds = sources::FeedConfig {
+        feeds: match args.first().filter(|s| !s.starts_with("--")) {
+            Some(list) => list.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+            None => sources::FeedConfig::load_list(data_dir)?,
+        },
+        ..Default::default()
+    };
+    if let Some(spec) = flag_value(args, "--watch") {
+        feeds.watch_interval = Some(crawler::watch::parse_interval(spec)?);
+    }
+    
+    if feeds.feeds.is_empty() {
+        out!("❌ 沒有 feed（在命令列指定，或寫在 {}/feeds.txt，每行一個網址）", data_dir);
         return Ok(());
     }
     
-    engine.run().await?;
+    out!("⚙️  設定：");
+    out!("  - Feeds: {} 個", feeds.feeds.len());
+    match feeds.watch_interval {
+        Some(interval) => out!("  - 常駐模式: 每 {} 分鐘檢查一次\n", (interval.as_secs() / 60).max(1)),
+        None => out!("  - 只檢查一次\n"),
+    }
+    
+    let config = build_crawler_config(data_dir, backend, proxy_config, args)?;
+    let source = sources::FeedSource::new(data_dir, feeds, config)?;
+    let publisher = event_sink.map(|sink| spawn_image_publisher(source.subscribe(256), sink));
+    
+    let result = source.run().await;
+    drop(source);
+    if let Some(publisher) = publisher {
+        publisher.await?;
+    }
+    result?;
+    
+    out!("\n💡 下一步：");
+    out!("  - cargo run dedup          # 分析重複圖片");
+    
+    Ok(())
+}
+
+/// 從 KnowYourMeme 條目下載圖片與結構化資料
+async fn run_knowyourmeme(
+    data_dir: &str,
+    backend: MetadataBackend,
+    event_sink: Option<events::EventSink>,
+    proxy_config: proxy::ProxyConfig,
+    args: &[String],
+) -> Result<()> {
+    out!("=== KnowYourMeme ===\n");
+    
+    let mut kym = sources::KymConfig::default();
+    if let Some(pages) = parse_flag(args, "--pages")? {
+        kym.max_list_pages = pages;
+    }
+    if let Some(pages) = parse_flag(args, "--gallery-pages")? {
+        kym.gallery_pages = pages;
+    }
+    
+    out!("⚙️  設定：");
+    out!("  - 條目列表最多: {} 頁", kym.max_list_pages);
+    out!("  - 每個條目相簿: {} 頁\n", kym.gallery_pages);
+    
+    let config = build_crawler_config(data_dir, backend, proxy_config, args)?;
+    let source = sources::KnowYourMemeSource::new(data_dir, kym, config)?;
+    let publisher = event_sink.map(|sink| spawn_image_publisher(source.subscribe(256), sink));
+    
+    let result = source.run().await;
+    drop(source);
+    if let Some(publisher) = publisher {
+        publisher.await?;
+    }
+    result?;
+    
+    out!("\n💡 下一步：");
+    out!("  - cargo run dedup          # 分析重複圖片");
     
-    println!("\n💡 查看結果：");
-    println!("  - cargo run search-stats");
+    Ok(())
+}
+
+/// 把重複組輸出成格狀預覽圖（data/review/）
+fn run_review(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    out!("=== 重複圖片預覽 ===\n");
+    
+    let mut options = review::MontageOptions::default();
+    if let Some(tile) = parse_flag(args, "--tile")? {
+        options.tile_size = tile;
+    }
+    if let Some(columns) = parse_flag(args, "--columns")? {
+        options.columns = columns;
+    }
+    options.max_groups = parse_flag(args, "--max-groups")?;
+    
+    let context = DataContext::open(data_dir, backend)?;
+    let analyzer = DedupAnalyzer::from_context(Arc::clone(&context));
+    let result = analyzer.analyze()?;
+    
+    if result.duplicates.is_empty() {
+        out!("✅ 沒有重複圖片");
+        return Ok(());
+    }
+    
+    let summary = review::write_review(context.file_manager(), &result.duplicates, &options)?;
+    
+    out!("🖼️  已輸出 {} 張預覽圖到 {}/review/", summary.montages, data_dir);
+    out!("📋 完整檔名對照: {}/review/index.tsv", data_dir);
+    if summary.unreadable > 0 {
+        out!("⚠️  {} 個檔案找不到或無法解碼（以灰色格子標示）", summary.unreadable);
+    }
+    
+    Ok(())
+}
+
+async fn run_dedup(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    let mode = args.first().map(|s| s.as_str()).filter(|s| !s.starts_with("--"));
+    if mode == Some("restore") {
+        return run_dedup_restore(data_dir, backend, args.get(1).map(|s| s.as_str()));
+    }
+    if let Some(dirs) = flag_value(args, "--dirs") {
+        return run_dedup_dirs(dirs, backend, mode, args);
+    }
+    
+    out!("=== 重複圖片分析 ===\n");
+    
+    let max_delete = parse_flag::<usize>(args, "--max-delete")?;
+    
+    let analyzer = DedupAnalyzer::from_context(DataContext::open(data_dir, backend)?)
+        .with_trash(args.iter().any(|a| a == "--trash"));
+    let result = analyzer.analyze()?;
+    
+    result.print_report();
+    analyzer.mark_duplicates(&result)?;
+    
+    match mode {
+        Some("remove") => {
+            let impact = analyzer.impact(&result)?;
+            impact.print();
+            out!("📄 影響摘要: {}\n", impact.save(data_dir)?);
+            impact.check_limit(max_delete)?;
+            
+            if !args.iter().any(|a| a == "--yes") {
+                out!("⚠️  確定要刪除重複圖片嗎？(y/N)");
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input)?;
+                
+                if input.trim().to_lowercase() != "y" {
+                    out!("❌ 已取消");
+                    return Ok(());
+                }
+            }
+            analyzer.remove_duplicates(&result, false)?;
+        }
+        Some("preview") | None => {
+            out!("💡 預覽模式：");
+            analyzer.remove_duplicates(&result, true)?;
+            
+            let impact = analyzer.impact(&result)?;
+            impact.print();
+            out!("📄 影響摘要: {}", impact.save(data_dir)?);
+            out!("\n💡 執行 'cargo run dedup remove' 來實際刪除");
+        }
+        Some(other) => {
+            out!("未知模式: {}", other);
+        }
+    }
+    
+    Ok(())
+}
+
+/// 跨資料目錄去重（`--dirs` 的順序就是保留的優先順序）
+fn run_dedup_dirs(dirs: &str, backend: MetadataBackend, mode: Option<&str>, args: &[String]) -> Result<()> {
+    let dirs: Vec<&str> = dirs.split(',').map(str::trim).filter(|d| !d.is_empty()).collect();
+    if dirs.len() < 2 {
+        anyhow::bail!("--dirs 至少需要兩個資料目錄，例如 data/memes_tw,data/imgflip");
+    }
+    
+    out!("=== 跨目錄重複圖片分析 ===\n");
+    
+    let max_delete = parse_flag::<usize>(args, "--max-delete")?;
+    let trash = args.iter().any(|a| a == "--trash");
+    let mut analyzers = Vec::with_capacity(dirs.len());
+    for dir in &dirs {
+        if !std::path::Path::new(dir).is_dir() {
+            anyhow::bail!("找不到資料目錄: {}", dir);
+        }
+        analyzers.push(DedupAnalyzer::from_context(DataContext::open(dir, backend)?).with_trash(trash));
+    }
+    
+    let result = DedupAnalyzer::analyze_dirs(&analyzers)?;
+    result.print_report();
+    out!("✅ 報告已儲存到 {}\n", result.save()?);
+    
+    let mut files_deleted = 0;
+    for (dir, analyzer) in dirs.iter().zip(&analyzers) {
+        if result.deletions(dir).is_empty() {
+            continue;
+        }
+        let impact = analyzer.cross_impact(&result)?;
+        out!("📂 {}", dir);
+        impact.print();
+        out!("📄 影響摘要: {}\n", impact.save(dir)?);
+        files_deleted += impact.files_deleted;
+    }
+    
+    match mode {
+        Some("remove") => {
+            if let Some(max) = max_delete && files_deleted > max {
+                anyhow::bail!("dedup 會刪除 {} 個檔案，超過上限 --max-delete {}，未執行", files_deleted, max);
+            }
+            if files_deleted == 0 {
+                return Ok(());
+            }
+            
+            if !args.iter().any(|a| a == "--yes") {
+                out!("⚠️  確定要刪除 {} 個目錄中的 {} 張重複圖片嗎？(y/N)", dirs.len(), files_deleted);
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input)?;
+                
+                if input.trim().to_lowercase() != "y" {
+                    out!("❌ 已取消");
+                    return Ok(());
+                }
+            }
+            
+            let mut removed = 0;
+            for (dir, analyzer) in dirs.iter().zip(&analyzers) {
+                let deletions = result.deletions(dir);
+                if deletions.is_empty() {
+                    continue;
+                }
+                out!("📂 {}", dir);
+                let removal = analyzer.remove_files(&deletions)?;
+                removed += removal.removed;
+                if let Some(id) = removal.trash {
+                    out!("🗑️  已移到回收桶: {}", file_manager::join_path(dir, &format!("trash/{}", id)));
+                }
+            }
+            out!("✅ 跨目錄去重完成，刪除 {} 張圖片", removed);
+        }
+        Some("preview") | None => {
+            out!("💡 執行 'cargo run dedup remove --dirs {}' 來實際刪除", dirs.join(","));
+        }
+        Some(other) => {
+            out!("未知模式: {}", other);
+        }
+    }
+    
+    Ok(())
+}
+
+/// 還原回收桶中的圖片（dedup remove --trash、prune、verify apply 移除的；沒有指定批次時列出回收桶）
+fn run_dedup_restore(data_dir: &str, backend: MetadataBackend, id: Option<&str>) -> Result<()> {
+    let Some(id) = id else {
+        let batches = trash::list(data_dir)?;
+        if batches.is_empty() {
+            out!("📭 回收桶是空的");
+            return Ok(());
+        }
+        
+        out!("=== 回收桶（{}/）===\n", trash::TRASH_DIR);
+        for (id, manifest) in &batches {
+            out!("  🗑️  {}  {} 張  ({}, {})",
+                id,
+                manifest.entries.len(),
+                manifest.command,
+                manifest.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"));
+        }
+        out!("\n💡 執行 'cargo run dedup restore <批次>' 來還原");
+        return Ok(());
+    };
+    
+    let ctx = DataContext::open(data_dir, backend)?;
+    let summary = trash::restore(&ctx, id)?;
+    out!("╔══════════════════════════════════╗");
+    out!("║       ♻️  還原完成               ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 還原圖片:   {:>18} ║", summary.files);
+    out!("║ metadata:   {:>18} ║", summary.metadata);
+    out!("║ 搜尋結果:   {:>18} ║", summary.results);
+    out!("╚══════════════════════════════════╝");
+    if !summary.skipped.is_empty() {
+        out!("⚠️  {} 張的檔名已被佔用，仍留在 {}/{}:", summary.skipped.len(), trash::TRASH_DIR, id);
+        for filename in &summary.skipped {
+            out!("   {}", filename);
+        }
+    }
+    
+    Ok(())
+}
+
+/// 反向搜尋的關鍵字過濾
+fn default_keyword_filter() -> KeywordFilter {
+    KeywordFilter {
+        blocklist: vec![
+            "porn".to_string(),
+            "xxx".to_string(),
+            "adult".to_string(),
+            "sex".to_string(),
+        ],
+        allowlist: vec![],
+        min_length: 3,
+        ..KeywordFilter::default()
+    }
+}
+
+/// 內建過濾規則加上資料目錄的 keyword_filter.json
+fn load_keyword_filter(data_dir: &str) -> Result<KeywordFilter> {
+    default_keyword_filter().with_rules_file(data_dir)
+}
+
+/// 依名稱建立搜尋服務（內建服務或 plugins.json 登記的外掛，未知名稱回傳 None）
+fn build_search_services(
+    data_dir: &str,
+    service_name: Option<&str>,
+    plugins: &plugins::PluginsConfig,
+    filter: &KeywordFilter,
+    proxy_config: &proxy::ProxyConfig,
+    headers: &Option<Arc<headers::HeaderRotator>>,
+    limiter: &Arc<rate_limit::AdaptiveRateLimiter>,
+) -> Result<Option<Vec<Arc<dyn reverse_search::ReverseSearchService>>>> {
+    let tineye = || -> Result<Arc<dyn reverse_search::ReverseSearchService>> {
+        Ok(Arc::new(
+            reverse_search::services::tineye::TinEyeService::new(filter.clone())?
+                .with_proxies(proxy_config)?
+                .with_headers(headers.clone())
+                .with_rate_limiter(Arc::clone(limiter))
+        ))
+    };
+    let bing = || -> Result<Arc<dyn reverse_search::ReverseSearchService>> {
+        Ok(Arc::new(
+            reverse_search::services::bing::BingService::new(filter.clone())?
+                .with_proxies(proxy_config)?
+                .with_headers(headers.clone())
+                .with_rate_limiter(Arc::clone(limiter))
+        ))
+    };
+    let lens = || -> Result<Arc<dyn reverse_search::ReverseSearchService>> {
+        Ok(Arc::new(
+            reverse_search::services::lens::GoogleLensService::new(filter.clone())?
+                .with_proxies(proxy_config)?
+                .with_headers(headers.clone())
+                .with_rate_limiter(Arc::clone(limiter))
+        ))
+    };
+    let iqdb = || -> Result<Arc<dyn reverse_search::ReverseSearchService>> {
+        Ok(Arc::new(
+            reverse_search::services::iqdb::IqdbService::new(filter.clone())?
+                .with_proxies(proxy_config)?
+                .with_headers(headers.clone())
+                .with_rate_limiter(Arc::clone(limiter))
+        ))
+    };
+    let trace_moe = || -> Result<Arc<dyn reverse_search::ReverseSearchService>> {
+        Ok(Arc::new(
+            reverse_search::services::trace_moe::TraceMoeService::new(filter.clone())?
+                .with_proxies(proxy_config)?
+                .with_rate_limiter(Arc::clone(limiter))
+        ))
+    };
+    let bing_visual_config = reverse_search::services::bing_visual::BingVisualConfig::load(data_dir)?;
+    let bing_api = || -> Result<Arc<dyn reverse_search::ReverseSearchService>> {
+        Ok(Arc::new(
+            reverse_search::services::bing_visual::BingVisualApiService::new(bing_visual_config.clone(), filter.clone())?
+                .with_proxies(proxy_config)?
+                .with_rate_limiter(Arc::clone(limiter))
+        ))
+    };
+    
+    Ok(match service_name {
+        Some("tineye") => Some(vec![tineye()?]),
+        Some("bing") => Some(vec![bing()?]),
+        Some("lens") => Some(vec![lens()?]),
+        Some("bing-api") => Some(vec![bing_api()?]),
+        Some("iqdb") => Some(vec![iqdb()?]),
+        Some("tracemoe") => Some(vec![trace_moe()?]),
+        // 動畫截圖來源（不放進 all，trace.moe 的免費配額有限）
+        Some("anime") => Some(vec![iqdb()?, trace_moe()?]),
+        // 有設定 API key 時才加入官方 API
+        Some("all") => {
+            let mut services = vec![tineye()?, bing()?, lens()?];
+            if bing_visual_config.has_key() {
+                services.push(bing_api()?);
+            }
+            Some(services)
+        }
+        // 預設使用 TinEye
+        None => Some(vec![tineye()?]),
+        Some(name) => plugins.service(name).map(|config| {
+            vec![Arc::new(plugins::PluginService::new(config.clone())) as Arc<dyn reverse_search::ReverseSearchService>]
+        }),
+    })
+}
+
+/// 未知服務時列出可用的名稱
+fn print_available_services(plugins: &plugins::PluginsConfig) {
+    let mut names = vec!["tineye", "bing", "lens", "bing-api", "iqdb", "tracemoe", "anime", "all"];
+    names.extend(plugins.services.iter().map(|s| s.name.as_str()));
+    out!("可用服務: {}", names.join(", "));
+}
+
+/// 所有 profile 共用的搜尋快取（`--no-cache`、`--no-shared-cache` 時停用；找不到家目錄時只用本地快取）
+fn shared_search_cache(args: &[String]) -> Option<reverse_search::cache::SearchCache> {
+    if args.iter().any(|a| a == "--no-cache" || a == "--no-shared-cache") {
+        return None;
+    }
+    reverse_search::cache::SearchCache::shared().ok()
+}
+
+/// 關鍵字後處理（旗標: --keyword-lang --no-keyword-normalize --translate）
+fn build_keyword_normalizer(args: &[String]) -> Option<reverse_search::KeywordNormalizer> {
+    if args.iter().any(|a| a == "--no-keyword-normalize") {
+        return None;
+    }
+    let language = flag_value(args, "--keyword-lang").unwrap_or(reverse_search::keywords::DEFAULT_LANGUAGE);
+    let mut normalizer = reverse_search::KeywordNormalizer::new(language);
+    if let Some(command) = flag_value(args, "--translate") {
+        normalizer = normalizer.with_translator(Arc::new(reverse_search::keywords::CommandTranslator::new(command)));
+    }
+    Some(normalizer)
+}
+
+/// 建立反向搜尋引擎（search 與 pipeline 共用，旗標: --upload --verify --concurrency --no-cache --no-shared-cache --flush-interval --fsync --block-cooldown --block-webhook，以及 `build_keyword_normalizer` 的旗標）
+fn build_search_engine(
+    context: &Arc<DataContext>,
+    services: Vec<Arc<dyn reverse_search::ReverseSearchService>>,
+    limiter: Arc<rate_limit::AdaptiveRateLimiter>,
+    event_sink: Option<events::EventSink>,
+    args: &[String],
+) -> Result<ReverseSearchEngine> {
+    let mut engine = ReverseSearchEngine::from_context(Arc::clone(context), services)
+        .with_upload(args.iter().any(|a| a == "--upload"))
+        .with_verify(args.iter().any(|a| a == "--verify"))
+        .with_concurrency(parse_flag(args, "--concurrency")?.unwrap_or(1))
+        .with_cache(!args.iter().any(|a| a == "--no-cache"))
+        .with_shared_cache(shared_search_cache(args))
+        .with_keyword_normalizer(build_keyword_normalizer(args))
+        .with_rate_limiter(limiter);
+    let flush_interval = match parse_flag(args, "--flush-interval")? {
+        Some(secs) => std::time::Duration::from_secs(secs),
+        None => reverse_search::writer::DEFAULT_FLUSH_INTERVAL,
+    };
+    let sync = match flag_value(args, "--fsync") {
+        Some(name) => reverse_search::writer::SyncPolicy::parse(name)?,
+        None => reverse_search::writer::SyncPolicy::default(),
+    };
+    engine = engine.with_result_writing(flush_interval, sync);
+    if let Some(secs) = parse_flag(args, "--block-cooldown")? {
+        engine = engine.with_block_cooldown(std::time::Duration::from_secs(secs));
+    }
+    if let Some(url) = flag_value(args, "--block-webhook") {
+        engine = engine.with_block_webhook(url.to_string());
+    }
+    if let Some(sink) = event_sink {
+        engine = engine.with_events(sink);
+    }
+    if std::path::Path::new(&context.path(tier::COLD_INDEX_FILE)).exists() {
+        engine = engine.with_cold_storage(context.cold_storage()?);
+    }
+    Ok(engine)
+}
+
+async fn run_reverse_search(
+    data_dir: &str,
+    backend: MetadataBackend,
+    event_sink: Option<events::EventSink>,
+    proxy_config: &proxy::ProxyConfig,
+    args: &[String],
+) -> Result<()> {
+    // --redo-service <name> 只重新搜尋該服務，等同 search <name> --redo-empty
+    let redo_service = flag_value(args, "--redo-service");
+    let service_name = args.first().map(|s| s.as_str()).filter(|s| !s.starts_with("--")).or(redo_service);
+    if service_name == Some("local") {
+        return run_local_search(data_dir, backend, &args[1..]);
+    }
+    let redo = redo_service.is_some() || args.iter().any(|a| a == "--redo-empty");
+    
+    out!("=== 反向圖片搜尋 ===\n");
+    
+    let upload = args.iter().any(|a| a == "--upload");
+    let verify = args.iter().any(|a| a == "--verify");
+    let concurrency = parse_flag::<usize>(args, "--concurrency")?.unwrap_or(1).max(1);
+    
+    let filter = load_keyword_filter(data_dir)?;
+    
+    // 所有服務共用限流器，學到的延遲存在 rate_limits.json
+    let limiter = Arc::new(rate_limit::AdaptiveRateLimiter::load(data_dir)?);
+    
+    let headers = headers::HeaderRotator::load(data_dir)?;
+    let plugins = plugins::PluginsConfig::load(data_dir)?;
+    let Some(services) = build_search_services(data_dir, service_name, &plugins, &filter, proxy_config, &headers, &limiter)? else {
+        out!("❌ 未知服務: {}", service_name.unwrap_or_default());
+        print_available_services(&plugins);
+        return Ok(());
+    };
+    
+    out!("⚙️  設定：");
+    out!("  - 服務: {}", 
+        services.iter().map(|s| s.name()).collect::<Vec<_>>().join(", ")
+    );
+    out!("  - 並發數: {}", concurrency);
+    out!("  - 搜尋方式: {}", if upload { "上傳本地檔案" } else { "圖片 URL" });
+    if upload && verify {
+        out!("  - 上傳前驗證檔案 hash");
+    }
+    out!("  - 關鍵字最小長度: {}", filter.min_length);
+    out!("  - 黑名單: {:?}", filter.blocklist);
+    out!("  - 過濾規則: {} 條（{}）\n", filter.rule_count(), reverse_search::filter::RULES_FILE);
+    
+    let service_count = services.len();
+    let context = DataContext::open(data_dir, backend)?;
+    let engine = build_search_engine(&context, services, limiter, event_sink, args)?;
+    
+    let progress = engine.load_progress()?;
+    if redo {
+        out!("🔁 只重新搜尋結果是空的（沒有關鍵字與 best guess）或失敗的圖片，新結果取代舊結果\n");
+    } else if progress.completed_count() > 0 {
+        out!("📋 已搜尋過 {} 張圖片（新加入的服務會補搜）", progress.completed_count());
+        out!("⏭️  將從上次中斷處繼續\n");
+    }
+    
+    out!("⚠️  注意：");
+    out!("  - 可以隨時 Ctrl+C 中斷，下次會自動繼續");
+    out!("  - 進度會自動儲存\n");
+    
+    out!("確定要開始嗎？(y/N)");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    
+    if input.trim().to_lowercase() != "y" {
+        out!("❌ 已取消");
+        return Ok(());
+    }
+    
+    if redo {
+        engine.redo().await?;
+    } else {
+        engine.run().await?;
+    }
+    
+    // 多個服務時另外寫出每張圖片一筆的合併結果
+    if service_count > 1 {
+        context.invalidate();
+        // 新結果在搜尋時已正規化過
+        let (merged, path) = write_merged_results(&context, None, None)?;
+        out!("🧮 已合併 {} 張圖片的各服務結果 → {}", merged.len(), path);
+    }
+    
+    out!("\n💡 查看結果：");
+    out!("  - cargo run search-stats");
+    
+    Ok(())
+}
+
+/// 合併所有搜尋結果並寫出（預設 reverse_search_merged.jsonl），回傳合併結果與路徑
+///
+/// 有 `normalizer` 時先正規化各結果的關鍵字（只影響合併結果，不改寫原始結果）。
+fn write_merged_results(
+    context: &DataContext,
+    output: Option<&str>,
+    normalizer: Option<&reverse_search::KeywordNormalizer>,
+) -> Result<(Vec<reverse_search::AggregatedResult>, String)> {
+    let mut results = context.search_results()?.to_vec();
+    if let Some(normalizer) = normalizer {
+        for result in &mut results {
+            normalizer.apply(result);
+        }
+    }
+    let merged = reverse_search::ReverseSearchAggregator::from_results(results.iter()).merge_all();
+    let path = output
+        .map(str::to_string)
+        .unwrap_or_else(|| context.path(context::files::SEARCH_MERGED));
+    reverse_search::aggregate::write_merged(&path, &merged)?;
+    Ok((merged, path))
+}
+
+/// 把既有的搜尋結果合併成每張圖片一筆（關鍵字依跨服務一致度排序、相關網站依網域去重）
+fn run_merge_results(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    let context = DataContext::open(data_dir, backend)?;
+    let results = context.search_results()?;
+    if results.is_empty() {
+        out!("⚠️  尚無搜尋結果（請先執行 cargo run search）");
+        return Ok(());
+    }
+    
+    let normalizer = build_keyword_normalizer(args);
+    let (merged, path) = write_merged_results(&context, flag_value(args, "--output"), normalizer.as_ref())?;
+    
+    let multi = merged.iter().filter(|m| m.services.len() > 1).count();
+    let agreed = merged.iter().filter(|m| m.services.len() > 1 && !m.consensus_keywords(2).is_empty()).count();
+    let with_guess = merged.iter().filter(|m| m.best_guess.is_some()).count();
+    
+    out!("\n╔══════════════════════════════════╗");
+    out!("║          合併搜尋結果            ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 搜尋結果:   {:>18} ║", results.len());
+    out!("║ 圖片數:     {:>18} ║", merged.len());
+    out!("║ 多個服務:   {:>18} ║", multi);
+    out!("║ 有共識關鍵字: {:>16} ║", agreed);
+    out!("║ 有 best guess: {:>15} ║", with_guess);
+    out!("╚══════════════════════════════════╝");
+    out!("\n✅ 已寫入 {}", path);
+    
+    Ok(())
+}
+
+/// 以內容雜湊與感知雜湊在本地語料庫找同一張（或相似的）圖，不送出任何請求
+fn run_local_search(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    let Some(path) = args.first().filter(|a| !a.starts_with("--")) else {
+        anyhow::bail!("用法: search local <圖片檔> [--max-distance N] [--limit N]");
+    };
+    let max_distance = parse_flag::<u32>(args, "--max-distance")?.unwrap_or(10);
+    let limit = parse_flag::<usize>(args, "--limit")?.unwrap_or(5).max(1);
+    
+    out!("=== 本地比對: {} ===\n", path);
+    
+    let bytes = std::fs::read(path).with_context(|| format!("無法讀取 {}", path))?;
+    let content_hash = integrity::sha256_hex(&bytes);
+    let phash = image::load_from_memory(&bytes).ok().map(|image| media::dhash(&image));
+    if phash.is_none() {
+        out!("⚠️  無法解碼圖片，只比對內容雜湊\n");
+    }
+    
+    let context = DataContext::open(data_dir, backend)?;
+    let metadata = context.metadata()?;
+    let results = context.search_results()?;
+    
+    let mut index = reverse_search::local::PhashIndex::load(data_dir)?;
+    let computed = index.update(context.file_manager(), &metadata);
+    if computed > 0 {
+        out!("🔢 已計算 {} 張圖片的感知雜湊", computed);
+        index.save(data_dir)?;
+    }
+    
+    let matches = reverse_search::local::find_matches(
+        &content_hash, phash, &metadata, &index, &results, max_distance, limit,
+    );
+    if matches.is_empty() {
+        out!("🤷 語料庫中沒有相似的圖片（距離 ≤ {}）", max_distance);
+        return Ok(());
+    }
+    
+    for (rank, found) in matches.iter().enumerate() {
+        let similarity = if found.exact {
+            "完全相同".to_string()
+        } else {
+            format!("距離 {}", found.distance)
+        };
+        out!("{}. {} ({})", rank + 1, found.filename, similarity);
+        out!("   標題: {}", found.title.as_deref().unwrap_or("（未標註）"));
+        if !found.keywords.is_empty() {
+            out!("   關鍵字: {}", found.keywords.join(", "));
+        }
+        if !found.tags.is_empty() {
+            out!("   標籤: {}", found.tags.join(", "));
+        }
+    }
+    
+    Ok(())
+}
+
+/// 爬取 → 去重 → 反向搜尋，一次執行完（中斷後重新執行會從未完成的階段繼續）
+async fn run_pipeline(
+    data_dir: &str,
+    backend: MetadataBackend,
+    event_sink: Option<events::EventSink>,
+    proxy_config: proxy::ProxyConfig,
+    args: &[String],
+) -> Result<()> {
+    out!("=== Pipeline: 爬取 → 去重 → 反向搜尋 ===\n");
+    
+    let service_name = args.first().map(|s| s.as_str()).filter(|s| !s.starts_with("--"));
+    let limiter = Arc::new(rate_limit::AdaptiveRateLimiter::load(data_dir)?);
+    let context = DataContext::open(data_dir, backend)?;
+    
+    let search = if args.iter().any(|a| a == "--no-search") {
+        None
+    } else {
+        let filter = load_keyword_filter(data_dir)?;
+        let headers = headers::HeaderRotator::load(data_dir)?;
+        let plugins = plugins::PluginsConfig::load(data_dir)?;
+        let Some(services) = build_search_services(data_dir, service_name, &plugins, &filter, &proxy_config, &headers, &limiter)? else {
+            out!("❌ 未知服務: {}", service_name.unwrap_or_default());
+            print_available_services(&plugins);
+            return Ok(());
+        };
+        Some(build_search_engine(&context, services, limiter, event_sink.clone(), args)?)
+    };
+    
+    let crawler = build_crawler(data_dir, backend, proxy_config, args)?;
+    let publisher = event_sink.map(|sink| spawn_image_publisher(crawler.subscribe(256), sink));
+    
+    let dedup = DedupAnalyzer::from_context(Arc::clone(&context))
+        .with_trash(args.iter().any(|a| a == "--trash"));
+    
+    let pipeline = pipeline::Pipeline::new(context, crawler, dedup, search)
+        .with_remove_duplicates(args.iter().any(|a| a == "--remove-duplicates"))
+        .with_max_delete(parse_flag(args, "--max-delete")?);
+    
+    let result = pipeline.run().await;
+    drop(pipeline);
+    if let Some(publisher) = publisher {
+        publisher.await?;
+    }
+    result?;
+    
+    let clients = client_pool::ClientFactory::global();
+    out!("\n🔌 共用 HTTP client: {} 個連線池（重複使用 {} 次）", clients.pooled(), clients.reused());
+    let connections = client_pool::ConnectionStats::global().snapshot();
+    out!(
+        "🔌 {} 個請求、{} 條新連線（{} 次 TLS 交握），連線重用 {:.1}%",
+        connections.requests,
+        connections.new_connections,
+        connections.tls_handshakes,
+        connections.reuse_rate() * 100.0,
+    );
+    
+    out!("\n💡 查看結果：");
+    out!("  - cargo run search-stats");
+    
+    Ok(())
+}
+
+/// 依後端讀取所有反向搜尋結果
+fn load_search_results(data_dir: &str, backend: MetadataBackend) -> Result<Vec<reverse_search::ReverseSearchResult>> {
+    Ok(DataContext::open(data_dir, backend)?.search_results()?.to_vec())
+}
+
+/// 把搜尋關鍵字與推測標題合併進 metadata（metadata_enriched.jsonl，--in-place 時同時更新 metadata 後端）
+fn run_enrich(data_dir: &str, backend: MetadataBackend, in_place: bool) -> Result<()> {
+    let metadata_store = store::open_store(data_dir, backend)?;
+    let metadata = metadata_store.load_all_metadata()?;
+    let results = load_search_results(data_dir, backend)?;
+    
+    if results.is_empty() {
+        out!("⚠️  尚無搜尋結果（請先執行 cargo run search）");
+        return Ok(());
+    }
+    
+    let (enriched, count) = export::enrich(&metadata, &results);
+    let fm = file_manager::FileManager::new(data_dir)?;
+    fm.save_enriched_metadata(&enriched)?;
+    out!("🏷️  {} / {} 張圖片有搜尋結果，已寫入 {}/metadata_enriched.jsonl", count, enriched.len(), data_dir);
+    
+    if in_place {
+        if backend == MetadataBackend::Jsonl {
+            fm.backup_metadata()?;
+        }
+        metadata_store.rewrite_metadata(&enriched)?;
+        out!("✅ 已更新 metadata（keywords / suggested_title）");
+    }
+    
+    Ok(())
+}
+
+/// 匯出資料集：每張圖片一列，合併 metadata 與搜尋結果
+fn run_export(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    let format = match flag_value(args, "--format") {
+        Some(name) => export::ExportFormat::parse(name)?,
+        None => export::ExportFormat::default(),
+    };
+    let output = flag_value(args, "--output")
+        .map(|path| path.to_string())
+        .unwrap_or_else(|| format.default_output(data_dir));
+    
+    let mut metadata = store::open_store(data_dir, backend)?.load_all_metadata()?;
+    if metadata.is_empty() {
+        out!("⚠️  尚無圖片（請先執行 cargo run crawl）");
+        return Ok(());
+    }
+    if let Some(spec) = flag_value(args, "--exclude-class") {
+        let before = metadata.len();
+        metadata = classify::exclude_classes(metadata, &classify::MemeClass::parse_list(spec)?);
+        out!("🧪 依分類排除 {} 張圖片（{}）", before - metadata.len(), spec);
+    }
+    if args.iter().any(|a| a == "--exclude-nsfw") {
+        let before = metadata.len();
+        metadata.retain(|m| !nsfw::is_flagged(m));
+        out!("🔞 排除 {} 張標記為 NSFW 的圖片", before - metadata.len());
+    }
+    if let Some(spec) = flag_value(args, "--exclude-media") {
+        let excluded = spec.split(',').map(|name| media::MediaType::parse(name.trim())).collect::<Result<Vec<_>>>()?;
+        let unknown = metadata.iter().filter(|m| m.media_type.is_none()).count();
+        let before = metadata.len();
+        metadata.retain(|m| !m.media_type.is_some_and(|media_type| excluded.contains(&media_type)));
+        out!("🎞️  依媒體類型排除 {} 張圖片（{}）", before - metadata.len(), spec);
+        if unknown > 0 {
+            out!("   {} 張沒有記錄媒體類型，未過濾（cargo run reconcile apply 可補上）", unknown);
+        }
+    }
+    let size_filter = crawler::SizeFilter {
+        min_width: parse_flag(args, "--min-width")?,
+        min_height: parse_flag(args, "--min-height")?,
+        max_bytes: parse_flag(args, "--max-bytes")?,
+    };
+    if !size_filter.is_empty() {
+        // 用下載時記錄的尺寸與大小，不重新讀取圖片
+        let unknown = metadata.iter().filter(|m| m.width.is_none() || m.file_size.is_none()).count();
+        let before = metadata.len();
+        metadata.retain(|m| size_filter.check_metadata(m).is_none());
+        out!("📐 依尺寸/大小排除 {} 張圖片", before - metadata.len());
+        if unknown > 0 {
+            out!("   {} 張沒有記錄尺寸或大小，未過濾（cargo run reconcile apply 可補上）", unknown);
+        }
+    }
+    if let Some(spec) = flag_value(args, "--license") {
+        let filter = export::LicenseFilter::parse(spec)?;
+        let before = metadata.len();
+        metadata.retain(|m| filter.matches(m.license()));
+        out!("📜 依授權保留 {} 張圖片，排除 {} 張（{}）", metadata.len(), before - metadata.len(), spec);
+    }
+    let results = load_search_results(data_dir, backend)?;
+    
+    let rows = export::build_rows(&metadata, &results);
+    let searched = rows.iter().filter(|row| !row.services.is_empty()).count();
+    
+    if format == export::ExportFormat::Hf {
+        let split = export::SplitConfig::parse(
+            flag_value(args, "--split").unwrap_or("0.8,0.1,0.1"),
+            parse_flag(args, "--seed")?.unwrap_or(0),
+        )?;
+        let context = DataContext::open(data_dir, backend)?;
+        let summary = export::write_imagefolder(&context, &output, &rows, &split)?;
+        
+        out!("📤 已匯出 imagefolder 資料集到 {}（{} 張有搜尋結果）", output, searched);
+        for (name, count) in &summary.splits {
+            out!("  {:<12} {:>6} 張", name, count);
+        }
+        if summary.missing > 0 {
+            out!("⚠️  {} 張圖片檔案不存在，已略過", summary.missing);
+        }
+        write_attribution(&output, &metadata, args)?;
+        out!("\n💡 datasets.load_dataset(\"imagefolder\", data_dir=\"{}\")", output);
+        return Ok(());
+    }
+    
+    export::write(&output, format, &rows)?;
+    out!("📤 已匯出 {} 張圖片（{} 張有搜尋結果）到 {}", rows.len(), searched, output);
+    
+    // 來源標示放在資料集檔案旁邊
+    let dir = std::path::Path::new(&output)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| ".".to_string());
+    write_attribution(&dir, &metadata, args)?;
+    
+    Ok(())
+}
+
+/// 匯出時一併寫入來源標示（attribution.jsonl 與 ATTRIBUTION），`--no-attribution` 時略過
+fn write_attribution(dir: &str, metadata: &[types::ImageMetadata], args: &[String]) -> Result<()> {
+    if args.iter().any(|a| a == "--no-attribution") {
+        return Ok(());
+    }
+    
+    let records = export::build_attribution(metadata);
+    let (_, text_path) = export::write_attribution(dir, &records)?;
+    let licensed = records.iter().filter(|r| r.license.is_some()).count();
+    out!("📜 已寫入來源標示 {}（{} 張有授權資訊）", text_path, licensed);
+    
+    Ok(())
+}
+
+/// 標註的匯入/匯出（格式見 `labels` 模組）
+fn run_labels(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    let metadata_store = store::open_store(data_dir, backend)?;
+    let metadata = metadata_store.load_all_metadata()?;
+    if metadata.is_empty() {
+        out!("⚠️  尚無圖片（請先執行 cargo run crawl）");
+        return Ok(());
+    }
+    
+    // 位置參數的檔案（略過旗標）
+    let path = args.get(1).filter(|a| !a.starts_with("--"));
+    
+    match (args.first().map(|s| s.as_str()), path) {
+        (Some("export"), path) => {
+            // 尚未 enrich 的搜尋結果也一併匯出，作為標註的起點
+            let (enriched, _) = export::enrich(&metadata, &load_search_results(data_dir, backend)?);
+            
+            if flag_value(args, "--format") == Some("label-studio") {
+                let default_path = format!("{}/label_studio_tasks.json", data_dir);
+                let path = path.map(|s| s.as_str()).unwrap_or(&default_path);
+                let tasks = labels::label_studio::build_tasks(&enriched, flag_value(args, "--image-prefix"));
+                let prelabeled = tasks.iter().filter(|t| !t.predictions.is_empty()).count();
+                let config_path = labels::label_studio::write_tasks(path, &tasks)?;
+                out!("📤 已匯出 {} 個 Label Studio 任務（{} 個有預標註）到 {}", tasks.len(), prelabeled, path);
+                out!("   標註介面設定: {}", config_path);
+                return Ok(());
+            }
+            
+            let default_path = format!("{}/labels.json", data_dir);
+            let path = path.map(|s| s.as_str()).unwrap_or(&default_path);
+            let records = labels::build_labels(&enriched);
+            let labeled = records.iter().filter(|r| r.title.is_some() || !r.keywords.is_empty() || !r.tags.is_empty()).count();
+            labels::write_labels(path, records)?;
+            out!("📤 已匯出 {} 張圖片的標註（{} 張有內容）到 {}", enriched.len(), labeled, path);
+        }
+        (Some("import"), Some(path)) => {
+            let (format, records) = labels::read_labels(path)?;
+            let mode = if args.iter().any(|a| a == "--replace") {
+                labels::ImportMode::Replace
+            } else if args.iter().any(|a| a == "--merge") {
+                labels::ImportMode::Merge
+            } else {
+                format.default_mode()
+            };
+            
+            let (updated, summary) = labels::apply(&metadata, &records, mode);
+            out!("📥 {} 筆標註（{:?}，{:?}），對應到 {} 筆，{} 張圖片有變更",
+                records.len(), format, mode, summary.matched, summary.updated);
+            if !summary.unmatched.is_empty() {
+                out!("⚠️  {} 筆找不到對應的圖片:", summary.unmatched.len());
+                for key in summary.unmatched.iter().take(10) {
+                    out!("  {}", key);
+                }
+            }
+            
+            if summary.updated > 0 {
+                if backend == MetadataBackend::Jsonl {
+                    file_manager::FileManager::new(data_dir)?.backup_metadata()?;
+                }
+                metadata_store.rewrite_metadata(&updated)?;
+                out!("✅ 已更新 metadata（suggested_title / keywords / tags）");
+            }
+        }
+        _ => {
+            out!("用法: cargo run labels export [file] [--format label-studio] [--image-prefix <prefix>]");
+            out!("      cargo run labels import <file.json|file.csv> [--replace|--merge]");
+        }
+    }
+    
+    Ok(())
+}
+
+fn run_tags(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    let results = load_search_results(data_dir, backend)?;
+    let index = TagIndex::build(&results);
+    
+    if index.is_empty() {
+        out!("⚠️  尚無標籤（請先執行 cargo run search）");
+        return Ok(());
+    }
+    
+    match args.first().map(|s| s.as_str()) {
+        Some("list") | None => {
+            out!("🏷️  共 {} 個標籤\n", index.len());
+            for (tag, count) in index.counts() {
+                out!("  {:>6}  {}", count, tag);
+            }
+        }
+        Some("show") => {
+            let Some(tag) = args.get(1) else {
+                out!("用法: cargo run tags show <tag>");
+                return Ok(());
+            };
+            
+            match index.files_for(tag) {
+                Some(files) => {
+                    out!("🏷️  {} ({} 張)\n", tag, files.len());
+                    for file in files {
+                        out!("  {}/images/{}", data_dir, file);
+                    }
+                }
+                None => out!("❌ 找不到標籤: {}", tag),
+            }
+        }
+        Some("cooccurrence") => {
+            let top = args.get(1)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(20);
+            
+            out!("🔗 最常一起出現的標籤 (前 {} 組):\n", top);
+            for ((a, b), count) in index.cooccurrence(top) {
+                out!("  {:>6}  {} + {}", count, a, b);
+            }
+        }
+        Some(other) => {
+            out!("未知子命令: {}", other);
+            out!("可用子命令: list, show <tag>, cooccurrence [N]");
+        }
+    }
+    
+    Ok(())
+}
+
+fn run_profile(args: &[String]) -> Result<()> {
+    match (args.first().map(|s| s.as_str()), args.get(1)) {
+        (Some("list") | None, _) => {
+            let profiles = profile::list_profiles()?;
+            if profiles.is_empty() {
+                out!("⚠️  尚無 profile（cargo run profile create <name>）");
+            } else {
+                out!("📁 Profiles ({}):", profile::profiles_root()?.display());
+                for name in profiles {
+                    out!("  - {}", name);
+                }
+            }
+        }
+        (Some("create"), Some(name)) => {
+            let dir = profile::create_profile(name)?;
+            out!("✅ 已建立 profile: {} ({})", name, dir.display());
+            out!("💡 使用方式: cargo run -- --profile {} crawl", name);
+        }
+        (Some("delete"), Some(name)) => {
+            out!("⚠️  確定要刪除 profile '{}' 及其所有資料嗎？(y/N)", name);
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+            
+            if input.trim().to_lowercase() == "y" {
+                profile::delete_profile(name)?;
+                out!("🗑️  已刪除 profile: {}", name);
+            } else {
+                out!("❌ 已取消");
+            }
+        }
+        _ => {
+            out!("用法: cargo run profile [list | create <name> | delete <name>]");
+        }
+    }
+    
+    Ok(())
+}
+
+fn run_store(data_dir: &str, args: &[String]) -> Result<()> {
+    match args.first().map(|s| s.as_str()) {
+        Some("import") => {
+            let file_manager = file_manager::FileManager::new(data_dir)?;
+            let metadata = file_manager.load_all_metadata()?;
+            
+            let duplicates_path = format!("{}/duplicates.json", data_dir);
+            let groups: Vec<types::DuplicateRecord> = if std::path::Path::new(&duplicates_path).exists() {
+                serde_json::from_str(&std::fs::read_to_string(&duplicates_path)?)?
+            } else {
+                vec![]
+            };
+            
+            let results = reverse_search::load_all_results(
+                &format!("{}/reverse_search_results.jsonl", data_dir)
+            )?;
+            
+            let db_path = format!("{}/metadata.db", data_dir);
+            let store = SqliteStore::open(&db_path)?;
+            store.import(&metadata, &groups, &results)?;
+            
+            out!("✅ 已匯入 {}", db_path);
+            out!("   圖片 metadata: {} 筆", metadata.len());
+            out!("   重複組:        {} 組", groups.len());
+            out!("   搜尋結果:      {} 筆", results.len());
+            out!("\n💡 之後加上 --store sqlite 使用此資料庫");
+        }
+        _ => {
+            out!("用法: cargo run store import   # 將 JSONL 資料匯入 metadata.db");
+        }
+    }
+    
+    Ok(())
+}
+
+async fn run_redownload(data_dir: &str) -> Result<()> {
+    let file_manager = file_manager::FileManager::new(data_dir)?;
+    let queue = integrity::load_redownload_queue(&file_manager)?;
+    
+    if queue.is_empty() {
+        out!("✅ 重新下載佇列是空的");
+        return Ok(());
+    }
+    
+    out!("🔁 重新下載 {} 張損毀的圖片...\n", queue.len());
+    let (fixed, failed) = integrity::redownload_queued(&file_manager).await?;
+    
+    out!("\n✅ 已修復 {} 張", fixed);
+    if failed > 0 {
+        out!("⚠️  {} 張失敗，仍留在 redownload_queue.jsonl", failed);
+    }
+    
+    Ok(())
+}
+
+fn run_prune(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    let Some(expr) = flag_value(args, "--where") else {
+        out!("用法: cargo run prune --where \"page_number>1500 || age_days>30\" [apply]");
+        return Ok(());
+    };
+    let policy = prune::Policy::parse(expr)?;
+    
+    out!("=== 依條件刪除圖片 ===\n");
+    out!("📋 條件: {}\n", expr);
+    
+    let file_manager = file_manager::FileManager::new(data_dir)?;
+    let metadata_store = store::open_store(data_dir, backend)?;
+    
+    // 標籤來自反向搜尋結果
+    let results = load_search_results(data_dir, backend)?;
+    let mut tags: std::collections::HashMap<String, std::collections::BTreeSet<String>> = Default::default();
+    for result in &results {
+        for keyword in &result.keywords {
+            if let Some(tag) = tags::canonicalize_tag(keyword) {
+                tags.entry(result.filename.clone()).or_default().insert(tag);
+            }
+        }
+    }
+    let ctx = prune::PruneContext { now: chrono::Utc::now(), tags };
+    
+    let metadata = metadata_store.load_all_metadata()?;
+    let plan = prune::plan_prune(&metadata, &policy, &ctx);
+    
+    if plan.is_empty() {
+        out!("🎉 沒有符合條件的圖片");
+        return Ok(());
+    }
+    
+    for m in plan.iter().take(20) {
+        out!("  🗑️  {} (第 {} 頁)", m.filename, m.page_number);
+    }
+    if plan.len() > 20 {
+        out!("  ... 還有 {} 張", plan.len() - 20);
+    }
+    out!("\n共 {} 張圖片符合條件（總數 {}）\n", plan.len(), metadata.len());
+    
+    let deletions = plan.iter().map(|m| (m.filename.clone(), None)).collect();
+    let impact = ImpactSummary::compute("prune", &file_manager, &metadata, &results, &deletions);
+    impact.print();
+    out!("📄 影響摘要: {}", impact.save(data_dir)?);
+    
+    if !args.iter().any(|a| a == "apply") {
+        out!("\n💡 加上 apply 來實際移除（圖片移到回收桶）");
+        return Ok(());
+    }
+    impact.check_limit(parse_flag(args, "--max-delete")?)?;
+    
+    if !args.iter().any(|a| a == "--yes") {
+        out!("\n⚠️  確定要刪除這 {} 張圖片嗎？(y/N)", plan.len());
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        
+        if input.trim().to_lowercase() != "y" {
+            out!("❌ 已取消");
+            return Ok(());
+        }
+    }
+    
+    if backend == MetadataBackend::Jsonl {
+        file_manager.backup_metadata()?;
+    }
+    let summary = prune::apply_prune(&file_manager, metadata_store.as_ref(), expr, &plan)?;
+    out!("✅ 已移除 {} 張圖片（紀錄於 prune_log.jsonl）", summary.removed);
+    print_trash_batch(summary.trash.as_deref());
+    
+    Ok(())
+}
+
+/// 顯示移除的圖片所在的回收桶批次與還原方式
+fn print_trash_batch(id: Option<&str>) {
+    if let Some(id) = id {
+        out!("🗑️  圖片已移到 {}/{}（cargo run dedup restore {} 還原）", trash::TRASH_DIR, id, id);
+    }
+}
+
+fn run_fix_extensions(data_dir: &str, backend: MetadataBackend, mode: Option<&str>) -> Result<()> {
+    out!("=== 修正副檔名 ===\n");
+    
+    let context = DataContext::open(data_dir, backend)?;
+    let file_manager = context.file_manager();
+    
+    let metadata = context.metadata()?;
+    let template = FilenameTemplate::load(data_dir)?;
+    out!("📝 檔名樣板: {}\n", template.pattern());
+    
+    let plans = maintenance::plan_extension_fixes(file_manager, &metadata, &template)?;
+    
+    if plans.is_empty() {
+        out!("🎉 所有檔案的副檔名都正確！");
+        return Ok(());
+    }
+    
+    for plan in plans.iter().take(20) {
+        out!("  📝 {} -> {}", plan.from, plan.to);
+    }
+    if plans.len() > 20 {
+        out!("  ... 還有 {} 個檔案", plans.len() - 20);
+    }
+    out!("\n共 {} 個檔案需要修正", plans.len());
+    
+    match mode {
+        Some("apply") => {
+            if backend == MetadataBackend::Jsonl {
+                file_manager.backup_metadata()?;
+            }
+            let count = maintenance::apply_renames(&context, &plans)?;
+            out!("✅ 已修正 {} 個檔案（metadata、搜尋結果與搜尋進度已同步更新）", count);
+        }
+        Some("preview") | None => {
+            out!("\n💡 執行 'cargo run fix-extensions apply' 來實際改名");
+        }
+        Some(other) => {
+            out!("未知模式: {}", other);
+        }
+    }
+    
+    Ok(())
+}
+
+/// 依保留規則清理暫存檔與舊備份，或切換啟動時的自動清理
+fn run_gc(data_dir: &str, args: &[String]) -> Result<()> {
+    let mut config = gc::GcConfig::load(data_dir)?;
+    
+    if let Some(value) = flag_value(args, "--auto") {
+        config.auto = match value {
+            "on" => true,
+            "off" => false,
+            other => anyhow::bail!("--auto 需要 on 或 off: {}", other),
+        };
+        config.save(data_dir)?;
+        out!("✅ 啟動時自動清理: {}", if config.auto { "開啟" } else { "關閉" });
+        return Ok(());
+    }
+    
+    out!("=== 清理暫存檔與舊備份 ===\n");
+    out!("📋 保留規則（gc.json）:");
+    out!("   暫存檔 (.tmp/.part): {} 小時", config.temp_max_age_hours);
+    out!("   備份:                最新 {} 份", config.keep_backups);
+    out!("   除錯 HTML:           {} 天", config.debug_max_age_days);
+    out!("   舊 log:              {} 天\n", config.log_max_age_days);
+    
+    let plan = gc::plan_gc(data_dir, &config, std::time::SystemTime::now())?;
+    if plan.is_empty() {
+        out!("🎉 沒有需要清理的檔案");
+        return Ok(());
+    }
+    
+    for item in plan.iter().take(20) {
+        out!("  🗑️  [{}] {} ({} bytes)", item.kind.label(), item.path.display(), item.size);
+    }
+    if plan.len() > 20 {
+        out!("  ... 還有 {} 個", plan.len() - 20);
+    }
+    let total: u64 = plan.iter().map(|item| item.size).sum();
+    out!("\n共 {} 個檔案，{:.1} MB", plan.len(), total as f64 / 1_048_576.0);
+    
+    match args.first().map(|s| s.as_str()) {
+        Some("apply") => {
+            let (removed, freed) = gc::apply_gc(&plan);
+            out!("\n✅ 已刪除 {} 個檔案，釋放 {:.1} MB", removed, freed as f64 / 1_048_576.0);
+        }
+        Some("preview") | None => {
+            out!("\n💡 執行 'cargo run gc apply' 來刪除");
+        }
+        Some(other) => {
+            out!("未知模式: {}", other);
+        }
+    }
+    
+    Ok(())
+}
+
+/// 原圖分層：依 tier.json 把舊的（或 --exported 匯出過的）原圖移到冷儲存，本地留縮圖；`fetch` 取回原圖
+fn run_tier(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    let storage = tier::ColdStorage::open(data_dir)?;
+    let ctx = DataContext::open(data_dir, backend)?;
+    
+    if args.first().map(|s| s.as_str()) == Some("fetch") {
+        let filenames: Vec<_> = args[1..].iter().filter(|a| !a.starts_with("--")).collect();
+        if filenames.is_empty() {
+            anyhow::bail!("用法: tier fetch <檔名>...");
+        }
+        for filename in filenames {
+            match storage.ensure_local(ctx.file_manager(), filename)? {
+                true => out!("📥 已取回 {}", filename),
+                false if std::path::Path::new(&ctx.file_manager().get_image_path(filename)).exists() => {
+                    out!("✅ {} 已在本地", filename)
+                }
+                false => out!("❓ 本地與冷儲存都找不到 {}", filename),
+            }
+        }
+        return Ok(());
+    }
+    
+    let config = storage.config();
+    let days = parse_flag(args, "--days")?.unwrap_or(config.older_than_days);
+    let exported = match flag_value(args, "--exported") {
+        Some(dir) => tier::load_exported(dir)?,
+        None => Default::default(),
+    };
+    
+    out!("=== 原圖分層 ===\n");
+    out!("📋 規則（tier.json）:");
+    out!("   冷儲存:   {}", storage.store().location());
+    out!("   移動條件: 下載超過 {} 天{}", days,
+        if exported.is_empty() { String::new() } else { format!("，或已匯出（{} 張）", exported.len()) });
+    out!("   縮圖:     {}px（{}/）\n", config.thumbnail_size, thumbnail::THUMBNAIL_DIR);
+    
+    let (cold_count, cold_bytes) = storage.cold_usage();
+    if cold_count > 0 {
+        out!("🧊 冷儲存已有 {} 張，{:.1} MB\n", cold_count, cold_bytes as f64 / 1_048_576.0);
+    }
+    
+    let cutoff = tier::TierConfig { older_than_days: days, ..config.clone() }.cutoff(chrono::Utc::now());
+    let plan = storage.plan(ctx.file_manager(), &ctx.metadata()?, &exported, cutoff);
+    if plan.is_empty() {
+        out!("🎉 沒有需要移到冷儲存的原圖");
+        return Ok(());
+    }
+    
+    for item in plan.iter().take(20) {
+        out!("  🧊 [{}] {} ({} bytes)", item.reason.label(), item.filename, item.size);
+    }
+    if plan.len() > 20 {
+        out!("  ... 還有 {} 張", plan.len() - 20);
+    }
+    let total: u64 = plan.iter().map(|item| item.size).sum();
+    out!("\n共 {} 張，{:.1} MB", plan.len(), total as f64 / 1_048_576.0);
+    
+    match args.first().map(|s| s.as_str()).filter(|s| !s.starts_with("--")) {
+        Some("apply") => {
+            let mut moved = 0;
+            let mut freed = 0;
+            for item in &plan {
+                match storage.offload(ctx.file_manager(), item) {
+                    Ok(()) => {
+                        moved += 1;
+                        freed += item.size;
+                    }
+                    Err(e) => eout!("  ⚠️  {}: {}", item.filename, e),
+                }
+            }
+            out!("\n✅ 已移動 {} 張到冷儲存，釋放 {:.1} MB", moved, freed as f64 / 1_048_576.0);
+        }
+        Some("preview") | None => {
+            out!("\n💡 執行 'cargo run tier apply' 來移動");
+        }
+        Some(other) => {
+            out!("未知模式: {}", other);
+        }
+    }
+    
+    Ok(())
+}
+
+/// 最近的操作紀錄（--limit N 筆，可用 --user/--command 篩選，--failed 只看失敗的）
+fn run_history(data_dir: &str, args: &[String]) -> Result<()> {
+    let limit = parse_flag(args, "--limit")?.unwrap_or(20);
+    let user = flag_value(args, "--user");
+    let command = flag_value(args, "--command");
+    let failed_only = args.iter().any(|a| a == "--failed");
+    
+    let records: Vec<_> = history::load(data_dir)?
+        .into_iter()
+        .filter(|r| user.is_none() || r.user.as_deref() == user)
+        .filter(|r| command.is_none_or(|c| r.command == c))
+        .filter(|r| !failed_only || r.status == history::ExitStatus::Error)
+        .collect();
+    if records.is_empty() {
+        out!("📭 沒有符合的操作紀錄（{}）", history::HISTORY_FILE);
+        return Ok(());
+    }
+    
+    out!("=== 操作紀錄（最近 {} / {} 筆）===\n", limit.min(records.len()), records.len());
+    for record in records.iter().rev().take(limit) {
+        let who = match (&record.user, &record.host) {
+            (Some(user), Some(host)) => format!("{}@{}", user, host),
+            (Some(user), None) => user.clone(),
+            (None, Some(host)) => format!("@{}", host),
+            (None, None) => "-".to_string(),
+        };
+        let status = match record.status {
+            history::ExitStatus::Ok => "✅",
+            history::ExitStatus::Error => "❌",
+        };
+        let added = match record.counts.get("images_added") {
+            Some(added) if *added != 0 => format!("  {:+} 張", added),
+            _ => String::new(),
+        };
+        let invocation = std::iter::once(&record.command).chain(&record.args).cloned().collect::<Vec<_>>().join(" ");
+        out!("  {} {}  {:<16} {:>8.1}s  {}{}",
+            status,
+            record.started_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            who,
+            record.duration_ms as f64 / 1000.0,
+            invocation,
+            added,
+        );
+        if let Some(error) = &record.error {
+            out!("       {}", error);
+        }
+    }
+    
+    Ok(())
+}
+
+/// 資料集規模：目前的快照，或 `growth` 顯示每天的成長（`--csv` 匯出給畫圖用）
+fn run_stats(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    match args.first().map(|s| s.as_str()) {
+        Some("growth") => {}
+        None => {
+            let ctx = DataContext::open(data_dir, backend)?;
+            let snapshot = metrics::MetricsSnapshot::compute(&ctx.metadata()?, "stats", chrono::Utc::now());
+            out!("╔══════════════════════════════════╗");
+            out!("║       📊 資料集規模             ║");
+            out!("╠══════════════════════════════════╣");
+            out!("║ 圖片數:     {:>18} ║", snapshot.total_images);
+            out!("║ 不重複雜湊: {:>18} ║", snapshot.unique_hashes);
+            out!("║ 已標註:     {:>17.1}% ║", snapshot.labeled_pct());
+            out!("║ 圖片大小:   {:>15.1} MB ║", snapshot.disk_usage_bytes as f64 / 1_048_576.0);
+            out!("╚══════════════════════════════════╝");
+            return Ok(());
+        }
+        Some(other) => anyhow::bail!("未知的 stats 子命令: {}（可用 growth）", other),
+    }
+    
+    let history = metrics::load_history(data_dir)?;
+    if history.is_empty() {
+        out!("📭 還沒有記錄（爬取、去重、匯入標註等改變資料集的命令完成後會自動記錄當天的規模）");
+        return Ok(());
+    }
+    
+    out!("=== 資料集成長（{} 天）===\n", history.len());
+    out!("  {:<10}  {:>8}  {:>8}  {:>7}  {:>10}  {:>7}", "日期", "圖片", "不重複", "標註%", "大小 MB", "新增");
+    let mut previous: Option<usize> = None;
+    for s in &history {
+        let added = match previous {
+            Some(p) => format!("{:+}", s.total_images as i64 - p as i64),
+            None => "-".to_string(),
+        };
+        out!("  {:<10}  {:>8}  {:>8}  {:>7.1}  {:>10.1}  {:>7}",
+            s.date, s.total_images, s.unique_hashes, s.labeled_pct(),
+            s.disk_usage_bytes as f64 / 1_048_576.0, added);
+        previous = Some(s.total_images);
+    }
+    
+    if let Some(path) = flag_value(args, "--csv") {
+        metrics::write_csv(path, &history)?;
+        out!("\n✅ 已匯出: {}", path);
+    }
+    
+    Ok(())
+}
+
+/// 以 embedding 把同模板的圖片分群（preview 只顯示，apply 寫入 metadata 的 extra.template_cluster）
+fn run_cluster(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    out!("=== 模板分群 ===\n");
+    
+    let context = DataContext::open(data_dir, backend)?;
+    let metadata = context.metadata()?;
+    if metadata.is_empty() {
+        out!("⚠️  尚無圖片（請先執行 cargo run crawl）");
+        return Ok(());
+    }
+    
+    let mode = args.first().map(|s| s.as_str()).filter(|s| !s.starts_with("--"));
+    if !matches!(mode, Some("preview") | Some("apply") | None) {
+        out!("未知子命令: {}", mode.unwrap_or_default());
+        return Ok(());
+    }
+    let threshold = parse_flag::<f32>(args, "--threshold")?.unwrap_or(cluster::DEFAULT_THRESHOLD);
+    let embedder: Box<dyn cluster::Embedder> = match flag_value(args, "--model") {
+        Some(command) => Box::new(cluster::ModelEmbedder::new(command)),
+        None => Box::new(cluster::LayoutEmbedder),
+    };
+    
+    out!("🧬 計算 embedding（{}）...", embedder.name());
+    let mut cache = cluster::EmbeddingCache::load(data_dir)?;
+    let report = cluster::embed_all(embedder.as_ref(), &context, &metadata, &mut cache)?;
+    let clusters = cluster::cluster_hashes(&report.embeddings, threshold);
+    let mut updated = metadata.as_ref().clone();
+    let clustered = cluster::apply_clusters(&mut updated, &clusters);
+    
+    out!("╔══════════════════════════════════╗");
+    out!("║       🧬 模板分群結果           ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 不同內容:   {:>18} ║", report.embeddings.len());
+    out!("║ 新計算:     {:>18} ║", report.computed);
+    out!("║ 失敗:       {:>18} ║", report.failed.len());
+    out!("║ 群組數:     {:>18} ║", clusters.len());
+    out!("║ 有群組的圖片: {:>16} ║", clustered);
+    out!("╚══════════════════════════════════╝");
+    for (filename, error) in report.failed.iter().take(10) {
+        out!("  ⚠️  {}: {}", filename, error);
+    }
+    
+    if mode != Some("apply") {
+        let mut largest: Vec<(usize, &Vec<String>)> = clusters.iter().enumerate().collect();
+        largest.sort_by_key(|(_, members)| std::cmp::Reverse(members.len()));
+        for (i, members) in largest.into_iter().take(10) {
+            let names: Vec<&str> = members
+                .iter()
+                .filter_map(|hash| updated.iter().find(|m| &m.content_hash == hash))
+                .map(|m| m.filename.as_str())
+                .take(5)
+                .collect();
+            out!("  {}  {} 種  {}", cluster::cluster_id(i), members.len(), names.join(", "));
+        }
+        out!("\n💡 執行 'cargo run cluster apply' 將群組寫入 metadata（extra.{}）", cluster::CLUSTER_FIELD);
+        return Ok(());
+    }
+    
+    if backend == MetadataBackend::Jsonl {
+        context.file_manager().backup_metadata()?;
+    }
+    context.rewrite_metadata(updated)?;
+    out!("\n✅ 已寫入 {} 張圖片的模板群組", clustered);
+    
+    Ok(())
+}
+
+/// 產生 thumbnails/ 下的縮圖（HTML 報告與預覽圖優先使用），中斷後重跑會接著做
+fn run_thumbnails(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    out!("=== 縮圖 ===\n");
+    
+    let context = DataContext::open(data_dir, backend)?;
+    let metadata = context.metadata()?;
+    
+    if args.first().map(|s| s.as_str()) == Some("clean") {
+        let orphans = thumbnail::orphans(context.file_manager(), &metadata)?;
+        for path in &orphans {
+            std::fs::remove_file(path).with_context(|| format!("無法刪除 {}", path.display()))?;
+        }
+        out!("🧹 已刪除 {} 張沒有對應圖片的縮圖", orphans.len());
+        return Ok(());
+    }
+    if metadata.is_empty() {
+        out!("⚠️  尚無圖片（請先執行 cargo run crawl）");
+        return Ok(());
+    }
+    
+    let size = parse_flag(args, "--size")?.unwrap_or(thumbnail::DEFAULT_SIZE);
+    let workers = match parse_flag::<usize>(args, "--workers")? {
+        Some(workers) => workers,
+        None => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+    };
+    out!("🖼️  長邊 {}px，WebP，{} 個執行緒\n", size, workers);
+    
+    let report = thumbnail::generate_all(
+        &context,
+        &metadata,
+        size,
+        args.iter().any(|a| a == "--force"),
+        workers,
+    )?;
+    
+    out!("╔══════════════════════════════════╗");
+    out!("║       🖼️  縮圖結果               ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 產生:       {:>18} ║", report.generated);
+    out!("║ 已是最新:   {:>18} ║", report.skipped);
+    out!("║ 原圖不在本地: {:>16} ║", report.missing);
+    out!("║ 失敗:       {:>18} ║", report.failed.len());
+    out!("╚══════════════════════════════════╝");
+    for (filename, error) in report.failed.iter().take(10) {
+        out!("  ⚠️  {}: {}", filename, error);
+    }
+    out!("\n📁 {}/{}/", data_dir, thumbnail::THUMBNAIL_DIR);
+    
+    Ok(())
+}
+
+fn describe_normalize(options: &normalize::NormalizeOptions) -> String {
+    let mut steps = Vec::new();
+    if options.strip_metadata {
+        steps.push("移除 EXIF/XMP".to_string());
+    }
+    match options.format {
+        Some(normalize::OutputFormat::Jpeg) => steps.push(format!("轉成 JPEG（品質 {}）", options.quality)),
+        Some(normalize::OutputFormat::Webp) => steps.push("轉成 WebP（無損）".to_string()),
+        Some(normalize::OutputFormat::Png) => steps.push("轉成 PNG".to_string()),
+        None => {}
+    }
+    steps.join("、")
+}
+
+/// 移除圖片的 EXIF 或統一格式（preview 只計算，apply 覆寫圖片並更新 hash、大小與副檔名）
+fn run_normalize(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    out!("=== 圖片正規化 ===\n");
+    
+    let context = DataContext::open(data_dir, backend)?;
+    let metadata = context.metadata()?;
+    if metadata.is_empty() {
+        out!("⚠️  尚無圖片（請先執行 cargo run crawl）");
+        return Ok(());
+    }
+    
+    let mode = args.first().map(|s| s.as_str()).filter(|s| !s.starts_with("--"));
+    if !matches!(mode, Some("preview") | Some("apply") | None) {
+        out!("未知子命令: {}", mode.unwrap_or_default());
+        return Ok(());
+    }
+    let options = normalize::NormalizeOptions {
+        strip_metadata: !args.iter().any(|a| a == "--keep-metadata"),
+        format: flag_value(args, "--format").map(normalize::OutputFormat::parse).transpose()?,
+        quality: parse_flag(args, "--quality")?.unwrap_or(normalize::DEFAULT_QUALITY),
+    };
+    if !options.strip_metadata && options.format.is_none() {
+        anyhow::bail!("--keep-metadata 需要搭配 --format（否則沒有要轉換的）");
+    }
+    let workers = match parse_flag::<usize>(args, "--workers")? {
+        Some(workers) => workers,
+        None => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+    };
+    out!("🧽 {}，{} 個執行緒\n", describe_normalize(&options), workers);
+    
+    let apply = mode == Some("apply");
+    if apply && backend == MetadataBackend::Jsonl {
+        context.file_manager().backup_metadata()?;
+    }
+    let report = normalize::normalize_all(&context, &metadata, &options, workers, apply);
+    let files: std::collections::HashSet<&str> = report.changed.iter().map(|(i, _)| metadata[*i].filename.as_str()).collect();
+    
+    out!("╔══════════════════════════════════╗");
+    out!("║       🧽 正規化結果             ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 需要轉換:   {:>18} ║", files.len());
+    out!("║ 不需轉換:   {:>18} ║", report.unchanged);
+    out!("║ 失敗:       {:>18} ║", report.failed.len());
+    out!("╚══════════════════════════════════╝");
+    for (filename, error) in report.failed.iter().take(10) {
+        out!("  ⚠️  {}: {}", filename, error);
+    }
+    
+    if !apply {
+        for (i, normalized) in report.changed.iter().take(10) {
+            let before = metadata[*i].file_size.unwrap_or_default();
+            out!("  {}: {} -> {} bytes", metadata[*i].filename, before, normalized.file_size);
+        }
+        out!("\n💡 執行 'cargo run normalize apply' 覆寫圖片並更新 metadata");
+        return Ok(());
+    }
+    if report.changed.is_empty() {
+        out!("\n✅ 沒有需要轉換的圖片");
+        return Ok(());
+    }
+    
+    // 圖片已在轉換時寫入，這裡只更新 metadata
+    let mut updated = metadata.as_ref().clone();
+    for (i, normalized) in &report.changed {
+        normalize::update_metadata(&mut updated[*i], normalized);
+    }
+    context.rewrite_metadata(updated.clone())?;
+    out!("\n✅ 已轉換 {} 張圖片（hash、大小與尺寸已更新）", files.len());
+    
+    // 格式改變後副檔名也跟著改
+    let plans = maintenance::plan_extension_fixes(context.file_manager(), &updated, &FilenameTemplate::load(data_dir)?)?;
+    if !plans.is_empty() {
+        let count = maintenance::apply_renames(&context, &plans)?;
+        out!("📝 已修正 {} 個檔案的副檔名", count);
+    }
+    
+    Ok(())
+}
+
+/// 擷取圖片上的文字（preview 只顯示，apply 寫入 metadata 的 ocr_text）
+fn run_ocr(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    out!("=== 圖片文字辨識 ===\n");
+    
+    let context = DataContext::open(data_dir, backend)?;
+    let metadata = context.metadata()?;
+    if metadata.is_empty() {
+        out!("⚠️  尚無圖片（請先執行 cargo run crawl）");
+        return Ok(());
+    }
+    
+    let mode = args.first().map(|s| s.as_str()).filter(|s| !s.starts_with("--"));
+    if !matches!(mode, Some("preview") | Some("apply") | None) {
+        out!("未知子命令: {}", mode.unwrap_or_default());
+        return Ok(());
+    }
+    let redo = args.iter().any(|a| a == "--all");
+    let workers = match parse_flag::<usize>(args, "--workers")? {
+        Some(workers) => workers,
+        None => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+    };
+    let engine: Box<dyn ocr::OcrEngine> = match flag_value(args, "--model") {
+        Some(command) => {
+            out!("🔤 OCR 程式: {}", command);
+            Box::new(ocr::CommandOcr::new(command))
+        }
+        None => {
+            let languages = flag_value(args, "--lang").unwrap_or(ocr::TesseractOcr::DEFAULT_LANGUAGES);
+            out!("🔤 tesseract（{}）", languages);
+            Box::new(ocr::TesseractOcr::new(languages))
+        }
+    };
+    
+    let report = ocr::ocr_all(engine.as_ref(), &context, &metadata, redo, workers);
+    let with_text = report.recognized.iter().filter(|(_, text)| !text.is_empty()).count();
+    
+    out!("╔══════════════════════════════════╗");
+    out!("║       🔤 文字辨識結果           ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 辨識:       {:>18} ║", report.recognized.len());
+    out!("║ 有文字:     {:>18} ║", with_text);
+    out!("║ 已有結果略過: {:>16} ║", report.skipped);
+    out!("║ 失敗:       {:>18} ║", report.failed.len());
+    out!("╚══════════════════════════════════╝");
+    for (filename, error) in report.failed.iter().take(10) {
+        out!("  ⚠️  {}: {}", filename, error);
+    }
+    
+    if mode != Some("apply") {
+        for (i, text) in report.recognized.iter().filter(|(_, text)| !text.is_empty()).take(10) {
+            out!("  {}: {}", metadata[*i].filename, text.replace('\n', " / "));
+        }
+        if report.skipped > 0 {
+            out!("\n💡 加上 --all 重新辨識已有 ocr_text 的圖片");
+        }
+        out!("\n💡 執行 'cargo run ocr apply' 將文字寫入 metadata（ocr_text）");
+        return Ok(());
+    }
+    if report.recognized.is_empty() {
+        out!("\n✅ 沒有需要寫入的文字");
+        return Ok(());
+    }
+    
+    let mut updated = metadata.as_ref().clone();
+    for (i, text) in &report.recognized {
+        updated[*i].ocr_text = Some(text.clone());
+    }
+    if backend == MetadataBackend::Jsonl {
+        context.file_manager().backup_metadata()?;
+    }
+    context.rewrite_metadata(updated)?;
+    out!("\n✅ 已寫入 {} 張圖片的 ocr_text", report.recognized.len());
+    
+    Ok(())
+}
+
+/// 迷因/非迷因分類（規則式，`--model` 改用外部模型），borderline 的圖片放進人工確認佇列
+fn run_classify(data_dir: &str, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    out!("=== 迷因圖分類 ===\n");
+    
+    let context = DataContext::open(data_dir, backend)?;
+    let metadata = context.metadata()?;
+    if metadata.is_empty() {
+        out!("⚠️  尚無圖片（請先執行 cargo run crawl）");
+        return Ok(());
+    }
+    
+    match args.first().map(|s| s.as_str()).filter(|s| !s.starts_with("--")) {
+        Some("mark") => {
+            let (Some(filename), Some(class)) = (args.get(1), args.get(2)) else {
+                out!("用法: cargo run classify mark <filename> meme|not_meme");
+                return Ok(());
+            };
+            let class = classify::MemeClass::parse(class)?;
+            let mut updated = metadata.as_ref().clone();
+            let Some(m) = updated.iter_mut().find(|m| &m.filename == filename) else {
+                anyhow::bail!("找不到圖片: {}", filename);
+            };
+            
+            let score = match class {
+                classify::MemeClass::Meme => 1.0,
+                classify::MemeClass::NotMeme => 0.0,
+                classify::MemeClass::Borderline => 0.5,
+            };
+            let classification = classify::Classification { class, score, reasons: vec!["人工標記".to_string()] };
+            classify::set_class(m, &classification, "manual");
+            context.rewrite_metadata(updated)?;
+            
+            let (_, queued) = classify::write_review_queue(context.file_manager(), &context.metadata()?)?;
+            out!("✅ 已將 {} 標記為 {}（確認佇列剩 {} 張）", filename, class.label(), queued);
+        }
+        Some("nsfw") => run_classify_nsfw(&context, backend, &args[1..])?,
+        Some("review") => {
+            let (path, queued) = classify::write_review_queue(context.file_manager(), &metadata)?;
+            if queued == 0 {
+                out!("✅ 沒有待確認的圖片");
+            } else {
+                out!("📋 {} 張待確認的圖片: {}", queued, path);
+                out!("💡 確認後執行 'cargo run classify mark <filename> meme|not_meme'");
+            }
+        }
+        mode @ (Some("preview") | Some("apply") | None) => {
+            let classifier: Box<dyn classify::MemeClassifier> = match flag_value(args, "--model") {
+                Some(command) => Box::new(classify::CommandClassifier::new(command, Default::default())),
+                None => Box::new(classify::RuleClassifier::default()),
+            };
+            let source = if flag_value(args, "--model").is_some() { "model" } else { "rules" };
+            let reclassify = args.iter().any(|a| a == "--all");
+            let report = classify::classify_all(classifier.as_ref(), &context, &metadata, reclassify);
+            
+            out!("╔══════════════════════════════════╗");
+            out!("║       🧪 分類結果               ║");
+            out!("╠══════════════════════════════════╣");
+            out!("║ meme:       {:>18} ║", report.count(classify::MemeClass::Meme));
+            out!("║ not_meme:   {:>18} ║", report.count(classify::MemeClass::NotMeme));
+            out!("║ borderline: {:>18} ║", report.count(classify::MemeClass::Borderline));
+            out!("║ 略過:       {:>18} ║", report.skipped);
+            out!("║ 失敗:       {:>18} ║", report.failed.len());
+            out!("╚══════════════════════════════════╝");
+            for (filename, error) in report.failed.iter().take(10) {
+                out!("  ⚠️  {}: {}", filename, error);
+            }
+            
+            if mode != Some("apply") {
+                for (i, classification) in report.classified.iter().filter(|(_, c)| c.class != classify::MemeClass::Meme).take(20) {
+                    out!("  {:<10} {:.2}  {}  ({})", classification.class.label(), classification.score, metadata[*i].filename, classification.reasons.join("; "));
+                }
+                out!("\n💡 執行 'cargo run classify apply' 將分類寫入 metadata（extra.meme_class）");
+                return Ok(());
+            }
+            
+            let mut updated = metadata.as_ref().clone();
+            for (i, classification) in &report.classified {
+                classify::set_class(&mut updated[*i], classification, source);
+            }
+            if backend == MetadataBackend::Jsonl {
+                context.file_manager().backup_metadata()?;
+            }
+            context.rewrite_metadata(updated)?;
+            
+            let (path, queued) = classify::write_review_queue(context.file_manager(), &context.metadata()?)?;
+            out!("\n✅ 已寫入 {} 張圖片的分類", report.classified.len());
+            if queued > 0 {
+                out!("📋 {} 張待人工確認: {}", queued, path);
+            }
+        }
+        Some(other) => {
+            out!("未知子命令: {}", other);
+            out!("可用子命令: preview, apply, review, mark <filename> <class>, nsfw");
+        }
+    }
+    
+    Ok(())
+}
+
+/// NSFW 偵測（本機模型）：preview 只顯示，apply 寫入 metadata，加上 --quarantine 時移到隔離目錄
+fn run_classify_nsfw(context: &DataContext, backend: MetadataBackend, args: &[String]) -> Result<()> {
+    let metadata = context.metadata()?;
+    let quarantine = nsfw::Quarantine::new(context.root());
+    
+    let mode = args.first().map(|s| s.as_str()).filter(|s| !s.starts_with("--"));
+    match mode {
+        Some("release") => {
+            let Some(filename) = args.get(1) else {
+                out!("用法: cargo run classify nsfw release <filename>");
+                return Ok(());
+            };
+            let released = quarantine.release(context.file_manager(), filename)?;
+            let mut updated = metadata.as_ref().clone();
+            updated.extend(released);
+            context.rewrite_metadata(updated)?;
+            out!("✅ 已將 {} 放回資料集（標記為非 NSFW）", filename);
+            return Ok(());
+        }
+        Some("list") => {
+            let quarantined = quarantine.load()?;
+            if quarantined.is_empty() {
+                out!("✅ 隔離目錄是空的");
+            }
+            for m in &quarantined {
+                let score = m.extra.get(nsfw::SCORE_FIELD).and_then(|v| v.as_f64()).unwrap_or_default();
+                out!("  {:.2}  {}", score, m.filename);
+            }
+            return Ok(());
+        }
+        Some("preview") | Some("apply") | None => {}
+        Some(other) => {
+            out!("未知子命令: {}", other);
+            out!("可用子命令: preview, apply, list, release <filename>");
+            return Ok(());
+        }
+    }
+    
+    let Some(command) = flag_value(args, "--model") else {
+        out!("⚠️  NSFW 偵測需要本機模型: --model <command>（執行 <command> <圖片路徑>，stdout 輸出 0~1 的分數）");
+        return Ok(());
+    };
+    let threshold = parse_flag(args, "--threshold")?.unwrap_or(nsfw::DEFAULT_THRESHOLD);
+    let quarantine_flagged = args.iter().any(|a| a == "--quarantine");
+    let report = nsfw::scan_all(&nsfw::CommandDetector::new(command), context.file_manager(), &metadata, args.iter().any(|a| a == "--all"));
+    let flagged = report.flagged(threshold);
+    
+    out!("╔══════════════════════════════════╗");
+    out!("║       🔞 NSFW 偵測結果          ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 偵測:       {:>18} ║", report.scored.len());
+    out!("║ NSFW:       {:>18} ║", flagged);
+    out!("║ 已偵測略過: {:>18} ║", report.skipped);
+    out!("║ 失敗:       {:>18} ║", report.failed.len());
+    out!("╚══════════════════════════════════╝");
+    for (filename, error) in report.failed.iter().take(10) {
+        out!("  ⚠️  {}: {}", filename, error);
+    }
+    
+    if mode != Some("apply") {
+        for (i, score) in report.scored.iter().filter(|(_, score)| *score >= threshold).take(20) {
+            out!("  {:.2}  {}", score, metadata[*i].filename);
+        }
+        out!("\n💡 執行 'cargo run classify nsfw apply' 將結果寫入 metadata（extra.{}），加上 --quarantine 移到 {}/", nsfw::FLAG_FIELD, nsfw::QUARANTINE_DIR);
+        return Ok(());
+    }
+    
+    let mut updated = metadata.as_ref().clone();
+    for (i, score) in &report.scored {
+        nsfw::tag(&mut updated[*i], *score, threshold);
+    }
+    if backend == MetadataBackend::Jsonl {
+        context.file_manager().backup_metadata()?;
+    }
+    if quarantine_flagged {
+        let (moved, kept): (Vec<_>, Vec<_>) = updated.into_iter().partition(nsfw::is_flagged);
+        for m in &moved {
+            quarantine.move_in(std::path::Path::new(&context.file_manager().get_image_path(&m.filename)), m)?;
+        }
+        context.rewrite_metadata(kept)?;
+        out!("\n✅ 已寫入 {} 張圖片的偵測結果，{} 張移到 {}", report.scored.len(), moved.len(), quarantine.dir().display());
+        if !moved.is_empty() {
+            out!("💡 誤判時執行 'cargo run classify nsfw release <filename>' 放回");
+        }
+    } else {
+        context.rewrite_metadata(updated)?;
+        out!("\n✅ 已寫入 {} 張圖片的偵測結果（{} 張 NSFW）", report.scored.len(), flagged);
+    }
+    
+    Ok(())
+}
+
+/// 重新計算圖片 hash，找出內容損毀的項目
+fn run_verify(data_dir: &str, backend: MetadataBackend, mode: Option<&str>) -> Result<()> {
+    out!("=== 驗證圖片完整性 ===\n");
+    
+    let file_manager = file_manager::FileManager::new(data_dir)?;
+    let metadata_store = store::open_store(data_dir, backend)?;
+    
+    let metadata = metadata_store.load_all_metadata()?;
+    let report = integrity::verify_images(&file_manager, &metadata)?;
+    
+    out!("🔍 已比對 {} 個檔案", report.checked);
+    if !report.missing.is_empty() {
+        out!("\n⚠️  {} 個檔案不存在（可用 reconcile 檢查）:", report.missing.len());
+        for filename in report.missing.iter().take(20) {
+            out!("  - {}", filename);
+        }
+        if report.missing.len() > 20 {
+            out!("  ... 還有 {} 個", report.missing.len() - 20);
+        }
+    }
+    
+    if report.corrupted.is_empty() {
+        out!("\n🎉 所有圖片內容都與 hash 一致！");
+        return Ok(());
+    }
+    
+    out!("\n❌ {} 個檔案已損毀:", report.corrupted.len());
+    for mismatch in report.corrupted.iter().take(20) {
+        out!("  - {}", mismatch);
+    }
+    if report.corrupted.len() > 20 {
+        out!("  ... 還有 {} 個", report.corrupted.len() - 20);
+    }
+    
+    match mode {
+        Some("apply") => {
+            let corrupted: std::collections::HashSet<&str> =
+                report.corrupted.iter().map(|m| m.filename.as_str()).collect();
+            let plan: Vec<_> = metadata
+                .into_iter()
+                .filter(|m| corrupted.contains(m.filename.as_str()))
+                .collect();
+            
+            if backend == MetadataBackend::Jsonl {
+                file_manager.backup_metadata()?;
+            }
+            let summary = prune::apply_prune(&file_manager, metadata_store.as_ref(), "verify: hash mismatch", &plan)?;
+            out!("\n✅ 已移除 {} 張損毀的圖片與其 metadata（紀錄於 prune_log.jsonl）", summary.removed);
+            print_trash_batch(summary.trash.as_deref());
+        }
+        Some("preview") | None => {
+            out!("\n💡 執行 'cargo run verify apply' 來刪除損毀的圖片與其 metadata");
+        }
+        Some(other) => {
+            out!("未知模式: {}", other);
+        }
+    }
+    
+    Ok(())
+}
+
+/// 對照實際檔案補齊舊 metadata 的欄位，並回報不一致
+fn run_reconcile(data_dir: &str, backend: MetadataBackend, mode: Option<&str>) -> Result<()> {
+    out!("=== 比對 metadata 與檔案 ===\n");
+    
+    let file_manager = file_manager::FileManager::new(data_dir)?;
+    let metadata_store = store::open_store(data_dir, backend)?;
+    
+    let mut metadata = metadata_store.load_all_metadata()?;
+    let report = maintenance::reconcile_metadata(&file_manager, &mut metadata)?;
+    
+    out!("📏 可補上檔案大小: {} 筆", report.filled_size);
+    out!("🖼️  可補上尺寸:     {} 筆", report.filled_dimensions);
+    out!("🎞️  可補上媒體類型: {} 筆", report.filled_media_type);
+    out!("🕒 可補上下載時間: {} 筆（以檔案修改時間）", report.filled_downloaded_at);
+    
+    if !report.discrepancies.is_empty() {
+        out!("\n⚠️  {} 個不一致:", report.discrepancies.len());
+        for discrepancy in report.discrepancies.iter().take(20) {
+            out!("  - {}", discrepancy);
+        }
+        if report.discrepancies.len() > 20 {
+            out!("  ... 還有 {} 個", report.discrepancies.len() - 20);
+        }
+    }
+    
+    if !report.changed() {
+        out!("\n🎉 metadata 欄位都已齊全！");
+        return Ok(());
+    }
+    
+    match mode {
+        Some("apply") => {
+            if backend == MetadataBackend::Jsonl {
+                file_manager.backup_metadata()?;
+            }
+            metadata_store.rewrite_metadata(&metadata)?;
+            out!("\n✅ 已更新 metadata（不一致的項目只回報，未修改）");
+        }
+        Some("preview") | None => {
+            out!("\n💡 執行 'cargo run reconcile apply' 來寫入補上的欄位");
+        }
+        Some(other) => {
+            out!("未知模式: {}", other);
+        }
+    }
     
     Ok(())
 }
 
 fn print_help() {
-    println!("Memes Crawler - 圖片爬蟲工具\n");
-    println!("用法:");
-    println!("  cargo run                        # 執行爬蟲");
-    println!("  cargo run crawl                  # 執行爬蟲");
-    println!("  cargo run dedup [preview|remove] # 分析/刪除重複圖片");
-    println!("  cargo run search [service]       # 反向圖片搜尋");
-    println!("  cargo run search-stats           # 顯示搜尋統計");
-    println!("  cargo run --help                 # 顯示此幫助\n");
-    println!("反向搜尋服務:");
-    println!("  tineye   - TinEye 反向搜尋 (預設)");
-    println!("  bing     - Bing 反向搜尋");
-    println!("  all      - 使用所有服務\n");
-    println!("範例:");
-    println!("  cargo run search tineye          # 只用 TinEye");
-    println!("  cargo run search bing            # 只用 Bing");
-    println!("  cargo run search all             # 兩個都用\n");
-    println!("資料檔案:");
-    println!("  ./data/images/                      # 圖片");
-    println!("  ./data/metadata.jsonl               # 圖片 metadata");
-    println!("  ./data/progress.json                # 爬蟲進度");
-    println!("  ./data/duplicates.json              # 重複圖片");
-    println!("  ./data/search_progress.json         # 搜尋進度");
-    println!("  ./data/reverse_search_results.jsonl # 搜尋結果");
+    out!("Memes Crawler - 圖片爬蟲工具\n");
+    out!("用法:");
+    out!("  cargo run                        # 執行爬蟲");
+    out!("  cargo run crawl                  # 執行爬蟲");
+    out!("  cargo run crawl --pages <N>      # 只爬到第 N 頁（預設每次執行前偵測最後一頁）");
+    out!("  cargo run crawl --site imgflip [--pages N] # 爬其他內建網站（memes_tw, imgflip；建議搭配 --profile）");
+    out!("  cargo run crawl --api <config.json> [--pages N] # 爬分頁端點回傳 JSON 的網站（url_template、items_path、image_field、name_field、license_field、has_more_field、total_pages_field）");
+    out!("  cargo run crawl --selectors <config.json> [--pages N] # 以 CSS 選擇器設定網站（url_template、container_selector、image_selector、name_selector、next_selector...）");
+    out!("  cargo run crawl --regex <config.json> [--pages N] # 以正規表示式從 inline <script> JSON 取圖片（url_template、pattern 的具名群組 url/title/license、scope）");
+    out!("  cargo run crawl --plugin <name> [--pages N] # 以 plugins.json 登記的外部程式解析頁面（stdin/stdout JSONL：parse_page、parse_detail_page、parse_licenses、page_hint）");
+    out!("  cargo run crawl --detail <config.json> [--detail-concurrency 4] # 列表頁只有縮圖時，進詳細頁取原圖與標籤/欄位（image_selector、image_attr、tag_selector、fields）");
+    out!("  cargo run parse-test [--site memes_tw|--selectors cfg|--api cfg|--regex cfg|--plugin name] [--url <url>|--page N|--file page.html]");
+    out!("                                   # 只解析單一頁面並列出 (網址, 名稱)，不下載，調整選擇器用");
+    out!("  cargo run parse-compare --old \"--site memes_tw\" --new \"--selectors new.json\" [--pages 1-3|--url <url>|--file a.html,b.html]");
+    out!("                                   # 新舊設定解析相同頁面，列出多出/缺少/改名的項目");
+    out!("  cargo run crawl --warmup <N>     # 先慢速爬 N 頁，自動調整並發數與延遲");
+    out!("  cargo run crawl --concurrency <N> --batch-delay <ms> # 並發數（預設 10）與每批次間隔（預設 1000）");
+    out!("  cargo run crawl --allowed-hours 01:00-07:00 [--timezone Asia/Taipei|+08:00]");
+    out!("                                   # 只在指定時段爬取，時段外自動暫停（時區名稱隨夏令時間調整）");
+    out!("  cargo run crawl --min-width <px> --min-height <px> --max-bytes <N>");
+    out!("                                   # 略過縮圖與過大的檔案");
+    out!("  cargo run crawl --no-animated --no-video # 不下載動圖（多影格 GIF/WebP/APNG）或影片（mp4、webm）");
+    out!("  cargo run crawl --parse-workers <N>  # 同時解析頁面的執行緒數（預設 CPU 核心數）");
+    out!("  cargo run crawl --max-in-flight <N>  # 同時下載的圖片數上限（預設 32）");
+    out!("  cargo run crawl --breaker-window 20 --breaker-threshold 0.5 --breaker-probe 60 # 最近 N 頁失敗率超過門檻時暫停，每隔幾秒試抓直到恢復（--no-breaker 關閉）");
+    out!("  cargo run crawl --normalize [--normalize-format webp|jpeg|png] [--quality 90]");
+    out!("                                   # 下載後移除 EXIF/XMP 或統一格式（hash 依轉換後的內容計算；JPEG 才有品質設定）");
+    out!("  cargo run crawl --nsfw-model <command> [--nsfw-threshold 0.8] [--nsfw-action tag|quarantine]");
+    out!("                                   # 下載時以本機 NSFW 模型偵測，標記 metadata 或移到 quarantine/（預設 tag）");
+    out!("  cargo run crawl --dedup-window <N>   # 同一次執行中內容相同的圖片只存一份，記住最近 N 個 hash（預設 100000，0 關閉）");
+    out!("  cargo run crawl --download-timeout <secs> --download-retries <N>");
+    out!("                                   # 圖片下載的逾時（預設 60）與重試次數（預設 2），與頁面請求分開");
+    out!("  cargo run crawl --download-backoff <ms> # 圖片下載第一次重試前的等待（預設 1000，之後每次加倍）");
+    out!("  cargo run crawl --retry-downloads    # 重新下載 failed_downloads.jsonl 中失敗的圖片");
+    out!("  cargo run crawl --rps <n> [--burst <n>] # 每個 host 每秒的請求數上限（頁面與圖片下載共用，預設 5；0 表示不限制）");
+    out!("  cargo run crawl --ignore-robots      # 不遵守 robots.txt（預設遵守 Disallow 與 Crawl-delay；reddit/kym/feed 同樣適用）");
+    out!("  cargo run crawl --deterministic [--seed N] [--sample 0.1]");
+    out!("                                   # 可重現模式：固定頁序/頁內順序/檔名，依 seed 抽樣（記錄於 dataset_manifest.json）");
+    out!("  cargo run crawl --watch [--interval 6h] # 常駐：爬完後定期從第 1 頁增量爬取沒看過的圖片");
+    out!("  cargo run crawl --from-urls urls.txt # 直接下載清單中的圖片網址（每行 網址[<Tab>名稱]，可中斷續傳）");
+    out!("  cargo run bench [--pages 20] [--concurrency 2,5,10] [--batch-delay 0,500,1000] # 以各組設定爬 N 頁，比較每秒圖片數與失敗率並建議設定（可加 crawl 的 --site 等參數）");
+    out!("  cargo run bench --mock [--latency 50] [--mock-limit 16] # 改爬本機模擬網站（回應延遲 ms，同時超過 N 個請求時回 429），只量測爬蟲本身");
+    out!("  cargo run diff-crawl [--site imgflip] [--from N] [--to N]");
+    out!("                                   # 重爬已完成的頁面，消失/換圖的項目記錄到 site_changes.jsonl");
+    out!("  cargo run reddit [memes,dankmemes] [--sort hot|new|top:week] [--pages N] [--nsfw]");
+    out!("                                   # 從 Reddit 版面下載圖片（可搭配 crawl 的過濾旗標）");
+    out!("  cargo run kym [--pages N] [--gallery-pages N]");
+    out!("                                   # 從 KnowYourMeme 條目下載圖片，名稱/年份/標籤/About 寫入 metadata");
+    out!("  cargo run feeds [url1,url2] [--watch 30m]");
+    out!("                                   # 從 RSS/Atom 下載新文章的圖片（預設讀 feeds.txt，--watch 常駐輪詢）");
+    out!("  cargo run dedup [preview|remove] # 分析/刪除重複圖片（同時產生 data/dedup_report.html 供目視檢查）");
+    out!("  cargo run dedup remove --max-delete <N> [--yes]");
+    out!("                                   # 刪除數超過 N 時不執行；--yes 不詢問（非互動執行）");
+    out!("  cargo run dedup remove --trash   # 重複圖片移到 data/trash/<時間>/（附 manifest），而不是直接刪除");
+    out!("  cargo run dedup restore [<批次>] # 還原回收桶中的一次移除（圖片、metadata 與搜尋結果）；不指定時列出回收桶");
+    out!("  cargo run dedup [preview|remove] --dirs data/memes_tw,data/imgflip");
+    out!("                                   # 跨資料目錄去重：保留 --dirs 中排在前面的目錄的檔案");
+    out!("  cargo run enrich [--in-place]    # 把搜尋關鍵字與推測標題合併進 metadata（metadata_enriched.jsonl）");
+    out!("  cargo run export [--format csv|parquet] [--output path]");
+    out!("  cargo run export ... --no-attribution  # 不寫入來源標示（attribution.jsonl、ATTRIBUTION）");
+    out!("  cargo run export ... --exclude-class not_meme[,borderline] # 排除 classify 分類的圖片（未分類的保留）");
+    out!("  cargo run export ... --exclude-nsfw  # 排除標記為 NSFW 的圖片");
+    out!("  cargo run export ... --min-width <px> --min-height <px> --max-bytes <N> # 依下載時記錄的尺寸/大小過濾");
+    out!("  cargo run export ... --exclude-media animated,video # 依媒體類型排除（image, animated, video）");
+    out!("  cargo run export ... --license cc-by[,cc0,...]        # 只匯出符合授權的圖片（any = 有標示授權）");
+    out!("                                   # 匯出資料集（每張圖片一列，含關鍵字與 best guess）");
+    out!("  cargo run export --format hf [--split 0.8,0.1,0.1] [--seed N] [--output dir]");
+    out!("                                   # 匯出 Hugging Face imagefolder 目錄（依內容雜湊固定分配 split）");
+    out!("  cargo run labels export [file]   # 匯出標註（標題/關鍵字/標籤，固定格式的 JSON）");
+    out!("  cargo run labels export [file] --format label-studio [--image-prefix /data/local-files/?d=images/]");
+    out!("                                   # 匯出 Label Studio 任務（搜尋結果作為預標註）與標註介面設定");
+    out!("  cargo run labels import <file> [--replace|--merge]");
+    out!("                                   # 匯入標註檔、試算表（CSV）或 Label Studio 匯出，依內容雜湊或檔名合併進 metadata");
+    out!("  cargo run review [--max-groups N] [--tile 200] [--columns 4]");
+    out!("                                   # 把重複組輸出成格狀預覽圖（data/review/），標示檔名與大小");
+    out!("  cargo run classify [preview|apply] [--all] [--model <command>]");
+    out!("                                   # 依尺寸/長寬比/色彩/上下緣文字分類 meme、not_meme、borderline（寫入 extra.meme_class）");
+    out!("                                   # --model 改用外部程式：執行 <command> <圖片路徑>，stdout 輸出 0~1 的分數");
+    out!("  cargo run classify review        # 列出 borderline 的圖片（classify_review.tsv）");
+    out!("  cargo run cluster [preview|apply] [--threshold 0.9] [--model <command>]");
+    out!("                                   # 依 embedding 把同模板不同文字的圖片分群（extra.template_cluster）；--model 為外部 embedding 程式（外掛協定的 embed）");
+    out!("  cargo run thumbnails [--size 256] [--workers N] [--force]");
+    out!("                                   # 產生 WebP 縮圖到 data/thumbnails/（HTML 報告與預覽圖優先使用；已是最新的略過，可中斷後重跑）");
+    out!("  cargo run thumbnails clean       # 刪除對應圖片已不在 metadata 的縮圖");
+    out!("  cargo run normalize [preview|apply] [--format webp|jpeg|png] [--quality 90] [--keep-metadata] [--workers N]");
+    out!("                                   # 移除 EXIF/XMP（有方向標記的先轉正）或統一格式，apply 後更新 hash、大小、尺寸與副檔名");
+    out!("  cargo run ocr [preview|apply] [--all] [--lang chi_tra+eng] [--workers N] [--model <command>]");
+    out!("                                   # 以 tesseract 擷取圖片上的文字存到 metadata 的 ocr_text；--model 改用外部 OCR 程式（<command> <圖片路徑>，stdout 為文字）");
+    out!("  cargo run classify mark <filename> meme|not_meme # 人工標記（重新分類時保留）");
+    out!("  cargo run classify nsfw [preview|apply] --model <command> [--threshold 0.8] [--quarantine] [--all]");
+    out!("                                   # 以本機 NSFW 模型偵測（extra.nsfw）；--quarantine 把 NSFW 圖片移到 quarantine/ 不進資料集");
+    out!("  cargo run classify nsfw list|release <filename> # 列出隔離的圖片 / 誤判時放回資料集");
+    out!("  cargo run search [service]       # 反向圖片搜尋");
+    out!("  cargo run search <plugin>        # 以 plugins.json 登記的外部程式搜尋（stdin/stdout JSONL：search）");
+    out!("  cargo run search [service] --upload # 上傳本地圖片搜尋（原圖已刪除時）");
+    out!("  cargo run search [service] --upload --verify # 上傳前驗證 hash，損毀時加入重新下載佇列");
+    out!("  cargo run search [service] --concurrency 4 # 同時搜尋 4 張（各服務仍依自己的間隔）");
+    out!("  cargo run search [service] --no-cache # 不沿用相同內容先前的搜尋結果（search_cache/）");
+    out!("  cargo run search [service] --no-shared-cache # 不使用各 profile 共用的快取（~/.meme-crawler/search_cache/）");
+    out!("  cargo run search [service] --flush-interval 5 --fsync never|checkpoint|always");
+    out!("                                   # 搜尋結果緩衝寫入的間隔（秒）與 fsync 時機（預設 1 秒、儲存進度前）");
+    out!("  cargo run search [service] --block-cooldown 900 [--block-webhook <url>]");
+    out!("  cargo run search [service] --keyword-lang zh-TW [--translate <command>] # 關鍵字正規化（全形轉半形、小寫、停用詞、繁簡轉換）；--translate 以外部程式翻譯其他語言的關鍵字（外掛協定的 translate）");
+    out!("  cargo run search [service] --no-keyword-normalize # 保留服務回傳的原始關鍵字");
+    out!("  cargo run search [service] --redo-empty # 只重新搜尋結果是空的或失敗的圖片，新結果取代結果檔中的舊結果");
+    out!("  cargo run search --redo-service bing # 同上，只重新搜尋指定服務的結果");
+    out!("                                   # 遇到驗證碼時暫停該服務的秒數，並 POST JSON 通知");
+    out!("  cargo run pipeline [service] [--remove-duplicates [--max-delete N] [--trash]] [--no-search]");
+    out!("                                   # 爬取 → 去重 → 反向搜尋一次執行，中斷後從未完成的階段繼續");
+    out!("  cargo run redownload             # 重新下載佇列中損毀的圖片");
+    out!("  cargo run search local <file> [--max-distance 10] [--limit 5] # 不連網，在本地語料庫找同一張梗圖與它的標題/關鍵字");
+    out!("  cargo run search-stats           # 顯示搜尋統計（含各服務 p50/p95 回應時間）");
+    out!("  cargo run merge-results [--output <path>] [--keyword-lang zh-TW] [--translate <command>] # 每張圖片合併成一筆（關鍵字依跨服務一致度排序、網站依網域去重，多服務搜尋後自動執行）");
+    out!("  cargo run tags [list]            # 各標籤圖片數");
+    out!("  cargo run tags show <tag>        # 列出標籤下的圖片");
+    out!("  cargo run tags cooccurrence [N]  # 最常一起出現的標籤組合");
+    out!("  cargo run profile [list|create|delete] <name> # 管理 profile");
+    out!("  cargo run -- --profile <name> <command>       # 在 profile 中執行命令");
+    out!("  cargo run -- --plain <command>   # 只輸出 ASCII（emoji 與框線換成 [OK]/[!]/+=|，舊版 Windows 主控台自動啟用）");
+    out!("  cargo run store import           # 將 JSONL 資料匯入 SQLite (metadata.db)");
+    out!("  cargo run prune --where <條件> [apply] # 依條件移除圖片到回收桶（dedup restore 可還原），例如 \"page_number>1500 || nsfw_score>0.8\"");
+    out!("  cargo run prune --where <條件> apply --max-delete <N> [--yes]");
+    out!("  cargo run rename [preview|apply] # 依實際內容修正副檔名並套用檔名樣板（同 fix-extensions）");
+    out!("  cargo run gc [preview|apply]       # 依 gc.json 的保留規則清理暫存檔、舊備份、除錯 HTML 與舊 log");
+    out!("  cargo run gc --auto on|off         # 啟動時是否自動清理（預設開啟）");
+    out!("  cargo run tier [preview|apply]     # 依 tier.json 把下載超過 N 天的原圖移到冷儲存，本地留縮圖");
+    out!("  cargo run tier apply --days 30 --exported ./hf_dataset # 指定天數，並移動已匯出的原圖");
+    out!("  cargo run tier fetch <檔名>...     # 從冷儲存取回原圖（search --upload 會自動取回）");
+    out!("  cargo run verify [preview|apply]   # 重新計算圖片 hash，回報（apply 時刪除）內容損毀的項目");
+    out!("  cargo run reconcile [preview|apply] # 補上舊 metadata 缺少的檔案大小/尺寸/下載時間，回報不一致");
+    out!("  cargo run stats                  # 目前的圖片數、不重複雜湊、標註比例與圖片大小");
+    out!("  cargo run stats growth [--csv <path>] # 每天的資料集成長（--csv 匯出給畫圖用）");
+    out!("  cargo run history [--limit 20] [--user <名稱>] [--command <命令>] [--failed] # 最近的操作紀錄（誰在何時執行了什麼）");
+    out!("  cargo run -- --filename-pattern \"{{site}}_p{{page}}_{{hash8}}.{{ext}}\" <command>");
+    out!("                                   # 設定檔名樣板（{{hash}} {{hash8}} {{title}} {{title_n}} {{ext}} {{page}} {{date}} {{site}} {{template_id}}）");
+    out!("  cargo run -- --filename-pattern \"{{title_n}}.{{ext}}\" <command>");
+    out!("                                   # 以標題命名，同名的不同圖片依序為「標題 (2)」「標題 (3)」（編號記在 metadata）");
+    out!("  cargo run -- --store sqlite <command>         # 使用 SQLite metadata 後端");
+    out!("  cargo run -- --events nats://127.0.0.1:4222 <command> # 發佈新圖片/搜尋結果事件");
+    out!("  cargo run -- --events kafka+http://127.0.0.1:8082 <command> # 經 Kafka REST Proxy 發佈");
+    out!("  cargo run -- --events-prefix <prefix> ...     # subject/topic 前綴（預設 memes）");
+    out!("  cargo run -- --proxy <url> <command>          # 透過代理爬取與搜尋");
+    out!("  cargo run -- --proxy-list <file> [--proxy-rotation round-robin|random|sticky] [--proxy-cooldown <秒>] <command>");
+    out!("                                   # 代理清單輪替，被封鎖 (403/429) 的代理暫停使用");
+    out!("  cargo run --help                 # 顯示此幫助\n");
+    out!("反向搜尋服務:");
+    out!("  tineye   - TinEye 反向搜尋 (預設)");
+    out!("  bing     - Bing 反向搜尋");
+    out!("  lens     - Google 智慧鏡頭");
+    out!("  bing-api - Bing Visual Search 官方 API（需要 key）");
+    out!("  iqdb     - IQDB 動畫/插畫圖庫搜尋（Danbooru、Gelbooru 等）");
+    out!("  tracemoe - trace.moe 動畫截圖來源（作品、集數、時間）");
+    out!("  anime    - iqdb + tracemoe");
+    out!("  all      - 使用所有服務（bing-api 在有 key 時才加入）\n");
+    out!("範例:");
+    out!("  cargo run search tineye          # 只用 TinEye");
+    out!("  cargo run search bing            # 只用 Bing");
+    out!("  cargo run search lens            # 只用 Google 智慧鏡頭（自動處理同意頁）");
+    out!("  cargo run search bing-api        # Bing Visual Search 官方 API（key 放 BING_VISUAL_SEARCH_KEY 或 bing_visual.json）");
+    out!("  cargo run search anime --upload  # 動畫截圖：上傳到 IQDB 與 trace.moe，記錄相似度與集數");
+    out!("  cargo run search all             # 全部都用\n");
+    out!("資料檔案（使用 --profile 時位於 ~/.meme-crawler/profiles/<name>/）:");
+    out!("  ./data/images/                      # 圖片");
+    out!("  ./data/metadata.jsonl               # 圖片 metadata");
+    out!("  ./data/progress.json                # 爬蟲進度");
+    out!("  ./data/watch_state.json             # crawl --watch 看過的圖片網址");
+    out!("  ./data/reddit_progress.json         # Reddit 各版的翻頁進度");
+    out!("  ./data/kym_progress.json            # KnowYourMeme 列表進度");
+    out!("  ./data/feeds.txt                    # feeds 命令預設讀取的 RSS/Atom 網址");
+    out!("  ./data/feed_progress.json           # 各 feed 已處理的文章");
+    out!("  ./data/url_list_progress.json       # crawl --from-urls 各清單已完成/失敗的網址");
+    out!("  ./data/detail_pages.jsonl           # 已解析的詳細頁（原圖網址、標籤、欄位），重爬時不再抓取");
+    out!("  ./data/run_report.json              # 最近一次爬取報告");
+    out!("  ./data/dataset_manifest.json        # 資料集清單（可重現設定與內容摘要）");
+    out!("  ./data/site_changes.jsonl           # diff-crawl 發現的網站變動");
+    out!("  ./data/duplicates.json              # 重複圖片");
+    out!("  ./data/dedup_index.db               # 去重用的雜湊索引（只補讀 metadata.jsonl 新增的行，可刪除重建）");
+    out!("  ./data/dedup_report.html            # 重複組的縮圖、雜湊、解析度與保留的檔案（dedup 時產生，用瀏覽器開啟）");
+    out!("  ./data/trash/<時間>/                # dedup remove --trash、prune、verify apply 移除的圖片與 manifest.json（dedup restore 還原）");
+    out!("  ./data/review/                      # 重複組預覽圖與 index.tsv");
+    out!("  ./data/classify_review.tsv          # classify 待人工確認的 borderline 圖片");
+    out!("  ./data/embeddings.jsonl             # cluster 計算過的 embedding（依模型與內容雜湊快取）");
+    out!("  ./data/quarantine/                  # 判定為 NSFW 而隔離的圖片與它們的 metadata.jsonl（不在資料集內）");
+    out!("  ./data/search_progress.json         # 搜尋進度");
+    out!("  ./data/search_cache/                # 依內容雜湊快取的搜尋結果（<service>/<hash>.json）");
+    out!("  ~/.meme-crawler/search_cache/       # 各 profile 共用的搜尋快取（.claim 為搜尋中的認領）");
+    out!("  ./data/reverse_search_results.jsonl # 搜尋結果");
+    out!("  ./data/reverse_search_merged.jsonl  # 各服務合併後的搜尋結果（每張圖片一筆）");
+    out!("  ./data/phash_index.json             # search local 快取的各圖片感知雜湊");
+    out!("  ./data/service_latency.jsonl        # 各服務每次呼叫的耗時（search-stats 顯示 p50/p95）");
+    out!("  ./data/metadata_enriched.jsonl      # enrich 合併搜尋結果後的 metadata");
+    out!("  ./data/dataset.csv                  # export 匯出的資料集（或 dataset.parquet）");
+    out!("  ./data/labels.json                  # labels export 預設輸出的標註檔");
+    out!("  ./data/label_studio_tasks.json      # labels export --format label-studio 的任務（.xml 為標註介面）");
+    out!("  ./data/hf_dataset/                  # export --format hf 的 imagefolder 目錄");
+    out!("  ./data/ATTRIBUTION                  # export 寫在資料集旁的來源標示（attribution.jsonl 為每張圖片的記錄）");
+    out!("  ./data/pipeline_state.json          # pipeline 已完成的階段");
+    out!("  ./data/redownload_queue.jsonl       # 待重新下載的損毀圖片");
+    out!("  ./data/filename_pattern.txt         # 檔名樣板（--filename-pattern）");
+    out!("  ./data/headers.json                 # User-Agent 輪替清單與各網站的 headers（Referer、Accept-Language、Cookie，值可用 {{origin}} {{url}}）");
+    out!("  ./data/rate_limits.json             # 各網站與搜尋服務學到的請求間隔");
+    out!("  ./data/bench_report.json            # bench 各組設定的量測結果與建議設定");
+    out!("  ./data/plugins.json                 # 外部 Parser 與搜尋服務外掛（name、command、url_template / supports_upload、delay_ms）");
+    out!("  ./data/bing_visual.json             # Bing Visual Search API 設定（api_key、endpoint、market；key 也可放 BING_VISUAL_SEARCH_KEY）");
+    out!("  ./data/keyword_filter.json          # 搜尋關鍵字的過濾規則（正規表示式、外部清單檔、各語言最小長度、保留詞、相關網站的網域黑白名單）");
+    out!("  ./data/failed_downloads.jsonl       # 重試後仍下載失敗的圖片（crawl --retry-downloads 再試）");
+    out!("  ./data/rename_journal.json          # 進行中的改名交易（中斷時下次啟動自動完成）");
+    out!("  ./data/gc.json                      # 清理的保留規則與自動清理開關");
+    out!("  ./data/tier.json                    # 原圖分層規則（冷儲存目錄、天數、縮圖大小）");
+    out!("  ./data/cold_index.json              # 已移到冷儲存的原圖");
+    out!("  ./data/thumbnails/                  # 縮圖（thumbnails 產生，原圖移到冷儲存時也會留下）");
+    out!("  ./data/metrics_history.jsonl        # 每天的資料集規模（改變資料集的命令完成後記錄，stats growth 顯示）");
+    out!("  ./data/history.jsonl                # 每個命令的操作紀錄（參數、耗時、結果與數量，history 顯示）");
+    out!("  ./data/prune_log.jsonl              # prune 刪除紀錄");
+    out!("  ./data/impact_<dedup|prune>.json    # 刪除前計算的影響摘要（檔案數、釋放空間、受影響的紀錄）");
+    out!("  ./data/metadata.db                  # SQLite 後端（--store sqlite）");
 }
\ No newline at end of file
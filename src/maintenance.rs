@@ -0,0 +1,407 @@
+use crate::context::{files, DataContext};
+use crate::crawler::downloader::detect_extension;
+use crate::crawler::naming::{FilenameFields, FilenameTemplate, TitleNumbers};
+use crate::file_manager::{FileManager, RenameEntry, RenameJournal};
+use crate::media;
+use crate::reverse_search::{self, types::SearchProgress, ReverseSearchResult};
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// 單一檔案的改名計畫
+#[derive(Debug, Clone)]
+pub struct RenamePlan {
+    pub from: String,
+    pub to: String,
+}
+
+/// 找出檔名與樣板不符的圖片
+///
+/// 包含副檔名與實際內容不符（例如 `jpg?w=600` 或 PNG 存成 .jpg），
+/// 以及更換檔名樣板後需要改名的檔案。
+pub fn plan_extension_fixes(
+    file_manager: &FileManager,
+    metadata_list: &[ImageMetadata],
+    template: &FilenameTemplate,
+) -> Result<Vec<RenamePlan>> {
+    let mut plans: Vec<RenamePlan> = Vec::new();
+    let mut planned: HashSet<&str> = HashSet::new();
+    let mut title_numbers = template.numbers_titles().then(|| TitleNumbers::from_metadata(metadata_list));
+
+    for metadata in metadata_list {
+        if planned.contains(metadata.filename.as_str()) {
+            continue;
+        }
+
+        let path = file_manager.get_image_path(&metadata.filename);
+        if !Path::new(&path).exists() {
+            continue;
+        }
+
+        // 只需要檔頭判斷格式
+        let mut head = [0u8; 16];
+        let n = fs::File::open(&path)
+            .and_then(|mut f| f.read(&mut head))
+            .with_context(|| format!("無法讀取 {}", metadata.filename))?;
+
+        let ext = detect_extension(None, &head[..n], &metadata.url);
+        let title_number = match &mut title_numbers {
+            Some(numbers) => numbers.assign(&metadata.description, &metadata.content_hash),
+            None => 1,
+        };
+        let expected = template.render(&FilenameFields {
+            hash: &metadata.content_hash,
+            title: &metadata.description,
+            title_number,
+            ext,
+            page: metadata.page_number,
+            downloaded_at: metadata.downloaded_at,
+            url: &metadata.url,
+        });
+
+        if expected != metadata.filename {
+            planned.insert(&metadata.filename);
+            plans.push(RenamePlan {
+                from: metadata.filename.clone(),
+                to: expected,
+            });
+        }
+    }
+
+    Ok(plans)
+}
+
+/// 執行改名，並同步更新 metadata、搜尋結果與搜尋進度中的檔名
+///
+/// 目標檔案已存在（且不是其他計畫的來源）時跳過（避免覆蓋），回傳實際改名的數量。
+/// 透過 `FileManager` 的改名交易執行，中途 crash 時由 `recover_renames` 完成。
+pub fn apply_renames(context: &DataContext, plans: &[RenamePlan]) -> Result<usize> {
+    let file_manager = context.file_manager();
+    let sources: HashSet<&str> = plans.iter().map(|plan| plan.from.as_str()).collect();
+    let entries: Vec<RenameEntry> = plans
+        .iter()
+        .filter(|plan| {
+            let exists = Path::new(&file_manager.get_image_path(&plan.to)).exists() && !sources.contains(plan.to.as_str());
+            if exists {
+                eout!("  ⚠️  目標已存在，跳過: {} -> {}", plan.from, plan.to);
+            }
+            !exists
+        })
+        .map(|plan| RenameEntry {
+            from: plan.from.clone(),
+            to: plan.to.clone(),
+        })
+        .collect();
+
+    if entries.is_empty() {
+        return Ok(0);
+    }
+
+    let journal = file_manager.begin_renames(&entries)?;
+    finish_renames(context, &journal)
+}
+
+/// 完成上次中斷的改名交易（沒有時回傳 0）
+pub fn recover_renames(context: &DataContext) -> Result<usize> {
+    match context.file_manager().pending_renames()? {
+        Some(journal) => finish_renames(context, &journal),
+        None => Ok(0),
+    }
+}
+
+/// 改名檔案、更新紀錄檔，最後完成交易（每一步都可重複執行）
+fn finish_renames(context: &DataContext, journal: &RenameJournal) -> Result<usize> {
+    let renamed: HashMap<String, String> = context
+        .file_manager()
+        .apply_journaled_renames(journal)?
+        .into_iter()
+        .map(|entry| (entry.from, entry.to))
+        .collect();
+
+    if !renamed.is_empty() {
+        update_renamed_records(context, &renamed)?;
+    }
+
+    context.file_manager().commit_renames()?;
+    Ok(renamed.len())
+}
+
+/// 將 metadata、搜尋結果與搜尋進度中的舊檔名換成新檔名（依 metadata 後端）
+fn update_renamed_records(context: &DataContext, renamed: &HashMap<String, String>) -> Result<()> {
+    // 同一次交易可能重做，讀最新的內容
+    context.invalidate();
+
+    // metadata
+    let mut metadata_list = context.metadata()?.as_ref().clone();
+    if metadata_list.iter().any(|m| renamed.contains_key(&m.filename)) {
+        for metadata in &mut metadata_list {
+            if let Some(to) = renamed.get(&metadata.filename) {
+                metadata.filename = to.clone();
+            }
+        }
+        context.rewrite_metadata(metadata_list)?;
+    }
+
+    // 搜尋結果（結果檔，SQLite 後端時也包含資料庫）
+    let results_file = context.path(files::SEARCH_RESULTS);
+    if let Some(results) = rename_results(reverse_search::load_all_results(&results_file)?, renamed) {
+        reverse_search::rewrite_all_results(&results_file, &results)?;
+    }
+    if let Some(sqlite) = context.sqlite()
+        && let Some(results) = rename_results(sqlite.load_search_results()?, renamed)
+    {
+        sqlite.rewrite_search_results(&results)?;
+    }
+    context.invalidate();
+
+    // 搜尋進度
+    let progress_file = context.path(files::SEARCH_PROGRESS);
+    if Path::new(&progress_file).exists() {
+        let mut progress: SearchProgress = serde_json::from_str(&fs::read_to_string(&progress_file)?)
+            .context("無法解析 search_progress.json")?;
+
+        progress.completed_files = progress
+            .completed_files
+            .into_iter()
+            .map(|f| renamed.get(&f).cloned().unwrap_or(f))
+            .collect();
+        progress.completed = progress
+            .completed
+            .into_iter()
+            .map(|(f, services)| (renamed.get(&f).cloned().unwrap_or(f), services))
+            .collect();
+
+        let temp_path = format!("{}.tmp", progress_file);
+        fs::write(&temp_path, serde_json::to_string_pretty(&progress)?)?;
+        fs::rename(&temp_path, &progress_file)?;
+    }
+
+    Ok(())
+}
+
+/// 換掉搜尋結果中改名的檔名（沒有要換的時回傳 None）
+fn rename_results(mut results: Vec<ReverseSearchResult>, renamed: &HashMap<String, String>) -> Option<Vec<ReverseSearchResult>> {
+    if !results.iter().any(|r| renamed.contains_key(&r.filename)) {
+        return None;
+    }
+    for result in &mut results {
+        if let Some(to) = renamed.get(&result.filename) {
+            result.filename = to.clone();
+        }
+    }
+    Some(results)
+}
+
+/// metadata 與實際檔案不一致的地方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Discrepancy {
+    /// metadata 有記錄，但檔案不存在
+    MissingFile(String),
+    /// images/ 中沒有 metadata 的檔案
+    Orphan(String),
+    /// 記錄的檔案大小與實際不同
+    SizeMismatch { filename: String, recorded: u64, actual: u64 },
+    /// 記錄的尺寸與實際不同
+    DimensionMismatch { filename: String, recorded: (u32, u32), actual: (u32, u32) },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingFile(filename) => write!(f, "檔案不存在: {}", filename),
+            Self::Orphan(filename) => write!(f, "沒有 metadata: {}", filename),
+            Self::SizeMismatch { filename, recorded, actual } => {
+                write!(f, "大小不符: {}（記錄 {} bytes，實際 {} bytes）", filename, recorded, actual)
+            }
+            Self::DimensionMismatch { filename, recorded, actual } => write!(
+                f,
+                "尺寸不符: {}（記錄 {}x{}，實際 {}x{}）",
+                filename, recorded.0, recorded.1, actual.0, actual.1
+            ),
+        }
+    }
+}
+
+/// 比對結果
+#[derive(Debug, Default)]
+pub struct ReconcileReport {
+    /// 補上檔案大小的筆數
+    pub filled_size: usize,
+    /// 補上尺寸的筆數
+    pub filled_dimensions: usize,
+    /// 以檔案修改時間補上下載時間的筆數
+    pub filled_downloaded_at: usize,
+    /// 補上媒體類型的筆數
+    pub filled_media_type: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl ReconcileReport {
+    /// metadata 是否有欄位被補上
+    pub fn changed(&self) -> bool {
+        self.filled_size + self.filled_dimensions + self.filled_downloaded_at + self.filled_media_type > 0
+    }
+}
+
+/// 對照 images/ 的實際檔案，補上舊 metadata 缺少的檔案大小、尺寸、媒體類型與下載時間
+///
+/// 只補空白的欄位；已經有值但與實際不同的只回報，不覆寫。
+pub fn reconcile_metadata(
+    file_manager: &FileManager,
+    metadata_list: &mut [ImageMetadata],
+) -> Result<ReconcileReport> {
+    let mut report = ReconcileReport::default();
+    let mut known = HashSet::new();
+
+    for metadata in metadata_list.iter_mut() {
+        known.insert(metadata.filename.clone());
+
+        let path = file_manager.get_image_path(&metadata.filename);
+        let Ok(file_info) = fs::metadata(&path) else {
+            report.discrepancies.push(Discrepancy::MissingFile(metadata.filename.clone()));
+            continue;
+        };
+
+        let actual_size = file_info.len();
+        match metadata.file_size {
+            None => {
+                metadata.file_size = Some(actual_size);
+                report.filled_size += 1;
+            }
+            Some(recorded) if recorded != actual_size => {
+                report.discrepancies.push(Discrepancy::SizeMismatch {
+                    filename: metadata.filename.clone(),
+                    recorded,
+                    actual: actual_size,
+                });
+            }
+            Some(_) => {}
+        }
+
+        // 只讀檔頭取得尺寸（無法辨識的格式略過）
+        if let Some(actual) = media::probe_file_dimensions(&path) {
+            match (metadata.width, metadata.height) {
+                (Some(width), Some(height)) if (width, height) != actual => {
+                    report.discrepancies.push(Discrepancy::DimensionMismatch {
+                        filename: metadata.filename.clone(),
+                        recorded: (width, height),
+                        actual,
+                    });
+                }
+                (Some(_), Some(_)) => {}
+                _ => {
+                    metadata.width = Some(actual.0);
+                    metadata.height = Some(actual.1);
+                    report.filled_dimensions += 1;
+                }
+            }
+        }
+
+        if metadata.media_type.is_none() {
+            if let Some(media_type) = media::probe_file_media_type(&path) {
+                metadata.media_type = Some(media_type);
+                report.filled_media_type += 1;
+            }
+        }
+
+        if metadata.downloaded_at == DateTime::<Utc>::default() {
+            if let Ok(modified) = file_info.modified() {
+                metadata.downloaded_at = modified.into();
+                report.filled_downloaded_at += 1;
+            }
+        }
+    }
+
+    let images_dir = format!("{}/images", file_manager.root_dir());
+    let mut orphans = Vec::new();
+    for entry in fs::read_dir(&images_dir).context("無法讀取 images 目錄")? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_file() && !name.starts_with('.') && !known.contains(&name) {
+            orphans.push(name);
+        }
+    }
+    orphans.sort();
+    report.discrepancies.extend(orphans.into_iter().map(Discrepancy::Orphan));
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(filename: &str) -> ImageMetadata {
+        ImageMetadata {
+            url: String::new(),
+            downloaded_at: DateTime::<Utc>::default(),
+            ..ImageMetadata::for_test(filename, "")
+        }
+    }
+
+    #[test]
+    fn test_reconcile_metadata() {
+        let dir = std::env::temp_dir().join(format!("meme-reconcile-{}", std::process::id()));
+        let file_manager = FileManager::new(dir.to_str().unwrap()).unwrap();
+
+        let mut png = Vec::new();
+        image::RgbImage::new(30, 20)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        file_manager.save_image("a.png", &png).unwrap();
+        file_manager.save_image("b.png", &png).unwrap();
+        file_manager.save_image("orphan.png", &png).unwrap();
+
+        let mut b = metadata("b.png");
+        b.width = Some(30);
+        b.height = Some(99);
+        b.file_size = Some(1);
+        b.downloaded_at = Utc::now();
+        let mut list = vec![metadata("a.png"), b, metadata("gone.png")];
+
+        let report = reconcile_metadata(&file_manager, &mut list).unwrap();
+
+        assert_eq!(list[0].file_size, Some(png.len() as u64));
+        assert_eq!((list[0].width, list[0].height), (Some(30), Some(20)));
+        assert!(list[0].downloaded_at > DateTime::<Utc>::default());
+        // 已有的值不覆寫
+        assert_eq!(list[1].height, Some(99));
+        assert_eq!((report.filled_size, report.filled_dimensions, report.filled_downloaded_at), (1, 1, 1));
+        assert_eq!(report.filled_media_type, 2);
+        assert_eq!(list[0].media_type, Some(media::MediaType::Image));
+        assert_eq!(report.discrepancies, [
+            Discrepancy::SizeMismatch { filename: "b.png".to_string(), recorded: 1, actual: png.len() as u64 },
+            Discrepancy::DimensionMismatch { filename: "b.png".to_string(), recorded: (30, 99), actual: (30, 20) },
+            Discrepancy::MissingFile("gone.png".to_string()),
+            Discrepancy::Orphan("orphan.png".to_string()),
+        ]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rename_sqlite_records() {
+        let dir = std::env::temp_dir().join(format!("meme-rename-sqlite-{}", std::process::id()));
+        let context = DataContext::open(dir.to_str().unwrap(), crate::store::MetadataBackend::Sqlite).unwrap();
+        context.file_manager().save_image("a.jpg", b"a").unwrap();
+        context.store().append_metadata(&metadata("a.jpg")).unwrap();
+        let result: ReverseSearchResult = serde_json::from_value(serde_json::json!({
+            "filename": "a.jpg", "service": "bing", "keywords": ["doge"], "related_sites": [],
+            "searched_at": "2024-01-01T00:00:00Z",
+        })).unwrap();
+        context.store().append_search_result(&result).unwrap();
+
+        let plans = [RenamePlan { from: "a.jpg".to_string(), to: "a.png".to_string() }];
+        assert_eq!(apply_renames(&context, &plans).unwrap(), 1);
+        assert_eq!(context.metadata().unwrap()[0].filename, "a.png");
+        assert_eq!(context.search_results().unwrap()[0].filename, "a.png");
+        assert!(context.file_manager().pending_renames().unwrap().is_none());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,371 @@
+use anyhow::{Context, Result};
+use image::{AnimationDecoder, DynamicImage};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Command;
+
+/// 擷取影片畫面用的程式
+pub const FFMPEG: &str = "ffmpeg";
+
+/// 動圖最多解碼的影格數（選代表畫面用）
+const MAX_FRAMES: usize = 200;
+
+/// 依檔案內容（magic bytes）判斷的圖片格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaFormat {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Avif,
+    /// HEIC/HEIF（手機拍照的格式，無法解碼，只保留原檔）
+    Heic,
+    Bmp,
+    Ico,
+    Mp4,
+    Webm,
+    /// 無法辨識（HTML 錯誤頁、SVG、截斷的檔案...）
+    Unknown,
+}
+
+impl MediaFormat {
+    /// 存檔用的副檔名（無法辨識時為 None）
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::Jpeg => Some("jpg"),
+            Self::Png => Some("png"),
+            Self::Gif => Some("gif"),
+            Self::Webp => Some("webp"),
+            Self::Avif => Some("avif"),
+            Self::Heic => Some("heic"),
+            Self::Bmp => Some("bmp"),
+            Self::Ico => Some("ico"),
+            Self::Mp4 => Some("mp4"),
+            Self::Webm => Some("webm"),
+            Self::Unknown => None,
+        }
+    }
+
+    pub fn mime_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Jpeg => Some("image/jpeg"),
+            Self::Png => Some("image/png"),
+            Self::Gif => Some("image/gif"),
+            Self::Webp => Some("image/webp"),
+            Self::Avif => Some("image/avif"),
+            Self::Heic => Some("image/heic"),
+            Self::Bmp => Some("image/bmp"),
+            Self::Ico => Some("image/x-icon"),
+            Self::Mp4 => Some("video/mp4"),
+            Self::Webm => Some("video/webm"),
+            Self::Unknown => None,
+        }
+    }
+
+    pub fn is_known(&self) -> bool {
+        *self != Self::Unknown
+    }
+
+    pub fn is_video(&self) -> bool {
+        matches!(self, Self::Mp4 | Self::Webm)
+    }
+}
+
+/// 媒體類型（寫入 metadata 的 `media_type`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    /// 靜態圖片
+    Image,
+    /// 多影格的 GIF、WebP 或 APNG
+    Animated,
+    /// mp4、webm
+    Video,
+}
+
+impl MediaType {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "image" => Ok(Self::Image),
+            "animated" => Ok(Self::Animated),
+            "video" => Ok(Self::Video),
+            other => anyhow::bail!("未知的媒體類型: {}（可用: image, animated, video）", other),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Animated => "animated",
+            Self::Video => "video",
+        }
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl fmt::Display for MediaFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension().unwrap_or("unknown"))
+    }
+}
+
+/// 從檔案開頭的 magic bytes 判斷格式（只需要前 16 bytes）
+pub fn detect_format(bytes: &[u8]) -> MediaFormat {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        MediaFormat::Jpeg
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        MediaFormat::Png
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        MediaFormat::Gif
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        MediaFormat::Webp
+    } else if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        // ISO BMFF：依主要品牌區分，不認得的品牌不當作影片
+        match &bytes[8..12] {
+            b"avif" | b"avis" => MediaFormat::Avif,
+            b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1" | b"msf1" => MediaFormat::Heic,
+            b"isom" | b"iso2" | b"iso4" | b"iso5" | b"iso6" | b"mp41" | b"mp42" | b"avc1" | b"M4V " | b"qt  "
+            | b"3gp4" | b"3gp5" | b"3g2a" | b"dash" | b"mmp4" | b"f4v " => MediaFormat::Mp4,
+            _ => MediaFormat::Unknown,
+        }
+    } else if bytes.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        // EBML 標頭（webm 與 mkv 相同）
+        MediaFormat::Webm
+    } else if bytes.starts_with(b"BM") {
+        MediaFormat::Bmp
+    } else if bytes.starts_with(&[0x00, 0x00, 0x01, 0x00]) {
+        MediaFormat::Ico
+    } else {
+        MediaFormat::Unknown
+    }
+}
+
+/// 只讀檔案開頭判斷格式（無法讀取時為 Unknown）
+pub fn probe_file_format(path: impl AsRef<Path>) -> MediaFormat {
+    let mut head = [0u8; 16];
+    let Ok(mut file) = std::fs::File::open(path) else {
+        return MediaFormat::Unknown;
+    };
+    let read = std::io::Read::read(&mut file, &mut head).unwrap_or(0);
+    detect_format(&head[..read])
+}
+
+/// 判斷媒體類型（GIF 與 APNG 需要完整內容才能數影格）
+pub fn detect_media_type(bytes: &[u8]) -> MediaType {
+    let format = detect_format(bytes);
+    if format.is_video() {
+        MediaType::Video
+    } else if is_animated(bytes, format) {
+        MediaType::Animated
+    } else {
+        MediaType::Image
+    }
+}
+
+/// 讀取檔案判斷媒體類型（無法讀取時為 None）
+pub fn probe_file_media_type(path: impl AsRef<Path>) -> Option<MediaType> {
+    std::fs::read(path).ok().map(|bytes| detect_media_type(&bytes))
+}
+
+/// 多影格的 GIF、WebP 或 APNG
+pub fn is_animated(bytes: &[u8], format: MediaFormat) -> bool {
+    match format {
+        MediaFormat::Gif => image::codecs::gif::GifDecoder::new(Cursor::new(bytes))
+            .map(|decoder| decoder.into_frames().take(2).count() > 1)
+            .unwrap_or(false),
+        // VP8X 的 flags 第 2 個 bit 是動畫
+        MediaFormat::Webp => bytes.len() > 20 && &bytes[12..16] == b"VP8X" && bytes[20] & 0x02 != 0,
+        // acTL 區塊在第一個 IDAT 之前
+        MediaFormat::Png => {
+            let mut pos = 8;
+            while pos + 8 <= bytes.len() {
+                let length = u32::from_be_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]]) as usize;
+                match &bytes[pos + 4..pos + 8] {
+                    b"acTL" => return true,
+                    b"IDAT" => return false,
+                    _ => pos += 12 + length,
+                }
+            }
+            false
+        }
+        _ => false,
+    }
+}
+
+/// 去重與搜尋用的代表畫面：靜態圖片本身、動圖的中間影格、影片以 ffmpeg 選出的畫面
+pub fn representative_frame(path: impl AsRef<Path>) -> Result<DynamicImage> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).with_context(|| format!("無法讀取 {}", path.display()))?;
+    let format = detect_format(&bytes);
+
+    if format.is_video() {
+        return video_frame(path);
+    }
+    if is_animated(&bytes, format) {
+        let frames = match format {
+            MediaFormat::Gif => image::codecs::gif::GifDecoder::new(Cursor::new(&bytes))
+                .map(|decoder| decoder.into_frames()),
+            MediaFormat::Webp => image::codecs::webp::WebPDecoder::new(Cursor::new(&bytes))
+                .map(|decoder| decoder.into_frames()),
+            _ => image::codecs::png::PngDecoder::new(Cursor::new(&bytes))
+                .and_then(|decoder| decoder.apng())
+                .map(|decoder| decoder.into_frames()),
+        };
+        if let Ok(frames) = frames {
+            let mut frames: Vec<_> = frames.take(MAX_FRAMES).filter_map(|frame| frame.ok()).collect();
+            if !frames.is_empty() {
+                let frame = frames.swap_remove(frames.len() / 2);
+                return Ok(DynamicImage::ImageRgba8(frame.into_buffer()));
+            }
+        }
+    }
+
+    image::load_from_memory(&bytes).map_err(|e| anyhow::anyhow!("無法解碼 {}: {}", path.display(), e))
+}
+
+/// 以 ffmpeg 的 thumbnail 濾鏡從影片開頭選一張有代表性的畫面
+fn video_frame(path: &Path) -> Result<DynamicImage> {
+    let output = Command::new(FFMPEG)
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args(["-vf", "thumbnail", "-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-"])
+        .output()
+        .with_context(|| format!("無法執行 {}（擷取影片畫面需要安裝 ffmpeg）", FFMPEG))?;
+    if !output.status.success() || output.stdout.is_empty() {
+        anyhow::bail!(
+            "無法擷取 {} 的畫面: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    image::load_from_memory(&output.stdout).context("ffmpeg 輸出的畫面無法解碼")
+}
+
+/// 讀取圖片尺寸（只解析標頭，不解碼整張圖；無法解析時為 None）
+pub fn probe_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// 讀取圖片檔的尺寸（只讀檔頭）
+pub fn probe_file_dimensions(path: impl AsRef<Path>) -> Option<(u32, u32)> {
+    image::ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// 差異雜湊（dHash）：縮成 9x8 灰階後比較左右相鄰像素，縮放/重新壓縮後仍相近
+pub fn dhash(image: &image::DynamicImage) -> u64 {
+    let small = image::imageops::resize(&image.to_luma8(), 9, 8, image::imageops::FilterType::Triangle);
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// 讀取圖片檔並計算 dHash（動圖與影片以代表畫面計算）
+pub fn dhash_file(path: impl AsRef<Path>) -> Result<u64> {
+    Ok(dhash(&representative_frame(path)?))
+}
+
+/// 兩個感知雜湊相差的位元數（0 表示幾乎相同）
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        let mut png = Vec::new();
+        image::RgbImage::new(12, 7)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        assert_eq!(detect_format(&png), MediaFormat::Png);
+        assert_eq!(probe_dimensions(&png), Some((12, 7)));
+
+        assert_eq!(detect_format(b"RIFF\x00\x00\x00\x00WEBPVP8 "), MediaFormat::Webp);
+        assert_eq!(detect_format(b"\x00\x00\x00\x1cftypavif"), MediaFormat::Avif);
+        assert_eq!(detect_format(b"\x00\x00\x00\x18ftypheic"), MediaFormat::Heic);
+        assert_eq!(detect_format(b"\x00\x00\x00\x18ftypmif1").extension(), Some("heic"));
+        assert!(!detect_format(b"\x00\x00\x00\x18ftypheic").is_video());
+        assert_eq!(detect_format(b"\x00\x00\x00\x18ftypcrx "), MediaFormat::Unknown);
+        assert_eq!(detect_format(b"GIF89a...").mime_type(), Some("image/gif"));
+
+        let html = b"<!DOCTYPE html><html>";
+        assert_eq!(detect_format(html), MediaFormat::Unknown);
+        assert!(!detect_format(html).is_known());
+        assert_eq!(probe_dimensions(html), None);
+    }
+
+    #[test]
+    fn test_media_type() {
+        assert_eq!(detect_format(b"\x00\x00\x00\x18ftypmp42"), MediaFormat::Mp4);
+        assert_eq!(detect_format(b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81"), MediaFormat::Webm);
+        assert_eq!(detect_media_type(b"\x00\x00\x00\x18ftypisom"), MediaType::Video);
+        assert_eq!(MediaFormat::Webm.mime_type(), Some("video/webm"));
+
+        // 三個影格的 GIF：動圖，代表畫面是中間的影格
+        let mut gif = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut gif);
+            let frames = [0u8, 128, 255].map(|level| {
+                image::Frame::new(image::RgbaImage::from_pixel(8, 8, image::Rgba([level, level, level, 255])))
+            });
+            encoder.encode_frames(frames).unwrap();
+        }
+        assert_eq!(detect_media_type(&gif), MediaType::Animated);
+
+        let dir = std::env::temp_dir().join(format!("meme-media-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.gif");
+        std::fs::write(&path, &gif).unwrap();
+        assert_eq!(probe_file_media_type(&path), Some(MediaType::Animated));
+        assert_eq!(representative_frame(&path).unwrap().to_rgba8().get_pixel(0, 0)[0], 128);
+
+        let mut png = Vec::new();
+        image::RgbImage::new(4, 4)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        assert_eq!(detect_media_type(&png), MediaType::Image);
+        assert_eq!(MediaType::parse("video").unwrap(), MediaType::Video);
+        assert!(MediaType::parse("audio").is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_dhash() {
+        let gradient = image::RgbImage::from_fn(64, 48, |x, y| image::Rgb([(x * 4) as u8, (y * 5) as u8, 128]));
+        let original = image::DynamicImage::ImageRgb8(gradient);
+        let resized = original.resize_exact(100, 75, image::imageops::FilterType::Nearest);
+        let flipped = original.fliph();
+
+        assert!(hamming_distance(dhash(&original), dhash(&resized)) <= 4);
+        assert!(hamming_distance(dhash(&original), dhash(&flipped)) > 32);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// 資料集成長的時間序列（每天一筆）
+pub const HISTORY_FILE: &str = "metrics_history.jsonl";
+
+/// 某一天結束時的資料集規模
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    /// 日期（UTC）
+    pub date: NaiveDate,
+    pub recorded_at: DateTime<Utc>,
+    /// 最後一個執行完成的命令
+    pub command: String,
+    pub total_images: usize,
+    /// 不重複的內容雜湊數
+    pub unique_hashes: usize,
+    /// 有推測標題或關鍵字的圖片數
+    pub labeled: usize,
+    /// 圖片的總大小（bytes，依 metadata 記錄的 file_size，不掃描目錄）
+    pub disk_usage_bytes: u64,
+}
+
+impl MetricsSnapshot {
+    pub fn compute(metadata: &[ImageMetadata], command: &str, now: DateTime<Utc>) -> Self {
+        let unique_hashes = metadata
+            .iter()
+            .map(|m| m.content_hash.as_str())
+            .filter(|hash| !hash.is_empty())
+            .collect::<HashSet<_>>()
+            .len();
+        let labeled = metadata
+            .iter()
+            .filter(|m| m.suggested_title.is_some() || !m.keywords.is_empty())
+            .count();
+
+        Self {
+            date: now.date_naive(),
+            recorded_at: now,
+            command: command.to_string(),
+            total_images: metadata.len(),
+            unique_hashes,
+            labeled,
+            disk_usage_bytes: metadata.iter().filter_map(|m| m.file_size).sum(),
+        }
+    }
+
+    /// 有標註的比例（0~100）
+    pub fn labeled_pct(&self) -> f64 {
+        if self.total_images == 0 {
+            0.0
+        } else {
+            self.labeled as f64 * 100.0 / self.total_images as f64
+        }
+    }
+}
+
+fn history_path(data_dir: &str) -> String {
+    format!("{}/{}", data_dir, HISTORY_FILE)
+}
+
+/// 讀取所有快照（檔案不存在時為空，無法解析的行略過）
+pub fn load_history(data_dir: &str) -> Result<Vec<MetricsSnapshot>> {
+    let path = history_path(data_dir);
+    if !Path::new(&path).exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(fs::read_to_string(&path)
+        .with_context(|| format!("無法讀取 {}", path))?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// 記錄快照：同一天只保留最後一筆（取代當天較早的記錄）；回傳記錄前的最後一筆
+pub fn record(data_dir: &str, snapshot: &MetricsSnapshot) -> Result<Option<MetricsSnapshot>> {
+    let mut history = load_history(data_dir)?;
+    let previous = history.last().cloned();
+    history.retain(|s| s.date != snapshot.date);
+    history.push(snapshot.clone());
+    history.sort_by_key(|s| s.date);
+
+    let mut content = String::new();
+    for s in &history {
+        content.push_str(&serde_json::to_string(s)?);
+        content.push('\n');
+    }
+
+    let path = history_path(data_dir);
+    let temp_path = format!("{}.tmp", path);
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, &path).with_context(|| format!("無法更新 {}", path))?;
+    Ok(previous)
+}
+
+/// 匯出成 CSV（方便畫圖）
+pub fn write_csv(path: &str, history: &[MetricsSnapshot]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path).with_context(|| format!("無法建立 {}", path))?;
+    writer.write_record(["date", "total_images", "unique_hashes", "labeled", "labeled_pct", "disk_usage_bytes", "command"])?;
+
+    for s in history {
+        writer.write_record([
+            s.date.to_string(),
+            s.total_images.to_string(),
+            s.unique_hashes.to_string(),
+            s.labeled.to_string(),
+            format!("{:.1}", s.labeled_pct()),
+            s.disk_usage_bytes.to_string(),
+            s.command.clone(),
+        ])?;
+    }
+
+    writer.flush().context("無法寫入 CSV 檔")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_history() {
+        let dir = std::env::temp_dir().join(format!("meme-metrics-{}", std::process::id()));
+        let data_dir = dir.to_str().unwrap();
+        fs::create_dir_all(&dir).unwrap();
+
+        let metadata: Vec<ImageMetadata> = [("a.jpg", "h1", true, Some(5)), ("b.jpg", "h1", false, Some(7)), ("c.jpg", "h2", false, None)]
+            .iter()
+            .map(|(filename, hash, labeled, size)| serde_json::from_value(serde_json::json!({
+                "filename": filename, "description": "", "url": "", "content_hash": hash,
+                "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z",
+                "keywords": if *labeled { vec!["doge"] } else { vec![] },
+                "file_size": size,
+            })).unwrap())
+            .collect();
+
+        let day1 = "2024-03-01T08:00:00Z".parse().unwrap();
+        let snapshot = MetricsSnapshot::compute(&metadata, "crawl", day1);
+        assert_eq!((snapshot.total_images, snapshot.unique_hashes, snapshot.labeled), (3, 2, 1));
+        assert_eq!(snapshot.disk_usage_bytes, 12);
+        assert_eq!(record(data_dir, &snapshot).unwrap(), None);
+
+        // 同一天再記錄會取代，隔天則新增一筆；回傳的上一筆用來算新增數
+        let later = MetricsSnapshot::compute(&metadata[..2], "dedup", "2024-03-01T20:00:00Z".parse().unwrap());
+        assert_eq!(record(data_dir, &later).unwrap().map(|s| s.total_images), Some(3));
+        let next_day = MetricsSnapshot::compute(&metadata, "crawl", "2024-03-02T08:00:00Z".parse().unwrap());
+        assert_eq!(record(data_dir, &next_day).unwrap().map(|s| s.total_images), Some(2));
+
+        let history = load_history(data_dir).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!((history[0].command.as_str(), history[0].total_images), ("dedup", 2));
+        assert_eq!(history[1].date.to_string(), "2024-03-02");
+
+        let csv_path = dir.join("growth.csv");
+        write_csv(csv_path.to_str().unwrap(), &history).unwrap();
+        assert_eq!(fs::read_to_string(&csv_path).unwrap().lines().count(), 3);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,507 @@
+use crate::context::DataContext;
+use crate::integrity;
+use crate::media::{self, MediaFormat};
+use crate::terminal;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Rgb, RgbImage};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// JPEG 的預設品質
+pub const DEFAULT_QUALITY: u8 = 90;
+
+/// 統一轉換的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg,
+    /// 無損 WebP（編碼器不支援有損壓縮，`quality` 不適用）
+    Webp,
+    Png,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "webp" => Ok(Self::Webp),
+            "png" => Ok(Self::Png),
+            other => anyhow::bail!("未知的格式: {}（可用: webp, jpeg, png）", other),
+        }
+    }
+
+    fn media_format(&self) -> MediaFormat {
+        match self {
+            Self::Jpeg => MediaFormat::Jpeg,
+            Self::Webp => MediaFormat::Webp,
+            Self::Png => MediaFormat::Png,
+        }
+    }
+
+    fn from_media(format: MediaFormat) -> Option<Self> {
+        match format {
+            MediaFormat::Jpeg => Some(Self::Jpeg),
+            MediaFormat::Webp => Some(Self::Webp),
+            MediaFormat::Png => Some(Self::Png),
+            _ => None,
+        }
+    }
+}
+
+/// 下載後的轉換設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// 移除 EXIF、XMP、IPTC 與文字註解（保留 ICC 色彩設定）
+    pub strip_metadata: bool,
+    /// 統一轉成此格式（None 表示保留原格式；動圖不轉換）
+    pub format: Option<OutputFormat>,
+    /// JPEG 品質（1~100）
+    pub quality: u8,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            strip_metadata: true,
+            format: None,
+            quality: DEFAULT_QUALITY,
+        }
+    }
+}
+
+/// 轉換圖片內容，沒有需要改變時回傳 None
+///
+/// 移除 EXIF 後方向資訊也會消失，所以有旋轉標記的圖片先轉正再以原格式重新編碼；
+/// 其他情況只刪除 metadata 區段，不重新壓縮。影片與無法辨識的格式（SVG...）維持原樣。
+pub fn normalize(bytes: &[u8], options: &NormalizeOptions) -> Result<Option<Vec<u8>>> {
+    let source = media::detect_format(bytes);
+    let animated = media::is_animated(bytes, source);
+
+    if let Some(target) = options.format {
+        let decodable = source.is_known() && !source.is_video() && !matches!(source, MediaFormat::Avif | MediaFormat::Heic);
+        if target.media_format() != source && decodable && !animated {
+            let image = decode_oriented(bytes)?;
+            return encode(&image, target, options.quality).map(Some);
+        }
+    }
+    if !options.strip_metadata {
+        return Ok(None);
+    }
+
+    if let Some(format) = OutputFormat::from_media(source) {
+        if !animated && orientation(bytes) != Orientation::NoTransforms {
+            let image = decode_oriented(bytes)?;
+            return encode(&image, format, options.quality).map(Some);
+        }
+    }
+    Ok(match source {
+        MediaFormat::Jpeg => strip_jpeg(bytes),
+        MediaFormat::Png => strip_png(bytes),
+        MediaFormat::Webp => strip_webp(bytes),
+        _ => None,
+    })
+}
+
+/// EXIF 記錄的方向（沒有或無法解析時為不旋轉）
+fn orientation(bytes: &[u8]) -> Orientation {
+    ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_decoder().ok())
+        .and_then(|mut decoder| decoder.orientation().ok())
+        .unwrap_or(Orientation::NoTransforms)
+}
+
+/// 解碼並依 EXIF 方向轉正
+fn decode_oriented(bytes: &[u8]) -> Result<DynamicImage> {
+    let mut decoder = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_decoder()
+        .context("無法解碼圖片")?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder).context("無法解碼圖片")?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn encode(image: &DynamicImage, format: OutputFormat, quality: u8) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match format {
+        OutputFormat::Jpeg => {
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality.clamp(1, 100));
+            flatten(image).write_with_encoder(encoder).context("無法編碼 JPEG")?;
+        }
+        OutputFormat::Webp | OutputFormat::Png => {
+            // WebP 編碼器只接受 8 位元的 RGB/RGBA
+            let image = if image.color().has_alpha() {
+                DynamicImage::ImageRgba8(image.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+            let format = if format == OutputFormat::Webp { ImageFormat::WebP } else { ImageFormat::Png };
+            image.write_to(&mut Cursor::new(&mut bytes), format).context("無法編碼圖片")?;
+        }
+    }
+    Ok(bytes)
+}
+
+/// JPEG 沒有透明度：透明的部分合成到白色背景
+fn flatten(image: &DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let pixel = rgba.get_pixel(x, y);
+        let alpha = pixel[3] as u16;
+        Rgb([0, 1, 2].map(|i| ((pixel[i] as u16 * alpha + 255 * (255 - alpha)) / 255) as u8))
+    })
+}
+
+/// 移除 JPEG 的 EXIF/XMP（APP1）、IPTC（APP13）與註解（COM）區段
+fn strip_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut output = bytes[..2].to_vec();
+    let mut pos = 2;
+    let mut removed = false;
+
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        if marker == 0xFF {
+            // 填充用的 0xFF
+            pos += 1;
+            continue;
+        }
+        // 影像資料開始（SOS）之後都保留
+        if marker == 0xDA {
+            break;
+        }
+
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let end = (pos + 2 + length).min(bytes.len());
+        let data = &bytes[(pos + 4).min(end)..end];
+        let private = match marker {
+            0xE1 => data.starts_with(b"Exif\0") || data.starts_with(b"http://ns.adobe.com/"),
+            0xED | 0xFE => true,
+            _ => false,
+        };
+        if private {
+            removed = true;
+        } else {
+            output.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+
+    removed.then(|| {
+        output.extend_from_slice(&bytes[pos..]);
+        output
+    })
+}
+
+/// 移除 PNG 的 eXIf、文字（tEXt/zTXt/iTXt）與時間（tIME）區塊
+fn strip_png(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut output = bytes[..8].to_vec();
+    let mut pos = 8;
+    let mut removed = false;
+
+    while pos + 12 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[pos..pos + 4].try_into().ok()?) as usize;
+        let end = (pos + 12 + length).min(bytes.len());
+        if matches!(&bytes[pos + 4..pos + 8], b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            removed = true;
+        } else {
+            output.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+
+    removed.then(|| {
+        output.extend_from_slice(&bytes[pos..]);
+        output
+    })
+}
+
+/// 移除 WebP 的 EXIF 與 XMP 區塊（同時清掉 VP8X 的對應 flag 並更新 RIFF 大小）
+fn strip_webp(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut output = bytes[..12].to_vec();
+    let mut pos = 12;
+    let mut removed = false;
+
+    while pos + 8 <= bytes.len() {
+        let fourcc = &bytes[pos..pos + 4];
+        let length = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let end = (pos + 8 + length + length % 2).min(bytes.len());
+        if matches!(fourcc, b"EXIF" | b"XMP ") {
+            removed = true;
+        } else {
+            let start = output.len();
+            output.extend_from_slice(&bytes[pos..end]);
+            if fourcc == b"VP8X" && output.len() > start + 8 {
+                output[start + 8] &= !(0x04 | 0x08);
+            }
+        }
+        pos = end;
+    }
+
+    if !removed {
+        return None;
+    }
+    let riff_size = (output.len() - 8) as u32;
+    output[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(output)
+}
+
+/// 轉換後的內容摘要（不保留內容本身）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalized {
+    pub content_hash: String,
+    pub file_size: u64,
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl Normalized {
+    pub fn of(bytes: &[u8]) -> Self {
+        Self {
+            content_hash: integrity::sha256_hex(bytes),
+            file_size: bytes.len() as u64,
+            dimensions: media::probe_dimensions(bytes),
+        }
+    }
+}
+
+/// `normalize_all` 的結果
+#[derive(Debug, Default)]
+pub struct NormalizeReport {
+    /// (索引, 轉換結果)，索引對應傳入的 metadata（同一個檔案出現多次時都列入）
+    pub changed: Vec<(usize, Normalized)>,
+    /// 不需要轉換的檔案數
+    pub unchanged: usize,
+    /// 找不到或無法轉換的檔案
+    pub failed: Vec<(String, String)>,
+}
+
+/// 以 `workers` 個執行緒轉換圖片
+///
+/// `write` 時各執行緒轉換完就覆寫圖片（暫存檔再改名），否則只計算；
+/// 轉換後的內容不會留在記憶體。已移到冷儲存的原圖先取回。
+pub fn normalize_all(
+    context: &DataContext,
+    metadata: &[ImageMetadata],
+    options: &NormalizeOptions,
+    workers: usize,
+    write: bool,
+) -> NormalizeReport {
+    let mut seen = HashSet::new();
+    let pending: Vec<usize> = (0..metadata.len())
+        .filter(|&i| seen.insert(metadata[i].filename.as_str()))
+        .collect();
+
+    let pb = ProgressBar::new(pending.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} 張 ({percent}%) {eta} {msg}")
+            .unwrap()
+            .progress_chars("=>-")
+    );
+
+    let next = AtomicUsize::new(0);
+    let unchanged = AtomicUsize::new(0);
+    let converted = Mutex::new(HashMap::new());
+    let failed = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..workers.max(1).min(pending.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(&i) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let filename = metadata[i].filename.as_str();
+                    let result = context
+                        .original_path(filename)
+                        .and_then(|path| std::fs::read(path).context("無法讀取圖片"))
+                        .and_then(|bytes| normalize(&bytes, options))
+                        .and_then(|converted| match converted {
+                            Some(bytes) if write => context.file_manager().save_image(filename, &bytes).map(|()| Some(Normalized::of(&bytes))),
+                            Some(bytes) => Ok(Some(Normalized::of(&bytes))),
+                            None => Ok(None),
+                        });
+                    match result {
+                        Ok(Some(normalized)) => {
+                            converted.lock().unwrap().insert(filename, normalized);
+                        }
+                        Ok(None) => {
+                            unchanged.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => failed.lock().unwrap().push((filename.to_string(), e.to_string())),
+                    }
+                    pb.set_message(terminal::message(filename.to_string()));
+                    pb.inc(1);
+                }
+            });
+        }
+    });
+    pb.finish_and_clear();
+
+    let converted = converted.into_inner().unwrap();
+    let mut report = NormalizeReport {
+        changed: Vec::new(),
+        unchanged: unchanged.into_inner(),
+        failed: failed.into_inner().unwrap(),
+    };
+    report.failed.sort();
+    for (i, m) in metadata.iter().enumerate() {
+        if let Some(normalized) = converted.get(m.filename.as_str()) {
+            report.changed.push((i, normalized.clone()));
+        }
+    }
+
+    report
+}
+
+/// 更新 metadata 的 hash、大小與尺寸（依轉換後的內容）
+pub fn update_metadata(metadata: &mut ImageMetadata, normalized: &Normalized) {
+    metadata.content_hash = normalized.content_hash.clone();
+    metadata.file_size = Some(normalized.file_size);
+    if let Some((width, height)) = normalized.dimensions {
+        metadata.width = Some(width);
+        metadata.height = Some(height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MetadataBackend;
+    use serde_json::json;
+
+    fn encoded(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+        bytes
+    }
+
+    /// 只有一個 IFD 的 EXIF（`orientation` 為 None 時沒有任何欄位）
+    fn exif(orientation: Option<u16>) -> Vec<u8> {
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        match orientation {
+            Some(value) => {
+                tiff.extend(1u16.to_le_bytes());
+                tiff.extend([0x12, 0x01, 3, 0, 1, 0, 0, 0]);
+                tiff.extend(value.to_le_bytes());
+                tiff.extend([0, 0]);
+            }
+            None => tiff.extend(0u16.to_le_bytes()),
+        }
+        tiff.extend(0u32.to_le_bytes());
+        tiff
+    }
+
+    fn jpeg_with_exif(orientation: Option<u16>) -> Vec<u8> {
+        let jpeg = encoded(DynamicImage::new_rgb8(4, 2), ImageFormat::Jpeg);
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend(exif(orientation));
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend([0xFF, 0xE1]);
+        bytes.extend(((payload.len() + 2) as u16).to_be_bytes());
+        bytes.extend(payload);
+        bytes.extend([0xFF, 0xFE, 0, 6]);
+        bytes.extend(b"note");
+        bytes.extend(&jpeg[2..]);
+        bytes
+    }
+
+    #[test]
+    fn test_strip() {
+        let options = NormalizeOptions::default();
+
+        // JPEG：移除 EXIF 與註解，影像資料不變
+        let jpeg = jpeg_with_exif(None);
+        let stripped = normalize(&jpeg, &options).unwrap().unwrap();
+        assert!(!stripped.windows(4).any(|w| w == b"Exif"));
+        assert!(!stripped.windows(4).any(|w| w == b"note"));
+        assert_eq!(image::load_from_memory(&stripped).unwrap().width(), 4);
+        assert_eq!(normalize(&stripped, &options).unwrap(), None);
+
+        // 有方向標記：轉正後重新編碼
+        let rotated = normalize(&jpeg_with_exif(Some(6)), &options).unwrap().unwrap();
+        assert_eq!(media::probe_dimensions(&rotated), Some((2, 4)));
+        assert!(!rotated.windows(4).any(|w| w == b"Exif"));
+
+        // PNG：移除文字區塊
+        let png = encoded(DynamicImage::new_rgba8(3, 3), ImageFormat::Png);
+        let mut with_text = png[..33].to_vec();
+        with_text.extend(7u32.to_be_bytes());
+        with_text.extend(b"tEXtkey\0val");
+        with_text.extend([0; 4]);
+        with_text.extend(&png[33..]);
+        assert_eq!(normalize(&with_text, &options).unwrap(), Some(png.clone()));
+        assert_eq!(normalize(&png, &options).unwrap(), None);
+
+        // WebP：移除 EXIF 區塊、清掉 VP8X 的 flag 並更新 RIFF 大小
+        let webp = encoded(DynamicImage::new_rgb8(5, 5), ImageFormat::WebP);
+        let exif = exif(None);
+        let mut chunks = b"VP8X".to_vec();
+        chunks.extend(10u32.to_le_bytes());
+        chunks.extend([0x08, 0, 0, 0, 4, 0, 0, 4, 0, 0]);
+        chunks.extend(&webp[12..]);
+        chunks.extend(b"EXIF");
+        chunks.extend((exif.len() as u32).to_le_bytes());
+        chunks.extend(&exif);
+        let mut with_exif = b"RIFF".to_vec();
+        with_exif.extend(((chunks.len() + 4) as u32).to_le_bytes());
+        with_exif.extend(b"WEBP");
+        with_exif.extend(chunks);
+        let stripped = normalize(&with_exif, &options).unwrap().unwrap();
+        assert!(!stripped.windows(4).any(|w| w == b"EXIF"));
+        assert_eq!(stripped[20], 0);
+        assert_eq!(u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize, stripped.len() - 8);
+        assert_eq!(image::load_from_memory(&stripped).unwrap().width(), 5);
+
+        // 無法辨識的內容維持原樣
+        assert_eq!(normalize(b"<svg></svg>", &options).unwrap(), None);
+    }
+
+    #[test]
+    fn test_normalize_all() {
+        let dir = std::env::temp_dir().join(format!("meme-normalize-{}", std::process::id()));
+        let context = DataContext::open(dir.to_str().unwrap(), MetadataBackend::Jsonl).unwrap();
+        let file_manager = context.file_manager();
+        let mut transparent = image::RgbaImage::new(6, 4);
+        transparent.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        file_manager.save_image("a.png", &encoded(DynamicImage::ImageRgba8(transparent), ImageFormat::Png)).unwrap();
+        file_manager.save_image("b.jpg", &encoded(DynamicImage::new_rgb8(4, 2), ImageFormat::Jpeg)).unwrap();
+
+        let mut metadata: Vec<ImageMetadata> = ["a.png", "b.jpg", "a.png", "gone.png"]
+            .iter()
+            .map(|filename| serde_json::from_value(json!({
+                "filename": filename, "description": "", "url": "", "content_hash": filename,
+                "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z",
+            })).unwrap())
+            .collect();
+
+        // 統一成 JPEG：已是 JPEG 且沒有 EXIF 的不變；同一個檔案只轉一次，兩筆 metadata 都列入
+        let options = NormalizeOptions { format: Some(OutputFormat::Jpeg), ..Default::default() };
+        let preview = normalize_all(&context, &metadata, &options, 2, false);
+        assert_eq!(preview.changed.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!((preview.unchanged, preview.failed.len()), (1, 1));
+        let original = std::fs::read(file_manager.get_image_path("a.png")).unwrap();
+        assert_eq!(media::detect_format(&original), MediaFormat::Png);
+
+        let report = normalize_all(&context, &metadata, &options, 2, true);
+        let (i, normalized) = &report.changed[0];
+        assert_eq!(normalized, &preview.changed[0].1);
+        let bytes = std::fs::read(file_manager.get_image_path("a.png")).unwrap();
+        assert_eq!(media::detect_format(&bytes), MediaFormat::Jpeg);
+        // 透明的部分變成白色
+        let converted = image::load_from_memory(&bytes).unwrap().to_rgb8();
+        assert!(converted.get_pixel(5, 3).0.iter().all(|&c| c > 240));
+
+        update_metadata(&mut metadata[*i], normalized);
+        assert_eq!(metadata[0].content_hash, integrity::sha256_hex(&bytes));
+        assert_eq!((metadata[0].width, metadata[0].height, metadata[0].file_size), (Some(6), Some(4), Some(bytes.len() as u64)));
+
+        assert!(OutputFormat::parse("gif").is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
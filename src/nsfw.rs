@@ -0,0 +1,316 @@
+use crate::file_manager::FileManager;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+/// 偵測結果寫入 metadata 的 `extra` 欄位
+pub const FLAG_FIELD: &str = "nsfw";
+pub const SCORE_FIELD: &str = "nsfw_score";
+
+/// 隔離目錄（圖片與它們的 metadata.jsonl，不算在資料集內）
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// 分數達到此值視為 NSFW
+pub const DEFAULT_THRESHOLD: f64 = 0.8;
+
+/// NSFW 偵測（0~1 的分數，越高越可能是成人內容）
+pub trait NsfwDetector: Send + Sync {
+    fn score(&self, path: &Path) -> Result<f64>;
+}
+
+/// 以外部程式偵測（例如以 onnxruntime 載入 NSFW 模型的腳本，在本機執行）
+///
+/// 執行 `<command> <圖片路徑>`，stdout 第一行是 0~1 的分數。
+#[derive(Debug, Clone)]
+pub struct CommandDetector {
+    command: String,
+}
+
+impl CommandDetector {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+        }
+    }
+}
+
+impl NsfwDetector for CommandDetector {
+    fn score(&self, path: &Path) -> Result<f64> {
+        let mut parts = self.command.split_whitespace();
+        let program = parts.next().context("NSFW 模型需要指令")?;
+        let output = Command::new(program)
+            .args(parts)
+            .arg(path)
+            .output()
+            .with_context(|| format!("無法執行 NSFW 模型: {}", self.command))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "NSFW 模型失敗（{}）: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let score: f64 = stdout
+            .lines()
+            .next()
+            .and_then(|line| line.trim().parse().ok())
+            .with_context(|| format!("NSFW 模型的輸出不是分數: {}", stdout.trim()))?;
+        Ok(score.clamp(0.0, 1.0))
+    }
+}
+
+/// 偵測到 NSFW 時的處理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NsfwAction {
+    /// 只在 metadata 標記
+    #[default]
+    Tag,
+    /// 標記並移到隔離目錄（不進資料集）
+    Quarantine,
+}
+
+impl NsfwAction {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "tag" => Ok(Self::Tag),
+            "quarantine" => Ok(Self::Quarantine),
+            other => anyhow::bail!("未知的 NSFW 處理方式: {}（可用: tag, quarantine）", other),
+        }
+    }
+}
+
+/// 下載時的 NSFW 過濾（偵測器、門檻與處理方式）
+#[derive(Clone)]
+pub struct NsfwFilter {
+    detector: Arc<dyn NsfwDetector>,
+    pub threshold: f64,
+    pub action: NsfwAction,
+}
+
+impl std::fmt::Debug for NsfwFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NsfwFilter")
+            .field("threshold", &self.threshold)
+            .field("action", &self.action)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NsfwFilter {
+    pub fn new(detector: Arc<dyn NsfwDetector>, threshold: f64, action: NsfwAction) -> Self {
+        Self { detector, threshold, action }
+    }
+
+    pub fn score(&self, path: &Path) -> Result<f64> {
+        self.detector.score(path)
+    }
+
+    pub fn is_flagged(&self, score: f64) -> bool {
+        score >= self.threshold
+    }
+}
+
+/// 寫入偵測結果
+pub fn tag(metadata: &mut ImageMetadata, score: f64, threshold: f64) {
+    metadata.extra.insert(FLAG_FIELD.to_string(), (score >= threshold).into());
+    metadata
+        .extra
+        .insert(SCORE_FIELD.to_string(), ((score * 100.0).round() / 100.0).into());
+}
+
+/// 是否被標記為 NSFW
+pub fn is_flagged(metadata: &ImageMetadata) -> bool {
+    metadata.extra.get(FLAG_FIELD).and_then(|v| v.as_bool()) == Some(true)
+}
+
+/// 是否偵測過
+pub fn is_scored(metadata: &ImageMetadata) -> bool {
+    metadata.extra.contains_key(SCORE_FIELD)
+}
+
+/// 隔離目錄：被判定為 NSFW 的圖片移到這裡，metadata 追加到 `quarantine/metadata.jsonl`
+pub struct Quarantine {
+    dir: PathBuf,
+}
+
+impl Quarantine {
+    pub fn new(data_dir: &str) -> Self {
+        Self {
+            dir: Path::new(data_dir).join(QUARANTINE_DIR),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn metadata_path(&self) -> PathBuf {
+        self.dir.join("metadata.jsonl")
+    }
+
+    /// 把圖片移進隔離目錄並記錄 metadata（圖片已移走時只記錄 metadata）
+    pub fn move_in(&self, image_path: &Path, metadata: &ImageMetadata) -> Result<()> {
+        fs::create_dir_all(&self.dir).with_context(|| format!("無法建立 {}", self.dir.display()))?;
+        if image_path.exists() {
+            fs::rename(image_path, self.dir.join(&metadata.filename))
+                .with_context(|| format!("無法隔離 {}", metadata.filename))?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.metadata_path())
+            .context("無法開啟隔離的 metadata")?;
+        writeln!(file, "{}", serde_json::to_string(metadata)?)?;
+        Ok(())
+    }
+
+    /// 隔離中的圖片
+    pub fn load(&self) -> Result<Vec<ImageMetadata>> {
+        let content = match fs::read_to_string(self.metadata_path()) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("無法讀取隔離的 metadata"),
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("解析隔離的 metadata 失敗"))
+            .collect()
+    }
+
+    /// 誤判時放回資料集：圖片移回 images/，回傳標記為非 NSFW 的 metadata（由呼叫端寫回）
+    pub fn release(&self, file_manager: &FileManager, filename: &str) -> Result<Vec<ImageMetadata>> {
+        let (mut released, kept): (Vec<_>, Vec<_>) = self.load()?.into_iter().partition(|m| m.filename == filename);
+        if released.is_empty() {
+            anyhow::bail!("隔離目錄中沒有 {}", filename);
+        }
+
+        let path = self.dir.join(filename);
+        if path.exists() {
+            fs::rename(&path, file_manager.get_image_path(filename))
+                .with_context(|| format!("無法放回 {}", filename))?;
+        }
+
+        let temp_path = self.metadata_path().with_extension("jsonl.tmp");
+        let mut content = String::new();
+        for m in &kept {
+            content.push_str(&serde_json::to_string(m)?);
+            content.push('\n');
+        }
+        fs::write(&temp_path, content)?;
+        fs::rename(&temp_path, self.metadata_path()).context("無法更新隔離的 metadata")?;
+
+        for m in &mut released {
+            m.extra.insert(FLAG_FIELD.to_string(), false.into());
+        }
+        Ok(released)
+    }
+}
+
+/// 偵測全部圖片的結果
+#[derive(Debug, Default)]
+pub struct NsfwReport {
+    /// (索引, 分數)，索引對應傳入的 metadata
+    pub scored: Vec<(usize, f64)>,
+    /// 已偵測過而略過的數量
+    pub skipped: usize,
+    /// 找不到或無法偵測的檔案
+    pub failed: Vec<(String, String)>,
+}
+
+impl NsfwReport {
+    pub fn flagged(&self, threshold: f64) -> usize {
+        self.scored.iter().filter(|(_, score)| *score >= threshold).count()
+    }
+}
+
+/// 偵測圖片（`rescan` 為 false 時略過已偵測過的）
+pub fn scan_all(
+    detector: &dyn NsfwDetector,
+    file_manager: &FileManager,
+    metadata: &[ImageMetadata],
+    rescan: bool,
+) -> NsfwReport {
+    let mut report = NsfwReport::default();
+
+    for (i, m) in metadata.iter().enumerate() {
+        if !rescan && is_scored(m) {
+            report.skipped += 1;
+            continue;
+        }
+
+        match detector.score(Path::new(&file_manager.get_image_path(&m.filename))) {
+            Ok(score) => report.scored.push((i, score)),
+            Err(e) => report.failed.push((m.filename.clone(), e.to_string())),
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 以檔案內容當作分數
+    struct FileScore;
+
+    impl NsfwDetector for FileScore {
+        fn score(&self, path: &Path) -> Result<f64> {
+            Ok(fs::read_to_string(path)?.trim().parse()?)
+        }
+    }
+
+    #[test]
+    fn test_scan_and_quarantine() {
+        let dir = std::env::temp_dir().join(format!("meme-nsfw-{}", std::process::id()));
+        let data_dir = dir.to_str().unwrap();
+        let file_manager = FileManager::new(data_dir).unwrap();
+        file_manager.save_image("safe.jpg", b"0.1").unwrap();
+        file_manager.save_image("nsfw.jpg", b"0.95").unwrap();
+
+        let mut metadata: Vec<ImageMetadata> = ["safe.jpg", "nsfw.jpg", "gone.jpg"]
+            .iter()
+            .map(|filename| serde_json::from_value(serde_json::json!({
+                "filename": filename, "description": "", "url": "", "content_hash": filename,
+                "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z",
+            })).unwrap())
+            .collect();
+
+        let report = scan_all(&FileScore, &file_manager, &metadata, false);
+        assert_eq!((report.scored.len(), report.failed.len()), (2, 1));
+        assert_eq!(report.flagged(DEFAULT_THRESHOLD), 1);
+        for (i, score) in &report.scored {
+            tag(&mut metadata[*i], *score, DEFAULT_THRESHOLD);
+        }
+        assert!(!is_flagged(&metadata[0]) && is_flagged(&metadata[1]));
+        assert_eq!(scan_all(&FileScore, &file_manager, &metadata, false).skipped, 2);
+
+        // 隔離後不在 images/，放回時標記為非 NSFW
+        let quarantine = Quarantine::new(data_dir);
+        let path = file_manager.get_image_path("nsfw.jpg");
+        quarantine.move_in(Path::new(&path), &metadata[1]).unwrap();
+        assert!(!Path::new(&path).exists());
+        assert!(quarantine.dir().join("nsfw.jpg").exists());
+        assert_eq!(quarantine.load().unwrap().len(), 1);
+
+        assert!(quarantine.release(&file_manager, "safe.jpg").is_err());
+        let released = quarantine.release(&file_manager, "nsfw.jpg").unwrap();
+        assert_eq!(released.len(), 1);
+        assert!(!is_flagged(&released[0]) && is_scored(&released[0]));
+        assert!(Path::new(&path).exists());
+        assert!(quarantine.load().unwrap().is_empty());
+
+        assert_eq!(NsfwAction::parse("quarantine").unwrap(), NsfwAction::Quarantine);
+        assert!(NsfwAction::parse("delete").is_err());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
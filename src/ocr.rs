@@ -0,0 +1,242 @@
+use crate::context::DataContext;
+use crate::terminal;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 從圖片擷取文字
+pub trait OcrEngine: Send + Sync {
+    fn recognize(&self, path: &Path) -> Result<String>;
+}
+
+/// 呼叫 tesseract 命令列（需另外安裝 tesseract 與語言資料）
+#[derive(Debug, Clone)]
+pub struct TesseractOcr {
+    program: String,
+    languages: String,
+}
+
+impl Default for TesseractOcr {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_LANGUAGES)
+    }
+}
+
+impl TesseractOcr {
+    /// 繁體中文 + 英文
+    pub const DEFAULT_LANGUAGES: &'static str = "chi_tra+eng";
+
+    /// `languages` 為 tesseract 的 `-l`，例如 `chi_tra+eng`
+    pub fn new(languages: &str) -> Self {
+        Self {
+            program: "tesseract".to_string(),
+            languages: languages.to_string(),
+        }
+    }
+}
+
+impl OcrEngine for TesseractOcr {
+    fn recognize(&self, path: &Path) -> Result<String> {
+        // --psm 11：不假設版面，找出散落各處的文字（迷因的標題在上下緣）
+        let output = Command::new(&self.program)
+            .arg(path)
+            .arg("stdout")
+            .args(["-l", &self.languages, "--psm", "11"])
+            .output();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                anyhow::bail!("找不到 tesseract（請安裝 tesseract-ocr 與 {} 語言資料，或以 --model 指定 OCR 程式）", self.languages)
+            }
+            Err(e) => return Err(e).context("無法執行 tesseract"),
+        };
+        if !output.status.success() {
+            anyhow::bail!(
+                "tesseract 失敗（{}）: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// 以外部程式擷取文字（例如以 onnxruntime 執行 OCR 模型的腳本）
+///
+/// 執行 `<command> <圖片路徑>`，stdout 就是辨識出的文字。
+#[derive(Debug, Clone)]
+pub struct CommandOcr {
+    command: String,
+}
+
+impl CommandOcr {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+        }
+    }
+}
+
+impl OcrEngine for CommandOcr {
+    fn recognize(&self, path: &Path) -> Result<String> {
+        let mut parts = self.command.split_whitespace();
+        let program = parts.next().context("--model 需要指令")?;
+        let output = Command::new(program)
+            .args(parts)
+            .arg(path)
+            .output()
+            .with_context(|| format!("無法執行 OCR 程式: {}", self.command))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "OCR 程式失敗（{}）: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// 整理 OCR 輸出：每行去掉多餘空白，丟掉沒有任何文字或數字的雜訊行
+pub fn clean_text(raw: &str) -> String {
+    raw.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| line.chars().any(char::is_alphanumeric))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `ocr_all` 的結果
+#[derive(Debug, Default)]
+pub struct OcrReport {
+    /// (索引, 文字)，索引對應傳入的 metadata；沒有文字時為空字串
+    pub recognized: Vec<(usize, String)>,
+    /// 已有 OCR 結果而略過的數量
+    pub skipped: usize,
+    /// 找不到或無法辨識的檔案
+    pub failed: Vec<(String, String)>,
+}
+
+/// 以 `workers` 個執行緒辨識圖片文字（`redo` 為 false 時略過已有 `ocr_text` 的）
+///
+/// 相同內容的圖片只辨識一次；已移到冷儲存的原圖先取回。
+pub fn ocr_all(
+    engine: &dyn OcrEngine,
+    context: &DataContext,
+    metadata: &[ImageMetadata],
+    redo: bool,
+    workers: usize,
+) -> OcrReport {
+    let mut report = OcrReport::default();
+    let mut pending: Vec<usize> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for (i, m) in metadata.iter().enumerate() {
+        if !redo && m.ocr_text.is_some() {
+            report.skipped += 1;
+        } else if seen.insert(m.content_hash.as_str()) {
+            pending.push(i);
+        }
+    }
+
+    let pb = ProgressBar::new(pending.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} 張 ({percent}%) {eta} {msg}")
+            .unwrap()
+            .progress_chars("=>-")
+    );
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<(usize, Result<String>)>> = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..workers.max(1).min(pending.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(&i) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let text = context
+                        .original_path(&metadata[i].filename)
+                        .and_then(|path| engine.recognize(Path::new(&path)))
+                        .map(|raw| clean_text(&raw));
+                    pb.set_message(terminal::message(metadata[i].filename.clone()));
+                    pb.inc(1);
+                    results.lock().unwrap().push((i, text));
+                }
+            });
+        }
+    });
+    pb.finish_and_clear();
+
+    // 結果依 metadata 順序，並套用到相同內容的其他圖片
+    let mut by_hash = std::collections::HashMap::new();
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    for (i, text) in results {
+        match text {
+            Ok(text) => {
+                by_hash.insert(metadata[i].content_hash.as_str(), text);
+            }
+            Err(e) => report.failed.push((metadata[i].filename.clone(), e.to_string())),
+        }
+    }
+    for (i, m) in metadata.iter().enumerate() {
+        if !redo && m.ocr_text.is_some() {
+            continue;
+        }
+        if let Some(text) = by_hash.get(m.content_hash.as_str()) {
+            report.recognized.push((i, text.clone()));
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MetadataBackend;
+    use serde_json::json;
+
+    /// 以檔案內容當作辨識結果
+    struct FileText;
+
+    impl OcrEngine for FileText {
+        fn recognize(&self, path: &Path) -> Result<String> {
+            Ok(std::fs::read_to_string(path)?)
+        }
+    }
+
+    #[test]
+    fn test_ocr_all() {
+        assert_eq!(clean_text("  WHEN  YOU \n\n ~ .. ~\n  終於 下班\n"), "WHEN YOU\n終於 下班");
+
+        let dir = std::env::temp_dir().join(format!("meme-ocr-{}", std::process::id()));
+        let context = DataContext::open(dir.to_str().unwrap(), MetadataBackend::Jsonl).unwrap();
+        let file_manager = context.file_manager();
+        file_manager.save_image("a.jpg", "ONE DOES NOT\n\n  SIMPLY ".as_bytes()).unwrap();
+        file_manager.save_image("b.jpg", b"other").unwrap();
+
+        let metadata: Vec<ImageMetadata> = serde_json::from_value(json!([
+            { "filename": "a.jpg", "description": "", "url": "", "content_hash": "h1", "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z" },
+            { "filename": "a-copy.jpg", "description": "", "url": "", "content_hash": "h1", "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z" },
+            { "filename": "b.jpg", "description": "", "url": "", "content_hash": "h2", "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z", "ocr_text": "done" },
+            { "filename": "gone.jpg", "description": "", "url": "", "content_hash": "h3", "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z" },
+        ]))
+        .unwrap();
+
+        let report = ocr_all(&FileText, &context, &metadata, false, 4);
+        assert_eq!(report.recognized, vec![(0, "ONE DOES NOT\nSIMPLY".to_string()), (1, "ONE DOES NOT\nSIMPLY".to_string())]);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.failed.len(), 1);
+
+        let report = ocr_all(&FileText, &context, &metadata, true, 1);
+        assert_eq!(report.recognized.len(), 3);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
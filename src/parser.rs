@@ -1,5 +1,9 @@
 use scraper::{Html, Selector};
-use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 /// Parser Trait - 不同網站實作不同的 Parser
 pub trait PageParser: Send + Sync {
@@ -8,10 +12,113 @@ pub trait PageParser: Send + Sync {
     fn parse_page(&self, html: &str) -> Result<Vec<(String, String)>>;
     
     /// 取得網站的 base URL（用於處理相對路徑）
+    #[allow(dead_code)]
     fn base_url(&self) -> &str;
+    
+    /// `parse_page` 回傳的是否為詳細頁網址（需要再進詳細頁取得原圖）
+    fn follows_detail_pages(&self) -> bool {
+        false
+    }
+    
+    /// 從詳細頁解析原圖 URL
+    fn parse_detail(&self, _html: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+    
+    /// 從詳細頁解析原圖 URL 與額外的 metadata（預設只有 `parse_detail` 的原圖）
+    fn parse_detail_page(&self, html: &str) -> Result<Option<DetailPage>> {
+        Ok(self.parse_detail(html)?.map(|image_url| DetailPage {
+            image_url,
+            ..Default::default()
+        }))
+    }
+    
+    /// 解析頁面上各圖片的授權（圖片 URL -> 授權文字），沒有設定授權選擇器時為空
+    fn parse_licenses(&self, _html: &str) -> Result<HashMap<String, String>> {
+        Ok(HashMap::new())
+    }
+    
+    /// 分頁偵測方式（未指定總頁數時，引擎依此判斷最後一頁）
+    fn pagination(&self) -> Pagination {
+        Pagination::None
+    }
+    
+    /// 從列表頁取得分頁資訊
+    fn page_hint(&self, html: &str) -> Result<PageHint> {
+        self.pagination().detect(html)
+    }
+}
+
+/// 詳細頁的解析結果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DetailPage {
+    /// 原圖網址
+    pub image_url: String,
+    /// 詳細頁上的標籤
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 詳細頁上的其他欄位（寫入 metadata 的 `extra`）
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// 分頁偵測方式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Pagination {
+    /// 頁面上沒有可用的分頁資訊（引擎逐步探測到沒有項目的頁面）
+    #[default]
+    None,
+    /// 「下一頁」連結的選擇器，找不到時表示這是最後一頁
+    NextLink(String),
+    /// 分頁列頁碼連結的選擇器，取文字或 `page=N` 中最大的頁碼
+    MaxPage(String),
+}
+
+/// 單頁的分頁資訊（None 表示無法判斷）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PageHint {
+    /// 是否還有下一頁
+    pub has_next: Option<bool>,
+    /// 頁面上看到的最大頁碼
+    pub last_page: Option<u32>,
+}
+
+impl Pagination {
+    pub fn detect(&self, html: &str) -> Result<PageHint> {
+        let css = match self {
+            Self::None => return Ok(PageHint::default()),
+            Self::NextLink(css) | Self::MaxPage(css) => css,
+        };
+        let selector = Selector::parse(css)
+            .map_err(|e| anyhow::anyhow!("分頁選擇器解析失敗: {:?}", e))?;
+        let document = Html::parse_document(html);
+        
+        Ok(match self {
+            Self::NextLink(_) => PageHint {
+                has_next: Some(document.select(&selector).next().is_some()),
+                last_page: None,
+            },
+            _ => PageHint {
+                has_next: None,
+                last_page: document.select(&selector).filter_map(|link| page_number(&link)).max(),
+            },
+        })
+    }
+}
+
+/// 頁碼連結的頁碼（文字是數字時用文字，否則找 href 中的 `page=N`）
+fn page_number(link: &scraper::ElementRef) -> Option<u32> {
+    if let Ok(number) = link.text().collect::<String>().trim().parse() {
+        return Some(number);
+    }
+    
+    let href = link.value().attr("href")?;
+    let (_, rest) = href.split_once("page=")?;
+    rest.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
 }
 
 /// Memes.tw 的 Parser 實作
+#[allow(dead_code)]
 pub struct MemesTwParser {
     base_url: String,
     container_selector: Selector,
@@ -20,6 +127,7 @@ pub struct MemesTwParser {
 }
 
 impl MemesTwParser {
+    #[allow(dead_code)]
     pub fn new() -> Result<Self> {
         Ok(Self {
             base_url: "https://memes.tw".to_string(),
@@ -72,34 +180,78 @@ impl PageParser for MemesTwParser {
 pub struct GenericParser {
     base_url: String,
     config: ParserConfig,
+    pagination: Pagination,
 }
 
 /// Parser 配置
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParserConfig {
     /// 容器選擇器（包含單個項目的元素）
     pub container_selector: String,
     /// 圖片 URL 選擇器（相對於容器）
     pub image_selector: String,
     /// 圖片 URL 的屬性名稱（通常是 "src"）
+    ///
+    /// 值是空的或延遲載入的佔位圖（`data:` URI）時，依序改用 `data-src`、`srcset`
+    /// 中解析度最高的候選、`src`，見 `image_source`。
+    #[serde(default = "ParserConfig::default_image_attr")]
     pub image_attr: String,
+    /// 容器內所有符合的圖片都取（預設只取第一張）
+    #[serde(default)]
+    pub all_images: bool,
+    /// 圖片網址的來源
+    #[serde(default)]
+    pub source_kind: SourceKind,
     /// 名稱選擇器（相對於容器）
     pub name_selector: String,
     /// 名稱提取方式
+    #[serde(default)]
     pub name_extraction: NameExtraction,
+    /// 授權選擇器（先在容器內找，找不到時用整頁第一個符合的元素，例如頁尾的 `a[rel=license]`）
+    #[serde(default)]
+    pub license_selector: Option<String>,
+}
+
+impl ParserConfig {
+    fn default_image_attr() -> String {
+        "src".to_string()
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum NameExtraction {
     /// 從元素的文字內容提取
+    #[default]
     TextContent,
     /// 從元素的屬性提取
     Attribute(String),
 }
 
+/// 圖片網址的來源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    /// 從元素屬性取得（`image_attr`，並處理延遲載入與 srcset）
+    #[default]
+    Attribute,
+    /// 從 inline style 的 `background-image: url(...)` 取得（`image_selector` 選到帶 style 的元素）
+    StyleBackground,
+}
+
 impl GenericParser {
     pub fn new(base_url: String, config: ParserConfig) -> Self {
-        Self { base_url, config }
+        Self {
+            base_url,
+            config,
+            pagination: Pagination::None,
+        }
+    }
+    
+    /// 設定分頁偵測方式
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = pagination;
+        self
     }
     
     /// 建立 Memes.tw 的配置
@@ -110,11 +262,32 @@ impl GenericParser {
             image_attr: "src".to_string(),
             name_selector: "header > b".to_string(),
             name_extraction: NameExtraction::TextContent,
+            license_selector: None,
+            all_images: false,
+            source_kind: SourceKind::Attribute,
         };
         
         Ok(Self::new("https://memes.tw".to_string(), config))
     }
     
+    /// 容器內的圖片 URL（已轉成完整網址；`all_images` 關閉時最多一張）
+    fn container_images(&self, container: &scraper::ElementRef, image_selector: &Selector) -> Vec<String> {
+        let urls = container
+            .select(image_selector)
+            .filter_map(|elem| match self.config.source_kind {
+                SourceKind::Attribute => image_source(&elem, &self.config.image_attr),
+                SourceKind::StyleBackground => elem.value().attr("style").and_then(background_image_url),
+            })
+            .map(|url| normalize_url(&url, &self.base_url));
+        
+        if self.config.all_images {
+            let mut seen = std::collections::HashSet::new();
+            urls.filter(|url| seen.insert(url.clone())).collect()
+        } else {
+            urls.take(1).collect()
+        }
+    }
+    
     /// 建立自訂配置（範例：假設的另一個網站）
     #[allow(dead_code)]
     pub fn custom_site(base_url: &str, config: ParserConfig) -> Self {
@@ -159,28 +332,768 @@ impl PageParser for GenericParser {
                 .unwrap_or_else(|| "unknown".to_string());
             
             // 提取圖片 URL
-            let image_url = container
-                .select(&image_selector)
-                .next()
-                .and_then(|elem| elem.value().attr(&self.config.image_attr))
-                .map(|s| s.to_string());
+            for url in self.container_images(&container, &image_selector) {
+                results.push((url, name.clone()));
+            }
+        }
+        
+        Ok(results)
+    }
+    
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+    
+    fn pagination(&self) -> Pagination {
+        self.pagination.clone()
+    }
+    
+    fn parse_licenses(&self, html: &str) -> Result<HashMap<String, String>> {
+        let Some(license_selector) = &self.config.license_selector else {
+            return Ok(HashMap::new());
+        };
+        
+        let document = Html::parse_document(html);
+        let container_selector = Selector::parse(&self.config.container_selector)
+            .map_err(|e| anyhow::anyhow!("容器選擇器錯誤: {:?}", e))?;
+        let image_selector = Selector::parse(&self.config.image_selector)
+            .map_err(|e| anyhow::anyhow!("圖片選擇器錯誤: {:?}", e))?;
+        let license_selector = Selector::parse(license_selector)
+            .map_err(|e| anyhow::anyhow!("授權選擇器錯誤: {:?}", e))?;
+        
+        // 整頁的授權只看容器外的元素，不會把某張圖片的授權套到其他圖片
+        let page_license = document
+            .select(&license_selector)
+            .filter(|elem| {
+                !elem.ancestors()
+                    .filter_map(scraper::ElementRef::wrap)
+                    .any(|ancestor| container_selector.matches(&ancestor))
+            })
+            .find_map(license_text);
+        let mut licenses = HashMap::new();
+        
+        for container in document.select(&container_selector) {
+            let urls = self.container_images(&container, &image_selector);
+            if urls.is_empty() {
+                continue;
+            }
             
-            if let Some(url) = image_url {
-                let full_url = normalize_url(&url, &self.base_url);
-                results.push((full_url, name));
+            let license = container
+                .select(&license_selector)
+                .find_map(license_text)
+                .or_else(|| page_license.clone());
+            if let Some(license) = license {
+                for url in urls {
+                    licenses.insert(url, license.clone());
+                }
             }
         }
         
+        Ok(licenses)
+    }
+}
+
+/// 圖片元素的網址：先用設定的屬性，是空的或佔位圖時依序改用延遲載入的屬性、
+/// `srcset` 中解析度最高的候選，最後才是 `src`
+///
+/// 設定的屬性是 `src` 時也照這個順序，`srcset` 有更高解析度的版本時不會只拿到縮圖。
+fn image_source(elem: &scraper::ElementRef, preferred_attr: &str) -> Option<String> {
+    let attr = |name: &str| {
+        elem.value()
+            .attr(name)
+            .map(str::trim)
+            .filter(|value| is_real_image_url(value))
+            .map(str::to_string)
+    };
+    let srcset = |name: &str| elem.value().attr(name).and_then(best_srcset_candidate);
+    
+    let preferred = match preferred_attr {
+        "src" => None,
+        name if name.ends_with("srcset") => srcset(name),
+        name => attr(name),
+    };
+    preferred
+        .or_else(|| attr("data-src"))
+        .or_else(|| attr("data-lazy-src"))
+        .or_else(|| attr("data-original"))
+        .or_else(|| srcset("data-srcset"))
+        .or_else(|| srcset("srcset"))
+        .or_else(|| attr("src"))
+}
+
+/// inline style 中 `background-image`/`background` 的第一個圖片網址
+///
+/// 例如 `background-image: url("/a.jpg")`、`background: #000 url('/a.jpg') no-repeat`。
+fn background_image_url(style: &str) -> Option<String> {
+    let style = style.replace("&quot;", "\"");
+    
+    style
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .filter(|(property, _)| matches!(property.trim().to_ascii_lowercase().as_str(), "background-image" | "background"))
+        .flat_map(|(_, value)| value.split("url(").skip(1))
+        .filter_map(|rest| {
+            let url = rest.split(')').next()?.trim().trim_matches(|c| c == '"' || c == '\'');
+            is_real_image_url(url).then(|| url.to_string())
+        })
+        .next()
+}
+
+/// 不是空值也不是延遲載入的佔位圖（`data:` URI 的透明像素）
+fn is_real_image_url(url: &str) -> bool {
+    !url.is_empty() && !url.starts_with("data:") && !url.starts_with("about:")
+}
+
+/// `srcset` 中解析度最高的候選（寬度描述 `800w` 優先於密度描述 `2x`，沒有描述視為 `1x`）
+///
+/// 網址本身可能含逗號（`data:` URI），所以依空白切開，逗號只當作候選的結尾。
+fn best_srcset_candidate(srcset: &str) -> Option<String> {
+    let score = |descriptor: &str| {
+        if let Some(width) = descriptor.strip_suffix('w') {
+            Some((1, width.parse::<f64>().ok()?))
+        } else {
+            Some((0, descriptor.strip_suffix('x')?.parse::<f64>().ok()?))
+        }
+    };
+    
+    let mut candidates = Vec::new();
+    let mut tokens = srcset.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        let token = token.trim_start_matches(',');
+        let (url, descriptor) = match token.strip_suffix(',') {
+            Some(url) => (url, "1x"),
+            None => match tokens.peek().map(|next| next.trim_end_matches(',')) {
+                Some(next) if score(next).is_some() => {
+                    tokens.next();
+                    (token, next)
+                }
+                _ => (token, "1x"),
+            },
+        };
+        if let Some(score) = score(descriptor).filter(|_| is_real_image_url(url)) {
+            candidates.push((score, url));
+        }
+    }
+    
+    candidates
+        .into_iter()
+        .max_by(|(a, _), (b, _)| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+        .map(|(_, url)| url.to_string())
+}
+
+/// 授權元素的文字（沒有文字時用 `content`（meta）或 `href`（連結））
+fn license_text(elem: scraper::ElementRef) -> Option<String> {
+    let text = elem.text().collect::<String>().trim().to_string();
+    if !text.is_empty() {
+        return Some(text);
+    }
+    
+    elem.value()
+        .attr("content")
+        .or_else(|| elem.value().attr("href"))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Imgflip 模板 Parser
+///
+/// 列表頁（`/memetemplates?page=N`）只有縮圖，圖片延遲載入放在 `data-src`；
+/// 原圖要從每個模板的詳細頁（`/meme/<name>`）取得，所以列表頁回傳詳細頁網址。
+pub struct ImgflipParser {
+    base_url: String,
+    container_selector: Selector,
+    link_selector: Selector,
+    detail_image_selector: Selector,
+    og_image_selector: Selector,
+}
+
+impl ImgflipParser {
+    pub fn new() -> Result<Self> {
+        let selector = |css: &str| Selector::parse(css)
+            .map_err(|e| anyhow::anyhow!("選擇器解析失敗: {:?}", e));
+        
+        Ok(Self {
+            base_url: "https://imgflip.com".to_string(),
+            container_selector: selector("div.mt-box")?,
+            link_selector: selector("h3.mt-title > a")?,
+            detail_image_selector: selector("img#mtm-img")?,
+            og_image_selector: selector(r#"meta[property="og:image"]"#)?,
+        })
+    }
+}
+
+impl PageParser for ImgflipParser {
+    fn parse_page(&self, html: &str) -> Result<Vec<(String, String)>> {
+        let document = Html::parse_document(html);
+        let mut results = Vec::new();
+        
+        for container in document.select(&self.container_selector) {
+            let Some(link) = container.select(&self.link_selector).next() else {
+                continue;
+            };
+            let Some(href) = link.value().attr("href") else {
+                continue;
+            };
+            
+            let name = link.text().collect::<String>().trim().to_string();
+            let name = if name.is_empty() { "unknown".to_string() } else { name };
+            results.push((normalize_url(href, &self.base_url), name));
+        }
+        
         Ok(results)
     }
     
     fn base_url(&self) -> &str {
         &self.base_url
     }
+    
+    fn follows_detail_pages(&self) -> bool {
+        true
+    }
+    
+    fn parse_detail(&self, html: &str) -> Result<Option<String>> {
+        let document = Html::parse_document(html);
+        
+        // 延遲載入時真正的網址在 data-src，src 只是佔位圖
+        let image = document
+            .select(&self.detail_image_selector)
+            .next()
+            .and_then(|img| img.value().attr("data-src").or_else(|| img.value().attr("src")))
+            .or_else(|| {
+                document
+                    .select(&self.og_image_selector)
+                    .next()
+                    .and_then(|meta| meta.value().attr("content"))
+            });
+        
+        Ok(image.map(|url| normalize_url(url, &self.base_url)))
+    }
+}
+
+/// 選擇器網站設定（`crawl --selectors <config.json>`）
+///
+/// ```json
+/// {
+///   "url_template": "https://example.com/gallery?page={page}",
+///   "container_selector": "div.card",
+///   "image_selector": "img",
+///   "name_selector": "h3",
+///   "next_selector": "a.next"
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectorSiteConfig {
+    /// 列表頁網址（`{page}` 代入頁碼）
+    pub url_template: String,
+    #[serde(flatten)]
+    pub parser: ParserConfig,
+    /// 「下一頁」連結的選擇器（見 `Pagination::NextLink`）
+    #[serde(default)]
+    pub next_selector: Option<String>,
+    /// 分頁列頁碼連結的選擇器（見 `Pagination::MaxPage`）
+    #[serde(default)]
+    pub max_page_selector: Option<String>,
+}
+
+impl SelectorSiteConfig {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取選擇器設定: {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("無法解析選擇器設定: {}", path))
+    }
+    
+    /// 依設定建立 GenericParser（base URL 為網址樣板的 origin）
+    pub fn parser(&self) -> Result<GenericParser> {
+        let url = reqwest::Url::parse(&self.url_template.replace("{page}", "1"))
+            .with_context(|| format!("無效的列表網址: {}", self.url_template))?;
+        let pagination = match (&self.next_selector, &self.max_page_selector) {
+            (Some(css), _) => Pagination::NextLink(css.clone()),
+            (None, Some(css)) => Pagination::MaxPage(css.clone()),
+            (None, None) => Pagination::None,
+        };
+        
+        Ok(GenericParser::new(url.origin().ascii_serialization(), self.parser.clone())
+            .with_pagination(pagination))
+    }
+}
+
+/// 兩個 Parser 對同一頁的解析差異（以圖片網址對應）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseDiff {
+    /// 兩邊都有且名稱相同
+    pub unchanged: usize,
+    /// 只有舊設定取得的 (網址, 名稱)
+    pub removed: Vec<(String, String)>,
+    /// 只有新設定取得的 (網址, 名稱)
+    pub added: Vec<(String, String)>,
+    /// 網址相同但名稱不同的 (網址, 舊名稱, 新名稱)
+    pub renamed: Vec<(String, String, String)>,
+}
+
+impl ParseDiff {
+    pub fn compare(old: &[(String, String)], new: &[(String, String)]) -> Self {
+        let old_names: HashMap<&str, &str> = old.iter().map(|(url, name)| (url.as_str(), name.as_str())).collect();
+        let new_names: HashMap<&str, &str> = new.iter().map(|(url, name)| (url.as_str(), name.as_str())).collect();
+        let mut diff = Self::default();
+        
+        for (url, name) in old {
+            match new_names.get(url.as_str()) {
+                None => diff.removed.push((url.clone(), name.clone())),
+                Some(new_name) if new_name != name => {
+                    diff.renamed.push((url.clone(), name.clone(), new_name.to_string()));
+                }
+                Some(_) => diff.unchanged += 1,
+            }
+        }
+        diff.added = new
+            .iter()
+            .filter(|(url, _)| !old_names.contains_key(url.as_str()))
+            .cloned()
+            .collect();
+        
+        diff
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty() && self.renamed.is_empty()
+    }
+}
+
+/// 詳細頁設定（`crawl --detail <config.json>`）
+///
+/// ```json
+/// {
+///   "image_selector": "img.full",
+///   "tag_selector": "ul.tags a",
+///   "fields": { "origin": "dl.origin dd", "views": "span.views" }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetailConfig {
+    /// 原圖選擇器（找不到時用 `og:image`）
+    #[serde(default = "DetailConfig::default_image_selector")]
+    pub image_selector: String,
+    /// 原圖網址的屬性（同 `ParserConfig::image_attr`，`<meta>` 用 `content`）
+    #[serde(default = "DetailConfig::default_image_attr")]
+    pub image_attr: String,
+    /// 標籤選擇器（每個符合元素的文字是一個標籤）
+    #[serde(default)]
+    pub tag_selector: Option<String>,
+    /// 額外欄位名稱 -> 選擇器（取第一個符合元素的文字或 `content`）
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl DetailConfig {
+    fn default_image_selector() -> String {
+        "img".to_string()
+    }
+    
+    fn default_image_attr() -> String {
+        "src".to_string()
+    }
+    
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取詳細頁設定: {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("無法解析詳細頁設定: {}", path))
+    }
+}
+
+/// 兩段式解析：列表 Parser 取得的網址視為詳細頁，原圖與 metadata 從詳細頁取得
+///
+/// 列表 Parser 照常設定（例如 `image_attr` 設為 `href` 取項目連結），分頁偵測也沿用它的。
+pub struct DetailParser {
+    list: Arc<dyn PageParser>,
+    image_selector: Selector,
+    image_attr: String,
+    og_image_selector: Selector,
+    tag_selector: Option<Selector>,
+    fields: Vec<(String, Selector)>,
+}
+
+impl DetailParser {
+    pub fn new(list: Arc<dyn PageParser>, config: DetailConfig) -> Result<Self> {
+        let selector = |css: &str| Selector::parse(css)
+            .map_err(|e| anyhow::anyhow!("詳細頁選擇器錯誤 ({}): {:?}", css, e));
+        
+        Ok(Self {
+            list,
+            image_selector: selector(&config.image_selector)?,
+            image_attr: config.image_attr,
+            og_image_selector: selector(r#"meta[property="og:image"]"#)?,
+            tag_selector: config.tag_selector.as_deref().map(selector).transpose()?,
+            fields: config.fields
+                .iter()
+                .map(|(name, css)| Ok((name.clone(), selector(css)?)))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl PageParser for DetailParser {
+    fn parse_page(&self, html: &str) -> Result<Vec<(String, String)>> {
+        self.list.parse_page(html)
+    }
+    
+    fn base_url(&self) -> &str {
+        self.list.base_url()
+    }
+    
+    fn follows_detail_pages(&self) -> bool {
+        true
+    }
+    
+    fn parse_detail(&self, html: &str) -> Result<Option<String>> {
+        Ok(self.parse_detail_page(html)?.map(|detail| detail.image_url))
+    }
+    
+    fn parse_detail_page(&self, html: &str) -> Result<Option<DetailPage>> {
+        let document = Html::parse_document(html);
+        
+        let image = document
+            .select(&self.image_selector)
+            .find_map(|elem| image_source(&elem, &self.image_attr))
+            .or_else(|| {
+                document
+                    .select(&self.og_image_selector)
+                    .find_map(|meta| meta.value().attr("content").map(str::to_string))
+            });
+        let Some(image) = image else {
+            return Ok(None);
+        };
+        
+        let mut tags = Vec::new();
+        for tag in self.tag_selector.iter().flat_map(|selector| document.select(selector)) {
+            let text = tag.text().collect::<String>().trim().to_string();
+            if !text.is_empty() && !tags.contains(&text) {
+                tags.push(text);
+            }
+        }
+        
+        let extra = self.fields
+            .iter()
+            .filter_map(|(name, selector)| {
+                let value = document.select(selector).find_map(license_text)?;
+                Some((name.clone(), serde_json::Value::String(value)))
+            })
+            .collect();
+        
+        Ok(Some(DetailPage {
+            image_url: normalize_url(&image, self.list.base_url()),
+            tags,
+            extra,
+        }))
+    }
+    
+    fn pagination(&self) -> Pagination {
+        self.list.pagination()
+    }
+    
+    fn page_hint(&self, html: &str) -> Result<PageHint> {
+        self.list.page_hint(html)
+    }
+}
+
+/// JSON API Parser 配置（`crawl --api <config.json>`）
+///
+/// 路徑可寫成 `data.memes`、`data.children[0].url`（開頭的 `$.` 可省略），
+/// 或 JSON Pointer 形式的 `/data/memes`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonApiConfig {
+    /// 分頁 API 網址（`{page}` 代入頁碼）
+    pub url_template: String,
+    /// 項目陣列的路徑（空白表示回應本身就是陣列）
+    #[serde(default)]
+    pub items_path: String,
+    /// 圖片網址欄位（相對於項目）
+    pub image_field: String,
+    /// 名稱欄位（相對於項目）
+    #[serde(default)]
+    pub name_field: Option<String>,
+    /// 授權欄位（相對於項目）
+    #[serde(default)]
+    pub license_field: Option<String>,
+    /// 是否還有下一頁的欄位（相對於回應，例如 `has_more`）
+    #[serde(default)]
+    pub has_more_field: Option<String>,
+    /// 總頁數欄位（相對於回應，例如 `meta.total_pages`）
+    #[serde(default)]
+    pub total_pages_field: Option<String>,
+}
+
+impl JsonApiConfig {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取 API 設定: {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("無法解析 API 設定: {}", path))
+    }
+}
+
+/// 分頁端點回傳 JSON 的網站用的 Parser
+pub struct JsonApiParser {
+    base_url: String,
+    config: JsonApiConfig,
+}
+
+impl JsonApiParser {
+    pub fn new(config: JsonApiConfig) -> Result<Self> {
+        let url = reqwest::Url::parse(&config.url_template.replace("{page}", "1"))
+            .with_context(|| format!("無效的 API 網址: {}", config.url_template))?;
+        
+        Ok(Self {
+            base_url: url.origin().ascii_serialization(),
+            config,
+        })
+    }
+    
+    /// API 網址樣板（`{page}` 代入頁碼）
+    pub fn url_template(&self) -> &str {
+        &self.config.url_template
+    }
+    
+    /// 依 `items_path` 取出項目陣列
+    fn items(&self, body: &str) -> Result<Vec<serde_json::Value>> {
+        let root: serde_json::Value = serde_json::from_str(body).context("API 回應不是有效的 JSON")?;
+        
+        match json_path(&root, &self.config.items_path) {
+            Some(serde_json::Value::Array(items)) => Ok(items.clone()),
+            Some(_) => anyhow::bail!("{} 不是陣列", self.config.items_path),
+            // 最後一頁之後的回應常常沒有這個欄位，視為沒有項目
+            None => Ok(Vec::new()),
+        }
+    }
+    
+    /// 項目的圖片網址（沒有時為 None）
+    fn image_url(&self, item: &serde_json::Value) -> Option<String> {
+        json_text(item, &self.config.image_field).map(|url| normalize_url(&url, &self.base_url))
+    }
+}
+
+impl PageParser for JsonApiParser {
+    fn parse_page(&self, body: &str) -> Result<Vec<(String, String)>> {
+        let mut results = Vec::new();
+        
+        for item in self.items(body)? {
+            let Some(url) = self.image_url(&item) else {
+                continue;
+            };
+            let name = self.config.name_field
+                .as_deref()
+                .and_then(|field| json_text(&item, field))
+                .unwrap_or_else(|| "unknown".to_string());
+            results.push((url, name));
+        }
+        
+        Ok(results)
+    }
+    
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+    
+    fn parse_licenses(&self, body: &str) -> Result<HashMap<String, String>> {
+        let Some(field) = &self.config.license_field else {
+            return Ok(HashMap::new());
+        };
+        
+        Ok(self.items(body)?
+            .iter()
+            .filter_map(|item| Some((self.image_url(item)?, json_text(item, field)?)))
+            .collect())
+    }
+    
+    fn page_hint(&self, body: &str) -> Result<PageHint> {
+        let root: serde_json::Value = serde_json::from_str(body).context("API 回應不是有效的 JSON")?;
+        let field = |path: &Option<String>| path.as_deref().and_then(|path| json_path(&root, path));
+        
+        Ok(PageHint {
+            has_next: field(&self.config.has_more_field).and_then(|v| v.as_bool()),
+            last_page: field(&self.config.total_pages_field)
+                .and_then(|v| v.as_u64().or_else(|| v.as_str()?.parse().ok()))
+                .map(|n| n.min(u32::MAX as u64) as u32),
+        })
+    }
+}
+
+/// Regex Parser 配置（`crawl --regex <config.json>`）
+///
+/// 用於圖片資料藏在 inline `<script>` JSON 裡、CSS 選擇器拿不到的網站：
+///
+/// ```json
+/// {
+///   "url_template": "https://example.com/memes?page={page}",
+///   "scope": "<script id=\"__NEXT_DATA__\"[^>]*>(.*?)</script>",
+///   "pattern": "\"title\":\"(?P<title>[^\"]*)\",\"image\":\"(?P<url>[^\"]+)\""
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegexParserConfig {
+    /// 列表頁網址（`{page}` 代入頁碼）
+    pub url_template: String,
+    /// 每個符合的位置是一張圖片：具名群組 `url`（必要）、`title`、`license`
+    pub pattern: String,
+    /// 先用這個正規表示式的第一個群組縮小範圍（例如某個 `<script>` 的內容），空白表示整頁
+    ///
+    /// 範圍常跨越多行，`.` 也會符合換行。
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+impl RegexParserConfig {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取 regex 設定: {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("無法解析 regex 設定: {}", path))
+    }
+}
+
+/// 以正規表示式的擷取群組取出圖片網址與名稱的 Parser
+///
+/// 擷取到的值若是 JSON 字串內容（`https:\/\/...`、`\u0026`）會先解除跳脫。
+pub struct RegexParser {
+    base_url: String,
+    url_template: String,
+    pattern: Regex,
+    scope: Option<Regex>,
+}
+
+impl RegexParser {
+    pub fn new(config: RegexParserConfig) -> Result<Self> {
+        let url = reqwest::Url::parse(&config.url_template.replace("{page}", "1"))
+            .with_context(|| format!("無效的列表網址: {}", config.url_template))?;
+        let pattern = Regex::new(&config.pattern)
+            .with_context(|| format!("圖片正規表示式錯誤: {}", config.pattern))?;
+        if !pattern.capture_names().any(|name| name == Some("url")) {
+            anyhow::bail!("圖片正規表示式需要具名群組 (?P<url>...): {}", config.pattern);
+        }
+        let scope = config.scope
+            .as_deref()
+            .filter(|scope| !scope.is_empty())
+            .map(|scope| {
+                RegexBuilder::new(scope)
+                    .dot_matches_new_line(true)
+                    .build()
+                    .with_context(|| format!("範圍正規表示式錯誤: {}", scope))
+            })
+            .transpose()?;
+        
+        Ok(Self {
+            base_url: url.origin().ascii_serialization(),
+            url_template: config.url_template,
+            pattern,
+            scope,
+        })
+    }
+    
+    /// 列表網址樣板（`{page}` 代入頁碼）
+    pub fn url_template(&self) -> &str {
+        &self.url_template
+    }
+    
+    /// 依序取出 (網址, 名稱, 授權)，同一網址只取第一次出現
+    fn matches(&self, html: &str) -> Vec<(String, String, Option<String>)> {
+        let scopes: Vec<&str> = match &self.scope {
+            Some(scope) => scope
+                .captures_iter(html)
+                .filter_map(|caps| caps.get(1).or_else(|| caps.get(0)))
+                .map(|m| m.as_str())
+                .collect(),
+            None => vec![html],
+        };
+        
+        let mut seen = std::collections::HashSet::new();
+        let mut results = Vec::new();
+        for scope in scopes {
+            for caps in self.pattern.captures_iter(scope) {
+                let group = |name: &str| caps.name(name).map(|m| unescape_captured(m.as_str())).filter(|v| !v.is_empty());
+                let Some(url) = group("url") else {
+                    continue;
+                };
+                let url = normalize_url(&url, &self.base_url);
+                if !seen.insert(url.clone()) {
+                    continue;
+                }
+                let name = group("title").unwrap_or_else(|| "unknown".to_string());
+                results.push((url, name, group("license")));
+            }
+        }
+        results
+    }
+}
+
+impl PageParser for RegexParser {
+    fn parse_page(&self, html: &str) -> Result<Vec<(String, String)>> {
+        Ok(self.matches(html).into_iter().map(|(url, name, _)| (url, name)).collect())
+    }
+    
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+    
+    fn parse_licenses(&self, html: &str) -> Result<HashMap<String, String>> {
+        Ok(self.matches(html)
+            .into_iter()
+            .filter_map(|(url, _, license)| Some((url, license?)))
+            .collect())
+    }
+}
+
+/// 擷取值的跳脫：JSON 字串（`\/`、`\uXXXX`、`\"`）與常見的 HTML 實體
+fn unescape_captured(raw: &str) -> String {
+    let text = if raw.contains('\\') {
+        serde_json::from_str::<String>(&format!("\"{}\"", raw)).unwrap_or_else(|_| raw.replace("\\/", "/"))
+    } else {
+        raw.to_string()
+    };
+    
+    text.replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .trim()
+        .to_string()
+}
+
+/// 依路徑取值（`a.b[0].c`、`$.a.b` 或 JSON Pointer `/a/b/0`）
+fn json_path<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    if path.starts_with('/') {
+        return value.pointer(path);
+    }
+    
+    let path = path.strip_prefix('$').unwrap_or(path).trim_start_matches('.');
+    let mut current = value;
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        // name[0][1]：先取欄位再依序取索引
+        let (name, indexes) = match segment.find('[') {
+            Some(pos) => segment.split_at(pos),
+            None => (segment, ""),
+        };
+        if !name.is_empty() {
+            current = current.get(name)?;
+        }
+        for index in indexes.split(['[', ']']).filter(|s| !s.is_empty()) {
+            // `[*]` 表示整個陣列（項目路徑的結尾）
+            if index == "*" {
+                continue;
+            }
+            current = current.get(index.parse::<usize>().ok()?)?;
+        }
+    }
+    Some(current)
+}
+
+/// 依路徑取文字（數字轉成字串，空字串視為沒有）
+fn json_text(value: &serde_json::Value, path: &str) -> Option<String> {
+    let text = match json_path(value, path)? {
+        serde_json::Value::String(s) => s.trim().to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        _ => return None,
+    };
+    (!text.is_empty()).then_some(text)
 }
 
 /// 正規化 URL（處理相對路徑）
-fn normalize_url(url: &str, base_url: &str) -> String {
+pub(crate) fn normalize_url(url: &str, base_url: &str) -> String {
     if url.starts_with("http://") || url.starts_with("https://") {
         url.to_string()
     } else if url.starts_with("//") {
@@ -215,6 +1128,30 @@ mod tests {
         assert!(results[0].0.contains("test1.jpg"));
     }
     
+    #[test]
+    fn test_imgflip_parser() {
+        let list = r#"
+        <div class="mt-box">
+            <h3 class="mt-title"><a title="Drake Hotline Bling Meme" href="/meme/Drake-Hotline-Bling">Drake Hotline Bling</a></h3>
+            <div class="mt-img-wrap"><a href="/meme/Drake-Hotline-Bling"><img class="shadow" src="data:image/gif;base64,R0lGOD" data-src="//i.imgflip.com/4/30b1gx.jpg" /></a></div>
+        </div>
+        "#;
+        
+        let parser = ImgflipParser::new().unwrap();
+        let items = parser.parse_page(list).unwrap();
+        assert!(parser.follows_detail_pages());
+        assert_eq!(items, vec![(
+            "https://imgflip.com/meme/Drake-Hotline-Bling".to_string(),
+            "Drake Hotline Bling".to_string(),
+        )]);
+        
+        let detail = r#"<img id="mtm-img" src="/s/loading.gif" data-src="//i.imgflip.com/30b1gx.jpg" />"#;
+        assert_eq!(parser.parse_detail(detail).unwrap().as_deref(), Some("https://i.imgflip.com/30b1gx.jpg"));
+        
+        let og_only = r#"<head><meta property="og:image" content="https://i.imgflip.com/1bij.jpg" /></head>"#;
+        assert_eq!(parser.parse_detail(og_only).unwrap().as_deref(), Some("https://i.imgflip.com/1bij.jpg"));
+    }
+    
     #[test]
     fn test_generic_parser() {
         let html = r#"
@@ -230,6 +1167,9 @@ mod tests {
             image_attr: "data-src".to_string(),
             name_selector: "h2.title".to_string(),
             name_extraction: NameExtraction::TextContent,
+            license_selector: None,
+            all_images: false,
+            source_kind: SourceKind::Attribute,
         };
         
         let parser = GenericParser::new("https://example.com".to_string(), config);
@@ -238,5 +1178,257 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].1, "圖片標題");
         assert_eq!(results[0].0, "https://example.com/photo.jpg");
+        assert!(parser.parse_licenses(html).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_lazy_load_and_srcset() {
+        let html = r#"
+        <div class="item">
+            <h2>Gallery</h2>
+            <img src="data:image/gif;base64,R0lGODlhAQABAAAAACw=" data-src="/lazy.jpg" />
+            <img src="/small.jpg" srcset="/small.jpg 320w, /large.jpg 1280w, /medium.jpg 640w" />
+            <img src="data:image/gif;base64,R0lGODlhAQABAAAAACw=" data-srcset="/a.png 1x, /a@2x.png 2x" />
+            <img src="/plain.jpg" />
+            <img src="/plain.jpg" />
+        </div>
+        "#;
+        let config = |all_images| ParserConfig {
+            container_selector: "div.item".to_string(),
+            image_selector: "img".to_string(),
+            image_attr: "src".to_string(),
+            name_selector: "h2".to_string(),
+            name_extraction: NameExtraction::TextContent,
+            license_selector: None,
+            all_images,
+            source_kind: SourceKind::Attribute,
+        };
+        
+        let parser = GenericParser::new("https://example.com".to_string(), config(true));
+        let urls: Vec<_> = parser.parse_page(html).unwrap().into_iter().map(|(url, _)| url).collect();
+        assert_eq!(urls, vec![
+            "https://example.com/lazy.jpg",
+            "https://example.com/large.jpg",
+            "https://example.com/a@2x.png",
+            "https://example.com/plain.jpg",
+        ]);
+        
+        // 只取第一張時也略過佔位圖
+        let parser = GenericParser::new("https://example.com".to_string(), config(false));
+        assert_eq!(parser.parse_page(html).unwrap(), vec![
+            ("https://example.com/lazy.jpg".to_string(), "Gallery".to_string()),
+        ]);
+        
+        assert_eq!(best_srcset_candidate("a.jpg 2x, b.jpg 300w, c.jpg"), Some("b.jpg".to_string()));
+        assert_eq!(best_srcset_candidate("data:image/png;base64,AAAA 1x"), None);
+        assert_eq!(best_srcset_candidate("data:image/png;base64,AAAA 2x, real.jpg 1x"), Some("real.jpg".to_string()));
+    }
+    
+    #[test]
+    fn test_style_background() {
+        let html = r#"
+        <div class="tile">
+            <a class="thumb" style="width: 200px; background-image: url('/bg/doge.jpg')"></a>
+            <span>Doge</span>
+        </div>
+        <div class="tile">
+            <a class="thumb" style="background: #000 url(&quot;https://cdn.example.com/cat.png&quot;) no-repeat center"></a>
+            <span>Cat</span>
+        </div>
+        <div class="tile">
+            <a class="thumb" style="background-image: url(data:image/gif;base64,R0lGOD)"></a>
+            <span>Placeholder</span>
+        </div>
+        "#;
+        let config = ParserConfig {
+            container_selector: "div.tile".to_string(),
+            image_selector: "a.thumb".to_string(),
+            image_attr: "src".to_string(),
+            all_images: false,
+            source_kind: SourceKind::StyleBackground,
+            name_selector: "span".to_string(),
+            name_extraction: NameExtraction::TextContent,
+            license_selector: None,
+        };
+        
+        let parser = GenericParser::new("https://example.com".to_string(), config);
+        assert_eq!(parser.parse_page(html).unwrap(), vec![
+            ("https://example.com/bg/doge.jpg".to_string(), "Doge".to_string()),
+            ("https://cdn.example.com/cat.png".to_string(), "Cat".to_string()),
+        ]);
+        assert_eq!(background_image_url("color: red"), None);
+    }
+    
+    #[test]
+    fn test_selector_config_and_diff() {
+        let config: SelectorSiteConfig = serde_json::from_value(serde_json::json!({
+            "url_template": "https://example.com/gallery?page={page}",
+            "container_selector": "div.card",
+            "image_selector": "img",
+            "name_selector": "img",
+            "name_extraction": { "attribute": "alt" },
+            "next_selector": "a.next",
+        })).unwrap();
+        let parser = config.parser().unwrap();
+        assert_eq!(parser.base_url(), "https://example.com");
+        assert_eq!(parser.pagination(), Pagination::NextLink("a.next".to_string()));
+        
+        let html = r#"
+        <div class="card"><img src="/a.jpg" alt="A"></div>
+        <div class="card"><img src="/b.jpg" alt="B"></div>
+        <div class="card"><img data-src="/c.jpg" alt="C"></div>
+        "#;
+        let new_items = parser.parse_page(html).unwrap();
+        let old_items = vec![
+            ("https://example.com/a.jpg".to_string(), "A".to_string()),
+            ("https://example.com/b.jpg".to_string(), "unknown".to_string()),
+            ("https://example.com/old.jpg".to_string(), "Old".to_string()),
+        ];
+        
+        let diff = ParseDiff::compare(&old_items, &new_items);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.removed, vec![("https://example.com/old.jpg".to_string(), "Old".to_string())]);
+        assert_eq!(diff.added, vec![("https://example.com/c.jpg".to_string(), "C".to_string())]);
+        assert_eq!(diff.renamed, vec![(
+            "https://example.com/b.jpg".to_string(),
+            "unknown".to_string(),
+            "B".to_string(),
+        )]);
+        assert!(ParseDiff::compare(&new_items, &new_items).is_empty());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_license_selector() {
+        let html = r#"
+        <div class="item">
+            <img class="photo" src="/a.jpg" />
+            <span class="license">CC BY-SA 4.0</span>
+        </div>
+        <div class="item">
+            <img class="photo" src="/b.jpg" />
+        </div>
+        <footer><a rel="license" href="https://creativecommons.org/licenses/by/4.0/"></a></footer>
+        "#;
+        
+        let config = ParserConfig {
+            container_selector: "div.item".to_string(),
+            image_selector: "img.photo".to_string(),
+            image_attr: "src".to_string(),
+            name_selector: "h2".to_string(),
+            name_extraction: NameExtraction::TextContent,
+            license_selector: Some("span.license, a[rel=license]".to_string()),
+            all_images: false,
+            source_kind: SourceKind::Attribute,
+        };
+        
+        let parser = GenericParser::new("https://example.com".to_string(), config);
+        let licenses = parser.parse_licenses(html).unwrap();
+        
+        // 容器內的授權優先，沒有時用頁尾的授權連結
+        assert_eq!(licenses["https://example.com/a.jpg"], "CC BY-SA 4.0");
+        assert_eq!(licenses["https://example.com/b.jpg"], "https://creativecommons.org/licenses/by/4.0/");
+    }
+    
+    #[test]
+    fn test_regex_parser() {
+        let config: RegexParserConfig = serde_json::from_value(serde_json::json!({
+            "url_template": "https://example.com/memes?page={page}",
+            "scope": r#"<script id="__NEXT_DATA__"[^>]*>(.*?)</script>"#,
+            "pattern": r#""title":"(?P<title>[^"]*)","image":"(?P<url>[^"]+)"(?:,"license":"(?P<license>[^"]*)")?"#,
+        })).unwrap();
+        let parser = RegexParser::new(config).unwrap();
+        
+        let html = r#"
+        <script>var ignored = {"title":"外面","image":"/outside.jpg"};</script>
+        <script id="__NEXT_DATA__" type="application/json">{"items":[
+            {"title":"Doge","image":"https:\/\/cdn.example.com\/doge.jpg?a=1\u0026b=2","license":"CC0"},
+            {"title":"Caf\u00e9","image":"/img/cafe.png"},
+            {"title":"重複","image":"/img/cafe.png"}
+        ]}</script>
+        "#;
+        assert_eq!(parser.parse_page(html).unwrap(), vec![
+            ("https://cdn.example.com/doge.jpg?a=1&b=2".to_string(), "Doge".to_string()),
+            ("https://example.com/img/cafe.png".to_string(), "Café".to_string()),
+        ]);
+        
+        let licenses = parser.parse_licenses(html).unwrap();
+        assert_eq!(licenses.len(), 1);
+        assert_eq!(licenses["https://cdn.example.com/doge.jpg?a=1&b=2"], "CC0");
+        
+        let missing_url: RegexParserConfig = serde_json::from_value(serde_json::json!({
+            "url_template": "https://example.com/?page={page}",
+            "pattern": "src=\"([^\"]+)\"",
+        })).unwrap();
+        assert!(RegexParser::new(missing_url).is_err());
+    }
+    
+    #[test]
+    fn test_json_api_parser() {
+        let config: JsonApiConfig = serde_json::from_value(serde_json::json!({
+            "url_template": "https://api.example.com/v1/memes?page={page}",
+            "items_path": "$.data.memes[*]",
+            "image_field": "images[0].url",
+            "name_field": "title",
+            "license_field": "/meta/license",
+        })).unwrap();
+        let parser = JsonApiParser::new(config).unwrap();
+        assert_eq!(parser.base_url(), "https://api.example.com");
+        
+        let body = r#"{"data": {"memes": [
+            {"title": "Doge", "images": [{"url": "https://cdn.example.com/doge.jpg"}], "meta": {"license": "CC-BY"}},
+            {"title": 42, "images": [{"url": "/img/42.png"}]},
+            {"title": "沒有圖片", "images": []}
+        ]}}"#;
+        let items = parser.parse_page(body).unwrap();
+        assert_eq!(items, vec![
+            ("https://cdn.example.com/doge.jpg".to_string(), "Doge".to_string()),
+            ("https://api.example.com/img/42.png".to_string(), "42".to_string()),
+        ]);
+        
+        let licenses = parser.parse_licenses(body).unwrap();
+        assert_eq!(licenses.len(), 1);
+        assert_eq!(licenses["https://cdn.example.com/doge.jpg"], "CC-BY");
+        
+        assert_eq!(parser.page_hint(body).unwrap(), PageHint::default());
+        
+        let paged: JsonApiConfig = serde_json::from_value(serde_json::json!({
+            "url_template": "https://api.example.com/v1/memes?page={page}",
+            "image_field": "url",
+            "has_more_field": "has_more",
+            "total_pages_field": "meta.total_pages",
+        })).unwrap();
+        let hint = JsonApiParser::new(paged).unwrap()
+            .page_hint(r#"{"has_more": false, "meta": {"total_pages": "7"}}"#)
+            .unwrap();
+        assert_eq!(hint, PageHint { has_next: Some(false), last_page: Some(7) });
+        
+        // 最後一頁之後沒有項目；不是 JSON 時回傳錯誤
+        assert!(parser.parse_page(r#"{"data": {}}"#).unwrap().is_empty());
+        assert!(parser.parse_page("<html></html>").is_err());
+    }
+    
+    #[test]
+    fn test_pagination() {
+        let html = r#"
+        <ul class="pagination">
+            <li><a href="?page=1">1</a></li>
+            <li><a href="?page=2">2</a></li>
+            <li><a href="/list?page=1594&sort=new">最後一頁</a></li>
+            <li><a rel="next" href="?page=2">下一頁</a></li>
+        </ul>
+        "#;
+        
+        let max_page = Pagination::MaxPage("ul.pagination a".to_string());
+        assert_eq!(max_page.detect(html).unwrap().last_page, Some(1594));
+        
+        let next = Pagination::NextLink(r#"a[rel="next"]"#.to_string());
+        assert_eq!(next.detect(html).unwrap().has_next, Some(true));
+        assert_eq!(next.detect("<p>最後一頁</p>").unwrap().has_next, Some(false));
+        
+        assert_eq!(Pagination::None.detect(html).unwrap(), PageHint::default());
+        assert!(Pagination::NextLink("a[".to_string()).detect(html).is_err());
+        
+        let parser = GenericParser::memes_tw().unwrap().with_pagination(next);
+        assert_eq!(parser.page_hint(html).unwrap().has_next, Some(true));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::crawler::CrawlerEngine;
+use crate::context::{files, DataContext};
+use crate::dedup::DedupAnalyzer;
+use crate::reverse_search::ReverseSearchEngine;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 流程階段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStage {
+    Crawl,
+    Dedup,
+    Search,
+}
+
+impl PipelineStage {
+    pub const ALL: [PipelineStage; 3] = [Self::Crawl, Self::Dedup, Self::Search];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Crawl => "爬取",
+            Self::Dedup => "去重",
+            Self::Search => "反向搜尋",
+        }
+    }
+}
+
+/// 流程狀態（pipeline_state.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineState {
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// 已完成的階段
+    pub completed: Vec<PipelineStage>,
+    /// 全部完成的時間
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl PipelineState {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            updated_at: Utc::now(),
+            completed: vec![],
+            finished_at: None,
+        }
+    }
+
+    pub fn is_completed(&self, stage: PipelineStage) -> bool {
+        self.completed.contains(&stage)
+    }
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 爬取 → 去重 → 反向搜尋 一次執行完
+///
+/// 每個階段本身都可續傳（progress.json / search_progress.json），
+/// 流程狀態只記錄哪些階段已完成；中斷後重新執行會從未完成的階段繼續。
+pub struct Pipeline {
+    context: Arc<DataContext>,
+    state_file: String,
+    crawler: CrawlerEngine,
+    dedup: DedupAnalyzer,
+    /// None 表示略過搜尋階段
+    search: Option<ReverseSearchEngine>,
+    /// 去重階段是否實際刪除重複圖片（預設只標記）
+    remove_duplicates: bool,
+    /// 刪除的檔案數上限，超過時停止流程（None 表示不限制）
+    max_delete: Option<usize>,
+}
+
+impl Pipeline {
+    pub fn new(
+        context: Arc<DataContext>,
+        crawler: CrawlerEngine,
+        dedup: DedupAnalyzer,
+        search: Option<ReverseSearchEngine>,
+    ) -> Self {
+        Self {
+            state_file: context.path(files::PIPELINE_STATE),
+            context,
+            crawler,
+            dedup,
+            search,
+            remove_duplicates: false,
+            max_delete: None,
+        }
+    }
+
+    /// 去重階段實際刪除重複圖片
+    pub fn with_remove_duplicates(mut self, remove: bool) -> Self {
+        self.remove_duplicates = remove;
+        self
+    }
+
+    /// 去重階段刪除的檔案數上限（影響超過時不刪除並停止流程）
+    pub fn with_max_delete(mut self, max_delete: Option<usize>) -> Self {
+        self.max_delete = max_delete;
+        self
+    }
+
+    /// 讀取流程狀態（上一輪已全部完成時開始新的一輪）
+    pub fn load_state(&self) -> Result<PipelineState> {
+        if !Path::new(&self.state_file).exists() {
+            return Ok(PipelineState::new());
+        }
+
+        let state: PipelineState = serde_json::from_str(&fs::read_to_string(&self.state_file)?)
+            .context("無法解析 pipeline_state.json")?;
+
+        if state.finished_at.is_some() {
+            return Ok(PipelineState::new());
+        }
+        Ok(state)
+    }
+
+    fn save_state(&self, state: &mut PipelineState) -> Result<()> {
+        state.updated_at = Utc::now();
+
+        let temp_path = format!("{}.tmp", self.state_file);
+        fs::write(&temp_path, serde_json::to_string_pretty(state)?)?;
+        fs::rename(&temp_path, &self.state_file)?;
+        Ok(())
+    }
+
+    pub async fn run(&self) -> Result<()> {
+        let mut state = self.load_state()?;
+        self.save_state(&mut state)?;
+
+        for (i, stage) in PipelineStage::ALL.iter().enumerate() {
+            out!("\n━━━━━━━━ [{}/{}] {} ━━━━━━━━\n", i + 1, PipelineStage::ALL.len(), stage.label());
+
+            if state.is_completed(*stage) {
+                out!("✅ 已完成，略過");
+                continue;
+            }
+
+            let done = match stage {
+                PipelineStage::Crawl => {
+                    self.crawler.run().await?;
+                    // 爬蟲直接寫入 metadata 後端，之後的階段要重新讀取
+                    self.context.invalidate();
+                    self.crawler.is_complete().await?
+                }
+                PipelineStage::Dedup => {
+                    let result = self.dedup.analyze()?;
+                    result.print_report();
+                    self.dedup.mark_duplicates(&result)?;
+                    if self.remove_duplicates {
+                        let impact = self.dedup.impact(&result)?;
+                        impact.print();
+                        impact.save(self.context.root())?;
+                        impact.check_limit(self.max_delete)?;
+                        self.dedup.remove_duplicates(&result, false)?;
+                    }
+                    true
+                }
+                PipelineStage::Search => match &self.search {
+                    Some(search) => {
+                        search.run().await?;
+                        search.pending_count()? == 0
+                    }
+                    None => {
+                        out!("⏭️  未設定搜尋服務，略過");
+                        true
+                    }
+                },
+            };
+
+            if !done {
+                self.save_state(&mut state)?;
+                out!("\n⏸️  {}階段未完成，流程已暫停；重新執行 pipeline 會從這裡繼續", stage.label());
+                return Ok(());
+            }
+
+            state.completed.push(*stage);
+            self.save_state(&mut state)?;
+        }
+
+        state.finished_at = Some(Utc::now());
+        self.save_state(&mut state)?;
+
+        let elapsed = Utc::now() - state.started_at;
+        out!("\n╔══════════════════════════════════╗");
+        out!("║       ✨ 流程完成               ║");
+        out!("╠══════════════════════════════════╣");
+        out!("║ 階段:     {:>20} ║", "爬取 → 去重 → 搜尋");
+        out!("║ 總耗時:   {:>18}分 ║", elapsed.num_minutes());
+        out!("╚══════════════════════════════════╝");
+
+        Ok(())
+    }
+}
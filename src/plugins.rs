@@ -0,0 +1,501 @@
+//! 外部程式實作的網站 Parser 與反向搜尋服務（不需要重新編譯）
+//!
+//! 外掛是常駐的子程式，透過 stdin/stdout 以 JSONL 溝通：每個請求一行
+//! `{"id": 1, "method": "parse_page", "params": {...}}`，外掛回一行
+//! `{"id": 1, "result": ...}` 或 `{"id": 1, "error": "訊息"}`。
+//!
+//! | method              | params                 | result                                          |
+//! |---------------------|------------------------|-------------------------------------------------|
+//! | `parse_page`        | `html`                 | `[{"url", "name"}]`                             |
+//! | `parse_licenses`    | `html`                 | `{"<圖片網址>": "<授權>"}`                       |
+//! | `page_hint`         | `html`                 | `{"has_next": bool?, "last_page": N?}`           |
+//! | `parse_detail_page` | `html`                 | `{"image_url", "tags", "extra"}` 或 `null`      |
+//! | `search`            | `metadata`、`path`?    | `{"suggested_title", "keywords", "related_sites", "best_guess", "similarity"?}` |
+//! | `embed`             | `path`                 | `[0.12, -0.5, ...]`（`cluster --model`）         |
+//! | `translate`         | `texts`、`target`      | `["譯文", null, ...]`（`search --translate`）    |
+//!
+//! 外掛在資料目錄的 `plugins.json` 登記；只需實作自己宣告支援的 method。
+//! 回應的 `id` 必須與請求相同；超過 `timeout_secs`（預設 60 秒）沒有回應時
+//! 結束外掛，下次呼叫重新啟動。
+
+use crate::parser::{normalize_url, DetailPage, PageHint, PageParser};
+use crate::reverse_search::{ReverseSearchResult, ReverseSearchService};
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 外掛登記檔
+pub const PLUGINS_FILE: &str = "plugins.json";
+
+/// 外掛請求的預設逾時
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// `plugins.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginsConfig {
+    pub parsers: Vec<ParserPluginConfig>,
+    pub services: Vec<ServicePluginConfig>,
+}
+
+/// 網站 Parser 外掛
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParserPluginConfig {
+    pub name: String,
+    /// 啟動外掛的指令（以空白分隔參數）
+    pub command: String,
+    /// 列表頁網址，`{page}` 代入頁碼
+    pub url_template: String,
+    /// 補齊相對網址用（預設取 url_template 的 origin）
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// `parse_page` 回傳的是詳細頁網址，要再呼叫 `parse_detail_page`
+    #[serde(default)]
+    pub detail_pages: bool,
+    /// 支援 `parse_licenses`
+    #[serde(default)]
+    pub licenses: bool,
+    /// 支援 `page_hint`
+    #[serde(default)]
+    pub page_hint: bool,
+    /// 每個請求的逾時（秒）
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+/// 反向搜尋服務外掛
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServicePluginConfig {
+    pub name: String,
+    pub command: String,
+    /// `search` 可接受本地檔案路徑（`params.path`）
+    #[serde(default)]
+    pub supports_upload: bool,
+    /// 建議的請求間隔（毫秒）
+    #[serde(default = "default_delay_ms")]
+    pub delay_ms: u64,
+    /// 每個請求的逾時（秒）
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_delay_ms() -> u64 {
+    1000
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT.as_secs()
+}
+
+impl PluginsConfig {
+    /// 讀取資料目錄的 plugins.json（不存在時沒有外掛）
+    pub fn load(data_dir: &str) -> Result<Self> {
+        let path = Path::new(data_dir).join(PLUGINS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(&fs::read_to_string(&path)?).context("無法解析 plugins.json")
+    }
+
+    pub fn parser(&self, name: &str) -> Option<&ParserPluginConfig> {
+        self.parsers.iter().find(|p| p.name == name)
+    }
+
+    pub fn service(&self, name: &str) -> Option<&ServicePluginConfig> {
+        self.services.iter().find(|s| s.name == name)
+    }
+}
+
+struct Running {
+    child: Child,
+    stdin: ChildStdin,
+    /// 讀取 stdout 的執行緒逐行送來（外掛結束時關閉）
+    lines: Receiver<std::io::Result<String>>,
+}
+
+/// 常駐的外掛子程式（第一次呼叫時啟動，結束、逾時或通訊失敗時下次重新啟動）
+pub struct PluginProcess {
+    name: String,
+    command: String,
+    timeout: Duration,
+    running: Mutex<Option<Running>>,
+    next_id: Mutex<u64>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    id: Option<u64>,
+    #[serde(default)]
+    result: Value,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl PluginProcess {
+    pub fn new(name: &str, command: &str, timeout: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            timeout,
+            running: Mutex::new(None),
+            next_id: Mutex::new(0),
+        }
+    }
+
+    fn spawn(&self) -> Result<Running> {
+        let mut parts = self.command.split_whitespace();
+        let program = parts.next().with_context(|| format!("外掛 {} 沒有設定指令", self.name))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("無法啟動外掛 {}: {}", self.name, self.command))?;
+
+        let stdin = child.stdin.take().context("無法取得外掛的 stdin")?;
+        let stdout = BufReader::new(child.stdout.take().context("無法取得外掛的 stdout")?);
+
+        // 另開執行緒讀取，呼叫端才能設定逾時
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in stdout.lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Running { child, stdin, lines })
+    }
+
+    /// 送出一個請求並等待回應（同一個外掛的請求依序處理）
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id
+        };
+
+        let mut running = self.running.lock().unwrap();
+        let exited = match running.as_mut() {
+            Some(process) => process.child.try_wait()?.is_some(),
+            None => true,
+        };
+        if exited {
+            *running = Some(self.spawn()?);
+        }
+        let process = running.as_mut().unwrap();
+
+        let request = json!({ "id": id, "method": method, "params": params });
+        let exchange = (|| -> Result<Response> {
+            writeln!(process.stdin, "{}", request)?;
+            process.stdin.flush()?;
+            let line = match process.lines.recv_timeout(self.timeout) {
+                Ok(line) => line?,
+                Err(RecvTimeoutError::Timeout) => anyhow::bail!("超過 {} 秒沒有回應", self.timeout.as_secs_f32()),
+                Err(RecvTimeoutError::Disconnected) => anyhow::bail!("外掛已結束"),
+            };
+            let response: Response = serde_json::from_str(&line)
+                .with_context(|| format!("回應不是 JSON: {}", line.trim()))?;
+            if response.id != Some(id) {
+                anyhow::bail!("回應的 id 不符（預期 {}，收到 {:?}）", id, response.id);
+            }
+            Ok(response)
+        })();
+        let response = match exchange {
+            Ok(response) => response,
+            Err(e) => {
+                // 之後的回應無法對應請求，結束外掛，下次呼叫重新啟動
+                if let Some(mut process) = running.take() {
+                    let _ = process.child.kill();
+                    let _ = process.child.wait();
+                }
+                return Err(e).with_context(|| format!("外掛 {} 通訊失敗（{}）", self.name, method));
+            }
+        };
+
+        if let Some(error) = response.error {
+            anyhow::bail!("外掛 {} 回報錯誤（{}）: {}", self.name, method, error);
+        }
+        Ok(response.result)
+    }
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        if let Some(mut process) = self.running.lock().unwrap().take() {
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PluginItem {
+    url: String,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize, Default)]
+struct PluginPageHint {
+    #[serde(default)]
+    has_next: Option<bool>,
+    #[serde(default)]
+    last_page: Option<u32>,
+}
+
+/// 以外掛解析網站頁面
+pub struct PluginParser {
+    config: ParserPluginConfig,
+    base_url: String,
+    process: PluginProcess,
+}
+
+impl PluginParser {
+    pub fn new(config: ParserPluginConfig) -> Self {
+        let base_url = config.base_url.clone().unwrap_or_else(|| origin_of(&config.url_template));
+        Self {
+            process: PluginProcess::new(&config.name, &config.command, Duration::from_secs(config.timeout_secs)),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            config,
+        }
+    }
+
+    pub fn url_template(&self) -> &str {
+        &self.config.url_template
+    }
+}
+
+impl PageParser for PluginParser {
+    fn parse_page(&self, html: &str) -> Result<Vec<(String, String)>> {
+        let items: Vec<PluginItem> = serde_json::from_value(self.process.call("parse_page", json!({ "html": html }))?)
+            .with_context(|| format!("外掛 {} 的 parse_page 結果格式錯誤", self.config.name))?;
+        Ok(items
+            .into_iter()
+            .filter(|item| !item.url.is_empty())
+            .map(|item| (normalize_url(&item.url, &self.base_url), item.name))
+            .collect())
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn follows_detail_pages(&self) -> bool {
+        self.config.detail_pages
+    }
+
+    fn parse_detail_page(&self, html: &str) -> Result<Option<DetailPage>> {
+        if !self.config.detail_pages {
+            return Ok(None);
+        }
+        let detail: Option<DetailPage> = serde_json::from_value(self.process.call("parse_detail_page", json!({ "html": html }))?)
+            .with_context(|| format!("外掛 {} 的 parse_detail_page 結果格式錯誤", self.config.name))?;
+        Ok(detail.map(|mut detail| {
+            detail.image_url = normalize_url(&detail.image_url, &self.base_url);
+            detail
+        }))
+    }
+
+    fn parse_licenses(&self, html: &str) -> Result<HashMap<String, String>> {
+        if !self.config.licenses {
+            return Ok(HashMap::new());
+        }
+        let licenses: HashMap<String, String> = serde_json::from_value(self.process.call("parse_licenses", json!({ "html": html }))?)
+            .with_context(|| format!("外掛 {} 的 parse_licenses 結果格式錯誤", self.config.name))?;
+        Ok(licenses
+            .into_iter()
+            .map(|(url, license)| (normalize_url(&url, &self.base_url), license))
+            .collect())
+    }
+
+    fn page_hint(&self, html: &str) -> Result<PageHint> {
+        if !self.config.page_hint {
+            return Ok(PageHint::default());
+        }
+        let hint: PluginPageHint = serde_json::from_value(self.process.call("page_hint", json!({ "html": html }))?)
+            .unwrap_or_default();
+        Ok(PageHint {
+            has_next: hint.has_next,
+            last_page: hint.last_page,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct PluginSearchResult {
+    #[serde(default)]
+    suggested_title: Option<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    related_sites: Vec<String>,
+    #[serde(default)]
+    best_guess: Option<String>,
+    #[serde(default)]
+    similarity: Option<f64>,
+}
+
+/// 以外掛實作的反向搜尋服務
+pub struct PluginService {
+    config: ServicePluginConfig,
+    process: Arc<PluginProcess>,
+}
+
+impl PluginService {
+    pub fn new(config: ServicePluginConfig) -> Self {
+        Self {
+            process: Arc::new(PluginProcess::new(&config.name, &config.command, Duration::from_secs(config.timeout_secs))),
+            config,
+        }
+    }
+
+    async fn call_search(&self, metadata: &ImageMetadata, path: Option<&Path>) -> Result<ReverseSearchResult> {
+        let params = json!({
+            "metadata": metadata,
+            "path": path.map(|p| p.to_string_lossy().to_string()),
+        });
+        let process = Arc::clone(&self.process);
+        // 外掛以同步 IO 溝通，不佔用 async 執行緒
+        let value = tokio::task::spawn_blocking(move || process.call("search", params)).await??;
+        let result: PluginSearchResult = serde_json::from_value(value)
+            .with_context(|| format!("外掛 {} 的 search 結果格式錯誤", self.config.name))?;
+
+        Ok(ReverseSearchResult {
+            filename: metadata.filename.clone(),
+            service: self.config.name.clone(),
+            suggested_title: result.suggested_title,
+            keywords: result.keywords,
+            related_sites: result.related_sites,
+            best_guess: result.best_guess,
+            similarity: result.similarity,
+            searched_at: chrono::Utc::now(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ReverseSearchService for PluginService {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn search(&self, metadata: &ImageMetadata) -> Result<ReverseSearchResult> {
+        self.call_search(metadata, None).await
+    }
+
+    async fn search_by_upload(&self, path: &Path, metadata: &ImageMetadata) -> Result<ReverseSearchResult> {
+        self.call_search(metadata, Some(path)).await
+    }
+
+    fn supports_upload(&self) -> bool {
+        self.config.supports_upload
+    }
+
+    fn suggested_delay_ms(&self) -> u64 {
+        self.config.delay_ms
+    }
+}
+
+/// `https://example.com/list?page={page}` -> `https://example.com`
+fn origin_of(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return String::new();
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    format!("{}://{}", scheme, host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 依 method 回固定結果的外掛（`slow` 不回應，`stale` 回錯的 id）
+    fn write_plugin(dir: &Path) -> String {
+        let script = dir.join("plugin.sh");
+        fs::write(
+            &script,
+            r#"while read -r line; do
+  id=$(echo "$line" | sed 's/^{"id":\([0-9]*\).*/\1/')
+  case "$line" in
+    *'"parse_page"'*) echo "{\"id\":$id,\"result\":[{\"url\":\"/img/1.jpg\",\"name\":\"one\"},{\"url\":\"https://cdn.example.com/2.jpg\",\"name\":\"two\"}]}" ;;
+    *'"page_hint"'*) echo "{\"id\":$id,\"result\":{\"has_next\":false}}" ;;
+    *'"search"'*) echo "{\"id\":$id,\"result\":{\"best_guess\":\"Doge\",\"keywords\":[\"shiba\"]}}" ;;
+    *'"slow"'*) sleep 5 ;;
+    *'"stale"'*) echo '{"id":0,"result":null}' ;;
+    *) echo "{\"id\":$id,\"error\":\"unsupported\"}" ;;
+  esac
+done
+"#,
+        )
+        .unwrap();
+        format!("sh {}", script.display())
+    }
+
+    #[tokio::test]
+    async fn test_plugin_parser_and_service() {
+        let dir = std::env::temp_dir().join(format!("meme-plugin-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let command = write_plugin(&dir);
+
+        let parser = PluginParser::new(ParserPluginConfig {
+            name: "example".to_string(),
+            command: command.clone(),
+            url_template: "https://example.com/list?page={page}".to_string(),
+            base_url: None,
+            detail_pages: false,
+            licenses: false,
+            page_hint: true,
+            timeout_secs: 1,
+        });
+        assert_eq!(parser.parse_page("<html></html>").unwrap(), vec![
+            ("https://example.com/img/1.jpg".to_string(), "one".to_string()),
+            ("https://cdn.example.com/2.jpg".to_string(), "two".to_string()),
+        ]);
+        assert_eq!(parser.page_hint("").unwrap().has_next, Some(false));
+        assert!(parser.parse_licenses("").unwrap().is_empty());
+        let error = parser.process.call("unknown", json!({})).unwrap_err();
+        assert!(error.to_string().contains("unsupported"));
+
+        // 逾時或 id 不符時結束外掛，下次呼叫重新啟動
+        assert!(format!("{:#}", parser.process.call("slow", json!({})).unwrap_err()).contains("沒有回應"));
+        assert_eq!(parser.page_hint("").unwrap().has_next, Some(false));
+        assert!(format!("{:#}", parser.process.call("stale", json!({})).unwrap_err()).contains("id 不符"));
+        assert_eq!(parser.page_hint("").unwrap().has_next, Some(false));
+
+        let service = PluginService::new(ServicePluginConfig {
+            name: "example-search".to_string(),
+            command,
+            supports_upload: false,
+            delay_ms: 500,
+            timeout_secs: 1,
+        });
+        let metadata: ImageMetadata = serde_json::from_value(json!({
+            "filename": "a.jpg",
+            "description": "",
+            "url": "https://example.com/a.jpg",
+            "content_hash": "abc",
+            "page_number": 1,
+            "downloaded_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let result = service.search(&metadata).await.unwrap();
+        assert_eq!(result.service, "example-search");
+        assert_eq!(result.best_guess.as_deref(), Some("Doge"));
+        assert_eq!(result.keywords, vec!["shiba"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+/// 使用者層級的設定與共用資料目錄（~/.meme-crawler）
+pub fn app_root() -> Result<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .context("找不到使用者家目錄（HOME / USERPROFILE）")?;
+
+    Ok(PathBuf::from(home).join(".meme-crawler"))
+}
+
+/// 所有 profile 的根目錄（~/.meme-crawler/profiles）
+pub fn profiles_root() -> Result<PathBuf> {
+    Ok(app_root()?.join("profiles"))
+}
+
+/// 檢查 profile 名稱（只允許英數字、`-`、`_`，避免路徑穿越）
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !valid {
+        anyhow::bail!("profile 名稱只能包含英數字、'-' 與 '_': {}", name);
+    }
+
+    Ok(())
+}
+
+/// 取得 profile 的資料目錄（profile 必須已存在）
+pub fn profile_dir(name: &str) -> Result<String> {
+    validate_name(name)?;
+
+    let dir = profiles_root()?.join(name);
+    if !dir.is_dir() {
+        anyhow::bail!(
+            "profile 不存在: {}（請先執行 cargo run profile create {}）",
+            name,
+            name
+        );
+    }
+
+    Ok(dir.to_string_lossy().into_owned())
+}
+
+/// 列出所有 profile
+pub fn list_profiles() -> Result<Vec<String>> {
+    let root = profiles_root()?;
+    if !root.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&root).context("無法讀取 profiles 目錄")? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    names.sort();
+    Ok(names)
+}
+
+/// 建立 profile
+pub fn create_profile(name: &str) -> Result<PathBuf> {
+    validate_name(name)?;
+
+    let dir = profiles_root()?.join(name);
+    if dir.exists() {
+        anyhow::bail!("profile 已存在: {}", name);
+    }
+
+    fs::create_dir_all(dir.join("images"))
+        .with_context(|| format!("無法建立 profile 目錄: {}", dir.display()))?;
+
+    Ok(dir)
+}
+
+/// 刪除 profile（包含所有資料）
+pub fn delete_profile(name: &str) -> Result<()> {
+    validate_name(name)?;
+
+    let dir = profiles_root()?.join(name);
+    if !dir.is_dir() {
+        anyhow::bail!("profile 不存在: {}", name);
+    }
+
+    fs::remove_dir_all(&dir)
+        .with_context(|| format!("無法刪除 profile 目錄: {}", dir.display()))?;
+
+    Ok(())
+}
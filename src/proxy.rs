@@ -0,0 +1,332 @@
+use crate::client_pool::{self, ClientFactory, ConnectionStats};
+use crate::headers::HeaderRotator;
+use crate::rate_limit::{AdaptiveRateLimiter, HostTokenBucket};
+use anyhow::{Context, Result};
+use reqwest::{Client, ClientBuilder, RequestBuilder, Response, StatusCode};
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 代理輪替策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RotationStrategy {
+    /// 每個請求輪流使用下一個代理（預設）
+    #[default]
+    RoundRobin,
+    /// 每個請求隨機挑選
+    Random,
+    /// 持續使用同一個代理，被封鎖時才換下一個
+    Sticky,
+}
+
+impl RotationStrategy {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "round-robin" => Ok(Self::RoundRobin),
+            "random" => Ok(Self::Random),
+            "sticky" => Ok(Self::Sticky),
+            other => anyhow::bail!("未知的輪替策略: {}（可用: round-robin, random, sticky）", other),
+        }
+    }
+}
+
+/// 代理設定
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    /// 代理 URL（http:// 或 https://），空白表示直接連線
+    pub proxies: Vec<String>,
+    pub strategy: RotationStrategy,
+    /// 代理被封鎖（403/429/連線失敗）後暫停使用的秒數
+    pub cooldown_secs: u64,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            proxies: vec![],
+            strategy: RotationStrategy::default(),
+            cooldown_secs: 300,
+        }
+    }
+}
+
+impl ProxyConfig {
+    pub fn is_empty(&self) -> bool {
+        self.proxies.is_empty()
+    }
+
+    /// 讀取代理清單檔（每行一個 URL，`#` 開頭為註解）
+    pub fn load_list(path: &str) -> Result<Vec<String>> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("無法讀取代理清單: {}", path))?;
+
+        Ok(content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_string())
+            .collect())
+    }
+}
+
+/// 單一代理（或直接連線）的 client 與冷卻狀態
+struct ProxySlot {
+    proxy: Option<String>,
+    client: Client,
+    cooldown_until: Mutex<Option<Instant>>,
+}
+
+impl ProxySlot {
+    fn available_at(&self) -> Option<Instant> {
+        *self.cooldown_until.lock().unwrap()
+    }
+}
+
+/// 依策略輪替代理的 HTTP client 組
+///
+/// reqwest 的代理綁定在 `Client` 上，所以每個代理各建一個 client；
+/// 沒有設定代理時只有一個直接連線的 client。
+/// 設定限流器後，每個請求送出前依 host 等待，並回報成功/被限流。
+pub struct ProxyRotator {
+    slots: Vec<ProxySlot>,
+    strategy: RotationStrategy,
+    cooldown: Duration,
+    next: AtomicUsize,
+    /// 限流器與未知 host 的起始延遲（毫秒）
+    rate_limiter: Option<(Arc<AdaptiveRateLimiter>, u64)>,
+    /// 每個 host 的請求速率上限（與圖片下載共用）
+    token_bucket: Option<Arc<HostTokenBucket>>,
+    /// User-Agent 輪替與各網站的 headers
+    headers: Option<Arc<HeaderRotator>>,
+    /// 從 `ClientFactory` 共用 client 時的設定名稱（None 表示自己建立）
+    profile: Option<String>,
+}
+
+impl ProxyRotator {
+    /// `builder` 提供各服務自己的 client 設定（timeout、headers...），代理由這裡加上
+    pub fn new(config: &ProxyConfig, builder: impl Fn() -> ClientBuilder) -> Result<Self> {
+        Self::build(config, None, builder)
+    }
+
+    /// 同 `new`，但 client 從全域的 `ClientFactory` 取得，相同 `profile` 與代理的元件共用連線池
+    pub fn shared(config: &ProxyConfig, profile: &str, builder: impl Fn() -> ClientBuilder) -> Result<Self> {
+        Self::build(config, Some(profile.to_string()), builder)
+    }
+
+    fn build(config: &ProxyConfig, profile: Option<String>, builder: impl Fn() -> ClientBuilder) -> Result<Self> {
+        Ok(Self {
+            slots: build_slots(config, profile.as_deref(), builder)?,
+            strategy: config.strategy,
+            cooldown: Duration::from_secs(config.cooldown_secs),
+            next: AtomicUsize::new(0),
+            rate_limiter: None,
+            token_bucket: None,
+            headers: None,
+            profile,
+        })
+    }
+
+    /// 更換代理設定（保留限流器）
+    pub fn set_proxies(&mut self, config: &ProxyConfig, builder: impl Fn() -> ClientBuilder) -> Result<()> {
+        self.slots = build_slots(config, self.profile.as_deref(), builder)?;
+        self.strategy = config.strategy;
+        self.cooldown = Duration::from_secs(config.cooldown_secs);
+        self.next.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// 使用自適應限流器，`default_delay_ms` 為尚未學到延遲的 host 的起始值
+    pub fn set_rate_limiter(&mut self, limiter: Arc<AdaptiveRateLimiter>, default_delay_ms: u64) {
+        self.rate_limiter = Some((limiter, default_delay_ms));
+    }
+
+    /// 每個請求送出前先從 host 的 token bucket 取得 token
+    pub fn set_token_bucket(&mut self, bucket: Option<Arc<HostTokenBucket>>) {
+        self.token_bucket = bucket;
+    }
+
+    /// 每個請求送出前換上輪替的 User-Agent 與網站的 headers
+    pub fn set_headers(&mut self, headers: Option<Arc<HeaderRotator>>) {
+        self.headers = headers;
+    }
+
+    /// 代理數量（直接連線時為 0）
+    pub fn proxy_count(&self) -> usize {
+        self.slots.iter().filter(|s| s.proxy.is_some()).count()
+    }
+
+    /// 挑選下一個可用的代理；全部冷卻中時等到最早恢復的那個
+    pub async fn acquire(&self) -> (usize, &Client) {
+        loop {
+            match self.pick(Instant::now()) {
+                Ok(index) => return (index, &self.slots[index].client),
+                Err(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+
+    /// 依策略挑選不在冷卻中的代理，全部冷卻中時回傳需要等待的時間
+    fn pick(&self, now: Instant) -> std::result::Result<usize, Duration> {
+        let len = self.slots.len();
+        let start = match self.strategy {
+            RotationStrategy::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            RotationStrategy::Random => RandomState::new().build_hasher().finish() as usize,
+            RotationStrategy::Sticky => self.next.load(Ordering::Relaxed),
+        };
+
+        for offset in 0..len {
+            let index = (start + offset) % len;
+            if self.slots[index].available_at().is_none_or(|until| until <= now) {
+                if self.strategy == RotationStrategy::Sticky {
+                    self.next.store(index, Ordering::Relaxed);
+                }
+                return Ok(index);
+            }
+        }
+
+        let earliest = self.slots
+            .iter()
+            .filter_map(|s| s.available_at())
+            .min()
+            .unwrap_or(now);
+        Err(earliest.saturating_duration_since(now).max(Duration::from_millis(100)))
+    }
+
+    /// 標記代理被封鎖，冷卻一段時間（直接連線不冷卻）
+    pub fn mark_blocked(&self, index: usize) {
+        let slot = &self.slots[index];
+        let Some(proxy) = &slot.proxy else {
+            return;
+        };
+
+        *slot.cooldown_until.lock().unwrap() = Some(Instant::now() + self.cooldown);
+        eout!("    🚫 代理被封鎖，暫停 {} 秒: {}", self.cooldown.as_secs(), proxy);
+    }
+
+    /// 以挑選的代理送出請求；403/429 或連線失敗時將該代理冷卻
+    pub async fn send(&self, build: impl Fn(&Client) -> RequestBuilder) -> Result<Response> {
+        let (index, client) = self.acquire().await;
+        let mut request = build(client).build().context("無法建立請求")?;
+        if let Some(headers) = &self.headers {
+            headers.apply(&mut request);
+        }
+        let host = request.url().host_str().unwrap_or_default().to_string();
+
+        if let Some(bucket) = &self.token_bucket {
+            bucket.acquire(&host).await;
+        }
+        if let Some((limiter, default_delay_ms)) = &self.rate_limiter {
+            limiter.wait(&host, *default_delay_ms).await;
+        }
+
+        ConnectionStats::global().record_request();
+        match client.execute(request).await {
+            Ok(response) => {
+                let throttled = matches!(response.status(), StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS);
+                if throttled {
+                    self.mark_blocked(index);
+                }
+
+                if let Some((limiter, _)) = &self.rate_limiter {
+                    if throttled {
+                        limiter.on_throttled(&host, retry_after(&response));
+                    } else if response.status().is_success() {
+                        limiter.on_success(&host);
+                    }
+                }
+                Ok(response)
+            }
+            Err(e) => {
+                if e.is_connect() || e.is_timeout() {
+                    self.mark_blocked(index);
+                }
+                Err(e.into())
+            }
+        }
+    }
+}
+
+fn build_slots(config: &ProxyConfig, profile: Option<&str>, builder: impl Fn() -> ClientBuilder) -> Result<Vec<ProxySlot>> {
+    let mut slots = Vec::new();
+
+    if config.proxies.is_empty() {
+        let client = match profile {
+            Some(profile) => ClientFactory::global().client(profile, None, &builder)?,
+            None => client_pool::instrument(builder()).build().context("無法建立 HTTP 客戶端")?,
+        };
+        slots.push(ProxySlot {
+            proxy: None,
+            client,
+            cooldown_until: Mutex::new(None),
+        });
+    }
+
+    for url in &config.proxies {
+        let client = match profile {
+            Some(profile) => ClientFactory::global().client(profile, Some(url), &builder)?,
+            None => {
+                let proxy = reqwest::Proxy::all(url)
+                    .with_context(|| format!("無效的代理 URL: {}", url))?;
+                client_pool::instrument(builder()).proxy(proxy).build().context("無法建立 HTTP 客戶端")?
+            }
+        };
+        slots.push(ProxySlot {
+            proxy: Some(url.clone()),
+            client,
+            cooldown_until: Mutex::new(None),
+        });
+    }
+
+    Ok(slots)
+}
+
+/// 解析 Retry-After（只支援秒數）
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotator(strategy: RotationStrategy) -> ProxyRotator {
+        let config = ProxyConfig {
+            proxies: vec!["http://127.0.0.1:9001".to_string(), "http://127.0.0.1:9002".to_string()],
+            strategy,
+            cooldown_secs: 60,
+        };
+        ProxyRotator::new(&config, Client::builder).unwrap()
+    }
+
+    #[test]
+    fn test_rotation_and_cooldown() {
+        let now = Instant::now();
+
+        let rr = rotator(RotationStrategy::RoundRobin);
+        assert_eq!(rr.pick(now), Ok(0));
+        assert_eq!(rr.pick(now), Ok(1));
+        rr.mark_blocked(0);
+        assert_eq!(rr.pick(now), Ok(1));
+        assert_eq!(rr.pick(now), Ok(1));
+        rr.mark_blocked(1);
+        assert!(rr.pick(now).is_err());
+
+        let sticky = rotator(RotationStrategy::Sticky);
+        assert_eq!(sticky.pick(now), Ok(0));
+        assert_eq!(sticky.pick(now), Ok(0));
+        sticky.mark_blocked(0);
+        assert_eq!(sticky.pick(now), Ok(1));
+        assert_eq!(sticky.pick(now), Ok(1));
+    }
+}
@@ -0,0 +1,468 @@
+use crate::file_manager::FileManager;
+use crate::store::MetadataStore;
+use crate::trash::{TrashBatch, TrashEntry};
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// 刪除條件（例如 `page_number>1500 || age_days>=30 && tag==cat`）
+///
+/// 欄位來自圖片 metadata（`filename`、`page_number`、`downloaded_at`、下載時記錄的 `width`/`height`/`file_size`...），
+/// metadata 沒有的欄位再找 `extra`（例如 `nsfw_score`，也可以寫 `extra.nsfw_score`）。
+/// 另外提供虛擬欄位：
+/// - `age_days`：下載至今的天數
+/// - `tag`：圖片的任一標籤（來自反向搜尋關鍵字）
+///
+/// 運算子：`==` `!=` `>` `>=` `<` `<=` `~`（包含子字串），`&&` `||` `!` 與括號。
+/// 圖片沒有的欄位一律視為不符合。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Policy {
+    Compare {
+        field: String,
+        op: CompareOp,
+        value: Literal,
+    },
+    Not(Box<Policy>),
+    And(Box<Policy>, Box<Policy>),
+    Or(Box<Policy>, Box<Policy>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Number(f64),
+    Text(String),
+}
+
+/// 評估條件時需要的額外資料
+pub struct PruneContext {
+    pub now: DateTime<Utc>,
+    /// 檔名 -> 標籤
+    pub tags: HashMap<String, BTreeSet<String>>,
+}
+
+impl Policy {
+    /// 解析條件字串
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+
+        let policy = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            anyhow::bail!("條件語法錯誤：多餘的 {:?}", token);
+        }
+
+        Ok(policy)
+    }
+
+    /// 判斷圖片是否符合條件
+    pub fn matches(&self, metadata: &ImageMetadata, ctx: &PruneContext) -> bool {
+        let fields = serde_json::to_value(metadata).unwrap_or(Value::Null);
+        self.eval(metadata, &fields, ctx)
+    }
+
+    fn eval(&self, metadata: &ImageMetadata, fields: &Value, ctx: &PruneContext) -> bool {
+        match self {
+            Policy::Not(inner) => !inner.eval(metadata, fields, ctx),
+            Policy::And(a, b) => a.eval(metadata, fields, ctx) && b.eval(metadata, fields, ctx),
+            Policy::Or(a, b) => a.eval(metadata, fields, ctx) || b.eval(metadata, fields, ctx),
+            Policy::Compare { field, op, value } => match field.as_str() {
+                "age_days" => {
+                    let age = (ctx.now - metadata.downloaded_at).num_seconds() as f64 / 86_400.0;
+                    compare(&Value::from(age), *op, value)
+                }
+                "tag" => {
+                    let tags = ctx.tags.get(&metadata.filename);
+                    let check = |t: &String| compare(&Value::from(t.as_str()), *op, value);
+
+                    // `tag != x` 表示「沒有 x 這個標籤」
+                    if *op == CompareOp::Ne {
+                        tags.is_none_or(|tags| tags.iter().all(check))
+                    } else {
+                        tags.is_some_and(|tags| tags.iter().any(check))
+                    }
+                }
+                // 網站或偵測器記錄的欄位（nsfw_score、template_cluster...）在 extra 裡，
+                // 也可以寫成 `extra.<key>`
+                _ => {
+                    let extra = &fields["extra"];
+                    let actual = match field.strip_prefix("extra.") {
+                        Some(key) => extra.get(key),
+                        None => fields.get(field).or_else(|| extra.get(field)),
+                    };
+                    actual.is_some_and(|v| compare(v, *op, value))
+                }
+            },
+        }
+    }
+}
+
+fn compare(actual: &Value, op: CompareOp, expected: &Literal) -> bool {
+    match (actual, expected) {
+        (Value::Number(n), Literal::Number(e)) => {
+            let Some(n) = n.as_f64() else { return false };
+            match op {
+                CompareOp::Eq => n == *e,
+                CompareOp::Ne => n != *e,
+                CompareOp::Gt => n > *e,
+                CompareOp::Ge => n >= *e,
+                CompareOp::Lt => n < *e,
+                CompareOp::Le => n <= *e,
+                CompareOp::Contains => false,
+            }
+        }
+        (Value::String(s), expected) => {
+            let e = match expected {
+                Literal::Text(t) => t.clone(),
+                Literal::Number(n) => n.to_string(),
+            };
+            match op {
+                CompareOp::Eq => *s == e,
+                CompareOp::Ne => *s != e,
+                CompareOp::Gt => *s > e,
+                CompareOp::Ge => *s >= e,
+                CompareOp::Lt => *s < e,
+                CompareOp::Le => *s <= e,
+                CompareOp::Contains => s.contains(&e),
+            }
+        }
+        (Value::Bool(b), Literal::Text(t)) => match op {
+            CompareOp::Eq => b.to_string() == *t,
+            CompareOp::Ne => b.to_string() != *t,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Text(String),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => { tokens.push(Token::LParen); i += 1; }
+            ')' => { tokens.push(Token::RParen); i += 1; }
+            '&' if next == Some('&') => { tokens.push(Token::And); i += 2; }
+            '|' if next == Some('|') => { tokens.push(Token::Or); i += 2; }
+            '=' if next == Some('=') => { tokens.push(Token::Op(CompareOp::Eq)); i += 2; }
+            '!' if next == Some('=') => { tokens.push(Token::Op(CompareOp::Ne)); i += 2; }
+            '>' if next == Some('=') => { tokens.push(Token::Op(CompareOp::Ge)); i += 2; }
+            '<' if next == Some('=') => { tokens.push(Token::Op(CompareOp::Le)); i += 2; }
+            '!' => { tokens.push(Token::Not); i += 1; }
+            '>' => { tokens.push(Token::Op(CompareOp::Gt)); i += 1; }
+            '<' => { tokens.push(Token::Op(CompareOp::Lt)); i += 1; }
+            '~' => { tokens.push(Token::Op(CompareOp::Contains)); i += 1; }
+            '"' | '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == c)
+                    .with_context(|| format!("條件語法錯誤：字串缺少結尾的 {}", c))?;
+                tokens.push(Token::Text(chars[i + 1..i + 1 + end].iter().collect()));
+                i += end + 2;
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let number = text.parse()
+                    .map_err(|_| anyhow::anyhow!("條件語法錯誤：無效的數字 {}", text))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '-')) {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            other => anyhow::bail!("條件語法錯誤：無法識別的字元 '{}'", other),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Policy> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            left = Policy::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Policy> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            left = Policy::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Policy> {
+        match self.next() {
+            Some(Token::Not) => Ok(Policy::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let inner = self.parse_or()?;
+                if self.next() != Some(Token::RParen) {
+                    anyhow::bail!("條件語法錯誤：缺少 ')'");
+                }
+                Ok(inner)
+            }
+            Some(Token::Ident(field)) => {
+                let Some(Token::Op(op)) = self.next() else {
+                    anyhow::bail!("條件語法錯誤：{} 後面需要比較運算子", field);
+                };
+                let value = match self.next() {
+                    Some(Token::Number(n)) => Literal::Number(n),
+                    Some(Token::Text(t)) | Some(Token::Ident(t)) => Literal::Text(t),
+                    other => anyhow::bail!("條件語法錯誤：{} 後面需要值，得到 {:?}", field, other),
+                };
+                Ok(Policy::Compare { field, op, value })
+            }
+            other => anyhow::bail!("條件語法錯誤：預期欄位名稱，得到 {:?}", other),
+        }
+    }
+}
+
+/// 刪除紀錄（prune_log.jsonl）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PruneRecord {
+    pub pruned_at: DateTime<Utc>,
+    pub policy: String,
+    pub filename: String,
+    pub content_hash: String,
+    pub url: String,
+}
+
+/// 列出符合條件的圖片（同一檔名只列一次）
+pub fn plan_prune(
+    metadata_list: &[ImageMetadata],
+    policy: &Policy,
+    ctx: &PruneContext,
+) -> Vec<ImageMetadata> {
+    let mut seen = HashSet::new();
+
+    metadata_list
+        .iter()
+        .filter(|m| policy.matches(m, ctx))
+        .filter(|m| seen.insert(m.filename.clone()))
+        .cloned()
+        .collect()
+}
+
+/// 實際移除的結果
+#[derive(Debug, Default)]
+pub struct PruneSummary {
+    pub removed: usize,
+    /// 圖片移到的回收桶批次（可用 `dedup restore` 還原）
+    pub trash: Option<String>,
+}
+
+/// 把圖片移到回收桶、更新 metadata，並把每一筆移除寫入 prune_log.jsonl
+///
+/// 回收桶批次的 manifest 記錄原本的 metadata，`dedup restore <批次>` 可以還原。
+pub fn apply_prune(
+    file_manager: &FileManager,
+    store: &dyn MetadataStore,
+    policy: &str,
+    plan: &[ImageMetadata],
+) -> Result<PruneSummary> {
+    use std::io::Write;
+
+    let log_path = format!("{}/prune_log.jsonl", file_manager.root_dir());
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("無法開啟 {}", log_path))?;
+    let mut batch = TrashBatch::create(file_manager.root_dir(), &format!("prune: {}", policy))?;
+
+    let mut removed: HashSet<&str> = HashSet::new();
+    let mut trashed = 0;
+    for metadata in plan {
+        let path = file_manager.get_image_path(&metadata.filename);
+
+        if Path::new(&path).exists() {
+            if let Err(e) = batch.move_in(&path, &metadata.filename) {
+                eout!("  ⚠️  {}: {}", metadata.filename, e);
+                continue;
+            }
+            batch.push(TrashEntry {
+                filename: metadata.filename.clone(),
+                kept: None,
+                metadata: Some(metadata.clone()),
+                results: Vec::new(),
+            });
+            trashed += 1;
+        }
+        // 檔案已不存在時仍移除 metadata（只留在 prune_log.jsonl）
+
+        let record = PruneRecord {
+            pruned_at: Utc::now(),
+            policy: policy.to_string(),
+            filename: metadata.filename.clone(),
+            content_hash: metadata.content_hash.clone(),
+            url: metadata.url.clone(),
+        };
+        writeln!(log, "{}", serde_json::to_string(&record)?)?;
+        removed.insert(&metadata.filename);
+    }
+
+    // 改寫 metadata 前先寫 manifest，中途失敗時仍能還原已移走的圖片
+    let trash = if trashed > 0 {
+        batch.save()?;
+        Some(batch.id().to_string())
+    } else {
+        batch.discard()?;
+        None
+    };
+
+    if !removed.is_empty() {
+        let remaining: Vec<ImageMetadata> = store
+            .load_all_metadata()?
+            .into_iter()
+            .filter(|m| !removed.contains(m.filename.as_str()))
+            .collect();
+        store.rewrite_metadata(&remaining)?;
+    }
+
+    Ok(PruneSummary { removed: removed.len(), trash })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn metadata(filename: &str, page: u32, days_ago: i64) -> ImageMetadata {
+        ImageMetadata {
+            description: "測試".to_string(),
+            page_number: page,
+            downloaded_at: Utc::now() - Duration::days(days_ago),
+            ..ImageMetadata::for_test(filename, &"0".repeat(64))
+        }
+    }
+
+    #[test]
+    fn test_policy_eval() {
+        let mut tags = HashMap::new();
+        tags.insert("a.jpg".to_string(), BTreeSet::from(["cat".to_string()]));
+        let ctx = PruneContext { now: Utc::now(), tags };
+
+        let a = metadata("a.jpg", 10, 40);
+        let b = metadata("b.png", 1600, 1);
+
+        let policy = Policy::parse("page_number>1500 || age_days>=30 && tag==cat").unwrap();
+        assert!(policy.matches(&a, &ctx));
+        assert!(policy.matches(&b, &ctx));
+
+        let policy = Policy::parse("!(filename ~ \".png\") && page_number <= 10").unwrap();
+        assert!(policy.matches(&a, &ctx));
+        assert!(!policy.matches(&b, &ctx));
+
+        // 不存在的欄位視為不符合
+        let policy = Policy::parse("nsfw_score>0.8 || width<200").unwrap();
+        assert!(!policy.matches(&a, &ctx));
+
+        // metadata 沒有的欄位從 extra 找
+        let mut flagged = metadata("c.jpg", 1, 1);
+        flagged.extra.insert("nsfw_score".to_string(), serde_json::json!(0.93));
+        assert!(policy.matches(&flagged, &ctx));
+        assert!(Policy::parse("extra.nsfw_score>=0.9").unwrap().matches(&flagged, &ctx));
+        assert!(!Policy::parse("extra.page_number>0").unwrap().matches(&flagged, &ctx));
+
+        assert!(Policy::parse("page_number >").is_err());
+        assert!(Policy::parse("(page_number > 1").is_err());
+    }
+
+    #[test]
+    fn test_prune_to_trash() {
+        use crate::context::DataContext;
+        use crate::store::MetadataBackend;
+
+        let dir = std::env::temp_dir().join(format!("meme-prune-{}", std::process::id()));
+        let data_dir = dir.to_str().unwrap();
+        let file_manager = FileManager::new(data_dir).unwrap();
+
+        let kept = metadata("a.jpg", 1, 1);
+        let mut flagged = metadata("b.jpg", 1, 1);
+        flagged.extra.insert("nsfw_score".to_string(), serde_json::json!(0.93));
+        for meta in [&kept, &flagged] {
+            fs::write(file_manager.get_image_path(&meta.filename), "image").unwrap();
+            file_manager.append_metadata(meta).unwrap();
+        }
+
+        let ctx = PruneContext { now: Utc::now(), tags: HashMap::new() };
+        let policy = "nsfw_score>0.8";
+        let plan = plan_prune(&file_manager.load_all_metadata().unwrap(), &Policy::parse(policy).unwrap(), &ctx);
+        assert_eq!(plan.iter().map(|m| m.filename.as_str()).collect::<Vec<_>>(), vec!["b.jpg"]);
+
+        let summary = apply_prune(&file_manager, &file_manager, policy, &plan).unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(!Path::new(&file_manager.get_image_path("b.jpg")).exists());
+        assert_eq!(file_manager.load_all_metadata().unwrap().len(), 1);
+
+        // 移到回收桶的圖片可以還原
+        let context = DataContext::open(data_dir, MetadataBackend::Jsonl).unwrap();
+        let restored = crate::trash::restore(&context, &summary.trash.unwrap()).unwrap();
+        assert_eq!((restored.files, restored.metadata), (1, 1));
+        assert!(Path::new(&file_manager.get_image_path("b.jpg")).exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
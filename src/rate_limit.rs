@@ -0,0 +1,337 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 連續成功幾次後加速
+const SPEEDUP_AFTER: u32 = 10;
+/// 延遲下限（毫秒）
+const MIN_DELAY_MS: u64 = 100;
+/// 延遲上限（毫秒）
+const MAX_DELAY_MS: u64 = 60_000;
+
+/// 每個 host 學到的延遲（rate_limits.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostDelay {
+    pub delay_ms: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct HostState {
+    delay_ms: u64,
+    next_allowed: Option<Instant>,
+    success_streak: u32,
+    /// 網站要求的最小間隔（robots.txt 的 Crawl-delay），加速時不低於此值
+    min_delay_ms: u64,
+}
+
+impl HostState {
+    fn new(delay_ms: u64) -> Self {
+        Self {
+            delay_ms,
+            next_allowed: None,
+            success_streak: 0,
+            min_delay_ms: MIN_DELAY_MS,
+        }
+    }
+}
+
+/// 自適應限流器（依 host 控制請求間隔）
+///
+/// 遇到 429/403（或呼叫端偵測到驗證碼）時延遲加倍，空結果時拉長 1.5 倍，連續成功後逐步縮短；
+/// 反向搜尋服務另以 `service_key` 記錄各自的間隔。
+/// 學到的延遲存在 `rate_limits.json`，下次執行直接沿用。
+#[derive(Debug)]
+pub struct AdaptiveRateLimiter {
+    hosts: Mutex<BTreeMap<String, HostState>>,
+    path: Option<String>,
+}
+
+impl AdaptiveRateLimiter {
+    /// 不保存狀態的限流器
+    pub fn new() -> Self {
+        Self {
+            hosts: Mutex::new(BTreeMap::new()),
+            path: None,
+        }
+    }
+
+    /// 讀取資料目錄的 rate_limits.json（不存在時從空白開始）
+    pub fn load(data_dir: &str) -> Result<Self> {
+        let path = format!("{}/rate_limits.json", data_dir);
+        let mut hosts = BTreeMap::new();
+
+        if Path::new(&path).exists() {
+            let saved: BTreeMap<String, HostDelay> = serde_json::from_str(&fs::read_to_string(&path)?)
+                .context("無法解析 rate_limits.json")?;
+
+            for (host, saved) in saved {
+                hosts.insert(host, HostState::new(saved.delay_ms));
+            }
+        }
+
+        Ok(Self {
+            hosts: Mutex::new(hosts),
+            path: Some(path),
+        })
+    }
+
+    /// 儲存學到的延遲
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let snapshot: BTreeMap<String, HostDelay> = self.lock()
+            .iter()
+            .map(|(host, state)| (host.clone(), HostDelay {
+                delay_ms: state.delay_ms,
+                updated_at: Utc::now(),
+            }))
+            .collect();
+
+        let temp_path = format!("{}.tmp", path);
+        fs::write(&temp_path, serde_json::to_string_pretty(&snapshot)?)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, HostState>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 目前的延遲（未知的 host 回傳 None）
+    pub fn delay_ms(&self, host: &str) -> Option<u64> {
+        self.lock().get(host).map(|s| s.delay_ms)
+    }
+
+    /// 直接設定延遲（例如暖身量測的結果）
+    pub fn set_delay(&self, host: &str, delay_ms: u64) {
+        let mut hosts = self.lock();
+        let state = hosts.entry(host.to_string()).or_insert(HostState::new(delay_ms));
+        state.delay_ms = delay_ms.clamp(state.min_delay_ms, MAX_DELAY_MS);
+    }
+
+    /// 設定 host 的最小間隔（例如 robots.txt 的 Crawl-delay），目前的延遲較短時一併拉長
+    pub fn set_min_delay(&self, host: &str, min_delay_ms: u64) {
+        let min_delay_ms = min_delay_ms.clamp(MIN_DELAY_MS, MAX_DELAY_MS);
+        let mut hosts = self.lock();
+        let state = hosts.entry(host.to_string()).or_insert(HostState::new(min_delay_ms));
+        state.min_delay_ms = min_delay_ms;
+        state.delay_ms = state.delay_ms.max(min_delay_ms);
+    }
+
+    /// 預約下一個請求的時間並回傳需要等待多久
+    ///
+    /// 未知的 host 以 `default_delay_ms` 為起始延遲。
+    fn reserve(&self, host: &str, default_delay_ms: u64, now: Instant) -> Duration {
+        let mut hosts = self.lock();
+        let state = hosts.entry(host.to_string()).or_insert(HostState::new(
+            default_delay_ms.clamp(MIN_DELAY_MS, MAX_DELAY_MS),
+        ));
+
+        let start = state.next_allowed.map_or(now, |t| t.max(now));
+        state.next_allowed = Some(start + Duration::from_millis(state.delay_ms));
+        start - now
+    }
+
+    /// 等待輪到這個 host
+    pub async fn wait(&self, host: &str, default_delay_ms: u64) {
+        let wait = self.reserve(host, default_delay_ms, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 請求成功：連續成功一段時間後縮短延遲
+    pub fn on_success(&self, host: &str) {
+        let mut hosts = self.lock();
+        if let Some(state) = hosts.get_mut(host) {
+            state.success_streak += 1;
+            if state.success_streak >= SPEEDUP_AFTER {
+                state.success_streak = 0;
+                state.delay_ms = (state.delay_ms * 9 / 10).max(state.min_delay_ms);
+            }
+        }
+    }
+
+    /// 空結果或失敗（常見於被封鎖但回傳 200 的服務）：延遲拉長 1.5 倍，不額外暫停
+    pub fn on_empty(&self, key: &str) {
+        let mut hosts = self.lock();
+        if let Some(state) = hosts.get_mut(key) {
+            state.success_streak = 0;
+            state.delay_ms = (state.delay_ms * 3 / 2).clamp(state.min_delay_ms, MAX_DELAY_MS);
+        }
+    }
+
+    /// 被限流（429/403/驗證碼）：延遲加倍，並依 Retry-After 暫停
+    pub fn on_throttled(&self, host: &str, retry_after: Option<Duration>) {
+        let mut hosts = self.lock();
+        let state = hosts.entry(host.to_string()).or_insert(HostState::new(MIN_DELAY_MS));
+
+        state.success_streak = 0;
+        state.delay_ms = (state.delay_ms * 2).clamp(1000, MAX_DELAY_MS);
+
+        let pause = retry_after.unwrap_or(Duration::from_millis(state.delay_ms));
+        let resume = Instant::now() + pause;
+        state.next_allowed = Some(state.next_allowed.map_or(resume, |t| t.max(resume)));
+
+        eout!("    🐢 {} 被限流，延遲調整為 {}ms", host, state.delay_ms);
+    }
+
+    /// 暫停一段時間（例如服務回傳驗證碼），延遲本身不變
+    pub fn pause(&self, key: &str, duration: Duration) {
+        let mut hosts = self.lock();
+        let state = hosts.entry(key.to_string()).or_insert(HostState::new(MIN_DELAY_MS));
+
+        state.success_streak = 0;
+        let resume = Instant::now() + duration;
+        state.next_allowed = Some(state.next_allowed.map_or(resume, |t| t.max(resume)));
+    }
+}
+
+impl Default for AdaptiveRateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 每個 host 的 token bucket（爬頁面與下載圖片共用）
+///
+/// 每秒補充 `rate` 個 token，最多累積 `burst` 個；每個請求取一個 token，沒有時等待。
+/// 與 `AdaptiveRateLimiter` 的間隔疊加：不論並發數多少，同一 host 的請求速率都不超過上限。
+#[derive(Debug)]
+pub struct HostTokenBucket {
+    rate: f64,
+    burst: f64,
+    hosts: Mutex<HashMap<String, Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// 可以是負數（已預約的請求）
+    tokens: f64,
+    updated: Instant,
+}
+
+impl HostTokenBucket {
+    /// `rate` 為每秒請求數，`burst` 為允許的瞬間請求數（至少 1）
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate: rate.max(0.01),
+            burst: burst.max(1) as f64,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// 取一個 token 並回傳需要等待多久
+    fn reserve(&self, host: &str, now: Instant) -> Duration {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = hosts.entry(host.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+
+    /// 等待輪到這個 host 的下一個請求
+    pub async fn acquire(&self, host: &str) {
+        let wait = self.reserve(host, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// 反向搜尋服務在限流器中的 key（與 host 分開，記錄每個服務自己的間隔）
+pub fn service_key(service: &str) -> String {
+    format!("service:{}", service)
+}
+
+/// 取出 URL 的 host（解析失敗時回傳原字串）
+pub fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_delay() {
+        let limiter = AdaptiveRateLimiter::new();
+        let now = Instant::now();
+
+        // 第一個請求不用等，之後依延遲排隊
+        assert_eq!(limiter.reserve("a.com", 500, now), Duration::ZERO);
+        assert_eq!(limiter.reserve("a.com", 500, now), Duration::from_millis(500));
+        assert_eq!(limiter.reserve("b.com", 500, now), Duration::ZERO);
+
+        limiter.on_throttled("a.com", None);
+        assert_eq!(limiter.delay_ms("a.com"), Some(1000));
+        limiter.on_throttled("a.com", None);
+        assert_eq!(limiter.delay_ms("a.com"), Some(2000));
+
+        for _ in 0..SPEEDUP_AFTER {
+            limiter.on_success("a.com");
+        }
+        assert_eq!(limiter.delay_ms("a.com"), Some(1800));
+
+        // 未出現過的 key 不受影響；空結果拉長 1.5 倍
+        limiter.on_empty("c.com");
+        assert_eq!(limiter.delay_ms("c.com"), None);
+        limiter.on_empty("a.com");
+        assert_eq!(limiter.delay_ms("a.com"), Some(2700));
+
+        // Crawl-delay 是下限，連續成功也不會更快
+        limiter.set_min_delay("d.com", 3000);
+        for _ in 0..SPEEDUP_AFTER {
+            limiter.on_success("d.com");
+        }
+        assert_eq!(limiter.delay_ms("d.com"), Some(3000));
+
+        assert_eq!(host_of("https://memes.tw/maker?page=2"), "memes.tw");
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let bucket = HostTokenBucket::new(2.0, 2);
+        let now = Instant::now();
+
+        // 先用掉 burst，之後每 0.5 秒一個
+        assert_eq!(bucket.reserve("a.com", now), Duration::ZERO);
+        assert_eq!(bucket.reserve("a.com", now), Duration::ZERO);
+        assert_eq!(bucket.reserve("a.com", now), Duration::from_millis(500));
+        assert_eq!(bucket.reserve("a.com", now), Duration::from_millis(1000));
+        assert_eq!(bucket.reserve("b.com", now), Duration::ZERO);
+
+        // 補充的 token 不超過 burst
+        let later = now + Duration::from_secs(60);
+        assert_eq!(bucket.reserve("a.com", later), Duration::ZERO);
+        assert_eq!(bucket.reserve("a.com", later), Duration::ZERO);
+        assert_eq!(bucket.reserve("a.com", later), Duration::from_millis(500));
+        assert_eq!(host_of("https://imgflip.com/memetemplates?page={page}"), "imgflip.com");
+    }
+}
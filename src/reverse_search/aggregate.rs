@@ -0,0 +1,249 @@
+use super::types::ReverseSearchResult;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+
+/// 一個關鍵字與它的跨服務一致度
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredKeyword {
+    pub keyword: String,
+    /// 提到這個關鍵字的服務數
+    pub services: usize,
+    /// 提到的服務數 / 搜尋過這張圖片的服務數（0~1）
+    pub score: f64,
+}
+
+/// 單張圖片合併各服務後的結果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregatedResult {
+    pub filename: String,
+    pub services: Vec<String>,
+    /// 最多服務給出的標題（同票時取服務名稱排序在前的）
+    pub suggested_title: Option<String>,
+    pub best_guess: Option<String>,
+    /// 依一致度排序（高的在前）
+    pub keywords: Vec<ScoredKeyword>,
+    /// 每個網域保留一個網址，依提到的服務數排序
+    pub related_sites: Vec<String>,
+    /// 各服務回報的最高相似度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f64>,
+    /// 最近一次搜尋的時間
+    pub searched_at: DateTime<Utc>,
+}
+
+impl AggregatedResult {
+    /// 至少 `min_services` 個服務都提到的關鍵字
+    pub fn consensus_keywords(&self, min_services: usize) -> Vec<&str> {
+        self.keywords
+            .iter()
+            .filter(|k| k.services >= min_services)
+            .map(|k| k.keyword.as_str())
+            .collect()
+    }
+}
+
+/// 合併多個服務的搜尋結果，每張圖片產生一筆 `AggregatedResult`
+///
+/// 同一張圖片、同一個服務有多筆結果時（重新搜尋過）只採用最新的一筆。
+#[derive(Debug, Default)]
+pub struct ReverseSearchAggregator {
+    /// 檔名 → 服務 → 最新的結果
+    results: BTreeMap<String, BTreeMap<String, ReverseSearchResult>>,
+}
+
+impl ReverseSearchAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_results<'a>(results: impl IntoIterator<Item = &'a ReverseSearchResult>) -> Self {
+        let mut aggregator = Self::new();
+        for result in results {
+            aggregator.add(result);
+        }
+        aggregator
+    }
+
+    pub fn add(&mut self, result: &ReverseSearchResult) {
+        let services = self.results.entry(result.filename.clone()).or_default();
+        match services.get(&result.service) {
+            Some(existing) if existing.searched_at > result.searched_at => {}
+            _ => {
+                services.insert(result.service.clone(), result.clone());
+            }
+        }
+    }
+
+    /// 有結果的圖片數
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// 單張圖片的合併結果（沒有結果時為 None）
+    pub fn merge(&self, filename: &str) -> Option<AggregatedResult> {
+        let services = self.results.get(filename)?;
+        Some(aggregate(filename, services.values().collect()))
+    }
+
+    /// 所有圖片的合併結果（依檔名排序）
+    pub fn merge_all(&self) -> Vec<AggregatedResult> {
+        self.results
+            .iter()
+            .map(|(filename, services)| aggregate(filename, services.values().collect()))
+            .collect()
+    }
+}
+
+/// 比對用的關鍵字（不分大小寫、合併空白）
+fn normalize_key(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// 網址的網域（去掉 `www.`，無法解析時用整個網址）
+fn domain(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.trim_start_matches("www.").to_lowercase()))
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// 依服務數投票（同票時取先出現的），顯示第一次出現的寫法
+fn vote<'a>(values: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut tally: Vec<(String, String, usize)> = Vec::new();
+    for value in values.map(str::trim).filter(|v| !v.is_empty()) {
+        let key = normalize_key(value);
+        match tally.iter_mut().find(|(k, _, _)| *k == key) {
+            Some((_, _, count)) => *count += 1,
+            None => tally.push((key, value.to_string(), 1)),
+        }
+    }
+
+    // max_by_key 同值時取最後一個，反向找才會取先出現的
+    tally.into_iter().rev().max_by_key(|(_, _, count)| *count).map(|(_, value, _)| value)
+}
+
+/// `results` 每個服務一筆，依服務名稱排序
+fn aggregate(filename: &str, results: Vec<&ReverseSearchResult>) -> AggregatedResult {
+    let total = results.len().max(1);
+
+    // 同一個服務對同一個關鍵字/網域只算一次
+    let mut keywords: Vec<(String, String, usize)> = Vec::new();
+    let mut sites: Vec<(String, String, usize)> = Vec::new();
+    for result in &results {
+        let mut seen_keywords = Vec::new();
+        for keyword in result.keywords.iter().map(|k| k.trim()).filter(|k| !k.is_empty()) {
+            let key = normalize_key(keyword);
+            if seen_keywords.contains(&key) {
+                continue;
+            }
+            match keywords.iter_mut().find(|(k, _, _)| *k == key) {
+                Some((_, _, count)) => *count += 1,
+                None => keywords.push((key.clone(), keyword.to_string(), 1)),
+            }
+            seen_keywords.push(key);
+        }
+
+        let mut seen_domains = Vec::new();
+        for url in &result.related_sites {
+            let key = domain(url);
+            if seen_domains.contains(&key) {
+                continue;
+            }
+            match sites.iter_mut().find(|(k, _, _)| *k == key) {
+                Some((_, _, count)) => *count += 1,
+                None => sites.push((key.clone(), url.clone(), 1)),
+            }
+            seen_domains.push(key);
+        }
+    }
+    // 穩定排序，同分時保留先出現的順序
+    keywords.sort_by_key(|k| std::cmp::Reverse(k.2));
+    sites.sort_by_key(|s| std::cmp::Reverse(s.2));
+
+    AggregatedResult {
+        filename: filename.to_string(),
+        services: results.iter().map(|r| r.service.clone()).collect(),
+        suggested_title: vote(results.iter().filter_map(|r| r.suggested_title.as_deref())),
+        best_guess: vote(results.iter().filter_map(|r| r.best_guess.as_deref())),
+        keywords: keywords
+            .into_iter()
+            .map(|(_, keyword, count)| ScoredKeyword {
+                keyword,
+                services: count,
+                score: count as f64 / total as f64,
+            })
+            .collect(),
+        related_sites: sites.into_iter().map(|(_, url, _)| url).collect(),
+        similarity: results.iter().filter_map(|r| r.similarity).reduce(f64::max),
+        searched_at: results.iter().map(|r| r.searched_at).max().unwrap_or_else(Utc::now),
+    }
+}
+
+/// 寫出合併結果（每張圖片一行，先寫暫存檔再改名）
+pub fn write_merged(path: &str, merged: &[AggregatedResult]) -> Result<()> {
+    let temp_path = format!("{}.tmp", path);
+    let mut writer = std::io::BufWriter::new(fs::File::create(&temp_path)?);
+    for result in merged {
+        writeln!(writer, "{}", serde_json::to_string(result)?)?;
+    }
+    writer.flush()?;
+
+    fs::rename(&temp_path, path).with_context(|| format!("無法更新 {}", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn result(service: &str, keywords: &[&str], sites: &[&str], best_guess: Option<&str>, day: u32) -> ReverseSearchResult {
+        ReverseSearchResult {
+            filename: "doge.jpg".to_string(),
+            service: service.to_string(),
+            suggested_title: None,
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            related_sites: sites.iter().map(|s| s.to_string()).collect(),
+            best_guess: best_guess.map(str::to_string),
+            similarity: None,
+            searched_at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let results = vec![
+            result("bing", &["Doge", "shiba inu", "meme"], &["https://www.reddit.com/r/a", "https://imgur.com/x"], Some("doge meme"), 1),
+            result("lens", &["doge", "Shiba  Inu"], &["https://reddit.com/r/b", "https://knowyourmeme.com/memes/doge"], Some("Doge Meme"), 1),
+            result("tineye", &["old"], &[], Some("wow"), 1),
+            // 重新搜尋過的 tineye 取代舊的結果
+            result("tineye", &["doge", "dog"], &["https://knowyourmeme.com/memes/doge"], Some("shiba"), 2),
+        ];
+        let aggregator = ReverseSearchAggregator::from_results(&results);
+        assert_eq!(aggregator.len(), 1);
+
+        let merged = aggregator.merge("doge.jpg").unwrap();
+        assert_eq!(merged.services, vec!["bing", "lens", "tineye"]);
+        assert_eq!(merged.best_guess.as_deref(), Some("doge meme"));
+        assert_eq!(merged.keywords[0], ScoredKeyword { keyword: "Doge".to_string(), services: 3, score: 1.0 });
+        assert_eq!(merged.keywords[1].keyword, "shiba inu");
+        assert_eq!(merged.keywords[1].services, 2);
+        assert!(!merged.keywords.iter().any(|k| k.keyword == "old"));
+        assert_eq!(merged.consensus_keywords(2), vec!["Doge", "shiba inu"]);
+        assert_eq!(merged.related_sites, vec![
+            "https://www.reddit.com/r/a",
+            "https://knowyourmeme.com/memes/doge",
+            "https://imgur.com/x",
+        ]);
+        assert_eq!(merged.searched_at.date_naive().to_string(), "2024-01-02");
+        assert!(aggregator.merge("missing.jpg").is_none());
+    }
+}
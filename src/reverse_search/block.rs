@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+/// 驗證碼/攔截頁的特徵（小寫比對）與顯示的原因
+const MARKERS: &[(&str, &str)] = &[
+    ("g-recaptcha", "reCAPTCHA"),
+    ("google.com/recaptcha", "reCAPTCHA"),
+    ("hcaptcha.com", "hCaptcha"),
+    ("h-captcha", "hCaptcha"),
+    ("unusual traffic", "unusual traffic"),
+    ("/sorry/index", "Google sorry 頁面"),
+    ("challenge-platform", "Cloudflare challenge"),
+    ("<title>just a moment...</title>", "Cloudflare challenge"),
+    ("verify you are a human", "人機驗證"),
+    ("are you a robot", "人機驗證"),
+];
+
+/// 服務回傳驗證碼或攔截頁（HTTP 200 但沒有搜尋結果）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceBlocked {
+    pub service: String,
+    pub reason: String,
+}
+
+impl fmt::Display for ServiceBlocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} 回傳驗證碼/攔截頁（{}）", self.service, self.reason)
+    }
+}
+
+impl std::error::Error for ServiceBlocked {}
+
+/// 判斷回應是否為驗證碼/攔截頁，回傳原因
+pub fn detect_block(html: &str) -> Option<&'static str> {
+    let lower = html.to_lowercase();
+    MARKERS
+        .iter()
+        .find(|(marker, _)| lower.contains(marker))
+        .map(|(_, reason)| *reason)
+}
+
+/// 是攔截頁時回傳 `ServiceBlocked` 錯誤（可用 `downcast_ref` 判斷）
+pub fn check(service: &str, html: &str) -> Result<()> {
+    match detect_block(html) {
+        Some(reason) => Err(ServiceBlocked {
+            service: service.to_string(),
+            reason: reason.to_string(),
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// 服務被攔截時送出的通知
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockAlert {
+    /// 一行摘要（Slack/Discord 等 webhook 直接顯示）
+    pub text: String,
+    pub service: String,
+    pub reason: String,
+    pub filename: String,
+    /// 暫停秒數
+    pub paused_secs: u64,
+    pub at: DateTime<Utc>,
+}
+
+impl BlockAlert {
+    pub fn new(blocked: &ServiceBlocked, filename: &str, pause: Duration) -> Self {
+        Self {
+            text: format!("🚧 {}，暫停 {} 秒（{}）", blocked, pause.as_secs(), filename),
+            service: blocked.service.clone(),
+            reason: blocked.reason.clone(),
+            filename: filename.to_string(),
+            paused_secs: pause.as_secs(),
+            at: Utc::now(),
+        }
+    }
+}
+
+/// 以 JSON POST 送出通知
+pub async fn send_alert(webhook_url: &str, alert: &BlockAlert) -> Result<()> {
+    let response = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .post(webhook_url)
+        .json(alert)
+        .send()
+        .await
+        .context("webhook 請求失敗")?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("webhook 回應錯誤: {}", response.status());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_block() {
+        let google = r#"<html><body>Our systems have detected Unusual Traffic from your computer network.
+            <form action="/sorry/index"><div class="g-recaptcha"></div></form></body></html>"#;
+        assert_eq!(detect_block(google), Some("reCAPTCHA"));
+        assert_eq!(detect_block("<title>Just a moment...</title>"), Some("Cloudflare challenge"));
+        assert_eq!(detect_block("<html><title>cat meme - Bing</title></html>"), None);
+
+        let error = check("bing", "<div class=\"h-captcha\"></div>").unwrap_err();
+        let blocked = error.downcast_ref::<ServiceBlocked>().unwrap();
+        assert_eq!(blocked.service, "bing");
+        assert_eq!(blocked.reason, "hCaptcha");
+    }
+}
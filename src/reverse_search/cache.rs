@@ -0,0 +1,189 @@
+use super::types::ReverseSearchResult;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// 認領超過這個時間仍沒有結果，視為該程序已中斷
+const CLAIM_STALE: Duration = Duration::from_secs(10 * 60);
+
+/// 以內容雜湊 + 服務名稱快取搜尋結果（`search_cache/<service>/<content_hash>.json`）
+///
+/// 去重或重爬後同一張圖常以不同檔名再出現，命中快取時直接沿用先前的結果，不再送出請求。
+#[derive(Debug, Clone)]
+pub struct SearchCache {
+    dir: PathBuf,
+}
+
+impl SearchCache {
+    pub fn new(data_dir: &str) -> Self {
+        Self {
+            dir: Path::new(data_dir).join("search_cache"),
+        }
+    }
+
+    fn path(&self, service: &str, content_hash: &str) -> Option<PathBuf> {
+        // 雜湊與服務名稱會成為路徑，只接受安全的字元
+        let safe = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        (safe(service) && safe(content_hash))
+            .then(|| self.dir.join(service).join(format!("{}.json", content_hash)))
+    }
+
+    /// 讀取快取的結果，檔名換成目前的檔名（讀取失敗視為沒有快取）
+    pub fn get(&self, service: &str, content_hash: &str, filename: &str) -> Option<ReverseSearchResult> {
+        let path = self.path(service, content_hash)?;
+        let content = fs::read_to_string(path).ok()?;
+        let mut result: ReverseSearchResult = serde_json::from_str(&content).ok()?;
+        result.filename = filename.to_string();
+        Some(result)
+    }
+
+    /// 寫入快取（先寫暫存檔再改名）
+    pub fn put(&self, content_hash: &str, result: &ReverseSearchResult) -> Result<()> {
+        let Some(path) = self.path(&result.service, content_hash) else {
+            return Ok(());
+        };
+
+        fs::create_dir_all(path.parent().unwrap()).context("無法建立 search_cache 目錄")?;
+        // 多個程序可能同時寫入共用快取，暫存檔名加上 pid
+        let temp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&temp_path, serde_json::to_string(result)?)?;
+        fs::rename(&temp_path, &path).context("無法寫入搜尋快取")?;
+        Ok(())
+    }
+
+    pub fn contains(&self, service: &str, content_hash: &str) -> bool {
+        self.path(service, content_hash).is_some_and(|path| path.exists())
+    }
+
+    /// 所有 profile 共用的快取（`~/.meme-crawler/search_cache`）
+    pub fn shared() -> Result<Self> {
+        Ok(Self::new(&crate::profile::app_root()?.to_string_lossy()))
+    }
+
+    /// 認領一個內容的搜尋（`<content_hash>.claim`），避免多個 profile 同時搜尋同一張圖
+    ///
+    /// 其他程序持有未過期的認領時回傳 None；認領在 `CacheClaim` drop 時釋放。
+    pub fn try_claim(&self, service: &str, content_hash: &str) -> Result<Option<CacheClaim>> {
+        let Some(path) = self.path(service, content_hash) else {
+            return Ok(Some(CacheClaim { path: None }));
+        };
+        let claim_path = path.with_extension("claim");
+        fs::create_dir_all(path.parent().unwrap()).context("無法建立 search_cache 目錄")?;
+
+        for _ in 0..2 {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&claim_path) {
+                Ok(_) => return Ok(Some(CacheClaim { path: Some(claim_path) })),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&claim_path)
+                        .and_then(|info| info.modified())
+                        .map(|modified| SystemTime::now().duration_since(modified).unwrap_or_default() > CLAIM_STALE)
+                        .unwrap_or(true);
+                    if !stale {
+                        return Ok(None);
+                    }
+                    // 程序中斷留下的認領，清掉後再試一次
+                    fs::remove_file(&claim_path).ok();
+                }
+                Err(e) => return Err(e).context("無法建立搜尋認領檔"),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// 等待其他程序寫入結果（每秒檢查一次，認領釋放或逾時就停止）
+    pub async fn wait_for(
+        &self,
+        service: &str,
+        content_hash: &str,
+        filename: &str,
+        timeout: Duration,
+    ) -> Option<ReverseSearchResult> {
+        let claim_path = self.path(service, content_hash)?.with_extension("claim");
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            if let Some(result) = self.get(service, content_hash, filename) {
+                return Some(result);
+            }
+            if !claim_path.exists() || tokio::time::Instant::now() >= deadline {
+                return None;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+/// 搜尋認領（drop 時刪除認領檔）
+#[derive(Debug)]
+pub struct CacheClaim {
+    path: Option<PathBuf>,
+}
+
+impl Drop for CacheClaim {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("meme-cache-{}", std::process::id()));
+        let cache = SearchCache::new(dir.to_str().unwrap());
+
+        let result = ReverseSearchResult {
+            filename: "a.jpg".to_string(),
+            service: "bing".to_string(),
+            suggested_title: None,
+            keywords: vec!["doge".to_string()],
+            related_sites: vec![],
+            best_guess: Some("doge meme".to_string()),
+            similarity: None,
+            searched_at: Utc::now(),
+        };
+
+        assert!(cache.get("bing", "abc123", "b.jpg").is_none());
+        cache.put("abc123", &result).unwrap();
+        assert!(cache.contains("bing", "abc123"));
+        assert!(!cache.contains("tineye", "abc123"));
+
+        let cached = cache.get("bing", "abc123", "b.jpg").unwrap();
+        assert_eq!(cached.filename, "b.jpg");
+        assert_eq!(cached.best_guess.as_deref(), Some("doge meme"));
+
+        // 不安全的雜湊不會變成路徑
+        assert!(cache.put("../x", &result).is_ok());
+        assert!(!cache.contains("bing", "../x"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_claim() {
+        let dir = std::env::temp_dir().join(format!("meme-cache-claim-{}", std::process::id()));
+        let cache = SearchCache::new(dir.to_str().unwrap());
+
+        let claim = cache.try_claim("bing", "abc123").unwrap();
+        assert!(claim.is_some());
+        assert!(cache.try_claim("bing", "abc123").unwrap().is_none());
+        assert!(cache.try_claim("tineye", "abc123").unwrap().is_some());
+
+        // 認領中沒有結果時等到逾時；釋放後不再等待
+        let started = std::time::Instant::now();
+        assert!(cache.wait_for("bing", "abc123", "b.jpg", Duration::from_millis(100)).await.is_none());
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        drop(claim);
+        assert!(cache.wait_for("bing", "abc123", "b.jpg", Duration::from_secs(60)).await.is_none());
+        assert!(cache.try_claim("bing", "abc123").unwrap().is_some());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
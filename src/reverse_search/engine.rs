@@ -1,21 +1,74 @@
-use crate::file_manager::FileManager;
+use crate::context::{files, DataContext};
+use crate::types::ImageMetadata;
+use crate::shutdown::ShutdownSignal;
+use crate::status::StatusHandle;
+use crate::store::MetadataBackend;
+use crate::events::EventSink;
+use crate::integrity::{self, HashMismatch};
+use crate::media::{self, MediaType};
+use crate::rate_limit::{self, AdaptiveRateLimiter};
+use crate::tier::ColdStorage;
 use super::{
+    block::{self, BlockAlert, ServiceBlocked},
+    cache::SearchCache,
+    keywords::KeywordNormalizer,
+    latency::{self, LatencyRecord},
+    redo::{self, RedoTarget},
     trait_def::ReverseSearchService,
     types::{ReverseSearchResult, SearchProgress},
+    writer::{self, ResultWriter, SyncPolicy},
 };
 use anyhow::Result;
+use chrono::Utc;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::Semaphore;
-use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+use std::time::{Duration, Instant};
 use std::fs;
 use std::path::Path;
 
+/// 服務回傳驗證碼後預設暫停 15 分鐘
+const DEFAULT_BLOCK_COOLDOWN: Duration = Duration::from_secs(15 * 60);
+
+/// 其他 profile 正在搜尋同一內容時，等待它的結果的上限
+const SHARED_CLAIM_WAIT: Duration = Duration::from_secs(120);
+
+#[derive(Clone)]
 pub struct ReverseSearchEngine {
-    file_manager: FileManager,
+    context: Arc<DataContext>,
     services: Vec<Arc<dyn ReverseSearchService>>,
     concurrency: usize,
     progress_file: String,
     results_file: String,
+    /// 搜尋結果的緩衝寫入（並發的 task 共用）
+    results: Arc<ResultWriter>,
+    /// 每次呼叫的耗時紀錄
+    latency_file: String,
+    /// 優先上傳本地檔案搜尋（服務支援時）
+    upload: bool,
+    /// 搜尋結果事件發佈（選用）
+    events: Option<EventSink>,
+    /// 上傳前重新計算 hash，確認檔案未損毀
+    verify: bool,
+    /// 原圖已移到冷儲存時，上傳前自動取回
+    cold: Option<Arc<ColdStorage>>,
+    /// 服務共用的自適應限流器（未設定時只在本次執行內調整，不保存）
+    rate_limiter: Option<Arc<AdaptiveRateLimiter>>,
+    /// 服務回傳驗證碼/攔截頁後暫停的時間
+    block_cooldown: Duration,
+    /// 服務被攔截時通知的 webhook（選用）
+    block_webhook: Option<String>,
+    /// 各服務暫停到何時
+    paused_until: Arc<std::sync::Mutex<HashMap<String, Instant>>>,
+    /// 以內容雜湊快取的搜尋結果（None 表示停用）
+    cache: Option<SearchCache>,
+    /// 所有 profile 共用的快取（本地快取沒有時才查詢，None 表示停用）
+    shared_cache: Option<SearchCache>,
+    /// 新搜尋結果的關鍵字後處理（None 表示保留服務的原始關鍵字）
+    keyword_normalizer: Option<Arc<KeywordNormalizer>>,
+    /// 執行中的狀態（`status()` 取得的 handle 共用）
+    status: StatusHandle,
 }
 
 impl ReverseSearchEngine {
@@ -24,22 +77,149 @@ impl ReverseSearchEngine {
         services: Vec<Arc<dyn ReverseSearchService>>,
         concurrency: usize,
     ) -> Result<Self> {
-        Ok(Self {
-            file_manager: FileManager::new(data_dir)?,
+        let context = DataContext::open(data_dir, MetadataBackend::Jsonl)?;
+        Ok(Self::from_context(context, services).with_concurrency(concurrency))
+    }
+    
+    /// 使用共用的資料目錄環境（metadata 後端、路徑與讀取過的資料）
+    pub fn from_context(context: Arc<DataContext>, services: Vec<Arc<dyn ReverseSearchService>>) -> Self {
+        let results_file = context.path(files::SEARCH_RESULTS);
+        Self {
             services,
-            concurrency,
-            progress_file: format!("{}/search_progress.json", data_dir),
-            results_file: format!("{}/reverse_search_results.jsonl", data_dir),
-        })
+            concurrency: 1,
+            progress_file: context.path(files::SEARCH_PROGRESS),
+            results: Arc::new(ResultWriter::new(
+                &results_file,
+                writer::DEFAULT_FLUSH_INTERVAL,
+                SyncPolicy::default(),
+            )),
+            results_file,
+            latency_file: context.path(files::SERVICE_LATENCY),
+            upload: false,
+            events: None,
+            verify: false,
+            cold: None,
+            rate_limiter: None,
+            block_cooldown: DEFAULT_BLOCK_COOLDOWN,
+            block_webhook: None,
+            paused_until: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            cache: Some(SearchCache::new(context.root())),
+            shared_cache: None,
+            keyword_normalizer: None,
+            status: StatusHandle::new(),
+            context,
+        }
+    }
+    
+    /// 改用上傳本地檔案的方式搜尋
+    pub fn with_upload(mut self, upload: bool) -> Self {
+        self.upload = upload;
+        self
+    }
+    
+    /// 每筆搜尋結果完成時發佈事件
+    pub fn with_events(mut self, events: EventSink) -> Self {
+        self.events = Some(events);
+        self
+    }
+    
+    /// 上傳前驗證檔案 hash，不符時失敗並加入重新下載佇列
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+    
+    /// 上傳模式下本地沒有原圖時，從冷儲存取回
+    pub fn with_cold_storage(mut self, cold: Arc<ColdStorage>) -> Self {
+        self.cold = Some(cold);
+        self
+    }
+    
+    /// 使用指定的限流器控制各服務的請求間隔，結束時保存學到的延遲
+    pub fn with_rate_limiter(mut self, limiter: Arc<AdaptiveRateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+    
+    /// 同時搜尋的圖片數（同一服務的請求仍依各自的間隔排隊）
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    
+    /// 是否使用搜尋快取（預設啟用；停用時每張圖都重新搜尋）
+    pub fn with_cache(mut self, enabled: bool) -> Self {
+        self.cache = enabled.then(|| SearchCache::new(self.context.root()));
+        self
+    }
+    
+    /// 與其他 profile 共用的快取：同一內容在任一 profile 搜過就不再送出請求，
+    /// 多個 profile 同時搜尋同一內容時只有一個會送出請求，其他等待它的結果
+    pub fn with_shared_cache(mut self, cache: Option<SearchCache>) -> Self {
+        self.shared_cache = cache;
+        self
+    }
+    
+    /// 搜尋結果寫入檔案的間隔與 fsync 時機（預設每秒寫入、儲存進度前 fsync）
+    pub fn with_result_writing(mut self, flush_interval: Duration, sync: SyncPolicy) -> Self {
+        self.results = Arc::new(ResultWriter::new(&self.results_file, flush_interval, sync));
+        self
+    }
+    
+    /// 服務回傳驗證碼/攔截頁時暫停的時間
+    pub fn with_block_cooldown(mut self, cooldown: Duration) -> Self {
+        self.block_cooldown = cooldown;
+        self
+    }
+    
+    /// 寫入與快取前先正規化新結果的關鍵字
+    pub fn with_keyword_normalizer(mut self, normalizer: Option<KeywordNormalizer>) -> Self {
+        self.keyword_normalizer = normalizer.map(Arc::new);
+        self
+    }
+    
+    /// 服務被攔截時 POST JSON 通知（Slack/Discord 等讀取 `text` 欄位）
+    pub fn with_block_webhook(mut self, url: String) -> Self {
+        self.block_webhook = Some(url);
+        self
+    }
+    
+    /// 執行狀態的 handle，執行期間可從其他 task 查詢 `snapshot()`
+    pub fn status(&self) -> StatusHandle {
+        self.status.clone()
+    }
+    
+    /// 還有服務尚未搜尋的圖片數
+    pub fn pending_count(&self) -> Result<usize> {
+        let progress = self.load_progress()?;
+        Ok(self.context
+            .metadata()?
+            .iter()
+            .filter(|m| !self.pending_services(&progress, &m.filename).is_empty())
+            .count())
     }
     
+    /// 讀取進度（舊版只記錄檔名的進度依搜尋結果轉換成各服務的進度）
     pub fn load_progress(&self) -> Result<SearchProgress> {
         if !Path::new(&self.progress_file).exists() {
             return Ok(SearchProgress::new());
         }
         
         let content = fs::read_to_string(&self.progress_file)?;
-        Ok(serde_json::from_str(&content)?)
+        let mut progress: SearchProgress = serde_json::from_str(&content)?;
+        if !progress.completed_files.is_empty() {
+            progress.migrate_legacy(&self.context.search_results()?);
+        }
+        Ok(progress)
+    }
+    
+    /// 這張圖片還沒用來搜尋過的服務
+    fn pending_services(&self, progress: &SearchProgress, filename: &str) -> Vec<Arc<dyn ReverseSearchService>> {
+        self.services
+            .iter()
+            .filter(|service| !progress.is_completed(filename, service.name()))
+            .cloned()
+            .collect()
     }
     
     pub fn save_progress(&self, progress: &SearchProgress) -> Result<()> {
@@ -50,77 +230,722 @@ impl ReverseSearchEngine {
         Ok(())
     }
     
+    /// 寫入一筆搜尋結果（先進緩衝，儲存進度前才確定寫入）
     pub fn append_result(&self, result: &ReverseSearchResult) -> Result<()> {
-        use std::io::Write;
-        let mut file = fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.results_file)?;
-        
-        writeln!(file, "{}", serde_json::to_string(result)?)?;
+        self.results.append(result)?;
+        self.context.store().append_search_result(result)?;
         Ok(())
     }
     
+    /// 發佈搜尋結果事件（失敗不影響搜尋）
+    async fn publish_result(&self, result: &ReverseSearchResult) {
+        if let Some(events) = &self.events {
+            if let Err(e) = events.search_completed(result).await {
+                eout!("    ⚠️  事件發佈失敗: {}", e);
+            }
+        }
+    }
+    
+    /// 正規化新結果的關鍵字（翻譯程式是同步呼叫，放到 blocking 執行緒）
+    ///
+    /// 快取存的是正規化後的結果，沿用快取時不再處理。
+    async fn normalize_keywords(&self, mut result: ReverseSearchResult) -> ReverseSearchResult {
+        let Some(normalizer) = self.keyword_normalizer.clone() else {
+            return result;
+        };
+        if normalizer.translator().is_none() {
+            normalizer.apply(&mut result);
+            return result;
+        }
+        
+        let fallback = result.clone();
+        tokio::task::spawn_blocking(move || {
+            normalizer.apply(&mut result);
+            result
+        })
+        .await
+        .unwrap_or(fallback)
+    }
+    
+    /// 把既有的搜尋結果寫入快取（早於快取功能的結果，或共用快取還沒有的結果），回傳新增數
+    fn prime_cache(&self, metadata: &[ImageMetadata]) -> Result<usize> {
+        let caches: Vec<&SearchCache> = self.cache.iter().chain(&self.shared_cache).collect();
+        if caches.is_empty() {
+            return Ok(0);
+        }
+        
+        let hashes: HashMap<&str, &str> = metadata
+            .iter()
+            .map(|m| (m.filename.as_str(), m.content_hash.as_str()))
+            .collect();
+        
+        let mut added = 0;
+        for result in self.context.search_results()?.iter() {
+            let Some(hash) = hashes.get(result.filename.as_str()) else {
+                continue;
+            };
+            if is_empty_result(result) {
+                continue;
+            }
+            for cache in &caches {
+                if !cache.contains(&result.service, hash) {
+                    cache.put(hash, result)?;
+                    added += 1;
+                }
+            }
+        }
+        Ok(added)
+    }
+    
+    /// 服務是否在驗證碼暫停期間
+    fn is_paused(&self, service: &str) -> bool {
+        self.paused_until
+            .lock()
+            .unwrap()
+            .get(service)
+            .is_some_and(|until| *until > Instant::now())
+    }
+    
+    /// 服務回傳驗證碼/攔截頁：暫停一段時間，同一次暫停只通知一次
+    async fn on_blocked(&self, blocked: &ServiceBlocked, filename: &str, key: &str, limiter: &AdaptiveRateLimiter) {
+        limiter.pause(key, self.block_cooldown);
+        
+        let newly_paused = {
+            let mut paused = self.paused_until.lock().unwrap();
+            let now = Instant::now();
+            match paused.get(&blocked.service) {
+                Some(until) if *until > now => false,
+                _ => {
+                    paused.insert(blocked.service.clone(), now + self.block_cooldown);
+                    true
+                }
+            }
+        };
+        if !newly_paused {
+            return;
+        }
+        
+        eout!("  🚧 {}，暫停 {} 秒（{}）", blocked, self.block_cooldown.as_secs(), filename);
+        self.status.record_error(format!("{}（{}）", blocked, filename));
+        if let Some(webhook) = &self.block_webhook {
+            let alert = BlockAlert::new(blocked, filename, self.block_cooldown);
+            if let Err(e) = block::send_alert(webhook, &alert).await {
+                eout!("    ⚠️  通知失敗: {}", e);
+            }
+        }
+    }
+    
+    /// 以單一服務搜尋一張圖片（上傳模式下本地檔案不存在時退回 URL 搜尋）
+    async fn search_one(
+        &self,
+        service: &Arc<dyn ReverseSearchService>,
+        metadata: &ImageMetadata,
+    ) -> Result<ReverseSearchResult> {
+        if self.upload && service.supports_upload() {
+            if let Some(cold) = &self.cold {
+                if let Err(e) = cold.ensure_local(self.context.file_manager(), &metadata.filename) {
+                    eout!("    ⚠️  {}", e);
+                }
+            }
+            let path = self.context.file_manager().get_image_path(&metadata.filename);
+            if Path::new(&path).exists() {
+                if self.verify {
+                    if let Err(e) = integrity::read_verified(self.context.file_manager(), metadata) {
+                        if e.downcast_ref::<HashMismatch>().is_some() {
+                            integrity::queue_redownload(self.context.file_manager(), metadata)?;
+                            eout!("    🚨 {}（已加入重新下載佇列）", e);
+                        }
+                        return Err(e);
+                    }
+                }
+                // 搜尋服務只接受圖片：影片上傳代表畫面
+                if metadata.media_type == Some(MediaType::Video) || media::probe_file_format(&path).is_video() {
+                    let frame_path = self.context.file_manager().part_path(&format!("frame-{}", integrity::sha256_hex(path.as_bytes())));
+                    let (source, target) = (path.clone(), frame_path.clone());
+                    tokio::task::spawn_blocking(move || -> Result<()> {
+                        media::representative_frame(&source)?.save_with_format(&target, image::ImageFormat::Png)?;
+                        Ok(())
+                    })
+                    .await??;
+                    let result = service.search_by_upload(Path::new(&frame_path), metadata).await;
+                    fs::remove_file(&frame_path).ok();
+                    return result;
+                }
+                return service.search_by_upload(Path::new(&path), metadata).await;
+            }
+        }
+        
+        service.search(metadata).await
+    }
+    
     pub async fn run(&self) -> Result<()> {
-        println!("📖 讀取圖片列表...");
-        let all_metadata = self.file_manager.load_all_metadata()?;
+        out!("📖 讀取圖片列表...");
+        let all_metadata = self.context.metadata()?;
         
-        println!("📋 載入進度...");
-        let mut progress = self.load_progress()?;
+        out!("📋 載入進度...");
+        let progress = self.load_progress()?;
+        
+        let primed = self.prime_cache(&all_metadata)?;
+        if primed > 0 {
+            out!("♻️  已將 {} 筆既有結果加入快取", primed);
+        }
         
+        // 之後加入的服務只補搜它自己，已搜過的服務不重複
         let pending: Vec<_> = all_metadata
-            .into_iter()
-            .filter(|m| !progress.is_completed(&m.filename))
+            .iter()
+            .map(|m| {
+                let services = self.pending_services(&progress, &m.filename);
+                (m.clone(), services)
+            })
+            .filter(|(_, services)| !services.is_empty())
             .collect();
         
         if pending.is_empty() {
-            println!("✅ 所有圖片都已搜尋完成！");
+            out!("✅ 所有圖片都已搜尋完成！");
             return Ok(());
         }
         
-        println!("🔍 待搜尋: {} 張 (已完成: {})", 
+        out!("🔍 待搜尋: {} 張 (已搜尋過: {}，並發數: {})", 
             pending.len(), 
-            progress.completed_files.len()
+            progress.completed_count(),
+            self.concurrency.max(1)
         );
         
-        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        self.run_pending(pending, progress).await
+    }
+    
+    /// 重新搜尋已完成但結果是空的或失敗的圖片（只用引擎的服務），
+    /// 新結果取代結果檔中的舊結果而不是另外附加。回傳重新搜尋的（圖片, 服務）數
+    pub async fn redo(&self) -> Result<usize> {
+        out!("📋 找出空結果與失敗的搜尋...");
+        let all_metadata = self.context.metadata()?;
+        let mut progress = self.load_progress()?;
+        let names: Vec<&str> = self.services.iter().map(|s| s.name()).collect();
+        let known: HashSet<&str> = all_metadata.iter().map(|m| m.filename.as_str()).collect();
+        let targets: BTreeSet<RedoTarget> = redo::find_targets(&progress, &self.context.search_results()?, &names)
+            .into_iter()
+            .filter(|(filename, _)| known.contains(filename.as_str()))
+            .collect();
+        
+        if targets.is_empty() {
+            out!("✅ 沒有需要重新搜尋的結果");
+            return Ok(0);
+        }
+        
+        for (filename, service) in &targets {
+            progress.remove_completed(filename, service);
+        }
+        self.save_progress(&progress)?;
+        
+        let pending: Vec<_> = all_metadata
+            .iter()
+            .map(|m| {
+                let services: Vec<_> = self
+                    .services
+                    .iter()
+                    .filter(|s| targets.contains(&(m.filename.clone(), s.name().to_string())))
+                    .cloned()
+                    .collect();
+                (m.clone(), services)
+            })
+            .filter(|(_, services)| !services.is_empty())
+            .collect();
+        
+        out!("🔁 重新搜尋: {} 張（{} 組圖片/服務，並發數: {}）", pending.len(), targets.len(), self.concurrency.max(1));
+        self.run_pending(pending, progress).await?;
         
-        for (idx, metadata) in pending.iter().enumerate() {
-            println!("[{}/{}] 搜尋: {}", 
+        let removed = self.replace_redone(&targets)?;
+        out!("🧹 已以新結果取代 {} 筆舊結果", removed);
+        Ok(targets.len())
+    }
+    
+    /// 重新搜尋後每組（圖片, 服務）只留最新的結果（結果檔與 SQLite 後端）
+    fn replace_redone(&self, targets: &BTreeSet<RedoTarget>) -> Result<usize> {
+        // 改寫後的結果檔是新檔案，寫入器要重新開啟
+        self.results.close()?;
+        let (results, removed) = redo::replace_in_place(super::load_all_results(&self.results_file)?, targets);
+        if removed > 0 {
+            super::rewrite_all_results(&self.results_file, &results)?;
+        }
+        if let Some(sqlite) = self.context.sqlite() {
+            let (results, removed) = redo::replace_in_place(sqlite.load_search_results()?, targets);
+            if removed > 0 {
+                sqlite.rewrite_search_results(&results)?;
+            }
+        }
+        self.context.invalidate();
+        Ok(removed)
+    }
+    
+    /// 搜尋待處理的圖片與服務，每張完成後儲存進度；收到中斷訊號時停止派發
+    async fn run_pending(
+        &self,
+        pending: Vec<(ImageMetadata, Vec<Arc<dyn ReverseSearchService>>)>,
+        progress: SearchProgress,
+    ) -> Result<()> {
+        // 未設定限流器時用不保存的限流器，各服務仍各自維持間隔
+        let limiter = self.rate_limiter.clone().unwrap_or_else(|| Arc::new(AdaptiveRateLimiter::new()));
+        let semaphore = Arc::new(Semaphore::new(self.concurrency.max(1)));
+        let progress = Arc::new(Mutex::new(progress));
+        let engine = Arc::new(self.clone());
+        let shutdown = ShutdownSignal::install();
+        let total = pending.len();
+        let _running = self.status.start("search", Some(total as u64));
+        let mut tasks = JoinSet::new();
+        let mut interrupted = false;
+        
+        for (idx, (metadata, services)) in pending.into_iter().enumerate() {
+            let permit = Arc::clone(&semaphore).acquire_owned().await?;
+            
+            // 收到中斷訊號：不再派發新的圖片，等進行中的完成後存檔結束
+            if shutdown.is_triggered() {
+                interrupted = true;
+                break;
+            }
+            
+            out!("[{}/{}] 搜尋: {} ({})", 
                 idx + 1, 
-                pending.len(), 
-                metadata.filename
+                total, 
+                metadata.filename,
+                services.iter().map(|s| s.name()).collect::<Vec<_>>().join(", ")
             );
             
-            for service in &self.services {
-                let _permit = semaphore.acquire().await?;
+            let engine = Arc::clone(&engine);
+            let limiter = Arc::clone(&limiter);
+            let progress = Arc::clone(&progress);
+            tasks.spawn(async move {
+                let _permit = permit;
+                let completed = engine.search_image(&metadata, &services, &limiter).await?;
+                engine.status.record(completed.len() == services.len(), completed.len() as u64);
                 
-                println!("  🔎 使用 {} 搜尋...", service.name());
+                let mut progress = progress.lock().await;
+                for service in &completed {
+                    progress.add_completed(&metadata.filename, service);
+                }
+                // 結果先落盤再記錄完成，當機時不會有記錄完成卻沒有結果的圖片
+                engine.results.checkpoint()?;
+                engine.save_progress(&progress)?;
                 
-                match service.search(metadata).await {
-                    Ok(result) => {
-                        println!("    ✅ 找到 {} 個關鍵字", result.keywords.len());
-                        self.append_result(&result)?;
+                if (idx + 1) % 10 == 0 {
+                    out!("💾 已處理 {} 張\n", idx + 1);
+                }
+                Ok::<_, anyhow::Error>(())
+            });
+            
+            // 順便回收已結束的 task，寫檔錯誤可以及早回報
+            while let Some(result) = tasks.try_join_next() {
+                result??;
+            }
+        }
+        
+        while let Some(result) = tasks.join_next().await {
+            result??;
+        }
+        
+        self.results.checkpoint()?;
+        self.context.invalidate();
+        self.save_rate_limits()?;
+        
+        if interrupted {
+            out!("\n⏸️  已中斷，進度已儲存 (已完成 {} 張)", progress.lock().await.completed_count());
+            return Ok(());
+        }
+        
+        self.print_learned_delays();
+        out!("\n✅ 全部完成！");
+        Ok(())
+    }
+    
+    /// 以指定的服務搜尋一張圖片：各服務同時進行，各自依限流器的間隔等待
+    async fn search_image(
+        self: &Arc<Self>,
+        metadata: &ImageMetadata,
+        services: &[Arc<dyn ReverseSearchService>],
+        limiter: &Arc<AdaptiveRateLimiter>,
+    ) -> Result<Vec<String>> {
+        let mut searches = JoinSet::new();
+        
+        for service in services {
+            let engine = Arc::clone(self);
+            let service = Arc::clone(service);
+            let metadata = metadata.clone();
+            let limiter = Arc::clone(limiter);
+            searches.spawn(async move {
+                let done = engine.search_with_service(&service, &metadata, &limiter).await?;
+                Ok::<_, anyhow::Error>(done.then(|| service.name().to_string()))
+            });
+        }
+        
+        let mut completed = Vec::new();
+        while let Some(result) = searches.join_next().await {
+            completed.extend(result??);
+        }
+        Ok(completed)
+    }
+    
+    /// 等待該服務的間隔後搜尋，並依結果調整間隔（空結果/失敗時拉長，持續成功後縮短）
+    ///
+    /// 回傳是否算完成；遇到驗證碼/攔截頁時暫停該服務並回傳 false，下次執行會重新搜尋。
+    async fn search_with_service(
+        &self,
+        service: &Arc<dyn ReverseSearchService>,
+        metadata: &ImageMetadata,
+        limiter: &AdaptiveRateLimiter,
+    ) -> Result<bool> {
+        // 同一內容先前搜過就直接沿用，不佔用服務的請求額度
+        if let Some(cached) = self.cache.as_ref().and_then(|cache| {
+            cache.get(service.name(), &metadata.content_hash, &metadata.filename)
+        }) {
+            out!("  ♻️  {} [{}]: 沿用快取結果（{} 個關鍵字）", metadata.filename, service.name(), cached.keywords.len());
+            self.append_result(&cached)?;
+            self.publish_result(&cached).await;
+            return Ok(true);
+        }
+        
+        // 再查其他 profile 的結果；其他 profile 正在搜尋同一內容時等待它完成
+        // 認領在函式結束（結果寫入快取後）才釋放
+        let _claim = match &self.shared_cache {
+            Some(shared) => {
+                let (hash, filename) = (&metadata.content_hash, &metadata.filename);
+                let mut cached = shared.get(service.name(), hash, filename);
+                let mut claim = None;
+                if cached.is_none() {
+                    claim = shared.try_claim(service.name(), hash)?;
+                    if claim.is_none() {
+                        out!("  ⏳ {} [{}]: 其他 profile 正在搜尋相同內容，等待結果", filename, service.name());
+                        cached = shared.wait_for(service.name(), hash, filename, SHARED_CLAIM_WAIT).await;
                     }
-                    Err(e) => {
-                        eprintln!("    ❌ 失敗: {}", e);
+                }
+                
+                if let Some(cached) = cached {
+                    out!("  ♻️  {} [{}]: 沿用其他 profile 的結果（{} 個關鍵字）", filename, service.name(), cached.keywords.len());
+                    self.append_result(&cached)?;
+                    self.publish_result(&cached).await;
+                    if let Some(cache) = &self.cache {
+                        if let Err(e) = cache.put(hash, &cached) {
+                            eout!("    ⚠️  無法寫入快取: {}", e);
+                        }
                     }
+                    return Ok(true);
                 }
+                claim
+            }
+            None => None,
+        };
+        
+        let key = rate_limit::service_key(service.name());
+        
+        // 等待期間服務被暫停時，重新排到暫停結束之後
+        loop {
+            limiter.wait(&key, service.suggested_delay_ms()).await;
+            if !self.is_paused(service.name()) {
+                break;
+            }
+        }
+        
+        let started = Instant::now();
+        let result = self.search_one(service, metadata).await;
+        self.record_latency(service.name(), &metadata.filename, started.elapsed(), result.is_ok());
+        
+        if let Err(e) = &result {
+            if let Some(blocked) = e.downcast_ref::<ServiceBlocked>() {
+                self.on_blocked(blocked, &metadata.filename, &key, limiter).await;
+                return Ok(false);
+            }
+        }
+        
+        match &result {
+            Ok(result) if !is_empty_result(result) => limiter.on_success(&key),
+            _ => limiter.on_empty(&key),
+        }
+        
+        match result {
+            Ok(result) => {
+                let result = self.normalize_keywords(result).await;
+                out!("  ✅ {} [{}]: 找到 {} 個關鍵字", metadata.filename, service.name(), result.keywords.len());
+                self.append_result(&result)?;
+                self.publish_result(&result).await;
                 
-                tokio::time::sleep(Duration::from_millis(
-                    service.suggested_delay_ms()
-                )).await;
+                // 空結果可能是被擋下，不快取
+                if !is_empty_result(&result) {
+                    for cache in self.cache.iter().chain(&self.shared_cache) {
+                        if let Err(e) = cache.put(&metadata.content_hash, &result) {
+                            eout!("    ⚠️  無法寫入快取: {}", e);
+                        }
+                    }
+                }
             }
+            Err(e) => {
+                eout!("  ❌ {} [{}]: {}", metadata.filename, service.name(), e);
+                self.status.record_error(format!("{} [{}]: {:#}", metadata.filename, service.name(), e));
+            }
+        }
+        
+        Ok(true)
+    }
+    
+    /// 記錄單次呼叫耗時（寫入失敗只警告，不中斷搜尋）
+    fn record_latency(&self, service: &str, filename: &str, elapsed: Duration, ok: bool) {
+        let record = LatencyRecord {
+            service: service.to_string(),
+            filename: filename.to_string(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            ok,
+            at: Utc::now(),
+        };
+        
+        if let Err(e) = latency::append_latency(&self.latency_file, &record) {
+            eout!("    ⚠️  無法記錄耗時: {}", e);
+        }
+    }
+    
+    /// 顯示各服務學到的間隔（下次執行沿用）
+    fn print_learned_delays(&self) {
+        let Some(limiter) = &self.rate_limiter else {
+            return;
+        };
+        
+        for service in &self.services {
+            if let Some(delay) = limiter.delay_ms(&rate_limit::service_key(service.name())) {
+                out!("🐢 {} 間隔: {}ms（建議值 {}ms）", service.name(), delay, service.suggested_delay_ms());
+            }
+        }
+    }
+    
+    fn save_rate_limits(&self) -> Result<()> {
+        match &self.rate_limiter {
+            Some(limiter) => limiter.save(),
+            None => Ok(()),
+        }
+    }
+}
+/// 沒有任何可用資訊的結果（服務封鎖時常回傳 200 的空白/驗證頁）
+fn is_empty_result(result: &ReverseSearchResult) -> bool {
+    result.keywords.is_empty()
+        && result.related_sites.is_empty()
+        && result.best_guess.is_none()
+        && result.suggested_title.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::FileManager;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex as StdMutex;
+    
+    /// 每次搜尋耗時 200ms，記錄開始時間與同時進行的數量
+    struct SlowService {
+        name: &'static str,
+        in_flight: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+        starts: StdMutex<Vec<Instant>>,
+    }
+    
+    #[async_trait::async_trait]
+    impl ReverseSearchService for SlowService {
+        fn name(&self) -> &str {
+            self.name
+        }
+        
+        async fn search(&self, metadata: &ImageMetadata) -> Result<ReverseSearchResult> {
+            self.starts.lock().unwrap().push(Instant::now());
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
             
-            progress.add_completed(metadata.filename.clone());
-            self.save_progress(&progress)?;
-            
-            if (idx + 1) % 10 == 0 {
-                println!("💾 已處理 {} 張\n", idx + 1);
+            Ok(ReverseSearchResult {
+                filename: metadata.filename.clone(),
+                service: self.name.to_string(),
+                suggested_title: None,
+                keywords: vec!["doge".to_string()],
+                related_sites: vec![],
+                best_guess: None,
+                similarity: None,
+                searched_at: Utc::now(),
+            })
+        }
+        
+        fn suggested_delay_ms(&self) -> u64 {
+            100
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_run() {
+        let dir = std::env::temp_dir().join(format!("meme-search-{}", std::process::id()));
+        let data_dir = dir.to_str().unwrap();
+        let file_manager = FileManager::new(data_dir).unwrap();
+        for i in 0..4 {
+            file_manager.append_metadata(&ImageMetadata::for_test(&format!("{}.jpg", i), "")).unwrap();
+        }
+        
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let services: Vec<Arc<SlowService>> = ["a", "b"]
+            .into_iter()
+            .map(|name| Arc::new(SlowService {
+                name,
+                in_flight: Arc::clone(&in_flight),
+                peak: Arc::clone(&peak),
+                starts: StdMutex::new(vec![]),
+            }))
+            .collect();
+        
+        let engine = ReverseSearchEngine::new(
+            data_dir,
+            services.iter().map(|s| Arc::clone(s) as Arc<dyn ReverseSearchService>).collect(),
+            1,
+        ).unwrap().with_concurrency(4);
+        
+        let started = Instant::now();
+        engine.run().await.unwrap();
+        
+        // 依序執行需要 8 × 200ms
+        assert!(started.elapsed() < Duration::from_millis(1200));
+        assert!(peak.load(Ordering::SeqCst) > 2);
+        assert_eq!(engine.load_progress().unwrap().completed_count(), 4);
+        assert_eq!(fs::read_to_string(dir.join("reverse_search_results.jsonl")).unwrap().lines().count(), 8);
+        
+        let status = engine.status().snapshot();
+        assert!(!status.running);
+        assert_eq!((status.task.as_str(), status.total, status.completed, status.items), ("search", Some(4), 4, 8));
+        
+        // 同一服務的請求仍維持間隔
+        for service in &services {
+            // 記錄順序不一定是取得額度的順序
+            let mut starts = service.starts.lock().unwrap().clone();
+            starts.sort();
+            assert_eq!(starts.len(), 4);
+            for pair in starts.windows(2) {
+                assert!(pair[1] - pair[0] >= Duration::from_millis(90));
             }
         }
         
-        println!("\n✅ 全部完成！");
-        Ok(())
+        // 之後加入的服務只補搜新服務
+        let service_c = Arc::new(SlowService {
+            name: "c",
+            in_flight: Arc::clone(&in_flight),
+            peak: Arc::clone(&peak),
+            starts: StdMutex::new(vec![]),
+        });
+        let engine = ReverseSearchEngine::new(
+            data_dir,
+            vec![Arc::clone(&services[0]) as Arc<dyn ReverseSearchService>, Arc::clone(&service_c) as _],
+            4,
+        ).unwrap();
+        assert_eq!(engine.pending_count().unwrap(), 4);
+        engine.run().await.unwrap();
+        assert_eq!(services[0].starts.lock().unwrap().len(), 4);
+        assert_eq!(service_c.starts.lock().unwrap().len(), 4);
+        assert_eq!(engine.pending_count().unwrap(), 0);
+        
+        // 舊版進度：只有搜尋結果中出現過的服務算完成
+        fs::write(dir.join("search_progress.json"), r#"{"completed_files":["0.jpg"],"last_updated":"2024-01-01T00:00:00Z"}"#).unwrap();
+        let progress = engine.load_progress().unwrap();
+        assert!(progress.completed_files.is_empty());
+        assert!(progress.is_completed("0.jpg", "a") && progress.is_completed("0.jpg", "c"));
+        assert_eq!(progress.completed_count(), 1);
+        
+        fs::remove_dir_all(&dir).ok();
     }
-}
\ No newline at end of file
+    
+    /// 每次都回傳驗證碼頁
+    struct BlockedService {
+        calls: AtomicUsize,
+    }
+    
+    #[async_trait::async_trait]
+    impl ReverseSearchService for BlockedService {
+        fn name(&self) -> &str {
+            "blocked"
+        }
+        
+        async fn search(&self, _metadata: &ImageMetadata) -> Result<ReverseSearchResult> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            block::check(self.name(), "<div class=\"g-recaptcha\"></div>")?;
+            unreachable!()
+        }
+        
+        fn suggested_delay_ms(&self) -> u64 {
+            100
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_blocked_service_pauses() {
+        let dir = std::env::temp_dir().join(format!("meme-search-blocked-{}", std::process::id()));
+        let data_dir = dir.to_str().unwrap();
+        let file_manager = FileManager::new(data_dir).unwrap();
+        for i in 0..2 {
+            file_manager.append_metadata(&ImageMetadata::for_test(&format!("{}.jpg", i), "")).unwrap();
+        }
+        
+        let service = Arc::new(BlockedService { calls: AtomicUsize::new(0) });
+        let engine = ReverseSearchEngine::new(data_dir, vec![Arc::clone(&service) as _], 2)
+            .unwrap()
+            .with_block_cooldown(Duration::from_millis(300));
+        
+        let started = Instant::now();
+        engine.run().await.unwrap();
+        
+        // 第二張等到暫停結束才送出，兩張都不算完成，也不寫入空結果
+        assert_eq!(service.calls.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(engine.pending_count().unwrap(), 2);
+        assert!(!dir.join("reverse_search_results.jsonl").exists());
+        
+        fs::remove_dir_all(&dir).ok();
+    }
+    
+    #[tokio::test]
+    async fn test_cached_result_reused() {
+        let dir = std::env::temp_dir().join(format!("meme-search-cache-{}", std::process::id()));
+        let data_dir = dir.to_str().unwrap();
+        let file_manager = FileManager::new(data_dir).unwrap();
+        for i in 0..2 {
+            file_manager.append_metadata(&ImageMetadata::for_test(&format!("{}.jpg", i), "samehash")).unwrap();
+        }
+        
+        let service = Arc::new(SlowService {
+            name: "a",
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            peak: Arc::new(AtomicUsize::new(0)),
+            starts: StdMutex::new(vec![]),
+        });
+        let engine = ReverseSearchEngine::new(data_dir, vec![Arc::clone(&service) as _], 1).unwrap();
+        engine.run().await.unwrap();
+        
+        // 第二張內容相同，直接沿用第一張的結果
+        assert_eq!(service.starts.lock().unwrap().len(), 1);
+        assert_eq!(engine.pending_count().unwrap(), 0);
+        let results = super::super::load_all_results(engine.results_file.as_str()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].filename, "1.jpg");
+        assert_eq!(results[1].keywords, vec!["doge".to_string()]);
+        
+        // 另一個 profile 有相同內容時沿用共用快取，不再送出請求
+        let other_dir = dir.join("other");
+        let other = FileManager::new(other_dir.to_str().unwrap()).unwrap();
+        other.append_metadata(&ImageMetadata {
+            filename: "copy.jpg".to_string(),
+            ..file_manager.load_all_metadata().unwrap().remove(0)
+        }).unwrap();
+        let shared = SearchCache::new(dir.join("shared").to_str().unwrap());
+        
+        let engine = ReverseSearchEngine::new(data_dir, vec![Arc::clone(&service) as _], 1).unwrap()
+            .with_shared_cache(Some(shared.clone()));
+        engine.run().await.unwrap();
+        assert!(shared.contains("a", "samehash"));
+        
+        let other_engine = ReverseSearchEngine::new(other_dir.to_str().unwrap(), vec![Arc::clone(&service) as _], 1).unwrap()
+            .with_shared_cache(Some(shared));
+        other_engine.run().await.unwrap();
+        assert_eq!(service.starts.lock().unwrap().len(), 1);
+        let results = super::super::load_all_results(other_engine.results_file.as_str()).unwrap();
+        assert_eq!(results[0].filename, "copy.jpg");
+        
+        fs::remove_dir_all(&dir).ok();
+    }
+}
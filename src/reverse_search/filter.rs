@@ -0,0 +1,255 @@
+//! 反向搜尋關鍵字與相關網站的過濾規則
+//!
+//! 內建的黑名單之外，可在資料目錄放 `keyword_filter.json` 加入規則：
+//!
+//! ```json
+//! {
+//!   "blocklist": ["nsfw"],
+//!   "blocklist_files": ["blocklist.txt"],
+//!   "block_patterns": ["^\\d+x\\d+$", "^img_\\d+"],
+//!   "allow_patterns": [],
+//!   "always_keep": ["sussex"],
+//!   "always_keep_files": ["meme_terms.txt"],
+//!   "min_length": 3,
+//!   "min_length_by_language": { "zh": 2, "ja": 2 },
+//!   "blocked_domains": ["pinterest.com"],
+//!   "allowed_domains": []
+//! }
+//! ```
+//!
+//! `*_files` 是相對於資料目錄的清單檔（一行一個，`#` 開頭為註解），
+//! 方便使用外部維護的清單（如迷因領域的詞彙表）。
+
+use super::keywords::detect_language;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// 規則檔（資料目錄下）
+pub const RULES_FILE: &str = "keyword_filter.json";
+
+/// 關鍵字過濾器
+#[derive(Debug, Clone)]
+pub struct KeywordFilter {
+    /// 包含這些字（不分大小寫）的關鍵字會被去掉
+    pub blocklist: Vec<String>,
+    /// 不是空的時候，只保留包含其中一個字的關鍵字
+    pub allowlist: Vec<String>,
+    /// 最小長度（位元組）
+    pub min_length: usize,
+    /// 依語言的最小長度（字元數，如中文 2 個字），優先於 `min_length`
+    pub min_length_by_language: HashMap<String, usize>,
+    /// 符合任一個就去掉
+    pub block_patterns: Vec<Regex>,
+    /// 不是空的時候，符合其中一個也算在 allowlist 內
+    pub allow_patterns: Vec<Regex>,
+    /// 一定保留的關鍵字（完全相符，不分大小寫），不受其他規則影響
+    pub always_keep: Vec<String>,
+    /// 去掉這些網域（含子網域）的相關網站
+    pub blocked_domains: Vec<String>,
+    /// 不是空的時候，只保留這些網域的相關網站
+    pub allowed_domains: Vec<String>,
+}
+
+impl Default for KeywordFilter {
+    fn default() -> Self {
+        Self {
+            blocklist: vec![
+                "porn".to_string(),
+                "xxx".to_string(),
+                "adult".to_string(),
+            ],
+            allowlist: vec![],
+            min_length: 3,
+            min_length_by_language: HashMap::new(),
+            block_patterns: vec![],
+            allow_patterns: vec![],
+            always_keep: vec![],
+            blocked_domains: vec![],
+            allowed_domains: vec![],
+        }
+    }
+}
+
+/// `keyword_filter.json`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FilterRules {
+    blocklist: Vec<String>,
+    blocklist_files: Vec<String>,
+    allowlist: Vec<String>,
+    allowlist_files: Vec<String>,
+    block_patterns: Vec<String>,
+    allow_patterns: Vec<String>,
+    always_keep: Vec<String>,
+    always_keep_files: Vec<String>,
+    min_length: Option<usize>,
+    min_length_by_language: HashMap<String, usize>,
+    blocked_domains: Vec<String>,
+    allowed_domains: Vec<String>,
+}
+
+impl KeywordFilter {
+    /// 加入資料目錄 `keyword_filter.json` 的規則（不存在時不變）
+    pub fn with_rules_file(self, data_dir: &str) -> Result<Self> {
+        let path = Path::new(data_dir).join(RULES_FILE);
+        if !path.exists() {
+            return Ok(self);
+        }
+        let rules: FilterRules = serde_json::from_str(&fs::read_to_string(&path)?)
+            .with_context(|| format!("無法解析 {}", path.display()))?;
+        self.with_rules(rules, Path::new(data_dir))
+    }
+
+    fn with_rules(mut self, rules: FilterRules, base: &Path) -> Result<Self> {
+        self.blocklist.extend(lowercase(rules.blocklist));
+        self.blocklist.extend(lowercase(read_lists(base, &rules.blocklist_files)?));
+        self.allowlist.extend(lowercase(rules.allowlist));
+        self.allowlist.extend(lowercase(read_lists(base, &rules.allowlist_files)?));
+        self.always_keep.extend(lowercase(rules.always_keep));
+        self.always_keep.extend(lowercase(read_lists(base, &rules.always_keep_files)?));
+        self.block_patterns.extend(compile(&rules.block_patterns)?);
+        self.allow_patterns.extend(compile(&rules.allow_patterns)?);
+        if let Some(min_length) = rules.min_length {
+            self.min_length = min_length;
+        }
+        self.min_length_by_language.extend(
+            rules.min_length_by_language.into_iter().map(|(language, min)| (language.to_lowercase(), min)),
+        );
+        self.blocked_domains.extend(lowercase(rules.blocked_domains));
+        self.allowed_domains.extend(lowercase(rules.allowed_domains));
+        Ok(self)
+    }
+
+    /// 規則數（顯示用，不含最小長度）
+    pub fn rule_count(&self) -> usize {
+        self.blocklist.len()
+            + self.allowlist.len()
+            + self.block_patterns.len()
+            + self.allow_patterns.len()
+            + self.always_keep.len()
+            + self.blocked_domains.len()
+            + self.allowed_domains.len()
+    }
+
+    pub fn filter(&self, keywords: Vec<String>) -> Vec<String> {
+        keywords.into_iter().filter(|kw| self.keep(kw)).collect()
+    }
+
+    fn keep(&self, kw: &str) -> bool {
+        let kw_lower = kw.to_lowercase();
+        if self.always_keep.contains(&kw_lower) {
+            return true;
+        }
+
+        let long_enough = match detect_language(kw).and_then(|language| self.min_length_by_language.get(language)) {
+            Some(&min) => kw.chars().count() >= min,
+            None => kw.len() >= self.min_length,
+        };
+        if !long_enough {
+            return false;
+        }
+
+        if self.blocklist.iter().any(|blocked| kw_lower.contains(&blocked.to_lowercase())) {
+            return false;
+        }
+        if self.block_patterns.iter().any(|pattern| pattern.is_match(kw)) {
+            return false;
+        }
+
+        if !self.allowlist.is_empty() || !self.allow_patterns.is_empty() {
+            return self.allowlist.iter().any(|allowed| kw_lower.contains(&allowed.to_lowercase()))
+                || self.allow_patterns.iter().any(|pattern| pattern.is_match(kw));
+        }
+
+        true
+    }
+
+    /// 依網域過濾相關網站（無法解析的網址在有 `allowed_domains` 時去掉，否則保留）
+    pub fn filter_sites(&self, sites: Vec<String>) -> Vec<String> {
+        if self.blocked_domains.is_empty() && self.allowed_domains.is_empty() {
+            return sites;
+        }
+        sites
+            .into_iter()
+            .filter(|site| {
+                let Some(host) = reqwest::Url::parse(site).ok().and_then(|url| url.host_str().map(str::to_lowercase)) else {
+                    return self.allowed_domains.is_empty();
+                };
+                if self.blocked_domains.iter().any(|domain| matches_domain(&host, domain)) {
+                    return false;
+                }
+                self.allowed_domains.is_empty() || self.allowed_domains.iter().any(|domain| matches_domain(&host, domain))
+            })
+            .collect()
+    }
+}
+
+/// `host` 是 `domain` 或它的子網域
+fn matches_domain(host: &str, domain: &str) -> bool {
+    host == domain || host.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn lowercase(values: Vec<String>) -> impl Iterator<Item = String> {
+    values.into_iter().map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty())
+}
+
+/// 讀取清單檔（一行一個，略過空行與 `#` 註解）
+fn read_lists(base: &Path, files: &[String]) -> Result<Vec<String>> {
+    let mut entries = Vec::new();
+    for file in files {
+        let path = base.join(file);
+        let content = fs::read_to_string(&path).with_context(|| format!("無法讀取清單 {}", path.display()))?;
+        entries.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string),
+        );
+    }
+    Ok(entries)
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).with_context(|| format!("無效的正規表示式: {}", pattern)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_file() {
+        let dir = std::env::temp_dir().join(format!("meme-keyword-filter-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("terms.txt"), "# 迷因詞彙\nSussex\n\n").unwrap();
+        fs::write(dir.join(RULES_FILE), serde_json::json!({
+            "blocklist": ["sex"],
+            "block_patterns": ["^\\d+x\\d+$"],
+            "always_keep_files": ["terms.txt"],
+            "min_length_by_language": { "zh": 2 },
+            "blocked_domains": ["pinterest.com"],
+        }).to_string()).unwrap();
+
+        let filter = KeywordFilter::default().with_rules_file(dir.to_str().unwrap()).unwrap();
+        let keywords = ["doge", "1920x1080", "sexy doge", "Sussex", "梗", "梗圖", "ok"].map(String::from);
+        assert_eq!(filter.filter(keywords.to_vec()), vec!["doge", "Sussex", "梗圖"]);
+
+        let sites = ["https://www.pinterest.com/pin/1", "https://pinterest.com.example.org/a", "https://reddit.com/r/a"].map(String::from);
+        assert_eq!(filter.filter_sites(sites.to_vec()), vec!["https://pinterest.com.example.org/a", "https://reddit.com/r/a"]);
+
+        // 沒有規則檔時不變，無效的正規表示式回報錯誤
+        assert_eq!(KeywordFilter::default().with_rules_file("/nonexistent").unwrap().rule_count(), 3);
+        fs::write(dir.join(RULES_FILE), r#"{ "block_patterns": ["("] }"#).unwrap();
+        assert!(KeywordFilter::default().with_rules_file(dir.to_str().unwrap()).is_err());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,319 @@
+//! 反向搜尋關鍵字的後處理
+//!
+//! 服務拆出來的關鍵字常夾雜網址參數（`q=0.9`）、全形字元與大小寫不一的重複詞。
+//! `KeywordNormalizer` 依序做 Unicode 正規化（NFKC）、轉小寫、去掉雜訊與停用詞、
+//! 繁簡轉換，並可用外部翻譯程式把非目標語言的關鍵字翻成目標語言（保留原文）。
+//!
+//! 翻譯程式與外掛相同，透過 stdin/stdout 以 JSONL 溝通：
+//! `translate` 請求的 `params` 為 `{"texts": [...], "target": "zh-TW"}`，
+//! `result` 是對應順序的譯文陣列（無法翻譯的為 `null`）。
+
+use crate::plugins::{self, PluginProcess};
+use crate::reverse_search::ReverseSearchResult;
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+use unicode_normalization::UnicodeNormalization;
+
+/// 預設的目標語言
+pub const DEFAULT_LANGUAGE: &str = "zh-TW";
+
+/// 常用字的簡體/繁體對照（每兩個字一組；一簡對多繁的字如「发」「后」「里」不轉換）
+const VARIANTS: &str = "\
+    这這个個们們来來时時为為说說国國过過对對会會学學还還没沒见見长長问問门門马馬鸟鳥鱼魚龙龍车車东東丝絲两兩严嚴丧喪临臨丽麗\
+    举舉么麼义義乌烏乐樂乔喬习習乡鄉书書买買乱亂争爭亏虧亚亞产產亲親亿億仅僅从從仓倉仪儀价價众眾优優伟偉传傳伤傷伦倫伪偽体體\
+    侠俠侣侶侦偵侧側侨僑俭儉债債倾傾偿償儿兒兑兌党黨兰蘭关關兴興养養兽獸内內冈岡册冊写寫军軍农農冻凍净淨凉涼减減凤鳳凭憑击擊\
+    刘劉则則刚剛创創删刪别別剧劇剑劍办辦务務动動劳勞势勢区區医醫华華协協单單卖賣卢盧卫衛却卻厂廠厅廳压壓厌厭厕廁县縣参參双雙\
+    变變叙敘号號叹嘆吓嚇吕呂吗嗎吴吳员員听聽启啟呜嗚响響哑啞哗嘩唤喚啸嘯喷噴嘱囑团團园園围圍图圖圆圓圣聖场場坏壞块塊坚堅坛壇\
+    坟墳垒壘垦墾堕墮墙牆壮壯声聲壳殼处處备備头頭夹夾夺奪奋奮奖獎妆妝妇婦妈媽娱娛娄婁婴嬰孙孫宁寧宝寶实實审審宪憲宫宮宽寬宾賓\
+    寻尋导導寿壽将將尔爾尘塵尝嘗尧堯层層属屬岁歲岂豈岛島岭嶺峡峽币幣帅帥师師帐帳带帶帮幫广廣庄莊庆慶库庫应應废廢开開异異弃棄\
+    张張弹彈强強归歸录錄彦彥彻徹径徑忆憶忧憂怀懷态態怜憐总總恋戀恶惡恼惱悦悅悬懸惊驚惧懼惨慘惯慣愤憤懒懶战戰户戶执執扩擴扫掃\
+    扬揚扰擾抚撫抢搶护護报報担擔拟擬拥擁择擇挂掛挤擠挥揮损損换換据據掷擲揽攬摄攝摆擺摇搖敌敵数數斋齋断斷无無旧舊显顯晋晉晓曉\
+    暂暫术術机機杀殺杂雜权權条條杨楊极極构構枪槍柜櫃标標栏欄树樹样樣桥橋档檔梦夢检檢楼樓欢歡欧歐歼殲残殘毁毀毕畢气氣汇匯汉漢\
+    汤湯沟溝沪滬泪淚泽澤洁潔测測济濟浅淺浊濁涛濤涨漲润潤渐漸温溫湾灣湿濕满滿滚滾滞滯灭滅灯燈灵靈灾災炉爐点點炼煉烂爛烦煩烧燒\
+    热熱爱愛爷爺牵牽犹猶状狀独獨狭狹狮獅猎獵猫貓献獻环環现現玛瑪电電画畫畅暢疗療疯瘋监監盖蓋盘盤睁睜矿礦码碼础礎硕碩确確礼禮\
+    祸禍离離称稱积積稳穩穷窮窃竊竞競笔筆笼籠简簡类類粮糧紧緊红紅级級纪紀约約纯純纳納纸紙线線练練组組细細织織终終经經结結绕繞\
+    绘繪给給络絡绝絕统統继繼绩績续續维維综綜绿綠缓緩编編缘緣网網罗羅罚罰职職联聯聪聰肃肅胜勝胆膽脑腦脚腳脸臉腾騰舰艦节節芦蘆\
+    苏蘇苹蘋荣榮药藥获獲莱萊营營蓝藍虑慮虫蟲虽雖蚁蟻蛮蠻补補装裝观觀规規视視览覽觉覺计計认認让讓议議记記讲講许許论論设設访訪\
+    证證评評识識诉訴词詞译譯试試诗詩诚誠话話该該详詳语語误誤请請读讀课課谁誰调調谈談谢謝谱譜贝貝负負贡貢财財责責败敗货貨质質\
+    贩販购購贵貴费費贺賀资資赏賞赖賴赛賽赵趙趋趨跃躍践踐踪蹤轨軌转轉轮輪软軟轻輕载載较較辆輛辈輩辑輯输輸辞辭边邊达達运運进進\
+    远遠违違连連迟遲选選递遞逻邏遗遺邮郵邻鄰郑鄭酱醬释釋鉴鑑针針钓釣钢鋼钥鑰钱錢铁鐵铃鈴银銀链鏈销銷锁鎖错錯键鍵镜鏡闪閃闭閉\
+    闲閒间間闹鬧闻聞阅閱队隊阳陽阴陰阵陣阶階际際陆陸陈陳险險随隨隐隱难難雾霧靓靚韩韓页頁顶頂项項顺順顽頑顾顧预預领領频頻题題\
+    颜顏风風飞飛饭飯饮飲饱飽饼餅馆館驱驅驾駕验驗骂罵骑騎骗騙鲁魯鲜鮮鸡雞鸭鴨麦麥黄黃齐齊齿齒龟龜专專业業丛叢厉厲饿餓猪豬";
+
+/// 簡體 → 繁體
+static TO_TRADITIONAL: LazyLock<HashMap<char, char>> = LazyLock::new(|| {
+    let chars: Vec<char> = VARIANTS.chars().collect();
+    chars.chunks(2).map(|pair| (pair[0], pair[1])).collect()
+});
+
+/// 繁體 → 簡體
+static TO_SIMPLIFIED: LazyLock<HashMap<char, char>> =
+    LazyLock::new(|| TO_TRADITIONAL.iter().map(|(&simplified, &traditional)| (traditional, simplified)).collect());
+
+/// 內建的停用詞（英文、中文、日文，以及圖片網站常見的無意義詞）
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "the", "and", "or", "of", "to", "in", "on", "for", "with", "by", "at", "from", "as",
+    "is", "are", "was", "were", "be", "been", "it", "its", "this", "that", "these", "those",
+    "i", "me", "my", "you", "your", "we", "our", "they", "their", "he", "she", "his", "her",
+    "what", "when", "where", "how", "why", "who", "not", "no", "do", "does", "did", "so", "if",
+    "but", "just", "all", "can", "will", "more", "most", "about", "into", "out", "up", "vs", "via",
+    "image", "images", "picture", "pictures", "photo", "photos", "stock", "free", "download",
+    "jpg", "jpeg", "png", "gif", "webp", "html", "http", "https", "www", "com", "amp",
+    "的", "了", "是", "在", "和", "與", "及", "或", "也", "就", "都", "而", "著", "這", "那",
+    "一個", "我", "你", "他", "她", "它", "我們", "你們", "他們", "嗎", "呢", "吧", "啊",
+    "圖片", "照片", "下載", "免費", "高清", "桌布",
+    "の", "に", "は", "を", "が", "と", "で", "も", "た", "です", "ます", "こと", "これ", "それ",
+    "画像", "写真",
+];
+
+/// 中文關鍵字的寫法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChineseScript {
+    Traditional,
+    Simplified,
+    /// 不轉換
+    Keep,
+}
+
+impl ChineseScript {
+    /// 依語言代碼決定（zh-TW / zh-HK / zh-Hant 為繁體，zh-CN / zh-SG / zh-Hans 為簡體）
+    pub fn for_language(language: &str) -> Self {
+        let lower = language.to_lowercase();
+        if !lower.starts_with("zh") {
+            return Self::Keep;
+        }
+        if ["hant", "tw", "hk", "mo"].iter().any(|tag| lower.contains(tag)) {
+            Self::Traditional
+        } else if ["hans", "cn", "sg"].iter().any(|tag| lower.contains(tag)) {
+            Self::Simplified
+        } else {
+            Self::Keep
+        }
+    }
+
+    /// 轉換對照表中有的字
+    pub fn convert(self, text: &str) -> String {
+        let table = match self {
+            Self::Traditional => &*TO_TRADITIONAL,
+            Self::Simplified => &*TO_SIMPLIFIED,
+            Self::Keep => return text.to_string(),
+        };
+        text.chars().map(|c| table.get(&c).copied().unwrap_or(c)).collect()
+    }
+}
+
+/// 依文字判斷語言（只分辨日、韓、中與拉丁字母，判斷不出來時為 None）
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let has = |range: &[std::ops::RangeInclusive<char>]| text.chars().any(|c| range.iter().any(|r| r.contains(&c)));
+
+    if has(&['\u{3040}'..='\u{309f}', '\u{30a0}'..='\u{30ff}']) {
+        Some("ja")
+    } else if has(&['\u{ac00}'..='\u{d7af}', '\u{1100}'..='\u{11ff}']) {
+        Some("ko")
+    } else if has(&['\u{4e00}'..='\u{9fff}', '\u{3400}'..='\u{4dbf}']) {
+        Some("zh")
+    } else if text.chars().any(|c| c.is_ascii_alphabetic()) {
+        Some("en")
+    } else {
+        None
+    }
+}
+
+/// 把關鍵字翻成目標語言
+pub trait Translator: Send + Sync {
+    fn name(&self) -> &str;
+    /// 依 `texts` 的順序回傳譯文（無法翻譯的為 None）
+    fn translate(&self, texts: &[String], target: &str) -> Result<Vec<Option<String>>>;
+}
+
+/// 以外部程式翻譯（常駐子程式，模型或連線只建立一次）
+pub struct CommandTranslator {
+    name: String,
+    process: PluginProcess,
+}
+
+impl CommandTranslator {
+    pub fn new(command: &str) -> Self {
+        Self {
+            name: format!("command:{}", command),
+            process: PluginProcess::new("translator", command, plugins::DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+impl Translator for CommandTranslator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn translate(&self, texts: &[String], target: &str) -> Result<Vec<Option<String>>> {
+        let result = self.process.call("translate", json!({ "texts": texts, "target": target }))?;
+        let translated: Vec<Option<String>> = serde_json::from_value(result).context("翻譯程式的回應不是字串陣列")?;
+        if translated.len() != texts.len() {
+            anyhow::bail!("翻譯程式回傳 {} 筆，預期 {} 筆", translated.len(), texts.len());
+        }
+        Ok(translated)
+    }
+}
+
+/// 關鍵字後處理
+#[derive(Clone)]
+pub struct KeywordNormalizer {
+    /// 目標語言（BCP 47，如 zh-TW）
+    language: String,
+    script: ChineseScript,
+    stop_words: HashSet<String>,
+    translator: Option<Arc<dyn Translator>>,
+}
+
+impl KeywordNormalizer {
+    /// 以 `language` 為目標語言（決定繁簡轉換與要翻譯哪些關鍵字）
+    pub fn new(language: &str) -> Self {
+        let script = ChineseScript::for_language(language);
+        Self {
+            language: language.to_string(),
+            script,
+            stop_words: STOP_WORDS.iter().map(|w| script.convert(w)).collect(),
+            translator: None,
+        }
+    }
+
+    /// 加入額外的停用詞
+    pub fn with_stop_words(mut self, words: impl IntoIterator<Item = String>) -> Self {
+        let words: Vec<String> = words.into_iter().filter_map(|w| self.clean(&w)).collect();
+        self.stop_words.extend(words);
+        self
+    }
+
+    /// 翻譯非目標語言的關鍵字（譯文加在原文之後）
+    pub fn with_translator(mut self, translator: Arc<dyn Translator>) -> Self {
+        self.translator = Some(translator);
+        self
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn translator(&self) -> Option<&Arc<dyn Translator>> {
+        self.translator.as_ref()
+    }
+
+    /// NFKC、轉小寫、合併空白、去掉頭尾符號、繁簡轉換
+    fn clean(&self, raw: &str) -> Option<String> {
+        let text: String = raw.nfkc().collect::<String>().to_lowercase();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let text = text.trim_matches(|c: char| !c.is_alphanumeric());
+        (!text.is_empty()).then(|| self.script.convert(text))
+    }
+
+    /// 正規化單一關鍵字（雜訊或停用詞回傳 None）
+    pub fn normalize_keyword(&self, raw: &str) -> Option<String> {
+        let keyword = self.clean(raw)?;
+        if is_noise(&keyword) || self.stop_words.contains(&keyword) {
+            return None;
+        }
+        Some(keyword)
+    }
+
+    /// 正規化並去除重複；有翻譯程式時把非目標語言的關鍵字翻譯後加在後面
+    ///
+    /// 翻譯失敗只警告，保留未翻譯的結果。
+    pub fn normalize(&self, keywords: &[String]) -> Vec<String> {
+        let mut normalized: Vec<String> = Vec::new();
+        for keyword in keywords.iter().filter_map(|k| self.normalize_keyword(k)) {
+            if !normalized.contains(&keyword) {
+                normalized.push(keyword);
+            }
+        }
+
+        let Some(translator) = &self.translator else {
+            return normalized;
+        };
+        let target = self.language.split('-').next().unwrap_or_default().to_lowercase();
+        let foreign: Vec<String> = normalized
+            .iter()
+            .filter(|k| detect_language(k).is_some_and(|language| language != target))
+            .cloned()
+            .collect();
+        if foreign.is_empty() {
+            return normalized;
+        }
+
+        match translator.translate(&foreign, &self.language) {
+            Ok(translated) => {
+                for keyword in translated.iter().flatten().filter_map(|t| self.normalize_keyword(t)) {
+                    if !normalized.contains(&keyword) {
+                        normalized.push(keyword);
+                    }
+                }
+            }
+            Err(e) => eout!("    ⚠️  無法翻譯關鍵字（{}）: {}", translator.name(), e),
+        }
+        normalized
+    }
+
+    /// 處理一筆搜尋結果：關鍵字正規化，標題與 best guess 只做繁簡轉換
+    pub fn apply(&self, result: &mut ReverseSearchResult) {
+        result.keywords = self.normalize(&result.keywords);
+        for title in [&mut result.suggested_title, &mut result.best_guess].into_iter().flatten() {
+            *title = self.script.convert(title);
+        }
+    }
+}
+
+/// 不是詞的片段：網址與查詢參數、沒有字母的數字或符號、單一個拉丁字母
+fn is_noise(keyword: &str) -> bool {
+    if ["=", "://", "www.", "&"].iter().any(|marker| keyword.contains(marker)) {
+        return true;
+    }
+    if !keyword.chars().any(char::is_alphabetic) {
+        return true;
+    }
+    let mut chars = keyword.chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 把英文關鍵字加上前綴當作譯文
+    struct PrefixTranslator;
+
+    impl Translator for PrefixTranslator {
+        fn name(&self) -> &str {
+            "prefix"
+        }
+
+        fn translate(&self, texts: &[String], _target: &str) -> Result<Vec<Option<String>>> {
+            Ok(texts.iter().map(|t| (t != "doge").then(|| format!("譯 {}", t))).collect())
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let normalizer = KeywordNormalizer::new("zh-TW");
+        let raw: Vec<String> = [
+            "q=0.9", "Doge", "DOGE", "  Shiba   Inu ", "ＭＥＭＥ", "the", "2024", "“梗图”", "这个梗", "https://x.com/a", "x", "圖片",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(normalizer.normalize(&raw), vec!["doge", "shiba inu", "meme", "梗圖", "這個梗"]);
+
+        let simplified = KeywordNormalizer::new("zh-CN").with_stop_words(["Meme".to_string()]);
+        assert_eq!(simplified.normalize(&raw), vec!["doge", "shiba inu", "梗图", "这个梗"]);
+        assert_eq!(ChineseScript::for_language("en").convert("这个"), "这个");
+
+        let translating = KeywordNormalizer::new("zh-TW").with_translator(Arc::new(PrefixTranslator));
+        let keywords = ["doge", "shiba inu", "柴犬", "ミーム"].map(String::from);
+        assert_eq!(translating.normalize(&keywords), vec!["doge", "shiba inu", "柴犬", "ミーム", "譯 shiba inu", "譯 ミーム"]);
+
+        assert_eq!(detect_language("ミーム"), Some("ja"));
+        assert_eq!(detect_language("밈"), Some("ko"));
+        assert_eq!(detect_language("2024"), None);
+    }
+}
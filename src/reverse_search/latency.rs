@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// 單次搜尋呼叫的耗時（service_latency.jsonl）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyRecord {
+    pub service: String,
+    pub filename: String,
+    pub elapsed_ms: u64,
+    /// 是否成功（失敗的呼叫一樣花時間，也列入統計）
+    pub ok: bool,
+    pub at: DateTime<Utc>,
+}
+
+/// 單一服務的耗時統計
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencySummary {
+    pub service: String,
+    pub calls: usize,
+    pub failures: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    /// 累計耗時
+    pub total_ms: u64,
+}
+
+pub fn append_latency(path: &str, record: &LatencyRecord) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context("無法開啟 service_latency.jsonl")?;
+
+    // 整行一次寫入，並發搜尋時各行不會交錯
+    let line = format!("{}\n", serde_json::to_string(record)?);
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+pub fn load_latencies(path: &str) -> Result<Vec<LatencyRecord>> {
+    if !Path::new(path).exists() {
+        return Ok(vec![]);
+    }
+
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// 各服務的 p50/p95，依 p95 由慢到快排序
+pub fn summarize(records: &[LatencyRecord]) -> Vec<LatencySummary> {
+    let mut by_service: BTreeMap<&str, Vec<&LatencyRecord>> = BTreeMap::new();
+    for record in records {
+        by_service.entry(&record.service).or_default().push(record);
+    }
+
+    let mut summaries: Vec<LatencySummary> = by_service
+        .into_iter()
+        .map(|(service, records)| {
+            let mut elapsed: Vec<u64> = records.iter().map(|r| r.elapsed_ms).collect();
+            elapsed.sort_unstable();
+
+            LatencySummary {
+                service: service.to_string(),
+                calls: records.len(),
+                failures: records.iter().filter(|r| !r.ok).count(),
+                p50_ms: percentile(&elapsed, 50),
+                p95_ms: percentile(&elapsed, 95),
+                total_ms: elapsed.iter().sum(),
+            }
+        })
+        .collect();
+
+    summaries.sort_by(|a, b| b.p95_ms.cmp(&a.p95_ms).then_with(|| a.service.cmp(&b.service)));
+    summaries
+}
+
+/// nearest-rank 百分位數（輸入需已排序）
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(service: &str, elapsed_ms: u64, ok: bool) -> LatencyRecord {
+        LatencyRecord {
+            service: service.to_string(),
+            filename: "a.jpg".to_string(),
+            elapsed_ms,
+            ok,
+            at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let mut records: Vec<LatencyRecord> = (1..=100).map(|ms| record("fast", ms, true)).collect();
+        records.push(record("slow", 5000, false));
+        records.push(record("slow", 3000, true));
+
+        let summaries = summarize(&records);
+        assert_eq!(summaries[0].service, "slow");
+        assert_eq!((summaries[0].p50_ms, summaries[0].p95_ms, summaries[0].failures), (3000, 5000, 1));
+        assert_eq!((summaries[1].p50_ms, summaries[1].p95_ms, summaries[1].calls), (50, 95, 100));
+        assert_eq!(summaries[1].total_ms, 5050);
+    }
+}
@@ -0,0 +1,225 @@
+use super::types::ReverseSearchResult;
+use crate::file_manager::FileManager;
+use crate::media;
+use crate::thumbnail;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// 語料庫圖片的感知雜湊快取
+pub const PHASH_INDEX_FILE: &str = "phash_index.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhashEntry {
+    /// 計算時的內容雜湊，檔案內容換了就重算
+    pub content_hash: String,
+    pub phash: u64,
+}
+
+/// 檔名 -> 感知雜湊（`phash_index.json`）
+///
+/// 解碼整個語料庫很慢，只在新增或內容改變時計算，其餘沿用快取。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhashIndex {
+    pub entries: BTreeMap<String, PhashEntry>,
+}
+
+impl PhashIndex {
+    pub fn load(data_dir: &str) -> Result<Self> {
+        let path = Path::new(data_dir).join(PHASH_INDEX_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content).with_context(|| format!("無法解析 {}", path.display()))
+    }
+
+    /// 先寫暫存檔再改名
+    pub fn save(&self, data_dir: &str) -> Result<()> {
+        let path = Path::new(data_dir).join(PHASH_INDEX_FILE);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string(self)?)?;
+        fs::rename(&temp_path, &path).context("無法寫入 phash_index.json")?;
+        Ok(())
+    }
+
+    /// 補上新圖片、重算內容改變的圖片並移除已不在 metadata 的項目，回傳計算的張數
+    ///
+    /// 無法解碼的圖片（例如影片）直接略過。
+    pub fn update(&mut self, file_manager: &FileManager, metadata: &[ImageMetadata]) -> usize {
+        let current: HashSet<&str> = metadata.iter().map(|m| m.filename.as_str()).collect();
+        self.entries.retain(|filename, _| current.contains(filename.as_str()));
+
+        let mut computed = 0;
+        for meta in metadata {
+            let fresh = self
+                .entries
+                .get(&meta.filename)
+                .is_some_and(|entry| entry.content_hash == meta.content_hash);
+            if fresh {
+                continue;
+            }
+
+            // 原圖移到冷儲存後以縮圖計算（dHash 本來就先縮小）
+            let path = file_manager.get_image_path(&meta.filename);
+            let source = if Path::new(&path).exists() {
+                Some(path.into())
+            } else {
+                thumbnail::find(file_manager, &meta.filename)
+            };
+            if let Some(Ok(phash)) = source.map(media::dhash_file) {
+                self.entries.insert(meta.filename.clone(), PhashEntry {
+                    content_hash: meta.content_hash.clone(),
+                    phash,
+                });
+                computed += 1;
+            }
+        }
+        computed
+    }
+}
+
+/// 本地語料庫中相似的圖片與它已有的標籤
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalMatch {
+    pub filename: String,
+    /// 感知雜湊的漢明距離（0 最相近）
+    pub distance: u32,
+    /// 內容雜湊完全相同
+    pub exact: bool,
+    pub title: Option<String>,
+    pub keywords: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+/// 以內容雜湊與感知雜湊比對語料庫，完全相同的排最前，其餘依距離排序
+///
+/// 標題與關鍵字取自 metadata，沒有時用反向搜尋結果補上。
+pub fn find_matches(
+    content_hash: &str,
+    phash: Option<u64>,
+    metadata: &[ImageMetadata],
+    index: &PhashIndex,
+    results: &[ReverseSearchResult],
+    max_distance: u32,
+    limit: usize,
+) -> Vec<LocalMatch> {
+    let results_by_file: HashMap<&str, Vec<&ReverseSearchResult>> =
+        results.iter().fold(HashMap::new(), |mut map, result| {
+            map.entry(result.filename.as_str()).or_default().push(result);
+            map
+        });
+
+    let mut seen = HashSet::new();
+    let mut matches = Vec::new();
+    for meta in metadata {
+        if !seen.insert(meta.filename.as_str()) {
+            continue;
+        }
+
+        let exact = !content_hash.is_empty() && meta.content_hash == content_hash;
+        let distance = match (phash, index.entries.get(&meta.filename)) {
+            _ if exact => 0,
+            (Some(query), Some(entry)) => media::hamming_distance(query, entry.phash),
+            _ => continue,
+        };
+        if distance > max_distance {
+            continue;
+        }
+
+        let searched = results_by_file.get(meta.filename.as_str()).map(Vec::as_slice).unwrap_or_default();
+        let title = meta
+            .suggested_title
+            .clone()
+            .or_else(|| searched.iter().find_map(|r| r.suggested_title.clone()))
+            .or_else(|| searched.iter().find_map(|r| r.best_guess.clone()));
+        let mut keywords = meta.keywords.clone();
+        for result in searched {
+            for keyword in &result.keywords {
+                if !keywords.contains(keyword) {
+                    keywords.push(keyword.clone());
+                }
+            }
+        }
+
+        matches.push(LocalMatch {
+            filename: meta.filename.clone(),
+            distance,
+            exact,
+            title,
+            keywords,
+            tags: meta.tags.clone(),
+        });
+    }
+
+    matches.sort_by_key(|m| (!m.exact, m.distance));
+    matches.truncate(limit);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn meta(filename: &str, content_hash: &str, title: Option<&str>) -> ImageMetadata {
+        serde_json::from_value(json!({
+            "filename": filename,
+            "description": "",
+            "url": "",
+            "content_hash": content_hash,
+            "page_number": 1,
+            "downloaded_at": "2024-01-01T00:00:00Z",
+            "suggested_title": title,
+            "tags": ["reaction"],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let metadata = vec![
+            meta("drake.jpg", "aaa", Some("Drake Hotline Bling")),
+            meta("doge.jpg", "bbb", None),
+            meta("far.jpg", "ccc", Some("Unrelated")),
+            meta("copy.jpg", "ddd", None),
+        ];
+        let mut index = PhashIndex::default();
+        for (filename, content_hash, phash) in [
+            ("drake.jpg", "aaa", 0b1111u64),
+            ("doge.jpg", "bbb", 0b0111),
+            ("far.jpg", "ccc", u64::MAX),
+        ] {
+            index.entries.insert(filename.to_string(), PhashEntry {
+                content_hash: content_hash.to_string(),
+                phash,
+            });
+        }
+        let results: Vec<ReverseSearchResult> = serde_json::from_value(json!([{
+            "filename": "doge.jpg",
+            "service": "bing",
+            "suggested_title": null,
+            "keywords": ["doge", "shiba"],
+            "related_sites": [],
+            "best_guess": "Doge",
+            "searched_at": "2024-01-01T00:00:00Z",
+        }]))
+        .unwrap();
+
+        let matches = find_matches("ddd", Some(0b1111), &metadata, &index, &results, 10, 5);
+        let names: Vec<_> = matches.iter().map(|m| m.filename.as_str()).collect();
+        assert_eq!(names, vec!["copy.jpg", "drake.jpg", "doge.jpg"]);
+        assert!(matches[0].exact);
+        assert_eq!(matches[1].title.as_deref(), Some("Drake Hotline Bling"));
+        assert_eq!(matches[2].distance, 1);
+        assert_eq!(matches[2].title.as_deref(), Some("Doge"));
+        assert_eq!(matches[2].keywords, vec!["doge", "shiba"]);
+
+        assert_eq!(find_matches("zzz", Some(0b1111), &metadata, &index, &results, 10, 1).len(), 1);
+        assert!(find_matches("zzz", None, &metadata, &index, &results, 10, 5).is_empty());
+    }
+}
@@ -2,13 +2,26 @@
 pub mod types;
 pub mod trait_def;
 pub mod engine;
+#[allow(dead_code)]
 pub mod utils;
 pub mod services;
+pub mod latency;
+pub mod block;
+pub mod cache;
+pub mod writer;
+pub mod local;
+pub mod aggregate;
+pub mod keywords;
+pub mod filter;
+pub mod redo;
 
 // 重新導出常用項目（讓外部可以用 reverse_search::XXX 直接存取）
-pub use types::{ReverseSearchResult, SearchProgress, KeywordFilter};
+pub use types::{ReverseSearchResult, KeywordFilter};
 pub use trait_def::ReverseSearchService;
 pub use engine::ReverseSearchEngine;
+pub use block::ServiceBlocked;
+pub use aggregate::{AggregatedResult, ReverseSearchAggregator};
+pub use keywords::KeywordNormalizer;
 
 use anyhow::Result;
 use std::fs;
@@ -30,22 +43,38 @@ pub fn load_all_results(results_file: &str) -> Result<Vec<ReverseSearchResult>>
     Ok(results)
 }
 
-/// 顯示統計報告
-pub fn print_statistics(results_file: &str) -> Result<()> {
+/// 重寫搜尋結果檔（先寫暫存檔再改名）
+pub fn rewrite_all_results(results_file: &str, results: &[ReverseSearchResult]) -> Result<()> {
+    use std::io::Write;
+    
+    let temp_path = format!("{}.tmp", results_file);
+    let mut writer = std::io::BufWriter::new(fs::File::create(&temp_path)?);
+    
+    for result in results {
+        writeln!(writer, "{}", serde_json::to_string(result)?)?;
+    }
+    writer.flush()?;
+    
+    fs::rename(&temp_path, results_file)?;
+    Ok(())
+}
+
+/// 顯示統計報告（含 service_latency.jsonl 的各服務回應時間）
+pub fn print_statistics(results_file: &str, latency_file: &str) -> Result<()> {
     let results = load_all_results(results_file)?;
     
     if results.is_empty() {
-        println!("⚠️  尚無搜尋結果");
+        out!("⚠️  尚無搜尋結果");
         return Ok(());
     }
     
-    println!("\n╔══════════════════════════════════╗");
-    println!("║   📊 反向搜尋統計報告           ║");
-    println!("╠══════════════════════════════════╣");
-    println!("║ 總搜尋數:   {:>18} ║", results.len());
+    out!("\n╔══════════════════════════════════╗");
+    out!("║   📊 反向搜尋統計報告           ║");
+    out!("╠══════════════════════════════════╣");
+    out!("║ 總搜尋數:   {:>18} ║", results.len());
     
     let with_title = results.iter().filter(|r| r.best_guess.is_some()).count();
-    println!("║ 找到標題:   {:>18} ║", with_title);
+    out!("║ 找到標題:   {:>18} ║", with_title);
     
     let total_keywords: usize = results.iter().map(|r| r.keywords.len()).sum();
     let avg_keywords = if !results.is_empty() {
@@ -53,9 +82,9 @@ pub fn print_statistics(results_file: &str) -> Result<()> {
     } else {
         0.0
     };
-    println!("║ 平均關鍵字: {:>18.1} ║", avg_keywords);
+    out!("║ 平均關鍵字: {:>18.1} ║", avg_keywords);
     
-    println!("╚══════════════════════════════════╝\n");
+    out!("╚══════════════════════════════════╝\n");
     
     // 按服務統計
     use std::collections::HashMap;
@@ -64,23 +93,44 @@ pub fn print_statistics(results_file: &str) -> Result<()> {
         *by_service.entry(result.service.clone()).or_insert(0) += 1;
     }
     
-    println!("📊 各服務統計:");
+    out!("📊 各服務統計:");
     for (service, count) in by_service {
-        println!("  - {}: {} 次", service, count);
+        out!("  - {}: {} 次", service, count);
+    }
+    out!();
+    
+    let latencies = latency::summarize(&latency::load_latencies(latency_file)?);
+    if !latencies.is_empty() {
+        out!("⏱️  各服務回應時間（依 p95 由慢到快）:");
+        out!("  {:<16} {:>7} {:>6} {:>9} {:>9} {:>10}", "服務", "呼叫", "失敗", "p50", "p95", "累計");
+        for summary in &latencies {
+            out!(
+                "  {:<16} {:>7} {:>6} {:>7}ms {:>7}ms {:>9.1}m",
+                summary.service,
+                summary.calls,
+                summary.failures,
+                summary.p50_ms,
+                summary.p95_ms,
+                summary.total_ms as f64 / 60_000.0,
+            );
+        }
+        out!();
     }
-    println!();
     
     // 顯示範例
-    println!("📋 範例結果 (前 5 個):\n");
+    out!("📋 範例結果 (前 5 個):\n");
     for (i, result) in results.iter().take(5).enumerate() {
-        println!("{}. {} [{}]", i + 1, result.filename, result.service);
+        out!("{}. {} [{}]", i + 1, result.filename, result.service);
         if let Some(title) = &result.best_guess {
-            println!("   標題: {}", title);
+            out!("   標題: {}", title);
+        }
+        if let Some(similarity) = result.similarity {
+            out!("   相似度: {:.1}%", similarity);
         }
         if !result.keywords.is_empty() {
-            println!("   關鍵字: {}", result.keywords.join(", "));
+            out!("   關鍵字: {}", result.keywords.join(", "));
         }
-        println!();
+        out!();
     }
     
     Ok(())
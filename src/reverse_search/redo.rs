@@ -0,0 +1,124 @@
+//! 重新搜尋空結果或失敗的圖片（`search --redo-empty`、`search --redo-service <name>`）
+//!
+//! 重新搜尋的結果先照常附加到結果檔，完成後再取代舊的那筆（留在原本的位置），
+//! 結果檔不會多出同一張圖片、同一個服務的重複紀錄。
+
+use super::types::{ReverseSearchResult, SearchProgress};
+use std::collections::{BTreeSet, HashMap};
+
+/// 需要重新搜尋的（檔名, 服務）
+pub type RedoTarget = (String, String);
+
+/// 沒有關鍵字也沒有 best guess 的結果
+pub fn needs_redo(result: &ReverseSearchResult) -> bool {
+    result.keywords.is_empty() && result.best_guess.is_none()
+}
+
+/// 已記錄完成，但最新的結果是空的、或沒有結果（搜尋失敗）的圖片與服務
+pub fn find_targets(progress: &SearchProgress, results: &[ReverseSearchResult], services: &[&str]) -> BTreeSet<RedoTarget> {
+    let mut latest: HashMap<(&str, &str), &ReverseSearchResult> = HashMap::new();
+    for result in results {
+        let key = (result.filename.as_str(), result.service.as_str());
+        match latest.get(&key) {
+            Some(existing) if existing.searched_at > result.searched_at => {}
+            _ => {
+                latest.insert(key, result);
+            }
+        }
+    }
+
+    let mut targets = BTreeSet::new();
+    for (filename, completed) in &progress.completed {
+        for service in services.iter().filter(|service| completed.contains(**service)) {
+            if latest.get(&(filename.as_str(), *service)).is_none_or(|result| needs_redo(result)) {
+                targets.insert((filename.clone(), service.to_string()));
+            }
+        }
+    }
+    targets
+}
+
+/// 以重新搜尋的結果取代舊結果：每組只留最新的一筆，放在最早那筆的位置，回傳去掉的筆數
+pub fn replace_in_place(results: Vec<ReverseSearchResult>, targets: &BTreeSet<RedoTarget>) -> (Vec<ReverseSearchResult>, usize) {
+    let is_target = |result: &ReverseSearchResult| targets.contains(&(result.filename.clone(), result.service.clone()));
+
+    // 同時間的取後寫入的
+    let mut latest: HashMap<(String, String), ReverseSearchResult> = HashMap::new();
+    for result in results.iter().filter(|r| is_target(r)) {
+        let key = (result.filename.clone(), result.service.clone());
+        match latest.get(&key) {
+            Some(existing) if existing.searched_at > result.searched_at => {}
+            _ => {
+                latest.insert(key, result.clone());
+            }
+        }
+    }
+
+    let total = results.len();
+    let mut replaced = Vec::with_capacity(total);
+    for result in results {
+        if !is_target(&result) {
+            replaced.push(result);
+        } else if let Some(newest) = latest.remove(&(result.filename.clone(), result.service.clone())) {
+            replaced.push(newest);
+        }
+    }
+    let removed = total - replaced.len();
+    (replaced, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn result(filename: &str, service: &str, keywords: &[&str], day: u32) -> ReverseSearchResult {
+        ReverseSearchResult {
+            filename: filename.to_string(),
+            service: service.to_string(),
+            suggested_title: None,
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            related_sites: vec![],
+            best_guess: None,
+            similarity: None,
+            searched_at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_redo_targets() {
+        let mut progress = SearchProgress::new();
+        for filename in ["a.jpg", "b.jpg", "c.jpg"] {
+            progress.add_completed(filename, "bing");
+            progress.add_completed(filename, "tineye");
+        }
+        let results = vec![
+            result("a.jpg", "bing", &[], 1),
+            result("a.jpg", "tineye", &["3 matches"], 1),
+            result("b.jpg", "bing", &["doge"], 1),
+            result("b.jpg", "tineye", &["1 matches"], 1),
+            // c.jpg 的 bing 搜尋失敗，沒有結果；tineye 舊的是空結果，後來重新搜尋過
+            result("c.jpg", "tineye", &[], 1),
+            result("c.jpg", "tineye", &["doge"], 2),
+        ];
+
+        let targets = find_targets(&progress, &results, &["bing", "tineye"]);
+        let expected: BTreeSet<RedoTarget> = [("a.jpg", "bing"), ("c.jpg", "bing")]
+            .iter()
+            .map(|(f, s)| (f.to_string(), s.to_string()))
+            .collect();
+        assert_eq!(targets, expected);
+        assert_eq!(find_targets(&progress, &results, &["bing"]).len(), 2);
+
+        // 重新搜尋後附加的新結果取代 a.jpg 的舊結果
+        let mut appended = results.clone();
+        appended.push(result("a.jpg", "bing", &["wow"], 3));
+        appended.push(result("c.jpg", "bing", &["shiba"], 3));
+        let (replaced, removed) = replace_in_place(appended, &targets);
+        assert_eq!(removed, 1);
+        assert_eq!(replaced.len(), results.len() + 1);
+        assert_eq!(replaced[0].keywords, vec!["wow"]);
+        assert_eq!(replaced.last().unwrap().keywords, vec!["shiba"]);
+        assert_eq!(replaced[5].keywords, vec!["doge"]);
+    }
+}
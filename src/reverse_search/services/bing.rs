@@ -1,20 +1,52 @@
 use crate::types::ImageMetadata;
+use crate::headers::HeaderRotator;
+use crate::proxy::{ProxyConfig, ProxyRotator};
+use crate::rate_limit::AdaptiveRateLimiter;
 use crate::reverse_search::{
+    block,
     trait_def::ReverseSearchService,
     types::{ReverseSearchResult, KeywordFilter},
 };
 use anyhow::Result;
+use std::sync::Arc;
+use base64::Engine;
+use std::path::Path;
 use std::time::Duration;
 use scraper::{Html, Selector};
 use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT, ACCEPT};
 
 pub struct BingService {
-    client: reqwest::Client,
+    clients: ProxyRotator,
     filter: KeywordFilter,
 }
 
 impl BingService {
     pub fn new(filter: KeywordFilter) -> Result<Self> {
+        let clients = ProxyRotator::shared(&ProxyConfig::default(), "bing", Self::client_builder)?;
+        
+        Ok(Self { clients, filter })
+    }
+    
+    /// 透過代理搜尋
+    pub fn with_proxies(mut self, config: &ProxyConfig) -> Result<Self> {
+        self.clients.set_proxies(config, Self::client_builder)?;
+        Ok(self)
+    }
+    
+    /// 使用共用的自適應限流器（起始間隔為 `suggested_delay_ms`）
+    pub fn with_rate_limiter(mut self, limiter: Arc<AdaptiveRateLimiter>) -> Self {
+        let delay = self.suggested_delay_ms();
+        self.clients.set_rate_limiter(limiter, delay);
+        self
+    }
+    
+    /// 輪替 User-Agent 並加上網站的 headers（headers.json）
+    pub fn with_headers(mut self, headers: Option<Arc<HeaderRotator>>) -> Self {
+        self.clients.set_headers(headers);
+        self
+    }
+    
+    fn client_builder() -> reqwest::ClientBuilder {
         let mut headers = HeaderMap::new();
         headers.insert(USER_AGENT, HeaderValue::from_static(
             "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36"
@@ -23,12 +55,30 @@ impl BingService {
             "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"
         ));
         
-        let client = reqwest::Client::builder()
+        reqwest::Client::builder()
             .timeout(Duration::from_secs(30))
             .default_headers(headers)
-            .build()?;
+    }
+    
+    /// 解析 Bing 結果頁面
+    fn build_result(&self, html: &str, metadata: &ImageMetadata) -> ReverseSearchResult {
+        let document = Html::parse_document(html);
         
-        Ok(Self { client, filter })
+        let best_guess = extract_best_guess(&document);
+        let mut keywords = extract_keywords(&document);
+        keywords = self.filter.filter(keywords);
+        let related_sites = self.filter.filter_sites(extract_related_sites(&document));
+        
+        ReverseSearchResult {
+            filename: metadata.filename.clone(),
+            service: self.name().to_string(),
+            suggested_title: best_guess.clone(),
+            keywords,
+            related_sites,
+            best_guess,
+            similarity: None,
+            searched_at: chrono::Utc::now(),
+        }
     }
 }
 
@@ -44,29 +94,42 @@ impl ReverseSearchService for BingService {
             urlencoding::encode(&metadata.url)
         );
         
-        let html = self.client
-            .get(&search_url)
-            .send()
+        let html = self.clients
+            .send(|client| client.get(&search_url))
             .await?
             .text()
             .await?;
         
-        let document = Html::parse_document(&html);
+        block::check(self.name(), &html)?;
+        Ok(self.build_result(&html, metadata))
+    }
+    
+    async fn search_by_upload(
+        &self,
+        path: &Path,
+        metadata: &ImageMetadata,
+    ) -> Result<ReverseSearchResult> {
+        let bytes = tokio::fs::read(path).await?;
         
-        let best_guess = extract_best_guess(&document);
-        let mut keywords = extract_keywords(&document);
-        keywords = self.filter.filter(keywords);
-        let related_sites = extract_related_sites(&document);
+        // Bing 的上傳表單以 base64 傳送圖片內容
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&bytes);
         
-        Ok(ReverseSearchResult {
-            filename: metadata.filename.clone(),
-            service: self.name().to_string(),
-            suggested_title: best_guess.clone(),
-            keywords,
-            related_sites,
-            best_guess,
-            searched_at: chrono::Utc::now(),
-        })
+        let html = self.clients
+            .send(|client| {
+                client
+                    .post("https://www.bing.com/images/search?view=detailv2&iss=sbi&FORM=SBIIDP")
+                    .multipart(reqwest::multipart::Form::new().text("imageBin", encoded.clone()))
+            })
+            .await?
+            .text()
+            .await?;
+        
+        block::check(self.name(), &html)?;
+        Ok(self.build_result(&html, metadata))
+    }
+    
+    fn supports_upload(&self) -> bool {
+        true
     }
     
     fn suggested_delay_ms(&self) -> u64 {
@@ -152,4 +215,23 @@ fn extract_related_sites(document: &Html) -> Vec<String> {
     }
     
     sites
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reverse_search::services::golden;
+    
+    #[test]
+    fn test_extract_golden() {
+        let html = golden::fixture("bing");
+        assert_eq!(block::detect_block(&html), None);
+        
+        let document = Html::parse_document(&html);
+        golden::assert_golden("bing", &serde_json::json!({
+            "best_guess": extract_best_guess(&document),
+            "keywords": extract_keywords(&document),
+            "related_sites": extract_related_sites(&document),
+        }));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::types::ImageMetadata;
+use crate::proxy::{ProxyConfig, ProxyRotator};
+use crate::rate_limit::AdaptiveRateLimiter;
+use crate::reverse_search::{
+    trait_def::ReverseSearchService,
+    types::{ReverseSearchResult, KeywordFilter},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// API 設定檔（資料目錄下）
+pub const CONFIG_FILE: &str = "bing_visual.json";
+
+/// 存放 subscription key 的環境變數（優先於設定檔）
+pub const KEY_ENV: &str = "BING_VISUAL_SEARCH_KEY";
+
+/// 預設的 API 端點
+pub const DEFAULT_ENDPOINT: &str = "https://api.bing.microsoft.com/v7.0/images/visualsearch";
+
+/// 最多保留的相關網頁
+const MAX_PAGES: usize = 20;
+
+/// `bing_visual.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BingVisualConfig {
+    pub api_key: Option<String>,
+    /// 自訂端點（Azure 上自己的資源）
+    pub endpoint: String,
+    /// 市場代碼（影響 best guess 與相關搜尋的語言）
+    pub market: String,
+}
+
+impl Default for BingVisualConfig {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            market: "zh-TW".to_string(),
+        }
+    }
+}
+
+impl BingVisualConfig {
+    /// 讀取資料目錄的 bing_visual.json（不存在時用預設值），再以環境變數的 key 覆寫
+    pub fn load(data_dir: &str) -> Result<Self> {
+        let path = Path::new(data_dir).join(CONFIG_FILE);
+        let mut config: Self = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?).context("無法解析 bing_visual.json")?
+        } else {
+            Self::default()
+        };
+        
+        if let Ok(key) = std::env::var(KEY_ENV) && !key.trim().is_empty() {
+            config.api_key = Some(key.trim().to_string());
+        }
+        Ok(config)
+    }
+    
+    /// 是否已設定 key
+    pub fn has_key(&self) -> bool {
+        self.api_key.as_deref().is_some_and(|key| !key.is_empty())
+    }
+}
+
+/// Bing Visual Search 官方 API（Azure）
+///
+/// 回傳的是結構化 JSON，不像 `BingService` 依賴結果頁的 HTML selector：
+/// 標籤與相關搜尋當作關鍵字，`BestRepresentativeQuery` 當作 best guess，
+/// `PagesIncluding` 的網頁當作相關網站。
+pub struct BingVisualApiService {
+    config: BingVisualConfig,
+    api_key: String,
+    clients: ProxyRotator,
+    filter: KeywordFilter,
+}
+
+impl BingVisualApiService {
+    pub fn new(config: BingVisualConfig, filter: KeywordFilter) -> Result<Self> {
+        let api_key = config.api_key.clone().filter(|key| !key.is_empty()).with_context(|| {
+            format!("未設定 Bing Visual Search API key（環境變數 {} 或資料目錄的 {}）", KEY_ENV, CONFIG_FILE)
+        })?;
+        let clients = ProxyRotator::shared(&ProxyConfig::default(), "bing-api", Self::client_builder)?;
+        
+        Ok(Self { config, api_key, clients, filter })
+    }
+    
+    /// 透過代理搜尋
+    pub fn with_proxies(mut self, config: &ProxyConfig) -> Result<Self> {
+        self.clients.set_proxies(config, Self::client_builder)?;
+        Ok(self)
+    }
+    
+    /// 使用共用的自適應限流器（起始間隔為 `suggested_delay_ms`）
+    pub fn with_rate_limiter(mut self, limiter: Arc<AdaptiveRateLimiter>) -> Self {
+        let delay = self.suggested_delay_ms();
+        self.clients.set_rate_limiter(limiter, delay);
+        self
+    }
+    
+    fn client_builder() -> reqwest::ClientBuilder {
+        reqwest::Client::builder().timeout(Duration::from_secs(30))
+    }
+    
+    /// 送出 multipart 請求（表單不能重複使用，每次送出時重新建立）並解析回應
+    async fn request(&self, form: impl Fn() -> reqwest::multipart::Form) -> Result<Value> {
+        let response = self
+            .clients
+            .send(|client| {
+                client
+                    .post(&self.config.endpoint)
+                    .query(&[("mkt", self.config.market.as_str()), ("setLang", self.config.market.as_str())])
+                    .header("Ocp-Apim-Subscription-Key", &self.api_key)
+                    .multipart(form())
+            })
+            .await?;
+        let status = response.status();
+        let body: Value = response.json().await.context("無法解析 Bing Visual Search API 回應")?;
+        
+        if !status.is_success() {
+            let message = body["errors"][0]["message"].as_str().unwrap_or_default();
+            anyhow::bail!("Bing Visual Search API 回應 HTTP {}: {}", status, message);
+        }
+        Ok(body)
+    }
+    
+    fn build_result(&self, response: &Value, metadata: &ImageMetadata) -> ReverseSearchResult {
+        let best_guess = extract_best_guess(response);
+        
+        ReverseSearchResult {
+            filename: metadata.filename.clone(),
+            service: self.name().to_string(),
+            suggested_title: best_guess.clone(),
+            keywords: self.filter.filter(extract_keywords(response)),
+            related_sites: self.filter.filter_sites(extract_pages(response)),
+            best_guess,
+            similarity: None,
+            searched_at: chrono::Utc::now(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ReverseSearchService for BingVisualApiService {
+    fn name(&self) -> &str {
+        "bing-api"
+    }
+    
+    async fn search(&self, metadata: &ImageMetadata) -> Result<ReverseSearchResult> {
+        let knowledge_request = serde_json::json!({
+            "imageInfo": { "url": metadata.url }
+        })
+        .to_string();
+        
+        let response = self
+            .request(|| reqwest::multipart::Form::new().text("knowledgeRequest", knowledge_request.clone()))
+            .await?;
+        Ok(self.build_result(&response, metadata))
+    }
+    
+    async fn search_by_upload(
+        &self,
+        path: &Path,
+        metadata: &ImageMetadata,
+    ) -> Result<ReverseSearchResult> {
+        let bytes = tokio::fs::read(path).await?;
+        let mime = crate::media::detect_format(&bytes).mime_type().unwrap_or("image/jpeg");
+        
+        let response = self
+            .request(|| {
+                let part = reqwest::multipart::Part::bytes(bytes.clone())
+                    .file_name(metadata.filename.clone())
+                    .mime_str(mime)
+                    .expect("MIME 類型固定為合法值");
+                reqwest::multipart::Form::new().part("image", part)
+            })
+            .await?;
+        Ok(self.build_result(&response, metadata))
+    }
+    
+    fn supports_upload(&self) -> bool {
+        true
+    }
+    
+    fn requires_api_key(&self) -> bool {
+        true
+    }
+    
+    fn suggested_delay_ms(&self) -> u64 {
+        // 免費方案每秒 3 個請求
+        500
+    }
+}
+
+/// 所有標籤的 action
+fn actions(response: &Value) -> impl Iterator<Item = &Value> {
+    response["tags"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| tag["actions"].as_array())
+        .flatten()
+}
+
+/// `BestRepresentativeQuery` 的查詢字串
+fn extract_best_guess(response: &Value) -> Option<String> {
+    actions(response)
+        .filter(|action| action["actionType"] == "BestRepresentativeQuery")
+        .find_map(|action| action["displayName"].as_str())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// 標籤名稱與相關搜尋
+fn extract_keywords(response: &Value) -> Vec<String> {
+    let tags = response["tags"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| tag["displayName"].as_str());
+    let related = actions(response)
+        .filter(|action| action["actionType"] == "RelatedSearches")
+        .filter_map(|action| action["data"]["value"].as_array())
+        .flatten()
+        .filter_map(|item| item["displayText"].as_str().or_else(|| item["text"].as_str()));
+    
+    let mut keywords: Vec<String> = tags
+        .chain(related)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    
+    keywords.sort();
+    keywords.dedup();
+    
+    keywords
+}
+
+/// 包含這張圖片的網頁（`PagesIncluding`）
+fn extract_pages(response: &Value) -> Vec<String> {
+    let mut pages: Vec<String> = Vec::new();
+    let urls = actions(response)
+        .filter(|action| action["actionType"] == "PagesIncluding")
+        .filter_map(|action| action["data"]["value"].as_array())
+        .flatten()
+        .filter_map(|page| page["hostPageUrl"].as_str());
+    
+    for url in urls {
+        if !pages.iter().any(|seen| seen == url) {
+            pages.push(url.to_string());
+        }
+        if pages.len() == MAX_PAGES {
+            break;
+        }
+    }
+    
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reverse_search::services::golden;
+    
+    #[test]
+    fn test_extract_golden() {
+        let response: Value = serde_json::from_str(&golden::fixture_file("bing_visual.json")).unwrap();
+        golden::assert_golden("bing_visual", &serde_json::json!({
+            "best_guess": extract_best_guess(&response),
+            "keywords": extract_keywords(&response),
+            "pages": extract_pages(&response),
+        }));
+        
+        assert_eq!(extract_best_guess(&serde_json::json!({ "tags": [] })), None);
+        assert!(BingVisualApiService::new(BingVisualConfig::default(), KeywordFilter::default()).is_err());
+    }
+}
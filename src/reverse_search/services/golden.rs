@@ -0,0 +1,45 @@
+//! 服務擷取函式的 golden test 工具
+//!
+//! 結果頁的 fixture 放在 `fixtures/<name>.html`（API 回應用 `fixture_file` 讀取其他副檔名），
+//! 預期輸出在 `fixtures/<name>.golden.json`。
+//! 改了 selector 而輸出確實應該改變時，以 `UPDATE_GOLDEN=1 cargo test` 重新產生並檢查 diff。
+
+use std::fs;
+use std::path::PathBuf;
+
+fn fixture_path(file: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/reverse_search/services/fixtures")
+        .join(file)
+}
+
+/// 讀取結果頁 fixture
+pub fn fixture(name: &str) -> String {
+    fixture_file(&format!("{}.html", name))
+}
+
+/// 讀取 fixtures 目錄下的檔案
+pub fn fixture_file(file: &str) -> String {
+    let path = fixture_path(file);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("無法讀取 {}: {}", path.display(), e))
+}
+
+/// 與 golden 檔比對（設定 UPDATE_GOLDEN 時改為寫入）
+pub fn assert_golden(name: &str, actual: &serde_json::Value) {
+    let path = fixture_path(&format!("{}.golden.json", name));
+    let pretty = format!("{}\n", serde_json::to_string_pretty(actual).unwrap());
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, pretty).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("無法讀取 {}（以 UPDATE_GOLDEN=1 產生）: {}", path.display(), e));
+    let expected: serde_json::Value = serde_json::from_str(&expected).unwrap();
+    assert_eq!(
+        actual, &expected,
+        "{} 的擷取結果與 golden 檔不同（確定是預期的改變時以 UPDATE_GOLDEN=1 更新）\n實際:\n{}",
+        name, pretty
+    );
+}
@@ -1,20 +1,51 @@
 use crate::types::ImageMetadata;
+use crate::headers::HeaderRotator;
+use crate::proxy::{ProxyConfig, ProxyRotator};
+use crate::rate_limit::AdaptiveRateLimiter;
 use crate::reverse_search::{
+    block,
     trait_def::ReverseSearchService,
     types::{ReverseSearchResult, KeywordFilter},
     utils,
 };
 use anyhow::Result;
+use std::sync::Arc;
 use std::time::Duration;
 use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT, ACCEPT, ACCEPT_LANGUAGE};
 
+/// 舊的 `searchbyimage` 端點（Google 已停用，通常被擋；改用 `lens::GoogleLensService`）
 pub struct GoogleUrlService {
-    client: reqwest::Client,
+    clients: ProxyRotator,
     filter: KeywordFilter,
 }
 
 impl GoogleUrlService {
     pub fn new(filter: KeywordFilter) -> Result<Self> {
+        let clients = ProxyRotator::shared(&ProxyConfig::default(), "google", Self::client_builder)?;
+        
+        Ok(Self { clients, filter })
+    }
+    
+    /// 透過代理搜尋
+    pub fn with_proxies(mut self, config: &ProxyConfig) -> Result<Self> {
+        self.clients.set_proxies(config, Self::client_builder)?;
+        Ok(self)
+    }
+    
+    /// 使用共用的自適應限流器（起始間隔為 `suggested_delay_ms`）
+    pub fn with_rate_limiter(mut self, limiter: Arc<AdaptiveRateLimiter>) -> Self {
+        let delay = self.suggested_delay_ms();
+        self.clients.set_rate_limiter(limiter, delay);
+        self
+    }
+    
+    /// 輪替 User-Agent 並加上網站的 headers（headers.json）
+    pub fn with_headers(mut self, headers: Option<Arc<HeaderRotator>>) -> Self {
+        self.clients.set_headers(headers);
+        self
+    }
+    
+    fn client_builder() -> reqwest::ClientBuilder {
         // 建立更真實的 headers
         let mut headers = HeaderMap::new();
         headers.insert(USER_AGENT, HeaderValue::from_static(
@@ -27,13 +58,10 @@ impl GoogleUrlService {
             "zh-TW,zh;q=0.9,en-US;q=0.8,en;q=0.7"
         ));
         
-        let client = reqwest::Client::builder()
+        reqwest::Client::builder()
             .timeout(Duration::from_secs(30))
             .default_headers(headers)
             .cookie_store(true)  // 啟用 cookie
-            .build()?;
-        
-        Ok(Self { client, filter })
     }
 }
 
@@ -44,20 +72,15 @@ impl ReverseSearchService for GoogleUrlService {
     }
     
     async fn search(&self, metadata: &ImageMetadata) -> Result<ReverseSearchResult> {
-        // 檢查是否為 404 頁面
         let html = self.fetch_with_retry(&metadata.url).await?;
+        block::check(self.name(), &html)?;
         
-        // 如果是 404 頁面，提早回傳
+        // 404 錯誤頁也是被擋下的回應，不當成搜尋結果
         if html.contains("404") && html.contains("Error") {
-            return Ok(ReverseSearchResult {
-                filename: metadata.filename.clone(),
+            return Err(block::ServiceBlocked {
                 service: self.name().to_string(),
-                suggested_title: Some("⚠️ Google blocked request".to_string()),
-                keywords: vec!["blocked".to_string()],
-                related_sites: vec![],
-                best_guess: None,
-                searched_at: chrono::Utc::now(),
-            });
+                reason: "404 錯誤頁".to_string(),
+            }.into());
         }
         
         let document = scraper::Html::parse_document(&html);
@@ -65,7 +88,7 @@ impl ReverseSearchService for GoogleUrlService {
         let best_guess = utils::extract_best_guess(&document);
         let mut keywords = utils::extract_keywords(&document);
         keywords = self.filter.filter(keywords);
-        let related_sites = utils::extract_related_sites(&document);
+        let related_sites = self.filter.filter_sites(utils::extract_related_sites(&document));
         
         Ok(ReverseSearchResult {
             filename: metadata.filename.clone(),
@@ -74,6 +97,7 @@ impl ReverseSearchService for GoogleUrlService {
             keywords,
             related_sites,
             best_guess,
+            similarity: None,
             searched_at: chrono::Utc::now(),
         })
     }
@@ -96,23 +120,23 @@ impl GoogleUrlService {
                 tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
             }
             
-            match self.client.get(&search_url).send().await {
+            match self.clients.send(|client| client.get(&search_url)).await {
                 Ok(response) => {
                     if response.status().is_success() {
                         match response.text().await {
                             Ok(html) => return Ok(html),
                             Err(e) => {
-                                eprintln!("    讀取回應失敗 (嘗試 {}): {}", attempt + 1, e);
+                                eout!("    讀取回應失敗 (嘗試 {}): {}", attempt + 1, e);
                                 continue;
                             }
                         }
                     } else {
-                        eprintln!("    HTTP 錯誤 {}: 嘗試 {}", response.status(), attempt + 1);
+                        eout!("    HTTP 錯誤 {}: 嘗試 {}", response.status(), attempt + 1);
                         continue;
                     }
                 }
                 Err(e) => {
-                    eprintln!("    請求失敗 (嘗試 {}): {}", attempt + 1, e);
+                    eout!("    請求失敗 (嘗試 {}): {}", attempt + 1, e);
                     continue;
                 }
             }
@@ -120,4 +144,23 @@ impl GoogleUrlService {
         
         Err(anyhow::anyhow!("多次嘗試後仍失敗"))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reverse_search::services::golden;
+    
+    #[test]
+    fn test_extract_golden() {
+        let html = golden::fixture("google");
+        assert_eq!(block::detect_block(&html), None);
+        
+        let document = scraper::Html::parse_document(&html);
+        golden::assert_golden("google", &serde_json::json!({
+            "best_guess": utils::extract_best_guess(&document),
+            "keywords": utils::extract_keywords(&document),
+            "related_sites": utils::extract_related_sites(&document),
+        }));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::client_pool::ClientFactory;
 use crate::types::ImageMetadata;
 use crate::reverse_search::{
     trait_def::ReverseSearchService,
@@ -12,7 +13,7 @@ pub struct GoogleVisionService {
 
 impl GoogleVisionService {
     pub fn new(api_key: String) -> Result<Self> {
-        let client = reqwest::Client::new();
+        let client = ClientFactory::global().client("google-vision", None, reqwest::Client::builder)?;
         Ok(Self { api_key, client })
     }
 }
@@ -63,6 +64,7 @@ impl ReverseSearchService for GoogleVisionService {
             keywords,
             related_sites,
             best_guess: None,
+            similarity: None,
             searched_at: chrono::Utc::now(),
         })
     }
@@ -0,0 +1,239 @@
+use crate::types::ImageMetadata;
+use crate::headers::HeaderRotator;
+use crate::proxy::{ProxyConfig, ProxyRotator};
+use crate::rate_limit::AdaptiveRateLimiter;
+use crate::reverse_search::{
+    block,
+    trait_def::ReverseSearchService,
+    types::{ReverseSearchResult, KeywordFilter},
+};
+use anyhow::Result;
+use scraper::{ElementRef, Html, Selector};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SEARCH_URL: &str = "https://iqdb.org/";
+
+/// IQDB 接受的檔案大小上限
+const MAX_FILE_SIZE: usize = 8 * 1024 * 1024;
+
+/// 低於此相似度（%）的結果多半不是同一張圖（IQDB 把它們列為 Possible match）
+const MIN_SIMILARITY: f64 = 80.0;
+
+/// 動畫/插畫圖庫的反向搜尋（iqdb.org，涵蓋 Danbooru、Gelbooru、Zerochan 等）
+///
+/// 相似度達 `MIN_SIMILARITY` 的結果頁當作相關網站，它們的標籤當作關鍵字；
+/// 最相符結果的相似度記在 `similarity`。
+pub struct IqdbService {
+    clients: ProxyRotator,
+    filter: KeywordFilter,
+}
+
+/// 一筆相符結果
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+struct IqdbMatch {
+    url: String,
+    similarity: f64,
+    tags: Vec<String>,
+}
+
+impl IqdbService {
+    pub fn new(filter: KeywordFilter) -> Result<Self> {
+        let clients = ProxyRotator::shared(&ProxyConfig::default(), "iqdb", Self::client_builder)?;
+        
+        Ok(Self { clients, filter })
+    }
+    
+    /// 透過代理搜尋
+    pub fn with_proxies(mut self, config: &ProxyConfig) -> Result<Self> {
+        self.clients.set_proxies(config, Self::client_builder)?;
+        Ok(self)
+    }
+    
+    /// 使用共用的自適應限流器（起始間隔為 `suggested_delay_ms`）
+    pub fn with_rate_limiter(mut self, limiter: Arc<AdaptiveRateLimiter>) -> Self {
+        let delay = self.suggested_delay_ms();
+        self.clients.set_rate_limiter(limiter, delay);
+        self
+    }
+    
+    /// 輪替 User-Agent 並加上網站的 headers（headers.json）
+    pub fn with_headers(mut self, headers: Option<Arc<HeaderRotator>>) -> Self {
+        self.clients.set_headers(headers);
+        self
+    }
+    
+    fn client_builder() -> reqwest::ClientBuilder {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+    }
+    
+    /// 送出搜尋表單（multipart 表單不能重複使用，每次送出時重新建立）
+    async fn fetch(&self, form: impl Fn() -> reqwest::multipart::Form) -> Result<String> {
+        let response = self
+            .clients
+            .send(|client| client.post(SEARCH_URL).multipart(form().text("MAX_FILE_SIZE", MAX_FILE_SIZE.to_string())))
+            .await?;
+        let status = response.status();
+        let html = response.text().await?;
+        
+        if !status.is_success() {
+            anyhow::bail!("IQDB 回應 HTTP {}", status);
+        }
+        block::check(self.name(), &html)?;
+        Ok(html)
+    }
+    
+    fn build_result(&self, html: &str, metadata: &ImageMetadata) -> ReverseSearchResult {
+        let document = Html::parse_document(html);
+        let matches = extract_matches(&document);
+        let similarity = matches.iter().map(|m| m.similarity).reduce(f64::max);
+        let matches: Vec<IqdbMatch> = matches.into_iter().filter(|m| m.similarity >= MIN_SIMILARITY).collect();
+        
+        ReverseSearchResult {
+            filename: metadata.filename.clone(),
+            service: self.name().to_string(),
+            suggested_title: None,
+            keywords: self.filter.filter(extract_keywords(&matches)),
+            related_sites: self.filter.filter_sites(matches.into_iter().map(|m| m.url).collect()),
+            best_guess: None,
+            similarity,
+            searched_at: chrono::Utc::now(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ReverseSearchService for IqdbService {
+    fn name(&self) -> &str {
+        "iqdb"
+    }
+    
+    async fn search(&self, metadata: &ImageMetadata) -> Result<ReverseSearchResult> {
+        let html = self
+            .fetch(|| reqwest::multipart::Form::new().text("url", metadata.url.clone()))
+            .await?;
+        Ok(self.build_result(&html, metadata))
+    }
+    
+    async fn search_by_upload(
+        &self,
+        path: &Path,
+        metadata: &ImageMetadata,
+    ) -> Result<ReverseSearchResult> {
+        let bytes = tokio::fs::read(path).await?;
+        if bytes.len() > MAX_FILE_SIZE {
+            anyhow::bail!("檔案超過 IQDB 的 {} MB 上限", MAX_FILE_SIZE / 1024 / 1024);
+        }
+        let mime = crate::media::detect_format(&bytes).mime_type().unwrap_or("image/jpeg");
+        
+        let html = self
+            .fetch(|| {
+                let part = reqwest::multipart::Part::bytes(bytes.clone())
+                    .file_name(metadata.filename.clone())
+                    .mime_str(mime)
+                    .expect("MIME 類型固定為合法值");
+                reqwest::multipart::Form::new().part("file", part)
+            })
+            .await?;
+        Ok(self.build_result(&html, metadata))
+    }
+    
+    fn supports_upload(&self) -> bool {
+        true
+    }
+    
+    fn suggested_delay_ms(&self) -> u64 {
+        3000
+    }
+}
+
+/// 結果表格（第一個是上傳的圖片，之後是 Best / Additional / Possible match）
+fn extract_matches(document: &Html) -> Vec<IqdbMatch> {
+    let Ok(table_selector) = Selector::parse("#pages table") else {
+        return Vec::new();
+    };
+    
+    let mut matches: Vec<IqdbMatch> = Vec::new();
+    for table in document.select(&table_selector) {
+        let Some(found) = parse_match(table) else {
+            continue;
+        };
+        if !matches.iter().any(|m| m.url == found.url) {
+            matches.push(found);
+        }
+    }
+    
+    matches
+}
+
+fn parse_match(table: ElementRef) -> Option<IqdbMatch> {
+    let header = Selector::parse("th").ok()?;
+    let link = Selector::parse("td a[href]").ok()?;
+    let image = Selector::parse("img[alt]").ok()?;
+    let cell = Selector::parse("td").ok()?;
+    
+    let heading = table.select(&header).next()?.text().collect::<String>();
+    if !heading.contains("match") || heading.contains("No relevant") {
+        return None;
+    }
+    
+    let href = table.select(&link).next()?.value().attr("href")?;
+    let url = if href.starts_with("//") {
+        format!("https:{}", href)
+    } else {
+        href.to_string()
+    };
+    
+    // 「95% similarity」
+    let similarity = table.select(&cell).find_map(|td| {
+        let text = td.text().collect::<String>();
+        text.trim().strip_suffix("similarity")?.trim().trim_end_matches('%').parse::<f64>().ok()
+    })?;
+    
+    // alt 為「Rating: s Score: 10 Tags: 1girl smile」，沒有標籤的圖庫只有 Rating
+    let tags = table
+        .select(&image)
+        .next()
+        .and_then(|img| img.value().attr("alt"))
+        .and_then(|alt| alt.split_once("Tags:"))
+        .map(|(_, tags)| tags.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    
+    Some(IqdbMatch { url, similarity, tags })
+}
+
+/// 圖庫標籤（底線換成空白）
+fn extract_keywords(matches: &[IqdbMatch]) -> Vec<String> {
+    let mut keywords: Vec<String> = matches
+        .iter()
+        .flat_map(|m| m.tags.iter())
+        .map(|tag| tag.replace('_', " "))
+        .collect();
+    
+    keywords.sort();
+    keywords.dedup();
+    
+    keywords
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reverse_search::services::golden;
+    
+    #[test]
+    fn test_extract_golden() {
+        let html = golden::fixture("iqdb");
+        assert_eq!(block::detect_block(&html), None);
+        
+        let document = Html::parse_document(&html);
+        let matches = extract_matches(&document);
+        golden::assert_golden("iqdb", &serde_json::json!({
+            "keywords": extract_keywords(&matches),
+            "matches": matches,
+        }));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::types::ImageMetadata;
+use crate::headers::HeaderRotator;
+use crate::proxy::{ProxyConfig, ProxyRotator};
+use crate::rate_limit::AdaptiveRateLimiter;
+use crate::reverse_search::{
+    block,
+    trait_def::ReverseSearchService,
+    types::{ReverseSearchResult, KeywordFilter},
+};
+use anyhow::{Context, Result};
+use reqwest::cookie::Jar;
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT, ACCEPT, ACCEPT_LANGUAGE};
+use scraper::{Html, Selector};
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+/// 最多保留的視覺相符結果
+const MAX_MATCHES: usize = 20;
+
+/// 介面語言（影響 best guess 的語言）
+const LANGUAGE: &str = "zh-TW";
+
+/// 預先同意 cookie 設定，避免在歐盟等地區被導向 consent.google.com
+///
+/// 所有 client（含各代理）共用，同意頁送出後拿到的 cookie 也存在這裡。
+static COOKIES: LazyLock<Arc<Jar>> = LazyLock::new(|| {
+    let jar = Jar::default();
+    let url = "https://google.com".parse().unwrap();
+    jar.add_cookie_str("SOCS=CAESEwgDEgk0ODE3Nzk3MjQaAmVuIAEaBgiA_LyaBg; Domain=.google.com; Path=/", &url);
+    jar.add_cookie_str("CONSENT=YES+cb; Domain=.google.com; Path=/", &url);
+    Arc::new(jar)
+});
+
+/// Google 智慧鏡頭（取代已停用的 `searchbyimage`）
+///
+/// 網址搜尋走 `lens.google.com/uploadbyurl`，上傳搜尋走 `/v3/upload`，
+/// 結果頁的視覺相符項目當作相關網站，標題拆成關鍵字，相關搜尋當作 best guess。
+pub struct GoogleLensService {
+    clients: ProxyRotator,
+    filter: KeywordFilter,
+}
+
+impl GoogleLensService {
+    pub fn new(filter: KeywordFilter) -> Result<Self> {
+        let clients = ProxyRotator::shared(&ProxyConfig::default(), "lens", Self::client_builder)?;
+        
+        Ok(Self { clients, filter })
+    }
+    
+    /// 透過代理搜尋
+    pub fn with_proxies(mut self, config: &ProxyConfig) -> Result<Self> {
+        self.clients.set_proxies(config, Self::client_builder)?;
+        Ok(self)
+    }
+    
+    /// 使用共用的自適應限流器（起始間隔為 `suggested_delay_ms`）
+    pub fn with_rate_limiter(mut self, limiter: Arc<AdaptiveRateLimiter>) -> Self {
+        let delay = self.suggested_delay_ms();
+        self.clients.set_rate_limiter(limiter, delay);
+        self
+    }
+    
+    /// 輪替 User-Agent 並加上網站的 headers（headers.json）
+    pub fn with_headers(mut self, headers: Option<Arc<HeaderRotator>>) -> Self {
+        self.clients.set_headers(headers);
+        self
+    }
+    
+    fn client_builder() -> reqwest::ClientBuilder {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36"
+        ));
+        headers.insert(ACCEPT, HeaderValue::from_static(
+            "text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,*/*;q=0.8"
+        ));
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static(
+            "zh-TW,zh;q=0.9,en-US;q=0.8,en;q=0.7"
+        ));
+        
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .default_headers(headers)
+            .cookie_provider(Arc::clone(&COOKIES))
+    }
+    
+    /// 送出請求並取得結果頁；被導向同意頁時送出同意表單後重試一次
+    async fn fetch(&self, build: impl Fn(&reqwest::Client) -> reqwest::RequestBuilder) -> Result<String> {
+        for attempt in 0..2 {
+            let response = self.clients.send(&build).await?;
+            let on_consent = is_consent_url(response.url());
+            let status = response.status();
+            let html = response.text().await?;
+            
+            if !on_consent {
+                if !status.is_success() {
+                    anyhow::bail!("Google Lens 回應 HTTP {}", status);
+                }
+                return Ok(html);
+            }
+            if attempt > 0 {
+                break;
+            }
+            
+            let (action, fields) = consent_form(&html).ok_or_else(|| block::ServiceBlocked {
+                service: self.name().to_string(),
+                reason: "同意頁沒有可送出的表單".to_string(),
+            })?;
+            self.clients
+                .send(|client| client.post(&action).form(&fields))
+                .await
+                .context("無法送出 Google 同意表單")?;
+        }
+        
+        Err(block::ServiceBlocked {
+            service: self.name().to_string(),
+            reason: "送出同意表單後仍被導向同意頁".to_string(),
+        }
+        .into())
+    }
+    
+    fn build_result(&self, html: &str, metadata: &ImageMetadata) -> ReverseSearchResult {
+        let document = Html::parse_document(html);
+        
+        let matches = extract_visual_matches(&document);
+        let best_guess = extract_best_guess(&document);
+        let keywords = self.filter.filter(extract_keywords(&matches));
+        
+        ReverseSearchResult {
+            filename: metadata.filename.clone(),
+            service: self.name().to_string(),
+            suggested_title: best_guess.clone(),
+            keywords,
+            related_sites: self.filter.filter_sites(matches.into_iter().map(|(url, _)| url).collect()),
+            best_guess,
+            similarity: None,
+            searched_at: chrono::Utc::now(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ReverseSearchService for GoogleLensService {
+    fn name(&self) -> &str {
+        "lens"
+    }
+    
+    async fn search(&self, metadata: &ImageMetadata) -> Result<ReverseSearchResult> {
+        let search_url = format!(
+            "https://lens.google.com/uploadbyurl?url={}&hl={}",
+            urlencoding::encode(&metadata.url),
+            LANGUAGE
+        );
+        
+        let html = self.fetch(|client| client.get(&search_url)).await?;
+        block::check(self.name(), &html)?;
+        Ok(self.build_result(&html, metadata))
+    }
+    
+    async fn search_by_upload(
+        &self,
+        path: &Path,
+        metadata: &ImageMetadata,
+    ) -> Result<ReverseSearchResult> {
+        let bytes = tokio::fs::read(path).await?;
+        let mime = crate::media::detect_format(&bytes).mime_type().unwrap_or("image/jpeg");
+        let upload_url = format!(
+            "https://lens.google.com/v3/upload?hl={}&re=df&st={}&ep=gsbubb",
+            LANGUAGE,
+            chrono::Utc::now().timestamp_millis()
+        );
+        
+        // multipart 表單不能重複使用，每次送出時重新建立
+        let html = self
+            .fetch(|client| {
+                let part = reqwest::multipart::Part::bytes(bytes.clone())
+                    .file_name(metadata.filename.clone())
+                    .mime_str(mime)
+                    .expect("MIME 類型固定為合法值");
+                client
+                    .post(&upload_url)
+                    .multipart(reqwest::multipart::Form::new().part("encoded_image", part))
+            })
+            .await?;
+        
+        block::check(self.name(), &html)?;
+        Ok(self.build_result(&html, metadata))
+    }
+    
+    fn supports_upload(&self) -> bool {
+        true
+    }
+    
+    fn suggested_delay_ms(&self) -> u64 {
+        5000
+    }
+}
+
+fn is_consent_url(url: &reqwest::Url) -> bool {
+    url.host_str().is_some_and(|host| host.starts_with("consent."))
+}
+
+/// 同意頁中「全部接受」的表單（送出網址與欄位）
+fn consent_form(html: &str) -> Option<(String, Vec<(String, String)>)> {
+    let document = Html::parse_document(html);
+    let form_selector = Selector::parse("form[action]").ok()?;
+    let input_selector = Selector::parse("input[name]").ok()?;
+    
+    // 同一頁有「全部拒絕」與「全部接受」兩個表單，接受的 set_eom 為 false
+    let forms: Vec<_> = document
+        .select(&form_selector)
+        .filter(|form| form.value().attr("action").is_some_and(|action| action.contains("/save")))
+        .collect();
+    let form = forms
+        .iter()
+        .find(|form| {
+            form.select(&input_selector)
+                .any(|input| input.value().attr("name") == Some("set_eom") && input.value().attr("value") == Some("false"))
+        })
+        .or_else(|| forms.first())?;
+    
+    let action = form.value().attr("action")?;
+    let action = if action.starts_with("http") {
+        action.to_string()
+    } else {
+        format!("https://consent.google.com{}", action)
+    };
+    let fields = form
+        .select(&input_selector)
+        .filter_map(|input| {
+            let name = input.value().attr("name")?;
+            Some((name.to_string(), input.value().attr("value").unwrap_or_default().to_string()))
+        })
+        .collect();
+    Some((action, fields))
+}
+
+/// 不算視覺相符結果的 Google 自家網域
+fn is_google_host(host: &str) -> bool {
+    ["google.", "gstatic.com", "googleusercontent.com", "googleapis.com", "youtube.com"]
+        .iter()
+        .any(|domain| host.contains(domain))
+}
+
+/// 視覺相符的項目：(網頁網址, 標題)
+fn extract_visual_matches(document: &Html) -> Vec<(String, String)> {
+    let Ok(selector) = Selector::parse("a[href]") else {
+        return Vec::new();
+    };
+    let Ok(title_selector) = Selector::parse("[role='heading'], .UAiK1e") else {
+        return Vec::new();
+    };
+    
+    let mut matches: Vec<(String, String)> = Vec::new();
+    for elem in document.select(&selector) {
+        let Some(href) = elem.value().attr("href") else {
+            continue;
+        };
+        let Ok(url) = reqwest::Url::parse(href) else {
+            continue;
+        };
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none_or(is_google_host) {
+            continue;
+        }
+        
+        let title = elem
+            .value()
+            .attr("aria-label")
+            .map(str::to_string)
+            .or_else(|| elem.select(&title_selector).next().map(|t| t.text().collect::<String>()))
+            .unwrap_or_else(|| elem.text().collect::<String>());
+        let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        if title.is_empty() || matches.iter().any(|(seen, _)| seen == href) {
+            continue;
+        }
+        
+        matches.push((href.to_string(), title));
+        if matches.len() == MAX_MATCHES {
+            break;
+        }
+    }
+    
+    matches
+}
+
+/// 相關搜尋（`/search?q=...` 連結的查詢字串）
+fn extract_best_guess(document: &Html) -> Option<String> {
+    let selector = Selector::parse("a[href*='q=']").ok()?;
+    
+    document.select(&selector).find_map(|elem| {
+        let href = elem.value().attr("href")?;
+        let url = reqwest::Url::options()
+            .base_url(Some(&"https://www.google.com".parse().ok()?))
+            .parse(href)
+            .ok()?;
+        if !url.host_str().is_some_and(is_google_host) || !url.path().ends_with("/search") {
+            return None;
+        }
+        url.query_pairs()
+            .find(|(key, _)| key == "q")
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    })
+}
+
+/// 視覺相符項目的標題拆成詞（去掉 `|`、`-` 等網站名稱的分隔符號）
+fn extract_keywords(matches: &[(String, String)]) -> Vec<String> {
+    let mut keywords: Vec<String> = matches
+        .iter()
+        .flat_map(|(_, title)| title.split([' ', '|', '-', '–', '—', '·']).map(str::trim))
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    
+    keywords.sort();
+    keywords.dedup();
+    
+    keywords
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reverse_search::services::golden;
+    
+    #[test]
+    fn test_extract_golden() {
+        let html = golden::fixture("lens");
+        assert_eq!(block::detect_block(&html), None);
+        
+        let document = Html::parse_document(&html);
+        let matches = extract_visual_matches(&document);
+        golden::assert_golden("lens", &serde_json::json!({
+            "best_guess": extract_best_guess(&document),
+            "keywords": extract_keywords(&matches),
+            "visual_matches": matches,
+        }));
+    }
+    
+    #[test]
+    fn test_consent_form() {
+        let html = r#"
+            <form action="https://consent.google.com/save" method="POST">
+              <input type="hidden" name="gl" value="DE"><input type="hidden" name="set_eom" value="true">
+              <button>全部拒絕</button>
+            </form>
+            <form action="/save" method="POST">
+              <input type="hidden" name="gl" value="DE"><input type="hidden" name="set_eom" value="false">
+              <input type="hidden" name="continue" value="https://lens.google.com/uploadbyurl?url=x">
+              <button>全部接受</button>
+            </form>"#;
+        let (action, fields) = consent_form(html).unwrap();
+        assert_eq!(action, "https://consent.google.com/save");
+        assert!(fields.contains(&("set_eom".to_string(), "false".to_string())));
+        assert_eq!(fields.len(), 3);
+        assert_eq!(consent_form("<html></html>"), None);
+        
+        assert!(is_consent_url(&"https://consent.google.com/ml?continue=x".parse().unwrap()));
+        assert!(!is_consent_url(&"https://lens.google.com/search".parse().unwrap()));
+    }
+}
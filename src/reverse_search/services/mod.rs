@@ -1,3 +1,11 @@
+#[allow(dead_code)]
 pub mod google;
+pub mod lens;
 pub mod tineye;
-pub mod bing;
\ No newline at end of file
+pub mod bing;
+pub mod bing_visual;
+pub mod iqdb;
+pub mod trace_moe;
+
+#[cfg(test)]
+mod golden;
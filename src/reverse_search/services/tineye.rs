@@ -1,24 +1,79 @@
 use crate::types::ImageMetadata;
+use crate::headers::HeaderRotator;
+use crate::proxy::{ProxyConfig, ProxyRotator};
+use crate::rate_limit::AdaptiveRateLimiter;
 use crate::reverse_search::{
+    block,
     trait_def::ReverseSearchService,
-    types::ReverseSearchResult,
+    types::{ReverseSearchResult, KeywordFilter},
 };
 use anyhow::Result;
+use std::sync::Arc;
+use std::path::Path;
 use std::time::Duration;
 use scraper::{Html, Selector};
 
 pub struct TinEyeService {
-    client: reqwest::Client,
+    clients: ProxyRotator,
+    /// TinEye 只回傳相符數，過濾器只用在相關網站
+    filter: KeywordFilter,
 }
 
 impl TinEyeService {
-    pub fn new() -> Result<Self> {
-        let client = reqwest::Client::builder()
+    pub fn new(filter: KeywordFilter) -> Result<Self> {
+        let clients = ProxyRotator::shared(&ProxyConfig::default(), "tineye", Self::client_builder)?;
+        
+        Ok(Self { clients, filter })
+    }
+    
+    /// 透過代理搜尋
+    pub fn with_proxies(mut self, config: &ProxyConfig) -> Result<Self> {
+        self.clients.set_proxies(config, Self::client_builder)?;
+        Ok(self)
+    }
+    
+    /// 使用共用的自適應限流器（起始間隔為 `suggested_delay_ms`）
+    pub fn with_rate_limiter(mut self, limiter: Arc<AdaptiveRateLimiter>) -> Self {
+        let delay = self.suggested_delay_ms();
+        self.clients.set_rate_limiter(limiter, delay);
+        self
+    }
+    
+    /// 輪替 User-Agent 並加上網站的 headers（headers.json）
+    pub fn with_headers(mut self, headers: Option<Arc<HeaderRotator>>) -> Self {
+        self.clients.set_headers(headers);
+        self
+    }
+    
+    fn client_builder() -> reqwest::ClientBuilder {
+        reqwest::Client::builder()
             .timeout(Duration::from_secs(30))
             .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-            .build()?;
+    }
+    
+    /// 解析 TinEye 結果頁面
+    fn build_result(&self, html: &str, metadata: &ImageMetadata) -> ReverseSearchResult {
+        let document = Html::parse_document(html);
+        
+        let match_count = extract_match_count(&document);
+        let related_sites = self.filter.filter_sites(extract_related_sites(&document));
+        let title = extract_title(&document);
         
-        Ok(Self { client })
+        let mut keywords = vec![];
+        if match_count > 0 {
+            keywords.push(format!("{} matches", match_count));
+        }
+        
+        ReverseSearchResult {
+            filename: metadata.filename.clone(),
+            service: self.name().to_string(),
+            suggested_title: title,
+            keywords,
+            related_sites,
+            best_guess: None,
+            similarity: None,
+            searched_at: chrono::Utc::now(),
+        }
     }
 }
 
@@ -34,33 +89,42 @@ impl ReverseSearchService for TinEyeService {
             urlencoding::encode(&metadata.url)
         );
         
-        let html = self.client
-            .get(&search_url)
-            .send()
+        let html = self.clients
+            .send(|client| client.get(&search_url))
             .await?
             .text()
             .await?;
         
-        let document = Html::parse_document(&html);
-        
-        let match_count = extract_match_count(&document);
-        let related_sites = extract_related_sites(&document);
-        let title = extract_title(&document);
+        block::check(self.name(), &html)?;
+        Ok(self.build_result(&html, metadata))
+    }
+    
+    async fn search_by_upload(
+        &self,
+        path: &Path,
+        metadata: &ImageMetadata,
+    ) -> Result<ReverseSearchResult> {
+        let bytes = tokio::fs::read(path).await?;
         
-        let mut keywords = vec![];
-        if match_count > 0 {
-            keywords.push(format!("{} matches", match_count));
-        }
+        // multipart 表單不能重複使用，每次送出時重新建立
+        let html = self.clients
+            .send(|client| {
+                let part = reqwest::multipart::Part::bytes(bytes.clone())
+                    .file_name(metadata.filename.clone());
+                client
+                    .post("https://tineye.com/search")
+                    .multipart(reqwest::multipart::Form::new().part("image", part))
+            })
+            .await?
+            .text()
+            .await?;
         
-        Ok(ReverseSearchResult {
-            filename: metadata.filename.clone(),
-            service: self.name().to_string(),
-            suggested_title: title,
-            keywords,
-            related_sites,
-            best_guess: None,
-            searched_at: chrono::Utc::now(),
-        })
+        block::check(self.name(), &html)?;
+        Ok(self.build_result(&html, metadata))
+    }
+    
+    fn supports_upload(&self) -> bool {
+        true
     }
     
     fn suggested_delay_ms(&self) -> u64 {
@@ -120,4 +184,23 @@ fn extract_title(document: &Html) -> Option<String> {
     }
     
     None
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reverse_search::services::golden;
+    
+    #[test]
+    fn test_extract_golden() {
+        let html = golden::fixture("tineye");
+        assert_eq!(block::detect_block(&html), None);
+        
+        let document = Html::parse_document(&html);
+        golden::assert_golden("tineye", &serde_json::json!({
+            "match_count": extract_match_count(&document),
+            "title": extract_title(&document),
+            "related_sites": extract_related_sites(&document),
+        }));
+    }
+}
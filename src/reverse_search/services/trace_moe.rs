@@ -0,0 +1,237 @@
+use crate::types::ImageMetadata;
+use crate::proxy::{ProxyConfig, ProxyRotator};
+use crate::rate_limit::AdaptiveRateLimiter;
+use crate::reverse_search::{
+    trait_def::ReverseSearchService,
+    types::{ReverseSearchResult, KeywordFilter},
+};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// API 端點（`anilistInfo` 附上作品名稱，`cutBorders` 先裁掉截圖的黑邊）
+const API_URL: &str = "https://api.trace.moe/search?anilistInfo&cutBorders";
+
+/// 低於此相似度（0~1）的結果多半是錯的（trace.moe 文件的建議值）
+const MIN_SIMILARITY: f64 = 0.9;
+
+/// 動畫截圖來源搜尋（trace.moe）
+///
+/// 回傳作品、集數與片段時間：作品名稱（原文、羅馬拼音、英文與別名）當作關鍵字，
+/// 「作品 第 N 集 mm:ss」當作 best guess，AniList / MyAnimeList 頁面當作相關網站。
+/// 最相符結果的相似度低於 `MIN_SIMILARITY` 時只記錄相似度。
+pub struct TraceMoeService {
+    clients: ProxyRotator,
+    filter: KeywordFilter,
+}
+
+/// 最相符的片段
+#[derive(Debug, Clone, PartialEq)]
+struct SceneMatch {
+    title: Option<String>,
+    titles: Vec<String>,
+    episode: Option<String>,
+    /// 片段開始的秒數
+    from: Option<f64>,
+    similarity: f64,
+    anilist_id: Option<u64>,
+    mal_id: Option<u64>,
+}
+
+impl TraceMoeService {
+    pub fn new(filter: KeywordFilter) -> Result<Self> {
+        let clients = ProxyRotator::shared(&ProxyConfig::default(), "tracemoe", Self::client_builder)?;
+        
+        Ok(Self { clients, filter })
+    }
+    
+    /// 透過代理搜尋
+    pub fn with_proxies(mut self, config: &ProxyConfig) -> Result<Self> {
+        self.clients.set_proxies(config, Self::client_builder)?;
+        Ok(self)
+    }
+    
+    /// 使用共用的自適應限流器（起始間隔為 `suggested_delay_ms`）
+    pub fn with_rate_limiter(mut self, limiter: Arc<AdaptiveRateLimiter>) -> Self {
+        let delay = self.suggested_delay_ms();
+        self.clients.set_rate_limiter(limiter, delay);
+        self
+    }
+    
+    fn client_builder() -> reqwest::ClientBuilder {
+        reqwest::Client::builder().timeout(Duration::from_secs(30))
+    }
+    
+    async fn request(&self, build: impl Fn(&reqwest::Client) -> reqwest::RequestBuilder) -> Result<Value> {
+        let response = self.clients.send(build).await?;
+        let status = response.status();
+        let body: Value = response.json().await.context("無法解析 trace.moe 回應")?;
+        
+        let error = body["error"].as_str().unwrap_or_default();
+        if !status.is_success() || !error.is_empty() {
+            anyhow::bail!("trace.moe 回應 HTTP {}: {}", status, error);
+        }
+        Ok(body)
+    }
+    
+    fn build_result(&self, response: &Value, metadata: &ImageMetadata) -> ReverseSearchResult {
+        let scene = extract_best_match(response);
+        let mut result = ReverseSearchResult {
+            filename: metadata.filename.clone(),
+            service: self.name().to_string(),
+            suggested_title: None,
+            keywords: Vec::new(),
+            related_sites: Vec::new(),
+            best_guess: None,
+            similarity: scene.as_ref().map(|scene| round_percent(scene.similarity)),
+            searched_at: chrono::Utc::now(),
+        };
+        
+        if let Some(scene) = scene.filter(|scene| scene.similarity >= MIN_SIMILARITY) {
+            result.best_guess = scene.describe();
+            result.keywords = self.filter.filter(scene.titles.clone());
+            result.related_sites = self.filter.filter_sites(scene.pages());
+            result.suggested_title = scene.title;
+        }
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl ReverseSearchService for TraceMoeService {
+    fn name(&self) -> &str {
+        "tracemoe"
+    }
+    
+    async fn search(&self, metadata: &ImageMetadata) -> Result<ReverseSearchResult> {
+        let response = self
+            .request(|client| client.get(API_URL).query(&[("url", metadata.url.as_str())]))
+            .await?;
+        Ok(self.build_result(&response, metadata))
+    }
+    
+    async fn search_by_upload(
+        &self,
+        path: &Path,
+        metadata: &ImageMetadata,
+    ) -> Result<ReverseSearchResult> {
+        let bytes = tokio::fs::read(path).await?;
+        let mime = crate::media::detect_format(&bytes).mime_type().unwrap_or("image/jpeg");
+        
+        let response = self
+            .request(|client| {
+                client
+                    .post(API_URL)
+                    .header(reqwest::header::CONTENT_TYPE, mime)
+                    .body(bytes.clone())
+            })
+            .await?;
+        Ok(self.build_result(&response, metadata))
+    }
+    
+    fn supports_upload(&self) -> bool {
+        true
+    }
+    
+    fn suggested_delay_ms(&self) -> u64 {
+        // 未登入的配額是同時 1 個請求
+        2000
+    }
+}
+
+impl SceneMatch {
+    /// 「作品 第 N 集 mm:ss」
+    fn describe(&self) -> Option<String> {
+        let mut parts = vec![self.title.clone()?];
+        if let Some(episode) = &self.episode {
+            parts.push(format!("第 {} 集", episode));
+        }
+        if let Some(from) = self.from {
+            let secs = from.max(0.0) as u64;
+            parts.push(format!("{:02}:{:02}", secs / 60, secs % 60));
+        }
+        Some(parts.join(" "))
+    }
+    
+    fn pages(&self) -> Vec<String> {
+        let mut pages = Vec::new();
+        if let Some(id) = self.anilist_id {
+            pages.push(format!("https://anilist.co/anime/{}", id));
+        }
+        if let Some(id) = self.mal_id {
+            pages.push(format!("https://myanimelist.net/anime/{}", id));
+        }
+        pages
+    }
+}
+
+/// 相似度（0~1）轉成百分比，保留一位小數
+fn round_percent(similarity: f64) -> f64 {
+    (similarity * 1000.0).round() / 10.0
+}
+
+/// 相似度最高的片段（沒有結果時為 None）
+fn extract_best_match(response: &Value) -> Option<SceneMatch> {
+    let (best, similarity) = response["result"]
+        .as_array()?
+        .iter()
+        .filter_map(|item| Some((item, item["similarity"].as_f64()?)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+    
+    // 沒有 anilistInfo 時 anilist 只是 ID
+    let anilist = &best["anilist"];
+    let names = &anilist["title"];
+    let mut titles: Vec<String> = ["native", "romaji", "english"]
+        .iter()
+        .filter_map(|key| names[key].as_str())
+        .chain(anilist["synonyms"].as_array().into_iter().flatten().filter_map(Value::as_str))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    let title = titles.first().cloned();
+    titles.sort();
+    titles.dedup();
+    
+    // 集數可能是數字、字串或陣列（合併的檔案）
+    let episode = match &best["episode"] {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Array(items) if !items.is_empty() => {
+            Some(items.iter().map(|i| i.as_str().map(str::to_string).unwrap_or_else(|| i.to_string())).collect::<Vec<_>>().join("-"))
+        }
+        _ => None,
+    };
+    
+    Some(SceneMatch {
+        title,
+        titles,
+        episode,
+        from: best["from"].as_f64(),
+        similarity,
+        anilist_id: anilist["id"].as_u64().or_else(|| anilist.as_u64()),
+        mal_id: anilist["idMal"].as_u64(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reverse_search::services::golden;
+    
+    #[test]
+    fn test_extract_golden() {
+        let response: Value = serde_json::from_str(&golden::fixture_file("trace_moe.json")).unwrap();
+        let scene = extract_best_match(&response).unwrap();
+        golden::assert_golden("trace_moe", &serde_json::json!({
+            "best_guess": scene.describe(),
+            "keywords": scene.titles,
+            "pages": scene.pages(),
+            "similarity": round_percent(scene.similarity),
+        }));
+        
+        assert_eq!(extract_best_match(&serde_json::json!({ "error": "", "result": [] })), None);
+    }
+}
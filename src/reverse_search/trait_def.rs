@@ -1,6 +1,7 @@
 use crate::types::ImageMetadata;
 use super::types::ReverseSearchResult;
 use anyhow::Result;
+use std::path::Path;
 
 /// 反向搜尋服務 Trait
 #[async_trait::async_trait]
@@ -11,7 +12,24 @@ pub trait ReverseSearchService: Send + Sync {
     /// 搜尋單張圖片
     async fn search(&self, metadata: &ImageMetadata) -> Result<ReverseSearchResult>;
     
+    /// 上傳本地檔案搜尋（原始網址失效時使用）
+    ///
+    /// 預設退回使用 URL 搜尋，支援上傳的服務需覆寫此方法與 `supports_upload`。
+    async fn search_by_upload(
+        &self,
+        _path: &Path,
+        metadata: &ImageMetadata,
+    ) -> Result<ReverseSearchResult> {
+        self.search(metadata).await
+    }
+    
+    /// 是否支援上傳搜尋
+    fn supports_upload(&self) -> bool {
+        false
+    }
+    
     /// 是否需要 API key
+    #[allow(dead_code)]
     fn requires_api_key(&self) -> bool {
         false
     }
@@ -1,7 +1,9 @@
 use serde::{Serialize, Deserialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use chrono::{DateTime, Utc};
 
+pub use super::filter::KeywordFilter;
+
 /// 反向搜尋結果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReverseSearchResult {
@@ -11,13 +13,21 @@ pub struct ReverseSearchResult {
     pub keywords: Vec<String>,
     pub related_sites: Vec<String>,
     pub best_guess: Option<String>,
+    /// 最相符結果的相似度（0~100，只有回報分數的服務才有，如 IQDB、trace.moe）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f64>,
     pub searched_at: DateTime<Utc>,
 }
 
-/// 搜尋進度
+/// 搜尋進度（記錄每張圖片用哪些服務搜尋過，之後加入新服務時只補搜新服務）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchProgress {
+    /// 舊版格式：只記錄檔名，不知道用過哪些服務（載入時依搜尋結果轉換）
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
     pub completed_files: HashSet<String>,
+    /// 檔名 → 已搜尋過的服務
+    #[serde(default)]
+    pub completed: BTreeMap<String, BTreeSet<String>>,
     pub last_updated: DateTime<Utc>,
 }
 
@@ -25,17 +35,58 @@ impl SearchProgress {
     pub fn new() -> Self {
         Self {
             completed_files: HashSet::new(),
+            completed: BTreeMap::new(),
             last_updated: Utc::now(),
         }
     }
     
-    pub fn add_completed(&mut self, filename: String) {
-        self.completed_files.insert(filename);
+    pub fn add_completed(&mut self, filename: &str, service: &str) {
+        self.completed
+            .entry(filename.to_string())
+            .or_default()
+            .insert(service.to_string());
+        self.last_updated = Utc::now();
+    }
+    
+    /// 取消完成紀錄（重新搜尋前）
+    pub fn remove_completed(&mut self, filename: &str, service: &str) {
+        if let Some(services) = self.completed.get_mut(filename) {
+            services.remove(service);
+            if services.is_empty() {
+                self.completed.remove(filename);
+            }
+        }
         self.last_updated = Utc::now();
     }
     
-    pub fn is_completed(&self, filename: &str) -> bool {
-        self.completed_files.contains(filename)
+    pub fn is_completed(&self, filename: &str, service: &str) -> bool {
+        self.completed
+            .get(filename)
+            .is_some_and(|services| services.contains(service))
+    }
+    
+    /// 這張圖片還沒搜尋過的服務
+    pub fn pending_services<'a>(&self, filename: &str, services: &[&'a str]) -> Vec<&'a str> {
+        services
+            .iter()
+            .copied()
+            .filter(|service| !self.is_completed(filename, service))
+            .collect()
+    }
+    
+    /// 至少用一個服務搜尋過的圖片數
+    pub fn completed_count(&self) -> usize {
+        self.completed.len()
+    }
+    
+    /// 把舊版的檔名列表轉成檔名 → 服務：以搜尋結果中出現過的服務為準，
+    /// 沒有結果的服務（當時失敗或尚未加入）之後會重新搜尋。回傳轉換的檔案數
+    pub fn migrate_legacy(&mut self, results: &[ReverseSearchResult]) -> usize {
+        let legacy = std::mem::take(&mut self.completed_files);
+        for result in results.iter().filter(|r| legacy.contains(&r.filename)) {
+            self.add_completed(&result.filename, &result.service);
+        }
+        legacy.len()
     }
 }
 
@@ -44,51 +95,3 @@ impl Default for SearchProgress {
         Self::new()
     }
 }
-
-/// 關鍵字過濾器
-#[derive(Debug, Clone)]
-pub struct KeywordFilter {
-    pub blocklist: Vec<String>,
-    pub allowlist: Vec<String>,
-    pub min_length: usize,
-}
-
-impl Default for KeywordFilter {
-    fn default() -> Self {
-        Self {
-            blocklist: vec![
-                "porn".to_string(),
-                "xxx".to_string(),
-                "adult".to_string(),
-            ],
-            allowlist: vec![],
-            min_length: 3,
-        }
-    }
-}
-
-impl KeywordFilter {
-    pub fn filter(&self, keywords: Vec<String>) -> Vec<String> {
-        keywords
-            .into_iter()
-            .filter(|kw| {
-                if kw.len() < self.min_length {
-                    return false;
-                }
-                
-                let kw_lower = kw.to_lowercase();
-                if self.blocklist.iter().any(|blocked| kw_lower.contains(blocked)) {
-                    return false;
-                }
-                
-                if !self.allowlist.is_empty() {
-                    return self.allowlist.iter().any(|allowed| {
-                        kw_lower.contains(&allowed.to_lowercase())
-                    });
-                }
-                
-                true
-            })
-            .collect()
-    }
-}
\ No newline at end of file
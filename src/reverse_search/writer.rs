@@ -0,0 +1,165 @@
+use super::types::ReverseSearchResult;
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 預設每秒把緩衝寫到檔案一次
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 何時呼叫 fsync
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncPolicy {
+    /// 交給作業系統（當機或斷電時可能遺失最後幾筆）
+    Never,
+    /// 儲存進度前 fsync，進度記錄完成的結果一定已經落盤
+    #[default]
+    Checkpoint,
+    /// 每筆結果都 fsync
+    Always,
+}
+
+impl SyncPolicy {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "never" => Ok(Self::Never),
+            "checkpoint" => Ok(Self::Checkpoint),
+            "always" => Ok(Self::Always),
+            other => anyhow::bail!("未知的 fsync 方式: {}（可用: never, checkpoint, always）", other),
+        }
+    }
+}
+
+struct Inner {
+    /// 第一次寫入時才建立檔案
+    writer: Option<BufWriter<File>>,
+    last_flush: Instant,
+}
+
+/// 搜尋結果的緩衝寫入器（reverse_search_results.jsonl）
+///
+/// 並發搜尋共用同一個寫入器，結果先寫入緩衝，超過刷新間隔才寫到檔案；
+/// 引擎儲存進度前呼叫 `checkpoint`，確保進度中記錄完成的結果都已寫入。
+pub struct ResultWriter {
+    path: String,
+    flush_interval: Duration,
+    sync: SyncPolicy,
+    inner: Mutex<Inner>,
+}
+
+impl ResultWriter {
+    pub fn new(path: &str, flush_interval: Duration, sync: SyncPolicy) -> Self {
+        Self {
+            path: path.to_string(),
+            flush_interval,
+            sync,
+            inner: Mutex::new(Inner {
+                writer: None,
+                last_flush: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn append(&self, result: &ReverseSearchResult) -> Result<()> {
+        let line = format!("{}\n", serde_json::to_string(result)?);
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+
+        if inner.writer.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .with_context(|| format!("無法開啟 {}", self.path))?;
+            inner.writer = Some(BufWriter::new(file));
+        }
+        let writer = inner.writer.as_mut().unwrap();
+        writer.write_all(line.as_bytes())?;
+
+        if self.sync == SyncPolicy::Always {
+            writer.flush()?;
+            writer.get_ref().sync_data().context("無法同步搜尋結果檔")?;
+            inner.last_flush = Instant::now();
+        } else if inner.last_flush.elapsed() >= self.flush_interval {
+            writer.flush()?;
+            inner.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// 把緩衝寫到檔案，依設定 fsync
+    pub fn checkpoint(&self) -> Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+        let Some(writer) = inner.writer.as_mut() else {
+            return Ok(());
+        };
+
+        writer.flush().context("無法寫入搜尋結果")?;
+        if self.sync != SyncPolicy::Never {
+            writer.get_ref().sync_data().context("無法同步搜尋結果檔")?;
+        }
+        inner.last_flush = Instant::now();
+        Ok(())
+    }
+
+    /// 寫入緩衝並關閉檔案（結果檔被改寫前呼叫，下次寫入時重新開啟）
+    pub fn close(&self) -> Result<()> {
+        self.checkpoint()?;
+        self.inner.lock().unwrap().writer = None;
+        Ok(())
+    }
+}
+
+impl Drop for ResultWriter {
+    fn drop(&mut self) {
+        if let Some(writer) = self.inner.get_mut().unwrap().writer.as_mut() {
+            let _ = writer.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::fs;
+
+    #[test]
+    fn test_buffered_until_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("meme-writer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("results.jsonl");
+        let path = path.to_str().unwrap();
+
+        let result = ReverseSearchResult {
+            filename: "a.jpg".to_string(),
+            service: "bing".to_string(),
+            suggested_title: None,
+            keywords: vec!["doge".to_string()],
+            related_sites: vec![],
+            best_guess: None,
+            similarity: None,
+            searched_at: Utc::now(),
+        };
+
+        let writer = ResultWriter::new(path, Duration::from_secs(3600), SyncPolicy::Checkpoint);
+        writer.append(&result).unwrap();
+        writer.append(&result).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "");
+
+        writer.checkpoint().unwrap();
+        assert_eq!(super::super::load_all_results(path).unwrap().len(), 2);
+
+        // 沒有寫入過時不建立檔案
+        let untouched = dir.join("untouched.jsonl");
+        ResultWriter::new(untouched.to_str().unwrap(), Duration::ZERO, SyncPolicy::Always).checkpoint().unwrap();
+        assert!(!untouched.exists());
+
+        assert_eq!(SyncPolicy::parse("always").unwrap(), SyncPolicy::Always);
+        assert!(SyncPolicy::parse("sometimes").is_err());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,246 @@
+use crate::file_manager::FileManager;
+use crate::thumbnail;
+use crate::types::DuplicateRecord;
+use anyhow::{Context, Result};
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::Text;
+use image::{imageops, Rgb, RgbImage};
+use std::convert::Infallible;
+use std::fs;
+use std::io::Write;
+
+/// 每個標籤列的高度（FONT_6X10）
+const LINE_HEIGHT: u32 = 12;
+const CHAR_WIDTH: u32 = 6;
+const PADDING: u32 = 6;
+
+/// 預覽圖設定
+#[derive(Debug, Clone, Copy)]
+pub struct MontageOptions {
+    /// 每格圖片的最大邊長（像素）
+    pub tile_size: u32,
+    /// 每列格數
+    pub columns: u32,
+    /// 最多輸出幾組（None 表示全部）
+    pub max_groups: Option<usize>,
+}
+
+impl Default for MontageOptions {
+    fn default() -> Self {
+        Self {
+            tile_size: 200,
+            columns: 4,
+            max_groups: None,
+        }
+    }
+}
+
+/// 輸出結果
+#[derive(Debug, Default)]
+pub struct ReviewSummary {
+    /// 輸出的預覽圖數
+    pub montages: usize,
+    /// 找不到或無法解碼的檔案數
+    pub unreadable: usize,
+}
+
+/// 讓 embedded-graphics 直接畫在 `RgbImage` 上
+struct Canvas<'a>(&'a mut RgbImage);
+
+impl OriginDimensions for Canvas<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.0.width(), self.0.height())
+    }
+}
+
+impl DrawTarget for Canvas<'_> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> std::result::Result<(), Infallible>
+    where
+        I: IntoIterator<Item = Pixel<Rgb888>>,
+    {
+        for Pixel(point, color) in pixels {
+            let (x, y) = (point.x as u32, point.y as u32);
+            if point.x >= 0 && point.y >= 0 && x < self.0.width() && y < self.0.height() {
+                self.0.put_pixel(x, y, Rgb([color.r(), color.g(), color.b()]));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 在指定位置寫一行字（超過 `max_chars` 截斷；非 ASCII 字元顯示為 `?`）
+fn draw_label(image: &mut RgbImage, text: &str, x: u32, y: u32, max_chars: usize) {
+    let mut label: String = text.chars().take(max_chars).collect();
+    if text.chars().count() > max_chars && max_chars > 1 {
+        label.pop();
+        label.push('~');
+    }
+
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb888::BLACK);
+    let _ = Text::new(&label, Point::new(x as i32, (y + LINE_HEIGHT - 3) as i32), style)
+        .draw(&mut Canvas(image));
+}
+
+fn human_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0)
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+/// 把一組圖片排成格狀預覽，每格下方標示檔名、尺寸與檔案大小
+///
+/// 回傳預覽圖與無法讀取的檔案數。
+pub fn render_group(fm: &FileManager, files: &[String], options: &MontageOptions) -> (RgbImage, usize) {
+    let tile = options.tile_size.max(32);
+    let columns = options.columns.clamp(1, files.len().max(1) as u32);
+    let rows = (files.len() as u32).div_ceil(columns).max(1);
+    let cell_width = tile + PADDING * 2;
+    let cell_height = tile + PADDING * 2 + LINE_HEIGHT * 2;
+    let max_chars = (tile / CHAR_WIDTH) as usize;
+
+    let mut montage = RgbImage::from_pixel(cell_width * columns, cell_height * rows, Rgb([255, 255, 255]));
+    let mut unreadable = 0;
+
+    for (i, filename) in files.iter().enumerate() {
+        let x = (i as u32 % columns) * cell_width + PADDING;
+        let y = (i as u32 / columns) * cell_height + PADDING;
+        let path = fm.get_image_path(filename);
+
+        let size = fs::metadata(&path).map(|m| m.len()).ok();
+        // 縮圖夠大時不必解碼原圖，尺寸從原圖的檔頭讀取
+        let decoded = thumbnail::load(fm, filename, tile)
+            .and_then(|thumb| Some((thumb, image::image_dimensions(&path).ok()?)))
+            .or_else(|| image::open(&path).ok().map(|img| {
+                let dimensions = (img.width(), img.height());
+                (img, dimensions)
+            }));
+
+        let details = match (&decoded, size) {
+            (Some((img, (width, height))), Some(size)) => {
+                // 等比例縮到格子內（小圖不放大）
+                let scale = (tile as f64 / img.width().max(img.height()).max(1) as f64).min(1.0);
+                let thumb = imageops::thumbnail(
+                    &img.to_rgb8(),
+                    ((img.width() as f64 * scale) as u32).max(1),
+                    ((img.height() as f64 * scale) as u32).max(1),
+                );
+                let offset_x = x + (tile - thumb.width()) / 2;
+                let offset_y = y + (tile - thumb.height()) / 2;
+                imageops::overlay(&mut montage, &thumb, offset_x as i64, offset_y as i64);
+                format!("{}x{} {}", width, height, human_size(size))
+            }
+            (None, Some(size)) => {
+                unreadable += 1;
+                fill_placeholder(&mut montage, x, y, tile);
+                format!("unreadable {}", human_size(size))
+            }
+            (_, None) => {
+                unreadable += 1;
+                fill_placeholder(&mut montage, x, y, tile);
+                "missing".to_string()
+            }
+        };
+
+        draw_label(&mut montage, &format!("#{} {}", i + 1, filename), x, y + tile + 2, max_chars);
+        draw_label(&mut montage, &details, x, y + tile + 2 + LINE_HEIGHT, max_chars);
+    }
+
+    (montage, unreadable)
+}
+
+fn fill_placeholder(image: &mut RgbImage, x: u32, y: u32, tile: u32) {
+    for py in y..y + tile {
+        for px in x..x + tile {
+            image.put_pixel(px, py, Rgb([220, 220, 220]));
+        }
+    }
+}
+
+/// 把重複組輸出成預覽圖到 `data/review/`，並寫 `index.tsv` 對照完整檔名
+///
+/// 舊的 `group_*.png` 會先清掉，避免殘留已經處理過的組。
+pub fn write_review(fm: &FileManager, groups: &[DuplicateRecord], options: &MontageOptions) -> Result<ReviewSummary> {
+    let review_dir = format!("{}/review", fm.root_dir());
+    fs::create_dir_all(&review_dir).context("無法建立 review 目錄")?;
+
+    for entry in fs::read_dir(&review_dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if name.starts_with("group_") && name.ends_with(".png") {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    let mut index = fs::File::create(format!("{}/index.tsv", review_dir))
+        .context("無法建立 index.tsv")?;
+    writeln!(index, "montage\tposition\tcontent_hash\tfilename")?;
+
+    let mut summary = ReviewSummary::default();
+    let limit = options.max_groups.unwrap_or(groups.len());
+
+    for (i, group) in groups.iter().take(limit).enumerate() {
+        let name = format!("group_{:04}_{}.png", i + 1, &group.content_hash[..12.min(group.content_hash.len())]);
+        let (montage, unreadable) = render_group(fm, &group.files, options);
+
+        montage
+            .save(format!("{}/{}", review_dir, name))
+            .with_context(|| format!("無法儲存預覽圖: {}", name))?;
+
+        for (position, filename) in group.files.iter().enumerate() {
+            writeln!(index, "{}\t{}\t{}\t{}", name, position + 1, group.content_hash, filename)?;
+        }
+
+        summary.montages += 1;
+        summary.unreadable += unreadable;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_group() {
+        let dir = std::env::temp_dir().join(format!("meme-review-{}", std::process::id()));
+        let fm = FileManager::new(dir.to_str().unwrap()).unwrap();
+
+        let mut png = Vec::new();
+        RgbImage::from_pixel(400, 100, Rgb([255, 0, 0]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        fm.save_image("a.png", &png).unwrap();
+        fm.save_image("b.png", &png).unwrap();
+
+        let options = MontageOptions { tile_size: 100, columns: 4, max_groups: None };
+        let files = vec!["a.png".to_string(), "b.png".to_string(), "gone.png".to_string()];
+        let (montage, unreadable) = render_group(&fm, &files, &options);
+
+        // 3 格排成一列，每格 100 + 邊距與兩行標籤
+        assert_eq!(montage.dimensions(), (3 * 112, 112 + 24));
+        assert_eq!(unreadable, 1);
+        // 縮圖置中（400x100 → 100x25）
+        assert_eq!(montage.get_pixel(6 + 50, 6 + 50), &Rgb([255, 0, 0]));
+        assert_eq!(montage.get_pixel(6 + 50, 6 + 5), &Rgb([255, 255, 255]));
+
+        let groups = vec![DuplicateRecord {
+            content_hash: "0123456789abcdef".to_string(),
+            files: files.clone(),
+            name: None,
+        }];
+        let summary = write_review(&fm, &groups, &options).unwrap();
+        assert_eq!(summary.montages, 1);
+        assert!(dir.join("review/group_0001_0123456789ab.png").exists());
+        assert_eq!(fs::read_to_string(dir.join("review/index.tsv")).unwrap().lines().count(), 4);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
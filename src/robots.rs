@@ -0,0 +1,202 @@
+use std::fmt;
+use std::time::Duration;
+
+/// 比對 robots.txt 群組用的爬蟲名稱
+pub const AGENT_TOKEN: &str = "meme-data-crawler";
+
+/// 單條規則（`Allow` / `Disallow`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// 一個 host 的 robots.txt 中適用於本爬蟲的規則
+///
+/// 有指名本爬蟲（`AGENT_TOKEN`）的群組時只用那些群組，否則用 `User-agent: *`；
+/// 比對採最長符合的規則，長度相同時 `Allow` 優先（RFC 9309），支援 `*` 與結尾的 `$`。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// 全部允許（robots.txt 不存在時）
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// 全部禁止（robots.txt 暫時無法取得時，依 RFC 9309 視為禁止）
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![Rule { allow: false, pattern: "/".to_string() }],
+            crawl_delay: None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut specific = Self::default();
+        let mut wildcard = Self::default();
+        let mut has_specific = false;
+
+        // 目前群組的 user-agent（連續的 User-agent 行屬於同一群組）
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            if key == "user-agent" {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                agents.push(value.to_ascii_lowercase());
+                continue;
+            }
+            if agents.is_empty() {
+                continue;
+            }
+            in_rules = true;
+
+            let matches_us = agents.iter().any(|a| a == AGENT_TOKEN);
+            if matches_us {
+                has_specific = true;
+            }
+            let targets: Vec<&mut Self> = match (matches_us, agents.iter().any(|a| a == "*")) {
+                (true, true) => vec![&mut specific, &mut wildcard],
+                (true, false) => vec![&mut specific],
+                (false, true) => vec![&mut wildcard],
+                (false, false) => continue,
+            };
+
+            for target in targets {
+                match key.as_str() {
+                    // 空的 Disallow 表示全部允許
+                    "allow" | "disallow" if !value.is_empty() => target.rules.push(Rule {
+                        allow: key == "allow",
+                        pattern: value.to_string(),
+                    }),
+                    "crawl-delay" => {
+                        if let Ok(secs) = value.parse::<f64>() {
+                            if secs.is_finite() && secs >= 0.0 {
+                                target.crawl_delay = Some(Duration::from_secs_f64(secs));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if has_specific { specific } else { wildcard }
+    }
+
+    /// 路徑（含查詢字串）是否允許爬取
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let path = if path.is_empty() { "/" } else { path };
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    /// `Crawl-delay`（沒有設定時為 None）
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// robots.txt 規則比對（`*` 符合任意字元，結尾的 `$` 表示必須到路徑結尾）
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+/// robots.txt 不允許爬取的網址（`--ignore-robots` 可略過）
+#[derive(Debug, Clone)]
+pub struct RobotsDisallowed {
+    pub url: String,
+}
+
+impl fmt::Display for RobotsDisallowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "robots.txt 不允許爬取: {}（可用 --ignore-robots 略過）", self.url)
+    }
+}
+
+impl std::error::Error for RobotsDisallowed {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots_rules() {
+        let text = "
+            # 一般爬蟲
+            User-agent: *
+            Disallow: /admin
+            Disallow: /search?
+            Allow: /admin/public$
+            Crawl-delay: 2.5
+
+            User-agent: Googlebot
+            Disallow:
+        ";
+        let rules = RobotsRules::parse(text);
+        assert!(rules.is_allowed("/memes?page=2"));
+        assert!(!rules.is_allowed("/admin/users"));
+        assert!(rules.is_allowed("/admin/public"));
+        assert!(!rules.is_allowed("/admin/public/x"));
+        assert!(!rules.is_allowed("/search?q=doge"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_millis(2500)));
+
+        // 指名本爬蟲的群組優先於 *
+        let text = "
+            User-agent: *
+            Disallow: /
+
+            User-agent: meme-data-crawler
+            User-agent: other-bot
+            Disallow: /private/*.jpg$
+        ";
+        let rules = RobotsRules::parse(text);
+        assert!(rules.is_allowed("/memes"));
+        assert!(!rules.is_allowed("/private/a/b.jpg"));
+        assert!(rules.is_allowed("/private/a/b.jpg.html"));
+        assert_eq!(rules.crawl_delay(), None);
+
+        assert!(RobotsRules::allow_all().is_allowed("/"));
+        assert!(!RobotsRules::disallow_all().is_allowed("/anything"));
+    }
+}
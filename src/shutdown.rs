@@ -0,0 +1,43 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Ctrl+C 中斷旗標
+///
+/// 第一次 Ctrl+C 只設定旗標，引擎會停止派發新工作、等進行中的工作結束並儲存進度；
+/// 第二次 Ctrl+C 直接結束程序。
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal {
+    triggered: Arc<AtomicBool>,
+}
+
+impl ShutdownSignal {
+    /// 安裝 Ctrl+C 處理
+    pub fn install() -> Self {
+        let signal = Self::default();
+        let flag = signal.clone();
+
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                eout!("\n⏸️  收到中斷訊號，等待進行中的工作完成並儲存進度...（再按一次 Ctrl+C 強制結束）");
+                flag.trigger();
+
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    eout!("⛔ 強制結束");
+                    std::process::exit(130);
+                }
+            }
+        });
+
+        signal
+    }
+
+    /// 手動觸發中斷
+    pub fn trigger(&self) {
+        self.triggered.store(true, Ordering::SeqCst);
+    }
+
+    /// 是否已收到中斷訊號
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+}
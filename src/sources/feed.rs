@@ -0,0 +1,375 @@
+use crate::crawler::CrawlerConfig;
+use crate::crawler::downloader::{DownloadOutcome, ImageDownloader, ItemDetails};
+use crate::crawler::download_queue::{DownloadQueue, ImageJob};
+use crate::crawler::types::DownloadedImage;
+use crate::fetcher::{Fetcher, HttpFetcher};
+use crate::file_manager::FileManager;
+use crate::rate_limit::{self, AdaptiveRateLimiter};
+use crate::shutdown::ShutdownSignal;
+use crate::store;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+
+/// 未學到延遲前的請求間隔
+const DEFAULT_DELAY_MS: u64 = 2000;
+
+/// feed 來源設定
+#[derive(Debug, Clone, Default)]
+pub struct FeedConfig {
+    /// RSS/Atom 網址
+    pub feeds: Vec<String>,
+    /// 常駐模式的輪詢間隔（None 表示只跑一輪）
+    pub watch_interval: Option<Duration>,
+}
+
+impl FeedConfig {
+    /// 讀取資料目錄的 feeds.txt（每行一個網址，`#` 開頭為註解；不存在時為空）
+    pub fn load_list(data_dir: &str) -> Result<Vec<String>> {
+        let path = format!("{}/feeds.txt", data_dir);
+        if !Path::new(&path).exists() {
+            return Ok(vec![]);
+        }
+
+        Ok(fs::read_to_string(&path)
+            .context("無法讀取 feeds.txt")?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+/// feed 中的一篇文章
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedItem {
+    pub id: String,
+    pub title: String,
+    /// 文章網址
+    pub link: Option<String>,
+    /// enclosure / media:content / 內文第一張圖（都沒有時為 None，改讀文章頁的 og:image）
+    pub image_url: Option<String>,
+    pub categories: Vec<String>,
+}
+
+impl FeedItem {
+    /// 寫入 metadata 的標籤與欄位
+    fn details(&self, feed_url: &str) -> ItemDetails {
+        let mut extra = serde_json::Map::new();
+        extra.insert("source".to_string(), json!("feed"));
+        extra.insert("feed_url".to_string(), json!(feed_url));
+        if let Some(link) = &self.link {
+            extra.insert("entry_url".to_string(), json!(link));
+        }
+
+        ItemDetails {
+            tags: self.categories.clone(),
+            extra,
+            license: None,
+        }
+    }
+}
+
+/// 解析 RSS/Atom/JSON Feed
+pub fn parse_feed(body: &str) -> Result<Vec<FeedItem>> {
+    let feed = feed_rs::parser::parse(body.as_bytes()).context("無法解析 feed")?;
+    let img = Selector::parse("img[src]").unwrap();
+
+    Ok(feed.entries
+        .into_iter()
+        .map(|entry| {
+            let enclosure = entry.media
+                .iter()
+                .flat_map(|media| &media.content)
+                .filter(|content| match &content.content_type {
+                    Some(mime) => mime.to_string().starts_with("image/"),
+                    None => content.url.as_ref().is_some_and(|url| is_image_url(url.as_str())),
+                })
+                .find_map(|content| content.url.as_ref().map(|url| url.to_string()))
+                .or_else(|| entry.links
+                    .iter()
+                    .find(|link| link.rel.as_deref() == Some("enclosure")
+                        && link.media_type.as_deref().is_some_and(|t| t.starts_with("image/")))
+                    .map(|link| link.href.clone()));
+
+            // 沒有附件時取內文（或摘要）的第一張圖
+            let inline = || {
+                let html = entry.content.as_ref().and_then(|c| c.body.clone())
+                    .or_else(|| entry.summary.as_ref().map(|s| s.content.clone()))?;
+                Html::parse_fragment(&html)
+                    .select(&img)
+                    .find_map(|e| e.value().attr("src"))
+                    .filter(|src| src.starts_with("http"))
+                    .map(str::to_string)
+            };
+
+            let link = entry.links
+                .iter()
+                .find(|link| link.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+                .map(|link| link.href.clone());
+
+            FeedItem {
+                id: if entry.id.is_empty() { link.clone().unwrap_or_default() } else { entry.id.clone() },
+                title: entry.title.as_ref().map(|t| t.content.trim().to_string()).unwrap_or_default(),
+                image_url: enclosure.or_else(inline),
+                link,
+                categories: entry.categories.iter().map(|c| c.term.clone()).collect(),
+            }
+        })
+        .filter(|item| !item.id.is_empty())
+        .collect())
+}
+
+/// 直接指向圖片檔的 URL
+fn is_image_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+    [".jpg", ".jpeg", ".png", ".gif", ".webp"].iter().any(|ext| path.ends_with(ext))
+}
+
+/// 文章頁的 og:image
+pub fn extract_og_image(html: &str) -> Option<String> {
+    let selector = Selector::parse(r#"meta[property="og:image"]"#).unwrap();
+    Html::parse_document(html)
+        .select(&selector)
+        .find_map(|e| e.value().attr("content"))
+        .map(|s| s.trim().to_string())
+        .filter(|s| s.starts_with("http"))
+}
+
+/// 單一 feed 的進度
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeedState {
+    /// 已處理的文章 id（下載成功、未通過過濾或沒有圖片）
+    pub seen: BTreeSet<String>,
+    pub images_downloaded: usize,
+    pub last_polled: Option<DateTime<Utc>>,
+}
+
+/// RSS/Atom 來源
+///
+/// 每輪讀取所有 feed，只處理沒看過的文章：圖片取自 enclosure/media:content/內文，
+/// 都沒有時讀文章頁的 og:image，交給與爬蟲相同的下載器。
+/// 已處理的文章 id 存在 feed_progress.json；下載失敗的文章不記錄，下一輪重試。
+pub struct FeedSource {
+    fetcher: HttpFetcher,
+    downloader: ImageDownloader,
+    rate_limiter: Arc<AdaptiveRateLimiter>,
+    progress_file: String,
+    feeds: FeedConfig,
+    config: CrawlerConfig,
+}
+
+impl FeedSource {
+    pub fn new(data_dir: &str, feeds: FeedConfig, config: CrawlerConfig) -> Result<Self> {
+        let file_manager = Arc::new(Mutex::new(FileManager::new(data_dir)?));
+        let rate_limiter = Arc::new(AdaptiveRateLimiter::load(data_dir)?);
+        let fetcher = HttpFetcher::new(config.timeout_secs, config.max_retries)?
+            .with_robots(config.respect_robots)
+            .with_token_bucket(config.token_bucket.clone())
+            .with_headers(config.headers.clone())
+            .with_proxies(&config.proxy)?
+            .with_rate_limiter(Arc::clone(&rate_limiter), DEFAULT_DELAY_MS);
+        let downloader = ImageDownloader::from_config(file_manager, store::open_store(data_dir, config.metadata_backend)?, &config)?;
+
+        Ok(Self {
+            fetcher,
+            downloader,
+            rate_limiter,
+            progress_file: format!("{}/feed_progress.json", data_dir),
+            feeds,
+            config,
+        })
+    }
+
+    /// 訂閱下載完成的圖片（來源釋放後通道關閉）
+    pub fn subscribe(&self, buffer: usize) -> mpsc::Receiver<DownloadedImage> {
+        self.downloader.subscribe(buffer)
+    }
+
+    /// 讀取各 feed 的進度
+    pub fn load_progress(&self) -> Result<BTreeMap<String, FeedState>> {
+        if !Path::new(&self.progress_file).exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        serde_json::from_str(&fs::read_to_string(&self.progress_file)?)
+            .context("無法解析 feed_progress.json")
+    }
+
+    fn save_progress(&self, progress: &BTreeMap<String, FeedState>) -> Result<()> {
+        let temp_path = format!("{}.tmp", self.progress_file);
+        fs::write(&temp_path, serde_json::to_string_pretty(progress)?)?;
+        fs::rename(&temp_path, &self.progress_file)?;
+        Ok(())
+    }
+
+    /// 讀取所有 feed；設定了輪詢間隔時持續執行到 Ctrl+C
+    pub async fn run(&self) -> Result<()> {
+        let shutdown = ShutdownSignal::install();
+        let mut total = 0;
+
+        loop {
+            total += self.poll_once(&shutdown).await?;
+            self.rate_limiter.save()?;
+
+            let Some(interval) = self.feeds.watch_interval else {
+                break;
+            };
+            out!("💤 {} 分鐘後再次檢查（Ctrl+C 結束）\n", interval.as_secs() / 60);
+
+            let wake_at = tokio::time::Instant::now() + interval;
+            while tokio::time::Instant::now() < wake_at && !shutdown.is_triggered() {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            if shutdown.is_triggered() {
+                out!("⏸️  已停止輪詢，進度已儲存");
+                break;
+            }
+        }
+
+        out!("\n✨ Feed 完成，本次下載 {} 張圖片", total);
+        Ok(())
+    }
+
+    /// 讀取每個 feed 一次，回傳下載數
+    async fn poll_once(&self, shutdown: &ShutdownSignal) -> Result<usize> {
+        let mut progress = self.load_progress()?;
+        let mut total = 0;
+
+        for feed_url in &self.feeds.feeds {
+            if shutdown.is_triggered() {
+                break;
+            }
+
+            let items = match self.fetcher.fetch_page(feed_url).await.and_then(|body| parse_feed(&body)) {
+                Ok(items) => items,
+                Err(e) => {
+                    eout!("❌ {} 讀取失敗: {}", feed_url, e);
+                    continue;
+                }
+            };
+
+            // 來源網站記錄 feed 所在的網站（圖片常放在 CDN）
+            let downloader = self.downloader.clone().with_source_site(rate_limit::host_of(feed_url));
+            let queue = DownloadQueue::new(downloader, self.config.max_in_flight_images);
+
+            let mut state = progress.get(feed_url).cloned().unwrap_or_default();
+            let new_items: Vec<_> = items.into_iter().filter(|item| !state.seen.contains(&item.id)).collect();
+            let saved = self.download_items(&queue, feed_url, &new_items, &mut state).await?;
+            out!("📰 {}: {} 篇新文章，下載 {} 張", feed_url, new_items.len(), saved);
+
+            state.images_downloaded += saved;
+            state.last_polled = Some(Utc::now());
+            total += saved;
+
+            progress.insert(feed_url.clone(), state);
+            self.save_progress(&progress)?;
+        }
+
+        Ok(total)
+    }
+
+    /// 下載新文章的圖片，處理完的文章記入 `state.seen`，回傳成功數
+    async fn download_items(
+        &self,
+        queue: &DownloadQueue,
+        feed_url: &str,
+        items: &[FeedItem],
+        state: &mut FeedState,
+    ) -> Result<usize> {
+        let mut pending = Vec::new();
+        for item in items {
+            let image_url = match (&item.image_url, &item.link) {
+                (Some(url), _) => Some(url.clone()),
+                (None, Some(link)) => match self.fetcher.fetch_page(link).await {
+                    Ok(html) => extract_og_image(&html),
+                    Err(e) => {
+                        eout!("  ❌ 無法讀取文章頁 {}: {}", link, e);
+                        continue;
+                    }
+                },
+                (None, None) => None,
+            };
+
+            let Some(image_url) = image_url else {
+                state.seen.insert(item.id.clone());
+                continue;
+            };
+
+            let name = if item.title.is_empty() { rate_limit::host_of(feed_url) } else { item.title.clone() };
+            // feed 沒有頁碼，page 固定為 0
+            let job = ImageJob::new(image_url, name, 0).with_details(item.details(feed_url));
+            pending.push((item, queue.submit(job).await?));
+        }
+
+        let mut saved = 0;
+        for (item, result) in pending {
+            match result.await.unwrap_or_else(|_| Err(anyhow::anyhow!("下載工作中斷"))) {
+                Ok(DownloadOutcome::Saved) => {
+                    saved += 1;
+                    state.seen.insert(item.id.clone());
+                }
+                Ok(DownloadOutcome::Skipped(reason)) => {
+                    eout!("  略過 ({}): {}", item.title, reason);
+                    state.seen.insert(item.id.clone());
+                }
+                Ok(DownloadOutcome::Duplicate(existing)) => {
+                    eout!("  重複 ({}): 與 {} 相同", item.title, existing);
+                    state.seen.insert(item.id.clone());
+                }
+                Err(e) => eout!("  下載失敗 ({}): {}", item.title, e),
+            }
+        }
+        Ok(saved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_feed() {
+        let rss = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/"><channel><title>memes</title>
+  <item><guid>1</guid><title>Doge</title><link>https://blog.example.com/doge</link>
+    <enclosure url="https://cdn.example.com/doge.jpg" type="image/jpeg" length="1"/>
+    <category>dog</category></item>
+  <item><guid>2</guid><title>Inline</title><link>https://blog.example.com/inline</link>
+    <description>&lt;p&gt;&lt;img src="https://cdn.example.com/inline.png"&gt;&lt;/p&gt;</description></item>
+  <item><guid>3</guid><title>Text only</title><link>https://blog.example.com/text</link></item>
+</channel></rss>"#;
+
+        let items = parse_feed(rss).unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].image_url.as_deref(), Some("https://cdn.example.com/doge.jpg"));
+        assert_eq!(items[0].categories, ["dog"]);
+        assert_eq!(items[1].image_url.as_deref(), Some("https://cdn.example.com/inline.png"));
+        assert_eq!(items[2].image_url, None);
+        assert_eq!(items[2].link.as_deref(), Some("https://blog.example.com/text"));
+
+        let atom = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>memes</title><id>urn:feed</id><updated>2024-01-01T00:00:00Z</updated>
+  <entry><id>urn:a</id><title>Cat</title><updated>2024-01-01T00:00:00Z</updated>
+    <link href="https://blog.example.com/cat"/>
+    <link rel="enclosure" type="image/png" href="https://cdn.example.com/cat.png"/></entry>
+</feed>"#;
+
+        let items = parse_feed(atom).unwrap();
+        assert_eq!(items[0].id, "urn:a");
+        assert_eq!(items[0].link.as_deref(), Some("https://blog.example.com/cat"));
+        assert_eq!(items[0].image_url.as_deref(), Some("https://cdn.example.com/cat.png"));
+
+        let page = r#"<html><head><meta property="og:image" content="https://cdn.example.com/og.jpg"></head></html>"#;
+        assert_eq!(extract_og_image(page).as_deref(), Some("https://cdn.example.com/og.jpg"));
+    }
+}
@@ -0,0 +1,452 @@
+use crate::crawler::CrawlerConfig;
+use crate::crawler::downloader::{DownloadOutcome, ImageDownloader, ItemDetails};
+use crate::crawler::download_queue::{DownloadQueue, ImageJob};
+use crate::crawler::types::DownloadedImage;
+use crate::fetcher::{Fetcher, HttpFetcher};
+use crate::file_manager::FileManager;
+use crate::rate_limit::AdaptiveRateLimiter;
+use crate::shutdown::ShutdownSignal;
+use crate::store;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+
+const BASE_URL: &str = "https://knowyourmeme.com";
+
+/// 未學到延遲前的請求間隔
+const DEFAULT_DELAY_MS: u64 = 2000;
+
+/// 條目頁的結構化資料
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KymEntry {
+    /// 條目網址
+    pub url: String,
+    /// 梗的名稱
+    pub name: String,
+    /// 起源年份
+    pub origin_year: Option<u32>,
+    pub tags: Vec<String>,
+    /// About 段落
+    pub about: Option<String>,
+    /// 條目主圖（原始尺寸）
+    pub image_url: Option<String>,
+}
+
+impl KymEntry {
+    /// 寫入 metadata 的標籤與欄位
+    pub fn details(&self) -> ItemDetails {
+        let mut extra = serde_json::Map::new();
+        extra.insert("source".to_string(), json!("knowyourmeme"));
+        extra.insert("meme_name".to_string(), json!(self.name));
+        extra.insert("entry_url".to_string(), json!(self.url));
+        if let Some(year) = self.origin_year {
+            extra.insert("origin_year".to_string(), json!(year));
+        }
+        if let Some(about) = &self.about {
+            extra.insert("about".to_string(), Value::String(about.clone()));
+        }
+
+        ItemDetails {
+            tags: self.tags.clone(),
+            extra,
+            license: None,
+        }
+    }
+}
+
+/// 相簿的一頁
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KymGalleryPage {
+    /// 圖片網址（原始尺寸）
+    pub images: Vec<String>,
+    /// 是否還有下一頁
+    pub has_next: bool,
+}
+
+/// KnowYourMeme Parser
+///
+/// 三種頁面：條目列表（`/memes/page/N`）、條目頁（名稱/年份/標籤/About/主圖）、
+/// 相簿（`/memes/<slug>/photos/page/N`）。列表與相簿的圖片是縮圖，
+/// 網址中的尺寸目錄換成 `original` 就是原圖。
+pub struct KnowYourMemeParser {
+    entry_link: Selector,
+    title: Selector,
+    details_term: Selector,
+    tags: Selector,
+    about_heading: Selector,
+    og_image: Selector,
+    gallery_image: Selector,
+    next_page: Selector,
+}
+
+fn selector(css: &str) -> Result<Selector> {
+    Selector::parse(css).map_err(|e| anyhow::anyhow!("選擇器解析失敗: {:?}", e))
+}
+
+impl KnowYourMemeParser {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            entry_link: selector("table.entry_list td a.photo")?,
+            title: selector("section header h1, h1.entry-title")?,
+            details_term: selector("aside dl dt")?,
+            tags: selector("#entry_tags a, dl#entry_tags dd a")?,
+            about_heading: selector("section.bodycopy h2#about")?,
+            og_image: selector(r#"meta[property="og:image"]"#)?,
+            gallery_image: selector("#photo_gallery a.photo img")?,
+            next_page: selector(r#"a[rel="next"], .pagination a.next_page"#)?,
+        })
+    }
+
+    /// 條目列表頁：回傳條目網址
+    pub fn parse_entry_list(&self, html: &str) -> Vec<String> {
+        let document = Html::parse_document(html);
+        let mut entries: Vec<String> = document
+            .select(&self.entry_link)
+            .filter_map(|a| a.value().attr("href"))
+            .map(absolute_url)
+            .collect();
+
+        entries.dedup();
+        entries
+    }
+
+    /// 條目頁
+    pub fn parse_entry(&self, html: &str, url: &str) -> KymEntry {
+        let document = Html::parse_document(html);
+
+        let name = document
+            .select(&self.title)
+            .next()
+            .map(|h1| collapse_whitespace(&h1.text().collect::<String>()))
+            .unwrap_or_else(|| "unknown".to_string());
+
+        // 側欄 <dl>：<dt>Year</dt><dd><a>2016</a></dd>
+        let origin_year = document
+            .select(&self.details_term)
+            .find(|dt| dt.text().collect::<String>().trim().eq_ignore_ascii_case("year"))
+            .and_then(|dt| next_element(dt, "dd"))
+            .and_then(|dd| dd.text().collect::<String>().trim().parse().ok());
+
+        let mut tags: Vec<String> = document
+            .select(&self.tags)
+            .map(|a| a.text().collect::<String>().trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+
+        // About 標題之後的第一個段落
+        let about = document
+            .select(&self.about_heading)
+            .next()
+            .and_then(|h2| next_element(h2, "p"))
+            .map(|p| collapse_whitespace(&p.text().collect::<String>()))
+            .filter(|text| !text.is_empty());
+
+        let image_url = document
+            .select(&self.og_image)
+            .next()
+            .and_then(|meta| meta.value().attr("content"))
+            .map(|url| original_image_url(&absolute_url(url)));
+
+        KymEntry {
+            url: url.to_string(),
+            name,
+            origin_year,
+            tags,
+            about,
+            image_url,
+        }
+    }
+
+    /// 相簿頁（圖片延遲載入時網址在 data-src）
+    pub fn parse_gallery(&self, html: &str) -> KymGalleryPage {
+        let document = Html::parse_document(html);
+
+        let images = document
+            .select(&self.gallery_image)
+            .filter_map(|img| img.value().attr("data-src").or_else(|| img.value().attr("src")))
+            .filter(|url| !url.starts_with("data:"))
+            .map(|url| original_image_url(&absolute_url(url)))
+            .collect();
+
+        KymGalleryPage {
+            images,
+            has_next: document.select(&self.next_page).next().is_some(),
+        }
+    }
+}
+
+/// 同層中下一個指定標籤的元素
+fn next_element<'a>(element: ElementRef<'a>, tag: &str) -> Option<ElementRef<'a>> {
+    element
+        .next_siblings()
+        .filter_map(ElementRef::wrap)
+        .find(|e| e.value().name() == tag)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn absolute_url(url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url.to_string()
+    } else if url.starts_with("//") {
+        format!("https:{}", url)
+    } else {
+        format!("{}/{}", BASE_URL, url.trim_start_matches('/'))
+    }
+}
+
+/// 縮圖網址換成原圖（`/photos/images/masonry/...` → `/photos/images/original/...`）
+fn original_image_url(url: &str) -> String {
+    if !url.contains("kym-cdn.com") {
+        return url.to_string();
+    }
+
+    for size in ["masonry", "newsfeed", "list", "facebook", "mobile"] {
+        for kind in ["/photos/images/", "/entries/icons/"] {
+            let marker = format!("{}{}/", kind, size);
+            if url.contains(&marker) {
+                return url.replacen(&marker, &format!("{}original/", kind), 1);
+            }
+        }
+    }
+    url.to_string()
+}
+
+/// KnowYourMeme 來源設定
+#[derive(Debug, Clone)]
+pub struct KymConfig {
+    /// 最多爬幾頁條目列表（跨次執行累計）
+    pub max_list_pages: u32,
+    /// 每個條目額外抓幾頁相簿（0 表示只下載條目主圖）
+    pub gallery_pages: u32,
+}
+
+impl Default for KymConfig {
+    fn default() -> Self {
+        Self {
+            max_list_pages: 10,
+            gallery_pages: 0,
+        }
+    }
+}
+
+/// 爬取進度（kym_progress.json）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KymProgress {
+    /// 最後完成的列表頁
+    pub last_list_page: u32,
+    pub entries_crawled: usize,
+    pub images_downloaded: usize,
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+/// KnowYourMeme 來源
+///
+/// 逐頁讀取條目列表，每個條目解析名稱、起源年份、標籤與 About，
+/// 連同主圖（與選擇性的相簿圖片）交給共用的下載器；結構化欄位寫入 metadata 的 `tags`/`extra`。
+pub struct KnowYourMemeSource {
+    fetcher: HttpFetcher,
+    parser: KnowYourMemeParser,
+    downloader: ImageDownloader,
+    rate_limiter: Arc<AdaptiveRateLimiter>,
+    progress_file: String,
+    kym: KymConfig,
+    config: CrawlerConfig,
+}
+
+impl KnowYourMemeSource {
+    pub fn new(data_dir: &str, kym: KymConfig, config: CrawlerConfig) -> Result<Self> {
+        let file_manager = Arc::new(Mutex::new(FileManager::new(data_dir)?));
+        let rate_limiter = Arc::new(AdaptiveRateLimiter::load(data_dir)?);
+        let fetcher = HttpFetcher::new(config.timeout_secs, config.max_retries)?
+            .with_robots(config.respect_robots)
+            .with_token_bucket(config.token_bucket.clone())
+            .with_headers(config.headers.clone())
+            .with_proxies(&config.proxy)?
+            .with_rate_limiter(Arc::clone(&rate_limiter), DEFAULT_DELAY_MS);
+        let downloader = ImageDownloader::from_config(file_manager, store::open_store(data_dir, config.metadata_backend)?, &config)?
+            .with_source_site("knowyourmeme.com");
+
+        Ok(Self {
+            fetcher,
+            parser: KnowYourMemeParser::new()?,
+            downloader,
+            rate_limiter,
+            progress_file: format!("{}/kym_progress.json", data_dir),
+            kym,
+            config,
+        })
+    }
+
+    /// 訂閱下載完成的圖片（來源釋放後通道關閉）
+    pub fn subscribe(&self, buffer: usize) -> mpsc::Receiver<DownloadedImage> {
+        self.downloader.subscribe(buffer)
+    }
+
+    pub fn load_progress(&self) -> Result<KymProgress> {
+        if !Path::new(&self.progress_file).exists() {
+            return Ok(KymProgress::default());
+        }
+
+        serde_json::from_str(&fs::read_to_string(&self.progress_file)?)
+            .context("無法解析 kym_progress.json")
+    }
+
+    fn save_progress(&self, progress: &KymProgress) -> Result<()> {
+        let temp_path = format!("{}.tmp", self.progress_file);
+        fs::write(&temp_path, serde_json::to_string_pretty(progress)?)?;
+        fs::rename(&temp_path, &self.progress_file)?;
+        Ok(())
+    }
+
+    pub async fn run(&self) -> Result<()> {
+        let shutdown = ShutdownSignal::install();
+        let queue = DownloadQueue::new(self.downloader.clone(), self.config.max_in_flight_images);
+        let mut progress = self.load_progress()?;
+        let mut total = 0;
+
+        out!("📋 從列表第 {} 頁繼續\n", progress.last_list_page + 1);
+
+        while progress.last_list_page < self.kym.max_list_pages && !shutdown.is_triggered() {
+            let page = progress.last_list_page + 1;
+            let list_url = format!("{}/memes/page/{}", BASE_URL, page);
+
+            let entries = match self.fetcher.fetch_page(&list_url).await {
+                Ok(html) => self.parser.parse_entry_list(&html),
+                Err(e) => {
+                    eout!("❌ 列表第 {} 頁失敗: {}", page, e);
+                    break;
+                }
+            };
+            if entries.is_empty() {
+                out!("✅ 列表第 {} 頁沒有條目，已到底", page);
+                break;
+            }
+
+            let mut saved = 0;
+            for entry_url in &entries {
+                if shutdown.is_triggered() {
+                    break;
+                }
+                match self.crawl_entry(&queue, entry_url, page).await {
+                    Ok(count) => saved += count,
+                    Err(e) => eout!("❌ 條目失敗 ({}): {}", entry_url, e),
+                }
+            }
+
+            // 中斷時這一頁未完成，下次從同一頁重新開始
+            if shutdown.is_triggered() {
+                break;
+            }
+
+            out!("  📥 列表第 {} 頁: {} 個條目，下載 {} 張", page, entries.len(), saved);
+            progress.last_list_page = page;
+            progress.entries_crawled += entries.len();
+            progress.images_downloaded += saved;
+            progress.last_updated = Some(Utc::now());
+            self.save_progress(&progress)?;
+            total += saved;
+        }
+
+        if shutdown.is_triggered() {
+            out!("⏸️  已中斷，進度已儲存");
+        }
+
+        self.rate_limiter.save()?;
+        out!("\n✨ KnowYourMeme 完成，本次下載 {} 張圖片", total);
+        Ok(())
+    }
+
+    /// 爬取單一條目（主圖與相簿），回傳成功下載數
+    async fn crawl_entry(&self, queue: &DownloadQueue, entry_url: &str, page: u32) -> Result<usize> {
+        let html = self.fetcher.fetch_page(entry_url).await?;
+        let entry = self.parser.parse_entry(&html, entry_url);
+        let details = entry.details();
+
+        let mut images: Vec<String> = entry.image_url.iter().cloned().collect();
+        for gallery_page in 1..=self.kym.gallery_pages {
+            let url = format!("{}/photos/page/{}", entry_url.trim_end_matches('/'), gallery_page);
+            let gallery = self.parser.parse_gallery(&self.fetcher.fetch_page(&url).await?);
+            images.extend(gallery.images);
+            if !gallery.has_next {
+                break;
+            }
+        }
+        images.dedup();
+
+        let mut pending = Vec::with_capacity(images.len());
+        for url in images {
+            let job = ImageJob::new(url, entry.name.clone(), page).with_details(details.clone());
+            pending.push(queue.submit(job).await?);
+        }
+
+        let mut saved = 0;
+        for result in pending {
+            match result.await.unwrap_or_else(|_| Err(anyhow::anyhow!("下載工作中斷"))) {
+                Ok(DownloadOutcome::Saved) => saved += 1,
+                Ok(DownloadOutcome::Skipped(reason)) => eout!("略過 ({}): {}", entry.name, reason),
+                Ok(DownloadOutcome::Duplicate(existing)) => eout!("重複 ({}): 與 {} 相同", entry.name, existing),
+                Err(e) => eout!("下載失敗 ({}): {}", entry.name, e),
+            }
+        }
+        Ok(saved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry_and_gallery() {
+        let parser = KnowYourMemeParser::new().unwrap();
+
+        let list = r#"<table class="entry_list"><tr>
+            <td><a class="photo" href="/memes/drakeposting"><img data-src="x.jpg"></a></td>
+            <td><a class="photo" href="/memes/distracted-boyfriend"><img></a></td>
+        </tr></table>"#;
+        assert_eq!(parser.parse_entry_list(list), [
+            "https://knowyourmeme.com/memes/drakeposting",
+            "https://knowyourmeme.com/memes/distracted-boyfriend",
+        ]);
+
+        let entry = r#"<html><head>
+            <meta property="og:image" content="https://i.kym-cdn.com/entries/icons/facebook/000/024/194/drake.jpg">
+        </head><body>
+            <section><header><h1>
+                Drakeposting
+            </h1></header></section>
+            <aside class="left"><dl>
+                <dt>Status</dt><dd>Confirmed</dd>
+                <dt>Year</dt><dd><a href="/types/2015">2015</a></dd>
+            </dl></aside>
+            <dl id="entry_tags"><dt>Tags</dt><dd><a>hotline bling</a>, <a>drake</a></dd></dl>
+            <section class="bodycopy"><h2 id="about">About</h2><p>Drakeposting is a   two-panel image macro.</p></section>
+        </body></html>"#;
+
+        let entry = parser.parse_entry(entry, "https://knowyourmeme.com/memes/drakeposting");
+        assert_eq!(entry.name, "Drakeposting");
+        assert_eq!(entry.origin_year, Some(2015));
+        assert_eq!(entry.tags, ["drake", "hotline bling"]);
+        assert_eq!(entry.about.as_deref(), Some("Drakeposting is a two-panel image macro."));
+        assert_eq!(entry.image_url.as_deref(), Some("https://i.kym-cdn.com/entries/icons/original/000/024/194/drake.jpg"));
+        assert_eq!(entry.details().extra["origin_year"], json!(2015));
+
+        let gallery = r#"<div id="photo_gallery">
+            <a class="photo" href="/photos/1"><img src="data:image/gif;base64,R0l" data-src="https://i.kym-cdn.com/photos/images/masonry/001/1.png"></a>
+        </div><a rel="next" href="/memes/drakeposting/photos/page/2">Next</a>"#;
+        let page = parser.parse_gallery(gallery);
+        assert_eq!(page.images, ["https://i.kym-cdn.com/photos/images/original/001/1.png"]);
+        assert!(page.has_next);
+    }
+}
@@ -0,0 +1,11 @@
+// 非 HTML 模板網站的圖片來源（產生與爬蟲相同的 ImageMetadata）
+pub mod reddit;
+pub mod knowyourmeme;
+pub mod url_list;
+pub mod feed;
+
+// 重新導出
+pub use reddit::{RedditConfig, RedditSort, RedditSource};
+pub use knowyourmeme::{KnowYourMemeSource, KymConfig};
+pub use url_list::{UrlListSource, UrlListSummary};
+pub use feed::{FeedConfig, FeedSource};
@@ -0,0 +1,370 @@
+use crate::crawler::CrawlerConfig;
+use crate::crawler::downloader::{DownloadOutcome, ImageDownloader};
+use crate::crawler::download_queue::{DownloadQueue, ImageJob};
+use crate::crawler::types::DownloadedImage;
+use crate::fetcher::{Fetcher, HttpFetcher};
+use crate::file_manager::FileManager;
+use crate::rate_limit::AdaptiveRateLimiter;
+use crate::shutdown::ShutdownSignal;
+use crate::store;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+
+/// 未學到延遲前的請求間隔（未登入的 JSON API 大約每分鐘 10 次）
+const DEFAULT_DELAY_MS: u64 = 6000;
+
+/// Reddit 列表排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedditSort {
+    #[default]
+    Hot,
+    New,
+    /// `t` 參數：hour, day, week, month, year, all
+    Top(&'static str),
+}
+
+impl RedditSort {
+    /// 解析 `hot` / `new` / `top` / `top:week`
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.split_once(':') {
+            None if spec == "hot" => Ok(Self::Hot),
+            None if spec == "new" => Ok(Self::New),
+            None if spec == "top" => Ok(Self::Top("week")),
+            Some(("top", window)) => {
+                let window = ["hour", "day", "week", "month", "year", "all"]
+                    .into_iter()
+                    .find(|w| *w == window)
+                    .with_context(|| format!("未知的 top 時間範圍: {}（可用: hour, day, week, month, year, all）", window))?;
+                Ok(Self::Top(window))
+            }
+            _ => anyhow::bail!("未知的排序: {}（可用: hot, new, top, top:<範圍>）", spec),
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self {
+            Self::Hot => "hot",
+            Self::New => "new",
+            Self::Top(_) => "top",
+        }
+    }
+}
+
+/// Reddit 來源設定
+#[derive(Debug, Clone)]
+pub struct RedditConfig {
+    /// 版名（不含 `r/`），例如 memes、dankmemes
+    pub subreddits: Vec<String>,
+    pub sort: RedditSort,
+    /// 每個版最多爬幾頁列表（跨次執行累計）
+    pub max_pages: u32,
+    /// 每頁貼文數（API 上限 100）
+    pub limit: u32,
+    /// 是否包含 NSFW 貼文
+    pub include_nsfw: bool,
+}
+
+impl Default for RedditConfig {
+    fn default() -> Self {
+        Self {
+            subreddits: vec!["memes".to_string()],
+            sort: RedditSort::default(),
+            max_pages: 10,
+            limit: 100,
+            include_nsfw: false,
+        }
+    }
+}
+
+impl RedditConfig {
+    /// 解析逗號分隔的版名（`memes,r/dankmemes`）
+    pub fn parse_subreddits(list: &str) -> Vec<String> {
+        list.split(',')
+            .map(|s| s.trim().trim_start_matches("r/").to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// 列表 API 的 URL
+    pub fn listing_url(&self, subreddit: &str, after: Option<&str>) -> String {
+        let mut url = format!(
+            "https://www.reddit.com/r/{}/{}.json?limit={}&raw_json=1",
+            subreddit,
+            self.sort.path(),
+            self.limit.min(100),
+        );
+        if let RedditSort::Top(window) = self.sort {
+            url.push_str(&format!("&t={}", window));
+        }
+        if let Some(after) = after {
+            url.push_str(&format!("&after={}", urlencoding::encode(after)));
+        }
+        url
+    }
+}
+
+/// 列表中的圖片貼文
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedditPost {
+    pub id: String,
+    pub title: String,
+    pub image_url: String,
+    pub over_18: bool,
+}
+
+/// 一頁列表
+#[derive(Debug, Clone)]
+pub struct RedditListing {
+    pub posts: Vec<RedditPost>,
+    /// 下一頁的 token（None 表示已到底）
+    pub after: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawListing {
+    data: RawListingData,
+}
+
+#[derive(Deserialize)]
+struct RawListingData {
+    after: Option<String>,
+    children: Vec<RawChild>,
+}
+
+#[derive(Deserialize)]
+struct RawChild {
+    data: RawPost,
+}
+
+#[derive(Deserialize)]
+struct RawPost {
+    id: String,
+    title: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    post_hint: Option<String>,
+    #[serde(default)]
+    is_video: bool,
+    #[serde(default)]
+    over_18: bool,
+}
+
+/// 解析列表 JSON，只保留圖片貼文（影片、連結、相簿略過）
+pub fn parse_listing(json: &str) -> Result<RedditListing> {
+    let raw: RawListing = serde_json::from_str(json)
+        .context("無法解析 Reddit 列表")?;
+
+    let posts = raw.data.children
+        .into_iter()
+        .map(|child| child.data)
+        .filter(|post| !post.is_video)
+        .filter_map(|post| {
+            let url = post.url?;
+            let is_image = post.post_hint.as_deref() == Some("image") || is_image_url(&url);
+            is_image.then_some(RedditPost {
+                id: post.id,
+                title: post.title,
+                image_url: url,
+                over_18: post.over_18,
+            })
+        })
+        .collect();
+
+    Ok(RedditListing {
+        posts,
+        after: raw.data.after.filter(|a| !a.is_empty()),
+    })
+}
+
+/// 直接指向圖片檔的 URL
+fn is_image_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+    [".jpg", ".jpeg", ".png", ".gif", ".webp"].iter().any(|ext| path.ends_with(ext))
+}
+
+/// 單一版的爬取進度
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubredditProgress {
+    /// 下一頁的 token
+    pub after: Option<String>,
+    /// 已爬的列表頁數
+    pub pages: u32,
+    pub images_downloaded: usize,
+    /// 列表已到底
+    pub exhausted: bool,
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+/// Reddit 來源
+///
+/// 依 `after` token 翻頁讀取各版的列表，圖片交給與 HTML 爬蟲相同的下載器，
+/// 所以檔名樣板、尺寸過濾、metadata 與事件訂閱都一樣；
+/// 每頁結束後把 token 存到 reddit_progress.json，中斷後從同一頁繼續。
+pub struct RedditSource {
+    fetcher: HttpFetcher,
+    downloader: ImageDownloader,
+    rate_limiter: Arc<AdaptiveRateLimiter>,
+    progress_file: String,
+    reddit: RedditConfig,
+    config: CrawlerConfig,
+}
+
+impl RedditSource {
+    pub fn new(data_dir: &str, reddit: RedditConfig, config: CrawlerConfig) -> Result<Self> {
+        let file_manager = Arc::new(Mutex::new(FileManager::new(data_dir)?));
+        let rate_limiter = Arc::new(AdaptiveRateLimiter::load(data_dir)?);
+        let fetcher = HttpFetcher::new(config.timeout_secs, config.max_retries)?
+            .with_robots(config.respect_robots)
+            .with_token_bucket(config.token_bucket.clone())
+            .with_headers(config.headers.clone())
+            .with_proxies(&config.proxy)?
+            .with_rate_limiter(Arc::clone(&rate_limiter), DEFAULT_DELAY_MS);
+        let downloader = ImageDownloader::from_config(file_manager, store::open_store(data_dir, config.metadata_backend)?, &config)?
+            .with_source_site("reddit.com");
+
+        Ok(Self {
+            fetcher,
+            downloader,
+            rate_limiter,
+            progress_file: format!("{}/reddit_progress.json", data_dir),
+            reddit,
+            config,
+        })
+    }
+
+    /// 訂閱下載完成的圖片（來源釋放後通道關閉）
+    pub fn subscribe(&self, buffer: usize) -> mpsc::Receiver<DownloadedImage> {
+        self.downloader.subscribe(buffer)
+    }
+
+    /// 讀取各版進度
+    pub fn load_progress(&self) -> Result<BTreeMap<String, SubredditProgress>> {
+        if !Path::new(&self.progress_file).exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        serde_json::from_str(&fs::read_to_string(&self.progress_file)?)
+            .context("無法解析 reddit_progress.json")
+    }
+
+    fn save_progress(&self, progress: &BTreeMap<String, SubredditProgress>) -> Result<()> {
+        let temp_path = format!("{}.tmp", self.progress_file);
+        fs::write(&temp_path, serde_json::to_string_pretty(progress)?)?;
+        fs::rename(&temp_path, &self.progress_file)?;
+        Ok(())
+    }
+
+    pub async fn run(&self) -> Result<()> {
+        let shutdown = ShutdownSignal::install();
+        let queue = DownloadQueue::new(self.downloader.clone(), self.config.max_in_flight_images);
+        let mut progress = self.load_progress()?;
+        let mut total = 0;
+
+        for subreddit in &self.reddit.subreddits {
+            let mut state = progress.get(subreddit).cloned().unwrap_or_default();
+            if state.exhausted || state.pages >= self.reddit.max_pages {
+                out!("✅ r/{}: 已完成 {} 頁，略過", subreddit, state.pages);
+                continue;
+            }
+
+            out!("📡 r/{}（從第 {} 頁繼續）", subreddit, state.pages + 1);
+
+            while !state.exhausted && state.pages < self.reddit.max_pages && !shutdown.is_triggered() {
+                let page = state.pages + 1;
+                let url = self.reddit.listing_url(subreddit, state.after.as_deref());
+
+                let listing = match self.fetcher.fetch_page(&url).await.and_then(|body| parse_listing(&body)) {
+                    Ok(listing) => listing,
+                    Err(e) => {
+                        eout!("❌ r/{} 第 {} 頁失敗: {}", subreddit, page, e);
+                        break;
+                    }
+                };
+
+                let saved = self.download_posts(&queue, &listing.posts, page).await?;
+                out!("  📥 第 {} 頁: {} 則圖片貼文，下載 {} 張", page, listing.posts.len(), saved);
+
+                state.pages = page;
+                state.images_downloaded += saved;
+                state.exhausted = listing.after.is_none();
+                state.after = listing.after;
+                state.last_updated = Some(Utc::now());
+                total += saved;
+
+                progress.insert(subreddit.clone(), state.clone());
+                self.save_progress(&progress)?;
+            }
+
+            if shutdown.is_triggered() {
+                out!("⏸️  已中斷，進度已儲存");
+                break;
+            }
+        }
+
+        self.rate_limiter.save()?;
+        out!("\n✨ Reddit 完成，本次下載 {} 張圖片", total);
+        Ok(())
+    }
+
+    /// 下載一頁的圖片貼文，回傳成功數
+    async fn download_posts(&self, queue: &DownloadQueue, posts: &[RedditPost], page: u32) -> Result<usize> {
+        let mut pending = Vec::new();
+        for post in posts {
+            if post.over_18 && !self.reddit.include_nsfw {
+                continue;
+            }
+
+            let job = ImageJob::new(post.image_url.clone(), post.title.clone(), page);
+            pending.push((post.id.clone(), queue.submit(job).await?));
+        }
+
+        let mut saved = 0;
+        for (id, result) in pending {
+            match result.await.unwrap_or_else(|_| Err(anyhow::anyhow!("下載工作中斷"))) {
+                Ok(DownloadOutcome::Saved) => saved += 1,
+                Ok(DownloadOutcome::Skipped(reason)) => eout!("略過 ({}): {}", id, reason),
+                Ok(DownloadOutcome::Duplicate(existing)) => eout!("重複 ({}): 與 {} 相同", id, existing),
+                Err(e) => eout!("下載失敗 ({}): {}", id, e),
+            }
+        }
+        Ok(saved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listing() {
+        let json = r#"{"kind":"Listing","data":{"after":"t3_ccc","children":[
+            {"kind":"t3","data":{"id":"aaa","title":"cat meme","url":"https://i.redd.it/aaa.png","post_hint":"image","is_video":false,"over_18":false}},
+            {"kind":"t3","data":{"id":"bbb","title":"video","url":"https://v.redd.it/bbb","post_hint":"hosted:video","is_video":true}},
+            {"kind":"t3","data":{"id":"ccc","title":"imgur","url":"https://i.imgur.com/ccc.jpg?1","over_18":true}},
+            {"kind":"t3","data":{"id":"ddd","title":"link","url":"https://example.com/article"}}
+        ]}}"#;
+
+        let listing = parse_listing(json).unwrap();
+        assert_eq!(listing.after.as_deref(), Some("t3_ccc"));
+        assert_eq!(listing.posts.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(), ["aaa", "ccc"]);
+        assert!(listing.posts[1].over_18);
+
+        let config = RedditConfig {
+            subreddits: RedditConfig::parse_subreddits("memes, r/dankmemes"),
+            sort: RedditSort::parse("top:day").unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(config.subreddits, ["memes", "dankmemes"]);
+        assert_eq!(
+            config.listing_url("memes", Some("t3_ccc")),
+            "https://www.reddit.com/r/memes/top.json?limit=100&raw_json=1&t=day&after=t3_ccc"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use crate::crawler::CrawlerConfig;
+use crate::crawler::downloader::{DownloadOutcome, ImageDownloader, ItemDetails};
+use crate::crawler::download_queue::{DownloadQueue, ImageJob};
+use crate::crawler::types::DownloadedImage;
+use crate::file_manager::FileManager;
+use crate::rate_limit;
+use crate::shutdown::ShutdownSignal;
+use crate::store;
+use crate::terminal;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+
+/// 清單中的一個網址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlEntry {
+    pub url: String,
+    /// 圖片名稱（清單沒寫時取網址的檔名）
+    pub name: String,
+    /// 清單中的行號，寫入 metadata 的 `page_number`
+    pub line: u32,
+}
+
+/// 解析網址清單：每行 `網址` 或 `網址<Tab>名稱`，空行與 `#` 開頭的行略過，重複的網址只保留第一次
+pub fn parse_url_list(content: &str) -> Result<Vec<UrlEntry>> {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (url, name) = match line.split_once(char::is_whitespace) {
+            Some((url, name)) => (url, name.trim()),
+            None => (line, ""),
+        };
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            anyhow::bail!("第 {} 行不是網址: {}", i + 1, url);
+        }
+        if !seen.insert(url.to_string()) {
+            continue;
+        }
+
+        entries.push(UrlEntry {
+            url: url.to_string(),
+            name: if name.is_empty() { name_from_url(url) } else { name.to_string() },
+            line: i as u32 + 1,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// 網址最後一段去掉副檔名（`.../cat%20meme.jpg?x=1` → `cat meme`）
+fn name_from_url(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let segment = path.rsplit('/').next().unwrap_or_default();
+    let stem = segment.rsplit_once('.').map_or(segment, |(stem, _)| stem);
+    urlencoding::decode(stem).map(|s| s.into_owned()).unwrap_or_else(|_| stem.to_string())
+}
+
+/// 單一清單的下載進度
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UrlListProgress {
+    /// 已處理的網址（下載成功或未通過過濾）
+    pub completed: BTreeSet<String>,
+    /// 最近一次失敗的網址與原因（下次執行會重試）
+    pub failed: BTreeMap<String, String>,
+    pub images_downloaded: usize,
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+/// 本次執行的結果
+#[derive(Debug, Default)]
+pub struct UrlListSummary {
+    /// 清單中的網址數
+    pub total: usize,
+    /// 先前已完成而略過的網址數
+    pub already_done: usize,
+    pub saved: usize,
+    /// 未通過過濾條件的網址數
+    pub filtered: usize,
+    pub failed: usize,
+    pub interrupted: bool,
+}
+
+/// 網址清單來源
+///
+/// 不抓頁面也不解析，直接把清單中的圖片網址交給與爬蟲相同的下載器，
+/// 所以檔名樣板、尺寸過濾、metadata 與事件訂閱都一樣；
+/// 每批結束後把進度存到 url_list_progress.json（以清單路徑區分），中斷後只下載未完成的網址。
+pub struct UrlListSource {
+    downloader: ImageDownloader,
+    progress_file: String,
+    list_path: String,
+    config: CrawlerConfig,
+}
+
+impl UrlListSource {
+    pub fn new(data_dir: &str, list_path: &str, config: CrawlerConfig) -> Result<Self> {
+        let file_manager = Arc::new(Mutex::new(FileManager::new(data_dir)?));
+        let downloader = ImageDownloader::from_config(file_manager, store::open_store(data_dir, config.metadata_backend)?, &config)?;
+
+        Ok(Self {
+            downloader,
+            progress_file: format!("{}/url_list_progress.json", data_dir),
+            list_path: list_path.to_string(),
+            config,
+        })
+    }
+
+    /// 訂閱下載完成的圖片（來源釋放後通道關閉）
+    pub fn subscribe(&self, buffer: usize) -> mpsc::Receiver<DownloadedImage> {
+        self.downloader.subscribe(buffer)
+    }
+
+    /// 讀取各清單的進度
+    pub fn load_progress(&self) -> Result<BTreeMap<String, UrlListProgress>> {
+        if !Path::new(&self.progress_file).exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        serde_json::from_str(&fs::read_to_string(&self.progress_file)?)
+            .context("無法解析 url_list_progress.json")
+    }
+
+    fn save_progress(&self, progress: &BTreeMap<String, UrlListProgress>) -> Result<()> {
+        let temp_path = format!("{}.tmp", self.progress_file);
+        fs::write(&temp_path, serde_json::to_string_pretty(progress)?)?;
+        fs::rename(&temp_path, &self.progress_file)?;
+        Ok(())
+    }
+
+    pub async fn run(&self) -> Result<UrlListSummary> {
+        let content = fs::read_to_string(&self.list_path)
+            .with_context(|| format!("無法讀取網址清單: {}", self.list_path))?;
+        let entries = parse_url_list(&content)?;
+
+        let mut progress = self.load_progress()?;
+        let mut state = progress.get(&self.list_path).cloned().unwrap_or_default();
+
+        let pending: Vec<_> = entries
+            .iter()
+            .filter(|entry| !state.completed.contains(&entry.url))
+            .collect();
+        let mut summary = UrlListSummary {
+            total: entries.len(),
+            already_done: entries.len() - pending.len(),
+            ..Default::default()
+        };
+
+        out!("📋 清單: {} 個網址（已完成 {}，待下載 {}）", summary.total, summary.already_done, pending.len());
+        if !state.failed.is_empty() {
+            out!("🔁 重試上次失敗的 {} 個網址", state.failed.len());
+        }
+
+        let shutdown = ShutdownSignal::install();
+        let max_in_flight = self.config.max_in_flight_images.max(1);
+
+        // 來源網站依各網址的 host 記錄
+        let downloader = self.downloader.clone();
+        let queue = DownloadQueue::spawn_with(max_in_flight, move |job: ImageJob| {
+            let downloader = downloader.clone().with_source_site(rate_limit::host_of(&job.url));
+            async move { downloader.download_and_save_with(&job.url, &job.name, job.page, job.details).await }
+        });
+
+        let pb = ProgressBar::new(pending.len() as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} 張 ({percent}%) {eta} {msg}")
+                .unwrap()
+                .progress_chars("=>-")
+        );
+
+        // 每批送出數倍於同時下載數的網址，批次結束時存檔
+        for batch in pending.chunks(max_in_flight * 4) {
+            if shutdown.is_triggered() {
+                summary.interrupted = true;
+                break;
+            }
+
+            let mut results = Vec::with_capacity(batch.len());
+            for entry in batch {
+                let job = ImageJob::new(entry.url.clone(), entry.name.clone(), entry.line)
+                    .with_details(details());
+                results.push((entry, queue.submit(job).await?));
+            }
+
+            for (entry, result) in results {
+                match result.await.unwrap_or_else(|_| Err(anyhow::anyhow!("下載工作中斷"))) {
+                    Ok(outcome) => {
+                        if outcome == DownloadOutcome::Saved {
+                            summary.saved += 1;
+                            state.images_downloaded += 1;
+                        } else {
+                            summary.filtered += 1;
+                        }
+                        state.failed.remove(&entry.url);
+                        state.completed.insert(entry.url.clone());
+                    }
+                    Err(e) => {
+                        pb.println(terminal::message(format!("❌ 第 {} 行 {}: {}", entry.line, entry.url, e)));
+                        summary.failed += 1;
+                        state.failed.insert(entry.url.clone(), e.to_string());
+                    }
+                }
+                pb.inc(1);
+            }
+
+            pb.set_message(format!("失敗 {}", summary.failed));
+            state.last_updated = Some(Utc::now());
+            progress.insert(self.list_path.clone(), state.clone());
+            self.save_progress(&progress)?;
+        }
+
+        if summary.interrupted {
+            pb.abandon_with_message(terminal::message("⏸️  已中斷，進度已儲存"));
+        } else {
+            pb.finish_with_message(format!("完成（失敗 {}）", summary.failed));
+        }
+
+        Ok(summary)
+    }
+}
+
+/// 寫入 metadata 的來源標記（diff-crawl 不會把這些項目當成網站頁面的內容）
+fn details() -> ItemDetails {
+    let mut extra = serde_json::Map::new();
+    extra.insert("source".to_string(), json!("url_list"));
+
+    ItemDetails {
+        tags: Vec::new(),
+        extra,
+        license: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url_list() {
+        let content = "\
+# 其他爬蟲的輸出
+https://i.example.com/cat%20meme.jpg?w=1
+
+https://i.example.com/doge.png\tsuch wow
+https://i.example.com/cat%20meme.jpg?w=1
+";
+
+        let entries = parse_url_list(content).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "cat meme");
+        assert_eq!(entries[0].line, 2);
+        assert_eq!(entries[1].name, "such wow");
+        assert_eq!(entries[1].line, 4);
+
+        let error = parse_url_list("https://a.com/1.jpg\nnot-a-url\n").unwrap_err();
+        assert!(error.to_string().contains("第 2 行"));
+    }
+}
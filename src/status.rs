@@ -0,0 +1,193 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// 最多保留的最近錯誤數
+pub const RECENT_ERRORS: usize = 20;
+
+/// 執行中的錯誤
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusError {
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+/// 某一刻的執行狀態（可序列化成 JSON 給控制介面或 web UI，不必解析輸出）
+///
+/// 爬取的單位是頁面、項目是下載的圖片；搜尋的單位是圖片、項目是完成的服務數。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusSnapshot {
+    /// `crawl` 或 `search`（尚未開始時為空）
+    pub task: String,
+    pub running: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub elapsed_secs: f64,
+    /// 正在處理的頁面範圍（爬取時）
+    pub page_range: Option<(u32, u32)>,
+    /// 本次要處理的單位數（未知時為 None）
+    pub total: Option<u64>,
+    /// 已處理的單位數（含失敗）
+    pub completed: u64,
+    pub failed: u64,
+    pub items: u64,
+    /// 每分鐘處理的單位數
+    pub completed_per_minute: f64,
+    /// 每分鐘的項目數
+    pub items_per_minute: f64,
+    /// 最近的錯誤（舊的在前）
+    pub recent_errors: Vec<StatusError>,
+}
+
+impl StatusSnapshot {
+    /// 完成比例（0~100，總數未知時為 None）
+    pub fn percent(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| self.completed as f64 * 100.0 / total as f64)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    snapshot: StatusSnapshot,
+    started: Option<Instant>,
+    errors: VecDeque<StatusError>,
+}
+
+/// 執行狀態的共用 handle（複製後指向同一份狀態）
+///
+/// 引擎在執行時更新，其他 task 或執行緒隨時以 `snapshot` 取得目前狀態。
+#[derive(Debug, Clone, Default)]
+pub struct StatusHandle {
+    state: Arc<Mutex<State>>,
+}
+
+impl StatusHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 開始新的一次執行（清除上次的計數與錯誤），回傳的 guard 釋放時標記結束
+    pub fn start(&self, task: &str, total: Option<u64>) -> Running {
+        let mut state = self.state.lock().unwrap();
+        *state = State {
+            snapshot: StatusSnapshot {
+                task: task.to_string(),
+                running: true,
+                started_at: Some(Utc::now()),
+                total,
+                ..Default::default()
+            },
+            started: Some(Instant::now()),
+            errors: VecDeque::new(),
+        };
+        Running(self.clone())
+    }
+
+    pub fn set_page_range(&self, first: u32, last: u32) {
+        self.state.lock().unwrap().snapshot.page_range = Some((first, last));
+    }
+
+    /// 記錄一個處理完的單位與它的項目數
+    pub fn record(&self, ok: bool, items: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.snapshot.completed += 1;
+        state.snapshot.items += items;
+        if !ok {
+            state.snapshot.failed += 1;
+        }
+    }
+
+    pub fn record_error(&self, message: impl Into<String>) {
+        let mut state = self.state.lock().unwrap();
+        if state.errors.len() == RECENT_ERRORS {
+            state.errors.pop_front();
+        }
+        state.errors.push_back(StatusError {
+            at: Utc::now(),
+            message: message.into(),
+        });
+    }
+
+    /// 執行結束（計數保留到下次 `start`）
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = state.started.map(|started| started.elapsed().as_secs_f64()).unwrap_or_default();
+        state.snapshot.elapsed_secs = elapsed;
+        state.snapshot.running = false;
+        state.snapshot.page_range = None;
+        state.started = None;
+    }
+
+    /// 目前的狀態
+    pub fn snapshot(&self) -> StatusSnapshot {
+        let state = self.state.lock().unwrap();
+        let mut snapshot = state.snapshot.clone();
+        if let Some(started) = state.started {
+            snapshot.elapsed_secs = started.elapsed().as_secs_f64();
+        }
+        let minutes = snapshot.elapsed_secs / 60.0;
+        if minutes > 0.0 {
+            snapshot.completed_per_minute = snapshot.completed as f64 / minutes;
+            snapshot.items_per_minute = snapshot.items as f64 / minutes;
+        }
+        snapshot.recent_errors = state.errors.iter().cloned().collect();
+        snapshot
+    }
+}
+
+/// 執行中的標記（提早以錯誤結束時也會標記結束）
+#[must_use]
+pub struct Running(StatusHandle);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_handle() {
+        let status = StatusHandle::new();
+        assert!(!status.snapshot().running);
+
+        let shared = status.clone();
+        let running = status.start("crawl", Some(4));
+        status.set_page_range(1, 2);
+        status.record(true, 10);
+        status.record(false, 0);
+        for i in 0..RECENT_ERRORS + 5 {
+            status.record_error(format!("第 {} 頁失敗", i));
+        }
+
+        let snapshot = shared.snapshot();
+        assert!(snapshot.running);
+        assert_eq!(snapshot.page_range, Some((1, 2)));
+        assert_eq!((snapshot.completed, snapshot.failed, snapshot.items), (2, 1, 10));
+        assert_eq!(snapshot.percent(), Some(50.0));
+        assert_eq!(snapshot.recent_errors.len(), RECENT_ERRORS);
+        assert_eq!(snapshot.recent_errors[0].message, "第 5 頁失敗");
+        assert!(snapshot.items_per_minute > 0.0);
+
+        drop(running);
+        let snapshot = shared.snapshot();
+        assert!(!snapshot.running);
+        assert_eq!(snapshot.page_range, None);
+        assert_eq!(snapshot.items, 10);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let parsed: StatusSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!((parsed.task, parsed.total, parsed.items), ("crawl".to_string(), Some(4), 10));
+        assert_eq!(parsed.recent_errors, snapshot.recent_errors);
+
+        let _running = status.start("search", None);
+        assert_eq!(shared.snapshot().completed, 0);
+        assert_eq!(shared.snapshot().percent(), None);
+    }
+}
@@ -0,0 +1,77 @@
+// 子模組
+pub mod sqlite;
+
+pub use sqlite::SqliteStore;
+
+use crate::file_manager::FileManager;
+use crate::reverse_search::ReverseSearchResult;
+use crate::types::{DuplicateRecord, ImageMetadata};
+use anyhow::Result;
+use std::sync::Arc;
+
+/// Metadata 儲存後端 Trait
+///
+/// JSONL（`FileManager`）是預設後端；SQLite 後端把圖片、重複組與搜尋結果
+/// 放在同一個 `metadata.db`，大量資料時不必每次重寫/重掃整個 JSONL。
+pub trait MetadataStore: Send + Sync {
+    /// 新增一筆圖片 metadata
+    fn append_metadata(&self, metadata: &ImageMetadata) -> Result<()>;
+
+    /// 讀取所有圖片 metadata（依寫入順序）
+    fn load_all_metadata(&self) -> Result<Vec<ImageMetadata>>;
+
+    /// 以新的列表取代所有圖片 metadata（用於去重後更新）
+    fn rewrite_metadata(&self, metadata_list: &[ImageMetadata]) -> Result<()>;
+
+    /// 儲存重複組（JSONL 後端由 duplicates.json 負責，預設不做事）
+    fn save_duplicate_groups(&self, _groups: &[DuplicateRecord]) -> Result<()> {
+        Ok(())
+    }
+
+    /// 新增一筆反向搜尋結果（JSONL 後端由 reverse_search_results.jsonl 負責，預設不做事）
+    fn append_search_result(&self, _result: &ReverseSearchResult) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl MetadataStore for FileManager {
+    fn append_metadata(&self, metadata: &ImageMetadata) -> Result<()> {
+        FileManager::append_metadata(self, metadata)
+    }
+
+    fn load_all_metadata(&self) -> Result<Vec<ImageMetadata>> {
+        FileManager::load_all_metadata(self)
+    }
+
+    fn rewrite_metadata(&self, metadata_list: &[ImageMetadata]) -> Result<()> {
+        FileManager::rewrite_metadata(self, metadata_list)
+    }
+}
+
+/// 後端種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataBackend {
+    /// metadata.jsonl（預設）
+    #[default]
+    Jsonl,
+    /// metadata.db
+    Sqlite,
+}
+
+impl MetadataBackend {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "jsonl" => Ok(Self::Jsonl),
+            "sqlite" => Ok(Self::Sqlite),
+            other => anyhow::bail!("未知的 metadata 後端: {}（可用: jsonl, sqlite）", other),
+        }
+    }
+}
+
+/// 開啟資料目錄的 metadata 後端
+pub fn open_store(data_dir: &str, backend: MetadataBackend) -> Result<Arc<dyn MetadataStore>> {
+    Ok(match backend {
+        MetadataBackend::Jsonl => Arc::new(FileManager::new(data_dir)?),
+        MetadataBackend::Sqlite => Arc::new(SqliteStore::open(&format!("{}/metadata.db", data_dir))?),
+    })
+}
@@ -0,0 +1,278 @@
+use crate::dedup_index::HashSummary;
+use crate::reverse_search::ReverseSearchResult;
+use crate::types::{DuplicateRecord, ImageMetadata};
+use super::MetadataStore;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::sync::Mutex;
+
+/// SQLite metadata 後端
+///
+/// 常用查詢欄位（檔名、hash、URL、頁碼）獨立成欄位並建立索引，
+/// 完整記錄以 JSON 存在 `data` 欄位，之後擴充 metadata 欄位不需要改 schema。
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS images (
+        id            INTEGER PRIMARY KEY AUTOINCREMENT,
+        filename      TEXT NOT NULL,
+        content_hash  TEXT NOT NULL,
+        url           TEXT NOT NULL,
+        page_number   INTEGER NOT NULL,
+        downloaded_at TEXT NOT NULL,
+        data          TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_images_filename ON images(filename);
+    CREATE INDEX IF NOT EXISTS idx_images_hash ON images(content_hash);
+
+    CREATE TABLE IF NOT EXISTS duplicate_groups (
+        content_hash TEXT NOT NULL,
+        position     INTEGER NOT NULL,
+        filename     TEXT NOT NULL,
+        PRIMARY KEY (content_hash, position)
+    );
+
+    CREATE TABLE IF NOT EXISTS search_results (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        filename    TEXT NOT NULL,
+        service     TEXT NOT NULL,
+        best_guess  TEXT,
+        searched_at TEXT NOT NULL,
+        data        TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_results_filename ON search_results(filename);
+";
+
+impl SqliteStore {
+    /// 開啟（或建立）資料庫
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("無法開啟 SQLite 資料庫: {}", path))?;
+
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .context("無法設定 WAL 模式")?;
+        conn.execute_batch(SCHEMA)
+            .context("無法建立資料表")?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 讀取所有搜尋結果
+    pub fn load_search_results(&self) -> Result<Vec<ReverseSearchResult>> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare("SELECT data FROM search_results ORDER BY id")?;
+
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut list = Vec::new();
+        for row in rows {
+            list.push(serde_json::from_str(&row?).context("解析搜尋結果失敗")?);
+        }
+
+        Ok(list)
+    }
+
+    /// 以新的列表取代所有搜尋結果（重新搜尋後去掉被取代的舊結果）
+    pub fn rewrite_search_results(&self, results: &[ReverseSearchResult]) -> Result<()> {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM search_results", [])?;
+        for result in results {
+            insert_result(&tx, result)?;
+        }
+
+        tx.commit().context("無法提交搜尋結果更新")?;
+        Ok(())
+    }
+
+    /// 去重用的數量與重複組成員（以 content_hash 索引查詢，不讀取全部 metadata）
+    pub fn hash_summary(&self) -> Result<HashSummary> {
+        let conn = self.lock();
+        let (total, unique) = conn.query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT content_hash) FROM images",
+            [],
+            |row| Ok((row.get::<_, i64>(0)? as usize, row.get::<_, i64>(1)? as usize)),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT content_hash, data FROM images
+             WHERE content_hash IN (SELECT content_hash FROM images GROUP BY content_hash HAVING COUNT(*) > 1)
+             ORDER BY content_hash, id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+        let mut groups: Vec<(String, Vec<ImageMetadata>)> = Vec::new();
+        for row in rows {
+            let (hash, data) = row?;
+            let metadata: ImageMetadata = serde_json::from_str(&data).context("解析 metadata 失敗")?;
+            match groups.last_mut() {
+                Some((last, members)) if *last == hash => members.push(metadata),
+                _ => groups.push((hash, vec![metadata])),
+            }
+        }
+
+        Ok(HashSummary { total, unique, groups })
+    }
+
+    /// 以 JSONL 資料取代資料庫內容（搬移既有資料用）
+    pub fn import(
+        &self,
+        metadata_list: &[ImageMetadata],
+        groups: &[DuplicateRecord],
+        results: &[ReverseSearchResult],
+    ) -> Result<()> {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM images", [])?;
+        tx.execute("DELETE FROM duplicate_groups", [])?;
+        tx.execute("DELETE FROM search_results", [])?;
+
+        for metadata in metadata_list {
+            insert_metadata(&tx, metadata)?;
+        }
+        insert_groups(&tx, groups)?;
+        for result in results {
+            insert_result(&tx, result)?;
+        }
+
+        tx.commit().context("無法提交匯入交易")?;
+        Ok(())
+    }
+}
+
+impl MetadataStore for SqliteStore {
+    fn append_metadata(&self, metadata: &ImageMetadata) -> Result<()> {
+        insert_metadata(&self.lock(), metadata)
+    }
+
+    fn load_all_metadata(&self) -> Result<Vec<ImageMetadata>> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare("SELECT data FROM images ORDER BY id")?;
+
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut list = Vec::new();
+        for row in rows {
+            list.push(serde_json::from_str(&row?).context("解析 metadata 失敗")?);
+        }
+
+        Ok(list)
+    }
+
+    fn rewrite_metadata(&self, metadata_list: &[ImageMetadata]) -> Result<()> {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM images", [])?;
+        for metadata in metadata_list {
+            insert_metadata(&tx, metadata)?;
+        }
+
+        tx.commit().context("無法提交 metadata 更新")?;
+        Ok(())
+    }
+
+    fn save_duplicate_groups(&self, groups: &[DuplicateRecord]) -> Result<()> {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM duplicate_groups", [])?;
+        insert_groups(&tx, groups)?;
+
+        tx.commit().context("無法提交重複組")?;
+        Ok(())
+    }
+
+    fn append_search_result(&self, result: &ReverseSearchResult) -> Result<()> {
+        insert_result(&self.lock(), result)
+    }
+}
+
+fn insert_metadata(conn: &Connection, metadata: &ImageMetadata) -> Result<()> {
+    conn.execute(
+        "INSERT INTO images (filename, content_hash, url, page_number, downloaded_at, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            metadata.filename,
+            metadata.content_hash,
+            metadata.url,
+            metadata.page_number,
+            metadata.downloaded_at.to_rfc3339(),
+            serde_json::to_string(metadata)?,
+        ],
+    )
+    .context("無法寫入 metadata")?;
+
+    Ok(())
+}
+
+fn insert_groups(conn: &Connection, groups: &[DuplicateRecord]) -> Result<()> {
+    for group in groups {
+        for (position, filename) in group.files.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO duplicate_groups (content_hash, position, filename) VALUES (?1, ?2, ?3)",
+                params![group.content_hash, position as i64, filename],
+            )
+            .context("無法寫入重複組")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn insert_result(conn: &Connection, result: &ReverseSearchResult) -> Result<()> {
+    conn.execute(
+        "INSERT INTO search_results (filename, service, best_guess, searched_at, data)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            result.filename,
+            result.service,
+            result.best_guess,
+            result.searched_at.to_rfc3339(),
+            serde_json::to_string(result)?,
+        ],
+    )
+    .context("無法寫入搜尋結果")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(filename: &str, hash: &str) -> ImageMetadata {
+        ImageMetadata {
+            description: "測試".to_string(),
+            ..ImageMetadata::for_test(filename, hash)
+        }
+    }
+
+    #[test]
+    fn test_sqlite_store_roundtrip() {
+        let store = SqliteStore::open(":memory:").unwrap();
+
+        store.append_metadata(&metadata("a.jpg", "h1")).unwrap();
+        store.append_metadata(&metadata("b.jpg", "h1")).unwrap();
+        store.append_metadata(&metadata("c.jpg", "h2")).unwrap();
+
+        let all = store.load_all_metadata().unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all.iter().filter(|m| m.content_hash == "h1").count(), 2);
+
+        store.rewrite_metadata(&[metadata("a.jpg", "h1")]).unwrap();
+        let all = store.load_all_metadata().unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].filename, "a.jpg");
+    }
+}
@@ -0,0 +1,152 @@
+use crate::reverse_search::ReverseSearchResult;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// 標籤索引（tag -> 檔名集合）
+///
+/// 標籤來自反向搜尋的關鍵字，經過正規化後合併，同一張圖片在多個服務
+/// 找到的相同關鍵字只算一次。
+#[derive(Debug, Default)]
+pub struct TagIndex {
+    tags: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl TagIndex {
+    /// 從搜尋結果建立索引
+    pub fn build(results: &[ReverseSearchResult]) -> Self {
+        let mut index = Self::default();
+
+        for result in results {
+            for keyword in &result.keywords {
+                if let Some(tag) = canonicalize_tag(keyword) {
+                    index.insert(tag, &result.filename);
+                }
+            }
+        }
+
+        index
+    }
+
+    /// 加入一筆 (標籤, 檔名)
+    pub fn insert(&mut self, tag: String, filename: &str) {
+        self.tags
+            .entry(tag)
+            .or_default()
+            .insert(filename.to_string());
+    }
+
+    /// 標籤總數
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// 各標籤的圖片數（依數量遞減，同數量依字母排序）
+    pub fn counts(&self) -> Vec<(&str, usize)> {
+        let mut counts: Vec<(&str, usize)> = self.tags
+            .iter()
+            .map(|(tag, files)| (tag.as_str(), files.len()))
+            .collect();
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+    }
+
+    /// 取得某個標籤的所有檔名
+    pub fn files_for(&self, tag: &str) -> Option<&BTreeSet<String>> {
+        let tag = canonicalize_tag(tag)?;
+        self.tags.get(&tag)
+    }
+
+    /// 最常一起出現的標籤組合（前 `top` 組）
+    pub fn cooccurrence(&self, top: usize) -> Vec<((String, String), usize)> {
+        // 先反轉成 檔名 -> 標籤集合
+        let mut by_file: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (tag, files) in &self.tags {
+            for file in files {
+                by_file.entry(file.as_str()).or_default().push(tag.as_str());
+            }
+        }
+
+        let mut pairs: HashMap<(String, String), usize> = HashMap::new();
+        for tags in by_file.values() {
+            // BTreeMap 迭代有序，所以 tags 已排序，(a, b) 保證 a < b
+            for (i, a) in tags.iter().enumerate() {
+                for b in &tags[i + 1..] {
+                    *pairs.entry((a.to_string(), b.to_string())).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut pairs: Vec<_> = pairs.into_iter().collect();
+        pairs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        pairs.truncate(top);
+        pairs
+    }
+}
+
+/// 正規化標籤：去頭尾空白、轉小寫、合併連續空白
+///
+/// 純數字以及 TinEye 的 "N matches" 統計字串不算標籤。
+pub fn canonicalize_tag(raw: &str) -> Option<String> {
+    let tag = raw
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+
+    if tag.is_empty() {
+        return None;
+    }
+
+    let first_word = tag.split(' ').next().unwrap_or("");
+    if first_word.chars().all(|c| c.is_ascii_digit()) {
+        let rest = tag[first_word.len()..].trim();
+        if rest.is_empty() || rest == "matches" {
+            return None;
+        }
+    }
+
+    Some(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn result(filename: &str, keywords: &[&str]) -> ReverseSearchResult {
+        ReverseSearchResult {
+            filename: filename.to_string(),
+            service: "test".to_string(),
+            suggested_title: None,
+            keywords: keywords.iter().map(|s| s.to_string()).collect(),
+            related_sites: vec![],
+            best_guess: None,
+            similarity: None,
+            searched_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_tag_counts_and_cooccurrence() {
+        let results = vec![
+            result("a.jpg", &["Doge", "shiba  inu", "12 matches"]),
+            result("a.jpg", &["doge"]),
+            result("b.jpg", &["doge", "Shiba Inu"]),
+            result("c.jpg", &["pepe"]),
+        ];
+
+        let index = TagIndex::build(&results);
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.counts()[0], ("doge", 2));
+        assert_eq!(index.files_for("DOGE").unwrap().len(), 2);
+
+        let pairs = index.cooccurrence(10);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0], (("doge".to_string(), "shiba inu".to_string()), 2));
+    }
+}
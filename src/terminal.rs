@@ -0,0 +1,104 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// 依 `--plain` 與終端機能力設定輸出模式（程式開始時呼叫一次）
+///
+/// Windows 10 以後的主控台要開啟虛擬終端處理才會解讀進度列的 ANSI 控制碼；
+/// 舊版主控台（conhost，非 Windows Terminal）無法顯示 emoji 與框線字元，自動改用純 ASCII。
+pub fn init(plain: bool) {
+    let stdout = console::Term::stdout();
+    // 在 Windows 上檢查時會順便開啟 ENABLE_VIRTUAL_TERMINAL_PROCESSING
+    stdout.features().colors_supported();
+    console::Term::stderr().features().colors_supported();
+
+    let legacy_console = cfg!(windows) && stdout.is_term() && !stdout.features().wants_emoji();
+    set_plain(plain || legacy_console);
+}
+
+pub fn set_plain(plain: bool) {
+    PLAIN.store(plain, Ordering::Relaxed);
+    if plain {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+}
+
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// 輸出前的轉換：plain 模式時把 emoji、框線與符號換成 ASCII（中文等文字不變）
+pub fn render(text: &str) -> Cow<'_, str> {
+    if is_plain() && !text.is_ascii() {
+        Cow::Owned(to_ascii(text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// 進度列訊息用的 `render`
+pub fn message(text: impl Into<Cow<'static, str>>) -> Cow<'static, str> {
+    let text = text.into();
+    match render(&text) {
+        Cow::Owned(plain) => Cow::Owned(plain),
+        Cow::Borrowed(_) => text,
+    }
+}
+
+/// 把 emoji 與框線字元換成 ASCII
+///
+/// 狀態符號換成標記（`✅` -> `[OK]`、`⚠️` -> `[!]`、`❌` -> `[X]`、`💡` -> `[i]`），
+/// 其他裝飾用的 emoji 換成 `*`；框線字元一對一替換，統計表格仍然對齊。
+pub fn to_ascii(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            // 變體選擇符與零寬連接符（組合 emoji 用）
+            '\u{FE0E}' | '\u{FE0F}' | '\u{200D}' => {}
+            '✅' | '✓' | '✔' | '🎉' | '✨' => out.push_str("[OK]"),
+            '⚠' | '🚨' | '🚧' => out.push_str("[!]"),
+            '❌' | '✗' | '⛔' | '🚫' => out.push_str("[X]"),
+            '💡' | 'ℹ' => out.push_str("[i]"),
+            '❓' | '🤷' => out.push_str("[?]"),
+            '═' | '━' | '─' | '—' => out.push('='),
+            '║' | '┃' | '│' => out.push('|'),
+            // 其餘框線（角落、交叉）
+            '\u{2500}'..='\u{257F}' => out.push('+'),
+            '→' => out.push_str("->"),
+            '←' => out.push_str("<-"),
+            '≤' => out.push_str("<="),
+            '≥' => out.push_str(">="),
+            '×' => out.push('x'),
+            '·' | '•' => out.push('-'),
+            '…' => out.push_str("..."),
+            c if is_pictograph(c) => out.push('*'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// emoji 與各種圖形符號
+fn is_pictograph(c: char) -> bool {
+    matches!(c as u32, 0x2190..=0x21FF | 0x2300..=0x23FF | 0x2460..=0x27BF | 0x2900..=0x2BFF | 0x1F000..=0x1FAFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_ascii() {
+        assert_eq!(to_ascii("⚠️  無法讀取"), "[!]  無法讀取");
+        assert_eq!(to_ascii("✅ 完成 → 下一步"), "[OK] 完成 -> 下一步");
+        assert_eq!(to_ascii("📥 第 1 頁"), "* 第 1 頁");
+        assert_eq!(to_ascii("640×480 · 1.0 KB"), "640x480 - 1.0 KB");
+
+        let row = "║ 圖片數: {:>18} ║";
+        assert_eq!(to_ascii(row).chars().count(), row.chars().count());
+        assert_eq!(to_ascii("╔══╗"), "+==+");
+        assert!(to_ascii("🖼️ 🗑️ 🧊 ⏸️").is_ascii());
+    }
+}
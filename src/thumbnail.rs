@@ -0,0 +1,252 @@
+use crate::context::DataContext;
+use crate::file_manager::FileManager;
+use crate::media;
+use crate::terminal;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use image::{DynamicImage, ImageFormat};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 縮圖目錄（`thumbnails/<檔名>.webp`，HTML 報告、預覽圖與 web UI 共用）
+pub const THUMBNAIL_DIR: &str = "thumbnails";
+
+/// 縮圖長邊的預設像素
+pub const DEFAULT_SIZE: u32 = 256;
+
+/// 等比例縮到長邊 `size`（小圖不放大），編碼成 WebP（無損）
+pub fn encode(image: &DynamicImage, size: u32) -> Result<Vec<u8>> {
+    let size = size.max(1);
+    let thumb = if image.width().max(image.height()) > size {
+        image.thumbnail(size, size)
+    } else {
+        image.clone()
+    };
+    // WebP 編碼器只接受 8 位元的 RGB/RGBA
+    let thumb = if thumb.color().has_alpha() {
+        DynamicImage::ImageRgba8(thumb.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(thumb.to_rgb8())
+    };
+
+    let mut bytes = Vec::new();
+    thumb
+        .write_to(&mut std::io::Cursor::new(&mut bytes), ImageFormat::WebP)
+        .context("無法編碼縮圖")?;
+    Ok(bytes)
+}
+
+/// 由原圖產生縮圖（動圖與影片用代表畫面，原圖在冷儲存時先取回）
+pub fn generate(context: &DataContext, filename: &str, size: u32) -> Result<()> {
+    let image = media::representative_frame(context.original_path(filename)?)?;
+    context.file_manager().save_thumbnail(filename, &encode(&image, size)?)
+}
+
+/// 縮圖是否已是最新：比原圖新，且長邊符合 `size`（原圖比較小時與原圖相同）
+///
+/// 原圖已移到冷儲存時，只要有縮圖就算最新。
+pub fn is_fresh(file_manager: &FileManager, filename: &str, size: u32) -> bool {
+    let thumb_path = file_manager.get_thumbnail_path(filename);
+    let image_path = file_manager.get_image_path(filename);
+    let modified = |path: &str| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    let Some(thumb_modified) = modified(&thumb_path) else {
+        return false;
+    };
+    let Some(image_modified) = modified(&image_path) else {
+        return true;
+    };
+    if thumb_modified < image_modified {
+        return false;
+    }
+
+    let long_edge = |path: &str| image::image_dimensions(path).ok().map(|(w, h)| w.max(h));
+    match (long_edge(&thumb_path), long_edge(&image_path)) {
+        (Some(thumb), Some(image)) => thumb == image.min(size.max(1)),
+        _ => false,
+    }
+}
+
+/// 舊版（冷儲存移走原圖時產生）的 `thumbnails/<檔名>.jpg`
+fn legacy_path(file_manager: &FileManager, filename: &str) -> PathBuf {
+    Path::new(file_manager.root_dir()).join(THUMBNAIL_DIR).join(format!("{}.jpg", filename))
+}
+
+/// 已有的縮圖路徑（沒有時為 None）
+pub fn find(file_manager: &FileManager, filename: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(file_manager.get_thumbnail_path(filename));
+    if path.exists() {
+        return Some(path);
+    }
+    let legacy = legacy_path(file_manager, filename);
+    legacy.exists().then_some(legacy)
+}
+
+/// 相對於資料目錄、可放進 HTML 的縮圖網址（沒有縮圖時為 None）
+pub fn relative_url(file_manager: &FileManager, filename: &str) -> Option<String> {
+    let extension = find(file_manager, filename)?.extension()?.to_string_lossy().to_string();
+    Some(format!("{}/{}.{}", THUMBNAIL_DIR, urlencoding::encode(filename), extension))
+}
+
+/// 讀取縮圖（長邊小於 `min_size` 時回傳 None，由呼叫端改讀原圖）
+pub fn load(file_manager: &FileManager, filename: &str, min_size: u32) -> Option<DynamicImage> {
+    let image = image::open(find(file_manager, filename)?).ok()?;
+    (image.width().max(image.height()) >= min_size).then_some(image)
+}
+
+/// `generate_all` 的結果
+#[derive(Debug, Default)]
+pub struct ThumbnailReport {
+    pub generated: usize,
+    /// 已有最新縮圖而略過的數量
+    pub skipped: usize,
+    /// 原圖不在本地也不在冷儲存（也沒有縮圖）的數量
+    pub missing: usize,
+    /// 無法解碼或寫入的檔案
+    pub failed: Vec<(String, String)>,
+}
+
+/// 以 `workers` 個執行緒產生縮圖（`force` 為 false 時略過已是最新的，中斷後重跑會接著做）
+pub fn generate_all(
+    context: &DataContext,
+    metadata: &[ImageMetadata],
+    size: u32,
+    force: bool,
+    workers: usize,
+) -> Result<ThumbnailReport> {
+    let file_manager = context.file_manager();
+    let cold = context.cold_storage()?;
+    let mut report = ThumbnailReport::default();
+    let mut seen = HashSet::new();
+    let mut pending: Vec<&str> = Vec::new();
+    for m in metadata {
+        if !seen.insert(m.filename.as_str()) {
+            continue;
+        }
+        if !force && is_fresh(file_manager, &m.filename, size) {
+            report.skipped += 1;
+        } else if !Path::new(&file_manager.get_image_path(&m.filename)).exists() && !cold.is_cold(&m.filename) {
+            report.missing += 1;
+        } else {
+            pending.push(&m.filename);
+        }
+    }
+
+    let pb = ProgressBar::new(pending.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} 張 ({percent}%) {eta} {msg}")
+            .unwrap()
+            .progress_chars("=>-")
+    );
+
+    let next = AtomicUsize::new(0);
+    let generated = AtomicUsize::new(0);
+    let failed = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..workers.max(1).min(pending.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(&filename) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    match generate(context, filename, size) {
+                        Ok(()) => {
+                            generated.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => failed.lock().unwrap().push((filename.to_string(), e.to_string())),
+                    }
+                    pb.set_message(terminal::message(filename.to_string()));
+                    pb.inc(1);
+                }
+            });
+        }
+    });
+    pb.finish_and_clear();
+
+    report.generated = generated.into_inner();
+    report.failed = failed.into_inner().unwrap();
+    report.failed.sort();
+    Ok(report)
+}
+
+/// 對應的圖片已不在 metadata 的縮圖
+pub fn orphans(file_manager: &FileManager, metadata: &[ImageMetadata]) -> Result<Vec<PathBuf>> {
+    let dir = Path::new(file_manager.root_dir()).join(THUMBNAIL_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("無法讀取 {}", dir.display())),
+    };
+
+    let known: HashSet<&str> = metadata.iter().map(|m| m.filename.as_str()).collect();
+    let mut orphans = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let Some(filename) = name.strip_suffix(".webp").or_else(|| name.strip_suffix(".jpg")) else {
+            continue;
+        };
+        if !known.contains(filename) {
+            orphans.push(path);
+        }
+    }
+    orphans.sort();
+    Ok(orphans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MetadataBackend;
+    use serde_json::json;
+
+    #[test]
+    fn test_generate_all() {
+        let dir = std::env::temp_dir().join(format!("meme-thumbnail-{}", std::process::id()));
+        let context = DataContext::open(dir.to_str().unwrap(), MetadataBackend::Jsonl).unwrap();
+        let file_manager = context.file_manager();
+        let png = |width, height| {
+            let mut bytes = Vec::new();
+            DynamicImage::new_rgb8(width, height)
+                .write_to(&mut std::io::Cursor::new(&mut bytes), ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        file_manager.save_image("wide.png", &png(600, 300)).unwrap();
+        file_manager.save_image("small.png", &png(50, 20)).unwrap();
+        file_manager.save_image("broken.png", b"not an image").unwrap();
+
+        let metadata: Vec<ImageMetadata> = ["wide.png", "small.png", "broken.png", "cold.png"]
+            .iter()
+            .map(|filename| serde_json::from_value(json!({
+                "filename": filename, "description": "", "url": "", "content_hash": filename,
+                "page_number": 1, "downloaded_at": "2024-01-01T00:00:00Z",
+            })).unwrap())
+            .collect();
+
+        let report = generate_all(&context, &metadata, DEFAULT_SIZE, false, 4).unwrap();
+        assert_eq!((report.generated, report.skipped, report.missing), (2, 0, 1));
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(image::image_dimensions(file_manager.get_thumbnail_path("wide.png")).unwrap(), (256, 128));
+        assert_eq!(image::image_dimensions(file_manager.get_thumbnail_path("small.png")).unwrap(), (50, 20));
+        assert_eq!(relative_url(file_manager, "wide.png").unwrap(), "thumbnails/wide.png.webp");
+        assert!(load(file_manager, "wide.png", 128).is_some());
+        assert!(load(file_manager, "wide.png", 512).is_none());
+
+        // 重跑時略過已完成的；尺寸改變時重新產生
+        let report = generate_all(&context, &metadata, DEFAULT_SIZE, false, 4).unwrap();
+        assert_eq!((report.generated, report.skipped), (0, 2));
+        let report = generate_all(&context, &metadata, 128, false, 1).unwrap();
+        assert_eq!((report.generated, report.skipped), (1, 1));
+
+        // 舊版的 .jpg 縮圖仍找得到；不在 metadata 的縮圖是孤兒
+        fs::write(legacy_path(file_manager, "cold.png"), b"jpg").unwrap();
+        assert_eq!(relative_url(file_manager, "cold.png").unwrap(), "thumbnails/cold.png.jpg");
+        assert!(orphans(file_manager, &metadata).unwrap().is_empty());
+        assert_eq!(orphans(file_manager, &metadata[1..]).unwrap(), vec![PathBuf::from(file_manager.get_thumbnail_path("wide.png"))]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,421 @@
+use crate::file_manager::FileManager;
+use crate::integrity;
+use crate::thumbnail;
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 冷儲存設定
+pub const TIER_CONFIG_FILE: &str = "tier.json";
+
+/// 已移到冷儲存的原圖（檔名 -> 位置）
+pub const COLD_INDEX_FILE: &str = "cold_index.json";
+
+/// 分層設定（tier.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TierConfig {
+    /// 冷儲存目錄（相對路徑以資料目錄為準；S3 Glacier 等可用 rclone/s3fs 掛載成目錄）
+    pub cold_dir: String,
+    /// 下載超過幾天的原圖移到冷儲存
+    pub older_than_days: u64,
+    /// 留在本地的縮圖長邊像素
+    pub thumbnail_size: u32,
+}
+
+impl Default for TierConfig {
+    fn default() -> Self {
+        Self {
+            cold_dir: "cold".to_string(),
+            older_than_days: 90,
+            thumbnail_size: 256,
+        }
+    }
+}
+
+impl TierConfig {
+    pub fn load(data_dir: &str) -> Result<Self> {
+        let path = Path::new(data_dir).join(TIER_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(&fs::read_to_string(&path)?).context("無法解析 tier.json")
+    }
+
+    pub fn save(&self, data_dir: &str) -> Result<()> {
+        fs::create_dir_all(data_dir)?;
+        let path = Path::new(data_dir).join(TIER_CONFIG_FILE);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp_path, &path).context("無法儲存 tier.json")?;
+        Ok(())
+    }
+
+    /// 下載時間早於這個時間的原圖要移走
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::days(self.older_than_days as i64)
+    }
+
+    /// 冷儲存目錄的實際路徑
+    pub fn cold_path(&self, data_dir: &str) -> PathBuf {
+        let path = Path::new(&self.cold_dir);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            Path::new(data_dir).join(path)
+        }
+    }
+}
+
+/// 冷儲存後端：以內容雜湊為 key 存放原圖
+pub trait BlobStore: Send + Sync {
+    /// 顯示用的位置
+    fn location(&self) -> String;
+
+    /// 上傳檔案
+    fn put(&self, key: &str, source: &Path) -> Result<()>;
+
+    /// 下載到指定路徑
+    fn get(&self, key: &str, dest: &Path) -> Result<()>;
+
+    fn contains(&self, key: &str) -> bool;
+}
+
+/// 以目錄作為冷儲存（`<root>/<key 前兩碼>/<key>`）
+///
+/// 物件儲存掛載成目錄後即可使用，不需要額外的 SDK。
+pub struct DirBlobStore {
+    root: PathBuf,
+}
+
+impl DirBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn blob_path(&self, key: &str) -> PathBuf {
+        let prefix = key.get(..2).unwrap_or("__");
+        self.root.join(prefix).join(key)
+    }
+}
+
+impl BlobStore for DirBlobStore {
+    fn location(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    fn put(&self, key: &str, source: &Path) -> Result<()> {
+        let path = self.blob_path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("無法建立 {}", parent.display()))?;
+        }
+        let temp_path = path.with_extension("tmp");
+        fs::copy(source, &temp_path).with_context(|| format!("無法複製到冷儲存: {}", source.display()))?;
+        fs::rename(&temp_path, &path).context("無法寫入冷儲存")?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str, dest: &Path) -> Result<()> {
+        fs::copy(self.blob_path(key), dest).with_context(|| format!("無法從冷儲存取回 {}", key))?;
+        Ok(())
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.blob_path(key).exists()
+    }
+}
+
+/// 移到冷儲存的一張原圖
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColdEntry {
+    /// 冷儲存的 key（內容雜湊）
+    pub key: String,
+    pub size: u64,
+    pub offloaded_at: DateTime<Utc>,
+}
+
+/// 檔名 -> 冷儲存位置（`cold_index.json`）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColdIndex {
+    pub entries: BTreeMap<String, ColdEntry>,
+}
+
+/// 移到冷儲存的理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TierReason {
+    /// 下載超過設定天數
+    Age,
+    /// 已匯出到資料集
+    Exported,
+}
+
+impl TierReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Age => "過期",
+            Self::Exported => "已匯出",
+        }
+    }
+}
+
+/// 要移到冷儲存的原圖
+#[derive(Debug, Clone)]
+pub struct TierItem {
+    pub filename: String,
+    pub content_hash: String,
+    pub size: u64,
+    pub reason: TierReason,
+}
+
+/// 讀取匯出目錄的 attribution.jsonl，回傳其中的檔名
+pub fn load_exported(export_dir: &str) -> Result<HashSet<String>> {
+    #[derive(Deserialize)]
+    struct Exported {
+        filename: String,
+    }
+
+    let path = Path::new(export_dir).join("attribution.jsonl");
+    let content = fs::read_to_string(&path).with_context(|| format!("無法讀取 {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Exported>(line).ok())
+        .map(|record| record.filename)
+        .collect())
+}
+
+/// 原圖分層：舊的或已匯出的原圖移到冷儲存，本地只留縮圖與 metadata
+///
+/// 需要原圖的服務（例如上傳搜尋）呼叫 `ensure_local`，原圖在冷儲存時自動取回。
+pub struct ColdStorage {
+    data_dir: String,
+    config: TierConfig,
+    store: Box<dyn BlobStore>,
+    index: Mutex<ColdIndex>,
+}
+
+impl ColdStorage {
+    /// 依 tier.json 開啟冷儲存（預設為資料目錄下的 `cold/`）
+    pub fn open(data_dir: &str) -> Result<Self> {
+        let config = TierConfig::load(data_dir)?;
+        let store = Box::new(DirBlobStore::new(config.cold_path(data_dir)));
+        Self::with_store(data_dir, config, store)
+    }
+
+    pub fn with_store(data_dir: &str, config: TierConfig, store: Box<dyn BlobStore>) -> Result<Self> {
+        let path = Path::new(data_dir).join(COLD_INDEX_FILE);
+        let index = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?).context("無法解析 cold_index.json")?
+        } else {
+            ColdIndex::default()
+        };
+
+        Ok(Self {
+            data_dir: data_dir.to_string(),
+            config,
+            store,
+            index: Mutex::new(index),
+        })
+    }
+
+    pub fn config(&self) -> &TierConfig {
+        &self.config
+    }
+
+    pub fn store(&self) -> &dyn BlobStore {
+        self.store.as_ref()
+    }
+
+    /// 目前在冷儲存的 (張數, bytes)
+    pub fn cold_usage(&self) -> (usize, u64) {
+        let index = self.index.lock().unwrap();
+        (index.entries.len(), index.entries.values().map(|entry| entry.size).sum())
+    }
+
+    pub fn is_cold(&self, filename: &str) -> bool {
+        self.index.lock().unwrap().entries.contains_key(filename)
+    }
+
+    fn save_index(&self, index: &ColdIndex) -> Result<()> {
+        let path = Path::new(&self.data_dir).join(COLD_INDEX_FILE);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string(index)?)?;
+        fs::rename(&temp_path, &path).context("無法寫入 cold_index.json")?;
+        Ok(())
+    }
+
+    /// 找出要移到冷儲存的原圖（不移動）：在 `cutoff` 之前下載或列在 `exported` 中，且本地檔案還在
+    pub fn plan(
+        &self,
+        file_manager: &FileManager,
+        metadata: &[ImageMetadata],
+        exported: &HashSet<String>,
+        cutoff: DateTime<Utc>,
+    ) -> Vec<TierItem> {
+        let index = self.index.lock().unwrap();
+        let mut seen = HashSet::new();
+        let mut plan = Vec::new();
+
+        for meta in metadata {
+            if meta.content_hash.is_empty() || index.entries.contains_key(&meta.filename) {
+                continue;
+            }
+            if !seen.insert(meta.filename.as_str()) {
+                continue;
+            }
+
+            let reason = if exported.contains(&meta.filename) {
+                TierReason::Exported
+            } else if meta.downloaded_at < cutoff {
+                TierReason::Age
+            } else {
+                continue;
+            };
+            let Ok(info) = fs::metadata(file_manager.get_image_path(&meta.filename)) else {
+                continue;
+            };
+
+            plan.push(TierItem {
+                filename: meta.filename.clone(),
+                content_hash: meta.content_hash.clone(),
+                size: info.len(),
+                reason,
+            });
+        }
+
+        plan
+    }
+
+    /// 建立縮圖、上傳原圖並確認冷儲存已有檔案後才刪除本地原圖
+    ///
+    /// 無法解碼的檔案（例如影片）沒有縮圖，仍然移到冷儲存。
+    pub fn offload(&self, file_manager: &FileManager, item: &TierItem) -> Result<()> {
+        let path = file_manager.get_image_path(&item.filename);
+        let bytes = fs::read(&path).with_context(|| format!("無法讀取圖片: {}", item.filename))?;
+        if integrity::sha256_hex(&bytes) != item.content_hash {
+            anyhow::bail!("{} 的內容與 metadata 的 hash 不符，不移動", item.filename);
+        }
+
+        if let Ok(image) = image::load_from_memory(&bytes) {
+            let thumb = thumbnail::encode(&image, self.config.thumbnail_size)?;
+            file_manager
+                .save_thumbnail(&item.filename, &thumb)
+                .with_context(|| format!("無法寫入縮圖: {}", item.filename))?;
+        }
+
+        self.store.put(&item.content_hash, Path::new(&path))?;
+        if !self.store.contains(&item.content_hash) {
+            anyhow::bail!("冷儲存找不到剛上傳的 {}", item.filename);
+        }
+
+        let mut index = self.index.lock().unwrap();
+        index.entries.insert(item.filename.clone(), ColdEntry {
+            key: item.content_hash.clone(),
+            size: item.size,
+            offloaded_at: Utc::now(),
+        });
+        self.save_index(&index)?;
+        fs::remove_file(&path).with_context(|| format!("無法刪除本地原圖: {}", item.filename))?;
+        Ok(())
+    }
+
+    /// 確保原圖在本地，在冷儲存時取回並驗證 hash，回傳是否有取回
+    ///
+    /// 不在冷儲存也不在本地時回傳 false，由呼叫端決定如何處理。
+    pub fn ensure_local(&self, file_manager: &FileManager, filename: &str) -> Result<bool> {
+        let path = file_manager.get_image_path(filename);
+        if Path::new(&path).exists() {
+            return Ok(false);
+        }
+
+        let mut index = self.index.lock().unwrap();
+        let Some(entry) = index.entries.get(filename).cloned() else {
+            return Ok(false);
+        };
+
+        let part_path = file_manager.part_path(filename);
+        self.store.get(&entry.key, Path::new(&part_path))?;
+        let bytes = fs::read(&part_path)?;
+        if integrity::sha256_hex(&bytes) != entry.key {
+            fs::remove_file(&part_path).ok();
+            anyhow::bail!("從冷儲存取回的 {} 內容不符", filename);
+        }
+        file_manager.persist_image(&part_path, filename)?;
+
+        index.entries.remove(filename);
+        self.save_index(&index)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_offload_and_retrieve() {
+        let dir = std::env::temp_dir().join(format!("meme-tier-{}", std::process::id()));
+        fs::create_dir_all(dir.join("images")).unwrap();
+        let data_dir = dir.to_str().unwrap();
+        let file_manager = FileManager::new(data_dir).unwrap();
+
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(600, 300)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let meta = |filename: &str, content_hash: &str, downloaded_at: &str| -> ImageMetadata {
+            serde_json::from_value(json!({
+                "filename": filename,
+                "description": "",
+                "url": "",
+                "content_hash": content_hash,
+                "page_number": 1,
+                "downloaded_at": downloaded_at,
+            }))
+            .unwrap()
+        };
+        let hash = integrity::sha256_hex(&png);
+        fs::write(dir.join("images/old.png"), &png).unwrap();
+        fs::write(dir.join("images/new.png"), &png).unwrap();
+        fs::write(dir.join("images/shared.png"), &png).unwrap();
+        let metadata = vec![
+            meta("old.png", &hash, "2020-01-01T00:00:00Z"),
+            meta("new.png", &hash, "2024-06-01T00:00:00Z"),
+            meta("shared.png", &hash, "2024-06-01T00:00:00Z"),
+        ];
+
+        let storage = ColdStorage::open(data_dir).unwrap();
+        let cutoff = storage.config().cutoff("2024-06-02T00:00:00Z".parse().unwrap());
+        let exported = HashSet::from(["shared.png".to_string()]);
+        let plan = storage.plan(&file_manager, &metadata, &exported, cutoff);
+        let planned: Vec<_> = plan.iter().map(|item| (item.filename.as_str(), item.reason)).collect();
+        assert_eq!(planned, vec![("old.png", TierReason::Age), ("shared.png", TierReason::Exported)]);
+
+        for item in &plan {
+            storage.offload(&file_manager, item).unwrap();
+        }
+        assert!(!dir.join("images/old.png").exists());
+        assert!(dir.join("images/new.png").exists());
+        assert!(Path::new(&file_manager.get_thumbnail_path("old.png")).exists());
+        assert_eq!(storage.cold_usage().0, 2);
+
+        // 重新開啟後索引還在，已移走的不再列入
+        let storage = ColdStorage::open(data_dir).unwrap();
+        assert!(storage.is_cold("old.png"));
+        assert!(storage.plan(&file_manager, &metadata, &exported, cutoff).is_empty());
+
+        assert!(storage.ensure_local(&file_manager, "old.png").unwrap());
+        assert_eq!(fs::read(dir.join("images/old.png")).unwrap(), png);
+        assert!(!storage.is_cold("old.png"));
+        assert!(!storage.ensure_local(&file_manager, "old.png").unwrap());
+        assert!(!storage.ensure_local(&file_manager, "missing.png").unwrap());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,275 @@
+use crate::context::{files, DataContext};
+use crate::reverse_search::{self, ReverseSearchResult};
+use crate::types::ImageMetadata;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 資源回收桶目錄（每次移除一個 `<時間>/` 子目錄）
+pub const TRASH_DIR: &str = "trash";
+
+/// 批次的內容紀錄
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// 移到回收桶的一張圖片與還原所需的資料
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub filename: String,
+    /// 去重時保留的檔案（搜尋結果曾改指向它）
+    pub kept: Option<String>,
+    pub metadata: Option<ImageMetadata>,
+    /// 移除前屬於這個檔案的搜尋結果
+    #[serde(default)]
+    pub results: Vec<ReverseSearchResult>,
+}
+
+/// 一次移除的紀錄（`trash/<時間>/manifest.json`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashManifest {
+    pub created_at: DateTime<Utc>,
+    /// 移除的命令（例如 dedup）
+    pub command: String,
+    pub entries: Vec<TrashEntry>,
+}
+
+/// 進行中的一次移除：圖片先移進批次目錄，metadata 更新後寫入 manifest
+pub struct TrashBatch {
+    id: String,
+    dir: PathBuf,
+    manifest: TrashManifest,
+}
+
+impl TrashBatch {
+    /// 在 `trash/` 下建立以時間命名的批次目錄
+    pub fn create(data_dir: &str, command: &str) -> Result<Self> {
+        let created_at = Utc::now();
+        let base = created_at.format("%Y%m%d%H%M%S").to_string();
+        let root = Path::new(data_dir).join(TRASH_DIR);
+
+        let mut id = base.clone();
+        let mut suffix = 1;
+        while root.join(&id).exists() {
+            suffix += 1;
+            id = format!("{}-{}", base, suffix);
+        }
+        let dir = root.join(&id);
+        fs::create_dir_all(&dir).with_context(|| format!("無法建立 {}", dir.display()))?;
+
+        Ok(Self {
+            id,
+            dir,
+            manifest: TrashManifest {
+                created_at,
+                command: command.to_string(),
+                entries: Vec::new(),
+            },
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 把圖片移進批次目錄
+    pub fn move_in(&self, image_path: &str, filename: &str) -> Result<()> {
+        fs::rename(image_path, self.dir.join(filename))
+            .with_context(|| format!("無法移到回收桶: {}", filename))
+    }
+
+    pub fn push(&mut self, entry: TrashEntry) {
+        self.manifest.entries.push(entry);
+    }
+
+    /// 沒有移入任何圖片時刪除空的批次目錄
+    pub fn discard(self) -> Result<()> {
+        fs::remove_dir(&self.dir).with_context(|| format!("無法刪除 {}", self.dir.display()))
+    }
+
+    /// 寫入 manifest（先寫暫存檔再改名）
+    pub fn save(&self) -> Result<()> {
+        let path = self.dir.join(MANIFEST_FILE);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&self.manifest)?)?;
+        fs::rename(&temp_path, &path).context("無法寫入回收桶 manifest.json")?;
+        Ok(())
+    }
+}
+
+/// 列出回收桶中的批次（舊的在前）
+pub fn list(data_dir: &str) -> Result<Vec<(String, TrashManifest)>> {
+    let root = Path::new(data_dir).join(TRASH_DIR);
+    let entries = match fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("無法讀取 {}", root.display())),
+    };
+
+    let mut batches = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path().join(MANIFEST_FILE);
+        if !path.exists() {
+            continue;
+        }
+        let manifest: TrashManifest = serde_json::from_str(&fs::read_to_string(&path)?)
+            .with_context(|| format!("無法解析 {}", path.display()))?;
+        batches.push((entry.file_name().to_string_lossy().to_string(), manifest));
+    }
+
+    batches.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(batches)
+}
+
+/// 還原的結果
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RestoreSummary {
+    pub files: usize,
+    pub metadata: usize,
+    pub results: usize,
+    /// 目標檔名已存在而未還原的檔案
+    pub skipped: Vec<String>,
+}
+
+/// 還原一個批次：圖片移回 images/、metadata 加回、搜尋結果改回原本的檔案
+///
+/// 全部圖片都還原後刪除批次目錄；有略過的檔案時保留，處理後可再執行一次。
+pub fn restore(context: &DataContext, id: &str) -> Result<RestoreSummary> {
+    let dir = Path::new(context.root()).join(TRASH_DIR).join(id);
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let manifest: TrashManifest = serde_json::from_str(
+        &fs::read_to_string(&manifest_path).with_context(|| format!("找不到回收桶批次: {}", id))?,
+    )
+    .with_context(|| format!("無法解析 {}", manifest_path.display()))?;
+
+    let mut summary = RestoreSummary::default();
+    let mut restored = Vec::new();
+    for entry in &manifest.entries {
+        let from = dir.join(&entry.filename);
+        let to = context.file_manager().get_image_path(&entry.filename);
+        if Path::new(&to).exists() {
+            if from.exists() {
+                summary.skipped.push(entry.filename.clone());
+                continue;
+            }
+        } else if let Err(e) = fs::rename(&from, &to) {
+            eout!("  ⚠️  無法還原 {}: {}", entry.filename, e);
+            summary.skipped.push(entry.filename.clone());
+            continue;
+        } else {
+            summary.files += 1;
+        }
+        restored.push(entry);
+    }
+
+    let mut metadata = context.store().load_all_metadata()?;
+    let existing: HashSet<String> = metadata.iter().map(|m| m.filename.clone()).collect();
+    for entry in &restored {
+        if let Some(meta) = &entry.metadata {
+            if !existing.contains(&meta.filename) {
+                metadata.push(meta.clone());
+                summary.metadata += 1;
+            }
+        }
+    }
+    if summary.metadata > 0 {
+        context.rewrite_metadata(metadata)?;
+    }
+
+    // 去重時改指向保留檔案的結果（同服務、同時間）改回原本的檔名
+    let results_file = context.path(files::SEARCH_RESULTS);
+    let mut results = reverse_search::load_all_results(&results_file)?;
+    for entry in &restored {
+        let Some(kept) = &entry.kept else {
+            continue;
+        };
+        for original in &entry.results {
+            if let Some(result) = results.iter_mut().find(|r| {
+                r.filename == *kept && r.service == original.service && r.searched_at == original.searched_at
+            }) {
+                result.filename = entry.filename.clone();
+                summary.results += 1;
+            }
+        }
+    }
+    if summary.results > 0 {
+        reverse_search::rewrite_all_results(&results_file, &results)?;
+    }
+    context.invalidate();
+
+    if summary.skipped.is_empty() {
+        fs::remove_dir_all(&dir).with_context(|| format!("無法刪除 {}", dir.display()))?;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedup::DedupAnalyzer;
+    use crate::store::MetadataBackend;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_trash_and_restore() {
+        let dir = std::env::temp_dir().join(format!("meme-trash-{}", std::process::id()));
+        fs::create_dir_all(dir.join("images")).unwrap();
+        let data_dir = dir.to_str().unwrap();
+
+        let context = DataContext::open(data_dir, MetadataBackend::Jsonl).unwrap();
+        for filename in ["a.jpg", "b.jpg"] {
+            fs::write(dir.join("images").join(filename), "same").unwrap();
+            let meta: ImageMetadata = serde_json::from_value(json!({
+                "filename": filename,
+                "description": "",
+                "url": format!("https://example.com/{}", filename),
+                "content_hash": "aaaa0000bbbb1111cccc",
+                "page_number": 1,
+                "downloaded_at": "2024-01-01T00:00:00Z",
+            }))
+            .unwrap();
+            context.file_manager().append_metadata(&meta).unwrap();
+        }
+        let result: ReverseSearchResult = serde_json::from_value(json!({
+            "filename": "b.jpg",
+            "service": "bing",
+            "suggested_title": "Doge",
+            "keywords": [],
+            "related_sites": [],
+            "best_guess": null,
+            "searched_at": "2024-01-02T00:00:00Z",
+        }))
+        .unwrap();
+        let results_file = context.path(files::SEARCH_RESULTS);
+        reverse_search::rewrite_all_results(&results_file, &[result]).unwrap();
+
+        let analyzer = DedupAnalyzer::from_context(Arc::clone(&context)).with_trash(true);
+        let dedup = analyzer.analyze().unwrap();
+        assert_eq!(dedup.duplicates[0].files, vec!["a.jpg", "b.jpg"]);
+        analyzer.remove_duplicates(&dedup, false).unwrap();
+
+        let batches = list(data_dir).unwrap();
+        assert_eq!(batches.len(), 1);
+        let (id, manifest) = &batches[0];
+        assert_eq!(manifest.entries[0].filename, "b.jpg");
+        assert_eq!(manifest.entries[0].kept.as_deref(), Some("a.jpg"));
+        assert!(!dir.join("images/b.jpg").exists());
+        assert!(dir.join(TRASH_DIR).join(id).join("b.jpg").exists());
+        let results = reverse_search::load_all_results(&results_file).unwrap();
+        assert_eq!(results[0].filename, "a.jpg");
+        assert_eq!(context.metadata().unwrap().len(), 1);
+
+        let summary = restore(&context, id).unwrap();
+        assert_eq!((summary.files, summary.metadata, summary.results), (1, 1, 1));
+        assert!(dir.join("images/b.jpg").exists());
+        assert_eq!(context.metadata().unwrap().len(), 2);
+        let results = reverse_search::load_all_results(&results_file).unwrap();
+        assert_eq!(results[0].filename, "b.jpg");
+        assert!(list(data_dir).unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
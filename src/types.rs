@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use crate::media::MediaType;
 
 /// 單張圖片的 metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,8 +15,83 @@ pub struct ImageMetadata {
     pub content_hash: String,
     /// 來源頁面
     pub page_number: u32,
-    /// 下載時間
+    /// 下載時間（舊資料沒有時為 1970-01-01，可用 reconcile 以檔案修改時間補上）
+    #[serde(default)]
     pub downloaded_at: DateTime<Utc>,
+    /// 來源網站提供的標籤
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 來源網站（網域；舊資料為空字串）
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source_site: String,
+    /// 圖片寬度（無法解析尺寸時為 None）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// 圖片高度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// 檔案大小（bytes）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    /// 反向搜尋的關鍵字（`enrich` 合併進來）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// 反向搜尋推測的標題（`enrich` 合併進來）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggested_title: Option<String>,
+    /// 解析到的授權（例如 `CC BY 4.0`，來自 Parser 的 `license_selector`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// 同名不同圖的編號（檔名樣板有 `{title_n}` 時，第 2 張起檔名為 `標題 (2)`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_number: Option<u32>,
+    /// 圖片上的文字（`ocr` 擷取）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr_text: Option<String>,
+    /// 媒體類型（image、animated、video；舊資料沒有時為 None，可用 reconcile 補上）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<MediaType>,
+    /// 網站特有的欄位（例如 KnowYourMeme 的起源年份）
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ImageMetadata {
+    /// 授權（舊資料記在 `extra.license`）
+    pub fn license(&self) -> Option<&str> {
+        self.license
+            .as_deref()
+            .or_else(|| self.extra.get("license").and_then(|v| v.as_str()))
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+    }
+}
+
+#[cfg(test)]
+impl ImageMetadata {
+    /// 測試用的 metadata：只指定檔名與雜湊，其餘欄位為預設值
+    pub fn for_test(filename: &str, content_hash: &str) -> Self {
+        Self {
+            filename: filename.to_string(),
+            description: String::new(),
+            url: format!("https://example.com/{}", filename),
+            content_hash: content_hash.to_string(),
+            page_number: 1,
+            downloaded_at: Utc::now(),
+            tags: Vec::new(),
+            source_site: String::new(),
+            width: None,
+            height: None,
+            file_size: None,
+            keywords: Vec::new(),
+            suggested_title: None,
+            license: None,
+            title_number: None,
+            ocr_text: None,
+            media_type: None,
+            extra: Default::default(),
+        }
+    }
 }
 
 /// 爬取進度
@@ -29,6 +105,9 @@ pub struct Progress {
     pub last_updated: DateTime<Utc>,
     /// 失敗的頁面列表
     pub failed_pages: Vec<u32>,
+    /// 爬取的網站（網域）
+    #[serde(default)]
+    pub site: Option<String>,
 }
 
 impl Progress {
@@ -39,6 +118,7 @@ impl Progress {
             total_images_downloaded: 0,
             last_updated: Utc::now(),
             failed_pages: Vec::new(),
+            site: None,
         }
     }
     
@@ -71,4 +151,219 @@ pub struct DuplicateRecord {
     pub content_hash: String,
     /// 所有具有相同雜湊的檔案
     pub files: Vec<String>,
-}
\ No newline at end of file
+    /// 由成員的反向搜尋標籤推出的名稱（沒有標籤時取來源網站的名稱）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// 暖身階段的量測結果與選定的設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupResult {
+    /// 暖身爬取的頁數
+    pub pages: u32,
+    /// 請求次數（含重試）
+    pub requests: usize,
+    /// 失敗次數
+    pub failures: usize,
+    /// 被限流次數（HTTP 429）
+    pub rate_limited: usize,
+    /// 選定的並發數
+    pub chosen_concurrency: usize,
+    /// 選定的批次間隔（毫秒）
+    pub chosen_batch_delay_ms: u64,
+}
+
+impl WarmupResult {
+    /// 依量測到的錯誤率/限流率，從原本的設定推算安全的並發數與延遲
+    pub fn recommend(
+        pages: u32,
+        requests: usize,
+        failures: usize,
+        rate_limited: usize,
+        concurrency: usize,
+        batch_delay_ms: u64,
+    ) -> Self {
+        let error_rate = if requests > 0 {
+            failures as f64 / requests as f64
+        } else {
+            0.0
+        };
+        
+        let (chosen_concurrency, chosen_batch_delay_ms) = if rate_limited > 0 {
+            // 被限流：大幅降速
+            ((concurrency / 4).max(1), batch_delay_ms * 4)
+        } else if error_rate > 0.2 {
+            ((concurrency / 2).max(1), batch_delay_ms * 2)
+        } else if error_rate > 0.05 {
+            ((concurrency * 3 / 4).max(1), batch_delay_ms * 3 / 2)
+        } else {
+            (concurrency, batch_delay_ms)
+        };
+        
+        Self {
+            pages,
+            requests,
+            failures,
+            rate_limited,
+            chosen_concurrency,
+            chosen_batch_delay_ms,
+        }
+    }
+}
+
+/// 單次爬取的執行報告（寫入 run_report.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    /// 開始時間
+    pub started_at: DateTime<Utc>,
+    /// 結束時間
+    pub finished_at: DateTime<Utc>,
+    /// 起始頁
+    pub start_page: u32,
+    /// 總頁數
+    pub total_pages: u32,
+    /// 本次處理的頁數
+    pub pages_processed: u32,
+    /// 本次失敗的頁數
+    pub pages_failed: u32,
+    /// 本次下載的圖片數
+    pub images_downloaded: usize,
+    /// 實際使用的並發數
+    pub concurrency: usize,
+    /// 實際使用的批次間隔（毫秒，自適應限流的起始值）
+    pub batch_delay_ms: u64,
+    /// 結束時自適應限流學到的請求間隔（毫秒）
+    #[serde(default)]
+    pub request_delay_ms: Option<u64>,
+    /// 暖身階段結果（未啟用時為 None）
+    pub warmup: Option<WarmupResult>,
+    /// 是否被 Ctrl+C 中斷
+    #[serde(default)]
+    pub interrupted: bool,
+    /// 失敗率過高而暫停爬取的次數
+    #[serde(default)]
+    pub circuit_breaks: u32,
+    /// 本次執行的 HTTP 連線統計（舊報告沒有）
+    #[serde(default)]
+    pub connections: Option<ConnectionReport>,
+}
+
+/// HTTP 連線重複使用統計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionReport {
+    /// 送出的請求數（含重試）
+    pub requests: usize,
+    /// 新建立的連線數
+    pub new_connections: usize,
+    /// TLS 交握次數
+    pub tls_handshakes: usize,
+    /// 建立連線失敗次數
+    pub connect_failures: usize,
+    /// 建立連線花費的總時間（毫秒）
+    pub connect_ms: u64,
+}
+
+impl ConnectionReport {
+    /// 與較早快照的差值
+    pub fn since(&self, earlier: &ConnectionReport) -> ConnectionReport {
+        ConnectionReport {
+            requests: self.requests - earlier.requests,
+            new_connections: self.new_connections - earlier.new_connections,
+            tls_handshakes: self.tls_handshakes - earlier.tls_handshakes,
+            connect_failures: self.connect_failures - earlier.connect_failures,
+            connect_ms: self.connect_ms - earlier.connect_ms,
+        }
+    }
+    
+    /// 使用既有連線的請求數
+    pub fn reused(&self) -> usize {
+        self.requests.saturating_sub(self.new_connections)
+    }
+    
+    /// 使用既有連線的請求比例
+    pub fn reuse_rate(&self) -> f64 {
+        match self.requests {
+            0 => 0.0,
+            requests => self.reused() as f64 / requests as f64,
+        }
+    }
+    
+    /// 重新連線過多（大部分請求都要重新建立連線），連線建立成為瓶頸
+    pub fn reconnects_excessive(&self) -> bool {
+        self.requests >= 20 && self.reuse_rate() < 0.5
+    }
+}
+
+/// 資料集清單（寫入 dataset_manifest.json）
+///
+/// 記錄產生資料集的設定；可重現模式下，相同網站狀態重跑應得到相同的 `content_digest`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetManifest {
+    /// 產生時間
+    pub generated_at: DateTime<Utc>,
+    /// 來源網址
+    pub source: String,
+    /// 總頁數
+    pub total_pages: u32,
+    /// 是否為可重現模式（頁序固定、頁內依 URL 排序、逐張下載）
+    pub deterministic: bool,
+    /// 抽樣種子
+    pub seed: Option<u64>,
+    /// 抽樣比例（None 表示全部保留）
+    pub sample_rate: Option<f64>,
+    /// 檔名樣板
+    pub filename_pattern: String,
+    /// 圖片數
+    pub image_count: usize,
+    /// 依 metadata 順序計算的檔名/URL/內容雜湊摘要（不含下載時間）
+    pub content_digest: String,
+}
+
+/// 網站內容變動的種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// 重爬的頁面中都找不到
+    Removed,
+    /// 原本的頁面上同名項目換了圖片網址
+    Changed,
+}
+
+/// 重試後仍下載失敗的圖片（failed_downloads.jsonl，`crawl --retry-downloads` 再試一次）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedDownload {
+    pub url: String,
+    /// 圖片描述
+    pub name: String,
+    pub page: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source_site: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// 最後一次的錯誤
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// 重爬時發現的變動（寫入 site_changes.jsonl）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteChange {
+    /// 偵測時間
+    pub detected_at: DateTime<Utc>,
+    pub kind: ChangeKind,
+    /// 原本所在的頁面
+    pub page_number: u32,
+    /// 本地檔名
+    pub filename: String,
+    /// 圖片描述
+    pub description: String,
+    /// 原本的圖片網址
+    pub url: String,
+    /// 目前的圖片網址（僅 changed）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_url: Option<String>,
+}